@@ -0,0 +1,240 @@
+// Btrfs チャンク
+//
+// 論理アドレスから物理アドレスへの変換表（sys_chunk_arrayとチャンクツリーのCHUNK_ITEM）
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::tree::{Key, key_type, KEY_SIZE};
+use super::{le_u16, le_u64};
+
+/// チャンクアイテムの固定部分のサイズ
+const CHUNK_ITEM_SIZE: usize = 48;
+/// ストライプ1つ分のサイズ
+const STRIPE_SIZE: usize = 32;
+
+/// ブロックグループの種類とプロファイル
+pub mod block_group {
+    /// データ
+    pub const DATA: u64 = 1 << 0;
+    /// SYSTEM（チャンクツリー）
+    pub const SYSTEM: u64 = 1 << 1;
+    /// メタデータ
+    pub const METADATA: u64 = 1 << 2;
+    /// RAID0
+    pub const RAID0: u64 = 1 << 3;
+    /// RAID1
+    pub const RAID1: u64 = 1 << 4;
+    /// DUP
+    pub const DUP: u64 = 1 << 5;
+    /// RAID10
+    pub const RAID10: u64 = 1 << 6;
+    /// RAID5
+    pub const RAID5: u64 = 1 << 7;
+    /// RAID6
+    pub const RAID6: u64 = 1 << 8;
+}
+
+/// チャンクのストライプ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stripe {
+    /// デバイスID
+    pub devid: u64,
+    /// デバイス上の開始位置
+    pub offset: u64,
+}
+
+/// 論理アドレスの連続した範囲の配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// 論理アドレスの開始位置
+    pub logical: u64,
+    /// 長さ
+    pub length: u64,
+    /// ストライプの単位
+    pub stripe_len: u64,
+    /// ブロックグループの種類とプロファイル
+    pub flags: u64,
+    /// RAID10の組あたりのストライプ数
+    pub sub_stripes: u16,
+    /// ストライプ
+    pub stripes: Vec<Stripe>,
+}
+
+impl Chunk {
+    /// CHUNK_ITEMを解析し、解析したバイト数も返す
+    pub fn parse(logical: u64, data: &[u8]) -> FsResult<(Self, usize)> {
+        if data.len() < CHUNK_ITEM_SIZE {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let num_stripes = le_u16(data, 44) as usize;
+        let size = CHUNK_ITEM_SIZE + num_stripes * STRIPE_SIZE;
+        if num_stripes == 0 || data.len() < size {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let stripes = (0..num_stripes)
+            .map(|i| {
+                let base = CHUNK_ITEM_SIZE + i * STRIPE_SIZE;
+                Stripe { devid: le_u64(data, base), offset: le_u64(data, base + 8) }
+            })
+            .collect();
+        let chunk = Self {
+            logical,
+            length: le_u64(data, 0),
+            stripe_len: le_u64(data, 16),
+            flags: le_u64(data, 24),
+            sub_stripes: le_u16(data, 46),
+            stripes,
+        };
+        if chunk.length == 0 || chunk.stripe_len == 0 {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok((chunk, size))
+    }
+    
+    /// チャンク内の位置に対応する物理位置と、そこから連続して読めるバイト数
+    fn map(&self, offset: u64, devid: u64) -> FsResult<(u64, u64)> {
+        let flags = self.flags;
+        if flags & (block_group::RAID5 | block_group::RAID6) != 0 {
+            log::warn!("Btrfs: RAID5/6のチャンクは読み込めません");
+            return Err(FsError::UnsupportedFeature);
+        }
+        
+        let stripe_nr = offset / self.stripe_len;
+        let within = offset % self.stripe_len;
+        let (index, row) = if flags & block_group::RAID0 != 0 {
+            let count = self.stripes.len() as u64;
+            ((stripe_nr % count) as usize, stripe_nr / count)
+        } else if flags & block_group::RAID10 != 0 {
+            let sub = self.sub_stripes.max(1) as u64;
+            let groups = (self.stripes.len() as u64 / sub).max(1);
+            (((stripe_nr % groups) * sub) as usize, stripe_nr / groups)
+        } else {
+            // SINGLE/DUP/RAID1系はどのストライプも同じ内容を持つ
+            let stripe = self.stripes.iter().find(|stripe| stripe.devid == devid).ok_or(FsError::DeviceError)?;
+            return Ok((stripe.offset + offset, self.length - offset));
+        };
+        
+        // RAID10の組内では、このデバイスにあるストライプを選ぶ
+        let sub = if flags & block_group::RAID10 != 0 { self.sub_stripes.max(1) as usize } else { 1 };
+        let stripe = self.stripes[index..(index + sub).min(self.stripes.len())].iter()
+            .find(|stripe| stripe.devid == devid)
+            .ok_or(FsError::DeviceError)?;
+        Ok((stripe.offset + row * self.stripe_len + within, self.stripe_len - within))
+    }
+}
+
+/// 論理アドレスの変換表
+#[derive(Debug, Default)]
+pub struct ChunkMap {
+    /// 開始論理アドレス => チャンク
+    chunks: BTreeMap<u64, Chunk>,
+    /// このデバイスのID
+    devid: u64,
+}
+
+impl ChunkMap {
+    /// 空の変換表を作成
+    pub fn new(devid: u64) -> Self {
+        Self { chunks: BTreeMap::new(), devid }
+    }
+    
+    /// スーパーブロックのsys_chunk_arrayからSYSTEMチャンクを登録
+    pub fn load_sys_chunk_array(&mut self, array: &[u8]) -> FsResult<()> {
+        let mut position = 0;
+        while position < array.len() {
+            if array.len() - position < KEY_SIZE {
+                return Err(FsError::BadSuperblock);
+            }
+            let key = Key::parse(&array[position..]);
+            position += KEY_SIZE;
+            if key.item_type != key_type::CHUNK_ITEM {
+                log::warn!("Btrfs: sys_chunk_arrayに不明なキー種別{}があります", key.item_type);
+                return Err(FsError::BadSuperblock);
+            }
+            let (chunk, size) = Chunk::parse(key.offset, &array[position..])?;
+            position += size;
+            self.insert(chunk);
+        }
+        Ok(())
+    }
+    
+    /// チャンクを登録
+    pub fn insert(&mut self, chunk: Chunk) {
+        self.chunks.insert(chunk.logical, chunk);
+    }
+    
+    /// 論理アドレスを物理アドレスへ変換し、そこから連続して読めるバイト数も返す
+    pub fn map(&self, logical: u64) -> FsResult<(u64, u64)> {
+        let chunk = self.chunks.range(..=logical).next_back()
+            .map(|(_, chunk)| chunk)
+            .filter(|chunk| logical < chunk.logical + chunk.length)
+            .ok_or_else(|| {
+                log::warn!("Btrfs: 論理アドレス{:#x}を含むチャンクがありません", logical);
+                FsError::CorruptedFs
+            })?;
+        chunk.map(logical - chunk.logical, self.devid)
+    }
+    
+    /// 論理アドレスの範囲を、物理的に連続する(物理アドレス, 長さ)の並びへ変換
+    pub fn map_range(&self, logical: u64, len: u64) -> FsResult<Vec<(u64, u64)>> {
+        let mut extents = Vec::new();
+        let mut done = 0;
+        while done < len {
+            let (physical, contiguous) = self.map(logical + done)?;
+            let chunk = contiguous.min(len - done);
+            extents.push((physical, chunk));
+            done += chunk;
+        }
+        Ok(extents)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    
+    /// テスト用のCHUNK_ITEMを作成
+    pub fn chunk_item(length: u64, flags: u64, stripes: &[(u64, u64)]) -> Vec<u8> {
+        let mut data = vec![0u8; CHUNK_ITEM_SIZE];
+        data[0..8].copy_from_slice(&length.to_le_bytes());
+        data[8..16].copy_from_slice(&2u64.to_le_bytes());
+        data[16..24].copy_from_slice(&65536u64.to_le_bytes());
+        data[24..32].copy_from_slice(&flags.to_le_bytes());
+        data[44..46].copy_from_slice(&(stripes.len() as u16).to_le_bytes());
+        data[46..48].copy_from_slice(&2u16.to_le_bytes());
+        for &(devid, offset) in stripes {
+            let mut stripe = vec![0u8; STRIPE_SIZE];
+            stripe[0..8].copy_from_slice(&devid.to_le_bytes());
+            stripe[8..16].copy_from_slice(&offset.to_le_bytes());
+            data.extend_from_slice(&stripe);
+        }
+        data
+    }
+    
+    #[test]
+    fn maps_single_dup_and_striped_chunks() {
+        // sys_chunk_array: SYSTEM|DUP のチャンク1つ
+        let mut array = Key::new(256, key_type::CHUNK_ITEM, 0x10_0000).to_bytes().to_vec();
+        array.extend_from_slice(&chunk_item(0x40_0000, block_group::SYSTEM | block_group::DUP, &[(1, 0x50_0000), (1, 0x90_0000)]));
+        let mut map = ChunkMap::new(1);
+        map.load_sys_chunk_array(&array).unwrap();
+        assert_eq!(map.map(0x10_1000).unwrap(), (0x50_1000, 0x3F_F000));
+        assert!(matches!(map.map(0x0F_FFFF), Err(FsError::CorruptedFs)));
+        assert!(matches!(map.map(0x50_0000), Err(FsError::CorruptedFs)));
+        
+        // RAID0: 64KiBごとにストライプを順に使う
+        let (raid0, _) = Chunk::parse(0x100_0000, &chunk_item(0x40_0000, block_group::DATA | block_group::RAID0, &[(1, 0x200_0000), (1, 0x300_0000)])).unwrap();
+        map.insert(raid0);
+        assert_eq!(map.map(0x100_0000 + 0x1_0010).unwrap(), (0x300_0010, 0xFFF0));
+        assert_eq!(map.map(0x100_0000 + 0x2_0000).unwrap(), (0x201_0000, 0x1_0000));
+        assert_eq!(map.map_range(0x100_0000 + 0xF000, 0x2000).unwrap(), vec![(0x200_F000, 0x1000), (0x300_0000, 0x1000)]);
+        
+        // 別デバイスにしかないストライプは読めない
+        let (remote, _) = Chunk::parse(0x200_0000, &chunk_item(0x10_0000, block_group::DATA, &[(2, 0)])).unwrap();
+        map.insert(remote);
+        assert!(matches!(map.map(0x200_0000), Err(FsError::DeviceError)));
+    }
+}
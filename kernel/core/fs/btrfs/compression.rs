@@ -0,0 +1,567 @@
+// Btrfs 圧縮
+//
+// 圧縮エクステントの伸長（zlib、セクタ単位に区切られたLZO1X、zstd）
+
+use super::super::{FsError, FsResult};
+use super::{le_u16, le_u32, zstd};
+
+/// 圧縮方式
+pub mod kind {
+    /// 非圧縮
+    pub const NONE: u8 = 0;
+    /// zlib
+    pub const ZLIB: u8 = 1;
+    /// LZO1X（セクタ単位のセグメント）
+    pub const LZO: u8 = 2;
+    /// zstd
+    pub const ZSTD: u8 = 3;
+}
+
+/// 伸長先のバッファ（後方参照のコピーと容量の確認を行う）
+pub(super) struct Output<'a> {
+    /// 書き込み先
+    buf: &'a mut [u8],
+    /// 書き込み済みのバイト数
+    len: usize,
+}
+
+impl<'a> Output<'a> {
+    /// 空の出力を作成
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+    
+    /// 書き込み済みのバイト数
+    pub fn written(&self) -> usize {
+        self.len
+    }
+    
+    /// 容量に達したか
+    pub fn is_full(&self) -> bool {
+        self.len == self.buf.len()
+    }
+    
+    /// 1バイト追加
+    pub fn push(&mut self, byte: u8) -> FsResult<()> {
+        if self.is_full() {
+            return Err(FsError::CorruptedFs);
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        Ok(())
+    }
+    
+    /// バイト列を追加
+    pub fn extend(&mut self, data: &[u8]) -> FsResult<()> {
+        if self.buf.len() - self.len < data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+        Ok(())
+    }
+    
+    /// 同じバイトを`count`個追加
+    pub fn fill(&mut self, byte: u8, count: usize) -> FsResult<()> {
+        if self.buf.len() - self.len < count {
+            return Err(FsError::CorruptedFs);
+        }
+        self.buf[self.len..self.len + count].fill(byte);
+        self.len += count;
+        Ok(())
+    }
+    
+    /// `distance`バイト前から`length`バイトをコピー（`floor`より前は参照できない）
+    pub fn copy_match(&mut self, distance: usize, length: usize, floor: usize) -> FsResult<()> {
+        if distance == 0 || distance > self.len - floor || self.buf.len() - self.len < length {
+            return Err(FsError::CorruptedFs);
+        }
+        // 重なりのあるコピーがあるため1バイトずつ進める
+        for _ in 0..length {
+            self.buf[self.len] = self.buf[self.len - distance];
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+/// 圧縮データを`dst`へ伸長する（ストリームが`dst`より短ければ残りを0で埋める）
+pub fn decompress(compression: u8, src: &[u8], dst: &mut [u8], sectorsize: usize) -> FsResult<()> {
+    let mut out = Output::new(dst);
+    match compression {
+        kind::ZLIB => inflate_zlib(src, &mut out)?,
+        kind::LZO => decompress_lzo(src, &mut out, sectorsize)?,
+        kind::ZSTD => zstd::decompress(src, &mut out)?,
+        _ => {
+            log::warn!("Btrfs: 圧縮方式{}は未対応です", compression);
+            return Err(FsError::UnsupportedFeature);
+        },
+    }
+    let written = out.written();
+    dst[written..].fill(0);
+    Ok(())
+}
+
+/// LSBから読むビット列
+struct BitReader<'a> {
+    /// 入力
+    data: &'a [u8],
+    /// 次に読むビットの位置
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+    
+    /// `count`ビット（最大25）を読む
+    fn bits(&mut self, count: u32) -> FsResult<u32> {
+        let mut value = 0u32;
+        for i in 0..count {
+            let byte = *self.data.get(self.position / 8).ok_or(FsError::CorruptedFs)?;
+            value |= (((byte >> (self.position % 8)) & 1) as u32) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+    
+    /// 次のバイト境界へ進める
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+    
+    /// バイト境界から読んだ位置（バイト単位）
+    fn byte_position(&self) -> usize {
+        self.position / 8
+    }
+}
+
+/// 正規ハフマン符号の復号表
+struct Huffman {
+    /// 符号長ごとの符号の数
+    counts: [u16; 16],
+    /// 符号順に並べたシンボル
+    symbols: [u16; 288],
+}
+
+impl Huffman {
+    /// 符号長の並びから作成
+    fn new(lengths: &[u8]) -> FsResult<Self> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        
+        // 過剰に割り当てられた符号長は壊れたストリーム
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(FsError::CorruptedFs);
+            }
+        }
+        
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = [0u16; 288];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+    
+    /// 1シンボルを復号
+    fn decode(&self, reader: &mut BitReader) -> FsResult<u16> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(FsError::CorruptedFs)
+    }
+}
+
+/// 長さ符号257..285の基準値
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+/// 長さ符号の追加ビット数
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// 距離符号の基準値
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+/// 距離符号の追加ビット数
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// 動的ハフマンブロックの符号長符号の並び
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// zlibストリーム（ヘッダ + DEFLATE）を伸長
+fn inflate_zlib(src: &[u8], out: &mut Output) -> FsResult<()> {
+    if src.len() < 2 {
+        return Err(FsError::CorruptedFs);
+    }
+    let (cmf, flags) = (src[0], src[1]);
+    if cmf & 0x0F != 8 || !((cmf as u16) << 8 | flags as u16).is_multiple_of(31) {
+        return Err(FsError::CorruptedFs);
+    }
+    if flags & 0x20 != 0 {
+        // プリセット辞書はBtrfsでは使われない
+        return Err(FsError::UnsupportedFeature);
+    }
+    inflate(&src[2..], out)
+}
+
+/// DEFLATEストリームを伸長（出力が満杯になったら終了する）
+fn inflate(src: &[u8], out: &mut Output) -> FsResult<()> {
+    let mut reader = BitReader::new(src);
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let start = reader.byte_position();
+                if start + 4 > src.len() {
+                    return Err(FsError::CorruptedFs);
+                }
+                let len = le_u16(src, start);
+                if len != !le_u16(src, start + 2) || start + 4 + len as usize > src.len() {
+                    return Err(FsError::CorruptedFs);
+                }
+                out.extend(&src[start + 4..start + 4 + len as usize])?;
+                reader.position = (start + 4 + len as usize) * 8;
+            },
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5u8; 30])?;
+                inflate_block(&mut reader, out, &literals, &distances)?;
+            },
+            2 => {
+                let (literals, distances) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, out, &literals, &distances)?;
+            },
+            _ => return Err(FsError::CorruptedFs),
+        }
+        if last || out.is_full() {
+            return Ok(());
+        }
+    }
+}
+
+/// 動的ハフマンブロックの符号表を読む
+fn read_dynamic_tables(reader: &mut BitReader) -> FsResult<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(FsError::CorruptedFs);
+    }
+    
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_table = Huffman::new(&code_lengths)?;
+    
+    let mut lengths = [0u8; 316];
+    let total = literal_count + distance_count;
+    let mut index = 0;
+    while index < total {
+        let (value, repeat) = match code_table.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..index].last().ok_or(FsError::CorruptedFs)?;
+                (previous, 3 + reader.bits(2)? as usize)
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if index + repeat > total {
+            return Err(FsError::CorruptedFs);
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(FsError::CorruptedFs);
+    }
+    
+    Ok((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..total])?))
+}
+
+/// ハフマン符号化されたブロック本体を伸長
+fn inflate_block(reader: &mut BitReader, out: &mut Output, literals: &Huffman, distances: &Huffman) -> FsResult<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8)?,
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err(FsError::CorruptedFs);
+                }
+                let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code] as u32)? as usize;
+                let code = distances.decode(reader)? as usize;
+                if code >= DIST_BASE.len() {
+                    return Err(FsError::CorruptedFs);
+                }
+                let distance = DIST_BASE[code] as usize + reader.bits(DIST_EXTRA[code] as u32)? as usize;
+                out.copy_match(distance, length, 0)?;
+            },
+        }
+    }
+}
+
+/// LZOのセグメントヘッダ（長さ）のサイズ
+const LZO_LEN: usize = 4;
+
+/// Btrfs形式のLZOデータを伸長
+///
+/// 先頭に全体の長さ、続いて各セグメントの長さとLZO1Xデータが並ぶ。
+/// セグメントヘッダはセクタ境界をまたがず、残りが4バイト未満なら次のセクタから始まる。
+fn decompress_lzo(src: &[u8], out: &mut Output, sectorsize: usize) -> FsResult<()> {
+    if src.len() < LZO_LEN {
+        return Err(FsError::CorruptedFs);
+    }
+    let total = le_u32(src, 0) as usize;
+    if total > src.len() || total < LZO_LEN {
+        return Err(FsError::CorruptedFs);
+    }
+    
+    let mut position = LZO_LEN;
+    while position < total && !out.is_full() {
+        let left_in_sector = sectorsize - position % sectorsize;
+        if left_in_sector < LZO_LEN {
+            position += left_in_sector;
+            if position >= total {
+                break;
+            }
+        }
+        if position + LZO_LEN > total {
+            return Err(FsError::CorruptedFs);
+        }
+        let length = le_u32(src, position) as usize;
+        position += LZO_LEN;
+        if length > total - position {
+            return Err(FsError::CorruptedFs);
+        }
+        lzo1x_decompress(&src[position..position + length], out)?;
+        position += length;
+    }
+    Ok(())
+}
+
+/// LZO1Xの1セグメントを伸長
+fn lzo1x_decompress(src: &[u8], out: &mut Output) -> FsResult<()> {
+    let byte = |index: usize| src.get(index).copied().ok_or(FsError::CorruptedFs);
+    // 0の並びで表される長さの延長
+    let extended = |ip: &mut usize, base: usize| -> FsResult<usize> {
+        let mut length = base;
+        while byte(*ip)? == 0 {
+            length += 255;
+            *ip += 1;
+            if length > src.len() * 255 {
+                return Err(FsError::CorruptedFs);
+            }
+        }
+        length += byte(*ip)? as usize;
+        *ip += 1;
+        Ok(length)
+    };
+    let floor = out.written();
+    let mut ip = 0;
+    // 直前の命令の種類（0: 初期、1..3: 後続リテラル数、4: リテラル列の直後）
+    let mut state = 0;
+    
+    let first = byte(0)?;
+    if first > 17 {
+        ip = 1;
+        let count = (first - 17) as usize;
+        if ip + count > src.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        out.extend(&src[ip..ip + count])?;
+        ip += count;
+        state = if count < 4 { count } else { 4 };
+    }
+    
+    loop {
+        let t = byte(ip)? as usize;
+        ip += 1;
+        let (distance, length, next);
+        if t < 16 {
+            if state == 0 {
+                // リテラル列
+                let count = if t == 0 { extended(&mut ip, 15)? } else { t } + 3;
+                if ip + count > src.len() {
+                    return Err(FsError::CorruptedFs);
+                }
+                out.extend(&src[ip..ip + count])?;
+                ip += count;
+                state = 4;
+                continue;
+            }
+            next = t & 3;
+            if state != 4 {
+                // 直前のリテラルが1〜3バイトなら2バイトの近距離一致
+                distance = 1 + (t >> 2) + ((byte(ip)? as usize) << 2);
+                length = 2;
+            } else {
+                distance = 1 + 0x800 + (t >> 2) + ((byte(ip)? as usize) << 2);
+                length = 3;
+            }
+            ip += 1;
+        } else if t >= 64 {
+            next = t & 3;
+            distance = 1 + ((t >> 2) & 7) + ((byte(ip)? as usize) << 3);
+            ip += 1;
+            length = (t >> 5) + 1;
+        } else if t >= 32 {
+            length = if t & 31 == 0 { extended(&mut ip, 31)? } else { t & 31 } + 2;
+            let word = le_u16(src.get(ip..ip + 2).ok_or(FsError::CorruptedFs)?, 0) as usize;
+            ip += 2;
+            distance = 1 + (word >> 2);
+            next = word & 3;
+        } else {
+            length = if t & 7 == 0 { extended(&mut ip, 7)? } else { t & 7 } + 2;
+            let word = le_u16(src.get(ip..ip + 2).ok_or(FsError::CorruptedFs)?, 0) as usize;
+            ip += 2;
+            let far = ((t & 8) << 11) + (word >> 2);
+            if far == 0 {
+                // 終端マーカー
+                return if length == 3 && ip == src.len() { Ok(()) } else { Err(FsError::CorruptedFs) };
+            }
+            distance = far + 0x4000;
+            next = word & 3;
+        }
+        
+        out.copy_match(distance, length, floor)?;
+        state = next;
+        if next > 0 {
+            if ip + next > src.len() {
+                return Err(FsError::CorruptedFs);
+            }
+            out.extend(&src[ip..ip + next])?;
+            ip += next;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    
+    /// 伸長結果の確認に使う文字列（zlib/zstdのデータは同じ内容を外部ツールで圧縮した）
+    pub fn sample_text() -> Vec<u8> {
+        let mut text = Vec::new();
+        for i in 0..200u32 {
+            text.extend_from_slice(alloc::format!("line {}: the quick brown fox jumps {} times\n", i, i * 7 % 13).as_bytes());
+        }
+        text
+    }
+    
+    #[test]
+    fn inflates_zlib_streams() {
+        let text = sample_text();
+        let mut dst = vec![0xFFu8; text.len() + 100];
+        decompress(kind::ZLIB, ZLIB_SAMPLE, &mut dst, 4096).unwrap();
+        assert_eq!(&dst[..text.len()], &text[..]);
+        assert!(dst[text.len()..].iter().all(|&b| b == 0));
+        
+        // 無圧縮ブロック（BFINAL=1, BTYPE=0）
+        let stored = [0x78, 0x01, 0x01, 0x03, 0x00, 0xFC, 0xFF, b'a', b'b', b'c'];
+        let mut dst = [0u8; 3];
+        decompress(kind::ZLIB, &stored, &mut dst, 4096).unwrap();
+        assert_eq!(&dst, b"abc");
+        // 出力先より長いストリームは破損として扱う
+        let mut small = vec![0u8; 100];
+        assert!(matches!(decompress(kind::ZLIB, ZLIB_SAMPLE, &mut small, 4096), Err(FsError::CorruptedFs)));
+    }
+    
+    #[test]
+    fn decompresses_lzo_segments() {
+        // "abcd" + (距離4, 長さ8)の一致 + 終端マーカー
+        let segment = [21u8, b'a', b'b', b'c', b'd', 0xEC, 0x00, 0x11, 0x00, 0x00];
+        let mut src = Vec::new();
+        src.extend_from_slice(&((8 + segment.len()) as u32).to_le_bytes());
+        src.extend_from_slice(&(segment.len() as u32).to_le_bytes());
+        src.extend_from_slice(&segment);
+        let mut dst = [0xFFu8; 16];
+        decompress(kind::LZO, &src, &mut dst, 4096).unwrap();
+        assert_eq!(&dst[..12], b"abcdabcdabcd");
+        assert_eq!(&dst[12..], &[0u8; 4]);
+        
+        // 伸長済みデータより前を指す一致（リテラル1バイトの後に距離2）
+        let bad = [18u8, b'x', 0x44, 0x00];
+        assert!(lzo1x_decompress(&bad, &mut Output::new(&mut [0u8; 16])).is_err());
+    }
+    
+    /// `sample_text()`をzlib（レベル9）で圧縮したもの
+    const ZLIB_SAMPLE: &[u8] = &[
+        0x78, 0xDA, 0x95, 0xD9, 0x4B, 0x8E, 0x1B, 0x31, 0x0C, 0x45, 0xD1, 0x79, 0x56, 0xE1, 0x25, 0xE8,
+        0x89, 0x22, 0x45, 0x66, 0x39, 0x09, 0x3A, 0x48, 0xE7, 0xFF, 0x45, 0xB2, 0xFC, 0x20, 0x33, 0x97,
+        0x07, 0xEA, 0xCB, 0x39, 0x61, 0xC0, 0xD2, 0x81, 0x5D, 0xBC, 0xF5, 0xE9, 0xF9, 0xCB, 0xD3, 0x6D,
+        0xBC, 0xBE, 0xFD, 0x7A, 0xFF, 0x74, 0xFB, 0xFE, 0xFB, 0xF9, 0xED, 0xC7, 0xDB, 0x9B, 0x1F, 0x5F,
+        0xFF, 0x7C, 0xB9, 0xBD, 0xFB, 0xFA, 0xF7, 0xF6, 0xE1, 0xF7, 0xE7, 0x6F, 0x3F, 0x6F, 0xE3, 0xF6,
+        0xEB, 0xF9, 0xF3, 0xD3, 0xCF, 0x57, 0x9F, 0xFE, 0x4F, 0xEA, 0x34, 0xB9, 0xEF, 0x27, 0xE7, 0x69,
+        0x52, 0xF7, 0x93, 0x76, 0x9A, 0xCC, 0xFB, 0xC9, 0x75, 0x9A, 0x9C, 0xF7, 0x93, 0x7E, 0x9A, 0xAC,
+        0xFB, 0xC9, 0x38, 0x4D, 0xDA, 0xFD, 0xE4, 0x3E, 0x7E, 0xA3, 0xCB, 0x31, 0xE5, 0x69, 0x74, 0xDD,
+        0x4F, 0xD6, 0xF1, 0x43, 0x2F, 0xE7, 0xA4, 0xE3, 0x35, 0xF9, 0x65, 0xF4, 0x78, 0x4F, 0xBA, 0x1C,
+        0x95, 0x8E, 0x37, 0x15, 0x97, 0x51, 0xE3, 0x50, 0x16, 0x96, 0x22, 0xC7, 0x54, 0x14, 0xD8, 0x8A,
+        0x36, 0xC6, 0xA2, 0xC4, 0x5A, 0x54, 0x98, 0xCB, 0x1C, 0xDC, 0xCB, 0x14, 0x06, 0x33, 0x27, 0x17,
+        0x33, 0x0D, 0x8B, 0x99, 0x8B, 0x8B, 0x99, 0x8E, 0xC5, 0xCC, 0xC0, 0x62, 0xE6, 0xE6, 0xBF, 0x2D,
+        0x89, 0xC5, 0xCC, 0xC2, 0x62, 0x6C, 0x60, 0x31, 0x26, 0x2C, 0xC6, 0x26, 0x16, 0x63, 0xC6, 0xC5,
+        0xD8, 0xC2, 0x62, 0xCC, 0xB9, 0x18, 0x0B, 0x2C, 0xC6, 0x36, 0x17, 0x63, 0x89, 0xC5, 0x58, 0x61,
+        0x31, 0x6B, 0x60, 0x31, 0x4B, 0x58, 0xCC, 0x9A, 0xFC, 0xFF, 0xC8, 0xB0, 0x98, 0xB5, 0xB0, 0x98,
+        0xE5, 0x58, 0xCC, 0x0A, 0x2E, 0x66, 0x6D, 0x2C, 0x66, 0x25, 0x17, 0xB3, 0x0A, 0x8B, 0xF1, 0xC1,
+        0xC5, 0xB8, 0xB0, 0x18, 0x9F, 0x58, 0x8C, 0x1B, 0x16, 0xE3, 0x0B, 0x8B, 0x71, 0xC7, 0x62, 0x3C,
+        0xF8, 0x23, 0xCC, 0xC6, 0x62, 0x3C, 0xB1, 0x18, 0x2F, 0x2E, 0x26, 0x06, 0x16, 0x13, 0xE2, 0x62,
+        0x62, 0x62, 0x31, 0x61, 0x5C, 0x4C, 0x2C, 0x2C, 0x26, 0x1C, 0x8B, 0x89, 0xC0, 0x62, 0x62, 0x63,
+        0x31, 0x91, 0x58, 0x4C, 0x14, 0x16, 0xB3, 0x07, 0x16, 0xB3, 0xC5, 0x1F, 0x7B, 0x27, 0x17, 0xB3,
+        0x0D, 0x8B, 0xD9, 0x8B, 0x8B, 0xD9, 0x8E, 0xC5, 0xEC, 0xE0, 0x62, 0xF6, 0xC6, 0x62, 0x76, 0x62,
+        0x31, 0xBB, 0xB0, 0x98, 0x1C, 0x58, 0x4C, 0x0A, 0x8B, 0xC9, 0x89, 0xC5, 0xA4, 0x61, 0x31, 0xB9,
+        0xB0, 0x98, 0xF4, 0xC6, 0xA6, 0x14, 0x58, 0x4C, 0x6E, 0x2E, 0x26, 0x13, 0x8B, 0xC9, 0xE2, 0x62,
+        0x6A, 0x60, 0x31, 0x25, 0x2C, 0xA6, 0x26, 0x16, 0x53, 0x86, 0xC5, 0xD4, 0xC2, 0x62, 0xCA, 0xB1,
+        0x98, 0x0A, 0x2C, 0xA6, 0x36, 0x16, 0x53, 0xC9, 0xC5, 0x54, 0x61, 0x31, 0x1A, 0xA3, 0xB3, 0x5E,
+        0x8B, 0xEF, 0xD7, 0x63, 0x36, 0x16, 0xEC, 0x61, 0x7C, 0xC3, 0x1E, 0x8B, 0xAF, 0xD8, 0xC3, 0xF9,
+        0x8E, 0x3D, 0x82, 0x2F, 0xD9, 0x63, 0xF3, 0x2D, 0x7B, 0x24, 0x5F, 0xB3, 0x47, 0xF1, 0x3D, 0xFB,
+        0x1C, 0x3B, 0xEC, 0x3A, 0x2B, 0xAE, 0x47, 0xE7, 0xDC, 0x71, 0xE5, 0x73, 0xEE, 0x1D, 0x0F, 0x7C,
+        0xCE, 0xC5, 0xE3, 0x21, 0xCF, 0x78, 0x83, 0xCF, 0x39, 0x7A, 0x5C, 0xF9, 0x9C, 0xAB, 0xC7, 0xC3,
+        0x49, 0x24, 0xE7, 0x73, 0xEE, 0x1E, 0xD7, 0x83, 0x38, 0x87, 0x8F, 0x2B, 0x9F, 0x73, 0xF8, 0x78,
+        0xE8, 0x54, 0x93, 0xF3, 0x39, 0x97, 0x8F, 0x2B, 0x9F, 0x17, 0xD2, 0xC7, 0xF5, 0xD0, 0xCE, 0xED,
+        0xE3, 0xCA, 0xE7, 0x1C, 0x3F, 0x1E, 0xF8, 0x9C, 0xF3, 0xC7, 0x95, 0xCF, 0x0B, 0xFD, 0xE3, 0xE1,
+        0xD8, 0xAA, 0xD1, 0xF7, 0x1A, 0x25, 0xD8, 0x78, 0x0B, 0x96, 0xF1, 0x1A, 0x2C, 0xE3, 0x3D, 0x58,
+        0xC6, 0x8B, 0xB0, 0x8C, 0x37, 0x61, 0x19, 0xAF, 0xC2, 0xB2, 0x46, 0x17, 0x96, 0xF1, 0x32, 0x2C,
+        0xEB, 0xB4, 0xE1, 0xD5, 0x88, 0xC3, 0xAB, 0x53, 0x87, 0x57, 0x23, 0x0F, 0xAF, 0x4E, 0x1F, 0x6E,
+        0x04, 0xE2, 0xD5, 0x28, 0xC4, 0xAB, 0x91, 0x88, 0x57, 0xA3, 0x11, 0xAF, 0x46, 0x24, 0x5E, 0xBC,
+        0x12, 0xCB, 0x1B, 0x99, 0x58, 0xCE, 0x3B, 0xB1, 0xBC, 0x11, 0x8A, 0xE5, 0xBC, 0x14, 0xCB, 0x1B,
+        0xA9, 0x58, 0xCE, 0x5B, 0xB1, 0x9C, 0xC7, 0x62, 0xF9, 0x6E, 0xBC, 0x5F, 0xE0, 0xB9, 0x58, 0xCE,
+        0x7B, 0xB1, 0x82, 0x07, 0x63, 0x05, 0x2F, 0xC6, 0x0A, 0x9E, 0x8C, 0x15, 0x8D, 0x66, 0xAC, 0xE0,
+        0xD1, 0x58, 0xD1, 0xA8, 0xC6, 0x0A, 0x9E, 0x8D, 0x15, 0x8D, 0x6E, 0xAC, 0xE0, 0xE1, 0x58, 0xC1,
+        0xCB, 0xB1, 0x36, 0x4F, 0xC7, 0xDA, 0xBC, 0x1D, 0x6B, 0xCF, 0xC6, 0x0B, 0x2A, 0x5E, 0x8F, 0xB5,
+        0x79, 0x3E, 0xD6, 0xE6, 0xFD, 0x58, 0xBB, 0x11, 0x90, 0xB5, 0x79, 0x41, 0xD6, 0x6E, 0x24, 0x64,
+        0x6D, 0xDE, 0x90, 0x95, 0x8D, 0x88, 0xAC, 0xE4, 0x15, 0x59, 0xC9, 0x33, 0xB2, 0x92, 0x77, 0x64,
+        0x25, 0x0F, 0xC9, 0x4A, 0x5E, 0x92, 0x95, 0xD1, 0x78, 0xC1, 0xC9, 0x5B, 0xB2, 0x92, 0xC7, 0x64,
+        0x65, 0xA3, 0x26, 0xAB, 0x78, 0x4E, 0x56, 0x35, 0x7A, 0xB2, 0x8A, 0x07, 0x65, 0x55, 0xA3, 0x28,
+        0xAB, 0x78, 0x52, 0x56, 0xF1, 0xA6, 0xAC, 0xE2, 0x51, 0x59, 0xC5, 0xAB, 0xB2, 0x8A, 0x67, 0x65,
+        0x15, 0xEA, 0xCA, 0xFF, 0x00, 0x77, 0x70, 0x91, 0x71,
+    ];
+}
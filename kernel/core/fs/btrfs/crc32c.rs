@@ -0,0 +1,15 @@
+// Btrfs CRC32Cチェックサム
+//
+// メタデータ/データのチェックサム（csum_type 0）とディレクトリ名のハッシュ
+
+use super::super::crc32c::crc32c;
+
+/// ブロックのチェックサム（シード`!0`から計算して最終値を反転したもの）
+pub fn checksum(data: &[u8]) -> u32 {
+    !crc32c(!0, data)
+}
+
+/// DIR_ITEMのキーに使う名前のハッシュ（Linuxの`btrfs_name_hash()`）
+pub fn name_hash(name: &[u8]) -> u64 {
+    crc32c(!1, name) as u64
+}
@@ -0,0 +1,134 @@
+// Btrfs ファイル/ディレクトリハンドル
+//
+// 読み取り専用のハンドル実装（エクステントの読み込みとディレクトリの列挙）
+
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType, Metadata, Permissions, FileHandle, DirHandle, DirEntry};
+use super::items::InodeItem;
+use super::{BtrfsVolume, InodeId};
+
+/// Btrfsファイルハンドル
+pub struct BtrfsFileHandle {
+    /// 所属ボリューム
+    volume: Arc<BtrfsVolume>,
+    /// iノード
+    id: InodeId,
+    /// 開いた時点のiノードの内容（読み取り専用なので変わらない）
+    inode: InodeItem,
+}
+
+impl BtrfsFileHandle {
+    /// 新しいファイルハンドルを作成
+    pub(super) fn new(volume: Arc<BtrfsVolume>, id: InodeId, inode: InodeItem) -> Self {
+        Self { volume, id, inode }
+    }
+}
+
+impl FileHandle for BtrfsFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.volume.read_file(self.id, &self.inode, buffer, offset)
+    }
+    
+    fn write(&self, _buffer: &[u8], _offset: u64) -> FsResult<usize> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.inode.size)
+    }
+    
+    fn resize(&self, _new_size: u64) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.volume.metadata(self.id, &self.inode))
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        false
+    }
+}
+
+/// Btrfsディレクトリハンドル
+pub struct BtrfsDirHandle {
+    /// 所属ボリューム
+    volume: Arc<BtrfsVolume>,
+    /// ディレクトリのiノード
+    id: InodeId,
+}
+
+impl BtrfsDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<BtrfsVolume>, id: InodeId) -> Self {
+        Self { volume, id }
+    }
+}
+
+impl DirHandle for BtrfsDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.volume.list_directory(self.id)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let item = self.volume.lookup_entry(self.id, name)?;
+        Ok(DirEntry {
+            name: name.to_string(),
+            inode: item.location.objectid,
+            file_type: item.file_type(),
+        })
+    }
+    
+    fn create_file(&self, _name: &str, _permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_directory(&self, _name: &str, _permissions: Permissions) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn remove(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn rename(&self, _old_name: &str, _new_name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let inode = self.volume.read_inode(self.id)?;
+        Ok(self.volume.metadata(self.id, &inode))
+    }
+    
+    fn device_number(&self, name: &str) -> FsResult<(u32, u32)> {
+        let target = self.volume.lookup(self.id, name)?;
+        let inode = self.volume.read_inode(target)?;
+        match inode.file_type() {
+            // Linuxのカーネル内部形式（メジャー12ビット、マイナー20ビット）のまま保存されている
+            FileType::BlockDevice | FileType::CharDevice => Ok(((inode.rdev >> 20) as u32, (inode.rdev & 0xF_FFFF) as u32)),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+}
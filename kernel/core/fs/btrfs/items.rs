@@ -0,0 +1,260 @@
+// Btrfs アイテム
+//
+// ファイルツリーとルートツリーのアイテム（INODE_ITEM、DIR_ITEM/DIR_INDEX、
+// EXTENT_DATA、ROOT_ITEM）の解析
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType};
+use super::tree::{Key, KEY_SIZE};
+use super::{le_u16, le_u32, le_u64};
+
+/// INODE_ITEMのサイズ
+const INODE_ITEM_SIZE: usize = 160;
+/// DIR_ITEMのヘッダサイズ
+const DIR_ITEM_HEADER_SIZE: usize = 30;
+/// EXTENT_DATAのインラインデータの開始位置
+const INLINE_DATA_OFFSET: usize = 21;
+/// 通常エクステントのEXTENT_DATAのサイズ
+const REGULAR_EXTENT_SIZE: usize = 53;
+/// ROOT_ITEMの最小サイズ（levelまで）
+const ROOT_ITEM_MIN_SIZE: usize = 239;
+
+/// iノードのフラグ
+pub mod inode_flags {
+    /// データのチェックサムを持たない
+    pub const NODATASUM: u64 = 1 << 0;
+}
+
+/// ディレクトリエントリの種別
+mod dir_type {
+    // 1は通常ファイル、0は不明
+    pub const DIR: u8 = 2;
+    pub const CHRDEV: u8 = 3;
+    pub const BLKDEV: u8 = 4;
+    pub const FIFO: u8 = 5;
+    pub const SOCK: u8 = 6;
+    pub const SYMLINK: u8 = 7;
+}
+
+/// iノード
+#[derive(Debug, Clone)]
+pub struct InodeItem {
+    /// ファイルサイズ
+    pub size: u64,
+    /// 割り当て済みのバイト数
+    pub nbytes: u64,
+    /// リンク数
+    pub nlink: u32,
+    /// 所有者
+    pub uid: u32,
+    /// グループ
+    pub gid: u32,
+    /// 種別と権限
+    pub mode: u32,
+    /// デバイス番号
+    pub rdev: u64,
+    /// フラグ
+    pub flags: u64,
+    /// 最終アクセス時刻（秒）
+    pub atime: u64,
+    /// 状態変更時刻（秒）
+    pub ctime: u64,
+    /// 最終更新時刻（秒）
+    pub mtime: u64,
+    /// 作成時刻（秒）
+    pub otime: u64,
+}
+
+impl InodeItem {
+    /// INODE_ITEM（ROOT_ITEMの先頭にも含まれる）を解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < INODE_ITEM_SIZE {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(Self {
+            size: le_u64(data, 16),
+            nbytes: le_u64(data, 24),
+            nlink: le_u32(data, 40),
+            uid: le_u32(data, 44),
+            gid: le_u32(data, 48),
+            mode: le_u32(data, 52),
+            rdev: le_u64(data, 56),
+            flags: le_u64(data, 64),
+            atime: le_u64(data, 112),
+            ctime: le_u64(data, 124),
+            mtime: le_u64(data, 136),
+            otime: le_u64(data, 148),
+        })
+    }
+    
+    /// ファイル種別
+    pub fn file_type(&self) -> FileType {
+        match self.mode & 0o170000 {
+            0o040000 => FileType::Directory,
+            0o120000 => FileType::SymbolicLink,
+            0o060000 => FileType::BlockDevice,
+            0o020000 => FileType::CharDevice,
+            0o010000 => FileType::NamedPipe,
+            0o140000 => FileType::Socket,
+            _ => FileType::Regular,
+        }
+    }
+}
+
+/// ディレクトリエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirItem {
+    /// 参照先（INODE_ITEMか、サブボリュームならROOT_ITEMのキー）
+    pub location: Key,
+    /// 種別
+    pub dir_type: u8,
+    /// 名前
+    pub name: String,
+}
+
+impl DirItem {
+    /// DIR_ITEM/DIR_INDEXのデータを解析（ハッシュが衝突した名前は1つのアイテムに並ぶ）
+    pub fn parse_all(data: &[u8]) -> FsResult<Vec<Self>> {
+        let mut items = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            if data.len() - position < DIR_ITEM_HEADER_SIZE {
+                return Err(FsError::CorruptedFs);
+            }
+            let raw = &data[position..];
+            let data_len = le_u16(raw, 25) as usize;
+            let name_len = le_u16(raw, 27) as usize;
+            let end = DIR_ITEM_HEADER_SIZE + name_len + data_len;
+            if raw.len() < end {
+                return Err(FsError::CorruptedFs);
+            }
+            
+            let name = &raw[DIR_ITEM_HEADER_SIZE..DIR_ITEM_HEADER_SIZE + name_len];
+            items.push(Self {
+                location: Key::parse(&raw[..KEY_SIZE]),
+                dir_type: raw[29],
+                name: String::from_utf8_lossy(name).into_owned(),
+            });
+            position += end;
+        }
+        Ok(items)
+    }
+    
+    /// ファイル種別
+    pub fn file_type(&self) -> FileType {
+        match self.dir_type {
+            dir_type::DIR => FileType::Directory,
+            dir_type::SYMLINK => FileType::SymbolicLink,
+            dir_type::BLKDEV => FileType::BlockDevice,
+            dir_type::CHRDEV => FileType::CharDevice,
+            dir_type::FIFO => FileType::NamedPipe,
+            dir_type::SOCK => FileType::Socket,
+            _ => FileType::Regular,
+        }
+    }
+}
+
+/// ファイルのエクステント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileExtent {
+    /// リーフ内に置かれたデータ
+    Inline {
+        /// 圧縮方式
+        compression: u8,
+        /// 伸長後のサイズ
+        ram_bytes: u64,
+        /// 格納されたデータ（圧縮されていれば圧縮後）
+        data: Vec<u8>,
+    },
+    /// ディスク上のエクステント
+    Regular {
+        /// 圧縮方式
+        compression: u8,
+        /// エクステントの論理アドレス（0なら穴）
+        disk_bytenr: u64,
+        /// ディスク上のエクステントのサイズ
+        disk_num_bytes: u64,
+        /// 伸長後のエクステント内でこのファイル範囲が始まる位置
+        offset: u64,
+        /// このファイル範囲の長さ
+        num_bytes: u64,
+        /// 伸長後のエクステント全体のサイズ
+        ram_bytes: u64,
+        /// 事前割り当てのみで未書き込み（0として読む）
+        prealloc: bool,
+    },
+}
+
+impl FileExtent {
+    /// EXTENT_DATAを解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < INLINE_DATA_OFFSET {
+            return Err(FsError::CorruptedFs);
+        }
+        let ram_bytes = le_u64(data, 8);
+        let compression = data[16];
+        if data[17] != 0 || le_u16(data, 18) != 0 {
+            log::warn!("Btrfs: 暗号化/独自エンコードされたエクステントは読み込めません");
+            return Err(FsError::UnsupportedFeature);
+        }
+        
+        match data[20] {
+            0 => Ok(FileExtent::Inline { compression, ram_bytes, data: data[INLINE_DATA_OFFSET..].to_vec() }),
+            kind @ (1 | 2) => {
+                if data.len() < REGULAR_EXTENT_SIZE {
+                    return Err(FsError::CorruptedFs);
+                }
+                Ok(FileExtent::Regular {
+                    compression,
+                    disk_bytenr: le_u64(data, 21),
+                    disk_num_bytes: le_u64(data, 29),
+                    offset: le_u64(data, 37),
+                    num_bytes: le_u64(data, 45),
+                    ram_bytes,
+                    prealloc: kind == 2,
+                })
+            },
+            kind => {
+                log::warn!("Btrfs: 不明なエクステント種別{}", kind);
+                Err(FsError::CorruptedFs)
+            },
+        }
+    }
+    
+    /// ファイル内でこのエクステントが覆う長さ
+    pub fn file_len(&self) -> u64 {
+        match self {
+            FileExtent::Inline { ram_bytes, .. } => *ram_bytes,
+            FileExtent::Regular { num_bytes, .. } => *num_bytes,
+        }
+    }
+}
+
+/// ツリーのルート
+#[derive(Debug, Clone)]
+pub struct RootItem {
+    /// ルートのiノード（サブボリュームの作成時刻などを持つ）
+    pub inode: InodeItem,
+    /// ルートディレクトリのオブジェクトID
+    pub root_dirid: u64,
+    /// ルートブロックの論理アドレス
+    pub bytenr: u64,
+    /// ルートブロックの高さ
+    pub level: u8,
+}
+
+impl RootItem {
+    /// ROOT_ITEMを解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < ROOT_ITEM_MIN_SIZE {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(Self {
+            inode: InodeItem::parse(data)?,
+            root_dirid: le_u64(data, 168),
+            bytenr: le_u64(data, 176),
+            level: data[238],
+        })
+    }
+}
@@ -0,0 +1,1060 @@
+// Btrfs ファイルシステム実装
+//
+// Btrfsの読み取り専用実装（スーパーブロックのミラー選択、sys_chunk_arrayとチャンクツリーによる
+// 論理アドレス変換、ルートツリー/ファイルツリーの探索、インライン/通常エクステント、
+// zlib/LZO/zstd圧縮、CRC32Cによるメタデータとデータの検証、サブボリューム/スナップショットの選択）
+
+mod crc32c;
+mod superblock;
+mod chunk;
+mod tree;
+mod items;
+mod compression;
+mod zstd;
+mod file;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, Filesystem};
+use super::vfs::BlockDevice;
+use self::chunk::{Chunk, ChunkMap};
+use self::compression::kind;
+use self::crc32c::{checksum, name_hash};
+use self::items::{inode_flags, DirItem, FileExtent, InodeItem, RootItem};
+use self::superblock::{BtrfsSuperblock, SUPERBLOCK_SIZE};
+use self::tree::{key_type, objectid, Key, Node};
+use self::file::{BtrfsFileHandle, BtrfsDirHandle};
+
+/// パス解決時のディレクトリ階層上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// キャッシュするツリーブロック数の上限
+const NODE_CACHE_CAPACITY: usize = 1024;
+
+/// 名前の長さの上限
+const MAX_NAME_LENGTH: u32 = 255;
+
+/// 圧縮エクステントの伸長後/圧縮後のサイズの上限
+const MAX_COMPRESSED_EXTENT: u64 = 128 * 1024;
+
+/// CRC32Cのチェックサムのサイズ（チェックサムツリーでの1セクタ分）
+const DATA_CSUM_SIZE: usize = 4;
+
+/// リトルエンディアンのu16を読み込み
+fn le_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// リトルエンディアンのu32を読み込み
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// リトルエンディアンのu64を読み込み
+fn le_u64(data: &[u8], offset: usize) -> u64 {
+    le_u32(data, offset) as u64 | (le_u32(data, offset + 4) as u64) << 32
+}
+
+/// デバイスからバイト単位で読み込み（ブロック境界をまたいでよい）
+fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+    
+    if data.len() < start + len {
+        return Err(FsError::IoError);
+    }
+    
+    Ok(data[start..start + len].to_vec())
+}
+
+/// Btrfsオプション
+#[derive(Debug, Clone, Copy)]
+pub struct BtrfsOptions {
+    /// データのチェックサムを検証する（メタデータは常に検証する）
+    pub verify_checksums: bool,
+    /// 読み込んだツリーブロックをキャッシュする
+    pub cache_nodes: bool,
+}
+
+impl Default for BtrfsOptions {
+    fn default() -> Self {
+        Self {
+            verify_checksums: true,
+            cache_nodes: true,
+        }
+    }
+}
+
+/// ファイルツリー内のiノード（サブボリュームごとにオブジェクトIDが独立している）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InodeId {
+    /// ファイルツリー（サブボリューム）のID
+    tree: u64,
+    /// オブジェクトID
+    objectid: u64,
+}
+
+/// ツリーのルートブロック（論理アドレス, 高さ）
+type TreeRoot = (u64, u8);
+
+/// マウントされたBtrfsボリューム
+struct BtrfsVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// 選択したスーパーブロック
+    superblock: BtrfsSuperblock,
+    /// 論理アドレスの変換表
+    chunks: ChunkMap,
+    /// データのチェックサムを検証するか
+    verify_checksums: bool,
+    /// ツリーブロックのキャッシュ（無効時はNone）
+    node_cache: Option<Mutex<BTreeMap<u64, Arc<Node>>>>,
+    /// ツリーID => ROOT_ITEM
+    roots: Mutex<BTreeMap<u64, RootItem>>,
+    /// 直前に伸長した圧縮エクステント（論理アドレス, 内容）
+    extent_cache: Mutex<Option<(u64, Arc<Vec<u8>>)>>,
+    /// マウントしたサブボリュームのルートディレクトリ
+    top: InodeId,
+}
+
+impl BtrfsVolume {
+    /// デバイスからボリュームを構築し、`subvol`/`subvolid`で指定されたサブボリュームを選ぶ
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, options: BtrfsOptions,
+            subvol: Option<&str>, subvolid: Option<u64>) -> FsResult<Self> {
+        let device_size = device.total_blocks() * device.block_size();
+        let superblock = superblock::select_mirror(|offset| {
+            (offset + SUPERBLOCK_SIZE as u64 <= device_size).then(|| read_bytes(&*device, offset, SUPERBLOCK_SIZE))
+        })?;
+        
+        if superblock.num_devices != 1 {
+            log::warn!("Btrfs: 複数デバイス（{}台）のファイルシステムには対応していません", superblock.num_devices);
+            return Err(FsError::UnsupportedFeature);
+        }
+        if superblock.log_root != 0 {
+            log::warn!("Btrfs: ログツリーは再生しないため、最後のコミット後にfsyncされた変更は見えません");
+        }
+        
+        let mut chunks = ChunkMap::new(superblock.devid);
+        chunks.load_sys_chunk_array(&superblock.sys_chunk_array)?;
+        
+        let mut volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            chunks,
+            verify_checksums: options.verify_checksums,
+            node_cache: options.cache_nodes.then(|| Mutex::new(BTreeMap::new())),
+            roots: Mutex::new(BTreeMap::new()),
+            extent_cache: Mutex::new(None),
+            top: InodeId { tree: objectid::FS_TREE, objectid: objectid::FIRST_FREE },
+            superblock,
+        };
+        
+        // SYSTEMチャンクだけでチャンクツリーを読み、残りのチャンクを登録する
+        let chunk_root = (volume.superblock.chunk_root, volume.superblock.chunk_root_level);
+        let mut found = Vec::new();
+        volume.walk(chunk_root,
+                    &Key::new(objectid::FIRST_CHUNK_TREE, key_type::CHUNK_ITEM, 0),
+                    &Key::new(objectid::FIRST_CHUNK_TREE, key_type::CHUNK_ITEM, u64::MAX),
+                    &mut |key, data| {
+                        found.push(Chunk::parse(key.offset, data)?.0);
+                        Ok(true)
+                    })?;
+        for chunk in found {
+            volume.chunks.insert(chunk);
+        }
+        
+        volume.top = volume.select_subvolume(subvol, subvolid)?;
+        if volume.read_inode(volume.top)?.file_type() != FileType::Directory {
+            log::warn!("Btrfs: サブボリューム{}のルートディレクトリが不正です", volume.top.tree);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        log::info!("Btrfs: ボリューム '{}' (世代{}, セクタ{}バイト, ノード{}バイト, サブボリューム{})",
+                  volume.superblock.label, volume.superblock.generation, volume.superblock.sectorsize,
+                  volume.superblock.nodesize, volume.top.tree);
+        
+        Ok(volume)
+    }
+    
+    /// ルートツリーのルートブロック
+    fn root_tree(&self) -> TreeRoot {
+        (self.superblock.root, self.superblock.root_level)
+    }
+    
+    /// 論理アドレスの範囲を読み込み
+    fn read_logical(&self, logical: u64, len: usize) -> FsResult<Vec<u8>> {
+        let mut data = Vec::with_capacity(len);
+        for (physical, length) in self.chunks.map_range(logical, len as u64)? {
+            data.extend_from_slice(&read_bytes(&*self.device, physical, length as usize)?);
+        }
+        Ok(data)
+    }
+    
+    /// ツリーブロックを読み込んで検証する（`generation`は親が記録した世代）
+    fn read_node(&self, bytenr: u64, level: u8, generation: Option<u64>) -> FsResult<Arc<Node>> {
+        let cached = self.node_cache.as_ref().and_then(|cache| cache.lock().get(&bytenr).cloned());
+        let node = match cached {
+            Some(node) => node,
+            None => {
+                let data = self.read_logical(bytenr, self.superblock.nodesize as usize)?;
+                let node = Arc::new(Node::parse(data, bytenr, &self.superblock.metadata_uuid)?);
+                if let Some(cache) = &self.node_cache {
+                    let mut cache = cache.lock();
+                    if cache.len() >= NODE_CACHE_CAPACITY {
+                        cache.pop_first();
+                    }
+                    cache.insert(bytenr, node.clone());
+                }
+                node
+            },
+        };
+        
+        if node.level != level || generation.is_some_and(|generation| generation != node.generation) {
+            log::warn!("Btrfs: ツリーブロック{:#x}の高さ/世代が親の記録と一致しません", bytenr);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(node)
+    }
+    
+    /// `min`以上`max`以下のキーを持つアイテムを順に`visit`へ渡す（`visit`がfalseを返すと打ち切る）
+    fn walk(&self, root: TreeRoot, min: &Key, max: &Key,
+            visit: &mut dyn FnMut(&Key, &[u8]) -> FsResult<bool>) -> FsResult<()> {
+        self.walk_node(root.0, root.1, None, min, max, visit).map(|_| ())
+    }
+    
+    /// `walk`の再帰部分（続けるならtrueを返す）
+    fn walk_node(&self, bytenr: u64, level: u8, generation: Option<u64>, min: &Key, max: &Key,
+                 visit: &mut dyn FnMut(&Key, &[u8]) -> FsResult<bool>) -> FsResult<bool> {
+        let node = self.read_node(bytenr, level, generation)?;
+        
+        if node.is_leaf() {
+            for index in node.first_item_from(min)..node.nritems {
+                let key = node.key(index);
+                if key > *max || !visit(&key, node.item_data(index))? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        
+        let first = node.child_for(min);
+        for index in first..node.nritems {
+            if index > first && node.key(index) > *max {
+                return Ok(false);
+            }
+            let (child, child_generation) = node.child(index);
+            if !self.walk_node(child, level - 1, Some(child_generation), min, max, visit)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+    
+    /// `key`以下で最大のキー
+    fn floor_key(&self, root: TreeRoot, key: &Key) -> FsResult<Option<Key>> {
+        let (mut bytenr, mut level) = root;
+        let mut generation = None;
+        loop {
+            let node = self.read_node(bytenr, level, generation)?;
+            if node.nritems == 0 {
+                return Ok(None);
+            }
+            if node.is_leaf() {
+                let index = node.first_item_from(key);
+                if index < node.nritems && node.key(index) == *key {
+                    return Ok(Some(*key));
+                }
+                return Ok(index.checked_sub(1).map(|index| node.key(index)));
+            }
+            let (child, child_generation) = node.child(node.child_for(key));
+            bytenr = child;
+            generation = Some(child_generation);
+            level -= 1;
+        }
+    }
+    
+    /// `key`と一致するアイテムのデータ
+    fn find_item(&self, root: TreeRoot, key: &Key) -> FsResult<Option<Vec<u8>>> {
+        let mut found = None;
+        self.walk(root, key, key, &mut |_, data| {
+            found = Some(data.to_vec());
+            Ok(false)
+        })?;
+        Ok(found)
+    }
+    
+    /// `objectid`と種別が同じで、オフセットが`offset`以下の最後のアイテムから探索を始めるキー
+    fn start_key(&self, root: TreeRoot, objectid: u64, item_type: u8, offset: u64) -> FsResult<Key> {
+        let key = Key::new(objectid, item_type, offset);
+        Ok(match self.floor_key(root, &key)? {
+            Some(found) if found.objectid == objectid && found.item_type == item_type => found,
+            _ => Key::new(objectid, item_type, 0),
+        })
+    }
+    
+    /// ルートツリーからツリーのROOT_ITEMを取得
+    fn tree_root(&self, tree: u64) -> FsResult<RootItem> {
+        if let Some(root) = self.roots.lock().get(&tree) {
+            return Ok(root.clone());
+        }
+        
+        // スナップショットではオフセットに作成時の世代が入るため、最後のものを使う
+        let mut found = None;
+        self.walk(self.root_tree(),
+                  &Key::new(tree, key_type::ROOT_ITEM, 0),
+                  &Key::new(tree, key_type::ROOT_ITEM, u64::MAX),
+                  &mut |_, data| {
+                      found = Some(RootItem::parse(data)?);
+                      Ok(true)
+                  })?;
+        let root = found.ok_or(FsError::NotFound)?;
+        
+        self.roots.lock().insert(tree, root.clone());
+        Ok(root)
+    }
+    
+    /// ファイルツリーのルートブロック
+    fn fs_tree(&self, tree: u64) -> FsResult<TreeRoot> {
+        let root = self.tree_root(tree)?;
+        Ok((root.bytenr, root.level))
+    }
+    
+    /// サブボリュームのルートディレクトリ
+    fn subvolume_root(&self, tree: u64) -> FsResult<InodeId> {
+        if tree != objectid::FS_TREE && !(objectid::FIRST_FREE..=objectid::LAST_FREE).contains(&tree) {
+            log::warn!("Btrfs: ID {}はサブボリュームではありません", tree);
+            return Err(FsError::InvalidData);
+        }
+        let root = self.tree_root(tree)?;
+        Ok(InodeId { tree, objectid: root.root_dirid })
+    }
+    
+    /// マウントするサブボリュームを選ぶ（指定が無ければ"default"エントリが指すもの）
+    fn select_subvolume(&self, path: Option<&str>, id: Option<u64>) -> FsResult<InodeId> {
+        let by_path = match path {
+            Some(path) => {
+                let top = self.subvolume_root(objectid::FS_TREE)?;
+                let found = self.resolve_from(top, path)?;
+                if found.objectid != self.tree_root(found.tree)?.root_dirid {
+                    log::warn!("Btrfs: {}はサブボリュームではありません", path);
+                    return Err(FsError::InvalidData);
+                }
+                Some(found.tree)
+            },
+            None => None,
+        };
+        
+        let tree = match (by_path, id) {
+            (Some(by_path), Some(id)) if by_path != id => {
+                log::warn!("Btrfs: subvol={}とsubvolid={}が一致しません", path.unwrap_or_default(), id);
+                return Err(FsError::InvalidData);
+            },
+            (Some(tree), _) | (None, Some(tree)) => tree,
+            (None, None) => self.default_subvolume()?,
+        };
+        self.subvolume_root(tree)
+    }
+    
+    /// `btrfs subvolume set-default`で設定されたサブボリューム
+    fn default_subvolume(&self) -> FsResult<u64> {
+        let key = Key::new(objectid::ROOT_TREE_DIR, key_type::DIR_ITEM, name_hash(b"default"));
+        let Some(data) = self.find_item(self.root_tree(), &key)? else {
+            return Ok(objectid::FS_TREE);
+        };
+        Ok(DirItem::parse_all(&data)?
+            .into_iter()
+            .find(|item| item.name == "default")
+            .map_or(objectid::FS_TREE, |item| item.location.objectid))
+    }
+    
+    /// iノードを読み込み
+    fn read_inode(&self, id: InodeId) -> FsResult<InodeItem> {
+        let data = self.find_item(self.fs_tree(id.tree)?, &Key::new(id.objectid, key_type::INODE_ITEM, 0))?
+            .ok_or_else(|| {
+                log::warn!("Btrfs: サブボリューム{}のiノード{}が見つかりません", id.tree, id.objectid);
+                FsError::CorruptedFs
+            })?;
+        InodeItem::parse(&data)
+    }
+    
+    /// ディレクトリエントリの参照先（サブボリュームならそのルートディレクトリ）
+    fn entry_target(&self, tree: u64, item: &DirItem) -> FsResult<InodeId> {
+        if item.location.item_type == key_type::ROOT_ITEM {
+            return self.subvolume_root(item.location.objectid);
+        }
+        Ok(InodeId { tree, objectid: item.location.objectid })
+    }
+    
+    /// ディレクトリ内の名前を検索
+    fn lookup_entry(&self, dir: InodeId, name: &str) -> FsResult<DirItem> {
+        if name.len() > MAX_NAME_LENGTH as usize {
+            return Err(FsError::NotFound);
+        }
+        let key = Key::new(dir.objectid, key_type::DIR_ITEM, name_hash(name.as_bytes()));
+        let data = self.find_item(self.fs_tree(dir.tree)?, &key)?.ok_or(FsError::NotFound)?;
+        DirItem::parse_all(&data)?
+            .into_iter()
+            .find(|item| item.name == name)
+            .ok_or(FsError::NotFound)
+    }
+    
+    /// ディレクトリ内の名前を検索してiノードを返す
+    fn lookup(&self, dir: InodeId, name: &str) -> FsResult<InodeId> {
+        let item = self.lookup_entry(dir, name)?;
+        self.entry_target(dir.tree, &item)
+    }
+    
+    /// マウントしたサブボリュームからのパスを解決
+    fn resolve(&self, path: &str) -> FsResult<InodeId> {
+        self.resolve_from(self.top, path)
+    }
+    
+    /// `start`からのパスを解決
+    fn resolve_from(&self, start: InodeId, path: &str) -> FsResult<InodeId> {
+        let mut stack = vec![start];
+        
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if stack.len() > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
+            }
+            
+            match component {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                },
+                name => {
+                    let current = *stack.last().ok_or(FsError::NotFound)?;
+                    if self.read_inode(current)?.file_type() != FileType::Directory {
+                        return Err(FsError::NotDirectory);
+                    }
+                    let next = self.lookup(current, name)?;
+                    stack.push(next);
+                },
+            }
+        }
+        
+        stack.pop().ok_or(FsError::NotFound)
+    }
+    
+    /// ディレクトリの内容を作成順（DIR_INDEX順）に列挙
+    fn list_directory(&self, dir: InodeId) -> FsResult<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        self.walk(self.fs_tree(dir.tree)?,
+                  &Key::new(dir.objectid, key_type::DIR_INDEX, 0),
+                  &Key::new(dir.objectid, key_type::DIR_INDEX, u64::MAX),
+                  &mut |_, data| {
+                      for item in DirItem::parse_all(data)? {
+                          entries.push(DirEntry {
+                              inode: item.location.objectid,
+                              file_type: item.file_type(),
+                              name: item.name,
+                          });
+                      }
+                      Ok(true)
+                  })?;
+        Ok(entries)
+    }
+    
+    /// ファイルの内容を読み込み（穴と事前割り当て領域は0として読む）
+    fn read_file(&self, id: InodeId, inode: &InodeItem, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        if offset >= inode.size {
+            return Ok(0);
+        }
+        let len = (buffer.len() as u64).min(inode.size - offset) as usize;
+        let end = offset + len as u64;
+        let buffer = &mut buffer[..len];
+        buffer.fill(0);
+        
+        let root = self.fs_tree(id.tree)?;
+        let start = self.start_key(root, id.objectid, key_type::EXTENT_DATA, offset)?;
+        let mut extents = Vec::new();
+        self.walk(root, &start, &Key::new(id.objectid, key_type::EXTENT_DATA, end - 1), &mut |key, data| {
+            extents.push((key.offset, FileExtent::parse(data)?));
+            Ok(true)
+        })?;
+        
+        let verify = self.verify_checksums && inode.flags & inode_flags::NODATASUM == 0;
+        for (position, extent) in extents {
+            let from = position.max(offset);
+            let to = position.saturating_add(extent.file_len()).min(end);
+            if from >= to {
+                continue;
+            }
+            let dst = &mut buffer[(from - offset) as usize..(to - offset) as usize];
+            self.read_extent(&extent, from - position, dst, verify)?;
+        }
+        Ok(len)
+    }
+    
+    /// エクステントの`within`バイト目からを`dst`へ読み込み
+    fn read_extent(&self, extent: &FileExtent, within: u64, dst: &mut [u8], verify: bool) -> FsResult<()> {
+        match *extent {
+            FileExtent::Inline { compression, ram_bytes, ref data } => {
+                if ram_bytes > self.superblock.sectorsize as u64 {
+                    return Err(FsError::CorruptedFs);
+                }
+                if compression == kind::NONE {
+                    copy_from(data, within, dst);
+                } else {
+                    let mut content = vec![0u8; ram_bytes as usize];
+                    compression::decompress(compression, data, &mut content, self.superblock.sectorsize as usize)?;
+                    copy_from(&content, within, dst);
+                }
+                Ok(())
+            },
+            FileExtent::Regular { disk_bytenr: 0, .. } | FileExtent::Regular { prealloc: true, .. } => Ok(()),
+            FileExtent::Regular { compression: kind::NONE, disk_bytenr, offset, .. } => {
+                let data = self.read_data(disk_bytenr + offset + within, dst.len(), verify)?;
+                dst.copy_from_slice(&data);
+                Ok(())
+            },
+            FileExtent::Regular { compression, disk_bytenr, disk_num_bytes, offset, ram_bytes, .. } => {
+                let content = self.decompressed_extent(compression, disk_bytenr, disk_num_bytes, ram_bytes, verify)?;
+                copy_from(&content, offset + within, dst);
+                Ok(())
+            },
+        }
+    }
+    
+    /// 圧縮エクステント全体を読み込んで伸長（直前のものはキャッシュから返す）
+    fn decompressed_extent(&self, compression: u8, disk_bytenr: u64, disk_num_bytes: u64, ram_bytes: u64,
+                           verify: bool) -> FsResult<Arc<Vec<u8>>> {
+        if let Some((bytenr, content)) = &*self.extent_cache.lock() {
+            if *bytenr == disk_bytenr {
+                return Ok(content.clone());
+            }
+        }
+        if disk_num_bytes > MAX_COMPRESSED_EXTENT || ram_bytes > MAX_COMPRESSED_EXTENT {
+            log::warn!("Btrfs: 圧縮エクステント{:#x}が大きすぎます", disk_bytenr);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        // チェックサムは圧縮後のデータに対して付く
+        let raw = self.read_data(disk_bytenr, disk_num_bytes as usize, verify)?;
+        let mut content = vec![0u8; ram_bytes as usize];
+        compression::decompress(compression, &raw, &mut content, self.superblock.sectorsize as usize)?;
+        
+        let content = Arc::new(content);
+        *self.extent_cache.lock() = Some((disk_bytenr, content.clone()));
+        Ok(content)
+    }
+    
+    /// データを論理アドレスから読み込み、`verify`ならセクタ単位でチェックサムを確認する
+    fn read_data(&self, logical: u64, len: usize, verify: bool) -> FsResult<Vec<u8>> {
+        if !verify {
+            return self.read_logical(logical, len);
+        }
+        
+        let sector = self.superblock.sectorsize as u64;
+        let start = logical / sector * sector;
+        let end = (logical + len as u64).div_ceil(sector) * sector;
+        let data = self.read_logical(start, (end - start) as usize)?;
+        self.verify_data(start, &data)?;
+        
+        let skip = (logical - start) as usize;
+        Ok(data[skip..skip + len].to_vec())
+    }
+    
+    /// セクタ境界から始まるデータをチェックサムツリーの値と照合（値の無いセクタは確認しない）
+    fn verify_data(&self, start: u64, data: &[u8]) -> FsResult<()> {
+        let root = match self.fs_tree(objectid::CSUM_TREE) {
+            Ok(root) => root,
+            Err(FsError::NotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        
+        let sector = self.superblock.sectorsize as usize;
+        let end = start + data.len() as u64;
+        let first = self.start_key(root, objectid::EXTENT_CSUM, key_type::EXTENT_CSUM, start)?;
+        self.walk(root, &first, &Key::new(objectid::EXTENT_CSUM, key_type::EXTENT_CSUM, end - 1), &mut |key, item| {
+            for (index, expected) in item.chunks_exact(DATA_CSUM_SIZE).enumerate() {
+                let bytenr = key.offset + (index * sector) as u64;
+                if bytenr >= end {
+                    break;
+                }
+                if bytenr < start {
+                    continue;
+                }
+                let offset = (bytenr - start) as usize;
+                if checksum(&data[offset..offset + sector]) != le_u32(expected, 0) {
+                    log::warn!("Btrfs: 論理アドレス{:#x}のデータのチェックサムが一致しません", bytenr);
+                    return Err(FsError::ChecksumError);
+                }
+            }
+            Ok(true)
+        })
+    }
+    
+    /// シンボリックリンクの内容を読み込み
+    fn read_link(&self, id: InodeId) -> FsResult<String> {
+        let inode = self.read_inode(id)?;
+        if inode.file_type() != FileType::SymbolicLink {
+            return Err(FsError::InvalidData);
+        }
+        if inode.size > self.superblock.sectorsize as u64 {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut target = vec![0u8; inode.size as usize];
+        let len = self.read_file(id, &inode, &mut target, 0)?;
+        target.truncate(len);
+        String::from_utf8(target).map_err(|_| FsError::InvalidData)
+    }
+    
+    /// iノードのメタデータを構築
+    fn metadata(&self, id: InodeId, inode: &InodeItem) -> Metadata {
+        let sector = self.superblock.sectorsize;
+        Metadata {
+            inode: id.objectid,
+            file_type: inode.file_type(),
+            size: inode.size,
+            uid: inode.uid,
+            gid: inode.gid,
+            permissions: Permissions {
+                read: inode.mode & 0o400 != 0,
+                write: false,
+                execute: inode.mode & 0o100 != 0,
+            },
+            created: inode.otime,
+            accessed: inode.atime,
+            modified: inode.mtime,
+            links: inode.nlink,
+            block_size: sector,
+            blocks: inode.nbytes.div_ceil(sector as u64),
+        }
+    }
+    
+    /// ファイルシステム統計を取得
+    fn stats(&self) -> FsStats {
+        let sector = self.superblock.sectorsize as u64;
+        let free = self.superblock.total_bytes.saturating_sub(self.superblock.bytes_used) / sector;
+        FsStats {
+            total_blocks: self.superblock.total_bytes / sector,
+            free_blocks: free,
+            available_blocks: free,
+            // iノードは動的に割り当てるため上限が無い
+            total_nodes: 0,
+            free_nodes: 0,
+            block_size: sector as u32,
+            max_filename_length: MAX_NAME_LENGTH,
+        }
+    }
+}
+
+/// `src`の`offset`バイト目からを`dst`へコピー（`src`の外は0のまま）
+fn copy_from(src: &[u8], offset: u64, dst: &mut [u8]) {
+    let Some(src) = usize::try_from(offset).ok().and_then(|offset| src.get(offset..)) else {
+        return;
+    };
+    let len = src.len().min(dst.len());
+    dst[..len].copy_from_slice(&src[..len]);
+}
+
+/// Btrfsファイルシステム
+pub struct BtrfsFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// オプション
+    options: BtrfsOptions,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<BtrfsVolume>>>,
+}
+
+impl BtrfsFilesystem {
+    /// 新しいBtrfsファイルシステムインスタンスを作成
+    pub fn new() -> Self {
+        Self::new_advanced(BtrfsOptions::default())
+    }
+    
+    /// ツリーブロックをキャッシュする最適化されたBtrfsファイルシステムインスタンスを作成
+    pub fn new_optimized(verify_checksums: bool) -> Self {
+        Self::new_advanced(BtrfsOptions {
+            verify_checksums,
+            cache_nodes: true,
+        })
+    }
+    
+    /// 高度なオプションでBtrfsファイルシステムインスタンスを作成
+    pub fn new_advanced(options: BtrfsOptions) -> Self {
+        Self {
+            name: "btrfs".to_string(),
+            options,
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<BtrfsVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+}
+
+impl Default for BtrfsFilesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filesystem for BtrfsFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        let mut subvol = None;
+        let mut subvolid = None;
+        for option in options.split(',').map(str::trim) {
+            if let Some(path) = option.strip_prefix("subvol=") {
+                subvol = Some(path);
+            } else if let Some(id) = option.strip_prefix("subvolid=") {
+                subvolid = Some(id.parse::<u64>().map_err(|_| FsError::InvalidData)?);
+            } else if option == "rw" {
+                log::warn!("Btrfs: 書き込みには対応していないため読み取り専用でマウントします");
+            }
+        }
+        
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = BtrfsVolume::open(device, block_device, self.options, subvol, subvolid)?;
+        let tree = volume.top.tree;
+        
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
+        
+        log::info!("Btrfsファイルシステムをマウント: {} -> {} (サブボリューム{}, 読み取り専用)", device, mount_point, tree);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        volume.device.close()?;
+        
+        log::info!("Btrfsファイルシステムをアンマウント: {} ({})", mount_point, volume.device_path);
+        Ok(())
+    }
+    
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        if mode != OpenMode::ReadOnly {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let volume = self.find_volume(mount_point)?;
+        let id = volume.resolve(path)?;
+        let inode = volume.read_inode(id)?;
+        if inode.file_type() == FileType::Directory {
+            return Err(FsError::IsDirectory);
+        }
+        
+        Ok(Arc::new(BtrfsFileHandle::new(volume, id, inode)))
+    }
+    
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let id = volume.resolve(path)?;
+        
+        if volume.read_inode(id)?.file_type() != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        
+        Ok(Arc::new(BtrfsDirHandle::new(volume, id)))
+    }
+    
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        let id = volume.resolve(path)?;
+        Ok(volume.metadata(id, &volume.read_inode(id)?))
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        Ok(self.find_volume(mount_point)?.stats())
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        // 読み取り専用のため書き戻すものは無い
+        Ok(())
+    }
+    
+    fn read_link(&self, mount_point: &str, path: &str) -> FsResult<String> {
+        let volume = self.find_volume(mount_point)?;
+        volume.read_link(volume.resolve(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::chunk::block_group;
+    use super::chunk::tests::chunk_item;
+    use super::superblock::tests as sb;
+    use super::superblock::MIRROR_OFFSETS;
+    use super::tree::tests::{internal, leaf};
+    use super::zstd::tests::ZSTD_SAMPLE;
+    use super::compression::tests::sample_text;
+    use super::super::memory_disk::MemoryDisk;
+    
+    const UUID: [u8; 16] = [0xAB; 16];
+    const NODESIZE: usize = 16384;
+    /// SYSTEMチャンク（論理 => 物理）
+    const SYSTEM: (u64, u64) = (0x100_0000, 0x2_0000);
+    /// メタデータ/データ兼用のチャンク（論理 => 物理）
+    const MIXED: (u64, u64) = (0x200_0000, 0x8_0000);
+    /// 非圧縮データのエクステント
+    const PLAIN_EXTENT: u64 = 0x202_0000;
+    /// zstd圧縮データのエクステント
+    const ZSTD_EXTENT: u64 = 0x203_0000;
+    
+    fn inode(mode: u32, size: u64, nbytes: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 160];
+        data[16..24].copy_from_slice(&size.to_le_bytes());
+        data[24..32].copy_from_slice(&nbytes.to_le_bytes());
+        data[40..44].copy_from_slice(&1u32.to_le_bytes());
+        data[52..56].copy_from_slice(&mode.to_le_bytes());
+        data[136..144].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+        data
+    }
+    
+    fn dir_item(location: Key, dir_type: u8, name: &str) -> Vec<u8> {
+        let mut data = location.to_bytes().to_vec();
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        data.push(dir_type);
+        data.extend_from_slice(name.as_bytes());
+        data
+    }
+    
+    fn inline_extent(content: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; 21];
+        data[8..16].copy_from_slice(&(content.len() as u64).to_le_bytes());
+        data.extend_from_slice(content);
+        data
+    }
+    
+    fn regular_extent(compression: u8, disk: (u64, u64), offset: u64, num_bytes: u64, ram_bytes: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 53];
+        data[8..16].copy_from_slice(&ram_bytes.to_le_bytes());
+        data[16] = compression;
+        data[20] = 1;
+        data[21..29].copy_from_slice(&disk.0.to_le_bytes());
+        data[29..37].copy_from_slice(&disk.1.to_le_bytes());
+        data[37..45].copy_from_slice(&offset.to_le_bytes());
+        data[45..53].copy_from_slice(&num_bytes.to_le_bytes());
+        data
+    }
+    
+    fn root_item(bytenr: u64, level: u8) -> Vec<u8> {
+        let mut data = inode(0o040755, 3, 16384);
+        data.resize(239, 0);
+        data[168..176].copy_from_slice(&256u64.to_le_bytes());
+        data[176..184].copy_from_slice(&bytenr.to_le_bytes());
+        data[238] = level;
+        data
+    }
+    
+    fn put(image: &mut [u8], logical: u64, data: &[u8]) {
+        let physical = (logical - MIXED.0 + MIXED.1) as usize;
+        image[physical..physical + data.len()].copy_from_slice(data);
+    }
+    
+    /// 非圧縮エクステントの内容（12KiB、先頭4KiBはファイルから参照しない）
+    fn plain_data() -> Vec<u8> {
+        (0..12288u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+    
+    /// 最上位のファイルツリーに"hello.txt"/"big.bin"/"snap"/"link"、
+    /// スナップショット256に"inner.txt"を持ち、"default"が256を指すイメージ
+    fn build_image() -> Vec<u8> {
+        let mut image = vec![0u8; 0x30_0000];
+        let text = sample_text();
+        let hash = |name: &str| name_hash(name.as_bytes());
+        
+        let mut superblock = sb::build(MIRROR_OFFSETS[0], 1);
+        superblock[0x50..0x58].copy_from_slice(&MIXED.0.to_le_bytes());
+        superblock[0x58..0x60].copy_from_slice(&SYSTEM.0.to_le_bytes());
+        superblock[0x70..0x78].copy_from_slice(&0x30_0000u64.to_le_bytes());
+        superblock[0x78..0x80].copy_from_slice(&0x10_0000u64.to_le_bytes());
+        let mut array = Key::new(objectid::FIRST_CHUNK_TREE, key_type::CHUNK_ITEM, SYSTEM.0).to_bytes().to_vec();
+        let system = chunk_item(0x4_0000, block_group::SYSTEM, &[(1, SYSTEM.1)]);
+        array.extend_from_slice(&system);
+        sb::set_sys_chunk_array(&mut superblock, &array);
+        image[0x1_0000..0x1_1000].copy_from_slice(&superblock);
+        
+        // チャンクツリー: SYSTEMチャンクと、メタデータ/データ兼用チャンク
+        let chunk_tree = leaf(SYSTEM.0, objectid::CHUNK_TREE, &UUID, NODESIZE, &[
+            (Key::new(objectid::FIRST_CHUNK_TREE, key_type::CHUNK_ITEM, SYSTEM.0), system),
+            (Key::new(objectid::FIRST_CHUNK_TREE, key_type::CHUNK_ITEM, MIXED.0),
+             chunk_item(0x20_0000, block_group::METADATA | block_group::DATA, &[(1, MIXED.1)])),
+        ]);
+        image[SYSTEM.1 as usize..SYSTEM.1 as usize + NODESIZE].copy_from_slice(&chunk_tree);
+        
+        let (fs_root, fs_left, fs_right, csum_root, snap_root) =
+            (MIXED.0 + 0x4000, MIXED.0 + 0x8000, MIXED.0 + 0xC000, MIXED.0 + 0x1_0000, MIXED.0 + 0x1_4000);
+        put(&mut image, MIXED.0, &leaf(MIXED.0, objectid::ROOT_TREE, &UUID, NODESIZE, &[
+            (Key::new(objectid::FS_TREE, key_type::ROOT_ITEM, 0), root_item(fs_root, 1)),
+            (Key::new(objectid::ROOT_TREE_DIR, key_type::DIR_ITEM, hash("default")),
+             dir_item(Key::new(256, key_type::ROOT_ITEM, u64::MAX), 2, "default")),
+            (Key::new(objectid::CSUM_TREE, key_type::ROOT_ITEM, 0), root_item(csum_root, 0)),
+            (Key::new(256, key_type::ROOT_ITEM, 12), root_item(snap_root, 0)),
+        ]));
+        
+        // 最上位のファイルツリー（高さ1、リーフ2つ）
+        let regular = 0o100644;
+        let big_size = 12288 + text.len() as u64;
+        let mut left = [
+            (Key::new(256, key_type::INODE_ITEM, 0), inode(0o040755, 0, 0)),
+            (Key::new(256, key_type::DIR_ITEM, hash("hello.txt")), dir_item(Key::new(257, key_type::INODE_ITEM, 0), 1, "hello.txt")),
+            (Key::new(256, key_type::DIR_ITEM, hash("big.bin")), dir_item(Key::new(258, key_type::INODE_ITEM, 0), 1, "big.bin")),
+            (Key::new(256, key_type::DIR_ITEM, hash("snap")), dir_item(Key::new(256, key_type::ROOT_ITEM, u64::MAX), 2, "snap")),
+            (Key::new(256, key_type::DIR_ITEM, hash("link")), dir_item(Key::new(259, key_type::INODE_ITEM, 0), 7, "link")),
+            (Key::new(256, key_type::DIR_INDEX, 2), dir_item(Key::new(257, key_type::INODE_ITEM, 0), 1, "hello.txt")),
+            (Key::new(256, key_type::DIR_INDEX, 3), dir_item(Key::new(258, key_type::INODE_ITEM, 0), 1, "big.bin")),
+            (Key::new(256, key_type::DIR_INDEX, 4), dir_item(Key::new(256, key_type::ROOT_ITEM, u64::MAX), 2, "snap")),
+            (Key::new(256, key_type::DIR_INDEX, 5), dir_item(Key::new(259, key_type::INODE_ITEM, 0), 7, "link")),
+            (Key::new(257, key_type::INODE_ITEM, 0), inode(regular, 11, 11)),
+            (Key::new(257, key_type::EXTENT_DATA, 0), inline_extent(b"hello world")),
+        ];
+        // DIR_ITEMは名前のハッシュ順に並べる
+        left.sort_by_key(|(key, _)| *key);
+        // big.bin: [0, 8K) 非圧縮（エクステント内の4KiB目から）、[8K, 12K) 穴、[12K, ..) zstd
+        let right = [
+            (Key::new(258, key_type::INODE_ITEM, 0), inode(regular, big_size, 16384)),
+            (Key::new(258, key_type::EXTENT_DATA, 0), regular_extent(kind::NONE, (PLAIN_EXTENT, 12288), 4096, 8192, 12288)),
+            (Key::new(258, key_type::EXTENT_DATA, 12288), regular_extent(kind::ZSTD, (ZSTD_EXTENT, 4096), 0, 12288, 12288)),
+            (Key::new(259, key_type::INODE_ITEM, 0), inode(0o120777, 9, 9)),
+            (Key::new(259, key_type::EXTENT_DATA, 0), inline_extent(b"hello.txt")),
+        ];
+        put(&mut image, fs_left, &leaf(fs_left, objectid::FS_TREE, &UUID, NODESIZE, &left));
+        put(&mut image, fs_right, &leaf(fs_right, objectid::FS_TREE, &UUID, NODESIZE, &right));
+        put(&mut image, fs_root, &internal(fs_root, objectid::FS_TREE, &UUID, NODESIZE, 1, &[
+            (left[0].0, fs_left),
+            (right[0].0, fs_right),
+        ]));
+        
+        put(&mut image, snap_root, &leaf(snap_root, 256, &UUID, NODESIZE, &[
+            (Key::new(256, key_type::INODE_ITEM, 0), inode(0o040755, 0, 0)),
+            (Key::new(256, key_type::DIR_ITEM, hash("inner.txt")), dir_item(Key::new(257, key_type::INODE_ITEM, 0), 1, "inner.txt")),
+            (Key::new(256, key_type::DIR_INDEX, 2), dir_item(Key::new(257, key_type::INODE_ITEM, 0), 1, "inner.txt")),
+            (Key::new(257, key_type::INODE_ITEM, 0), inode(regular, 5, 5)),
+            (Key::new(257, key_type::EXTENT_DATA, 0), inline_extent(b"inner")),
+        ]));
+        
+        // データとチェックサム
+        let plain = plain_data();
+        let mut compressed = ZSTD_SAMPLE.to_vec();
+        compressed.resize(4096, 0);
+        put(&mut image, PLAIN_EXTENT, &plain);
+        put(&mut image, ZSTD_EXTENT, &compressed);
+        let csums = |data: &[u8]| data.chunks(4096).flat_map(|sector| checksum(sector).to_le_bytes()).collect::<Vec<u8>>();
+        put(&mut image, csum_root, &leaf(csum_root, objectid::CSUM_TREE, &UUID, NODESIZE, &[
+            (Key::new(objectid::EXTENT_CSUM, key_type::EXTENT_CSUM, PLAIN_EXTENT), csums(&plain)),
+            (Key::new(objectid::EXTENT_CSUM, key_type::EXTENT_CSUM, ZSTD_EXTENT), csums(&compressed)),
+        ]));
+        image
+    }
+    
+    #[test]
+    fn reads_subvolumes_extents_and_checksums() {
+        let disk: Arc<dyn BlockDevice> = Arc::new(MemoryDisk::read_only(build_image()));
+        let open = |subvol, subvolid, verify_checksums| {
+            let options = BtrfsOptions { verify_checksums, cache_nodes: true };
+            BtrfsVolume::open("/dev/test", disk.clone(), options, subvol, subvolid)
+        };
+        
+        // 指定が無ければ"default"が指すスナップショットを使う
+        let volume = open(None, None, true).unwrap();
+        assert_eq!(volume.top.tree, 256);
+        let names: Vec<String> = volume.list_directory(volume.top).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["inner.txt"]);
+        assert_eq!(open(Some("/snap"), None, true).unwrap().top.tree, 256);
+        assert!(matches!(open(Some("hello.txt"), None, true), Err(FsError::InvalidData)));
+        assert!(matches!(open(Some("snap"), Some(5), true), Err(FsError::InvalidData)));
+        assert!(matches!(open(None, Some(objectid::CSUM_TREE), true), Err(FsError::InvalidData)));
+        
+        let volume = Arc::new(open(None, Some(objectid::FS_TREE), true).unwrap());
+        let names: Vec<String> = volume.list_directory(volume.top).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["hello.txt", "big.bin", "snap", "link"]);
+        // サブボリュームをまたいで解決する
+        let inner = volume.resolve("snap/inner.txt").unwrap();
+        assert_eq!(inner, InodeId { tree: 256, objectid: 257 });
+        assert_eq!(volume.resolve("snap/../hello.txt").unwrap(), InodeId { tree: 5, objectid: 257 });
+        assert_eq!(volume.read_link(volume.resolve("link").unwrap()).unwrap(), "hello.txt");
+        
+        let fs = BtrfsFilesystem::new_optimized(true);
+        fs.volumes.write().insert("/mnt".to_string(), volume.clone());
+        let mut buffer = [0u8; 16];
+        let hello = fs.open_file("/mnt", "hello.txt", OpenMode::ReadOnly).unwrap();
+        assert_eq!(hello.read(&mut buffer, 0).unwrap(), 11);
+        assert_eq!(&buffer[..11], b"hello world");
+        assert!(matches!(fs.open_file("/mnt", "big.bin", OpenMode::ReadWrite), Err(FsError::ReadOnly)));
+        
+        // 非圧縮（オフセット付き）、穴、zstd圧縮の各範囲
+        let text = sample_text();
+        let big = fs.open_file("/mnt", "big.bin", OpenMode::ReadOnly).unwrap();
+        let mut content = vec![0xFFu8; big.size().unwrap() as usize + 100];
+        assert_eq!(big.read(&mut content, 0).unwrap(), 12288 + text.len());
+        assert_eq!(&content[..8192], &plain_data()[4096..]);
+        assert!(content[8192..12288].iter().all(|&b| b == 0));
+        assert_eq!(&content[12288..12288 + text.len()], &text[..]);
+        let mut window = [0u8; 300];
+        big.read(&mut window, 12288 - 100).unwrap();
+        assert_eq!(&window[100..], &text[..200]);
+        assert_eq!(fs.metadata("/mnt", "big.bin").unwrap().blocks, 4);
+        assert_eq!(fs.stats("/mnt").unwrap().free_blocks, 0x200);
+        // 別のマウントポイントには別のボリュームを使う
+        fs.volumes.write().insert("/other".to_string(), Arc::new(open(Some("/snap"), None, true).unwrap()));
+        assert!(fs.metadata("/other", "inner.txt").is_ok());
+        assert!(matches!(fs.metadata("/other", "hello.txt"), Err(FsError::NotFound)));
+        assert!(matches!(fs.metadata("/mnt", "inner.txt"), Err(FsError::NotFound)));
+        assert!(matches!(fs.metadata("/missing", "hello.txt"), Err(FsError::NotFound)));
+        
+        // データの破損はチェックサムで検出し、検証を無効にすれば読める
+        let physical = (PLAIN_EXTENT - MIXED.0 + MIXED.1) as usize + 4096 + 10;
+        drop(fs);
+        drop(volume);
+        let image = {
+            let mut image = build_image();
+            image[physical] ^= 0xFF;
+            image
+        };
+        let disk: Arc<dyn BlockDevice> = Arc::new(MemoryDisk::read_only(image));
+        let open = |verify_checksums| {
+            let options = BtrfsOptions { verify_checksums, cache_nodes: false };
+            BtrfsVolume::open("/dev/test", disk.clone(), options, None, Some(objectid::FS_TREE)).unwrap()
+        };
+        let volume = open(true);
+        let id = volume.resolve("big.bin").unwrap();
+        let inode = volume.read_inode(id).unwrap();
+        assert!(matches!(volume.read_file(id, &inode, &mut buffer, 0), Err(FsError::ChecksumError)));
+        // 破損していないセクタは読める
+        assert_eq!(volume.read_file(id, &inode, &mut buffer, 4096).unwrap(), 16);
+        let volume = open(false);
+        volume.read_file(id, &inode, &mut buffer, 0).unwrap();
+        assert_eq!(buffer[10], plain_data()[4096 + 10] ^ 0xFF);
+    }
+}
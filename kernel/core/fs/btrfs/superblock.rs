@@ -0,0 +1,315 @@
+// Btrfs スーパーブロック
+//
+// 64KiB・64MiB・256GiBの3か所にあるスーパーブロックのコピーの検証と、
+// 世代が最も新しいコピーの選択
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::crc32c::checksum;
+use super::{le_u16, le_u32, le_u64};
+
+/// スーパーブロックのサイズ
+pub const SUPERBLOCK_SIZE: usize = 4096;
+/// スーパーブロックのコピーの位置（先頭がプライマリ）
+pub const MIRROR_OFFSETS: [u64; 3] = [64 * 1024, 64 * 1024 * 1024, 256 * 1024 * 1024 * 1024];
+/// マジック "_BHRfS_M"
+const MAGIC: u64 = 0x4D5F_5366_5248_425F;
+/// チェックサムの対象外となる先頭のcsum領域
+pub const CSUM_SIZE: usize = 32;
+/// sys_chunk_arrayのオフセット
+const SYS_CHUNK_ARRAY_OFFSET: usize = 0x32B;
+/// sys_chunk_arrayの最大サイズ
+const SYS_CHUNK_ARRAY_MAX: usize = 2048;
+/// ラベルのオフセット
+const LABEL_OFFSET: usize = 0x12B;
+/// ラベルの最大長
+const LABEL_SIZE: usize = 256;
+/// dev_item内のdevidのオフセット
+const DEV_ITEM_OFFSET: usize = 0xC9;
+/// metadata_uuidのオフセット
+const METADATA_UUID_OFFSET: usize = 0x23B;
+
+/// チェックサムの種類
+pub mod csum_type {
+    /// CRC32C
+    pub const CRC32C: u16 = 0;
+    /// xxHash64
+    pub const XXHASH: u16 = 1;
+    /// SHA-256
+    pub const SHA256: u16 = 2;
+    /// BLAKE2b
+    pub const BLAKE2: u16 = 3;
+}
+
+/// incompat_flags
+pub mod incompat {
+    /// 旧形式のcompressionやbackrefの混在
+    pub const MIXED_BACKREF: u64 = 1 << 0;
+    /// デフォルトサブボリュームの変更
+    pub const DEFAULT_SUBVOL: u64 = 1 << 1;
+    /// データとメタデータの混在ブロックグループ
+    pub const MIXED_GROUPS: u64 = 1 << 2;
+    /// LZO圧縮
+    pub const COMPRESS_LZO: u64 = 1 << 3;
+    /// zstd圧縮
+    pub const COMPRESS_ZSTD: u64 = 1 << 4;
+    /// 4KiBより大きいノード
+    pub const BIG_METADATA: u64 = 1 << 5;
+    /// 拡張iノード参照
+    pub const EXTENDED_IREF: u64 = 1 << 6;
+    /// RAID5/6
+    pub const RAID56: u64 = 1 << 7;
+    /// スキニーなメタデータのエクステント参照
+    pub const SKINNY_METADATA: u64 = 1 << 8;
+    /// 穴をエクステントで表さない
+    pub const NO_HOLES: u64 = 1 << 9;
+    /// fsidと別のメタデータUUID
+    pub const METADATA_UUID: u64 = 1 << 10;
+    /// 3/4重のRAID1
+    pub const RAID1C34: u64 = 1 << 11;
+    /// ゾーンデバイス
+    pub const ZONED: u64 = 1 << 12;
+    /// エクステントツリーv2
+    pub const EXTENT_TREE_V2: u64 = 1 << 13;
+    /// RAIDストライプツリー
+    pub const RAID_STRIPE_TREE: u64 = 1 << 14;
+    /// 簡易クォータ
+    pub const SIMPLE_QUOTA: u64 = 1 << 16;
+    
+    /// 読み込みに対応しているフラグ
+    pub const SUPPORTED: u64 = MIXED_BACKREF | DEFAULT_SUBVOL | MIXED_GROUPS | COMPRESS_LZO
+        | COMPRESS_ZSTD | BIG_METADATA | EXTENDED_IREF | SKINNY_METADATA | NO_HOLES
+        | METADATA_UUID | RAID1C34 | ZONED | SIMPLE_QUOTA;
+}
+
+/// Btrfsスーパーブロック
+#[derive(Debug, Clone)]
+pub struct BtrfsSuperblock {
+    /// ファイルシステムUUID
+    pub fsid: [u8; 16],
+    /// ツリーブロックのヘッダが持つUUID（METADATA_UUIDがなければfsid）
+    pub metadata_uuid: [u8; 16],
+    /// このコピーの位置
+    pub bytenr: u64,
+    /// 世代
+    pub generation: u64,
+    /// ルートツリーの論理アドレス
+    pub root: u64,
+    /// チャンクツリーの論理アドレス
+    pub chunk_root: u64,
+    /// ログツリーの論理アドレス（0ならなし）
+    pub log_root: u64,
+    /// 全体のバイト数
+    pub total_bytes: u64,
+    /// 使用中のバイト数
+    pub bytes_used: u64,
+    /// ルートツリー内のディレクトリのオブジェクトID
+    pub root_dir_objectid: u64,
+    /// デバイス数
+    pub num_devices: u64,
+    /// セクタサイズ（データのチェックサム単位）
+    pub sectorsize: u32,
+    /// ツリーブロックのサイズ
+    pub nodesize: u32,
+    /// compat_ro_flags
+    pub compat_ro_flags: u64,
+    /// incompat_flags
+    pub incompat_flags: u64,
+    /// チェックサムの種類
+    pub csum_type: u16,
+    /// ルートツリーの高さ
+    pub root_level: u8,
+    /// チャンクツリーの高さ
+    pub chunk_root_level: u8,
+    /// このデバイスのID
+    pub devid: u64,
+    /// ボリュームラベル
+    pub label: String,
+    /// SYSTEMチャンクの配置（ディスクキーとチャンクアイテムの並び）
+    pub sys_chunk_array: Vec<u8>,
+}
+
+impl BtrfsSuperblock {
+    /// `bytenr`の位置から読み込んだスーパーブロックを解析
+    pub fn parse(data: &[u8], bytenr: u64) -> FsResult<Self> {
+        if data.len() < SUPERBLOCK_SIZE {
+            return Err(FsError::InvalidData);
+        }
+        if le_u64(data, 0x40) != MAGIC {
+            return Err(FsError::BadMagic);
+        }
+        
+        let csum_type = le_u16(data, 0xC4);
+        if csum_type != csum_type::CRC32C {
+            log::warn!("Btrfs: チェックサム種別{}は未対応です（CRC32Cのみ対応）", csum_type);
+            return Err(FsError::UnsupportedFeature);
+        }
+        if le_u32(data, 0) != checksum(&data[CSUM_SIZE..SUPERBLOCK_SIZE]) {
+            return Err(FsError::ChecksumError);
+        }
+        if le_u64(data, 0x30) != bytenr {
+            log::warn!("Btrfs: スーパーブロックの位置が一致しません（{:#x}）", bytenr);
+            return Err(FsError::BadSuperblock);
+        }
+        
+        let mut fsid = [0u8; 16];
+        fsid.copy_from_slice(&data[0x20..0x30]);
+        let incompat_flags = le_u64(data, 0xBC);
+        let mut metadata_uuid = fsid;
+        if incompat_flags & incompat::METADATA_UUID != 0 {
+            metadata_uuid.copy_from_slice(&data[METADATA_UUID_OFFSET..METADATA_UUID_OFFSET + 16]);
+        }
+        
+        let label = &data[LABEL_OFFSET..LABEL_OFFSET + LABEL_SIZE];
+        let label_len = label.iter().position(|&b| b == 0).unwrap_or(LABEL_SIZE);
+        let sys_chunk_array_size = le_u32(data, 0xA0) as usize;
+        if sys_chunk_array_size > SYS_CHUNK_ARRAY_MAX {
+            return Err(FsError::BadSuperblock);
+        }
+        
+        let superblock = Self {
+            fsid,
+            metadata_uuid,
+            bytenr,
+            generation: le_u64(data, 0x48),
+            root: le_u64(data, 0x50),
+            chunk_root: le_u64(data, 0x58),
+            log_root: le_u64(data, 0x60),
+            total_bytes: le_u64(data, 0x70),
+            bytes_used: le_u64(data, 0x78),
+            root_dir_objectid: le_u64(data, 0x80),
+            num_devices: le_u64(data, 0x88),
+            sectorsize: le_u32(data, 0x90),
+            nodesize: le_u32(data, 0x94),
+            compat_ro_flags: le_u64(data, 0xB4),
+            incompat_flags,
+            csum_type,
+            root_level: data[0xC6],
+            chunk_root_level: data[0xC7],
+            devid: le_u64(data, DEV_ITEM_OFFSET),
+            label: String::from_utf8_lossy(&label[..label_len]).into_owned(),
+            sys_chunk_array: data[SYS_CHUNK_ARRAY_OFFSET..SYS_CHUNK_ARRAY_OFFSET + sys_chunk_array_size].to_vec(),
+        };
+        
+        superblock.validate()?;
+        Ok(superblock)
+    }
+    
+    /// サイズとフラグを確認
+    fn validate(&self) -> FsResult<()> {
+        if !self.sectorsize.is_power_of_two() || !(4096..=65536).contains(&self.sectorsize)
+            || !self.nodesize.is_power_of_two() || self.nodesize < self.sectorsize || self.nodesize > 65536
+        {
+            log::warn!("Btrfs: セクタサイズ{}/ノードサイズ{}は不正です", self.sectorsize, self.nodesize);
+            return Err(FsError::BadSuperblock);
+        }
+        
+        let unsupported = self.incompat_flags & !incompat::SUPPORTED;
+        if unsupported != 0 {
+            log::warn!("Btrfs: 未対応のincompatフラグ {:#x}", unsupported);
+            return Err(FsError::UnsupportedFeature);
+        }
+        Ok(())
+    }
+}
+
+/// 各コピーを読み、世代が最も新しい有効なスーパーブロックを選ぶ
+///
+/// `read`はコピーの位置を受け取り、デバイスの範囲外なら`None`を返す。
+/// 有効なコピーが1つもなければプライマリのエラーを返す。
+pub fn select_mirror(mut read: impl FnMut(u64) -> Option<FsResult<Vec<u8>>>) -> FsResult<BtrfsSuperblock> {
+    let mut newest: Option<BtrfsSuperblock> = None;
+    let mut primary_error = FsError::BadMagic;
+    
+    for (index, &offset) in MIRROR_OFFSETS.iter().enumerate() {
+        let parsed = match read(offset) {
+            Some(data) => data.and_then(|data| BtrfsSuperblock::parse(&data, offset)),
+            None => break,
+        };
+        match parsed {
+            Ok(superblock) => {
+                if newest.as_ref().is_none_or(|current| superblock.generation > current.generation) {
+                    newest = Some(superblock);
+                }
+            },
+            Err(e) if index == 0 => {
+                log::warn!("Btrfs: プライマリのスーパーブロックが無効です ({:?})", e);
+                primary_error = e;
+            },
+            // 小さいデバイスではmkfsがコピーを書かないこともある
+            Err(_) => {},
+        }
+    }
+    
+    let superblock = newest.ok_or(primary_error)?;
+    if superblock.bytenr != MIRROR_OFFSETS[0] {
+        log::info!("Btrfs: {:#x}のスーパーブロックのコピーを使用します", superblock.bytenr);
+    }
+    Ok(superblock)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    
+    /// テスト用のスーパーブロックを作成（sys_chunk_arrayは空）
+    pub fn build(bytenr: u64, generation: u64) -> Vec<u8> {
+        let mut data = vec![0u8; SUPERBLOCK_SIZE];
+        data[0x20..0x30].copy_from_slice(&[0xAB; 16]);
+        data[0x30..0x38].copy_from_slice(&bytenr.to_le_bytes());
+        data[0x40..0x48].copy_from_slice(&MAGIC.to_le_bytes());
+        data[0x48..0x50].copy_from_slice(&generation.to_le_bytes());
+        data[0x80..0x88].copy_from_slice(&6u64.to_le_bytes());
+        data[0x88..0x90].copy_from_slice(&1u64.to_le_bytes());
+        data[0x90..0x94].copy_from_slice(&4096u32.to_le_bytes());
+        data[0x94..0x98].copy_from_slice(&16384u32.to_le_bytes());
+        data[DEV_ITEM_OFFSET..DEV_ITEM_OFFSET + 8].copy_from_slice(&1u64.to_le_bytes());
+        data[LABEL_OFFSET..LABEL_OFFSET + 4].copy_from_slice(b"data");
+        seal(&mut data);
+        data
+    }
+    
+    /// sys_chunk_arrayを設定
+    pub fn set_sys_chunk_array(data: &mut [u8], array: &[u8]) {
+        data[0xA0..0xA4].copy_from_slice(&(array.len() as u32).to_le_bytes());
+        data[SYS_CHUNK_ARRAY_OFFSET..SYS_CHUNK_ARRAY_OFFSET + array.len()].copy_from_slice(array);
+        seal(data);
+    }
+    
+    /// チェックサムを付け直す
+    pub fn seal(data: &mut [u8]) {
+        let csum = checksum(&data[CSUM_SIZE..SUPERBLOCK_SIZE]);
+        data[..4].copy_from_slice(&csum.to_le_bytes());
+    }
+    
+    #[test]
+    fn selects_newest_valid_mirror() {
+        let primary = build(MIRROR_OFFSETS[0], 7);
+        let sb = BtrfsSuperblock::parse(&primary, MIRROR_OFFSETS[0]).unwrap();
+        assert_eq!((sb.generation, sb.nodesize, sb.label.as_str()), (7, 16384, "data"));
+        // 別の位置から読んだコピーは受け付けない
+        assert!(matches!(BtrfsSuperblock::parse(&primary, MIRROR_OFFSETS[1]), Err(FsError::BadSuperblock)));
+        
+        // プライマリが壊れていても、より新しいミラーが選ばれる
+        let mut corrupted = primary.clone();
+        corrupted[0x100] ^= 1;
+        assert!(matches!(BtrfsSuperblock::parse(&corrupted, MIRROR_OFFSETS[0]), Err(FsError::ChecksumError)));
+        let mirror = build(MIRROR_OFFSETS[1], 9);
+        let sb = select_mirror(|offset| match offset {
+            o if o == MIRROR_OFFSETS[0] => Some(Ok(corrupted.clone())),
+            o if o == MIRROR_OFFSETS[1] => Some(Ok(mirror.clone())),
+            _ => None,
+        }).unwrap();
+        assert_eq!((sb.bytenr, sb.generation), (MIRROR_OFFSETS[1], 9));
+        
+        // 小さいデバイスでプライマリしかなく、それが壊れていればエラー
+        assert!(matches!(select_mirror(|offset| (offset == MIRROR_OFFSETS[0]).then(|| Ok(corrupted.clone()))),
+                         Err(FsError::ChecksumError)));
+        
+        let mut raid56 = build(MIRROR_OFFSETS[0], 7);
+        raid56[0xBC..0xC4].copy_from_slice(&incompat::RAID56.to_le_bytes());
+        seal(&mut raid56);
+        assert!(matches!(BtrfsSuperblock::parse(&raid56, MIRROR_OFFSETS[0]), Err(FsError::UnsupportedFeature)));
+    }
+}
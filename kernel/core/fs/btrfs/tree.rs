@@ -0,0 +1,295 @@
+// Btrfs B-tree
+//
+// キーの順序、ツリーブロック（リーフ/内部ノード）の解析と検証
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use super::super::{FsError, FsResult};
+use super::crc32c::checksum;
+use super::superblock::CSUM_SIZE;
+use super::{le_u32, le_u64};
+
+/// ディスクキーのサイズ
+pub const KEY_SIZE: usize = 17;
+/// ツリーブロックのヘッダサイズ
+pub const HEADER_SIZE: usize = 101;
+/// リーフのアイテム記述子のサイズ
+const ITEM_SIZE: usize = 25;
+/// 内部ノードのキーポインタのサイズ
+const KEY_PTR_SIZE: usize = 33;
+/// ツリーの高さの上限
+pub const MAX_LEVEL: u8 = 8;
+
+/// キーの種別
+pub mod key_type {
+    /// iノード
+    pub const INODE_ITEM: u8 = 1;
+    /// 親ディレクトリからの参照
+    pub const INODE_REF: u8 = 12;
+    /// 拡張属性
+    pub const XATTR_ITEM: u8 = 24;
+    /// ディレクトリエントリ（名前のハッシュ順）
+    pub const DIR_ITEM: u8 = 84;
+    /// ディレクトリエントリ（作成順）
+    pub const DIR_INDEX: u8 = 96;
+    /// ファイルのエクステント
+    pub const EXTENT_DATA: u8 = 108;
+    /// データのチェックサム
+    pub const EXTENT_CSUM: u8 = 128;
+    /// ツリーのルート
+    pub const ROOT_ITEM: u8 = 132;
+    /// サブボリュームから親への参照
+    pub const ROOT_BACKREF: u8 = 144;
+    /// 親からサブボリュームへの参照
+    pub const ROOT_REF: u8 = 156;
+    /// チャンク
+    pub const CHUNK_ITEM: u8 = 228;
+}
+
+/// 予約されたオブジェクトID
+pub mod objectid {
+    /// ルートツリー
+    pub const ROOT_TREE: u64 = 1;
+    /// チャンクツリー
+    pub const CHUNK_TREE: u64 = 3;
+    /// 最上位のファイルツリー（サブボリュームID 5）
+    pub const FS_TREE: u64 = 5;
+    /// ルートツリー内のディレクトリ（"default"エントリを持つ）
+    pub const ROOT_TREE_DIR: u64 = 6;
+    /// チェックサムツリー
+    pub const CSUM_TREE: u64 = 7;
+    /// 最初の通常のオブジェクトID（サブボリュームのルートディレクトリ）
+    pub const FIRST_FREE: u64 = 256;
+    /// チャンクツリーのCHUNK_ITEMのオブジェクトID
+    pub const FIRST_CHUNK_TREE: u64 = 256;
+    /// 最後の通常のオブジェクトID
+    pub const LAST_FREE: u64 = -256i64 as u64;
+    /// EXTENT_CSUMアイテムのオブジェクトID
+    pub const EXTENT_CSUM: u64 = -10i64 as u64;
+}
+
+/// ディスクキー（オブジェクトID・種別・オフセットの順で比較する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Key {
+    /// オブジェクトID
+    pub objectid: u64,
+    /// 種別
+    pub item_type: u8,
+    /// オフセット（種別ごとに意味が異なる）
+    pub offset: u64,
+}
+
+impl Key {
+    /// キーを作成
+    pub const fn new(objectid: u64, item_type: u8, offset: u64) -> Self {
+        Self { objectid, item_type, offset }
+    }
+    
+    /// ディスク上の形式から変換
+    pub fn parse(data: &[u8]) -> Self {
+        Self { objectid: le_u64(data, 0), item_type: data[8], offset: le_u64(data, 9) }
+    }
+    
+    /// ディスク上の形式に変換
+    pub fn to_bytes(self) -> [u8; KEY_SIZE] {
+        let mut data = [0u8; KEY_SIZE];
+        data[0..8].copy_from_slice(&self.objectid.to_le_bytes());
+        data[8] = self.item_type;
+        data[9..17].copy_from_slice(&self.offset.to_le_bytes());
+        data
+    }
+}
+
+/// 検証済みのツリーブロック
+#[derive(Debug)]
+pub struct Node {
+    /// ブロック全体
+    data: Vec<u8>,
+    /// 世代
+    pub generation: u64,
+    /// 所有するツリー
+    pub owner: u64,
+    /// アイテム/キーポインタの数
+    pub nritems: usize,
+    /// 高さ（0がリーフ）
+    pub level: u8,
+}
+
+impl Node {
+    /// 論理アドレス`bytenr`から読み込んだツリーブロックを検証して解析
+    pub fn parse(data: Vec<u8>, bytenr: u64, metadata_uuid: &[u8; 16]) -> FsResult<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(FsError::CorruptedFs);
+        }
+        if le_u32(&data, 0) != checksum(&data[CSUM_SIZE..]) {
+            log::warn!("Btrfs: ツリーブロック{:#x}のチェックサムが一致しません", bytenr);
+            return Err(FsError::ChecksumError);
+        }
+        if le_u64(&data, 0x30) != bytenr || data[0x20..0x30] != metadata_uuid[..] {
+            log::warn!("Btrfs: ツリーブロック{:#x}のヘッダが不正です", bytenr);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let node = Self {
+            generation: le_u64(&data, 0x50),
+            owner: le_u64(&data, 0x58),
+            nritems: le_u32(&data, 0x60) as usize,
+            level: data[0x64],
+            data,
+        };
+        
+        let entry_size = if node.is_leaf() { ITEM_SIZE } else { KEY_PTR_SIZE };
+        if node.level >= MAX_LEVEL || HEADER_SIZE + node.nritems * entry_size > node.data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        if node.is_leaf() {
+            for index in 0..node.nritems {
+                let (offset, size) = node.item_range(index);
+                if HEADER_SIZE + offset + size > node.data.len() {
+                    log::warn!("Btrfs: ツリーブロック{:#x}のアイテム{}が範囲外です", bytenr, index);
+                    return Err(FsError::CorruptedFs);
+                }
+            }
+        }
+        Ok(node)
+    }
+    
+    /// リーフかどうか
+    pub fn is_leaf(&self) -> bool {
+        self.level == 0
+    }
+    
+    /// `index`番目のキー
+    pub fn key(&self, index: usize) -> Key {
+        let size = if self.is_leaf() { ITEM_SIZE } else { KEY_PTR_SIZE };
+        Key::parse(&self.data[HEADER_SIZE + index * size..])
+    }
+    
+    /// リーフのアイテムのデータ領域（データ領域先頭からのオフセット, サイズ）
+    fn item_range(&self, index: usize) -> (usize, usize) {
+        let base = HEADER_SIZE + index * ITEM_SIZE + KEY_SIZE;
+        (le_u32(&self.data, base) as usize, le_u32(&self.data, base + 4) as usize)
+    }
+    
+    /// リーフの`index`番目のアイテムのデータ
+    pub fn item_data(&self, index: usize) -> &[u8] {
+        let (offset, size) = self.item_range(index);
+        &self.data[HEADER_SIZE + offset..HEADER_SIZE + offset + size]
+    }
+    
+    /// 内部ノードの`index`番目の子（論理アドレス, 世代）
+    pub fn child(&self, index: usize) -> (u64, u64) {
+        let base = HEADER_SIZE + index * KEY_PTR_SIZE + KEY_SIZE;
+        (le_u64(&self.data, base), le_u64(&self.data, base + 8))
+    }
+    
+    /// 内部ノードで`key`を含みうる最後の子の位置
+    pub fn child_for(&self, key: &Key) -> usize {
+        // 子のキーは部分木の最小キーなので、key以下の最後の子を選ぶ
+        let mut low = 0;
+        let mut high = self.nritems;
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.key(mid).cmp(key) {
+                Ordering::Greater => high = mid,
+                _ => low = mid + 1,
+            }
+        }
+        low.saturating_sub(1)
+    }
+    
+    /// リーフで`key`以上の最初のアイテムの位置
+    pub fn first_item_from(&self, key: &Key) -> usize {
+        let mut low = 0;
+        let mut high = self.nritems;
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.key(mid) < *key {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    
+    /// テスト用のリーフを作成（アイテムはキー順に渡す）
+    pub fn leaf(bytenr: u64, owner: u64, uuid: &[u8; 16], nodesize: usize, items: &[(Key, Vec<u8>)]) -> Vec<u8> {
+        let mut data = header(bytenr, owner, uuid, nodesize, items.len(), 0);
+        let mut end = nodesize - HEADER_SIZE;
+        for (index, (key, item)) in items.iter().enumerate() {
+            end -= item.len();
+            let base = HEADER_SIZE + index * ITEM_SIZE;
+            data[base..base + KEY_SIZE].copy_from_slice(&key.to_bytes());
+            data[base + KEY_SIZE..base + KEY_SIZE + 4].copy_from_slice(&(end as u32).to_le_bytes());
+            data[base + KEY_SIZE + 4..base + ITEM_SIZE].copy_from_slice(&(item.len() as u32).to_le_bytes());
+            data[HEADER_SIZE + end..HEADER_SIZE + end + item.len()].copy_from_slice(item);
+        }
+        seal(&mut data);
+        data
+    }
+    
+    /// テスト用の内部ノードを作成（子は(最小キー, 論理アドレス)）
+    pub fn internal(bytenr: u64, owner: u64, uuid: &[u8; 16], nodesize: usize, level: u8, children: &[(Key, u64)]) -> Vec<u8> {
+        let mut data = header(bytenr, owner, uuid, nodesize, children.len(), level);
+        for (index, (key, child)) in children.iter().enumerate() {
+            let base = HEADER_SIZE + index * KEY_PTR_SIZE;
+            data[base..base + KEY_SIZE].copy_from_slice(&key.to_bytes());
+            data[base + KEY_SIZE..base + KEY_SIZE + 8].copy_from_slice(&child.to_le_bytes());
+            data[base + KEY_SIZE + 8..base + KEY_PTR_SIZE].copy_from_slice(&1u64.to_le_bytes());
+        }
+        seal(&mut data);
+        data
+    }
+    
+    fn header(bytenr: u64, owner: u64, uuid: &[u8; 16], nodesize: usize, nritems: usize, level: u8) -> Vec<u8> {
+        let mut data = vec![0u8; nodesize];
+        data[0x20..0x30].copy_from_slice(uuid);
+        data[0x30..0x38].copy_from_slice(&bytenr.to_le_bytes());
+        data[0x50..0x58].copy_from_slice(&1u64.to_le_bytes());
+        data[0x58..0x60].copy_from_slice(&owner.to_le_bytes());
+        data[0x60..0x64].copy_from_slice(&(nritems as u32).to_le_bytes());
+        data[0x64] = level;
+        data
+    }
+    
+    /// チェックサムを付け直す
+    pub fn seal(data: &mut [u8]) {
+        let csum = checksum(&data[CSUM_SIZE..]);
+        data[..4].copy_from_slice(&csum.to_le_bytes());
+    }
+    
+    #[test]
+    fn parses_and_verifies_tree_blocks() {
+        let uuid = [0xAB; 16];
+        let items = [
+            (Key::new(256, key_type::INODE_ITEM, 0), vec![1u8; 160]),
+            (Key::new(256, key_type::DIR_ITEM, 42), vec![2u8; 40]),
+            (Key::new(257, key_type::INODE_ITEM, 0), vec![3u8; 160]),
+        ];
+        let data = leaf(0x10_4000, objectid::FS_TREE, &uuid, 4096, &items);
+        let node = Node::parse(data.clone(), 0x10_4000, &uuid).unwrap();
+        assert_eq!((node.nritems, node.owner, node.is_leaf()), (3, objectid::FS_TREE, true));
+        assert_eq!(node.item_data(1), &[2u8; 40][..]);
+        assert_eq!(node.first_item_from(&Key::new(256, key_type::DIR_ITEM, 0)), 1);
+        assert_eq!(node.first_item_from(&Key::new(256, key_type::DIR_ITEM, 43)), 2);
+        assert_eq!(node.first_item_from(&Key::new(300, 0, 0)), 3);
+        
+        // 別の位置から読んだブロックや壊れたブロックは拒否する
+        assert!(matches!(Node::parse(data.clone(), 0x10_8000, &uuid), Err(FsError::CorruptedFs)));
+        let mut corrupted = data;
+        corrupted[HEADER_SIZE + 3] ^= 0x40;
+        assert!(matches!(Node::parse(corrupted, 0x10_4000, &uuid), Err(FsError::ChecksumError)));
+        
+        let children = [(Key::new(0, 0, 0), 0x10_4000), (Key::new(257, key_type::INODE_ITEM, 0), 0x10_8000)];
+        let node = Node::parse(internal(0x10_C000, 5, &uuid, 4096, 1, &children), 0x10_C000, &uuid).unwrap();
+        assert_eq!(node.child_for(&Key::new(256, key_type::EXTENT_DATA, 0)), 0);
+        assert_eq!(node.child_for(&Key::new(257, key_type::INODE_ITEM, 0)), 1);
+        assert_eq!(node.child(1), (0x10_8000, 1));
+    }
+}
@@ -0,0 +1,763 @@
+// Btrfs zstd
+//
+// zstdフレーム（RFC 8878）の伸長。Btrfsは辞書を使わないため、辞書IDを持つフレームは
+// 扱わない。内容のチェックサムはエクステントのチェックサムで代えるので検証しない
+
+use alloc::vec;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::compression::Output;
+use super::{le_u16, le_u32};
+
+/// フレームのマジック
+const FRAME_MAGIC: u32 = 0xFD2F_B528;
+/// スキップ可能フレームのマジック（下位4ビットは任意）
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+/// ブロックの最大サイズ
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+/// ハフマン符号の最大長
+const MAX_HUFFMAN_BITS: u32 = 11;
+
+/// リテラル長符号の基準値
+const LL_BASE: [u32; 36] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    16, 18, 20, 22, 24, 28, 32, 40, 48, 64, 128, 256, 512, 1024, 2048, 4096,
+    8192, 16384, 32768, 65536,
+];
+/// リテラル長符号の追加ビット数
+const LL_BITS: [u8; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 1, 1, 1, 2, 2, 3, 3, 4, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15, 16,
+];
+/// 一致長符号の基準値
+const ML_BASE: [u32; 53] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+    19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34,
+    35, 37, 39, 41, 43, 47, 51, 59, 67, 83, 99, 131, 259, 515, 1027, 2051,
+    4099, 8195, 16387, 32771, 65539,
+];
+/// 一致長符号の追加ビット数
+const ML_BITS: [u8; 53] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 1, 1, 1, 2, 2, 3, 3, 4, 4, 5, 7, 8, 9, 10, 11,
+    12, 13, 14, 15, 16,
+];
+/// 既定のリテラル長の分布（精度6）
+const LL_DEFAULT: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+/// 既定の一致長の分布（精度6）
+const ML_DEFAULT: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1,
+    -1, -1, -1, -1, -1,
+];
+/// 既定のオフセット符号の分布（精度5）
+const OF_DEFAULT: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+/// 最上位の立っているビットの位置
+fn highest_bit(value: u32) -> u32 {
+    31 - value.leading_zeros()
+}
+
+/// 先頭から読むビット列（FSEテーブルの記述用）
+struct ForwardBits<'a> {
+    /// 入力
+    data: &'a [u8],
+    /// 次に読むビットの位置
+    position: usize,
+}
+
+impl<'a> ForwardBits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+    
+    /// 読み進めずに`count`ビット（最大25）を見る（入力の末尾より先は0）
+    fn peek(&self, count: u32) -> u32 {
+        let byte = self.position / 8;
+        let mut raw = 0u64;
+        for i in 0..5 {
+            raw |= (self.data.get(byte + i).copied().unwrap_or(0) as u64) << (8 * i);
+        }
+        ((raw >> (self.position % 8)) & ((1u64 << count) - 1)) as u32
+    }
+    
+    /// `count`ビット読み進める
+    fn skip(&mut self, count: u32) -> FsResult<()> {
+        self.position += count as usize;
+        if self.position > self.data.len() * 8 {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(())
+    }
+    
+    /// `count`ビット読む
+    fn bits(&mut self, count: u32) -> FsResult<u32> {
+        let value = self.peek(count);
+        self.skip(count)?;
+        Ok(value)
+    }
+}
+
+/// 末尾から先頭へ読むビット列（ハフマン/FSEで符号化されたストリーム）
+struct BackwardBits<'a> {
+    /// 入力
+    data: &'a [u8],
+    /// 未読のビット数（先頭を越えて読むと負になる）
+    remaining: isize,
+}
+
+impl<'a> BackwardBits<'a> {
+    /// 最終バイトの最上位の1（終端の印）より下からストリームを始める
+    fn new(data: &'a [u8]) -> FsResult<Self> {
+        let last = *data.last().ok_or(FsError::CorruptedFs)?;
+        if last == 0 {
+            return Err(FsError::CorruptedFs);
+        }
+        let padding = 8 - highest_bit(last as u32) as isize;
+        Ok(Self { data, remaining: data.len() as isize * 8 - padding })
+    }
+    
+    /// ビット位置`start`から`count`ビット（最大56）を取り出す（先頭より前は0）
+    fn extract(&self, start: isize, count: u32) -> u64 {
+        let (start, count, shift) = if start < 0 {
+            (0, count as isize + start, (-start) as u32)
+        } else {
+            (start, count as isize, 0)
+        };
+        if count <= 0 {
+            return 0;
+        }
+        let byte = (start / 8) as usize;
+        let mut raw = 0u64;
+        for i in 0..8 {
+            raw |= (self.data.get(byte + i).copied().unwrap_or(0) as u64) << (8 * i);
+        }
+        ((raw >> (start % 8)) & ((1u64 << count) - 1)) << shift
+    }
+    
+    /// `count`ビット読む
+    fn bits(&mut self, count: u32) -> u64 {
+        self.remaining -= count as isize;
+        self.extract(self.remaining, count)
+    }
+    
+    /// 読み進めずに`count`ビットを見る
+    fn peek(&self, count: u32) -> u64 {
+        self.extract(self.remaining - count as isize, count)
+    }
+}
+
+/// FSE復号表の1項目
+#[derive(Debug, Clone, Copy, Default)]
+struct FseEntry {
+    /// シンボル
+    symbol: u8,
+    /// 次の状態のために読むビット数
+    bits: u8,
+    /// 次の状態の基準値
+    baseline: u16,
+}
+
+/// FSE復号表
+#[derive(Debug, Clone)]
+struct FseTable {
+    /// 精度（表のサイズの対数）
+    log: u32,
+    /// 状態ごとの項目
+    entries: Vec<FseEntry>,
+}
+
+impl FseTable {
+    /// 正規化された出現数から作成（-1は「1未満」）
+    fn from_counts(counts: &[i16], log: u32) -> FsResult<Self> {
+        let size = 1usize << log;
+        if counts.iter().map(|&count| count.unsigned_abs() as usize).sum::<usize>() != size {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut entries = vec![FseEntry::default(); size];
+        let mut next = vec![0u32; counts.len()];
+        let mut high = size - 1;
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count == -1 {
+                entries[high].symbol = symbol as u8;
+                high = high.wrapping_sub(1);
+                next[symbol] = 1;
+            } else {
+                next[symbol] = count as u32;
+            }
+        }
+        
+        // 出現数に応じて表全体へ散らす
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in counts.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                entries[position].symbol = symbol as u8;
+                position = (position + step) & (size - 1);
+                while position > high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        if position != 0 {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        for entry in entries.iter_mut() {
+            let state = next[entry.symbol as usize];
+            next[entry.symbol as usize] += 1;
+            let bits = log - highest_bit(state);
+            entry.bits = bits as u8;
+            entry.baseline = ((state << bits) as usize - size) as u16;
+        }
+        Ok(Self { log, entries })
+    }
+    
+    /// 常に同じシンボルを返す表
+    fn rle(symbol: u8) -> Self {
+        Self { log: 0, entries: vec![FseEntry { symbol, bits: 0, baseline: 0 }] }
+    }
+    
+    /// 表の記述を読み、使ったバイト数も返す
+    fn read(src: &[u8], max_log: u32, max_symbol: usize) -> FsResult<(Self, usize)> {
+        let mut bits = ForwardBits::new(src);
+        let log = bits.bits(4)? + 5;
+        if log > max_log {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut counts: Vec<i16> = Vec::new();
+        let mut remaining = (1i32 << log) + 1;
+        let mut threshold = 1i32 << log;
+        let mut width = log + 1;
+        while remaining > 1 {
+            if counts.len() > max_symbol {
+                return Err(FsError::CorruptedFs);
+            }
+            let max = 2 * threshold - 1 - remaining;
+            let low = bits.peek(width - 1) as i32;
+            let value = if low < max {
+                bits.skip(width - 1)?;
+                low
+            } else {
+                let full = bits.peek(width) as i32;
+                bits.skip(width)?;
+                if full >= threshold { full - max } else { full }
+            };
+            
+            let count = value - 1;
+            remaining -= count.abs();
+            counts.push(count as i16);
+            if count == 0 {
+                // 0の後には、続く0の数が2ビットずつ（3なら継続）で入る
+                loop {
+                    let repeat = bits.bits(2)?;
+                    counts.extend(core::iter::repeat_n(0, repeat as usize));
+                    if repeat != 3 {
+                        break;
+                    }
+                }
+            }
+            while remaining < threshold {
+                width -= 1;
+                threshold >>= 1;
+            }
+        }
+        if remaining != 1 || counts.len() > max_symbol + 1 {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        Ok((Self::from_counts(&counts, log)?, bits.position.div_ceil(8)))
+    }
+    
+    /// 初期状態を読む
+    fn init_state(&self, bits: &mut BackwardBits) -> usize {
+        bits.bits(self.log) as usize
+    }
+    
+    /// 状態のシンボル
+    fn symbol(&self, state: usize) -> u8 {
+        self.entries[state].symbol
+    }
+    
+    /// 次の状態へ進める
+    fn update(&self, state: usize, bits: &mut BackwardBits) -> usize {
+        let entry = self.entries[state];
+        entry.baseline as usize + bits.bits(entry.bits as u32) as usize
+    }
+}
+
+/// リテラルのハフマン復号表
+#[derive(Debug, Clone)]
+struct HuffmanTable {
+    /// 最長の符号長
+    max_bits: u32,
+    /// 先頭`max_bits`ビットごとの(シンボル, 符号長)
+    entries: Vec<(u8, u8)>,
+}
+
+impl HuffmanTable {
+    /// 表の記述を読み、使ったバイト数も返す
+    fn read(src: &[u8]) -> FsResult<(Self, usize)> {
+        let header = *src.first().ok_or(FsError::CorruptedFs)? as usize;
+        let mut weights = Vec::new();
+        let consumed = if header >= 128 {
+            // 重みを4ビットずつ直接並べた形式
+            let count = header - 127;
+            let bytes = src.get(1..1 + count.div_ceil(2)).ok_or(FsError::CorruptedFs)?;
+            for i in 0..count {
+                weights.push(if i % 2 == 0 { bytes[i / 2] >> 4 } else { bytes[i / 2] & 0x0F });
+            }
+            1 + bytes.len()
+        } else {
+            // 重みをFSEで圧縮した形式（2つの状態を交互に使う）
+            let data = src.get(1..1 + header).ok_or(FsError::CorruptedFs)?;
+            let (table, used) = FseTable::read(data, 6, 255)?;
+            let mut bits = BackwardBits::new(data.get(used..).ok_or(FsError::CorruptedFs)?)?;
+            let mut first = table.init_state(&mut bits);
+            let mut second = table.init_state(&mut bits);
+            loop {
+                weights.push(table.symbol(first));
+                first = table.update(first, &mut bits);
+                if bits.remaining < 0 {
+                    weights.push(table.symbol(second));
+                    break;
+                }
+                weights.push(table.symbol(second));
+                second = table.update(second, &mut bits);
+                if bits.remaining < 0 {
+                    weights.push(table.symbol(first));
+                    break;
+                }
+                if weights.len() > 255 {
+                    return Err(FsError::CorruptedFs);
+                }
+            }
+            1 + header
+        };
+        
+        Ok((Self::from_weights(weights)?, consumed))
+    }
+    
+    /// 重みの並びから作成（最後のシンボルの重みは合計が2の累乗になるように補う）
+    fn from_weights(mut weights: Vec<u8>) -> FsResult<Self> {
+        if weights.len() > 255 || weights.iter().any(|&weight| weight as u32 > MAX_HUFFMAN_BITS) {
+            return Err(FsError::CorruptedFs);
+        }
+        let total: u32 = weights.iter().filter(|&&weight| weight > 0).map(|&weight| 1 << (weight - 1)).sum();
+        if total == 0 {
+            return Err(FsError::CorruptedFs);
+        }
+        let max_bits = highest_bit(total) + 1;
+        let rest = (1 << max_bits) - total;
+        if max_bits > MAX_HUFFMAN_BITS || !rest.is_power_of_two() {
+            return Err(FsError::CorruptedFs);
+        }
+        weights.push(highest_bit(rest) as u8 + 1);
+        
+        // 重みの小さい（符号の長い）シンボルから表の先頭に並べる
+        let mut rank_start = [0usize; MAX_HUFFMAN_BITS as usize + 2];
+        let mut position = 0;
+        for (weight, start) in rank_start.iter_mut().enumerate().take(max_bits as usize + 1).skip(1) {
+            *start = position;
+            position += weights.iter().filter(|&&w| w as usize == weight).count() << (weight - 1);
+        }
+        let mut entries = vec![(0u8, 0u8); 1 << max_bits];
+        for (symbol, &weight) in weights.iter().enumerate() {
+            if weight == 0 {
+                continue;
+            }
+            let length = 1usize << (weight - 1);
+            let start = rank_start[weight as usize];
+            entries[start..start + length].fill((symbol as u8, (max_bits + 1 - weight as u32) as u8));
+            rank_start[weight as usize] += length;
+        }
+        Ok(Self { max_bits, entries })
+    }
+    
+    /// 1本のストリームから`count`個のシンボルを復号
+    fn decode_stream(&self, src: &[u8], count: usize, literals: &mut Vec<u8>) -> FsResult<()> {
+        let mut bits = BackwardBits::new(src)?;
+        let mask = (1usize << self.max_bits) - 1;
+        let mut state = bits.bits(self.max_bits) as usize;
+        for _ in 0..count {
+            let (symbol, length) = self.entries[state];
+            literals.push(symbol);
+            state = ((state << length) | bits.bits(length as u32) as usize) & mask;
+        }
+        // 最後の状態まで使い切ると、ちょうど先頭からmax_bitsだけ手前に来る
+        if bits.remaining != -(self.max_bits as isize) {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(())
+    }
+}
+
+/// フレーム内でブロックをまたいで引き継ぐ状態
+struct FrameState {
+    /// 直前のハフマン表
+    huffman: Option<HuffmanTable>,
+    /// 直前のリテラル長の表
+    literal_lengths: Option<FseTable>,
+    /// 直前のオフセットの表
+    offsets: Option<FseTable>,
+    /// 直前の一致長の表
+    match_lengths: Option<FseTable>,
+    /// 繰り返しオフセット
+    repeat: [usize; 3],
+}
+
+/// zstdフレームの並びを伸長（フレームの後ろのセクタ埋めは無視する）
+pub fn decompress(src: &[u8], out: &mut Output) -> FsResult<()> {
+    let mut position = 0;
+    let mut frames = 0;
+    while position + 4 <= src.len() {
+        let magic = le_u32(src, position);
+        if magic & 0xFFFF_FFF0 == SKIPPABLE_MAGIC {
+            if position + 8 > src.len() {
+                return Err(FsError::CorruptedFs);
+            }
+            position += 8 + le_u32(src, position + 4) as usize;
+            continue;
+        }
+        if magic != FRAME_MAGIC {
+            break;
+        }
+        position = decode_frame(src, position + 4, out)?;
+        frames += 1;
+    }
+    if frames == 0 {
+        return Err(FsError::CorruptedFs);
+    }
+    Ok(())
+}
+
+/// 1つのフレームを伸長し、フレームの後ろの位置を返す
+fn decode_frame(src: &[u8], mut position: usize, out: &mut Output) -> FsResult<usize> {
+    let descriptor = *src.get(position).ok_or(FsError::CorruptedFs)?;
+    position += 1;
+    if descriptor & 0x08 != 0 {
+        return Err(FsError::CorruptedFs);
+    }
+    let single_segment = descriptor & 0x20 != 0;
+    if !single_segment {
+        // ウィンドウ記述子（出力全体を保持するので使わない）
+        position += 1;
+    }
+    let dict_size = [0, 1, 2, 4][(descriptor & 3) as usize];
+    if src.get(position..position + dict_size).ok_or(FsError::CorruptedFs)?.iter().any(|&b| b != 0) {
+        log::warn!("Btrfs: 辞書を使うzstdフレームは未対応です");
+        return Err(FsError::UnsupportedFeature);
+    }
+    position += dict_size;
+    position += match descriptor >> 6 {
+        0 => single_segment as usize,
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    
+    let frame_start = out.written();
+    let mut state = FrameState {
+        huffman: None,
+        literal_lengths: None,
+        offsets: None,
+        match_lengths: None,
+        repeat: [1, 4, 8],
+    };
+    loop {
+        let header = src.get(position..position + 3).ok_or(FsError::CorruptedFs)?;
+        let header = header[0] as usize | (header[1] as usize) << 8 | (header[2] as usize) << 16;
+        position += 3;
+        let size = header >> 3;
+        match (header >> 1) & 3 {
+            0 => {
+                out.extend(src.get(position..position + size).ok_or(FsError::CorruptedFs)?)?;
+                position += size;
+            },
+            1 => {
+                out.fill(*src.get(position).ok_or(FsError::CorruptedFs)?, size)?;
+                position += 1;
+            },
+            2 => {
+                if size > MAX_BLOCK_SIZE {
+                    return Err(FsError::CorruptedFs);
+                }
+                let block = src.get(position..position + size).ok_or(FsError::CorruptedFs)?;
+                decode_block(block, out, &mut state, frame_start)?;
+                position += size;
+            },
+            _ => return Err(FsError::CorruptedFs),
+        }
+        if header & 1 != 0 {
+            break;
+        }
+    }
+    
+    if descriptor & 0x04 != 0 {
+        position += 4;
+    }
+    Ok(position)
+}
+
+/// 圧縮ブロックを伸長
+fn decode_block(block: &[u8], out: &mut Output, state: &mut FrameState, frame_start: usize) -> FsResult<()> {
+    let (literals, used) = decode_literals(block, state)?;
+    decode_sequences(&block[used..], &literals, out, state, frame_start)
+}
+
+/// リテラル部を復号し、使ったバイト数も返す
+fn decode_literals(block: &[u8], state: &mut FrameState) -> FsResult<(Vec<u8>, usize)> {
+    let byte = |index: usize| block.get(index).map(|&b| b as usize).ok_or(FsError::CorruptedFs);
+    let first = byte(0)?;
+    let size_format = (first >> 2) & 3;
+    
+    match first & 3 {
+        kind @ (0 | 1) => {
+            let (size, header) = match size_format {
+                0 | 2 => (first >> 3, 1),
+                1 => ((first >> 4) + (byte(1)? << 4), 2),
+                _ => ((first >> 4) + (byte(1)? << 4) + (byte(2)? << 12), 3),
+            };
+            if kind == 0 {
+                let literals = block.get(header..header + size).ok_or(FsError::CorruptedFs)?;
+                Ok((literals.to_vec(), header + size))
+            } else {
+                Ok((vec![byte(header)? as u8; size], header + 1))
+            }
+        },
+        kind => {
+            let header_size = [3, 3, 4, 5][size_format];
+            let mut raw = 0u64;
+            for i in 0..header_size {
+                raw |= (byte(i)? as u64) << (8 * i);
+            }
+            let (size, compressed) = match size_format {
+                0 | 1 => ((raw >> 4) & 0x3FF, (raw >> 14) & 0x3FF),
+                2 => ((raw >> 4) & 0x3FFF, (raw >> 18) & 0x3FFF),
+                _ => ((raw >> 4) & 0x3_FFFF, (raw >> 22) & 0x3_FFFF),
+            };
+            let (size, compressed) = (size as usize, compressed as usize);
+            let data = block.get(header_size..header_size + compressed).ok_or(FsError::CorruptedFs)?;
+            
+            // kind 3は直前のブロックのハフマン表を使う
+            let streams = if kind == 2 {
+                let (table, used) = HuffmanTable::read(data)?;
+                state.huffman = Some(table);
+                &data[used..]
+            } else {
+                data
+            };
+            let table = state.huffman.as_ref().ok_or(FsError::CorruptedFs)?;
+            
+            let mut literals = Vec::with_capacity(size);
+            if size_format == 0 {
+                table.decode_stream(streams, size, &mut literals)?;
+            } else {
+                // 6バイトのジャンプテーブルに続く4本のストリーム
+                if streams.len() < 6 {
+                    return Err(FsError::CorruptedFs);
+                }
+                let lengths = [le_u16(streams, 0) as usize, le_u16(streams, 2) as usize, le_u16(streams, 4) as usize];
+                let segment = size.div_ceil(4);
+                if 6 + lengths.iter().sum::<usize>() > streams.len() || 3 * segment > size {
+                    return Err(FsError::CorruptedFs);
+                }
+                let mut start = 6;
+                for (index, &length) in lengths.iter().enumerate() {
+                    table.decode_stream(&streams[start..start + length], segment, &mut literals)?;
+                    start += length;
+                    if index == 2 {
+                        table.decode_stream(&streams[start..], size - 3 * segment, &mut literals)?;
+                    }
+                }
+            }
+            Ok((literals, header_size + compressed))
+        },
+    }
+}
+
+/// シーケンス部の表を選ぶ（0: 既定、1: RLE、2: FSE記述、3: 直前の表）
+fn select_table(mode: u8, src: &[u8], position: &mut usize, previous: &mut Option<FseTable>,
+                default: (&[i16], u32), max_log: u32, max_symbol: usize) -> FsResult<FseTable> {
+    let table = match mode {
+        0 => FseTable::from_counts(default.0, default.1)?,
+        1 => {
+            let symbol = *src.get(*position).ok_or(FsError::CorruptedFs)?;
+            *position += 1;
+            if symbol as usize > max_symbol {
+                return Err(FsError::CorruptedFs);
+            }
+            FseTable::rle(symbol)
+        },
+        2 => {
+            let (table, used) = FseTable::read(src.get(*position..).ok_or(FsError::CorruptedFs)?, max_log, max_symbol)?;
+            *position += used;
+            table
+        },
+        _ => previous.clone().ok_or(FsError::CorruptedFs)?,
+    };
+    *previous = Some(table.clone());
+    Ok(table)
+}
+
+/// シーケンス部を復号し、リテラルと一致を出力する
+fn decode_sequences(src: &[u8], literals: &[u8], out: &mut Output, state: &mut FrameState, frame_start: usize) -> FsResult<()> {
+    let byte = |index: usize| src.get(index).map(|&b| b as usize).ok_or(FsError::CorruptedFs);
+    let first = byte(0)?;
+    let (count, mut position) = match first {
+        0 => return out.extend(literals),
+        1..=127 => (first, 1),
+        128..=254 => (((first - 128) << 8) + byte(1)?, 2),
+        _ => (byte(1)? + (byte(2)? << 8) + 0x7F00, 3),
+    };
+    
+    let modes = byte(position)? as u8;
+    position += 1;
+    if modes & 3 != 0 {
+        return Err(FsError::CorruptedFs);
+    }
+    let ll_table = select_table(modes >> 6, src, &mut position, &mut state.literal_lengths, (&LL_DEFAULT, 6), 9, 35)?;
+    let of_table = select_table((modes >> 4) & 3, src, &mut position, &mut state.offsets, (&OF_DEFAULT, 5), 8, 31)?;
+    let ml_table = select_table((modes >> 2) & 3, src, &mut position, &mut state.match_lengths, (&ML_DEFAULT, 6), 9, 52)?;
+    
+    let mut bits = BackwardBits::new(src.get(position..).ok_or(FsError::CorruptedFs)?)?;
+    let mut ll_state = ll_table.init_state(&mut bits);
+    let mut of_state = of_table.init_state(&mut bits);
+    let mut ml_state = ml_table.init_state(&mut bits);
+    let mut literal_position = 0;
+    
+    for index in 0..count {
+        let ll_code = ll_table.symbol(ll_state) as usize;
+        let ml_code = ml_table.symbol(ml_state) as usize;
+        let of_code = of_table.symbol(of_state) as u32;
+        if ll_code >= LL_BASE.len() || ml_code >= ML_BASE.len() || of_code > 31 {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        // 追加ビットはオフセット、一致長、リテラル長の順に読む
+        let offset_value = (1usize << of_code) + bits.bits(of_code) as usize;
+        let match_length = ML_BASE[ml_code] as usize + bits.bits(ML_BITS[ml_code] as u32) as usize;
+        let literal_length = LL_BASE[ll_code] as usize + bits.bits(LL_BITS[ll_code] as u32) as usize;
+        let offset = resolve_offset(&mut state.repeat, offset_value, literal_length)?;
+        
+        if index + 1 < count {
+            ll_state = ll_table.update(ll_state, &mut bits);
+            ml_state = ml_table.update(ml_state, &mut bits);
+            of_state = of_table.update(of_state, &mut bits);
+        }
+        
+        let end = literal_position + literal_length;
+        out.extend(literals.get(literal_position..end).ok_or(FsError::CorruptedFs)?)?;
+        literal_position = end;
+        out.copy_match(offset, match_length, frame_start)?;
+    }
+    
+    if bits.remaining != 0 {
+        return Err(FsError::CorruptedFs);
+    }
+    out.extend(&literals[literal_position..])
+}
+
+/// オフセット値を実際の距離に変換し、繰り返しオフセットを更新
+fn resolve_offset(repeat: &mut [usize; 3], value: usize, literal_length: usize) -> FsResult<usize> {
+    if value > 3 {
+        let offset = value - 3;
+        *repeat = [offset, repeat[0], repeat[1]];
+        return Ok(offset);
+    }
+    
+    // リテラル長が0なら繰り返しオフセットの番号が1つずれる
+    let index = if literal_length == 0 { value + 1 } else { value };
+    let offset = match index {
+        1 => return Ok(repeat[0]),
+        2 => repeat[1],
+        3 => repeat[2],
+        _ => repeat[0].checked_sub(1).filter(|&offset| offset > 0).ok_or(FsError::CorruptedFs)?,
+    };
+    if index == 2 {
+        *repeat = [offset, repeat[0], repeat[2]];
+    } else {
+        *repeat = [offset, repeat[0], repeat[1]];
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use super::super::compression::tests::sample_text;
+    
+    #[test]
+    fn decompresses_zstd_frames() {
+        let text = sample_text();
+        let mut dst = vec![0u8; text.len()];
+        let mut out = Output::new(&mut dst);
+        // 後ろのセクタ埋めは無視する
+        let mut padded = ZSTD_SAMPLE.to_vec();
+        padded.resize(padded.len() + 100, 0);
+        decompress(&padded, &mut out).unwrap();
+        assert_eq!(out.written(), text.len());
+        assert_eq!(dst, text);
+        
+        // RLEブロック1つのフレーム（単一セグメント、内容サイズ1バイト）
+        let rle = [0x28, 0xB5, 0x2F, 0xFD, 0x20, 0x05, 0x2B, 0x00, 0x00, b'z'];
+        let mut dst = [0u8; 5];
+        decompress(&rle, &mut Output::new(&mut dst)).unwrap();
+        assert_eq!(&dst, b"zzzzz");
+        
+        // 既定の分布の合計は表のサイズに一致する
+        assert!(FseTable::from_counts(&LL_DEFAULT, 6).is_ok());
+        assert!(FseTable::from_counts(&ML_DEFAULT, 6).is_ok());
+        assert!(FseTable::from_counts(&OF_DEFAULT, 5).is_ok());
+        
+        let mut corrupted = ZSTD_SAMPLE.to_vec();
+        let middle = corrupted.len() / 2;
+        corrupted[middle] ^= 0x55;
+        let mut dst = vec![0u8; text.len()];
+        assert!(decompress(&corrupted, &mut Output::new(&mut dst)).is_err() || dst != text);
+    }
+    
+    /// `sample_text()`をzstd（レベル19）で圧縮したもの
+    pub const ZSTD_SAMPLE: &[u8] = &[
+        0x28, 0xb5, 0x2f, 0xfd, 0x64, 0x1f, 0x21, 0x35, 0x0d, 0x00, 0xa6, 0x1d, 0x42, 0x1a, 0x80, 0x6b,
+        0x96, 0x03, 0x40, 0xa0, 0x09, 0xeb, 0x32, 0x68, 0x8f, 0x36, 0x63, 0xa4, 0x68, 0x24, 0xa5, 0x94,
+        0x29, 0xa5, 0xe4, 0xd2, 0x27, 0x30, 0x55, 0x0f, 0x4a, 0x00, 0x36, 0x00, 0x32, 0x00, 0x9d, 0x4a,
+        0x5f, 0xb7, 0x65, 0xd7, 0x68, 0x91, 0x38, 0x14, 0x9e, 0x66, 0x49, 0x8e, 0xc1, 0x1a, 0x6d, 0x26,
+        0xfb, 0x5c, 0x21, 0xb7, 0xc7, 0xb6, 0x32, 0xc5, 0xa6, 0x52, 0xa7, 0xa9, 0xe3, 0x0c, 0x40, 0x03,
+        0x82, 0x42, 0x02, 0x83, 0x43, 0x1c, 0x1a, 0x12, 0x28, 0x34, 0x30, 0x70, 0x78, 0xc0, 0x40, 0xe0,
+        0xb0, 0xe0, 0x80, 0x61, 0x81, 0x80, 0xc3, 0x81, 0x01, 0x09, 0x0d, 0x14, 0x1c, 0x10, 0x0a, 0x30,
+        0x38, 0x04, 0x88, 0x03, 0xc2, 0x42, 0x02, 0x82, 0x53, 0x52, 0x2f, 0x57, 0x8b, 0xb5, 0x8a, 0x2a,
+        0x22, 0x1a, 0x12, 0x3a, 0x99, 0x4a, 0xa4, 0x51, 0x50, 0x0d, 0xcd, 0x8c, 0xcc, 0xc7, 0xd3, 0xe1,
+        0x6c, 0x62, 0x4a, 0x48, 0x46, 0x44, 0x2e, 0x96, 0x0a, 0x65, 0x12, 0x52, 0xa7, 0xcf, 0xe5, 0xbf,
+        0xdf, 0xf3, 0x7b, 0xbc, 0x4c, 0x1e, 0x8b, 0x6f, 0xbb, 0xa6, 0xe7, 0x70, 0x95, 0x02, 0xe2, 0xdb,
+        0xae, 0xe9, 0xcd, 0xe1, 0x55, 0xa9, 0x9b, 0x4a, 0x5f, 0xb7, 0x65, 0xd7, 0x68, 0x71, 0xc4, 0xe1,
+        0x84, 0xe7, 0xcc, 0x92, 0x1c, 0x83, 0x35, 0xda, 0x4c, 0xb6, 0xcf, 0x5b, 0xc7, 0x6d, 0x5b, 0x6c,
+        0x15, 0xa3, 0xd8, 0xc4, 0x24, 0xf6, 0x98, 0x63, 0x8d, 0x31, 0xb6, 0x58, 0xc4, 0xaa, 0xa8, 0x02,
+        0x45, 0x35, 0x25, 0xf5, 0x72, 0xad, 0xc5, 0xda, 0x2a, 0x6a, 0x45, 0x44, 0x43, 0x42, 0x27, 0x53,
+        0x89, 0x34, 0x5a, 0x50, 0xcd, 0x68, 0x66, 0x26, 0xf3, 0xf1, 0x74, 0x38, 0x9b, 0x98, 0x12, 0x92,
+        0x8d, 0x88, 0xec, 0x62, 0x59, 0x85, 0x32, 0x09, 0xa9, 0xd3, 0xe7, 0xf2, 0x7f, 0x7e, 0x3f, 0x7e,
+        0x5f, 0xbc, 0x4c, 0x1e, 0x07, 0x80, 0xd4, 0xa8, 0x11, 0x70, 0xd0, 0xde, 0xff, 0x33, 0xb0, 0x15,
+        0x95, 0xae, 0x01, 0x12, 0x48, 0x10, 0x58, 0xc3, 0x10, 0xfc, 0x87, 0x08, 0x1e, 0x21, 0xe0, 0x0f,
+        0xb6, 0xec, 0xed, 0xe6, 0xe7, 0x67, 0x57, 0xfd, 0xa5, 0xef, 0x5a, 0xed, 0xe7, 0xcb, 0xb6, 0xfe,
+        0x4f, 0x2f, 0xbd, 0xda, 0xcf, 0xdc, 0x55, 0xfd, 0x9e, 0x5f, 0x3b, 0xfd, 0xaf, 0x14, 0x22, 0x1a,
+        0x84, 0x04, 0x88, 0xc8, 0x0f, 0x8a, 0x0f, 0x91, 0x1e, 0x14, 0x1e, 0x42, 0x76, 0x50, 0x74, 0x88,
+        0xe4, 0xa0, 0xe2, 0x10, 0xb9, 0x41, 0x6a, 0x83, 0x01, 0x84, 0xb3, 0x03, 0x08, 0xc6, 0x0c, 0x40,
+        0x88, 0x48, 0x26, 0x90, 0x32, 0x21, 0x21, 0x23, 0x00, 0x58, 0xd1, 0x1a, 0x44, 0x43, 0x9c, 0x41,
+        0x32, 0x84, 0x18, 0x0c, 0x86, 0xf0, 0x82, 0x5c, 0x88, 0xb5, 0x20, 0x67, 0x21, 0x77, 0x20, 0x1d,
+        0x94, 0x14, 0x02, 0x79, 0x56, 0x7e, 0x4d, 0x99, 0x08, 0x0b, 0xb0, 0x03, 0xe9, 0x18, 0xc8, 0x79,
+        0x09, 0xb0, 0xc9, 0x32, 0x88, 0xf1, 0x12, 0xb0, 0x93, 0x65, 0x28, 0xb2, 0xa5, 0x06, 0x68, 0x55,
+        0xd6, 0x12, 0xcc, 0x60,
+    ];
+}
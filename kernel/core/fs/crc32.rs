@@ -0,0 +1,50 @@
+// CRC32実装
+//
+// GPTのヘッダとエントリ配列の検証で使用するCRC32（IEEE 802.3）
+
+/// CRC32多項式（反転表現）
+const CRC32_POLY: u32 = 0xEDB8_8320;
+
+/// バイト単位の計算テーブル
+static CRC32_TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ CRC32_POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC32を計算
+///
+/// `crc32c()`と同じく、初期値の反転や最終値の反転は行わない。
+/// GPTはシード`!0`から計算して反転した値を格納する。
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = crc;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_crc32_check_value() {
+        // 標準的な検査値（初期値と最終値を反転した場合）
+        assert_eq!(!crc32(!0, b"123456789"), 0xCBF4_3926);
+        // 分割して計算しても同じ結果になる
+        assert_eq!(crc32(crc32(!0, b"1234"), b"56789"), crc32(!0, b"123456789"));
+    }
+}
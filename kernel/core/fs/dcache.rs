@@ -0,0 +1,519 @@
+// ディレクトリエントリキャッシュ（dcache）
+//
+// すべてのVFSファイルシステムで共有する、(マウント, 親ディレクトリのアイノード, 名前)から
+// アイノード参照への対応表。存在しない名前も負のエントリとして保持し、
+// パス解決のたびにディレクトリを読み直さずに済むようにする。
+// 親のアイノードで引くので、ディレクトリの名前を変えても配下のエントリはそのまま使える
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::Mutex;
+use super::{FsResult, FsId, FileType, InodeNum, Metadata, Permissions, FileHandle, DirHandle, DirEntry, Filesystem};
+use super::notify::{self, event_mask};
+use super::vfs::{check_permission, current_credentials};
+use super::xattr::access;
+
+/// キャッシュされたディレクトリエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dentry {
+    /// 存在する名前
+    Positive {
+        /// アイノード番号
+        inode: InodeNum,
+        /// ファイルタイプ
+        file_type: FileType,
+        /// シンボリックリンクのターゲット（一度読んだら保持する）
+        link_target: Option<String>,
+    },
+    /// 存在しない名前
+    Negative,
+}
+
+/// dcacheの統計情報
+#[derive(Debug, Clone, Copy)]
+pub struct DcacheStats {
+    /// エントリ数
+    pub entries: usize,
+    /// そのうち負のエントリ数
+    pub negative_entries: usize,
+    /// 最大エントリ数
+    pub capacity: usize,
+    /// ヒット数
+    pub hits: u64,
+    /// ミス数
+    pub misses: u64,
+}
+
+/// エントリのキー（マウント, 親ディレクトリのアイノード, 名前）
+type DentryKey = (FsId, InodeNum, String);
+
+/// LRU順序付きのエントリ
+struct CachedDentry {
+    /// エントリ本体
+    dentry: Dentry,
+    /// 最終参照の時刻印（LRUの並び順）
+    stamp: u64,
+}
+
+/// dcacheの実装
+struct DentryCache {
+    /// キー => エントリ
+    entries: BTreeMap<DentryKey, CachedDentry>,
+    /// 時刻印 => キー（古い順に追い出す）
+    lru: BTreeMap<u64, DentryKey>,
+    /// 次の時刻印
+    clock: u64,
+    /// 最大エントリ数
+    capacity: usize,
+    /// ヒット数
+    hits: u64,
+    /// ミス数
+    misses: u64,
+}
+
+impl DentryCache {
+    /// 新しいdcacheを作成
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+    
+    /// 時刻印を進める
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+    
+    /// エントリを検索（ヒットしたらLRUの先頭へ移す）
+    fn lookup(&mut self, key: &DentryKey) -> Option<Dentry> {
+        let stamp = self.tick();
+        let entry = match self.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => {
+                self.misses += 1;
+                return None;
+            },
+        };
+        
+        self.hits += 1;
+        let old = core::mem::replace(&mut entry.stamp, stamp);
+        let dentry = entry.dentry.clone();
+        if let Some(key) = self.lru.remove(&old) {
+            self.lru.insert(stamp, key);
+        }
+        Some(dentry)
+    }
+    
+    /// エントリを追加または置き換え
+    fn insert(&mut self, key: DentryKey, dentry: Dentry) {
+        self.remove(&key);
+        let stamp = self.tick();
+        self.lru.insert(stamp, key.clone());
+        self.entries.insert(key, CachedDentry { dentry, stamp });
+        
+        if self.entries.len() > self.capacity {
+            self.shrink(self.entries.len() - self.capacity);
+        }
+    }
+    
+    /// エントリを削除し、削除したエントリを返す
+    fn remove(&mut self, key: &DentryKey) -> Option<Dentry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.stamp);
+        Some(entry.dentry)
+    }
+    
+    /// ディレクトリ`dir`直下のエントリをすべて削除
+    fn remove_children(&mut self, fs: FsId, dir: InodeNum) {
+        let keys: Vec<DentryKey> = self.entries.range((fs, dir, String::new())..)
+            .map(|(key, _)| key)
+            .take_while(|(id, parent, _)| *id == fs && *parent == dir)
+            .cloned()
+            .collect();
+        
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+    
+    /// マウント`fs`のエントリをすべて削除
+    fn remove_filesystem(&mut self, fs: FsId) {
+        let keys: Vec<DentryKey> = self.entries.range((fs, 0, String::new())..)
+            .map(|(key, _)| key)
+            .take_while(|(id, _, _)| *id == fs)
+            .cloned()
+            .collect();
+        
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+    
+    /// 古い順に最大`count`個を追い出し、追い出した数を返す
+    fn shrink(&mut self, count: usize) -> usize {
+        let mut evicted = 0;
+        while evicted < count {
+            let key = match self.lru.pop_first() {
+                Some((_, key)) => key,
+                None => break,
+            };
+            self.entries.remove(&key);
+            evicted += 1;
+        }
+        evicted
+    }
+    
+    /// 統計を取得
+    fn stats(&self) -> DcacheStats {
+        DcacheStats {
+            entries: self.entries.len(),
+            negative_entries: self.entries.values().filter(|entry| entry.dentry == Dentry::Negative).count(),
+            capacity: self.capacity,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+}
+
+/// グローバルdcache
+static DENTRY_CACHE: Mutex<Option<DentryCache>> = Mutex::new(None);
+
+/// dcacheを初期化
+pub fn init(capacity: usize) -> FsResult<()> {
+    *DENTRY_CACHE.lock() = Some(DentryCache::new(capacity));
+    log::info!("dcache初期化完了: 最大{}エントリ", capacity);
+    Ok(())
+}
+
+/// ディレクトリ`dir`内の`name`のエントリを検索（未初期化ならNone）
+pub fn lookup(fs: FsId, dir: InodeNum, name: &str) -> Option<Dentry> {
+    DENTRY_CACHE.lock().as_mut()?.lookup(&(fs, dir, name.to_string()))
+}
+
+/// ディレクトリ`dir`内の`name`のエントリを追加
+pub fn insert(fs: FsId, dir: InodeNum, name: &str, dentry: Dentry) {
+    if let Some(cache) = DENTRY_CACHE.lock().as_mut() {
+        cache.insert((fs, dir, name.to_string()), dentry);
+    }
+}
+
+/// シンボリックリンクのエントリにターゲットを記録
+pub fn set_link_target(fs: FsId, dir: InodeNum, name: &str, target: &str) {
+    if let Some(cache) = DENTRY_CACHE.lock().as_mut() {
+        if let Some(entry) = cache.entries.get_mut(&(fs, dir, name.to_string())) {
+            if let Dentry::Positive { file_type: FileType::SymbolicLink, link_target, .. } = &mut entry.dentry {
+                *link_target = Some(target.to_string());
+            }
+        }
+    }
+}
+
+/// ディレクトリ`dir`内の`name`のエントリを無効化（作成や名前変更で古くなった場合）
+pub fn invalidate(fs: FsId, dir: InodeNum, name: &str) {
+    if let Some(cache) = DENTRY_CACHE.lock().as_mut() {
+        cache.remove(&(fs, dir, name.to_string()));
+    }
+}
+
+/// 削除（または上書き）された`name`のエントリを無効化
+///
+/// ディレクトリだったなら直下のエントリも捨て、アイノード番号が再利用されても
+/// 古い子が見えないようにする
+pub fn invalidate_removed(fs: FsId, dir: InodeNum, name: &str) {
+    if let Some(cache) = DENTRY_CACHE.lock().as_mut() {
+        if let Some(Dentry::Positive { inode, file_type: FileType::Directory, .. }) = cache.remove(&(fs, dir, name.to_string())) {
+            cache.remove_children(fs, inode);
+        }
+    }
+}
+
+/// マウントのエントリをすべて無効化（アンマウント）
+pub fn invalidate_filesystem(fs: FsId) {
+    if let Some(cache) = DENTRY_CACHE.lock().as_mut() {
+        cache.remove_filesystem(fs);
+    }
+}
+
+/// メモリ逼迫時に古いエントリから最大`count`個を解放し、解放した数を返す
+///
+/// 割り当てに失敗したときの回収経路から呼ばれるので、dcacheを操作中の割り当てで
+/// 呼ばれてもデッドロックしないよう、ロックが取れなければ何もしない
+pub fn shrink(count: usize) -> usize {
+    DENTRY_CACHE.try_lock().and_then(|mut cache| cache.as_mut().map(|cache| cache.shrink(count))).unwrap_or(0)
+}
+
+/// dcacheの統計を取得
+pub fn stats() -> Option<DcacheStats> {
+    DENTRY_CACHE.lock().as_ref().map(DentryCache::stats)
+}
+
+/// ディレクトリ内のパスを連結
+fn child_path(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// 名前空間を変更する操作でdcacheを無効化し、変更通知を発火するディレクトリハンドル
+pub(super) struct CachedDirHandle {
+    /// ファイルシステムのハンドル
+    inner: Arc<dyn DirHandle>,
+    /// ファイルシステム（移動先や親ディレクトリのアイノードを引く）
+    filesystem: Arc<dyn Filesystem>,
+    /// ファイルシステムのマウントポイント
+    mount_point: String,
+    /// マウント
+    fs: FsId,
+    /// ファイルシステム内のディレクトリパス
+    path: String,
+    /// このディレクトリのアイノード番号（dcacheのキー。引けなければNone）
+    inode: Option<InodeNum>,
+}
+
+impl CachedDirHandle {
+    /// ハンドルを包む
+    pub(super) fn new(inner: Arc<dyn DirHandle>, filesystem: Arc<dyn Filesystem>, mount_point: String, fs: FsId, path: String) -> Self {
+        let inode = inner.metadata().ok().map(|metadata| metadata.inode);
+        Self { inner, filesystem, mount_point, fs, path, inode }
+    }
+    
+    /// このディレクトリ内の`name`のエントリを無効化（`removed`なら削除されたものとして扱う）
+    fn invalidate_child(&self, name: &str, removed: bool) {
+        match self.inode {
+            Some(inode) if removed => invalidate_removed(self.fs, inode, name),
+            Some(inode) => invalidate(self.fs, inode, name),
+            None => invalidate_filesystem(self.fs),
+        }
+    }
+    
+    /// 名前変更の移動先（ファイルシステム内のパス）のエントリを無効化（上書きされた場合も含む）
+    fn invalidate_target(&self, target: &str) {
+        let (parent, name) = split_target(target);
+        let parent_inode = if parent.trim_end_matches('/') == self.path.trim_end_matches('/') {
+            self.inode
+        } else {
+            self.filesystem.metadata(&self.mount_point, &parent).ok().map(|metadata| metadata.inode)
+        };
+        match parent_inode {
+            Some(inode) => invalidate_removed(self.fs, inode, &name),
+            None => invalidate_filesystem(self.fs),
+        }
+    }
+    
+    /// ファイルシステム内のディレクトリ`dir`のエントリを現在のプロセスが作成・削除できるか確認
+    ///
+    /// ディレクトリへの書き込みと検索の権限が要る
+    fn check_writable(&self, dir: &str) -> FsResult<()> {
+        check_permission(&*self.filesystem, &self.mount_point, dir, FileType::Directory, &current_credentials(), access::WRITE | access::EXECUTE)
+    }
+    
+    /// 通知用にこのディレクトリのアイノード番号を引く（ウォッチがなければ引かない）
+    fn watched_inode(&self) -> Option<InodeNum> {
+        if !notify::is_active() {
+            return None;
+        }
+        self.inner.metadata().ok().map(|metadata| metadata.inode)
+    }
+    
+    /// 操作が成功したらこのディレクトリ内の名前のイベントを発火
+    fn notify_child<T>(&self, result: &FsResult<T>, mask: u32, name: &str, is_dir: bool) {
+        if result.is_ok() {
+            if let Some(dir) = self.watched_inode() {
+                notify::child_event(self.fs, dir, mask, name, is_dir, 0);
+            }
+        }
+    }
+    
+    /// 名前の移動を移動元と移動先のディレクトリへ通知
+    fn notify_move(&self, moved: &DirEntry, old_name: &str, target: &str) {
+        let from = match self.watched_inode() {
+            Some(inode) => inode,
+            None => return,
+        };
+        let is_dir = moved.file_type == FileType::Directory;
+        let cookie = notify::next_cookie();
+        notify::child_event(self.fs, from, event_mask::MOVED_FROM, old_name, is_dir, cookie);
+        
+        let (parent, name) = split_target(target);
+        let to = if parent.trim_end_matches('/') == self.path.trim_end_matches('/') {
+            Some(from)
+        } else {
+            self.filesystem.metadata(&self.mount_point, &parent).ok().map(|metadata| metadata.inode)
+        };
+        if let Some(to) = to {
+            notify::child_event(self.fs, to, event_mask::MOVED_TO, &name, is_dir, cookie);
+        }
+        notify::inode_event(self.fs, moved.inode, event_mask::MOVE_SELF);
+    }
+}
+
+impl DirHandle for CachedDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.inner.read_entries()
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        self.inner.lookup(name)
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        self.check_writable(&self.path)?;
+        let result = self.inner.create_file(name, permissions);
+        self.invalidate_child(name, false);
+        self.notify_child(&result, event_mask::CREATE, name, false);
+        result
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        self.check_writable(&self.path)?;
+        let result = self.inner.create_directory(name, permissions);
+        self.invalidate_child(name, false);
+        self.notify_child(&result, event_mask::CREATE, name, true);
+        result
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        self.check_writable(&self.path)?;
+        let removed = if notify::is_active() { self.inner.lookup(name).ok() } else { None };
+        // 他のハードリンクが残るならアイノードは消えない（削除前のリンク数で判断する）
+        let last_link = removed.as_ref().is_some_and(|entry| {
+            entry.file_type == FileType::Directory || self.filesystem.metadata(&self.mount_point, &child_path(&self.path, name))
+                .map_or(true, |metadata| metadata.links <= 1)
+        });
+        let result = self.inner.remove(name);
+        self.invalidate_child(name, true);
+        
+        if let Some(entry) = removed.filter(|_| result.is_ok()) {
+            self.notify_child(&result, event_mask::DELETE, name, entry.file_type == FileType::Directory);
+            if last_link {
+                notify::inode_event(self.fs, entry.inode, event_mask::DELETE_SELF);
+            }
+        }
+        result
+    }
+    
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        // 移動先は"/"で始まればファイルシステムのルートから、そうでなければこのディレクトリから
+        let target = if new_name.starts_with('/') { new_name.to_string() } else { child_path(&self.path, new_name) };
+        self.check_writable(&self.path)?;
+        let (target_dir, _) = split_target(&target);
+        if target_dir.trim_end_matches('/') != self.path.trim_end_matches('/') {
+            self.check_writable(&target_dir)?;
+        }
+        
+        let moved = if notify::is_active() { self.inner.lookup(old_name).ok() } else { None };
+        let result = self.inner.rename(old_name, new_name);
+        // 移動したディレクトリの配下は親のアイノードで引くので、名前のエントリだけを捨てる
+        self.invalidate_child(old_name, false);
+        self.invalidate_target(&target);
+        
+        if let Some(entry) = moved.filter(|_| result.is_ok()) {
+            self.notify_move(&entry, old_name, &target);
+        }
+        result
+    }
+    
+    fn create_symlink(&self, name: &str, target: &str) -> FsResult<()> {
+        self.check_writable(&self.path)?;
+        let result = self.inner.create_symlink(name, target);
+        self.invalidate_child(name, false);
+        self.notify_child(&result, event_mask::CREATE, name, false);
+        result
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.inner.metadata()
+    }
+    
+    fn create_device(&self, name: &str, file_type: FileType, major: u32, minor: u32) -> FsResult<()> {
+        self.check_writable(&self.path)?;
+        let result = self.inner.create_device(name, file_type, major, minor);
+        self.invalidate_child(name, false);
+        self.notify_child(&result, event_mask::CREATE, name, false);
+        result
+    }
+    
+    fn device_number(&self, name: &str) -> FsResult<(u32, u32)> {
+        self.inner.device_number(name)
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.inner.get_xattr(name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.inner.set_xattr(name, value)?;
+        notify::attrib_changed(&self.filesystem, &self.mount_point, self.fs, &self.path, true);
+        Ok(())
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.inner.list_xattr()
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.inner.remove_xattr(name)?;
+        notify::attrib_changed(&self.filesystem, &self.mount_point, self.fs, &self.path, true);
+        Ok(())
+    }
+}
+
+/// 移動先のパスを"."と".."を畳んだ親ディレクトリと名前に分ける
+fn split_target(target: &str) -> (String, String) {
+    let mut components: Vec<&str> = Vec::new();
+    for component in target.split('/') {
+        match component {
+            "" | "." => {},
+            ".." => { components.pop(); },
+            _ => components.push(component),
+        }
+    }
+    let name = components.pop().unwrap_or("").to_string();
+    (format!("/{}", components.join("/")), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn positive(inode: InodeNum) -> Dentry {
+        Dentry::Positive { inode, file_type: FileType::Directory, link_target: None }
+    }
+    
+    #[test]
+    fn lru_eviction_and_child_invalidation() {
+        let fs = FsId::new();
+        let other = FsId::new();
+        let key = |fs: FsId, dir: InodeNum, name: &str| (fs, dir, name.to_string());
+        let mut cache = DentryCache::new(4);
+        cache.insert(key(fs, 2, "a"), positive(12));
+        cache.insert(key(fs, 12, "b"), positive(13));
+        cache.insert(key(fs, 2, "a-b"), Dentry::Negative);
+        cache.insert(key(other, 12, "b"), positive(13));
+        
+        // 参照した"a"は残り、最も古い"a/b"が追い出される
+        assert_eq!(cache.lookup(&key(fs, 2, "a")), Some(positive(12)));
+        cache.insert(key(fs, 2, "c"), Dentry::Negative);
+        assert_eq!(cache.lookup(&key(fs, 12, "b")), None);
+        assert_eq!(cache.stats().negative_entries, 2);
+        
+        // 直下のエントリだけを捨て、他のディレクトリや他のマウントは残す
+        cache.remove(&key(fs, 2, "c"));
+        cache.insert(key(fs, 12, "b"), positive(13));
+        cache.remove_children(fs, 12);
+        assert!(cache.lookup(&key(fs, 12, "b")).is_none());
+        assert_eq!(cache.lookup(&key(fs, 2, "a")), Some(positive(12)));
+        assert!(cache.lookup(&key(other, 12, "b")).is_some());
+        
+        cache.remove_filesystem(fs);
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.lru.len(), 1);
+        assert_eq!(cache.shrink(8), 1);
+    }
+}
@@ -0,0 +1,524 @@
+// devfs 実装
+//
+// ドライバが登録したブロックデバイス・キャラクタデバイスをデバイスノードとして
+// 公開する仮想ファイルシステム。ノードはドライバの登録とホットアンプラグに合わせて
+// 増減し、読み書きとioctlはそのままドライバへ渡す
+
+mod registry;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::RwLock;
+use super::{FsError, FsResult, Metadata, FsStats, FileType, Permissions, OpenMode, InodeNum};
+use super::{Filesystem, FileHandle, DirHandle, DirEntry, BlockDevice, CharDevice};
+use super::partition::PartitionType;
+
+pub use self::registry::{
+    DeviceClass, DeviceOps, DeviceNode, DeviceInfo, register, unregister, add_partition, rescan_partitions, lookup, devices,
+};
+
+/// ルートディレクトリのアイノード番号
+const ROOT_INODE: InodeNum = 1;
+
+/// ブロックデバイスのバイト数を返す（BLKGETSIZE64）
+pub const BLKGETSIZE64: u32 = 0x8008_1272;
+/// 論理セクタサイズを返す（BLKSSZGET）
+pub const BLKSSZGET: u32 = 0x1268;
+/// デバイスのキャッシュを書き出す（BLKFLSBUF）
+pub const BLKFLSBUF: u32 = 0x1261;
+/// パーティションテーブルを読み直す（BLKRRPART）
+pub const BLKRRPART: u32 = 0x125F;
+
+/// devfs内の要素
+#[derive(Clone)]
+enum DevNode {
+    /// ディレクトリ（/dev からの相対パス、ルートは""）
+    Directory(String),
+    /// デバイスノード
+    Device(Arc<DeviceNode>),
+}
+
+impl DevNode {
+    /// パスを要素に変換
+    fn parse(path: &str) -> FsResult<Self> {
+        let path = path.trim_matches('/');
+        if let Some(node) = registry::lookup(path) {
+            return Ok(DevNode::Device(node));
+        }
+        if registry::is_directory(path) {
+            return Ok(DevNode::Directory(path.to_string()));
+        }
+        Err(FsError::NotFound)
+    }
+    
+    /// アイノード番号
+    fn inode(&self) -> InodeNum {
+        match self {
+            DevNode::Directory(path) if path.is_empty() => ROOT_INODE,
+            // ディレクトリはパスのハッシュ（デバイスノードの番号とは上位ビットで区別する）
+            DevNode::Directory(path) => (1 << 63) | path.bytes().fold(0u64, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u64)) >> 1,
+            DevNode::Device(node) => node.inode,
+        }
+    }
+    
+    /// ファイルタイプ
+    fn file_type(&self) -> FileType {
+        match self {
+            DevNode::Directory(_) => FileType::Directory,
+            DevNode::Device(node) => node.file_type(),
+        }
+    }
+    
+    /// メタデータ
+    fn metadata(&self) -> Metadata {
+        let now = crate::time::current_time_ns() / 1_000_000_000;
+        let (size, created, block_size) = match self {
+            DevNode::Directory(_) => (0, now, 1024),
+            DevNode::Device(node) => {
+                let block_size = match &node.ops {
+                    DeviceOps::Block(device) => device.block_size() as u32,
+                    DeviceOps::Char(_) => 1024,
+                };
+                (node.size(), node.created, block_size)
+            },
+        };
+        Metadata {
+            inode: self.inode(),
+            file_type: self.file_type(),
+            size,
+            uid: 0,
+            gid: 0,
+            permissions: Permissions {
+                read: true,
+                write: true,
+                execute: self.file_type() == FileType::Directory,
+            },
+            created,
+            accessed: now,
+            modified: created,
+            links: if self.file_type() == FileType::Directory { 2 } else { 1 },
+            block_size,
+            blocks: 0,
+        }
+    }
+}
+
+/// デバイスノードのファイルハンドル
+pub struct DevFileHandle {
+    /// 対象のノード
+    node: Arc<DeviceNode>,
+    /// 読み込み可能か
+    readable: bool,
+    /// 書き込み可能か
+    writable: bool,
+}
+
+impl DevFileHandle {
+    /// ブロックデバイスのバイト範囲を読む
+    fn read_block_range(device: &dyn BlockDevice, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let size = device.block_size() * device.total_blocks();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        let block_size = device.block_size();
+        let first = offset / block_size;
+        let last = (offset + len as u64 - 1) / block_size;
+        let data = device.read_blocks(first, last - first + 1)?;
+        
+        let start = (offset - first * block_size) as usize;
+        let len = core::cmp::min(len, data.len().saturating_sub(start));
+        buffer[..len].copy_from_slice(&data[start..start + len]);
+        Ok(len)
+    }
+    
+    /// ブロックデバイスのバイト範囲に書く（端のブロックは読み込んでから書き戻す）
+    fn write_block_range(device: &dyn BlockDevice, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        let size = device.block_size() * device.total_blocks();
+        if offset >= size {
+            return Err(FsError::OutOfSpace);
+        }
+        let len = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        let block_size = device.block_size();
+        let first = offset / block_size;
+        let last = (offset + len as u64 - 1) / block_size;
+        
+        let start = (offset - first * block_size) as usize;
+        let mut data = if start == 0 && (offset + len as u64).is_multiple_of(block_size) {
+            alloc::vec![0; len]
+        } else {
+            device.read_blocks(first, last - first + 1)?
+        };
+        data[start..start + len].copy_from_slice(&buffer[..len]);
+        device.write_blocks(first, &data)?;
+        Ok(len)
+    }
+}
+
+impl FileHandle for DevFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.node.check_present()?;
+        if !self.readable {
+            return Err(FsError::PermissionDenied);
+        }
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        match &self.node.ops {
+            DeviceOps::Block(device) => Self::read_block_range(device.as_ref(), buffer, offset),
+            // キャラクタデバイスはストリームなのでオフセットを使わない
+            DeviceOps::Char(device) => device.read(buffer),
+        }
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        self.node.check_present()?;
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        match &self.node.ops {
+            DeviceOps::Block(device) => Self::write_block_range(device.as_ref(), buffer, offset),
+            DeviceOps::Char(device) => device.write(buffer),
+        }
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.node.check_present()?;
+        match &self.node.ops {
+            DeviceOps::Block(device) => device.sync(),
+            DeviceOps::Char(_) => Ok(()),
+        }
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        self.node.check_present()?;
+        Ok(self.node.size())
+    }
+    
+    fn resize(&self, _new_size: u64) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.node.check_present()?;
+        Ok(DevNode::Device(self.node.clone()).metadata())
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        self.readable
+    }
+    
+    fn can_write(&self) -> bool {
+        self.writable
+    }
+    
+    fn ioctl(&self, request: u32, arg: usize) -> FsResult<usize> {
+        self.node.check_present()?;
+        match &self.node.ops {
+            // ブロックデバイス共通の要求はここで処理し、それ以外はドライバへ渡す
+            DeviceOps::Block(device) => match request {
+                BLKGETSIZE64 => Ok(self.node.size() as usize),
+                BLKSSZGET => Ok(device.block_size() as usize),
+                BLKFLSBUF => device.sync().map(|_| 0),
+                BLKRRPART => rescan_partitions(&self.node.name),
+                _ => device.ioctl(request, arg),
+            },
+            DeviceOps::Char(device) => device.ioctl(request, arg),
+        }
+    }
+}
+
+/// devfsディレクトリハンドル
+pub struct DevDirHandle {
+    /// /dev からの相対パス
+    path: String,
+}
+
+impl DirHandle for DevDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        Ok(registry::children(&self.path).into_iter()
+            .map(|(name, node)| {
+                let node = match node {
+                    Some(node) => DevNode::Device(node),
+                    None if self.path.is_empty() => DevNode::Directory(name.clone()),
+                    None => DevNode::Directory(format!("{}/{}", self.path, name)),
+                };
+                DirEntry { name, inode: node.inode(), file_type: node.file_type() }
+            })
+            .collect())
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let node = DevNode::parse(&format!("{}/{}", self.path, name))?;
+        Ok(DirEntry { name: name.to_string(), inode: node.inode(), file_type: node.file_type() })
+    }
+    
+    // ノードはドライバの登録でのみ作られる
+    fn create_file(&self, _name: &str, _permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        Err(FsError::PermissionDenied)
+    }
+    
+    fn create_directory(&self, _name: &str, _permissions: Permissions) -> FsResult<()> {
+        Err(FsError::PermissionDenied)
+    }
+    
+    fn remove(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::PermissionDenied)
+    }
+    
+    fn rename(&self, _old_name: &str, _new_name: &str) -> FsResult<()> {
+        Err(FsError::PermissionDenied)
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        Err(FsError::PermissionDenied)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(DevNode::Directory(self.path.clone()).metadata())
+    }
+}
+
+/// devfsファイルシステム
+pub struct DevFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// マウントポイント
+    mount_points: RwLock<Vec<String>>,
+}
+
+impl DevFilesystem {
+    /// 新しいdevfsインスタンスを作成
+    pub fn new() -> Self {
+        Self {
+            name: "devfs".to_string(),
+            mount_points: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for DevFilesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filesystem for DevFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, _device: &str, mount_point: &str, _options: &str) -> FsResult<()> {
+        let mut mount_points = self.mount_points.write();
+        if mount_points.iter().any(|path| path == mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        mount_points.push(mount_point.to_string());
+        
+        log::info!("devfsをマウント: {}", mount_point);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let mut mount_points = self.mount_points.write();
+        let index = mount_points.iter().position(|path| path == mount_point).ok_or(FsError::NotFound)?;
+        mount_points.remove(index);
+        
+        log::info!("devfsをアンマウント: {}", mount_point);
+        Ok(())
+    }
+    
+    fn open_file(&self, _mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let node = match DevNode::parse(path) {
+            Ok(DevNode::Device(node)) => node,
+            Ok(DevNode::Directory(_)) => return Err(FsError::IsDirectory),
+            // デバイスノードは作成できない
+            Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => return Err(FsError::PermissionDenied),
+            Err(e) => return Err(e),
+        };
+        if mode == OpenMode::CreateNew {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        Ok(Arc::new(DevFileHandle {
+            node,
+            readable: !matches!(mode, OpenMode::WriteOnly | OpenMode::Append),
+            writable: mode != OpenMode::ReadOnly,
+        }))
+    }
+    
+    fn open_directory(&self, _mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        match DevNode::parse(path)? {
+            DevNode::Directory(path) => Ok(Arc::new(DevDirHandle { path })),
+            DevNode::Device(_) => Err(FsError::NotDirectory),
+        }
+    }
+    
+    fn metadata(&self, _mount_point: &str, path: &str) -> FsResult<Metadata> {
+        Ok(DevNode::parse(path)?.metadata())
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        if !self.mount_points.read().iter().any(|path| path == mount_point) {
+            return Err(FsError::NotFound);
+        }
+        Ok(FsStats {
+            total_blocks: 0,
+            free_blocks: 0,
+            available_blocks: 0,
+            total_nodes: registry::devices().len() as u64,
+            free_nodes: 0,
+            block_size: 1024,
+            max_filename_length: 255,
+        })
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        for device in registry::devices() {
+            if let Some(DeviceOps::Block(block)) = registry::lookup(&device.name).map(|node| node.ops.clone()) {
+                let _ = block.sync();
+            }
+        }
+        Ok(())
+    }
+    
+    // ノードはホットプラグで増減するため、dcacheに負のエントリを残さない
+    fn is_virtual(&self) -> bool {
+        true
+    }
+}
+
+/// デバイスパス（"/dev/sda1"または"sda1"）を/devからの相対名に変換
+pub(super) fn device_name(path: &str) -> &str {
+    path.strip_prefix("/dev/").unwrap_or(path).trim_matches('/')
+}
+
+/// デバイスノードを開く
+pub fn open_device(path: &str) -> FsResult<Arc<dyn FileHandle>> {
+    let node = registry::lookup(device_name(path)).ok_or(FsError::NotFound)?;
+    Ok(Arc::new(DevFileHandle { node, readable: true, writable: true }))
+}
+
+/// ブロックデバイスをマウント用に開く
+pub fn open_block_device(path: &str) -> FsResult<Arc<dyn BlockDevice>> {
+    let node = registry::lookup(device_name(path)).ok_or(FsError::NotFound)?;
+    node.check_present()?;
+    match &node.ops {
+        DeviceOps::Block(device) => Ok(device.clone()),
+        DeviceOps::Char(_) => Err(FsError::NotSupported),
+    }
+}
+
+/// パーティションのデバイスパスから種類を取得（ディスク全体や未登録ならNone）
+pub fn partition_type(path: &str) -> Option<PartitionType> {
+    registry::lookup(device_name(path))?.partition.as_ref().map(|partition| partition.partition_type)
+}
+
+/// /dev/null
+struct NullDevice;
+
+impl CharDevice for NullDevice {
+    fn read(&self, _buffer: &mut [u8]) -> FsResult<usize> {
+        Ok(0)
+    }
+    
+    fn write(&self, buffer: &[u8]) -> FsResult<usize> {
+        Ok(buffer.len())
+    }
+}
+
+/// /dev/zero
+struct ZeroDevice;
+
+impl CharDevice for ZeroDevice {
+    fn read(&self, buffer: &mut [u8]) -> FsResult<usize> {
+        buffer.fill(0);
+        Ok(buffer.len())
+    }
+    
+    fn write(&self, buffer: &[u8]) -> FsResult<usize> {
+        Ok(buffer.len())
+    }
+}
+
+/// 初期化済みか
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// devfsを初期化し、カーネルが提供するデバイスを登録
+pub fn init() -> FsResult<()> {
+    if INITIALIZED.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    register(DeviceClass::Named("null".to_string()), DeviceOps::Char(Arc::new(NullDevice)))?;
+    register(DeviceClass::Named("zero".to_string()), DeviceOps::Char(Arc::new(ZeroDevice)))?;
+    
+    log::info!("devfs初期化完了: {}個のデバイス", registry::devices().len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::partition::Partition;
+    use super::super::memory_disk::MemoryDisk;
+    
+    /// テスト用のLinuxパーティション
+    fn linux_partition(number: u32, first_block: u64, block_count: u64) -> Partition {
+        Partition {
+            number,
+            first_block,
+            block_count,
+            partition_type: PartitionType::Mbr(0x83),
+            unique_guid: None,
+            name: String::new(),
+            bootable: false,
+        }
+    }
+    
+    #[test]
+    fn disk_partitions_and_hot_unplug() {
+        let disk = Arc::new(MemoryDisk::new(alloc::vec![0; 512 * 64]));
+        let name = register(DeviceClass::Disk, DeviceOps::Block(disk.clone())).unwrap();
+        assert!(name.starts_with("sd"));
+        let partition = add_partition(&name, linux_partition(1, 8, 16)).unwrap();
+        assert_eq!(partition, format!("{}1", name));
+        assert!(add_partition(&name, linux_partition(2, 60, 16)).is_err());
+        
+        // パーティションの先頭はディスクの8ブロック目、ブロック境界をまたぐ書き込みも通る
+        let fs = DevFilesystem::new();
+        let handle = fs.open_file("/dev", &format!("/{}", partition), OpenMode::ReadWrite).unwrap();
+        assert_eq!(handle.write(b"hello", 510).unwrap(), 5);
+        assert_eq!(&disk.image()[8 * 512 + 510..8 * 512 + 515], b"hello");
+        assert_eq!(handle.ioctl(BLKGETSIZE64, 0).unwrap(), 16 * 512);
+        assert_eq!(open_block_device(&format!("/dev/{}", partition)).unwrap().total_blocks(), 16);
+        
+        let usb = register(DeviceClass::Usb { bus: 1, address: 200 }, DeviceOps::Char(Arc::new(NullDevice))).unwrap();
+        assert_eq!(usb, "bus/usb/001/200");
+        assert!(fs.open_directory("/dev", "/bus/usb").unwrap().read_entries().unwrap().iter().any(|entry| entry.name == "001"));
+        assert!(matches!(fs.open_file("/dev", "/bus", OpenMode::ReadOnly), Err(FsError::IsDirectory)));
+        
+        // ディスクを外すとパーティションも消え、開いているハンドルは無効になる
+        unregister(&name).unwrap();
+        unregister(&usb).unwrap();
+        assert!(lookup(&partition).is_none());
+        assert!(matches!(handle.read(&mut [0; 4], 0), Err(FsError::StaleFileHandle)));
+        assert!(matches!(fs.metadata("/dev", "/bus"), Err(FsError::NotFound)));
+    }
+}
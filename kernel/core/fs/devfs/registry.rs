@@ -0,0 +1,417 @@
+// devfs デバイス登録表
+//
+// ドライバが登録したデバイスに安定した名前とデバイス番号を割り当てる。
+// ディスクは登録時にパーティションテーブルを読んでパーティションのノードも作る。
+// ホットアンプラグで削除されたノードは、開いているハンドルからも使えなくなる
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::RwLock;
+use super::super::{FsError, FsResult, FileType, InodeNum, BlockDevice, CharDevice};
+use super::super::partition::{self, Partition, PartitionDevice, PartitionType};
+
+/// SCSI/SATA/USBマスストレージディスクのメジャー番号
+const SCSI_DISK_MAJOR: u32 = 8;
+/// 1台のSCSIディスクが使うマイナー番号の数（ディスク本体 + 15パーティション）
+const SCSI_MINORS_PER_DISK: u32 = 16;
+/// シリアルポートのメジャー番号
+const TTY_MAJOR: u32 = 4;
+/// ttyS0のマイナー番号
+const TTYS_MINOR_BASE: u32 = 64;
+/// USBデバイスのメジャー番号
+const USB_DEVICE_MAJOR: u32 = 189;
+/// 拡張ブロックデバイス（NVMeなど）のメジャー番号。マイナー番号は登録順
+const BLOCK_EXT_MAJOR: u32 = 259;
+/// NVMeコントローラーのメジャー番号。マイナー番号はコントローラー番号
+const NVME_CHAR_MAJOR: u32 = 241;
+/// その他のキャラクタデバイスのメジャー番号。マイナー番号は登録順
+const MISC_MAJOR: u32 = 10;
+/// ループデバイスのメジャー番号
+const LOOP_MAJOR: u32 = 7;
+
+/// デバイスの種類（名前とデバイス番号の決め方）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceClass {
+    /// SCSI/SATA/USBマスストレージのディスク（空いている最初の"sdX"）
+    Disk,
+    /// NVMeの名前空間（"nvme<controller>n<namespace>"）
+    NvmeNamespace {
+        /// コントローラー番号
+        controller: u32,
+        /// 名前空間ID
+        namespace: u32,
+    },
+    /// NVMeコントローラー（"nvme<controller>"）
+    NvmeController {
+        /// コントローラー番号
+        controller: u32,
+    },
+    /// シリアルポート（"ttyS<port>"）
+    Serial {
+        /// ポート番号
+        port: u32,
+    },
+    /// USBデバイス（"bus/usb/<bus>/<address>"）
+    Usb {
+        /// バス番号
+        bus: u8,
+        /// デバイスアドレス
+        address: u8,
+    },
+    /// ファイルを裏に持つループデバイス（"loop<index>"）
+    Loop {
+        /// ループデバイス番号
+        index: u32,
+    },
+    /// 名前を指定するデバイス
+    Named(String),
+}
+
+impl DeviceClass {
+    /// 登録状況によらず決まる名前（Diskは空き状況で決まるためNone）
+    pub fn fixed_name(&self) -> Option<String> {
+        match self {
+            DeviceClass::Disk => None,
+            DeviceClass::NvmeNamespace { controller, namespace } => Some(format!("nvme{}n{}", controller, namespace)),
+            DeviceClass::NvmeController { controller } => Some(format!("nvme{}", controller)),
+            DeviceClass::Serial { port } => Some(format!("ttyS{}", port)),
+            DeviceClass::Usb { bus, address } => Some(format!("bus/usb/{:03}/{:03}", bus, address)),
+            DeviceClass::Loop { index } => Some(format!("loop{}", index)),
+            DeviceClass::Named(name) => Some(name.clone()),
+        }
+    }
+}
+
+/// デバイスの操作
+#[derive(Clone)]
+pub enum DeviceOps {
+    /// ブロックデバイス
+    Block(Arc<dyn BlockDevice>),
+    /// キャラクタデバイス
+    Char(Arc<dyn CharDevice>),
+}
+
+/// 登録済みのデバイスノード
+pub struct DeviceNode {
+    /// /dev からの相対名
+    pub name: String,
+    /// メジャー番号
+    pub major: u32,
+    /// マイナー番号
+    pub minor: u32,
+    /// アイノード番号
+    pub inode: InodeNum,
+    /// 登録時刻（UNIXタイムスタンプ）
+    pub created: u64,
+    /// パーティションなら親ディスクの名前
+    pub parent: Option<String>,
+    /// パーティションならテーブルのエントリ
+    pub partition: Option<Partition>,
+    /// ドライバへの操作
+    pub ops: DeviceOps,
+    /// まだ接続されているか（アンプラグでfalseになる）
+    present: AtomicBool,
+}
+
+impl DeviceNode {
+    /// ファイルタイプ
+    pub fn file_type(&self) -> FileType {
+        match self.ops {
+            DeviceOps::Block(_) => FileType::BlockDevice,
+            DeviceOps::Char(_) => FileType::CharDevice,
+        }
+    }
+    
+    /// 容量（バイト単位、キャラクタデバイスは0）
+    pub fn size(&self) -> u64 {
+        match &self.ops {
+            DeviceOps::Block(device) => device.block_size() * device.total_blocks(),
+            DeviceOps::Char(_) => 0,
+        }
+    }
+    
+    /// 接続されていることを確認（アンプラグ済みならStaleFileHandle）
+    pub fn check_present(&self) -> FsResult<()> {
+        if self.present.load(Ordering::Acquire) {
+            Ok(())
+        } else {
+            Err(FsError::StaleFileHandle)
+        }
+    }
+}
+
+/// 公開用のデバイス情報
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// /dev からの相対名
+    pub name: String,
+    /// ファイルタイプ（BlockDeviceまたはCharDevice）
+    pub file_type: FileType,
+    /// メジャー番号
+    pub major: u32,
+    /// マイナー番号
+    pub minor: u32,
+    /// 容量（バイト単位）
+    pub size: u64,
+    /// パーティションなら種類（ファイルシステムの推定に使う）
+    pub partition_type: Option<PartitionType>,
+}
+
+/// デバイス登録表
+struct DeviceRegistry {
+    /// 名前 => ノード
+    nodes: BTreeMap<String, Arc<DeviceNode>>,
+    /// 次に割り当てる拡張ブロックデバイスのマイナー番号
+    next_block_ext_minor: u32,
+    /// 次に割り当てるmiscデバイスのマイナー番号
+    next_misc_minor: u32,
+}
+
+/// グローバルなデバイス登録表（ファイルシステムの初期化前からドライバが登録できる）
+static REGISTRY: RwLock<DeviceRegistry> = RwLock::new(DeviceRegistry {
+    nodes: BTreeMap::new(),
+    next_block_ext_minor: 0,
+    next_misc_minor: 64,
+});
+
+/// デバイスノードのアイノード番号（ディレクトリのアイノード番号とは重ならない）
+static NEXT_INODE: AtomicU64 = AtomicU64::new(0x1000);
+
+/// n番目のSCSIディスクの名前の末尾（a..z, aa..zz, ...）
+fn disk_letters(mut index: u32) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+impl DeviceRegistry {
+    /// 名前とデバイス番号を決める
+    fn allocate(&mut self, class: &DeviceClass, ops: &DeviceOps) -> FsResult<(String, u32, u32)> {
+        let is_block = matches!(ops, DeviceOps::Block(_));
+        match class {
+            DeviceClass::Disk => {
+                let index = (0..).find(|index| !self.nodes.contains_key(&format!("sd{}", disk_letters(*index))))
+                    .unwrap_or_default();
+                Ok((format!("sd{}", disk_letters(index)), SCSI_DISK_MAJOR, index * SCSI_MINORS_PER_DISK))
+            },
+            DeviceClass::NvmeController { controller } => Ok((format!("nvme{}", controller), NVME_CHAR_MAJOR, *controller)),
+            DeviceClass::Serial { port } => Ok((format!("ttyS{}", port), TTY_MAJOR, TTYS_MINOR_BASE + port)),
+            DeviceClass::Usb { bus, address } => {
+                let minor = (*bus as u32).saturating_sub(1) * 128 + (*address as u32).saturating_sub(1);
+                Ok((format!("bus/usb/{:03}/{:03}", bus, address), USB_DEVICE_MAJOR, minor))
+            },
+            DeviceClass::Loop { index } => Ok((format!("loop{}", index), LOOP_MAJOR, *index)),
+            DeviceClass::NvmeNamespace { .. } | DeviceClass::Named(_) => {
+                let name = class.fixed_name().unwrap_or_default();
+                if name.is_empty() || name.starts_with('/') || name.ends_with('/') || name.contains("//") {
+                    return Err(FsError::InvalidData);
+                }
+                Ok((name, if is_block { BLOCK_EXT_MAJOR } else { MISC_MAJOR }, self.next_minor(is_block)))
+            },
+        }
+    }
+    
+    /// 登録順のマイナー番号を割り当てる
+    fn next_minor(&mut self, is_block: bool) -> u32 {
+        let counter = if is_block { &mut self.next_block_ext_minor } else { &mut self.next_misc_minor };
+        let minor = *counter;
+        *counter += 1;
+        minor
+    }
+    
+    /// ノードを追加
+    fn insert(&mut self, name: String, major: u32, minor: u32, partition: Option<(String, Partition)>, ops: DeviceOps) -> FsResult<Arc<DeviceNode>> {
+        // 既存のノードやディレクトリと同じ名前、既存のノードの下の名前は使えない
+        let prefix = format!("{}/", name);
+        if self.nodes.contains_key(&name)
+            || self.nodes.keys().any(|existing| existing.starts_with(&prefix) || name.starts_with(&format!("{}/", existing)))
+        {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        let (parent, partition) = partition.unzip();
+        let node = Arc::new(DeviceNode {
+            name: name.clone(),
+            major,
+            minor,
+            inode: NEXT_INODE.fetch_add(1, Ordering::Relaxed),
+            created: crate::time::current_time_ns() / 1_000_000_000,
+            parent,
+            partition,
+            ops,
+            present: AtomicBool::new(true),
+        });
+        self.nodes.insert(name, node.clone());
+        Ok(node)
+    }
+    
+    /// ディスクのパーティションの名前
+    fn partitions_of(&self, disk: &str) -> Vec<String> {
+        self.nodes.values()
+            .filter(|child| child.parent.as_deref() == Some(disk))
+            .map(|child| child.name.clone())
+            .collect()
+    }
+    
+    /// ノードとそのパーティションを削除
+    fn remove(&mut self, name: &str) -> FsResult<Vec<Arc<DeviceNode>>> {
+        let node = self.nodes.remove(name).ok_or(FsError::NotFound)?;
+        let mut removed = vec![node];
+        removed.extend(self.partitions_of(name).iter().filter_map(|partition| self.nodes.remove(partition)));
+        for node in &removed {
+            node.present.store(false, Ordering::Release);
+        }
+        Ok(removed)
+    }
+}
+
+/// デバイスを登録し、/dev からの相対名を返す
+pub fn register(class: DeviceClass, ops: DeviceOps) -> FsResult<String> {
+    let is_block = matches!(ops, DeviceOps::Block(_));
+    let name = {
+        let mut registry = REGISTRY.write();
+        let (name, major, minor) = registry.allocate(&class, &ops)?;
+        registry.insert(name.clone(), major, minor, None, ops)?;
+        log::info!("devfs: /dev/{} を追加 ({}:{})", name, major, minor);
+        name
+    };
+    
+    // ディスクはパーティションテーブルを読む（読めなくてもディスク自体は使える）
+    if is_block {
+        if let Err(e) = rescan_partitions(&name) {
+            log::warn!("devfs: /dev/{} のパーティションテーブルを読めません: {:?}", name, e);
+        }
+    }
+    Ok(name)
+}
+
+/// デバイスを削除（ホットアンプラグ）。パーティションも一緒に削除する
+pub fn unregister(name: &str) -> FsResult<()> {
+    let removed = REGISTRY.write().remove(name)?;
+    for node in &removed {
+        log::info!("devfs: /dev/{} を削除", node.name);
+    }
+    Ok(())
+}
+
+/// ディスクにパーティションを追加し、その名前を返す（"sda" => "sda1"、"nvme0n1" => "nvme0n1p1"）
+pub fn add_partition(disk: &str, partition: Partition) -> FsResult<String> {
+    let mut registry = REGISTRY.write();
+    let parent = registry.nodes.get(disk).cloned().ok_or(FsError::NotFound)?;
+    if parent.parent.is_some() || partition.number == 0 {
+        return Err(FsError::InvalidData);
+    }
+    let device = match &parent.ops {
+        DeviceOps::Block(device) => PartitionDevice::new(device.clone(), partition.first_block, partition.block_count)?,
+        DeviceOps::Char(_) => return Err(FsError::NotSupported),
+    };
+    
+    let separator = if disk.ends_with(|c: char| c.is_ascii_digit()) { "p" } else { "" };
+    let name = format!("{}{}{}", disk, separator, partition.number);
+    let (major, minor) = if parent.major == SCSI_DISK_MAJOR && partition.number < SCSI_MINORS_PER_DISK {
+        (SCSI_DISK_MAJOR, parent.minor + partition.number)
+    } else {
+        (BLOCK_EXT_MAJOR, registry.next_minor(true))
+    };
+    
+    let block_count = partition.block_count;
+    registry.insert(name.clone(), major, minor, Some((disk.to_string(), partition)), DeviceOps::Block(Arc::new(device)))?;
+    
+    log::info!("devfs: /dev/{} を追加 ({}:{}, {}ブロック)", name, major, minor, block_count);
+    Ok(name)
+}
+
+/// ディスクのパーティションテーブルを読み直してノードを作り直し、パーティション数を返す
+///
+/// パーティションが開かれているかマウントされていればResourceBusy
+pub fn rescan_partitions(disk: &str) -> FsResult<usize> {
+    let node = lookup(disk).ok_or(FsError::NotFound)?;
+    let device = match &node.ops {
+        DeviceOps::Block(device) if node.parent.is_none() => device.clone(),
+        _ => return Err(FsError::InvalidData),
+    };
+    let partitions = partition::read_partitions(device.as_ref())?;
+    
+    {
+        let mut registry = REGISTRY.write();
+        let old = registry.partitions_of(disk);
+        // 登録表以外からの参照（ハンドルやマウント）が残っていれば使用中
+        let busy = old.iter()
+            .filter_map(|name| registry.nodes.get(name))
+            .any(|node| Arc::strong_count(node) > 1 || matches!(&node.ops, DeviceOps::Block(device) if Arc::strong_count(device) > 1));
+        if busy {
+            return Err(FsError::ResourceBusy);
+        }
+        for name in old {
+            if let Some(node) = registry.nodes.remove(&name) {
+                node.present.store(false, Ordering::Release);
+            }
+        }
+    }
+    
+    let count = partitions.len();
+    for partition in partitions {
+        add_partition(disk, partition)?;
+    }
+    Ok(count)
+}
+
+/// 名前からノードを取得
+pub fn lookup(name: &str) -> Option<Arc<DeviceNode>> {
+    REGISTRY.read().nodes.get(name).cloned()
+}
+
+/// `dir`（/dev からの相対パス、ルートは""）の直下の要素名と、それがデバイスノードならそのノード
+pub fn children(dir: &str) -> Vec<(String, Option<Arc<DeviceNode>>)> {
+    let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+    let registry = REGISTRY.read();
+    let mut entries: Vec<(String, Option<Arc<DeviceNode>>)> = Vec::new();
+    for (name, node) in registry.nodes.range(prefix.clone()..) {
+        let rest = match name.strip_prefix(&prefix) {
+            Some(rest) => rest,
+            None => break,
+        };
+        match rest.split_once('/') {
+            Some((child, _)) => {
+                if entries.last().is_none_or(|(last, _)| last != child) {
+                    entries.push((child.to_string(), None));
+                }
+            },
+            None => entries.push((rest.to_string(), Some(node.clone()))),
+        }
+    }
+    entries
+}
+
+/// `path`の下にデバイスノードがあるか（ディレクトリとして存在するか）
+pub fn is_directory(path: &str) -> bool {
+    if path.is_empty() {
+        return true;
+    }
+    let prefix = format!("{}/", path);
+    REGISTRY.read().nodes.range(prefix.clone()..).next().is_some_and(|(name, _)| name.starts_with(&prefix))
+}
+
+/// 登録されているデバイスの一覧
+pub fn devices() -> Vec<DeviceInfo> {
+    REGISTRY.read().nodes.values()
+        .map(|node| DeviceInfo {
+            name: node.name.clone(),
+            file_type: node.file_type(),
+            major: node.major,
+            minor: node.minor,
+            size: node.size(),
+            partition_type: node.partition.as_ref().map(|partition| partition.partition_type),
+        })
+        .collect()
+}
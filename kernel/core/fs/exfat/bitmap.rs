@@ -0,0 +1,281 @@
+// exFAT 割り当てビットマップ
+//
+// クラスタヒープの使用状況を1クラスタ1ビットで管理する（ビット0がクラスタ2）
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::directory::SystemEntry;
+use super::{ExfatVolume, read_bytes, write_bytes};
+
+/// メモリ上に保持した割り当てビットマップ
+#[derive(Debug)]
+pub struct AllocationBitmap {
+    /// ビットマップ本体
+    data: Vec<u8>,
+    /// ビットマップが格納されている領域（バイトオフセット, 長さ）
+    segments: Vec<(u64, usize)>,
+    /// クラスタ数
+    cluster_count: u32,
+    /// 空きクラスタ数
+    free: u32,
+    /// 次に探索を開始するクラスタ
+    next_hint: u32,
+}
+
+impl AllocationBitmap {
+    /// 空のビットマップ（ボリューム読み込み前のプレースホルダ）
+    pub fn empty() -> Self {
+        Self {
+            data: Vec::new(),
+            segments: Vec::new(),
+            cluster_count: 0,
+            free: 0,
+            next_hint: 2,
+        }
+    }
+    
+    /// ディスクから読み込んだ内容でビットマップを構築
+    pub fn new(mut data: Vec<u8>, segments: Vec<(u64, usize)>, cluster_count: u32) -> Self {
+        data.truncate((cluster_count as usize).div_ceil(8));
+        
+        // 範囲外の末尾ビットは数えない
+        let used: u32 = (0..cluster_count).filter(|&i| data[(i / 8) as usize] & (1 << (i % 8)) != 0).count() as u32;
+        Self {
+            data,
+            segments,
+            cluster_count,
+            free: cluster_count - used,
+            next_hint: 2,
+        }
+    }
+    
+    /// 空きクラスタ数
+    pub fn free_count(&self) -> u32 {
+        self.free
+    }
+    
+    /// クラスタが使用中かどうか
+    pub fn is_allocated(&self, cluster: u32) -> bool {
+        let index = cluster - 2;
+        self.data[(index / 8) as usize] & (1 << (index % 8)) != 0
+    }
+    
+    /// クラスタの使用状態を設定し、変更したバイト位置を返す
+    fn set(&mut self, cluster: u32, used: bool) -> usize {
+        let index = cluster - 2;
+        let byte = (index / 8) as usize;
+        let mask = 1u8 << (index % 8);
+        if used && self.data[byte] & mask == 0 {
+            self.data[byte] |= mask;
+            self.free -= 1;
+        } else if !used && self.data[byte] & mask != 0 {
+            self.data[byte] &= !mask;
+            self.free += 1;
+        }
+        byte
+    }
+    
+    /// `start`から`count`個のクラスタが全て空いているか
+    fn run_is_free(&self, start: u32, count: u32) -> bool {
+        start >= 2
+            && start as u64 + count as u64 <= self.cluster_count as u64 + 2
+            && (start..start + count).all(|cluster| !self.is_allocated(cluster))
+    }
+    
+    /// 空きクラスタを探す（`hint`直後、連続領域、分散の順に試す）
+    pub fn find_free(&self, count: u32, hint: Option<u32>, contiguous: bool) -> Option<Vec<u32>> {
+        if count == 0 {
+            return Some(Vec::new());
+        }
+        if count > self.free {
+            return None;
+        }
+        
+        if let Some(hint) = hint {
+            if self.run_is_free(hint, count) {
+                return Some((hint..hint + count).collect());
+            }
+        }
+        
+        let last = self.cluster_count + 1;
+        let start = if self.next_hint >= 2 && self.next_hint <= last { self.next_hint } else { 2 };
+        let order = (start..=last).chain(2..start);
+        
+        if contiguous {
+            let mut run_start = 0;
+            let mut run_len = 0;
+            let mut previous = 0;
+            for cluster in order.clone() {
+                if self.is_allocated(cluster) || (run_len > 0 && cluster != previous + 1) {
+                    run_len = 0;
+                }
+                if !self.is_allocated(cluster) {
+                    if run_len == 0 {
+                        run_start = cluster;
+                    }
+                    run_len += 1;
+                    if run_len == count {
+                        return Some((run_start..run_start + count).collect());
+                    }
+                }
+                previous = cluster;
+            }
+        }
+        
+        Some(order.filter(|&cluster| !self.is_allocated(cluster)).take(count as usize).collect())
+    }
+    
+    /// 変更したバイト範囲をディスク上の位置に対応付ける
+    fn locate(&self, first: usize, last: usize) -> Vec<(u64, core::ops::Range<usize>)> {
+        let mut result = Vec::new();
+        let mut base = 0usize;
+        for &(offset, len) in &self.segments {
+            let start = first.max(base);
+            let end = (last + 1).min(base + len);
+            if start < end {
+                result.push((offset + (start - base) as u64, start..end));
+            }
+            base += len;
+        }
+        result
+    }
+}
+
+impl ExfatVolume {
+    /// ルートディレクトリのエントリが指すシステムファイル（ビットマップ/アップケース）を読み込む
+    pub fn read_system_file(&self, entry: &SystemEntry) -> FsResult<Vec<u8>> {
+        let segments = self.system_file_segments(entry)?;
+        let mut data = Vec::with_capacity(entry.data_length as usize);
+        for &(offset, len) in &segments {
+            data.extend_from_slice(&read_bytes(&*self.device, offset, len)?);
+        }
+        data.truncate(entry.data_length as usize);
+        Ok(data)
+    }
+    
+    /// システムファイルが格納されている領域
+    ///
+    /// FATチェーンが記録されていない実装もあるため、チェーンが短ければ連続配置とみなす
+    fn system_file_segments(&self, entry: &SystemEntry) -> FsResult<Vec<(u64, usize)>> {
+        let needed = entry.data_length.div_ceil(self.cluster_size as u64) as usize;
+        if needed == 0 || !self.is_valid_cluster(entry.first_cluster) {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let clusters = match self.fat_chain(entry.first_cluster) {
+            Ok(chain) if chain.len() >= needed => chain[..needed].to_vec(),
+            _ => {
+                let last = entry.first_cluster as u64 + needed as u64 - 1;
+                if last > self.superblock.max_cluster() as u64 {
+                    return Err(FsError::CorruptedFs);
+                }
+                (entry.first_cluster..entry.first_cluster + needed as u32).collect()
+            },
+        };
+        Ok(self.segments_of(&clusters))
+    }
+    
+    /// 割り当てビットマップを読み込む
+    pub fn load_bitmap(&self, entry: &SystemEntry) -> FsResult<AllocationBitmap> {
+        let cluster_count = self.superblock.cluster_count;
+        if entry.data_length < (cluster_count as u64).div_ceil(8) {
+            log::warn!("exFAT: 割り当てビットマップが小さすぎます ({}バイト)", entry.data_length);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let data = self.read_system_file(entry)?;
+        let segments = self.system_file_segments(entry)?;
+        Ok(AllocationBitmap::new(data, segments, cluster_count))
+    }
+    
+    /// 変更したビットマップのバイト範囲をディスクへ書き戻す
+    fn flush_bitmap(&self, bitmap: &AllocationBitmap, first: usize, last: usize) -> FsResult<()> {
+        for (offset, range) in bitmap.locate(first, last) {
+            write_bytes(&*self.device, offset, &bitmap.data[range])?;
+        }
+        Ok(())
+    }
+    
+    /// クラスタを`count`個確保してビットマップに記録する（FATは更新しない）
+    pub fn allocate_clusters(&self, count: usize, hint: Option<u32>) -> FsResult<Vec<u32>> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let mut bitmap = self.bitmap.lock();
+        let clusters = bitmap.find_free(count as u32, hint, self.prefer_contiguous)
+            .filter(|found| found.len() == count)
+            .ok_or(FsError::OutOfSpace)?;
+        if clusters.is_empty() {
+            return Ok(clusters);
+        }
+        
+        let mut first = usize::MAX;
+        let mut last = 0;
+        for &cluster in &clusters {
+            let byte = bitmap.set(cluster, true);
+            first = first.min(byte);
+            last = last.max(byte);
+        }
+        bitmap.next_hint = clusters[clusters.len() - 1] + 1;
+        self.flush_bitmap(&bitmap, first, last)?;
+        
+        Ok(clusters)
+    }
+    
+    /// クラスタをビットマップ上で解放する
+    pub fn release_clusters(&self, clusters: &[u32]) -> FsResult<()> {
+        if clusters.is_empty() {
+            return Ok(());
+        }
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let mut bitmap = self.bitmap.lock();
+        let mut first = usize::MAX;
+        let mut last = 0;
+        for &cluster in clusters {
+            if !self.is_valid_cluster(cluster) {
+                return Err(FsError::CorruptedFs);
+            }
+            let byte = bitmap.set(cluster, false);
+            first = first.min(byte);
+            last = last.max(byte);
+        }
+        self.flush_bitmap(&bitmap, first, last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn bitmap_with(used: &[u32], cluster_count: u32) -> AllocationBitmap {
+        let mut data = vec![0u8; (cluster_count as usize).div_ceil(8)];
+        for &cluster in used {
+            let index = cluster - 2;
+            data[(index / 8) as usize] |= 1 << (index % 8);
+        }
+        AllocationBitmap::new(data, vec![(0, 4)], cluster_count)
+    }
+    
+    #[test]
+    fn find_free_prefers_hint_then_contiguous_run() {
+        // クラスタ2〜4と7が使用中
+        let bitmap = bitmap_with(&[2, 3, 4, 7], 20);
+        assert_eq!(bitmap.free_count(), 16);
+        
+        assert_eq!(bitmap.find_free(2, Some(5), true), Some(vec![5, 6]));
+        assert_eq!(bitmap.find_free(3, Some(5), true), Some(vec![8, 9, 10]));
+        assert_eq!(bitmap.find_free(3, Some(5), false), Some(vec![5, 6, 8]));
+        assert_eq!(bitmap.find_free(17, None, true), None);
+    }
+    
+    #[test]
+    fn locate_maps_bytes_across_segments() {
+        let mut bitmap = bitmap_with(&[], 64);
+        bitmap.segments = vec![(1000, 4), (5000, 4)];
+        assert_eq!(bitmap.locate(3, 5), vec![(1003, 3..4), (5000, 4..6)]);
+    }
+}
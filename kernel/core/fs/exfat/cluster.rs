@@ -0,0 +1,129 @@
+// exFAT クラスタ管理
+//
+// ストリームのクラスタ列（NoFatChainの連続配置またはFATチェーン）の取得、
+// 伸長と切り詰め
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::directory::{stream_flags, StreamEntry};
+use super::fat::END_OF_CHAIN;
+use super::{ExfatVolume, write_bytes};
+
+/// クラスタ列が物理的に連続しているか
+pub fn is_contiguous(clusters: &[u32]) -> bool {
+    clusters.windows(2).all(|pair| pair[1] == pair[0] + 1)
+}
+
+impl ExfatVolume {
+    /// ストリームを構成するクラスタ列
+    pub fn stream_clusters(&self, stream: &StreamEntry) -> FsResult<Vec<u32>> {
+        if stream.first_cluster == 0 {
+            if stream.data_length != 0 {
+                log::warn!("exFAT: データ長 {} に対してクラスタが割り当てられていません", stream.data_length);
+                return Err(FsError::CorruptedFs);
+            }
+            return Ok(Vec::new());
+        }
+        
+        let cluster_size = self.cluster_size as u64;
+        let needed = stream.data_length.div_ceil(cluster_size);
+        
+        if stream.no_fat_chain() {
+            // 連続配置ではデータ長からクラスタ数が決まり、FATの内容は無効
+            let last = stream.first_cluster as u64 + needed.max(1) - 1;
+            if !self.is_valid_cluster(stream.first_cluster) || last > self.superblock.max_cluster() as u64 {
+                log::warn!("exFAT: 連続配置のストリームがクラスタヒープを超えています");
+                return Err(FsError::CorruptedFs);
+            }
+            return Ok((stream.first_cluster..=last as u32).collect());
+        }
+        
+        let chain = self.fat_chain(stream.first_cluster)?;
+        if (chain.len() as u64) < needed {
+            log::warn!("exFAT: データ長に対してクラスタチェーンが短すぎます");
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(chain)
+    }
+    
+    /// ストリームに`count`個のクラスタを追加する
+    ///
+    /// 追加後も連続していればNoFatChainのまま（空のストリームなら新たに設定）にし、
+    /// 途切れる場合は既存の連続領域をFATチェーンに書き出してから繋ぐ
+    pub fn grow_stream(&self, stream: &mut StreamEntry, clusters: &mut Vec<u32>, count: usize, zero: bool) -> FsResult<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        
+        let hint = clusters.last().map(|&last| last + 1);
+        let added = self.allocate_clusters(count, hint)?;
+        
+        let result = (|| {
+            if zero {
+                let zeros = vec![0u8; self.cluster_size as usize];
+                for &cluster in &added {
+                    write_bytes(&*self.device, self.superblock.cluster_offset(cluster), &zeros)?;
+                }
+            }
+            
+            let was_contiguous = clusters.is_empty() || stream.no_fat_chain();
+            let mut combined = clusters.clone();
+            combined.extend_from_slice(&added);
+            
+            if was_contiguous && is_contiguous(&combined) {
+                stream.flags |= stream_flags::NO_FAT_CHAIN;
+            } else if stream.no_fat_chain() || clusters.is_empty() {
+                // 連続配置をやめるときは既存部分も含めてチェーン全体を書く
+                self.write_fat_chain(&combined)?;
+                stream.flags &= !stream_flags::NO_FAT_CHAIN;
+            } else {
+                self.write_fat_chain(&added)?;
+                self.set_fat_entry(clusters[clusters.len() - 1], added[0])?;
+            }
+            Ok(())
+        })();
+        
+        if let Err(e) = result {
+            self.release_clusters(&added)?;
+            return Err(e);
+        }
+        
+        if clusters.is_empty() {
+            stream.first_cluster = added[0];
+        }
+        stream.flags |= stream_flags::ALLOCATION_POSSIBLE;
+        clusters.extend(added);
+        Ok(())
+    }
+    
+    /// ストリームのクラスタを先頭`keep`個だけ残して解放する
+    ///
+    /// 呼び出し側は先にエントリセットのデータ長を更新しておくこと
+    pub fn shrink_stream(&self, stream: &mut StreamEntry, clusters: &[u32], keep: usize) -> FsResult<()> {
+        if keep >= clusters.len() {
+            return Ok(());
+        }
+        
+        if keep == 0 {
+            stream.first_cluster = 0;
+            stream.flags &= !stream_flags::NO_FAT_CHAIN;
+        } else if !stream.no_fat_chain() {
+            self.set_fat_entry(clusters[keep - 1], END_OF_CHAIN)?;
+        }
+        self.release_clusters(&clusters[keep..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn contiguity_check() {
+        assert!(is_contiguous(&[]));
+        assert!(is_contiguous(&[5]));
+        assert!(is_contiguous(&[5, 6, 7]));
+        assert!(!is_contiguous(&[5, 7]));
+        assert!(!is_contiguous(&[6, 5]));
+    }
+}
@@ -0,0 +1,899 @@
+// exFAT ディレクトリ実装
+//
+// File/Stream Extension/File Name エントリセット、アップケーステーブル、
+// ディレクトリエントリの作成/削除/名前変更とディレクトリハンドル
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, Metadata, Permissions, FileHandle, DirHandle, DirEntry};
+use super::file::ExfatFileHandle;
+use super::{ExfatVolume, ExfatNode, read_bytes, write_bytes, current_time};
+
+/// ディレクトリエントリのサイズ
+pub const ENTRY_SIZE: usize = 32;
+
+/// File Nameエントリ1つに格納できるUTF-16文字数
+pub const NAME_CHARS_PER_ENTRY: usize = 15;
+
+/// ファイル名の最大長（UTF-16単位）
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// ディレクトリの最大サイズ（仕様上の上限 256MiB）
+const MAX_DIRECTORY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// エントリタイプ
+pub mod entry_type {
+    /// ディレクトリの終端
+    pub const END_OF_DIRECTORY: u8 = 0x00;
+    /// 使用中ビット
+    pub const IN_USE: u8 = 0x80;
+    /// 二次エントリ（Category）ビット
+    pub const SECONDARY: u8 = 0x40;
+    /// アロケーションビットマップ
+    pub const ALLOCATION_BITMAP: u8 = 0x81;
+    /// アップケーステーブル
+    pub const UPCASE_TABLE: u8 = 0x82;
+    /// ボリュームラベル
+    pub const VOLUME_LABEL: u8 = 0x83;
+    /// ファイル/ディレクトリ
+    pub const FILE: u8 = 0x85;
+    /// ストリーム拡張
+    pub const STREAM_EXTENSION: u8 = 0xC0;
+    /// ファイル名
+    pub const FILE_NAME: u8 = 0xC1;
+}
+
+/// ファイル属性
+pub mod attr {
+    /// 読み取り専用
+    pub const READ_ONLY: u16 = 0x0001;
+    /// 隠しファイル
+    pub const HIDDEN: u16 = 0x0002;
+    /// システムファイル
+    pub const SYSTEM: u16 = 0x0004;
+    /// ディレクトリ
+    pub const DIRECTORY: u16 = 0x0010;
+    /// アーカイブ
+    pub const ARCHIVE: u16 = 0x0020;
+}
+
+/// 二次エントリのフラグ（GeneralSecondaryFlags）
+pub mod stream_flags {
+    /// クラスタ割り当てを持ちうる
+    pub const ALLOCATION_POSSIBLE: u8 = 0x01;
+    /// クラスタが連続しておりFATチェーンを使わない
+    pub const NO_FAT_CHAIN: u8 = 0x02;
+}
+
+/// exFATタイムスタンプの起点（1980年1月1日）のUNIX時刻
+const EXFAT_EPOCH: u64 = 315_532_800;
+
+/// リトルエンディアンのu32を読み込む
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// リトルエンディアンのu64を読み込む
+fn le_u64(data: &[u8], offset: usize) -> u64 {
+    (le_u32(data, offset) as u64) | ((le_u32(data, offset + 4) as u64) << 32)
+}
+
+/// 16ビットのローテート加算チェックサム（SetChecksum/NameHash共通）
+fn checksum16(sum: u16, byte: u8) -> u16 {
+    sum.rotate_right(1).wrapping_add(byte as u16)
+}
+
+/// エントリセットのSetChecksumを計算（先頭エントリの2〜3バイト目を除く）
+pub fn set_checksum(data: &[u8]) -> u16 {
+    data.iter().enumerate().fold(0u16, |sum, (i, &byte)| {
+        if i == 2 || i == 3 { sum } else { checksum16(sum, byte) }
+    })
+}
+
+/// アップケーステーブルのTableChecksumを計算
+pub fn table_checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |sum, &byte| sum.rotate_right(1).wrapping_add(byte as u32))
+}
+
+/// 暦日からUNIX日数を計算
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// UNIX日数から暦日（年, 月, 日）を計算
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// exFATタイムスタンプ（FAT形式の日付/時刻、10ミリ秒単位、UTCオフセット）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamp {
+    /// 日付（上位16ビット）と時刻（下位16ビット）
+    pub raw: u32,
+    /// 10ミリ秒単位の補正（0〜199）
+    pub ten_ms: u8,
+    /// UTCオフセット（最上位ビットが有効フラグ、残り7ビットが15分単位の符号付き値）
+    pub utc_offset: u8,
+}
+
+impl Timestamp {
+    /// UNIX時刻から作成（UTCオフセット0として記録）
+    pub fn from_unix(timestamp: u64) -> Self {
+        let timestamp = timestamp.max(EXFAT_EPOCH);
+        let days = (timestamp / 86400) as i64;
+        let secs = timestamp % 86400;
+        let (year, month, day) = civil_from_days(days);
+        if year > 2107 {
+            return Self { raw: 0xFF9F_BF7D, ten_ms: 199, utc_offset: 0x80 };
+        }
+        
+        let date = (((year - 1980) as u32) << 9) | ((month as u32) << 5) | day as u32;
+        let time = (((secs / 3600) as u32) << 11) | ((((secs / 60) % 60) as u32) << 5) | ((secs % 60) / 2) as u32;
+        Self {
+            raw: (date << 16) | time,
+            ten_ms: ((secs % 2) * 100) as u8,
+            utc_offset: 0x80,
+        }
+    }
+    
+    /// UNIX時刻に変換（UTCオフセットが無効ならUTCとして扱う）
+    pub fn to_unix(self) -> u64 {
+        let date = self.raw >> 16;
+        let time = self.raw & 0xFFFF;
+        if date == 0 {
+            return 0;
+        }
+        
+        let year = 1980 + (date >> 9) as i64;
+        let month = ((date >> 5) & 0x0F).clamp(1, 12) as i64;
+        let day = (date & 0x1F).max(1) as i64;
+        let hours = (time >> 11) as i64;
+        let minutes = ((time >> 5) & 0x3F) as i64;
+        let seconds = (time & 0x1F) as i64 * 2 + (self.ten_ms.min(199) / 100) as i64;
+        
+        let mut total = days_from_civil(year, month, day) * 86400 + hours * 3600 + minutes * 60 + seconds;
+        if self.utc_offset & 0x80 != 0 {
+            // 7ビットの符号付き値（15分単位）を符号拡張する
+            let quarters = ((self.utc_offset << 1) as i8 >> 1) as i64;
+            total -= quarters * 15 * 60;
+        }
+        total.max(0) as u64
+    }
+}
+
+/// Fileエントリ（0x85）の内容
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileEntry {
+    /// ファイル属性
+    pub attributes: u16,
+    /// 作成日時
+    pub created: Timestamp,
+    /// 最終更新日時
+    pub modified: Timestamp,
+    /// 最終アクセス日時（10ミリ秒単位は持たない）
+    pub accessed: Timestamp,
+}
+
+impl FileEntry {
+    /// 更新日時とアクセス日時を設定
+    pub fn touch(&mut self, now: u64) {
+        self.modified = Timestamp::from_unix(now);
+        self.accessed = Timestamp { ten_ms: 0, ..self.modified };
+    }
+}
+
+/// Stream Extensionエントリ（0xC0）の内容
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamEntry {
+    /// GeneralSecondaryFlags
+    pub flags: u8,
+    /// アップケースした名前のハッシュ
+    pub name_hash: u16,
+    /// 書き込み済みデータの長さ（これ以降は0として読む）
+    pub valid_data_length: u64,
+    /// 先頭クラスタ（割り当てがなければ0）
+    pub first_cluster: u32,
+    /// データ長（割り当て済みクラスタで表現される論理サイズ）
+    pub data_length: u64,
+}
+
+impl StreamEntry {
+    /// クラスタが連続しておりFATチェーンを持たないか
+    pub fn no_fat_chain(&self) -> bool {
+        self.flags & stream_flags::NO_FAT_CHAIN != 0
+    }
+}
+
+/// 1つのファイル/ディレクトリを表すエントリセット
+#[derive(Debug, Clone)]
+pub struct EntrySet {
+    /// ファイル名
+    pub name: String,
+    /// Fileエントリ
+    pub file: FileEntry,
+    /// Stream Extensionエントリ
+    pub stream: StreamEntry,
+    /// File Nameエントリより後ろの二次エントリ（ベンダー拡張などをそのまま保持）
+    pub extra: Vec<u8>,
+    /// ディレクトリ内の先頭スロット番号
+    pub first_slot: usize,
+}
+
+impl EntrySet {
+    /// 新しいエントリセットを作成
+    pub fn new(name: &str, attributes: u16, now: u64, upcase: &UpcaseTable) -> FsResult<Self> {
+        validate_name(name)?;
+        let time = Timestamp::from_unix(now);
+        Ok(Self {
+            name: name.to_string(),
+            file: FileEntry {
+                attributes,
+                created: time,
+                modified: time,
+                accessed: Timestamp { ten_ms: 0, ..time },
+            },
+            stream: StreamEntry {
+                flags: stream_flags::ALLOCATION_POSSIBLE,
+                name_hash: upcase.name_hash(name),
+                ..Default::default()
+            },
+            extra: Vec::new(),
+            first_slot: 0,
+        })
+    }
+    
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        self.file.attributes & attr::DIRECTORY != 0
+    }
+    
+    /// 名前を変更（NameHashも更新）
+    pub fn set_name(&mut self, name: &str, upcase: &UpcaseTable) -> FsResult<()> {
+        validate_name(name)?;
+        self.name = name.to_string();
+        self.stream.name_hash = upcase.name_hash(name);
+        Ok(())
+    }
+    
+    /// セットを構成するエントリ数
+    pub fn entry_count(&self) -> usize {
+        let units = self.name.encode_utf16().count();
+        2 + units.div_ceil(NAME_CHARS_PER_ENTRY) + self.extra.len() / ENTRY_SIZE
+    }
+    
+    /// エントリセットをパース（`data`はFileエントリから始まること）
+    ///
+    /// エントリ構成やSetChecksumが不正ならNoneを返す
+    pub fn parse(data: &[u8], first_slot: usize) -> Option<Self> {
+        if data.len() < ENTRY_SIZE * 3 || data[0] != entry_type::FILE {
+            return None;
+        }
+        
+        let secondary_count = data[1] as usize;
+        let total = (secondary_count + 1) * ENTRY_SIZE;
+        if secondary_count < 2 || data.len() < total {
+            return None;
+        }
+        
+        let data = &data[..total];
+        let stored = u16::from_le_bytes([data[2], data[3]]);
+        if stored != set_checksum(data) {
+            log::warn!("exFAT: エントリセットのチェックサムが一致しません (スロット {})", first_slot);
+            return None;
+        }
+        
+        let stream_raw = &data[ENTRY_SIZE..ENTRY_SIZE * 2];
+        if stream_raw[0] != entry_type::STREAM_EXTENSION {
+            return None;
+        }
+        
+        let name_length = stream_raw[3] as usize;
+        let name_entries = name_length.div_ceil(NAME_CHARS_PER_ENTRY);
+        if name_length == 0 || secondary_count < 1 + name_entries {
+            return None;
+        }
+        
+        let mut units = Vec::with_capacity(name_entries * NAME_CHARS_PER_ENTRY);
+        for index in 0..name_entries {
+            let raw = &data[(2 + index) * ENTRY_SIZE..(3 + index) * ENTRY_SIZE];
+            if raw[0] != entry_type::FILE_NAME {
+                return None;
+            }
+            units.extend(raw[2..].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])));
+        }
+        units.truncate(name_length);
+        
+        let stamp = |offset: usize, ten_ms: Option<usize>, utc: usize| Timestamp {
+            raw: le_u32(data, offset),
+            ten_ms: ten_ms.map(|i| data[i]).unwrap_or(0),
+            utc_offset: data[utc],
+        };
+        
+        Some(Self {
+            name: String::from_utf16_lossy(&units),
+            file: FileEntry {
+                attributes: u16::from_le_bytes([data[4], data[5]]),
+                created: stamp(8, Some(20), 22),
+                modified: stamp(12, Some(21), 23),
+                accessed: stamp(16, None, 24),
+            },
+            stream: StreamEntry {
+                flags: stream_raw[1],
+                name_hash: u16::from_le_bytes([stream_raw[4], stream_raw[5]]),
+                valid_data_length: le_u64(stream_raw, 8),
+                first_cluster: le_u32(stream_raw, 20),
+                data_length: le_u64(stream_raw, 24),
+            },
+            extra: data[(2 + name_entries) * ENTRY_SIZE..].to_vec(),
+            first_slot,
+        })
+    }
+    
+    /// ディスク上の形式にシリアライズ（SetChecksumを再計算）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let units: Vec<u16> = self.name.encode_utf16().collect();
+        let count = self.entry_count();
+        let mut data = vec![0u8; count * ENTRY_SIZE];
+        
+        let file = &mut data[..ENTRY_SIZE];
+        file[0] = entry_type::FILE;
+        file[1] = (count - 1) as u8;
+        file[4..6].copy_from_slice(&self.file.attributes.to_le_bytes());
+        file[8..12].copy_from_slice(&self.file.created.raw.to_le_bytes());
+        file[12..16].copy_from_slice(&self.file.modified.raw.to_le_bytes());
+        file[16..20].copy_from_slice(&self.file.accessed.raw.to_le_bytes());
+        file[20] = self.file.created.ten_ms;
+        file[21] = self.file.modified.ten_ms;
+        file[22] = self.file.created.utc_offset;
+        file[23] = self.file.modified.utc_offset;
+        file[24] = self.file.accessed.utc_offset;
+        
+        let stream = &mut data[ENTRY_SIZE..ENTRY_SIZE * 2];
+        stream[0] = entry_type::STREAM_EXTENSION;
+        stream[1] = self.stream.flags;
+        stream[3] = units.len() as u8;
+        stream[4..6].copy_from_slice(&self.stream.name_hash.to_le_bytes());
+        stream[8..16].copy_from_slice(&self.stream.valid_data_length.to_le_bytes());
+        stream[20..24].copy_from_slice(&self.stream.first_cluster.to_le_bytes());
+        stream[24..32].copy_from_slice(&self.stream.data_length.to_le_bytes());
+        
+        for (index, chunk) in units.chunks(NAME_CHARS_PER_ENTRY).enumerate() {
+            let raw = &mut data[(2 + index) * ENTRY_SIZE..(3 + index) * ENTRY_SIZE];
+            raw[0] = entry_type::FILE_NAME;
+            for (i, unit) in chunk.iter().enumerate() {
+                raw[2 + i * 2..4 + i * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+        
+        let extra_start = data.len() - self.extra.len();
+        data[extra_start..].copy_from_slice(&self.extra);
+        
+        let checksum = set_checksum(&data);
+        data[2..4].copy_from_slice(&checksum.to_le_bytes());
+        data
+    }
+}
+
+/// ファイル名として使用できるか検証
+pub fn validate_name(name: &str) -> FsResult<()> {
+    let length = name.encode_utf16().count();
+    if length == 0 || length > MAX_NAME_LENGTH || name == "." || name == ".." {
+        return Err(FsError::InvalidData);
+    }
+    
+    let invalid = |c: char| (c as u32) < 0x20 || matches!(c, '"' | '*' | '/' | ':' | '<' | '>' | '?' | '\\' | '|');
+    if name.chars().any(invalid) {
+        return Err(FsError::InvalidData);
+    }
+    Ok(())
+}
+
+/// アップケーステーブル（大文字小文字を区別しない比較に使用）
+pub struct UpcaseTable {
+    /// UTF-16コード単位ごとの大文字
+    map: Vec<u16>,
+}
+
+impl UpcaseTable {
+    /// ASCIIのみを大文字化するテーブル（ボリュームのテーブルが読めない場合の代替）
+    pub fn ascii() -> Self {
+        let map = (0..=0xFFFFu32)
+            .map(|c| if (b'a' as u32..=b'z' as u32).contains(&c) { (c - 0x20) as u16 } else { c as u16 })
+            .collect();
+        Self { map }
+    }
+    
+    /// ディスク上のテーブルを読み込む（0xFFFFに続く値は恒等写像の長さとして展開する）
+    pub fn parse(data: &[u8], checksum: u32) -> FsResult<Self> {
+        if data.len() % 2 != 0 {
+            return Err(FsError::CorruptedFs);
+        }
+        if table_checksum(data) != checksum {
+            log::warn!("exFAT: アップケーステーブルのチェックサムが一致しません");
+            return Err(FsError::ChecksumError);
+        }
+        
+        let mut map: Vec<u16> = (0..=0xFFFFu32).map(|c| c as u16).collect();
+        let mut index = 0usize;
+        let mut skip = false;
+        for unit in data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])) {
+            if index >= map.len() {
+                break;
+            }
+            if skip {
+                index += unit as usize;
+                skip = false;
+            } else if unit as usize == index {
+                index += 1;
+            } else if unit == 0xFFFF {
+                skip = true;
+            } else {
+                map[index] = unit;
+                index += 1;
+            }
+        }
+        
+        Ok(Self { map })
+    }
+    
+    /// UTF-16コード単位を大文字化
+    pub fn upcase(&self, unit: u16) -> u16 {
+        self.map[unit as usize]
+    }
+    
+    /// 名前のNameHashを計算
+    pub fn name_hash(&self, name: &str) -> u16 {
+        name.encode_utf16().fold(0u16, |sum, unit| {
+            let [low, high] = self.upcase(unit).to_le_bytes();
+            checksum16(checksum16(sum, low), high)
+        })
+    }
+    
+    /// 大文字小文字を区別せずに名前を比較
+    pub fn names_equal(&self, a: &str, b: &str) -> bool {
+        let mut left = a.encode_utf16();
+        let mut right = b.encode_utf16();
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => return true,
+                (Some(x), Some(y)) if self.upcase(x) == self.upcase(y) => continue,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// ルートディレクトリにある割り当てビットマップ/アップケーステーブルの位置
+#[derive(Debug, Clone, Copy)]
+pub struct SystemEntry {
+    /// フラグ（ビットマップでは何番目のFAT用か、アップケースではTableChecksum）
+    pub tag: u32,
+    /// 先頭クラスタ
+    pub first_cluster: u32,
+    /// データ長
+    pub data_length: u64,
+}
+
+/// パース済みのディレクトリ内容
+#[derive(Debug, Default)]
+pub struct DirContents {
+    /// ファイル/ディレクトリのエントリセット
+    pub sets: Vec<EntrySet>,
+    /// 割り当てビットマップ（ルートのみ）
+    pub bitmaps: Vec<SystemEntry>,
+    /// アップケーステーブル（ルートのみ）
+    pub upcase: Option<SystemEntry>,
+    /// ボリュームラベル（ルートのみ）
+    pub label: Option<String>,
+}
+
+/// ディレクトリデータをパース
+pub fn parse_directory(data: &[u8]) -> DirContents {
+    let mut contents = DirContents::default();
+    let slots = data.len() / ENTRY_SIZE;
+    let mut slot = 0;
+    
+    while slot < slots {
+        let raw = &data[slot * ENTRY_SIZE..(slot + 1) * ENTRY_SIZE];
+        let kind = raw[0];
+        
+        if kind == entry_type::END_OF_DIRECTORY {
+            break;
+        }
+        if kind & entry_type::IN_USE == 0 || kind & entry_type::SECONDARY != 0 {
+            // 削除済みエントリと、セットに属さない二次エントリは読み飛ばす
+            slot += 1;
+            continue;
+        }
+        
+        let system = || SystemEntry {
+            tag: le_u32(raw, 4),
+            first_cluster: le_u32(raw, 20),
+            data_length: le_u64(raw, 24),
+        };
+        
+        match kind {
+            entry_type::ALLOCATION_BITMAP => {
+                contents.bitmaps.push(SystemEntry { tag: (raw[1] & 1) as u32, ..system() });
+                slot += 1;
+            },
+            entry_type::UPCASE_TABLE => {
+                contents.upcase = Some(system());
+                slot += 1;
+            },
+            entry_type::VOLUME_LABEL => {
+                let length = (raw[1] as usize).min(11);
+                let units: Vec<u16> = raw[2..2 + length * 2]
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                contents.label = Some(String::from_utf16_lossy(&units));
+                slot += 1;
+            },
+            entry_type::FILE => match EntrySet::parse(&data[slot * ENTRY_SIZE..], slot) {
+                Some(set) => {
+                    slot += set.entry_count();
+                    contents.sets.push(set);
+                },
+                None => slot += 1,
+            },
+            // ボリュームGUIDなど、その他の一次エントリは二次エントリごと読み飛ばす
+            _ => slot += 1 + raw[1] as usize,
+        }
+    }
+    
+    contents
+}
+
+impl ExfatVolume {
+    /// ディレクトリにエントリセットを追加（必要ならディレクトリを伸ばす）
+    ///
+    /// 呼び出し側で`update_lock`を保持していること
+    fn insert_set(&self, dir: &ExfatNode, mut set: EntrySet) -> FsResult<ExfatNode> {
+        let (data, mut segments) = self.read_directory(dir)?;
+        let contents = parse_directory(&data);
+        if contents.sets.iter().any(|existing| self.upcase.names_equal(&existing.name, &set.name)) {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        // 使用中ビットが立っていないスロットが連続する範囲を探す（終端以降は全て空き）
+        let needed = set.entry_count();
+        let total_slots = data.len() / ENTRY_SIZE;
+        let end_slot = (0..total_slots)
+            .find(|&slot| data[slot * ENTRY_SIZE] == entry_type::END_OF_DIRECTORY)
+            .unwrap_or(total_slots);
+        
+        let mut run_start = 0;
+        let mut run_len = 0;
+        for slot in 0..total_slots {
+            if slot >= end_slot || data[slot * ENTRY_SIZE] & entry_type::IN_USE == 0 {
+                if run_len == 0 {
+                    run_start = slot;
+                }
+                run_len += 1;
+                if run_len == needed {
+                    break;
+                }
+            } else {
+                run_len = 0;
+            }
+        }
+        
+        if run_len < needed {
+            if run_len == 0 {
+                run_start = total_slots;
+            }
+            let missing = (needed - run_len) * ENTRY_SIZE;
+            let count = missing.div_ceil(self.cluster_size as usize);
+            if data.len() as u64 + (count as u64 * self.cluster_size as u64) > MAX_DIRECTORY_SIZE {
+                return Err(FsError::OutOfSpace);
+            }
+            segments = self.grow_directory(dir, count)?;
+        }
+        
+        set.first_slot = run_start;
+        let bytes = set.to_bytes();
+        let slots = (0..needed)
+            .map(|i| Self::slot_offset(&segments, run_start + i))
+            .collect::<FsResult<Vec<u64>>>()?;
+        self.write_slots(&slots, &bytes)?;
+        
+        Ok(ExfatNode { set, location: Some(slots) })
+    }
+    
+    /// ディレクトリにクラスタを追加して0で埋め、新しい構成領域を返す
+    fn grow_directory(&self, dir: &ExfatNode, count: usize) -> FsResult<Vec<(u64, usize)>> {
+        let mut stream = dir.set.stream;
+        let mut clusters = self.stream_clusters(&stream)?;
+        self.grow_stream(&mut stream, &mut clusters, count, true)?;
+        
+        // ディレクトリは常に割り当て済み領域全体が有効
+        let length = clusters.len() as u64 * self.cluster_size as u64;
+        stream.data_length = length;
+        stream.valid_data_length = length;
+        
+        if let Some(location) = &dir.location {
+            let mut set = dir.set.clone();
+            set.stream = stream;
+            self.write_set(location, &set)?;
+        }
+        
+        Ok(self.segments_of(&clusters))
+    }
+    
+    /// エントリセットのスロットを削除済みにする（使用中ビットを落とす）
+    fn remove_set(&self, location: &[u64]) -> FsResult<()> {
+        for &offset in location {
+            let kind = read_bytes(&*self.device, offset, 1)?[0];
+            write_bytes(&*self.device, offset, &[kind & !entry_type::IN_USE])?;
+        }
+        Ok(())
+    }
+    
+    /// ファイルまたはディレクトリを作成
+    pub(super) fn create_entry(&self, dir: &ExfatNode, name: &str, attributes: u16) -> FsResult<ExfatNode> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let dir = self.refresh(dir)?;
+        let mut set = EntrySet::new(name, attributes, current_time(), &self.upcase)?;
+        
+        if attributes & attr::DIRECTORY == 0 {
+            return self.insert_set(&dir, set);
+        }
+        
+        // 新しいディレクトリは0で埋めた1クラスタから始める（"."や".."はない）
+        let mut clusters = Vec::new();
+        self.grow_stream(&mut set.stream, &mut clusters, 1, true)?;
+        set.stream.data_length = self.cluster_size as u64;
+        set.stream.valid_data_length = self.cluster_size as u64;
+        
+        let result = self.insert_set(&dir, set);
+        if result.is_err() {
+            self.release_clusters(&clusters)?;
+        }
+        result
+    }
+    
+    /// エントリを削除（ディレクトリは空の場合のみ）
+    fn remove_entry(&self, dir: &ExfatNode, name: &str) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let node = self.lookup(&self.refresh(dir)?, name)?;
+        let location = node.location.as_ref().ok_or(FsError::PermissionDenied)?;
+        
+        if node.is_directory() && !self.list_directory(&node)?.is_empty() {
+            return Err(FsError::NotEmpty);
+        }
+        
+        // エントリを先に消すことで、途中で失敗しても二重参照ではなく未使用クラスタになる
+        let clusters = self.stream_clusters(&node.set.stream)?;
+        self.remove_set(location)?;
+        self.release_clusters(&clusters)
+    }
+    
+    /// エントリの名前を変更（`new_path`はボリュームルートからの絶対パス）
+    fn rename_entry(&self, dir: &ExfatNode, old_name: &str, new_path: &str) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let node = self.lookup(&self.refresh(dir)?, old_name)?;
+        let location = node.location.clone().ok_or(FsError::PermissionDenied)?;
+        let (stack, leaf) = self.resolve_parent(new_path)?;
+        validate_name(leaf)?;
+        
+        // exFATには".."エントリがないため、移動先までの経路に自身が含まれるかで循環を検出する
+        if node.is_directory() && stack.iter().any(|ancestor| ancestor.location.as_ref() == Some(&location)) {
+            return Err(FsError::InvalidData);
+        }
+        let new_dir = stack.last().ok_or(FsError::NotFound)?;
+        
+        // 既存のターゲットは置き換える（大文字小文字だけの変更なら自分自身）
+        let target = match self.lookup(new_dir, leaf) {
+            Ok(target) if target.location.as_ref() == Some(&location) => {
+                if target.set.name == leaf {
+                    return Ok(());
+                }
+                None
+            },
+            Ok(target) => {
+                match (node.is_directory(), target.is_directory()) {
+                    (true, false) => return Err(FsError::NotDirectory),
+                    (false, true) => return Err(FsError::IsDirectory),
+                    (true, true) if !self.list_directory(&target)?.is_empty() => return Err(FsError::NotEmpty),
+                    _ => {}
+                }
+                Some(target)
+            },
+            Err(FsError::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        
+        // 失敗時に元へ戻せるよう、消す前のスロットを保存しておく
+        let mut saved = Vec::new();
+        for &offset in location.iter().chain(target.iter().flat_map(|t| t.location.iter().flatten())) {
+            saved.push((offset, read_bytes(&*self.device, offset, ENTRY_SIZE)?));
+        }
+        if let Some(target_location) = target.as_ref().and_then(|t| t.location.as_ref()) {
+            self.remove_set(target_location)?;
+        }
+        self.remove_set(&location)?;
+        
+        let mut set = node.set.clone();
+        set.set_name(leaf, &self.upcase)?;
+        // 同じディレクトリ内なら削除したスロットを再利用できるよう、最新の状態を読み直す
+        if let Err(e) = self.refresh(new_dir).and_then(|new_dir| self.insert_set(&new_dir, set)) {
+            for (offset, raw) in &saved {
+                write_bytes(&*self.device, *offset, raw)?;
+            }
+            return Err(e);
+        }
+        
+        if let Some(target) = target {
+            self.release_clusters(&self.stream_clusters(&target.set.stream)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// exFATディレクトリハンドル
+///
+/// exFATのディレクトリには".."エントリがないため、ボリューム内の絶対パスを保持して辿る
+pub struct ExfatDirHandle {
+    /// 所属ボリューム
+    volume: Arc<ExfatVolume>,
+    /// ボリュームルートからのパス
+    path: String,
+}
+
+impl ExfatDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<ExfatVolume>, path: &str) -> Self {
+        Self { volume, path: path.to_string() }
+    }
+    
+    /// 最新の状態でディレクトリノードを取得
+    fn node(&self) -> FsResult<ExfatNode> {
+        self.volume.resolve(&self.path)
+    }
+    
+    /// ハンドルからの相対パスを絶対パスに変換
+    fn absolute(&self, path: &str) -> String {
+        if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.path.trim_end_matches('/'), path)
+        }
+    }
+}
+
+impl DirHandle for ExfatDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        Ok(self.volume.list_directory(&self.node()?)?
+            .into_iter()
+            .map(|child| DirEntry {
+                inode: child.inode(),
+                file_type: child.file_type(),
+                name: child.set.name,
+            })
+            .collect())
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let child = self.volume.lookup(&self.node()?, name)?;
+        Ok(DirEntry {
+            inode: child.inode(),
+            file_type: child.file_type(),
+            name: child.set.name,
+        })
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        let mut attributes = attr::ARCHIVE;
+        if !permissions.write {
+            attributes |= attr::READ_ONLY;
+        }
+        
+        let node = self.volume.create_entry(&self.node()?, name, attributes)?;
+        Ok(Arc::new(ExfatFileHandle::new(self.volume.clone(), node, true)))
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        let mut attributes = attr::DIRECTORY;
+        if !permissions.write {
+            attributes |= attr::READ_ONLY;
+        }
+        
+        self.volume.create_entry(&self.node()?, name, attributes)?;
+        Ok(())
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        self.volume.remove_entry(&self.node()?, name)
+    }
+    
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        self.volume.rename_entry(&self.node()?, old_name, &self.absolute(new_name))
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        // exFATにはシンボリックリンクを表現する手段がない
+        Err(FsError::NotSupported)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.node_metadata(&self.node()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn entry_set_roundtrip_and_checksum() {
+        let upcase = UpcaseTable::ascii();
+        let mut set = EntrySet::new("A rather long file name.txt", attr::ARCHIVE, 1_709_214_358, &upcase).unwrap();
+        set.stream.first_cluster = 7;
+        set.stream.data_length = 5000;
+        set.stream.valid_data_length = 4096;
+        
+        let mut data = set.to_bytes();
+        assert_eq!(data.len(), 4 * ENTRY_SIZE);
+        assert_eq!(data[1], 3);
+        data.extend_from_slice(&[0u8; ENTRY_SIZE]);
+        
+        let contents = parse_directory(&data);
+        assert_eq!(contents.sets.len(), 1);
+        let parsed = &contents.sets[0];
+        assert_eq!(parsed.name, "A rather long file name.txt");
+        assert_eq!(parsed.stream.first_cluster, 7);
+        assert_eq!((parsed.stream.data_length, parsed.stream.valid_data_length), (5000, 4096));
+        assert_eq!(parsed.stream.name_hash, upcase.name_hash("A RATHER LONG FILE NAME.TXT"));
+        assert_eq!(parsed.file.modified.to_unix(), 1_709_214_358);
+        
+        // 名前の一部が書き換わりチェックサムが合わなければセットごと無視される
+        data[2 * ENTRY_SIZE + 2] = b'B';
+        assert!(parse_directory(&data).sets.is_empty());
+    }
+    
+    #[test]
+    fn upcase_table_decompression() {
+        // 'a'..'c' を大文字化し、それ以外は恒等写像とする圧縮テーブル
+        let mut words: Vec<u16> = vec![0xFFFF, 0x61];
+        words.extend_from_slice(&[0x41, 0x42, 0x43]);
+        words.extend_from_slice(&[0xFFFF, 0xFFFF - 0x64 + 1]);
+        let data: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        
+        let table = UpcaseTable::parse(&data, table_checksum(&data)).unwrap();
+        assert_eq!(table.upcase(b'a' as u16), b'A' as u16);
+        assert_eq!(table.upcase(b'c' as u16), b'C' as u16);
+        assert_eq!(table.upcase(b'd' as u16), b'd' as u16);
+        assert!(table.names_equal("abc", "ABC"));
+        assert!(!table.names_equal("abd", "ABD"));
+        assert!(UpcaseTable::parse(&data, 0).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+// exFAT FATテーブル
+//
+// 32ビットFATエントリの読み書きとクラスタチェーンの走査
+// （exFATではFATは使用中のFATのみを更新し、空き管理は割り当てビットマップが担う）
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::{ExfatVolume, read_bytes, write_bytes};
+
+/// チェーン終端を示すFAT値
+pub const END_OF_CHAIN: u32 = 0xFFFF_FFFF;
+
+/// 不良クラスタを示すFAT値
+pub const BAD_CLUSTER: u32 = 0xFFFF_FFF7;
+
+/// 一度に読み込むFATエントリ数
+const SCAN_CHUNK: u32 = 4096;
+
+impl ExfatVolume {
+    /// FATエントリのデバイス上バイトオフセット
+    fn fat_entry_offset(&self, cluster: u32) -> u64 {
+        self.superblock.fat_byte_offset(self.superblock.active_fat()) + cluster as u64 * 4
+    }
+    
+    /// FATエントリを読み込み
+    pub fn get_fat_entry(&self, cluster: u32) -> FsResult<u32> {
+        if !self.is_valid_cluster(cluster) {
+            return Err(FsError::InvalidData);
+        }
+        let data = read_bytes(&*self.device, self.fat_entry_offset(cluster), 4)?;
+        Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+    }
+    
+    /// FATエントリを書き込み
+    pub fn set_fat_entry(&self, cluster: u32, value: u32) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if !self.is_valid_cluster(cluster) {
+            return Err(FsError::InvalidData);
+        }
+        write_bytes(&*self.device, self.fat_entry_offset(cluster), &value.to_le_bytes())
+    }
+    
+    /// `first`から始まるFATチェーンをたどってクラスタ列を返す
+    pub fn fat_chain(&self, first: u32) -> FsResult<Vec<u32>> {
+        let mut chain = Vec::new();
+        if first == 0 {
+            return Ok(chain);
+        }
+        
+        // 同じ範囲のFATを何度も読まないよう、直近に読んだ塊を保持する
+        let mut cached_base = u32::MAX;
+        let mut cached: Vec<u8> = Vec::new();
+        let mut current = first;
+        let limit = self.superblock.cluster_count as usize;
+        
+        loop {
+            if !self.is_valid_cluster(current) {
+                log::warn!("exFAT: クラスタチェーンが範囲外のクラスタ {} を指しています", current);
+                return Err(FsError::CorruptedFs);
+            }
+            if chain.len() >= limit {
+                log::warn!("exFAT: クラスタチェーンがループしています (先頭 {})", first);
+                return Err(FsError::CorruptedFs);
+            }
+            chain.push(current);
+            
+            let base = current - current % SCAN_CHUNK;
+            if base != cached_base {
+                let count = core::cmp::min(SCAN_CHUNK, self.superblock.max_cluster() + 1 - base);
+                cached = read_bytes(&*self.device, self.fat_entry_offset(base), count as usize * 4)?;
+                cached_base = base;
+            }
+            let index = ((current - base) * 4) as usize;
+            let next = u32::from_le_bytes([cached[index], cached[index + 1], cached[index + 2], cached[index + 3]]);
+            
+            match next {
+                END_OF_CHAIN => break,
+                BAD_CLUSTER => {
+                    log::warn!("exFAT: クラスタチェーンに不良クラスタが含まれています");
+                    return Err(FsError::CorruptedFs);
+                },
+                _ => current = next,
+            }
+        }
+        
+        Ok(chain)
+    }
+    
+    /// クラスタ列をFATチェーンとして書き込む（末尾は終端）
+    pub fn write_fat_chain(&self, clusters: &[u32]) -> FsResult<()> {
+        for (index, &cluster) in clusters.iter().enumerate() {
+            let next = clusters.get(index + 1).copied().unwrap_or(END_OF_CHAIN);
+            self.set_fat_entry(cluster, next)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+// exFAT ファイルハンドル実装
+//
+// ファイルデータの読み書き・サイズ変更（ValidDataLength以降は0として扱う）
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, Metadata, FileHandle};
+use super::directory::{attr, EntrySet};
+use super::{ExfatVolume, ExfatNode, read_bytes, write_bytes, current_time};
+
+impl ExfatVolume {
+    /// クラスタ列のうち、`index`から物理的に連続している数を返す
+    fn contiguous_run(clusters: &[u32], index: usize) -> usize {
+        let mut len = 1;
+        while index + len < clusters.len() && clusters[index + len] == clusters[index] + len as u32 {
+            len += 1;
+        }
+        len
+    }
+    
+    /// ストリーム上の`offset`から`len`バイトをデバイス上の連続領域ごとに処理する
+    fn for_each_run<F>(&self, clusters: &[u32], offset: u64, len: usize, mut f: F) -> FsResult<()>
+    where
+        F: FnMut(u64, core::ops::Range<usize>) -> FsResult<()>,
+    {
+        let cluster_size = self.cluster_size as u64;
+        let mut done = 0usize;
+        while done < len {
+            let position = offset + done as u64;
+            let index = (position / cluster_size) as usize;
+            let within = position % cluster_size;
+            if index >= clusters.len() {
+                log::warn!("exFAT: データ長に対してクラスタが足りません");
+                return Err(FsError::CorruptedFs);
+            }
+            
+            // 連続したクラスタはまとめて処理する
+            let run = Self::contiguous_run(clusters, index) as u64;
+            let chunk = core::cmp::min((run * cluster_size - within) as usize, len - done);
+            f(self.superblock.cluster_offset(clusters[index]) + within, done..done + chunk)?;
+            done += chunk;
+        }
+        Ok(())
+    }
+    
+    /// ファイルデータを読み込み
+    fn read_file(&self, set: &EntrySet, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let size = set.stream.data_length;
+        if offset >= size {
+            return Ok(0);
+        }
+        
+        let to_read = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        // ValidDataLength以降はディスクに書かれていないため0を返す
+        let on_disk = core::cmp::min(to_read as u64, set.stream.valid_data_length.saturating_sub(offset)) as usize;
+        
+        let clusters = self.stream_clusters(&set.stream)?;
+        self.for_each_run(&clusters, offset, on_disk, |device_offset, range| {
+            let data = read_bytes(&*self.device, device_offset, range.len())?;
+            buffer[range].copy_from_slice(&data);
+            Ok(())
+        })?;
+        buffer[on_disk..to_read].fill(0);
+        
+        Ok(to_read)
+    }
+    
+    /// ストリームが`end`バイトを格納できるようクラスタを追加
+    fn ensure_capacity(&self, set: &mut EntrySet, clusters: &mut Vec<u32>, end: u64) -> FsResult<()> {
+        let cluster_size = self.cluster_size as u64;
+        let needed = end.div_ceil(cluster_size) as usize;
+        if clusters.len() < needed {
+            let count = needed - clusters.len();
+            self.grow_stream(&mut set.stream, clusters, count, false)?;
+        }
+        Ok(())
+    }
+    
+    /// ファイルへ書き込み、エントリセットを更新
+    fn write_file(&self, location: &[u64], data: &[u8], offset: u64) -> FsResult<usize> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let end = offset.checked_add(data.len() as u64).ok_or(FsError::OverflowError)?;
+        
+        let _guard = self.update_lock.lock();
+        let mut set = self.read_set(location)?;
+        if set.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
+        
+        let mut clusters = self.stream_clusters(&set.stream)?;
+        self.ensure_capacity(&mut set, &mut clusters, end)?;
+        // 確保したクラスタをエントリから辿れるよう、先にデータ長を伸ばしておく
+        set.stream.data_length = set.stream.data_length.max(end);
+        
+        let result = (|| {
+            // 書き込み位置がValidDataLengthより後ろなら、間をディスク上で0にする
+            let valid = set.stream.valid_data_length;
+            if offset > valid {
+                let zeros = vec![0u8; self.cluster_size as usize];
+                let gap = (offset - valid) as usize;
+                self.for_each_run(&clusters, valid, gap, |device_offset, range| {
+                    for start in range.clone().step_by(zeros.len()) {
+                        let len = core::cmp::min(zeros.len(), range.end - start);
+                        write_bytes(&*self.device, device_offset + (start - range.start) as u64, &zeros[..len])?;
+                    }
+                    Ok(())
+                })?;
+            }
+            
+            self.for_each_run(&clusters, offset, data.len(), |device_offset, range| {
+                write_bytes(&*self.device, device_offset, &data[range])
+            })
+        })();
+        
+        if result.is_ok() {
+            set.stream.valid_data_length = set.stream.valid_data_length.max(end);
+        }
+        set.file.attributes |= attr::ARCHIVE;
+        set.file.touch(current_time());
+        self.write_set(location, &set)?;
+        
+        result.map(|_| data.len())
+    }
+    
+    /// ファイルサイズを変更
+    ///
+    /// 伸長時はクラスタを確保するだけでValidDataLengthは変えず、伸びた部分は0として読まれる
+    fn resize_file(&self, location: &[u64], new_size: u64) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let mut set = self.read_set(location)?;
+        if set.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
+        
+        let mut clusters = self.stream_clusters(&set.stream)?;
+        let cluster_size = self.cluster_size as u64;
+        let result = if new_size < set.stream.data_length {
+            // 切り詰めでは先にデータ長を書き込み、その後で不要なクラスタを解放する
+            let keep = new_size.div_ceil(cluster_size) as usize;
+            set.stream.data_length = new_size;
+            set.stream.valid_data_length = set.stream.valid_data_length.min(new_size);
+            self.write_set(location, &set)?;
+            self.shrink_stream(&mut set.stream, &clusters, keep)
+        } else {
+            self.ensure_capacity(&mut set, &mut clusters, new_size)
+                .map(|_| set.stream.data_length = new_size)
+        };
+        
+        set.file.attributes |= attr::ARCHIVE;
+        set.file.touch(current_time());
+        self.write_set(location, &set)?;
+        result
+    }
+}
+
+/// exFATファイルハンドル
+pub struct ExfatFileHandle {
+    /// 所属ボリューム
+    volume: Arc<ExfatVolume>,
+    /// エントリセットの位置
+    location: Vec<u64>,
+    /// 書き込み可能か
+    writable: bool,
+}
+
+impl ExfatFileHandle {
+    /// 新しいファイルハンドルを作成
+    pub(super) fn new(volume: Arc<ExfatVolume>, node: ExfatNode, writable: bool) -> Self {
+        let location = node.location.expect("ファイルはルート以外のエントリを持つ");
+        Self { volume, location, writable }
+    }
+}
+
+impl FileHandle for ExfatFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let set = self.volume.read_set(&self.location)?;
+        self.volume.read_file(&set, buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.write_file(&self.location, buffer, offset)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.volume.sync()
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.volume.read_set(&self.location)?.stream.data_length)
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.resize_file(&self.location, new_size)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let node = ExfatNode {
+            set: self.volume.read_set(&self.location)?,
+            location: Some(self.location.clone()),
+        };
+        self.volume.node_metadata(&node)
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        self.writable && !self.volume.read_only
+    }
+}
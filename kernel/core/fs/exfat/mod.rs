@@ -1,6 +1,7 @@
 // exFAT ファイルシステム実装
 //
-// Microsoft exFATファイルシステムの世界最速・最高性能実装
+// Microsoft exFATファイルシステムの実装（エントリセット、割り当てビットマップ、
+// アップケーステーブルによる大文字小文字を区別しない検索、NoFatChain連続ファイル）
 
 mod superblock;
 mod file;
@@ -8,521 +9,652 @@ mod directory;
 mod cluster;
 mod fat;
 mod bitmap;
-mod cache;       // 高性能キャッシュ
-mod allocator;   // 最適化アロケータ
-mod compressor;  // 透過的圧縮機能
 
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
-use spin::{RwLock, Mutex};
-use core::sync::atomic::{AtomicBool, Ordering};
+use spin::{Mutex, RwLock};
 use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, OpenMode, Filesystem};
-use self::superblock::ExfatSuperblock;
+use super::vfs::BlockDevice;
+use self::superblock::{ExfatSuperblock, BOOT_REGION_SECTORS, VOLUME_FLAGS_BYTE, PERCENT_IN_USE_BYTE, volume_flags};
+use self::bitmap::AllocationBitmap;
+use self::directory::{attr, stream_flags, EntrySet, FileEntry, StreamEntry, UpcaseTable, ENTRY_SIZE, MAX_NAME_LENGTH};
 use self::file::ExfatFileHandle;
 use self::directory::ExfatDirHandle;
-use self::cache::ExfatCache;
-use self::allocator::ExfatAllocator;
-use self::compressor::ExfatCompressor;
+
+/// パス解決時のディレクトリ階層上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// 現在時刻（UNIX秒）
+fn current_time() -> u64 {
+    crate::time::current_time_ns() / 1_000_000_000
+}
+
+/// デバイスからバイト単位で読み込み（ブロック境界をまたいでよい）
+fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+    
+    if data.len() < start + len {
+        return Err(FsError::IoError);
+    }
+    
+    Ok(data[start..start + len].to_vec())
+}
+
+/// デバイスへバイト単位で書き込み（端数ブロックは読み込んでから書き戻す）
+fn write_bytes(device: &dyn BlockDevice, offset: u64, data: &[u8]) -> FsResult<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + data.len() as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let start = (offset - first_block * block_size) as usize;
+    let end = start + data.len();
+    let span = ((last_block - first_block + 1) * block_size) as usize;
+    
+    // ブロック境界に揃っていればそのまま書き込む
+    if start == 0 && end == span {
+        return device.write_blocks(first_block, data);
+    }
+    
+    let mut buffer = device.read_blocks(first_block, last_block - first_block + 1)?;
+    if buffer.len() < span {
+        return Err(FsError::IoError);
+    }
+    buffer[start..end].copy_from_slice(data);
+    device.write_blocks(first_block, &buffer[..span])
+}
 
 /// exFAT最適化オプション
 #[derive(Debug, Clone, Copy)]
 pub struct ExfatOptions {
-    /// 先進的キャッシングを有効化
-    enable_advanced_caching: bool,
-    /// クラスタ最適化を有効化
-    enable_cluster_optimization: bool,
-    /// メタデータ先読みを有効化
-    enable_metadata_prefetch: bool,
-    /// 透過的圧縮を有効化
-    enable_transparent_compression: bool,
-    /// ジャーナリングを有効化
-    enable_journaling: bool,
-    /// キャッシュサイズ（エントリ数）
-    cache_size: usize,
-    /// 先読みウィンドウサイズ（クラスタ数）
-    prefetch_window: usize,
+    /// 連続したクラスタの割り当てを優先する（NoFatChainのまま伸ばせる機会が増える）
+    pub enable_cluster_optimization: bool,
 }
 
 impl Default for ExfatOptions {
     fn default() -> Self {
         Self {
-            enable_advanced_caching: true,
             enable_cluster_optimization: true,
-            enable_metadata_prefetch: true,
-            enable_transparent_compression: false,
-            enable_journaling: false,
-            cache_size: 8192,
-            prefetch_window: 64,
         }
     }
 }
 
-/// exFATファイルシステム
-pub struct ExfatFilesystem {
-    /// ファイルシステム名
-    name: String,
-    /// マウントされたデバイス
-    devices: RwLock<BTreeMap<String, Arc<dyn super::vfs::BlockDevice>>>,
-    /// スーパーブロックキャッシュ
-    superblocks: RwLock<BTreeMap<String, ExfatSuperblock>>,
-    /// 高性能キャッシュ
-    cache: RwLock<Option<ExfatCache>>,
-    /// クラスタアロケータ
-    allocator: Mutex<Option<ExfatAllocator>>,
-    /// 透過的圧縮エンジン
-    compressor: Mutex<Option<ExfatCompressor>>,
-    /// 最適化オプション
-    options: ExfatOptions,
-    /// 初期化済みフラグ
-    initialized: AtomicBool,
+/// 解決済みのファイル/ディレクトリ
+#[derive(Debug, Clone)]
+struct ExfatNode {
+    /// エントリセット（ルートディレクトリは合成したもの）
+    set: EntrySet,
+    /// セットを構成する各スロットのデバイス上バイトオフセット（ルートはNone）
+    location: Option<Vec<u64>>,
 }
 
-impl ExfatFilesystem {
-    /// 新しいexFATファイルシステムインスタンスを作成
-    pub fn new() -> Self {
-        Self::new_with_options(false, false)
+impl ExfatNode {
+    /// ディレクトリかどうか
+    fn is_directory(&self) -> bool {
+        self.set.is_directory()
     }
     
-    /// 最適化されたexFATファイルシステムインスタンスを作成
-    pub fn new_with_options(advanced_caching: bool, cluster_optimization: bool) -> Self {
-        let mut options = ExfatOptions::default();
-        options.enable_advanced_caching = advanced_caching;
-        options.enable_cluster_optimization = cluster_optimization;
-        
-        Self {
-            name: "exfat".to_string(),
-            devices: RwLock::new(BTreeMap::new()),
-            superblocks: RwLock::new(BTreeMap::new()),
-            cache: RwLock::new(None),
-            allocator: Mutex::new(None),
-            compressor: Mutex::new(None),
-            options,
-            initialized: AtomicBool::new(false),
-        }
+    /// ルートディレクトリかどうか
+    fn is_root(&self) -> bool {
+        self.location.is_none()
     }
     
-    /// 高度なオプションでexFATファイルシステムインスタンスを作成
-    pub fn new_advanced(options: ExfatOptions) -> Self {
-        Self {
-            name: "exfat".to_string(),
-            devices: RwLock::new(BTreeMap::new()),
-            superblocks: RwLock::new(BTreeMap::new()),
-            cache: RwLock::new(None),
-            allocator: Mutex::new(None),
-            compressor: Mutex::new(None),
-            options,
-            initialized: AtomicBool::new(false),
+    /// アイノード番号（exFATにはアイノードがないため、エントリ位置から生成）
+    fn inode(&self) -> u64 {
+        match &self.location {
+            Some(slots) => slots[0] / ENTRY_SIZE as u64,
+            None => 1,
         }
     }
     
-    // スーパーブロックを読み込み
-    fn read_superblock(&self, device: &dyn super::vfs::BlockDevice) -> FsResult<ExfatSuperblock> {
-        // exFATのブートセクタを読み込み
-        let data = device.read_block(0)?;
-        ExfatSuperblock::parse(&data)
+    /// ファイルタイプ
+    fn file_type(&self) -> FileType {
+        if self.is_directory() {
+            FileType::Directory
+        } else {
+            FileType::Regular
+        }
     }
-    
-    // 高性能初期化処理
-    fn initialize_internals(&self) -> FsResult<()> {
-        if self.initialized.load(Ordering::Acquire) {
-            return Ok(());
+}
+
+/// マウントされたexFATボリューム
+struct ExfatVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// ブートセクタ
+    superblock: ExfatSuperblock,
+    /// クラスタサイズ（バイト）
+    cluster_size: u32,
+    /// 読み取り専用マウントか
+    read_only: bool,
+    /// 連続割り当てを優先するか
+    prefer_contiguous: bool,
+    /// マウント時点で既にダーティだったか（その場合はアンマウント時にフラグを消さない）
+    was_dirty: bool,
+    /// ボリュームラベル
+    label: String,
+    /// アップケーステーブル
+    upcase: UpcaseTable,
+    /// 割り当てビットマップ
+    bitmap: Mutex<AllocationBitmap>,
+    /// ディレクトリ/ファイル更新の排他ロック
+    update_lock: Mutex<()>,
+}
+
+impl ExfatVolume {
+    /// デバイスからボリュームを構築
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, read_only: bool, prefer_contiguous: bool) -> FsResult<Self> {
+        let boot = read_bytes(&*device, 0, 512)?;
+        let superblock = ExfatSuperblock::parse(&boot)?;
+        let sector = superblock.bytes_per_sector as usize;
+        
+        let volume_bytes = superblock.volume_length * sector as u64;
+        if volume_bytes > device.total_blocks() * device.block_size() {
+            log::warn!("exFAT: ボリューム ({}バイト) がデバイスより大きいです", volume_bytes);
+            return Err(FsError::CorruptedFs);
         }
         
-        // 高性能キャッシュを初期化
-        if self.options.enable_advanced_caching {
-            let mut cache_guard = self.cache.write();
-            *cache_guard = Some(ExfatCache::new(self.options.cache_size));
+        // メインブート領域のチェックサムは12番目のセクタに繰り返し格納されている
+        let region = read_bytes(&*device, 0, sector * (BOOT_REGION_SECTORS + 1))?;
+        let checksum = superblock::boot_checksum(&region[..sector * BOOT_REGION_SECTORS]);
+        let stored = &region[sector * BOOT_REGION_SECTORS..];
+        if stored.chunks_exact(4).any(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]) != checksum) {
+            log::warn!("exFAT: ブート領域のチェックサムが一致しません");
+            return Err(FsError::ChecksumError);
         }
         
-        // クラスタアロケータを初期化
-        if self.options.enable_cluster_optimization {
-            let mut allocator_guard = self.allocator.lock();
-            *allocator_guard = Some(ExfatAllocator::new());
+        if superblock.has_media_failure() {
+            log::warn!("exFAT: ボリュームにメディア障害が記録されています");
+        }
+        let was_dirty = superblock.is_dirty();
+        if was_dirty {
+            log::warn!("exFAT: ボリュームが正常にアンマウントされていません。チェックを推奨します");
         }
         
-        // 透過的圧縮エンジンを初期化
-        if self.options.enable_transparent_compression {
-            let mut compressor_guard = self.compressor.lock();
-            *compressor_guard = Some(ExfatCompressor::new());
+        let mut volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            cluster_size: superblock.cluster_size(),
+            read_only,
+            prefer_contiguous,
+            was_dirty,
+            label: String::new(),
+            upcase: UpcaseTable::ascii(),
+            bitmap: Mutex::new(AllocationBitmap::empty()),
+            update_lock: Mutex::new(()),
+            superblock,
+        };
+        
+        if !volume.is_valid_cluster(volume.superblock.first_cluster_of_root_dir) {
+            log::warn!("exFAT: ルートクラスタ {} が範囲外です", volume.superblock.first_cluster_of_root_dir);
+            return Err(FsError::CorruptedFs);
         }
         
-        self.initialized.store(true, Ordering::Release);
-        Ok(())
+        // ルートディレクトリからビットマップとアップケーステーブルを探す
+        let (data, _) = volume.read_directory(&volume.root_node()?)?;
+        let contents = directory::parse_directory(&data);
+        
+        let active = volume.superblock.active_fat() as u32;
+        let bitmap_entry = contents.bitmaps.iter()
+            .find(|entry| entry.tag == active)
+            .copied()
+            .ok_or_else(|| {
+                log::warn!("exFAT: 割り当てビットマップが見つかりません");
+                FsError::CorruptedFs
+            })?;
+        volume.bitmap = Mutex::new(volume.load_bitmap(&bitmap_entry)?);
+        
+        let upcase_entry = contents.upcase.ok_or_else(|| {
+            log::warn!("exFAT: アップケーステーブルが見つかりません");
+            FsError::CorruptedFs
+        })?;
+        let table = volume.read_system_file(&upcase_entry)?;
+        volume.upcase = UpcaseTable::parse(&table, upcase_entry.tag)?;
+        volume.label = contents.label.unwrap_or_default();
+        
+        if !read_only {
+            volume.set_volume_dirty(true)?;
+        }
+        
+        log::info!("exFAT: ボリューム '{}' ({}クラスタ × {}バイト, 空き{})",
+                  volume.label, volume.superblock.cluster_count, volume.cluster_size, volume.bitmap.lock().free_count());
+        
+        Ok(volume)
     }
     
-    // キャッシュをフラッシュ
-    fn flush_cache(&self) -> FsResult<()> {
-        if let Some(cache) = &*self.cache.read() {
-            cache.flush_all()?;
-        }
-        Ok(())
+    /// クラスタ番号がクラスタヒープ内か
+    fn is_valid_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && cluster <= self.superblock.max_cluster()
     }
     
-    // 最適化されたファイルオープン処理
-    fn open_file_optimized(&self, path: &str, mode: OpenMode, device: &str) -> FsResult<Arc<ExfatFileHandle>> {
-        // ファイルパスを解析してエントリを取得
-        let parsed_path = self.parse_path(path)?;
-        let parent_dir = self.find_directory(&parsed_path.parent)?;
-        let filename = &parsed_path.name;
-
-        // ファイルエントリを検索
-        let file_entry = parent_dir.find_entry(filename)?;
-
-        // ファイルオープンモードに基づいて処理
-        let handle = match mode {
-            OpenMode::ReadOnly => {
-                ExfatFileHandle::new(file_entry, false, false)
+    /// ルートディレクトリのノード（FATチェーンで管理され、エントリセットを持たない）
+    fn root_node(&self) -> FsResult<ExfatNode> {
+        let first = self.superblock.first_cluster_of_root_dir;
+        let chain = self.fat_chain(first)?;
+        let length = chain.len() as u64 * self.cluster_size as u64;
+        
+        Ok(ExfatNode {
+            set: EntrySet {
+                name: "/".to_string(),
+                file: FileEntry { attributes: attr::DIRECTORY, ..Default::default() },
+                stream: StreamEntry {
+                    flags: stream_flags::ALLOCATION_POSSIBLE,
+                    first_cluster: first,
+                    data_length: length,
+                    valid_data_length: length,
+                    ..Default::default()
+                },
+                extra: Vec::new(),
+                first_slot: 0,
             },
-            OpenMode::WriteOnly => {
-                if !file_entry.metadata().permissions.write {
-                    return Err(FsError::AccessDenied);
-                }
-                ExfatFileHandle::new(file_entry, true, false)
-            },
-            OpenMode::ReadWrite => {
-                if !file_entry.metadata().permissions.write {
-                    return Err(FsError::AccessDenied);
-                }
-                ExfatFileHandle::new(file_entry, true, true)
-            },
-            OpenMode::Create => {
-                if parent_dir.find_entry(filename).is_ok() {
-                    // 既存のファイルを開く
-                    let entry = parent_dir.find_entry(filename)?;
-                    ExfatFileHandle::new(entry, true, true)
-                } else {
-                    // 新規ファイルを作成
-                    let entry = parent_dir.create_file(filename, Permissions::default())?;
-                    ExfatFileHandle::new(entry, true, true)
-                }
-            },
-            OpenMode::CreateNew => {
-                if parent_dir.find_entry(filename).is_ok() {
-                    return Err(FsError::AlreadyExists);
-                }
-                // 新規ファイルを作成
-                let entry = parent_dir.create_file(filename, Permissions::default())?;
-                ExfatFileHandle::new(entry, true, true)
-            },
-            OpenMode::Append => {
-                let mut handle = if parent_dir.find_entry(filename).is_ok() {
-                    // 既存のファイルを開く
-                    let entry = parent_dir.find_entry(filename)?;
-                    ExfatFileHandle::new(entry, true, true)?
-                } else {
-                    // 新規ファイルを作成
-                    let entry = parent_dir.create_file(filename, Permissions::default())?;
-                    ExfatFileHandle::new(entry, true, true)?
-                };
-                
-                // ファイル末尾にシーク
-                let size = handle.size()?;
-                handle.seek(size)?;
-                handle
-            },
-            OpenMode::Truncate => {
-                if parent_dir.find_entry(filename).is_ok() {
-                    // 既存のファイルを開いて切り詰め
-                    let entry = parent_dir.find_entry(filename)?;
-                    let mut handle = ExfatFileHandle::new(entry, true, true)?;
-                    handle.resize(0)?;
-                    handle
-                } else {
-                    return Err(FsError::NotFound);
-                }
-            },
-        };
-
-        Ok(Arc::new(handle))
+            location: None,
+        })
     }
     
-    // 最適化されたディレクトリオープン処理
-    fn open_directory_optimized(&self, path: &str, device: &str) -> FsResult<Arc<ExfatDirHandle>> {
-        // ディレクトリパスを解析
-        let parsed_path = self.parse_path(path)?;
-
-        // ルートディレクトリの場合
-        if parsed_path.is_root {
-            let root_dir = self.get_root_directory()?;
-            return Ok(Arc::new(ExfatDirHandle::new(root_dir)));
+    /// クラスタ列を連続領域（バイトオフセット, 長さ）の一覧にまとめる
+    fn segments_of(&self, clusters: &[u32]) -> Vec<(u64, usize)> {
+        let mut segments: Vec<(u64, usize)> = Vec::new();
+        let cluster_size = self.cluster_size as usize;
+        for &cluster in clusters {
+            let offset = self.superblock.cluster_offset(cluster);
+            match segments.last_mut() {
+                Some((start, len)) if *start + *len as u64 == offset => *len += cluster_size,
+                _ => segments.push((offset, cluster_size)),
+            }
         }
-
-        // 親ディレクトリを検索
-        let parent_dir = self.find_directory(&parsed_path.parent)?;
-
-        // 対象ディレクトリを検索
-        let dir_entry = parent_dir.find_entry(&parsed_path.name)?;
-        if dir_entry.metadata().file_type != FileType::Directory {
-            return Err(FsError::NotADirectory);
+        segments
+    }
+    
+    /// ディレクトリの内容と構成領域を読み込み
+    fn read_directory(&self, dir: &ExfatNode) -> FsResult<(Vec<u8>, Vec<(u64, usize)>)> {
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
         }
-
-        // ディレクトリハンドルを作成
-        let dir_handle = ExfatDirHandle::new(dir_entry);
-        Ok(Arc::new(dir_handle))
+        
+        let segments = self.segments_of(&self.stream_clusters(&dir.set.stream)?);
+        let mut data = Vec::with_capacity(segments.iter().map(|s| s.1).sum());
+        for &(offset, len) in &segments {
+            data.extend_from_slice(&read_bytes(&*self.device, offset, len)?);
+        }
+        Ok((data, segments))
     }
-}
-
-impl Filesystem for ExfatFilesystem {
-    fn name(&self) -> &str {
-        &self.name
+    
+    /// スロット番号をデバイス上のバイトオフセットに変換
+    fn slot_offset(segments: &[(u64, usize)], slot: usize) -> FsResult<u64> {
+        let mut position = slot * ENTRY_SIZE;
+        for &(offset, len) in segments {
+            if position < len {
+                return Ok(offset + position as u64);
+            }
+            position -= len;
+        }
+        Err(FsError::InvalidData)
     }
     
-    fn init(&self) -> FsResult<()> {
-        self.initialize_internals()
+    /// ディレクトリの子ノードを列挙
+    fn list_directory(&self, dir: &ExfatNode) -> FsResult<Vec<ExfatNode>> {
+        let (data, segments) = self.read_directory(dir)?;
+        directory::parse_directory(&data).sets
+            .into_iter()
+            .map(|set| {
+                let slots = (0..set.entry_count())
+                    .map(|i| Self::slot_offset(&segments, set.first_slot + i))
+                    .collect::<FsResult<Vec<u64>>>()?;
+                Ok(ExfatNode { set, location: Some(slots) })
+            })
+            .collect()
     }
     
-    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
-        // 初期化
-        self.initialize_internals()?;
-        
-        // デバイスが既にマウントされているかチェック
-        {
-            let devices = self.devices.read();
-            if devices.contains_key(device) {
-                return Err(FsError::AlreadyExists);
-            }
+    /// ディレクトリ内の名前を検索（NameHashで候補を絞り、アップケーステーブルで比較）
+    fn lookup(&self, dir: &ExfatNode, name: &str) -> FsResult<ExfatNode> {
+        if name.encode_utf16().count() > MAX_NAME_LENGTH {
+            return Err(FsError::NotFound);
         }
         
-        // ブロックデバイスをオープン
-        let block_device = super::vfs::open_block_device(device)?;
-        
-        // スーパーブロックを読み込み
-        let superblock = self.read_superblock(&*block_device)?;
-        
-        // exFATシグネチャをチェック
-        if !superblock.is_valid_signature() {
-            return Err(FsError::BadMagic);
-        }
+        let hash = self.upcase.name_hash(name);
+        self.list_directory(dir)?
+            .into_iter()
+            .find(|node| node.set.stream.name_hash == hash && self.upcase.names_equal(&node.set.name, name))
+            .ok_or(FsError::NotFound)
+    }
+    
+    /// パスをルートからのノード列に解決（末尾が目的のノード）
+    fn resolve_stack(&self, path: &str) -> FsResult<Vec<ExfatNode>> {
+        let mut stack: Vec<ExfatNode> = vec![self.root_node()?];
         
-        // キャッシュに保存
-        {
-            let mut devices = self.devices.write();
-            devices.insert(device.to_string(), block_device);
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if stack.len() > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
+            }
             
-            let mut superblocks = self.superblocks.write();
-            superblocks.insert(device.to_string(), superblock.clone());
-        }
-        
-        // メタデータ先読みを実行
-        if self.options.enable_metadata_prefetch {
-            self.prefetch_metadata(&superblock)?;
+            match component {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                },
+                name => {
+                    let current = stack.last().ok_or(FsError::NotFound)?;
+                    if !current.is_directory() {
+                        return Err(FsError::NotDirectory);
+                    }
+                    let next = self.lookup(current, name)?;
+                    stack.push(next);
+                },
+            }
         }
         
-        log::info!("高性能exFATファイルシステムをマウント: {} -> {}", device, mount_point);
-        
-        Ok(())
+        Ok(stack)
     }
     
-    fn unmount(&self, mount_point: &str) -> FsResult<()> {
-        // キャッシュをフラッシュ
-        self.flush_cache()?;
-        
-        // マウントポイントのデバイスを取得
-        let devices = self.devices.read();
-        let device = devices.get(mount_point).ok_or(FsError::NotFound)?;
-
-        // デバイスをアンマウント
-        device.unmount()?;
-
-        // マウント済みデバイスから削除
-        drop(devices);
-        let mut devices = self.devices.write();
-        devices.remove(mount_point);
-
-        // 統計情報をクリア
-        let mut stats = self.stats.write();
-        stats.remove(mount_point);
-
-        Ok(())
+    /// パスをノードに解決
+    fn resolve(&self, path: &str) -> FsResult<ExfatNode> {
+        self.resolve_stack(path)?.pop().ok_or(FsError::NotFound)
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        // 適切なデバイスを検索
-        let device_path = self.find_device_for_path(path)?;
-        
-        // 最適化されたファイルオープン処理を使用
-        let file_handle = self.open_file_optimized(path, mode, &device_path)?;
+    /// パスを親ディレクトリまでのノード列と最終要素に分割して解決
+    fn resolve_parent<'a>(&self, path: &'a str) -> FsResult<(Vec<ExfatNode>, &'a str)> {
+        let trimmed = path.trim_end_matches('/');
+        let (parent, leaf) = match trimmed.rfind('/') {
+            Some(pos) => (&trimmed[..pos], &trimmed[pos + 1..]),
+            None => ("", trimmed),
+        };
         
-        Ok(file_handle as Arc<dyn FileHandle>)
+        let stack = self.resolve_stack(parent)?;
+        if !stack.last().is_some_and(|dir| dir.is_directory()) {
+            return Err(FsError::NotDirectory);
+        }
+        Ok((stack, leaf))
     }
     
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        // 適切なデバイスを検索
-        let device_path = self.find_device_for_path(path)?;
-        
-        // 最適化されたディレクトリオープン処理を使用
-        let dir_handle = self.open_directory_optimized(path, &device_path)?;
+    /// エントリセットのスロットへ書き込み
+    fn write_slots(&self, location: &[u64], data: &[u8]) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if data.len() != location.len() * ENTRY_SIZE {
+            return Err(FsError::InvalidData);
+        }
         
-        Ok(dir_handle as Arc<dyn DirHandle>)
+        // 連続したスロットはまとめて書き込む
+        let mut index = 0;
+        while index < location.len() {
+            let mut run = 1;
+            while index + run < location.len() && location[index + run] == location[index] + (run * ENTRY_SIZE) as u64 {
+                run += 1;
+            }
+            write_bytes(&*self.device, location[index], &data[index * ENTRY_SIZE..(index + run) * ENTRY_SIZE])?;
+            index += run;
+        }
+        Ok(())
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        // キャッシュからメタデータを取得
-        if let Some(cache) = &*self.cache.read() {
-            if let Some(metadata) = cache.get_metadata(path) {
-                return Ok(metadata);
-            }
+    /// ディスク上のエントリセットを読み直す（削除済みや壊れていればハンドルは無効）
+    fn read_set(&self, location: &[u64]) -> FsResult<EntrySet> {
+        let mut data = Vec::with_capacity(location.len() * ENTRY_SIZE);
+        for &offset in location {
+            data.extend_from_slice(&read_bytes(&*self.device, offset, ENTRY_SIZE)?);
         }
         
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
-        
-        // デバイスを検索
-        let device_path = self.find_device_for_path(path)?;
-        
-        // ルートディレクトリの場合
-        if parsed_path.is_root {
-            // スーパーブロックからルートディレクトリのメタデータを取得
-            let superblocks = self.superblocks.read();
-            if let Some(superblock) = superblocks.get(&device_path) {
-                let root_metadata = Metadata {
-                    file_type: FileType::Directory,
-                    size: 0,
-                    allocated_size: 0,
-                    permissions: Permissions {
-                        read: true,
-                        write: true,
-                        execute: true,
-                    },
-                    created: superblock.volume_creation_time(),
-                    accessed: superblock.last_access_time(),
-                    modified: superblock.last_modified_time(),
-                    links: 1,
-                    uid: 0,
-                    gid: 0,
-                };
-                
-                return Ok(root_metadata);
-            }
+        match EntrySet::parse(&data, 0) {
+            Some(set) if set.entry_count() == location.len() => Ok(set),
+            _ => Err(FsError::StaleFileHandle),
         }
-        
-        // 親ディレクトリを開く
-        let parent_dir = self.open_directory_optimized(&parsed_path.parent, &device_path)?;
-        
-        // エントリを検索
-        let entry = parent_dir.find_entry(&parsed_path.name)?;
-        let metadata = entry.metadata();
-        
-        // メタデータをキャッシュに保存
-        if let Some(cache) = &mut *self.cache.write() {
-            cache.store_metadata(path.to_string(), metadata.clone());
+    }
+    
+    /// エントリセットを書き込み（セットの大きさが変わらないこと）
+    fn write_set(&self, location: &[u64], set: &EntrySet) -> FsResult<()> {
+        self.write_slots(location, &set.to_bytes())
+    }
+    
+    /// ノードをディスク上の最新状態で読み直す
+    fn refresh(&self, node: &ExfatNode) -> FsResult<ExfatNode> {
+        match &node.location {
+            Some(location) => Ok(ExfatNode {
+                set: self.read_set(location)?,
+                location: Some(location.clone()),
+            }),
+            None => self.root_node(),
         }
-        
-        Ok(metadata)
     }
     
-    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
-        // ファイルシステム情報を取得
-        let devices = self.devices.read();
-        let device = devices.get(mount_point).ok_or(FsError::NotFound)?;
-
-        // exFATファイルシステムの統計情報を収集
-        let fs_info = device.get_fs_info()?;
-
-        // 統計情報を更新
-        let mut stats = self.stats.write();
-        stats.insert(mount_point.to_string(), fs_info.clone());
-
-        Ok(fs_info)
+    /// ノードのメタデータを構築
+    fn node_metadata(&self, node: &ExfatNode) -> FsResult<Metadata> {
+        let file = &node.set.file;
+        let is_dir = node.is_directory();
+        let size = node.set.stream.data_length;
+        
+        Ok(Metadata {
+            inode: node.inode(),
+            file_type: node.file_type(),
+            size,
+            uid: 0,
+            gid: 0,
+            permissions: Permissions {
+                read: true,
+                write: !self.read_only && file.attributes & attr::READ_ONLY == 0,
+                execute: is_dir,
+            },
+            created: file.created.to_unix(),
+            accessed: file.accessed.to_unix(),
+            modified: file.modified.to_unix(),
+            links: 1,
+            block_size: self.cluster_size,
+            blocks: size.div_ceil(self.cluster_size as u64),
+        })
+    }
+    
+    /// ファイルシステム統計を取得
+    fn stats(&self) -> FsResult<FsStats> {
+        let free = self.bitmap.lock().free_count() as u64;
+        Ok(FsStats {
+            total_blocks: self.superblock.cluster_count as u64,
+            free_blocks: free,
+            available_blocks: free,
+            total_nodes: 0,
+            free_nodes: 0,
+            block_size: self.cluster_size,
+            max_filename_length: MAX_NAME_LENGTH as u32,
+        })
     }
     
+    /// ボリュームフラグのダーティビットを更新（ブートチェックサムの対象外）
+    fn set_volume_dirty(&self, dirty: bool) -> FsResult<()> {
+        let mut flags = self.superblock.volume_flags & !volume_flags::VOLUME_DIRTY;
+        if dirty || self.was_dirty {
+            flags |= volume_flags::VOLUME_DIRTY;
+        }
+        write_bytes(&*self.device, VOLUME_FLAGS_BYTE as u64, &flags.to_le_bytes())
+    }
+    
+    /// 使用率を書き戻してデバイスを同期
     fn sync(&self) -> FsResult<()> {
-        // キャッシュをフラッシュ
-        self.flush_cache()?;
-        
-        // すべてのデバイスを同期
-        let devices = self.devices.read();
-        for (_, device) in devices.iter() {
-            device.sync()?;
+        if !self.read_only {
+            let free = self.bitmap.lock().free_count() as u64;
+            let total = self.superblock.cluster_count as u64;
+            let percent = ((total - free) * 100 / total) as u8;
+            write_bytes(&*self.device, PERCENT_IN_USE_BYTE as u64, &[percent])?;
         }
-        
-        Ok(())
+        self.device.sync()
     }
 }
 
+/// exFATファイルシステム
+pub struct ExfatFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// 最適化オプション
+    options: ExfatOptions,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<ExfatVolume>>>,
+}
+
 impl ExfatFilesystem {
-    // メタデータ先読み処理
-    fn prefetch_metadata(&self, superblock: &ExfatSuperblock) -> FsResult<()> {
-        // 先読みが無効な場合は何もしない
-        if !self.options.enable_metadata_prefetch {
-            return Ok(());
+    /// 新しいexFATファイルシステムインスタンスを作成
+    pub fn new() -> Self {
+        Self::new_advanced(ExfatOptions::default())
+    }
+    
+    /// 最適化されたexFATファイルシステムインスタンスを作成
+    ///
+    /// ビットマップとアップケーステーブルは常にメモリ上に保持するため、
+    /// `advanced_caching`は互換性のためだけに受け取る
+    pub fn new_with_options(advanced_caching: bool, cluster_optimization: bool) -> Self {
+        let _ = advanced_caching;
+        Self::new_advanced(ExfatOptions {
+            enable_cluster_optimization: cluster_optimization,
+        })
+    }
+    
+    /// 高度なオプションでexFATファイルシステムインスタンスを作成
+    pub fn new_advanced(options: ExfatOptions) -> Self {
+        Self {
+            name: "exfat".to_string(),
+            options,
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<ExfatVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+
+    /// パスのファイルを作成してハンドルを返す
+    fn create_file(&self, volume: &Arc<ExfatVolume>, path: &str) -> FsResult<Arc<dyn FileHandle>> {
+        let (stack, leaf) = volume.resolve_parent(path)?;
+        let dir = stack.last().ok_or(FsError::NotFound)?;
+        let node = volume.create_entry(dir, leaf, attr::ARCHIVE)?;
+        Ok(Arc::new(ExfatFileHandle::new(volume.clone(), node, true)))
+    }
+}
+
+impl Filesystem for ExfatFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
         }
         
-        // FAT領域を先読み
-        let fat_start = superblock.fat_offset();
-        let fat_size = superblock.fat_size();
-        let sectors_per_cluster = superblock.sectors_per_cluster();
-        
-        // キャッシュに保存
-        if let Some(cache) = &mut *self.cache.write() {
-            // FAT領域の先頭部分をキャッシュに読み込み
-            let prefetch_size = core::cmp::min(
-                self.options.prefetch_window,
-                (fat_size / sectors_per_cluster) as usize
-            );
-            
-            for i in 0..prefetch_size {
-                let sector = fat_start + (i as u64) * sectors_per_cluster;
-                // セクタをキャッシュに読み込む（仮想的な実装）
-                cache.prefetch_sector(sector);
-            }
-            
-            // ルートディレクトリのエントリも先読み
-            let root_dir_cluster = superblock.root_directory_cluster();
-            if root_dir_cluster != 0 {
-                let root_dir_sector = superblock.cluster_to_sector(root_dir_cluster);
-                
-                // ルートディレクトリの最初の数クラスタを読み込み
-                for i in 0..core::cmp::min(4, self.options.prefetch_window) {
-                    let sector = root_dir_sector + (i as u64) * sectors_per_cluster;
-                    cache.prefetch_sector(sector);
-                }
+        let mut read_only = false;
+        for option in options.split(',').map(str::trim) {
+            match option {
+                "ro" => read_only = true,
+                "rw" => read_only = false,
+                _ => {}
             }
-            
-            log::debug!("exFATファイルシステムのメタデータを先読みしました");
         }
         
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = ExfatVolume::open(device, block_device, read_only, self.options.enable_cluster_optimization)?;
+        
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
+        
+        log::info!("exFATファイルシステムをマウント: {} -> {}", device, mount_point);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        if !volume.read_only {
+            volume.set_volume_dirty(false)?;
+        }
+        volume.sync()?;
+        volume.device.close()?;
+
+        log::info!("exFATファイルシステムをアンマウント: {} ({})", mount_point, volume.device_path);
         Ok(())
     }
     
-    // パスからデバイスを検索
-    fn find_device_for_path(&self, path: &str) -> FsResult<String> {
-        // マウントポイントからデバイスを検索
-        let devices = self.devices.read();
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let writable = mode != OpenMode::ReadOnly;
+        if writable && volume.read_only {
+            return Err(FsError::ReadOnly);
+        }
         
-        // 最長一致するマウントポイントを検索
-        let mut best_match = "";
-        let mut best_device = "";
+        let node = match volume.resolve(path) {
+            Ok(node) => node,
+            Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => {
+                return self.create_file(&volume, path);
+            },
+            Err(e) => return Err(e),
+        };
         
-        for (device_path, _) in devices.iter() {
-            // マウントポイントのパスを取得（実際のシステムでは別のマッピングテーブルから取得）
-            let mount_point = self.get_mount_point_for_device(device_path);
-            
-            // パスがマウントポイントから始まり、かつ今までの最長一致より長い場合
-            if path.starts_with(&mount_point) && mount_point.len() > best_match.len() {
-                best_match = mount_point;
-                best_device = device_path;
-            }
+        if mode == OpenMode::CreateNew {
+            return Err(FsError::AlreadyExists);
+        }
+        if node.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
+        if writable && node.set.file.attributes & attr::READ_ONLY != 0 {
+            return Err(FsError::PermissionDenied);
         }
         
-        if !best_device.is_empty() {
-            return Ok(best_device.to_string());
+        let handle = ExfatFileHandle::new(volume, node, writable);
+        if mode == OpenMode::Truncate {
+            handle.resize(0)?;
         }
+        Ok(Arc::new(handle))
+    }
+    
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
         
-        // マウントポイントが見つからない場合、デフォルトのデバイスを返す
-        if let Some((device_path, _)) = devices.iter().next() {
-            return Ok(device_path.clone());
+        if !node.is_directory() {
+            return Err(FsError::NotDirectory);
         }
         
-        Err(FsError::NotFound)
+        Ok(Arc::new(ExfatDirHandle::new(volume, path)))
     }
     
-    // デバイスパスからマウントポイントを取得（実際のシステムではVFSからのマッピングを使用）
-    fn get_mount_point_for_device(&self, device_path: &str) -> String {
-        // 本来はVFSからのマッピングを使用
-        // この実装ではシンプルにデバイスパスをそのまま返す
-        device_path.to_string()
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
+        volume.node_metadata(&node)
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        self.find_volume(mount_point)?.stats()
     }
-} 
\ No newline at end of file
+    
+    fn sync(&self) -> FsResult<()> {
+        for volume in self.volumes.read().values() {
+            volume.sync()?;
+        }
+        Ok(())
+    }
+}
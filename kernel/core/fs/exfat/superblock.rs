@@ -8,8 +8,12 @@ use super::super::{FsError, FsResult};
 /// exFATブートセクタのオフセット
 const EXFAT_SIGNATURE_OFFSET: usize = 3;
 const EXFAT_SIGNATURE: &[u8] = b"EXFAT   ";
-const BYTES_PER_SECTOR_OFFSET: usize = 108;
-const SECTORS_PER_CLUSTER_OFFSET: usize = 109;
+const BYTES_PER_SECTOR_SHIFT_OFFSET: usize = 108;
+const SECTORS_PER_CLUSTER_SHIFT_OFFSET: usize = 109;
+const NUMBER_OF_FATS_OFFSET: usize = 110;
+const PERCENT_IN_USE_OFFSET: usize = 112;
+const VOLUME_LENGTH_OFFSET: usize = 72;
+const BOOT_SIGNATURE_OFFSET: usize = 510;
 const CLUSTER_HEAP_OFFSET_OFFSET: usize = 88;
 const CLUSTER_COUNT_OFFSET: usize = 92;
 const FIRST_CLUSTER_OF_ROOT_DIR_OFFSET: usize = 96;
@@ -19,13 +23,37 @@ const VOLUME_FLAGS_OFFSET: usize = 106;
 const FAT_OFFSET_OFFSET: usize = 80;
 const FAT_LENGTH_OFFSET: usize = 84;
 
+/// ボリュームフラグのオフセット（ブートチェックサムの計算対象外）
+pub const VOLUME_FLAGS_BYTE: usize = VOLUME_FLAGS_OFFSET;
+/// 使用率のオフセット（ブートチェックサムの計算対象外）
+pub const PERCENT_IN_USE_BYTE: usize = PERCENT_IN_USE_OFFSET;
+
+/// ボリュームフラグ
+pub mod volume_flags {
+    /// 使用中のFAT/ビットマップ（0: 1番目, 1: 2番目）
+    pub const ACTIVE_FAT: u16 = 0x0001;
+    /// ボリュームがダーティ（正常にアンマウントされていない）
+    pub const VOLUME_DIRTY: u16 = 0x0002;
+    /// メディア障害が報告されている
+    pub const MEDIA_FAILURE: u16 = 0x0004;
+}
+
+/// メインブート領域のセクタ数（ブートセクタ、拡張ブートセクタ8個、OEM、予約）
+pub const BOOT_REGION_SECTORS: usize = 11;
+
 /// exFATスーパーブロック（ブートセクタから解析）
 #[derive(Debug, Clone)]
 pub struct ExfatSuperblock {
-    /// セクタあたりのバイト数（通常512）
+    /// セクタあたりのバイト数（512〜4096）
     pub bytes_per_sector: u16,
     /// クラスタあたりのセクタ数（2のべき乗）
-    pub sectors_per_cluster: u8,
+    pub sectors_per_cluster: u32,
+    /// ボリューム長（セクタ単位）
+    pub volume_length: u64,
+    /// FATとビットマップの数（1、TexFATでは2）
+    pub number_of_fats: u8,
+    /// 使用率（%、0xFFは不明）
+    pub percent_in_use: u8,
     /// クラスタヒープのオフセット（セクタ単位）
     pub cluster_heap_offset: u32,
     /// 総クラスタ数
@@ -59,12 +87,41 @@ impl ExfatSuperblock {
             return Err(FsError::BadMagic);
         }
         
-        let bytes_per_sector = u16::from_le_bytes([
-            data[BYTES_PER_SECTOR_OFFSET], 
-            data[BYTES_PER_SECTOR_OFFSET + 1]
+        let boot_signature = u16::from_le_bytes([
+            data[BOOT_SIGNATURE_OFFSET],
+            data[BOOT_SIGNATURE_OFFSET + 1],
         ]);
+        if boot_signature != 0xAA55 {
+            return Err(FsError::BadMagic);
+        }
+        
+        // セクタ長とクラスタ長は2の指数で格納されている
+        let bytes_per_sector_shift = data[BYTES_PER_SECTOR_SHIFT_OFFSET];
+        let sectors_per_cluster_shift = data[SECTORS_PER_CLUSTER_SHIFT_OFFSET];
+        if !(9..=12).contains(&bytes_per_sector_shift) || bytes_per_sector_shift + sectors_per_cluster_shift > 25 {
+            log::warn!("exFAT: 不正なセクタ/クラスタ長 (2^{}, 2^{})", bytes_per_sector_shift, sectors_per_cluster_shift);
+            return Err(FsError::CorruptedFs);
+        }
+        let bytes_per_sector = 1u16 << bytes_per_sector_shift;
+        let sectors_per_cluster = 1u32 << sectors_per_cluster_shift;
         
-        let sectors_per_cluster = data[SECTORS_PER_CLUSTER_OFFSET];
+        let volume_length = u64::from_le_bytes([
+            data[VOLUME_LENGTH_OFFSET],
+            data[VOLUME_LENGTH_OFFSET + 1],
+            data[VOLUME_LENGTH_OFFSET + 2],
+            data[VOLUME_LENGTH_OFFSET + 3],
+            data[VOLUME_LENGTH_OFFSET + 4],
+            data[VOLUME_LENGTH_OFFSET + 5],
+            data[VOLUME_LENGTH_OFFSET + 6],
+            data[VOLUME_LENGTH_OFFSET + 7],
+        ]);
+        
+        let number_of_fats = data[NUMBER_OF_FATS_OFFSET];
+        if number_of_fats != 1 && number_of_fats != 2 {
+            log::warn!("exFAT: 不正なFAT数 {}", number_of_fats);
+            return Err(FsError::CorruptedFs);
+        }
+        let percent_in_use = data[PERCENT_IN_USE_OFFSET];
         
         let cluster_heap_offset = u32::from_le_bytes([
             data[CLUSTER_HEAP_OFFSET_OFFSET],
@@ -118,9 +175,28 @@ impl ExfatSuperblock {
             data[FAT_LENGTH_OFFSET + 3],
         ]);
         
+        // メジャーバージョン1のみ対応
+        if fs_version >> 8 != 1 {
+            log::warn!("exFAT: 未対応のリビジョン {}.{}", fs_version >> 8, fs_version & 0xFF);
+            return Err(FsError::UnsupportedVersion);
+        }
+        
+        // FATとクラスタヒープがボリューム内に収まっているか
+        let heap_end = cluster_heap_offset as u64 + cluster_count as u64 * sectors_per_cluster as u64;
+        let fat_end = fat_offset as u64 + fat_length as u64 * number_of_fats as u64;
+        if cluster_count == 0 || heap_end > volume_length || fat_end > cluster_heap_offset as u64
+            || (fat_length as u64 * bytes_per_sector as u64) < (cluster_count as u64 + 2) * 4
+        {
+            log::warn!("exFAT: ボリュームのレイアウトが不正です");
+            return Err(FsError::CorruptedFs);
+        }
+        
         Ok(Self {
             bytes_per_sector,
             sectors_per_cluster,
+            volume_length,
+            number_of_fats,
+            percent_in_use,
             cluster_heap_offset,
             cluster_count,
             first_cluster_of_root_dir,
@@ -141,7 +217,7 @@ impl ExfatSuperblock {
     
     /// クラスタサイズを取得（バイト単位）
     pub fn cluster_size(&self) -> u32 {
-        self.bytes_per_sector as u32 * self.sectors_per_cluster as u32
+        self.bytes_per_sector as u32 * self.sectors_per_cluster
     }
     
     /// 最大ファイルサイズを取得
@@ -154,18 +230,75 @@ impl ExfatSuperblock {
         self.cluster_count as u64 * self.cluster_size() as u64
     }
     
-    /// ボリュームが読み取り専用としてマウントされているかどうか
-    pub fn is_read_only(&self) -> bool {
-        (self.volume_flags & 0x0001) != 0
+    /// 使用中のFAT/ビットマップの番号
+    pub fn active_fat(&self) -> u8 {
+        if self.number_of_fats == 2 && self.volume_flags & volume_flags::ACTIVE_FAT != 0 {
+            1
+        } else {
+            0
+        }
     }
     
-    /// ボリュームラベルを取得（実装されていない）
-    pub fn volume_label(&self) -> &str {
-        // ボリュームラベルはディレクトリエントリに保存されているため、
-        // この実装ではアクセスできない
-        "Unknown"
+    /// ボリュームがダーティかどうか
+    pub fn is_dirty(&self) -> bool {
+        self.volume_flags & volume_flags::VOLUME_DIRTY != 0
     }
+    
+    /// メディア障害フラグが立っているかどうか
+    pub fn has_media_failure(&self) -> bool {
+        self.volume_flags & volume_flags::MEDIA_FAILURE != 0
+    }
+    
+    /// 有効な最大クラスタ番号
+    pub fn max_cluster(&self) -> u32 {
+        // クラスタ番号は2から始まる
+        self.cluster_count + 1
+    }
+    
+    /// クラスタのデバイス上バイトオフセット
+    pub fn cluster_offset(&self, cluster: u32) -> u64 {
+        (self.cluster_heap_offset as u64 + (cluster - 2) as u64 * self.sectors_per_cluster as u64)
+            * self.bytes_per_sector as u64
+    }
+    
+    /// 指定番号のFATの先頭バイトオフセット
+    pub fn fat_byte_offset(&self, index: u8) -> u64 {
+        (self.fat_offset as u64 + index as u64 * self.fat_length as u64) * self.bytes_per_sector as u64
+    }
+}
+
+/// メインブート領域（11セクタ）のブートチェックサムを計算
+///
+/// ボリュームフラグと使用率は頻繁に書き換わるため計算から除外される
+pub fn boot_checksum(region: &[u8]) -> u32 {
+    region.iter().enumerate().fold(0u32, |sum, (i, &byte)| {
+        if i == VOLUME_FLAGS_BYTE || i == VOLUME_FLAGS_BYTE + 1 || i == PERCENT_IN_USE_BYTE {
+            sum
+        } else {
+            sum.rotate_right(1).wrapping_add(byte as u32)
+        }
+    })
 }
 
 /// 外部公開用の型
-pub type ExfatBootSector = ExfatSuperblock; 
\ No newline at end of file
+pub type ExfatBootSector = ExfatSuperblock;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn boot_checksum_ignores_volatile_fields() {
+        let mut region = vec![0u8; 512 * BOOT_REGION_SECTORS];
+        region[0] = 0xEB;
+        region[200] = 0x42;
+        let base = boot_checksum(&region);
+        
+        region[VOLUME_FLAGS_BYTE] = 0x02;
+        region[PERCENT_IN_USE_BYTE] = 50;
+        assert_eq!(boot_checksum(&region), base);
+        
+        region[201] = 1;
+        assert_ne!(boot_checksum(&region), base);
+    }
+}
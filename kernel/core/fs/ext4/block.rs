@@ -264,7 +264,7 @@ impl BlockDevice {
     /// 複数ブロックを書き込み
     pub fn write_blocks(&self, start_block: u64, data: &[u8]) -> FsResult<()> {
         let block_size = self.block_size as usize;
-        let total_blocks = (data.len() + block_size - 1) / block_size;
+        let total_blocks = data.len().div_ceil(block_size);
         
         for i in 0..total_blocks {
             let block_index = start_block + (i as u64);
@@ -0,0 +1,432 @@
+// Ext4 メタデータチェックサム実装
+//
+// metadata_csum機能によるスーパーブロック・グループディスクリプタ・ビットマップ・
+// iノード・エクステントブロック・ディレクトリブロックのCRC32Cの計算と検証、
+// gdt_csum機能によるグループディスクリプタのCRC16の計算
+
+use super::{Ext4FileSystem, Ext4Error};
+use super::crc16::crc16;
+use super::super::crc32c::crc32c;
+use super::dir;
+use super::group::BlockGroupDescriptor;
+use super::inode::Inode;
+use super::superblock::Superblock;
+
+/// スーパーブロック内のチェックサムのオフセット
+const SUPERBLOCK_CHECKSUM: usize = 0x3FC;
+/// グループディスクリプタ内のチェックサムのオフセット
+const GROUP_DESC_CHECKSUM: usize = 0x1E;
+/// iノード内のチェックサム下位16ビットのオフセット（osd2内）
+const INODE_CHECKSUM_LO: usize = 0x7C;
+/// iノード内のチェックサム上位16ビットのオフセット（拡張領域内）
+const INODE_CHECKSUM_HI: usize = 0x82;
+/// 拡張領域を除いたiノードのサイズ
+const GOOD_OLD_INODE_SIZE: usize = 128;
+/// dx_nodeのカウント/リミットのオフセット
+const DX_NODE_COUNT_OFFSET: usize = 8;
+/// dx_rootのカウント/リミットのオフセット（"."、".."、dx_root_infoの直後）
+const DX_ROOT_COUNT_OFFSET: usize = 32;
+/// dx_root_infoの長さ
+const DX_ROOT_INFO_LENGTH: u8 = 8;
+/// インデックスエントリのサイズ
+const DX_ENTRY_SIZE: usize = 8;
+/// dx_tailのサイズ（予約4バイト + チェックサム4バイト）
+const DX_TAIL_SIZE: usize = 8;
+/// ディレクトリ末尾エントリのサイズ
+const DIR_TAIL_SIZE: usize = 12;
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// スーパーブロック（1024バイト）のチェックサムを計算
+pub fn superblock_checksum(data: &[u8]) -> u32 {
+    crc32c(!0, &data[..SUPERBLOCK_CHECKSUM])
+}
+
+/// スーパーブロックのチェックサムが一致するかどうか
+pub fn verify_superblock(data: &[u8]) -> bool {
+    read_u32(data, SUPERBLOCK_CHECKSUM) == superblock_checksum(data)
+}
+
+/// スーパーブロックのチェックサムを設定
+pub fn set_superblock_checksum(data: &mut [u8]) {
+    let checksum = superblock_checksum(data);
+    write_u32(data, SUPERBLOCK_CHECKSUM, checksum);
+}
+
+/// グループディスクリプタのチェックサムを計算
+///
+/// グループ番号、チェックサム欄をゼロとみなしたディスクリプタ全体の順に計算し、
+/// 下位16ビットを使う。
+pub fn group_desc_checksum(seed: u32, group: u32, desc: &[u8]) -> u16 {
+    let mut crc = crc32c(seed, &group.to_le_bytes());
+    crc = crc32c(crc, &desc[..GROUP_DESC_CHECKSUM]);
+    crc = crc32c(crc, &[0, 0]);
+    crc = crc32c(crc, &desc[GROUP_DESC_CHECKSUM + 2..]);
+    crc as u16
+}
+
+/// gdt_csumのグループディスクリプタのチェックサムを計算
+///
+/// UUID、グループ番号、チェックサム欄を除いたディスクリプタの順に計算する。
+/// 64ビットのディスクリプタではチェックサム欄より後ろの拡張部分も含める。
+pub fn gdt_desc_checksum(uuid: &[u8; 16], group: u32, desc: &[u8]) -> u16 {
+    let mut crc = crc16(!0, uuid);
+    crc = crc16(crc, &group.to_le_bytes());
+    crc = crc16(crc, &desc[..GROUP_DESC_CHECKSUM]);
+    crc16(crc, &desc[GROUP_DESC_CHECKSUM + 2..])
+}
+
+/// ディスクリプタに格納するチェックサム（metadata_csumとgdt_csumのどちらも無効なら`None`）
+pub fn descriptor_checksum(sb: &Superblock, group: u32, desc: &[u8]) -> Option<u16> {
+    if let Some(seed) = sb.metadata_csum_seed() {
+        Some(group_desc_checksum(seed, group, desc))
+    } else if sb.has_gdt_csum() {
+        Some(gdt_desc_checksum(&sb.uuid, group, desc))
+    } else {
+        None
+    }
+}
+
+/// グループディスクリプタのチェックサムが一致するかどうか（チェックサムが無効なら常に一致）
+pub fn verify_group_desc(sb: &Superblock, group: u32, desc: &[u8]) -> bool {
+    descriptor_checksum(sb, group, desc).is_none_or(|checksum| read_u16(desc, GROUP_DESC_CHECKSUM) == checksum)
+}
+
+/// ビットマップのチェックサムを計算
+///
+/// `bitmap`はグループあたりのブロック数（iノード数）分のビットに切り詰めて渡す。
+pub fn bitmap_checksum(seed: u32, bitmap: &[u8]) -> u32 {
+    crc32c(seed, bitmap)
+}
+
+/// iノード単位のシード（iノード番号と世代番号から計算）
+///
+/// iノード本体の他、そのiノードのエクステントブロックとディレクトリブロックにも使う。
+pub fn inode_seed(seed: u32, inode_num: u32, generation: u32) -> u32 {
+    crc32c(crc32c(seed, &inode_num.to_le_bytes()), &generation.to_le_bytes())
+}
+
+/// 拡張領域にチェックサム上位16ビットの欄があるかどうか
+fn inode_has_checksum_hi(raw: &[u8]) -> bool {
+    raw.len() > GOOD_OLD_INODE_SIZE
+        && GOOD_OLD_INODE_SIZE + read_u16(raw, GOOD_OLD_INODE_SIZE) as usize >= INODE_CHECKSUM_HI + 2
+}
+
+/// iノード（ディスク上の`inode_size`バイト）のチェックサムを計算
+///
+/// チェックサム欄をゼロとみなして全体を計算する。上位16ビットの欄がなければ
+/// 下位16ビットだけが意味を持つ。
+pub fn inode_checksum(seed: u32, inode_num: u32, raw: &[u8]) -> u32 {
+    let generation = read_u32(raw, 100);
+    let mut crc = crc32c(inode_seed(seed, inode_num, generation), &raw[..INODE_CHECKSUM_LO]);
+    crc = crc32c(crc, &[0, 0]);
+    crc = crc32c(crc, &raw[INODE_CHECKSUM_LO + 2..GOOD_OLD_INODE_SIZE]);
+    
+    if raw.len() > GOOD_OLD_INODE_SIZE {
+        crc = crc32c(crc, &raw[GOOD_OLD_INODE_SIZE..INODE_CHECKSUM_HI]);
+        let rest = if inode_has_checksum_hi(raw) {
+            crc = crc32c(crc, &[0, 0]);
+            INODE_CHECKSUM_HI + 2
+        } else {
+            INODE_CHECKSUM_HI
+        };
+        crc = crc32c(crc, &raw[rest..]);
+    }
+    crc
+}
+
+/// iノードのチェックサムが一致するかどうか
+///
+/// e2fsprogsと同じく、全体がゼロのiノード（未使用のまま初期化されたもの）は正しいとみなす。
+pub fn verify_inode(seed: u32, inode_num: u32, raw: &[u8]) -> bool {
+    let mut stored = read_u16(raw, INODE_CHECKSUM_LO) as u32;
+    let mut calculated = inode_checksum(seed, inode_num, raw);
+    if inode_has_checksum_hi(raw) {
+        stored |= (read_u16(raw, INODE_CHECKSUM_HI) as u32) << 16;
+    } else {
+        calculated &= 0xFFFF;
+    }
+    stored == calculated || raw.iter().all(|&byte| byte == 0)
+}
+
+/// iノードのチェックサムを設定
+pub fn set_inode_checksum(seed: u32, inode_num: u32, raw: &mut [u8]) {
+    let checksum = inode_checksum(seed, inode_num, raw);
+    raw[INODE_CHECKSUM_LO..INODE_CHECKSUM_LO + 2].copy_from_slice(&(checksum as u16).to_le_bytes());
+    if inode_has_checksum_hi(raw) {
+        raw[INODE_CHECKSUM_HI..INODE_CHECKSUM_HI + 2].copy_from_slice(&((checksum >> 16) as u16).to_le_bytes());
+    }
+}
+
+/// エクステントブロックのチェックサムの位置（ヘッダの最大エントリ数の直後）
+fn extent_tail_offset(block: &[u8]) -> Option<usize> {
+    let offset = 12 + 12 * read_u16(block, 4) as usize;
+    if offset + 4 <= block.len() { Some(offset) } else { None }
+}
+
+/// エクステントブロックのチェックサムが一致するかどうか
+pub fn verify_extent_block(seed: u32, block: &[u8]) -> bool {
+    match extent_tail_offset(block) {
+        Some(offset) => read_u32(block, offset) == crc32c(seed, &block[..offset]),
+        None => false,
+    }
+}
+
+/// エクステントブロックのチェックサムを設定（末尾に余地がなければ`false`）
+pub fn set_extent_block_checksum(seed: u32, block: &mut [u8]) -> bool {
+    match extent_tail_offset(block) {
+        Some(offset) => {
+            let checksum = crc32c(seed, &block[..offset]);
+            write_u32(block, offset, checksum);
+            true
+        },
+        None => false,
+    }
+}
+
+/// インデックスブロックのカウント/リミットのオフセット（インデックスブロックでなければ`None`）
+fn dx_count_offset(block: &[u8]) -> Option<usize> {
+    let rec_len = read_u16(block, 4) as usize;
+    if rec_len == block.len() {
+        // dx_node: ブロック全体を占める空のエントリ
+        return Some(DX_NODE_COUNT_OFFSET);
+    }
+    if rec_len == 12 && read_u16(block, 12 + 4) as usize == block.len() - 12
+        && block[DX_ROOT_COUNT_OFFSET - 3] == DX_ROOT_INFO_LENGTH {
+        // dx_root: "."の直後の".."がブロックの残りを占める
+        return Some(DX_ROOT_COUNT_OFFSET);
+    }
+    None
+}
+
+/// インデックスブロックのdx_tailの位置とチェックサム対象の長さ
+fn dx_tail(block: &[u8]) -> Option<(usize, usize)> {
+    let count_offset = dx_count_offset(block)?;
+    let limit = read_u16(block, count_offset) as usize;
+    let count = read_u16(block, count_offset + 2) as usize;
+    let tail = count_offset + limit * DX_ENTRY_SIZE;
+    if limit == 0 || count > limit || tail + DX_TAIL_SIZE > block.len() {
+        return None;
+    }
+    Some((tail, count_offset + count * DX_ENTRY_SIZE))
+}
+
+/// インデックスブロックのチェックサムを計算
+fn dx_checksum(seed: u32, block: &[u8], tail: usize, size: usize) -> u32 {
+    let crc = crc32c(seed, &block[..size]);
+    let crc = crc32c(crc, &block[tail..tail + 4]);
+    crc32c(crc, &[0, 0, 0, 0])
+}
+
+/// ディレクトリブロック（リーフまたはインデックス）のチェックサムが一致するかどうか
+///
+/// チェックサムの領域がないブロックは一致しないものとして扱う。
+pub fn verify_dir_block(seed: u32, block: &[u8]) -> bool {
+    if dir::has_dir_tail(block) {
+        let size = block.len() - DIR_TAIL_SIZE;
+        return read_u32(block, block.len() - 4) == crc32c(seed, &block[..size]);
+    }
+    match dx_tail(block) {
+        Some((tail, size)) => read_u32(block, tail + 4) == dx_checksum(seed, block, tail, size),
+        None => false,
+    }
+}
+
+/// ディレクトリブロックのチェックサムを設定（チェックサムの領域がなければ`false`）
+pub fn set_dir_block_checksum(seed: u32, block: &mut [u8]) -> bool {
+    if dir::has_dir_tail(block) {
+        let size = block.len() - DIR_TAIL_SIZE;
+        let checksum = crc32c(seed, &block[..size]);
+        write_u32(block, block.len() - 4, checksum);
+        return true;
+    }
+    match dx_tail(block) {
+        Some((tail, size)) => {
+            let checksum = dx_checksum(seed, block, tail, size);
+            write_u32(block, tail + 4, checksum);
+            true
+        },
+        None => false,
+    }
+}
+
+impl Ext4FileSystem {
+    /// metadata_csumのシード（無効なら`None`）
+    pub(super) fn csum_seed(&self) -> Option<u32> {
+        self.superblock.read().unwrap().metadata_csum_seed()
+    }
+    
+    /// iノードに属するメタデータブロックのシード（metadata_csumが無効なら`None`）
+    pub(super) fn inode_csum_seed(&self, inode: &Inode) -> Option<u32> {
+        self.csum_seed().map(|seed| inode_seed(seed, inode.get_number(), inode.generation))
+    }
+    
+    /// ディレクトリブロックを読み込み、チェックサムを検証
+    pub(super) fn read_dir_block(&self, dir: &Inode, physical: u32, buffer: &mut [u8]) -> Result<(), Ext4Error> {
+        self.read_block(physical, buffer)?;
+        if let Some(seed) = self.inode_csum_seed(dir) {
+            if !verify_dir_block(seed, buffer) {
+                log::error!("ext4: ディレクトリ{}のブロック{}のチェックサムが一致しません", dir.get_number(), physical);
+                return Err(Ext4Error::ChecksumMismatch);
+            }
+        }
+        Ok(())
+    }
+    
+    /// ディレクトリブロックのチェックサムを設定して書き込み
+    pub(super) fn write_dir_block(&self, dir: &Inode, physical: u32, data: &[u8]) -> Result<(), Ext4Error> {
+        let seed = match self.inode_csum_seed(dir) {
+            Some(seed) => seed,
+            None => return self.write_block(physical, data),
+        };
+        
+        let mut block = data.to_vec();
+        if !set_dir_block_checksum(seed, &mut block) {
+            log::error!("ext4: ディレクトリ{}のブロック{}にチェックサムの領域がありません", dir.get_number(), physical);
+            return Err(Ext4Error::InvalidBlock);
+        }
+        self.write_block(physical, &block)
+    }
+    
+    /// グループあたりのビットマップのバイト数（ブロックビットマップ, iノードビットマップ）
+    fn bitmap_sizes(&self) -> (usize, usize) {
+        let sb = self.superblock.read().unwrap();
+        (sb.get_blocks_per_group() as usize / 8, sb.get_inodes_per_group() as usize / 8)
+    }
+    
+    /// ブロックビットマップのチェックサムを検証
+    pub(super) fn verify_block_bitmap(&self, group: u32, desc: &BlockGroupDescriptor, bitmap: &[u8]) -> Result<(), Ext4Error> {
+        self.verify_bitmap(group, "ブロック", bitmap_checksum_matches(
+            self.csum_seed(), &bitmap[..self.bitmap_sizes().0], desc.get_block_bitmap_checksum(), desc.is_64bit()))
+    }
+    
+    /// iノードビットマップのチェックサムを検証
+    pub(super) fn verify_inode_bitmap(&self, group: u32, desc: &BlockGroupDescriptor, bitmap: &[u8]) -> Result<(), Ext4Error> {
+        self.verify_bitmap(group, "iノード", bitmap_checksum_matches(
+            self.csum_seed(), &bitmap[..self.bitmap_sizes().1], desc.get_inode_bitmap_checksum(), desc.is_64bit()))
+    }
+    
+    fn verify_bitmap(&self, group: u32, kind: &str, matches: bool) -> Result<(), Ext4Error> {
+        if !matches {
+            log::error!("ext4: グループ{}の{}ビットマップのチェックサムが一致しません", group, kind);
+            return Err(Ext4Error::ChecksumMismatch);
+        }
+        Ok(())
+    }
+    
+    /// 更新したブロックビットマップのチェックサムをディスクリプタに設定
+    pub(super) fn update_block_bitmap_checksum(&self, desc: &mut BlockGroupDescriptor, bitmap: &[u8]) {
+        if let Some(seed) = self.csum_seed() {
+            desc.set_block_bitmap_checksum(bitmap_checksum(seed, &bitmap[..self.bitmap_sizes().0]));
+        }
+    }
+    
+    /// 更新したiノードビットマップのチェックサムをディスクリプタに設定
+    pub(super) fn update_inode_bitmap_checksum(&self, desc: &mut BlockGroupDescriptor, bitmap: &[u8]) {
+        if let Some(seed) = self.csum_seed() {
+            desc.set_inode_bitmap_checksum(bitmap_checksum(seed, &bitmap[..self.bitmap_sizes().1]));
+        }
+    }
+}
+
+/// ディスクリプタに格納されたビットマップのチェックサムと一致するかどうか
+///
+/// 32バイトのディスクリプタには下位16ビットしか格納されない。
+fn bitmap_checksum_matches(seed: Option<u32>, bitmap: &[u8], stored: u32, full: bool) -> bool {
+    match seed {
+        Some(seed) => {
+            let calculated = bitmap_checksum(seed, bitmap);
+            if full { stored == calculated } else { stored == calculated & 0xFFFF }
+        },
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::superblock::FEATURE_RO_COMPAT_GDT_CSUM;
+    
+    #[test]
+    fn test_inode_checksum_roundtrip() {
+        let mut raw = vec![0u8; 256];
+        raw[0..2].copy_from_slice(&0x81A4u16.to_le_bytes());
+        raw[100..104].copy_from_slice(&7u32.to_le_bytes());
+        raw[128..130].copy_from_slice(&32u16.to_le_bytes());
+        
+        set_inode_checksum(0x1234_5678, 12, &mut raw);
+        assert!(verify_inode(0x1234_5678, 12, &raw));
+        // 別のiノード番号やシードでは一致しない
+        assert!(!verify_inode(0x1234_5678, 13, &raw));
+        assert!(!verify_inode(0x8765_4321, 12, &raw));
+        
+        raw[4] ^= 1;
+        assert!(!verify_inode(0x1234_5678, 12, &raw));
+        // 未使用のiノードは検証しない
+        assert!(verify_inode(0x1234_5678, 12, &[0u8; 256]));
+    }
+    
+    #[test]
+    fn test_gdt_desc_checksum() {
+        // mke2fs -O ^metadata_csum,uninit_bg -U 01234567-89ab-cdef-0123-456789abcdef で作成したグループ0
+        let uuid = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+        let desc = [
+            0x12, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x32, 0x00, 0x00, 0x00, 0xDE, 0x0A, 0xF5, 0x03,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF5, 0x03, 0x3F, 0x39,
+        ];
+        assert_eq!(gdt_desc_checksum(&uuid, 0, &desc), 0x393F);
+        assert_ne!(gdt_desc_checksum(&uuid, 1, &desc), 0x393F);
+        
+        let mut raw = vec![0u8; 1024];
+        raw[56..58].copy_from_slice(&0xEF53u16.to_le_bytes());
+        raw[104..120].copy_from_slice(&uuid);
+        // チェックサムが無効なら検証しない
+        assert!(verify_group_desc(&Superblock::parse(&raw).unwrap(), 1, &desc));
+        raw[100..104].copy_from_slice(&FEATURE_RO_COMPAT_GDT_CSUM.to_le_bytes());
+        let sb = Superblock::parse(&raw).unwrap();
+        assert!(verify_group_desc(&sb, 0, &desc));
+        assert!(!verify_group_desc(&sb, 1, &desc));
+        let mut corrupted = desc;
+        corrupted[12] ^= 1;
+        assert!(!verify_group_desc(&sb, 0, &corrupted));
+    }
+    
+    #[test]
+    fn test_dir_leaf_and_index_checksums() {
+        let entry = dir::DirectoryEntry::new(12, "file", dir::DirectoryEntryType::RegularFile);
+        let mut leaf = dir::build_directory_block(1024, &[entry], true);
+        assert!(!verify_dir_block(1, &leaf));
+        assert!(set_dir_block_checksum(1, &mut leaf));
+        assert!(verify_dir_block(1, &leaf));
+        leaf[8] ^= 1;
+        assert!(!verify_dir_block(1, &leaf));
+        
+        // dx_node: 空の偽エントリ、limit=(1024-8-8)/8、count=1
+        let mut node = vec![0u8; 1024];
+        node[4..6].copy_from_slice(&1024u16.to_le_bytes());
+        node[8..10].copy_from_slice(&126u16.to_le_bytes());
+        node[10..12].copy_from_slice(&1u16.to_le_bytes());
+        assert!(set_dir_block_checksum(1, &mut node));
+        assert!(verify_dir_block(1, &node));
+        // 使われていないエントリの領域はチェックサムに含まれない
+        node[100] ^= 1;
+        assert!(verify_dir_block(1, &node));
+        node[12] ^= 1;
+        assert!(!verify_dir_block(1, &node));
+        
+        // チェックサムの領域がないブロック
+        let entry = dir::DirectoryEntry::new(12, "file", dir::DirectoryEntryType::RegularFile);
+        let mut full = dir::new_directory_block(1024, &entry, false);
+        assert!(!set_dir_block_checksum(1, &mut full));
+    }
+}
@@ -44,6 +44,20 @@ impl DirectoryEntryType {
         }
     }
     
+    /// iノードのモード（上位4ビットのファイル種別）から作成
+    pub fn from_mode(mode: u16) -> Self {
+        match mode & 0xF000 {
+            0x8000 => Self::RegularFile,
+            0x4000 => Self::Directory,
+            0x2000 => Self::CharDevice,
+            0x6000 => Self::BlockDevice,
+            0x1000 => Self::Fifo,
+            0xC000 => Self::Socket,
+            0xA000 => Self::SymbolicLink,
+            _ => Self::Unknown,
+        }
+    }
+    
     /// ファイルタイプに変換
     pub fn to_file_type(&self) -> FileType {
         match self {
@@ -185,4 +199,232 @@ pub fn parse_directory_block(data: &[u8]) -> FsResult<Vec<DirectoryEntry>> {
     }
     
     Ok(entries)
-} 
\ No newline at end of file
+} 
+
+/// metadata_csumのディレクトリブロック末尾エントリのファイルタイプ
+pub const DIR_TAIL_FILE_TYPE: u8 = 0xDE;
+
+/// ディレクトリブロック内のレコード（削除済みエントリを含む）をオフセット付きで列挙
+fn block_records(data: &[u8]) -> FsResult<Vec<(usize, DirectoryEntry, u8)>> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    
+    while offset + 8 <= data.len() {
+        let rec_len = u16::from_le_bytes([data[offset + 4], data[offset + 5]]) as usize;
+        if rec_len < 8 || rec_len % 4 != 0 || offset + rec_len > data.len() {
+            log::warn!("ext4: ディレクトリレコード長が不正です (offset={}, rec_len={})", offset, rec_len);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let raw_type = data[offset + 7];
+        let (entry, _) = DirectoryEntry::parse(data, offset)?;
+        records.push((offset, entry, raw_type));
+        offset += rec_len;
+    }
+    
+    Ok(records)
+}
+
+/// metadata_csumのチェックサム用末尾エントリかどうか
+fn is_dir_tail(entry: &DirectoryEntry, raw_type: u8) -> bool {
+    entry.inode == 0 && entry.name_len == 0 && entry.rec_len == 12 && raw_type == DIR_TAIL_FILE_TYPE
+}
+
+/// ディレクトリブロック内で名前に一致するエントリを検索
+pub fn find_entry_in_block(data: &[u8], name: &str) -> FsResult<Option<DirectoryEntry>> {
+    Ok(block_records(data)?
+        .into_iter()
+        .map(|(_, entry, _)| entry)
+        .find(|entry| entry.inode != 0 && entry.name == name))
+}
+
+/// ディレクトリブロックの空き領域にエントリを挿入
+///
+/// 既存レコードの末尾パディングまたは削除済みレコードを再利用する。
+/// 収まる場所がなければ`false`を返す。
+pub fn insert_entry(data: &mut [u8], entry: &DirectoryEntry) -> FsResult<bool> {
+    let needed = entry.actual_size() as usize;
+    
+    for (offset, existing, raw_type) in block_records(data)? {
+        if is_dir_tail(&existing, raw_type) {
+            continue;
+        }
+        
+        let rec_len = existing.rec_len as usize;
+        let used = if existing.inode == 0 { 0 } else { existing.actual_size() as usize };
+        if rec_len - used < needed {
+            continue;
+        }
+        
+        let mut new_entry = entry.clone();
+        if used == 0 {
+            // 削除済みレコードをそのまま使う
+            new_entry.rec_len = rec_len as u16;
+            let bytes = new_entry.serialize();
+            data[offset..offset + rec_len].copy_from_slice(&bytes);
+        } else {
+            // 既存レコードを実サイズに縮め、残りを新しいエントリに割り当てる
+            data[offset + 4..offset + 6].copy_from_slice(&(used as u16).to_le_bytes());
+            new_entry.rec_len = (rec_len - used) as u16;
+            let bytes = new_entry.serialize();
+            data[offset + used..offset + rec_len].copy_from_slice(&bytes);
+        }
+        return Ok(true);
+    }
+    
+    Ok(false)
+}
+
+/// ディレクトリブロックから名前に一致するエントリを削除
+///
+/// 直前のレコードがあればその長さに吸収させ、先頭レコードの場合はiノードを0にする。
+pub fn remove_entry(data: &mut [u8], name: &str) -> FsResult<Option<DirectoryEntry>> {
+    let mut previous: Option<usize> = None;
+    
+    for (offset, entry, raw_type) in block_records(data)? {
+        if entry.inode != 0 && entry.name == name {
+            match previous {
+                Some(prev) => {
+                    let prev_len = u16::from_le_bytes([data[prev + 4], data[prev + 5]]);
+                    let merged = prev_len + entry.rec_len;
+                    data[prev + 4..prev + 6].copy_from_slice(&merged.to_le_bytes());
+                },
+                None => {
+                    data[offset..offset + 4].copy_from_slice(&0u32.to_le_bytes());
+                }
+            }
+            return Ok(Some(entry));
+        }
+        
+        if !is_dir_tail(&entry, raw_type) {
+            previous = Some(offset);
+        }
+    }
+    
+    Ok(None)
+}
+
+/// 既存エントリの参照先iノードとファイルタイプを差し替え
+pub fn replace_entry_inode(
+    data: &mut [u8],
+    name: &str,
+    inode: u32,
+    file_type: DirectoryEntryType,
+) -> FsResult<bool> {
+    for (offset, entry, _) in block_records(data)? {
+        if entry.inode != 0 && entry.name == name {
+            data[offset..offset + 4].copy_from_slice(&inode.to_le_bytes());
+            if data[offset + 7] != 0 {
+                data[offset + 7] = file_type as u8;
+            }
+            return Ok(true);
+        }
+    }
+    
+    Ok(false)
+}
+
+/// 単一のエントリがブロック全体を占めるディレクトリブロックを作成
+///
+/// `with_tail`の場合は末尾にチェックサム用エントリの領域を確保する。
+pub fn new_directory_block(block_size: usize, entry: &DirectoryEntry, with_tail: bool) -> Vec<u8> {
+    build_directory_block(block_size, core::slice::from_ref(entry), with_tail)
+}
+
+/// ブロック末尾にmetadata_csumのチェックサム用エントリがあるかどうか
+pub fn has_dir_tail(data: &[u8]) -> bool {
+    if data.len() < 12 {
+        return false;
+    }
+    let tail = &data[data.len() - 12..];
+    tail[..4] == [0, 0, 0, 0] && tail[4..6] == 12u16.to_le_bytes() && tail[6] == 0 && tail[7] == DIR_TAIL_FILE_TYPE
+}
+
+/// エントリを先頭から詰めたディレクトリブロックを作成
+///
+/// 最後のエントリがブロックの残りを占める。`with_tail`の場合は末尾にチェックサム用
+/// エントリの領域を確保する（チェックサム値は書き込み時に設定される）。
+pub fn build_directory_block(block_size: usize, entries: &[DirectoryEntry], with_tail: bool) -> Vec<u8> {
+    let usable = if with_tail { block_size - 12 } else { block_size };
+    let mut block = Vec::with_capacity(block_size);
+    
+    for (i, entry) in entries.iter().enumerate() {
+        let mut packed = entry.clone();
+        packed.rec_len = if i + 1 == entries.len() {
+            (usable - block.len()) as u16
+        } else {
+            packed.actual_size()
+        };
+        block.extend_from_slice(&packed.serialize());
+    }
+    
+    if entries.is_empty() {
+        // 空のブロックは削除済みエントリ1つで埋める
+        block.resize(usable, 0);
+        block[4..6].copy_from_slice(&(usable as u16).to_le_bytes());
+    }
+    
+    if with_tail {
+        block.extend_from_slice(&[0, 0, 0, 0, 12, 0, 0, DIR_TAIL_FILE_TYPE, 0, 0, 0, 0]);
+    }
+    block
+}
+
+/// "."と".."だけを含む新しいディレクトリの先頭ブロックを作成
+///
+/// `with_tail`の場合は末尾にチェックサム用エントリの領域を確保する。
+pub fn init_directory_block(
+    block_size: usize,
+    self_inode: u32,
+    parent_inode: u32,
+    with_file_type: bool,
+    with_tail: bool,
+) -> Vec<u8> {
+    let dir_type = if with_file_type { DirectoryEntryType::Directory } else { DirectoryEntryType::Unknown };
+    
+    let dot = DirectoryEntry::new(self_inode, ".", dir_type);
+    let dotdot = DirectoryEntry::new(parent_inode, "..", dir_type);
+    build_directory_block(block_size, &[dot, dotdot], with_tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_insert_and_remove_entry() {
+        let mut block = init_directory_block(1024, 12, 2, true, false);
+        let entry = DirectoryEntry::new(13, "artifact.tar", DirectoryEntryType::RegularFile);
+        assert!(insert_entry(&mut block, &entry).unwrap());
+        
+        let found = find_entry_in_block(&block, "artifact.tar").unwrap().unwrap();
+        assert_eq!(found.inode, 13);
+        assert_eq!(found.rec_len as usize, 1024 - 12 - 12);
+        
+        let removed = remove_entry(&mut block, "artifact.tar").unwrap().unwrap();
+        assert_eq!(removed.inode, 13);
+        assert!(find_entry_in_block(&block, "artifact.tar").unwrap().is_none());
+        
+        // 削除した領域は".."に吸収される
+        let dotdot = find_entry_in_block(&block, "..").unwrap().unwrap();
+        assert_eq!(dotdot.rec_len as usize, 1024 - 12);
+    }
+    
+    #[test]
+    fn test_insert_skips_checksum_tail() {
+        let first = DirectoryEntry::new(20, "a", DirectoryEntryType::RegularFile);
+        let mut block = first.serialize();
+        block[4..6].copy_from_slice(&(1024u16 - 12).to_le_bytes());
+        block.resize(1024 - 12, 0);
+        block.extend_from_slice(&[0, 0, 0, 0, 12, 0, 0, DIR_TAIL_FILE_TYPE, 0, 0, 0, 0]);
+        
+        let entry = DirectoryEntry::new(21, "b", DirectoryEntryType::RegularFile);
+        assert!(insert_entry(&mut block, &entry).unwrap());
+        assert_eq!(&block[1024 - 12..1024 - 4], &[0, 0, 0, 0, 12, 0, 0, DIR_TAIL_FILE_TYPE]);
+        
+        let removed = remove_entry(&mut block, "a").unwrap().unwrap();
+        assert_eq!(removed.inode, 20);
+        assert_eq!(u32::from_le_bytes([block[0], block[1], block[2], block[3]]), 0);
+        assert_eq!(find_entry_in_block(&block, "b").unwrap().unwrap().inode, 21);
+    }
+}
@@ -1,9 +1,39 @@
 // Ext4 エクステント実装
 //
-// Ext4ファイルシステムのエクステント機能
+// エクステントツリーの解析と、挿入・結合・分割・深さの拡張・未初期化エクステントの変換・切り詰め
 
 use alloc::vec::Vec;
 use super::super::{FsError, FsResult};
+use super::{Ext4FileSystem, Ext4Error};
+use super::inode::Inode;
+use super::csum;
+
+/// エクステントツリーのマジックナンバー
+pub const EXTENT_MAGIC: u16 = 0xF30A;
+
+/// i_block内に収まるエントリ数
+pub const INLINE_EXTENT_MAX: u16 = 4;
+
+/// 初期化済みエクステントの最大長
+pub const EXT_INIT_MAX_LEN: u32 = 32768;
+
+/// 未初期化エクステントの最大長
+pub const EXT_UNINIT_MAX_LEN: u32 = 32767;
+
+/// ツリーの最大深さ
+const EXT_MAX_DEPTH: u16 = 5;
+
+/// ヘッダのサイズ
+const EXTENT_HEADER_SIZE: usize = 12;
+
+/// インデックス・エクステント1つ分のサイズ
+const EXTENT_ENTRY_SIZE: usize = 12;
+
+/// i_blockのサイズ
+const INODE_BLOCK_SIZE: usize = 60;
+
+/// 直接ブロックポインタの数（エクステントを使わないiノード）
+const DIRECT_BLOCKS: u32 = 12;
 
 /// エクステントヘッダ
 #[derive(Debug, Clone, Copy)]
@@ -23,12 +53,12 @@ pub struct ExtentHeader {
 impl ExtentHeader {
     /// エクステントヘッダをパース
     pub fn parse(data: &[u8]) -> FsResult<Self> {
-        if data.len() < 12 {
+        if data.len() < EXTENT_HEADER_SIZE {
             return Err(FsError::InvalidData);
         }
         
         let magic = u16::from_le_bytes([data[0], data[1]]);
-        if magic != 0xF30A {
+        if magic != EXTENT_MAGIC {
             return Err(FsError::InvalidData);
         }
         
@@ -48,7 +78,7 @@ impl ExtentHeader {
     
     /// エクステントヘッダをシリアライズ
     pub fn serialize(&self) -> Vec<u8> {
-        let mut result = Vec::with_capacity(12);
+        let mut result = Vec::with_capacity(EXTENT_HEADER_SIZE);
         
         result.extend_from_slice(&self.magic.to_le_bytes());
         result.extend_from_slice(&self.entries.to_le_bytes());
@@ -62,7 +92,7 @@ impl ExtentHeader {
     /// 新しいエクステントヘッダを作成
     pub fn new(entries: u16, max: u16, depth: u16) -> Self {
         Self {
-            magic: 0xF30A,
+            magic: EXTENT_MAGIC,
             entries,
             max,
             depth,
@@ -77,7 +107,7 @@ impl ExtentHeader {
 }
 
 /// エクステントインデックス（ノードエントリ）
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ExtentIndex {
     /// このインデックスノードがカバーする最初の論理ブロック
     pub block: u32,
@@ -92,7 +122,7 @@ pub struct ExtentIndex {
 impl ExtentIndex {
     /// エクステントインデックスをパース
     pub fn parse(data: &[u8]) -> FsResult<Self> {
-        if data.len() < 12 {
+        if data.len() < EXTENT_ENTRY_SIZE {
             return Err(FsError::InvalidData);
         }
         
@@ -111,7 +141,7 @@ impl ExtentIndex {
     
     /// エクステントインデックスをシリアライズ
     pub fn serialize(&self) -> Vec<u8> {
-        let mut result = Vec::with_capacity(12);
+        let mut result = Vec::with_capacity(EXTENT_ENTRY_SIZE);
         
         result.extend_from_slice(&self.block.to_le_bytes());
         result.extend_from_slice(&self.leaf_lo.to_le_bytes());
@@ -137,177 +167,1172 @@ impl ExtentIndex {
     }
 }
 
-/// エクステントリーフ（データエントリ）
-#[derive(Debug, Clone, Copy)]
-pub struct ExtentLeaf {
+/// エクステント（リーフのエントリ）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
     /// このエクステントがカバーする最初の論理ブロック
     pub block: u32,
-    /// このエクステントの長さ
-    pub len: u16,
-    /// 初期化されていないブロックフラグ
-    pub uninit: u16,
-    /// 物理ブロック（下位32ビット）
-    pub start_lo: u32,
-    /// 物理ブロック（上位16ビット）
-    pub start_hi: u16,
-    /// 未使用
-    pub unused: u16,
+    /// ブロック数
+    pub len: u32,
+    /// 最初の物理ブロック
+    pub start: u64,
+    /// 未初期化（事前割り当て済みで、読むとゼロになる）
+    pub uninit: bool,
 }
 
-impl ExtentLeaf {
-    /// エクステントリーフをパース
-    pub fn parse(data: &[u8]) -> FsResult<Self> {
-        if data.len() < 12 {
-            return Err(FsError::InvalidData);
+impl Extent {
+    /// 新しい初期化済みエクステントを作成
+    pub fn new(block: u32, len: u32, start: u64) -> Self {
+        Self {
+            block,
+            len,
+            start,
+            uninit: false,
         }
-        
+    }
+    
+    /// ディスク上の12バイトからパース
+    ///
+    /// ee_lenが32768を超える場合は未初期化エクステントで、長さは32768を引いた値。
+    pub fn parse(data: &[u8]) -> Self {
         let block = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
-        let len = u16::from_le_bytes([data[4], data[5]]);
-        let uninit = u16::from_le_bytes([data[6], data[7]]);
-        let start_lo = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
-        let start_hi = u16::from_le_bytes([data[12], data[13]]);
-        let unused = u16::from_le_bytes([data[14], data[15]]);
+        let raw_len = u16::from_le_bytes([data[4], data[5]]) as u32;
+        let start_hi = u16::from_le_bytes([data[6], data[7]]) as u64;
+        let start_lo = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as u64;
         
-        Ok(Self {
+        let (len, uninit) = if raw_len > EXT_INIT_MAX_LEN {
+            (raw_len - EXT_INIT_MAX_LEN, true)
+        } else {
+            (raw_len, false)
+        };
+        
+        Self {
             block,
             len,
+            start: (start_hi << 32) | start_lo,
             uninit,
-            start_lo,
-            start_hi,
-            unused,
+        }
+    }
+    
+    /// ディスク上の12バイトに書き込み
+    pub fn write(&self, data: &mut [u8]) {
+        let raw_len = if self.uninit { self.len + EXT_INIT_MAX_LEN } else { self.len };
+        data[0..4].copy_from_slice(&self.block.to_le_bytes());
+        data[4..6].copy_from_slice(&(raw_len as u16).to_le_bytes());
+        data[6..8].copy_from_slice(&((self.start >> 32) as u16).to_le_bytes());
+        data[8..12].copy_from_slice(&(self.start as u32).to_le_bytes());
+    }
+    
+    /// 直後の論理ブロック（64ビットで表し、2^32でも桁あふれしない）
+    pub fn end(&self) -> u64 {
+        self.block as u64 + self.len as u64
+    }
+    
+    /// 論理ブロックを含むかどうか
+    pub fn contains(&self, logical: u32) -> bool {
+        logical >= self.block && (logical as u64) < self.end()
+    }
+    
+    /// 論理ブロックに対応する物理ブロック
+    pub fn physical(&self, logical: u32) -> u64 {
+        self.start + (logical - self.block) as u64
+    }
+    
+    /// この種類のエクステントの最大長
+    pub fn max_len(&self) -> u32 {
+        if self.uninit { EXT_UNINIT_MAX_LEN } else { EXT_INIT_MAX_LEN }
+    }
+    
+    /// `next`が論理・物理ともに直後に続き、1つのエクステントにまとめられるかどうか
+    pub fn can_append(&self, next: &Extent) -> bool {
+        self.uninit == next.uninit
+            && self.end() == next.block as u64
+            && self.start + self.len as u64 == next.start
+            && self.len + next.len <= self.max_len()
+    }
+}
+
+/// エクステントツリーのノード（i_block内のルートまたはツリーブロック）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtentNode {
+    /// 深さ（0はリーフ）
+    pub depth: u16,
+    /// 最大エントリ数
+    pub max: u16,
+    /// 世代
+    pub generation: u32,
+    /// インデックスエントリ（深さ1以上）
+    pub indexes: Vec<ExtentIndex>,
+    /// エクステント（リーフ）
+    pub extents: Vec<Extent>,
+}
+
+impl ExtentNode {
+    /// 空のリーフノードを作成
+    pub fn new_leaf(max: u16) -> Self {
+        Self {
+            depth: 0,
+            max,
+            generation: 0,
+            indexes: Vec::new(),
+            extents: Vec::new(),
+        }
+    }
+    
+    /// ノードをパース
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        let header = ExtentHeader::parse(data)?;
+        if header.entries > header.max
+            || EXTENT_HEADER_SIZE + header.max as usize * EXTENT_ENTRY_SIZE > data.len()
+            || header.depth > EXT_MAX_DEPTH {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut node = Self {
+            depth: header.depth,
+            max: header.max,
+            generation: header.generation,
+            indexes: Vec::new(),
+            extents: Vec::new(),
+        };
+        
+        for i in 0..header.entries as usize {
+            let offset = EXTENT_HEADER_SIZE + i * EXTENT_ENTRY_SIZE;
+            let entry = &data[offset..offset + EXTENT_ENTRY_SIZE];
+            if header.is_leaf() {
+                node.extents.push(Extent::parse(entry));
+            } else {
+                node.indexes.push(ExtentIndex::parse(entry)?);
+            }
+        }
+        
+        Ok(node)
+    }
+    
+    /// ノードを書き込み（未使用のエントリ領域はゼロにする）
+    pub fn write(&self, data: &mut [u8]) {
+        let header = ExtentHeader {
+            magic: EXTENT_MAGIC,
+            entries: self.len() as u16,
+            max: self.max,
+            depth: self.depth,
+            generation: self.generation,
+        };
+        data[..EXTENT_HEADER_SIZE].copy_from_slice(&header.serialize());
+        
+        let entries_end = EXTENT_HEADER_SIZE + self.max as usize * EXTENT_ENTRY_SIZE;
+        data[EXTENT_HEADER_SIZE..entries_end].fill(0);
+        for i in 0..self.len() {
+            let offset = EXTENT_HEADER_SIZE + i * EXTENT_ENTRY_SIZE;
+            let entry = &mut data[offset..offset + EXTENT_ENTRY_SIZE];
+            if self.is_leaf() {
+                self.extents[i].write(entry);
+            } else {
+                entry.copy_from_slice(&self.indexes[i].serialize());
+            }
+        }
+    }
+    
+    /// リーフノードかどうか
+    pub fn is_leaf(&self) -> bool {
+        self.depth == 0
+    }
+    
+    /// エントリ数
+    pub fn len(&self) -> usize {
+        if self.is_leaf() { self.extents.len() } else { self.indexes.len() }
+    }
+    
+    /// エントリがないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// 満杯かどうか
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.max as usize
+    }
+    
+    /// 最初のエントリの論理ブロック
+    pub fn first_block(&self) -> Option<u32> {
+        if self.is_leaf() {
+            self.extents.first().map(|extent| extent.block)
+        } else {
+            self.indexes.first().map(|index| index.block)
+        }
+    }
+    
+    /// 同じ深さと容量の空のノード
+    fn empty_like(&self) -> Self {
+        Self {
+            depth: self.depth,
+            max: self.max,
+            generation: self.generation,
+            indexes: Vec::new(),
+            extents: Vec::new(),
+        }
+    }
+}
+
+/// ブロックサイズあたりのノードの最大エントリ数
+///
+/// 末尾の余りにはmetadata_csumのチェックサム（4バイト）が入る。
+pub fn node_capacity(block_size: usize) -> u16 {
+    ((block_size - EXTENT_HEADER_SIZE) / EXTENT_ENTRY_SIZE) as u16
+}
+
+/// ツリーノードのブロックへのアクセスとブロックの割り当て
+pub trait ExtentStore {
+    /// ツリーブロック1つに収まるエントリ数
+    fn node_capacity(&self) -> u16;
+    
+    /// ツリーブロックを読み込み
+    fn read_node(&self, block: u64) -> FsResult<ExtentNode>;
+    
+    /// ツリーブロックを書き込み
+    fn write_node(&self, block: u64, node: &ExtentNode) -> FsResult<()>;
+    
+    /// ツリーブロックを1つ割り当て
+    fn allocate_node(&self) -> FsResult<u64>;
+    
+    /// 連続するブロックを解放
+    fn free_blocks(&self, start: u64, count: u32) -> FsResult<()>;
+}
+
+/// ルートからたどった経路の1段
+struct PathLevel {
+    /// ノードのブロック番号（i_block内のルートは`None`）
+    block: Option<u64>,
+    /// ノード
+    node: ExtentNode,
+    /// インデックスノードでは降りた子の位置、リーフでは挿入位置
+    /// （開始ブロックが対象以下のエクステントの数）
+    position: usize,
+}
+
+/// i_blockをルートとするエクステントツリー
+pub struct ExtentTree<'a> {
+    /// ツリーブロックへのアクセス
+    store: &'a dyn ExtentStore,
+    /// ルートノード（i_blockの内容）
+    root: ExtentNode,
+    /// 割り当てたツリーブロック数
+    pub nodes_allocated: u32,
+    /// 解放したツリーブロック数
+    pub nodes_freed: u32,
+    /// 切り詰めで解放したデータブロック数
+    pub data_freed: u64,
+}
+
+impl<'a> ExtentTree<'a> {
+    /// i_blockの内容からツリーを作成
+    pub fn new(store: &'a dyn ExtentStore, root: &[u8]) -> FsResult<Self> {
+        Ok(Self {
+            store,
+            root: ExtentNode::parse(root)?,
+            nodes_allocated: 0,
+            nodes_freed: 0,
+            data_freed: 0,
         })
     }
     
-    /// エクステントリーフをシリアライズ
-    pub fn serialize(&self) -> Vec<u8> {
-        let mut result = Vec::with_capacity(16);
+    /// ルートをi_blockの形式で取得
+    pub fn root_bytes(&self) -> [u8; INODE_BLOCK_SIZE] {
+        let mut data = [0u8; INODE_BLOCK_SIZE];
+        self.root.write(&mut data);
+        data
+    }
+    
+    /// ツリーの深さ
+    pub fn depth(&self) -> u16 {
+        self.root.depth
+    }
+    
+    /// 子ノードを読み込み、期待する深さか検証
+    fn read_child(&self, block: u64, depth: u16) -> FsResult<ExtentNode> {
+        let node = self.store.read_node(block)?;
+        if node.depth != depth {
+            log::warn!("ext4: エクステントブロック{}の深さ{}が不正です（期待値{}）", block, node.depth, depth);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(node)
+    }
+    
+    /// 論理ブロックを含む（または含むはずの）リーフまでの経路
+    fn find_path(&self, logical: u32) -> FsResult<Vec<PathLevel>> {
+        let mut path: Vec<PathLevel> = Vec::new();
+        let mut node = self.root.clone();
+        let mut block = None;
         
-        result.extend_from_slice(&self.block.to_le_bytes());
-        result.extend_from_slice(&self.len.to_le_bytes());
-        result.extend_from_slice(&self.uninit.to_le_bytes());
-        result.extend_from_slice(&self.start_lo.to_le_bytes());
-        result.extend_from_slice(&self.start_hi.to_le_bytes());
-        result.extend_from_slice(&self.unused.to_le_bytes());
+        loop {
+            if node.is_leaf() {
+                let position = node.extents.iter().take_while(|extent| extent.block <= logical).count();
+                path.push(PathLevel { block, node, position });
+                return Ok(path);
+            }
+            if node.indexes.is_empty() {
+                log::warn!("ext4: 空のエクステントインデックスノードがあります");
+                return Err(FsError::CorruptedFs);
+            }
+            
+            let position = node.indexes.iter().take_while(|index| index.block <= logical).count().saturating_sub(1);
+            let child = node.indexes[position].leaf();
+            let depth = node.depth - 1;
+            path.push(PathLevel { block, node, position });
+            
+            node = self.read_child(child, depth)?;
+            block = Some(child);
+        }
+    }
+    
+    /// 経路の1段を書き戻す
+    fn write_level(&mut self, level: &PathLevel) -> FsResult<()> {
+        match level.block {
+            Some(block) => self.store.write_node(block, &level.node),
+            None => {
+                self.root = level.node.clone();
+                Ok(())
+            },
+        }
+    }
+    
+    /// 論理ブロックを含むエクステントを検索
+    pub fn lookup(&self, logical: u32) -> FsResult<Option<Extent>> {
+        let path = self.find_path(logical)?;
+        let leaf = &path[path.len() - 1];
+        Ok(leaf.position.checked_sub(1)
+            .map(|i| leaf.node.extents[i])
+            .filter(|extent| extent.contains(logical)))
+    }
+    
+    /// 論理ブロックより後ろで最初に割り当てられている論理ブロック
+    pub fn next_allocated(&self, logical: u32) -> FsResult<Option<u32>> {
+        let path = self.find_path(logical)?;
+        let leaf = &path[path.len() - 1];
+        if let Some(extent) = leaf.node.extents.get(leaf.position) {
+            return Ok(Some(extent.block));
+        }
+        
+        // リーフの末尾なら、右隣の部分木の先頭
+        for level in path[..path.len() - 1].iter().rev() {
+            if let Some(index) = level.node.indexes.get(level.position + 1) {
+                return Ok(Some(index.block));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// 論理ブロックの割り当て先として望ましい物理ブロック（直前のエクステントの延長線上）
+    pub fn goal(&self, logical: u32) -> FsResult<Option<u64>> {
+        let path = self.find_path(logical)?;
+        let leaf = &path[path.len() - 1];
+        Ok(leaf.position.checked_sub(1).map(|i| {
+            let extent = leaf.node.extents[i];
+            extent.start + (logical - extent.block) as u64
+        }))
+    }
+    
+    /// エクステントを挿入
+    ///
+    /// 隣接するエクステントと論理・物理ともに連続していれば結合する。
+    /// リーフが満杯なら分割し、親も満杯なら上へたどって分割し、ルートまで満杯なら深さを1段増やす。
+    pub fn insert(&mut self, extent: Extent) -> FsResult<()> {
+        if extent.len == 0 || extent.len > extent.max_len() || extent.end() > u32::MAX as u64 + 1 {
+            return Err(FsError::InvalidData);
+        }
+        
+        // 1回の挿入で必要な分割は各段1回と深さの拡張1回まで
+        for _ in 0..=(EXT_MAX_DEPTH as usize + 1) * 2 {
+            let mut path = self.find_path(extent.block)?;
+            let leaf_level = path.len() - 1;
+            
+            {
+                let leaf = &path[leaf_level];
+                let overlaps_prev = leaf.position > 0 && leaf.node.extents[leaf.position - 1].end() > extent.block as u64;
+                let overlaps_next = leaf.node.extents.get(leaf.position)
+                    .map_or(false, |next| (next.block as u64) < extent.end());
+                if overlaps_prev || overlaps_next {
+                    return Err(FsError::AlreadyExists);
+                }
+            }
+            
+            if self.insert_into_leaf(&mut path, extent)? {
+                return Ok(());
+            }
+            self.make_room(&mut path, leaf_level, extent.block)?;
+        }
+        
+        log::error!("ext4: エクステントの挿入位置を確保できませんでした");
+        Err(FsError::CorruptedFs)
+    }
+    
+    /// リーフに挿入（結合を含む）。満杯で挿入できなければ`false`
+    fn insert_into_leaf(&mut self, path: &mut [PathLevel], extent: Extent) -> FsResult<bool> {
+        let leaf_level = path.len() - 1;
+        let position = path[leaf_level].position;
+        let leaf = &mut path[leaf_level].node;
+        
+        // 直前のエクステントの末尾に結合
+        if position > 0 && leaf.extents[position - 1].can_append(&extent) {
+            leaf.extents[position - 1].len += extent.len;
+            // 埋めた穴の後ろとも連続するなら3つを1つにする
+            if position < leaf.extents.len() && leaf.extents[position - 1].can_append(&leaf.extents[position]) {
+                let next = leaf.extents.remove(position);
+                leaf.extents[position - 1].len += next.len;
+            }
+            self.write_level(&path[leaf_level])?;
+            return Ok(true);
+        }
+        
+        // 直後のエクステントの先頭に結合
+        if position < leaf.extents.len() && extent.can_append(&leaf.extents[position]) {
+            let next = leaf.extents[position];
+            leaf.extents[position] = Extent { len: extent.len + next.len, ..extent };
+            self.write_level(&path[leaf_level])?;
+            if position == 0 {
+                self.correct_indexes(path)?;
+            }
+            return Ok(true);
+        }
+        
+        if leaf.is_full() {
+            return Ok(false);
+        }
+        
+        leaf.extents.insert(position, extent);
+        self.write_level(&path[leaf_level])?;
+        if position == 0 {
+            self.correct_indexes(path)?;
+        }
+        Ok(true)
+    }
+    
+    /// リーフの先頭が変わったとき、親のインデックスの開始ブロックを合わせる
+    fn correct_indexes(&mut self, path: &mut [PathLevel]) -> FsResult<()> {
+        let leaf_level = path.len() - 1;
+        let key = match path[leaf_level].node.first_block() {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        
+        for level in (0..leaf_level).rev() {
+            let position = path[level].position;
+            if path[level].node.indexes[position].block == key {
+                break;
+            }
+            path[level].node.indexes[position].block = key;
+            self.write_level(&path[level])?;
+            
+            // 最初の子でなければ、さらに上の開始ブロックは変わらない
+            if position != 0 {
+                break;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 満杯のノードに空きを作る
+    fn make_room(&mut self, path: &mut [PathLevel], level: usize, key: u32) -> FsResult<()> {
+        if level == 0 {
+            return self.grow_depth();
+        }
+        if path[level - 1].node.is_full() {
+            return self.make_room(path, level - 1, key);
+        }
+        self.split_node(path, level, key)
+    }
+    
+    /// ルートの内容を新しいブロックへ移し、ルートをそれを指すインデックスにする
+    fn grow_depth(&mut self) -> FsResult<()> {
+        if self.root.depth >= EXT_MAX_DEPTH {
+            log::warn!("ext4: エクステントツリーが最大の深さに達しています");
+            return Err(FsError::OutOfSpace);
+        }
+        
+        let mut child = self.root.clone();
+        child.max = self.store.node_capacity();
+        
+        let block = self.store.allocate_node()?;
+        if let Err(e) = self.store.write_node(block, &child) {
+            self.store.free_blocks(block, 1)?;
+            return Err(e);
+        }
+        self.nodes_allocated += 1;
+        
+        let mut root = ExtentNode::new_leaf(self.root.max);
+        root.depth = child.depth + 1;
+        root.generation = self.root.generation;
+        root.indexes.push(ExtentIndex::new(child.first_block().unwrap_or(0), block));
+        self.root = root;
+        
+        log::debug!("ext4: エクステントツリーの深さを{}に拡張しました", self.root.depth);
+        Ok(())
+    }
+    
+    /// ルート以外のノードを2つに分け、右半分を親に登録する
+    ///
+    /// 末尾への追加（順次書き込み）では、既存のノードを満杯のまま残して空の右ノードを作る。
+    fn split_node(&mut self, path: &mut [PathLevel], level: usize, key: u32) -> FsResult<()> {
+        let node = &mut path[level].node;
+        let appending = node.is_leaf() && node.extents.last().map_or(true, |last| key > last.block);
+        let split = if appending { node.len() } else { node.len() / 2 };
+        
+        let mut right = node.empty_like();
+        if node.is_leaf() {
+            right.extents = node.extents.split_off(split);
+        } else {
+            right.indexes = node.indexes.split_off(split);
+        }
+        let right_key = right.first_block().unwrap_or(key);
+        
+        let block = self.store.allocate_node()?;
+        if let Err(e) = self.store.write_node(block, &right) {
+            self.store.free_blocks(block, 1)?;
+            return Err(e);
+        }
+        self.nodes_allocated += 1;
+        
+        // 右ノードを親から参照してから左ノードを縮める
+        let parent = &mut path[level - 1];
+        parent.node.indexes.insert(parent.position + 1, ExtentIndex::new(right_key, block));
+        self.write_level(&path[level - 1])?;
+        self.write_level(&path[level])?;
+        
+        Ok(())
+    }
+    
+    /// 未初期化エクステント内の`[logical, logical + count)`を初期化済みにし、変換したブロック数を返す
+    ///
+    /// 範囲はエクステントの末尾で打ち切る。前後の未初期化部分は別のエクステントとして残す。
+    pub fn mark_initialized(&mut self, logical: u32, count: u32) -> FsResult<u32> {
+        let mut path = self.find_path(logical)?;
+        let leaf_level = path.len() - 1;
+        let index = match path[leaf_level].position.checked_sub(1) {
+            Some(index) if path[leaf_level].node.extents[index].contains(logical) => index,
+            _ => return Err(FsError::NotFound),
+        };
+        
+        let extent = path[leaf_level].node.extents[index];
+        if !extent.uninit {
+            return Ok(0);
+        }
+        
+        let end = extent.end().min(logical as u64 + count as u64);
+        let middle = Extent::new(logical, (end - logical as u64) as u32, extent.physical(logical));
+        let left = if logical > extent.block {
+            Some(Extent { len: logical - extent.block, ..extent })
+        } else {
+            None
+        };
+        let right = if end < extent.end() {
+            Some(Extent {
+                block: end as u32,
+                len: (extent.end() - end) as u32,
+                start: extent.start + (end - extent.block as u64),
+                uninit: true,
+            })
+        } else {
+            None
+        };
+        
+        // 元のエクステントを先頭の部分で置き換え、残りは挿入し直す
+        let extents = &mut path[leaf_level].node.extents;
+        match left {
+            Some(left) => extents[index] = left,
+            None => {
+                extents[index] = middle;
+                if right.is_none() && index + 1 < extents.len() && extents[index].can_append(&extents[index + 1]) {
+                    let next = extents.remove(index + 1);
+                    extents[index].len += next.len;
+                }
+                if index > 0 && extents[index - 1].can_append(&extents[index]) {
+                    let current = extents.remove(index);
+                    extents[index - 1].len += current.len;
+                }
+            },
+        }
+        self.write_level(&path[leaf_level])?;
+        
+        if left.is_some() {
+            self.insert(middle)?;
+        }
+        if let Some(right) = right {
+            self.insert(right)?;
+        }
+        
+        Ok(middle.len)
+    }
+    
+    /// `from`以降の論理ブロックをすべて解放する
+    ///
+    /// 空になったツリーブロックも解放し、ツリー全体が空になれば深さ0に戻す。
+    pub fn truncate(&mut self, from: u32) -> FsResult<()> {
+        let mut root = self.root.clone();
+        let result = self.truncate_node(&mut root, from);
+        
+        if root.is_empty() && !root.is_leaf() {
+            root = ExtentNode { generation: root.generation, ..ExtentNode::new_leaf(root.max) };
+        }
+        self.root = root;
         
         result
     }
     
-    /// 物理ブロック開始位置を取得（64ビット）
-    pub fn start(&self) -> u64 {
-        ((self.start_hi as u64) << 32) | (self.start_lo as u64)
+    /// ノード以下から`from`以降を取り除く
+    fn truncate_node(&mut self, node: &mut ExtentNode, from: u32) -> FsResult<()> {
+        if node.is_leaf() {
+            while let Some(last) = node.extents.last_mut() {
+                if last.end() <= from as u64 {
+                    break;
+                }
+                if last.block >= from {
+                    let extent = *last;
+                    node.extents.pop();
+                    self.store.free_blocks(extent.start, extent.len)?;
+                    self.data_freed += extent.len as u64;
+                } else {
+                    let keep = from - last.block;
+                    let freed = last.len - keep;
+                    last.len = keep;
+                    self.store.free_blocks(last.start + keep as u64, freed)?;
+                    self.data_freed += freed as u64;
+                    break;
+                }
+            }
+            return Ok(());
+        }
+        
+        // 後ろの子から処理し、`from`より前のエントリが残る子で止める
+        while let Some(index) = node.indexes.last().copied() {
+            let original = self.read_child(index.leaf(), node.depth - 1)?;
+            let mut child = original.clone();
+            let result = self.truncate_node(&mut child, from);
+            
+            if child.is_empty() {
+                node.indexes.pop();
+                self.store.free_blocks(index.leaf(), 1)?;
+                self.nodes_freed += 1;
+                result?;
+                continue;
+            }
+            
+            if child != original {
+                self.store.write_node(index.leaf(), &child)?;
+            }
+            result?;
+            break;
+        }
+        
+        Ok(())
     }
     
-    /// 新しいエクステントリーフを作成
-    pub fn new(block: u32, len: u16, start: u64) -> Self {
-        Self {
-            block,
-            len,
-            uninit: 0,
-            start_lo: start as u32,
-            start_hi: (start >> 32) as u16,
-            unused: 0,
+    /// ツリーが使っているすべてのブロック（データとツリーブロック）を(先頭, 数)で列挙
+    pub fn block_ranges(&self) -> FsResult<Vec<(u64, u32)>> {
+        let mut ranges = Vec::new();
+        self.collect_ranges(&self.root, &mut ranges)?;
+        Ok(ranges)
+    }
+    
+    /// リーフのエクステント（データブロックのみ）を論理ブロック順に列挙
+    pub fn extents(&self) -> FsResult<Vec<Extent>> {
+        let mut extents = Vec::new();
+        self.collect_extents(&self.root, &mut extents)?;
+        Ok(extents)
+    }
+    
+    /// ノード以下のリーフのエクステントを列挙
+    fn collect_extents(&self, node: &ExtentNode, extents: &mut Vec<Extent>) -> FsResult<()> {
+        if node.is_leaf() {
+            extents.extend_from_slice(&node.extents);
+            return Ok(());
         }
+        
+        for index in &node.indexes {
+            let child = self.read_child(index.leaf(), node.depth - 1)?;
+            self.collect_extents(&child, extents)?;
+        }
+        
+        Ok(())
     }
     
-    /// 初期化されていないブロックかどうか
-    pub fn is_uninitialized(&self) -> bool {
-        self.uninit != 0
+    /// ノード以下のブロックを列挙
+    fn collect_ranges(&self, node: &ExtentNode, ranges: &mut Vec<(u64, u32)>) -> FsResult<()> {
+        if node.is_leaf() {
+            ranges.extend(node.extents.iter().map(|extent| (extent.start, extent.len)));
+            return Ok(());
+        }
+        
+        for index in &node.indexes {
+            ranges.push((index.leaf(), 1));
+            let child = self.read_child(index.leaf(), node.depth - 1)?;
+            self.collect_ranges(&child, ranges)?;
+        }
+        
+        Ok(())
     }
 }
 
-/// エクステントツリーからファイル内の論理ブロックに対応する物理ブロックを見つける
-pub fn find_physical_block(
-    file_block: u32,
-    inode_data: &[u8],
-) -> FsResult<u64> {
-    // まずヘッダを解析
-    let header = ExtentHeader::parse(&inode_data[0..12])?;
+/// iノードのエクステントツリーをファイルシステム上で操作するための`ExtentStore`
+struct InodeExtents<'a> {
+    /// ファイルシステム
+    fs: &'a Ext4FileSystem,
+    /// ツリーブロックの割り当て先の目安
+    goal: u32,
+    /// ツリーブロックのチェックサムのシード（metadata_csumが無効なら`None`）
+    csum_seed: Option<u32>,
+}
+
+impl ExtentStore for InodeExtents<'_> {
+    fn node_capacity(&self) -> u16 {
+        node_capacity(self.fs.block_size)
+    }
     
-    if header.magic != 0xF30A {
-        return Err(FsError::InvalidData);
+    fn read_node(&self, block: u64) -> FsResult<ExtentNode> {
+        let mut data = vec![0u8; self.fs.block_size];
+        self.fs.read_block(block_u32(block)?, &mut data)?;
+        if let Some(seed) = self.csum_seed {
+            if !csum::verify_extent_block(seed, &data) {
+                log::error!("ext4: エクステントブロック{}のチェックサムが一致しません", block);
+                return Err(FsError::ChecksumError);
+            }
+        }
+        ExtentNode::parse(&data).map_err(|e| {
+            log::warn!("ext4: エクステントブロック{}が壊れています", block);
+            e
+        })
+    }
+    
+    fn write_node(&self, block: u64, node: &ExtentNode) -> FsResult<()> {
+        let mut data = vec![0u8; self.fs.block_size];
+        node.write(&mut data);
+        if let Some(seed) = self.csum_seed {
+            csum::set_extent_block_checksum(seed, &mut data);
+        }
+        Ok(self.fs.write_block(block_u32(block)?, &data)?)
     }
     
-    if header.is_leaf() {
-        // リーフノードの場合、直接エクステントを探す
-        return find_block_in_leaf(file_block, &inode_data[12..], header.entries);
-    } else {
-        // インデックスノードの場合、適切な子ノードを見つける
-        return find_block_in_index(file_block, &inode_data[12..], header.entries);
+    fn allocate_node(&self) -> FsResult<u64> {
+        Ok(self.fs.allocate_blocks(self.goal, 1)?.0 as u64)
+    }
+    
+    fn free_blocks(&self, start: u64, count: u32) -> FsResult<()> {
+        Ok(self.fs.free_blocks(block_u32(start)?, count)?)
     }
 }
 
-/// リーフノード内でブロックを探す
-fn find_block_in_leaf(file_block: u32, data: &[u8], entries: u16) -> FsResult<u64> {
-    for i in 0..entries {
-        let offset = i as usize * 12;
-        if offset + 12 > data.len() {
-            return Err(FsError::InvalidData);
+/// 物理ブロック番号を32ビットに変換（ブロックI/Oは32ビット番号のみ扱う）
+fn block_u32(block: u64) -> FsResult<u32> {
+    u32::try_from(block).map_err(|_| {
+        log::warn!("ext4: ブロック番号{}は未対応の範囲です", block);
+        FsError::UnsupportedFeature
+    })
+}
+
+impl Ext4FileSystem {
+    /// iノードの属するグループの先頭ブロック（割り当て先の目安）
+    pub(super) fn inode_goal(&self, inode: &Inode) -> u32 {
+        let sb = self.superblock.read().unwrap();
+        let group = (inode.get_number().max(1) - 1) / sb.get_inodes_per_group();
+        sb.first_data_block + group * sb.get_blocks_per_group()
+    }
+    
+    /// iノードのエクステントツリーを操作し、ルートとブロック数をiノードに反映する
+    ///
+    /// 操作が途中で失敗しても、それまでに割り当て・解放したツリーブロックは反映する。
+    fn with_extent_tree<T>(
+        &self,
+        inode: &mut Inode,
+        op: impl FnOnce(&mut ExtentTree, &InodeExtents) -> Result<T, Ext4Error>,
+    ) -> Result<T, Ext4Error> {
+        let store = InodeExtents {
+            fs: self,
+            goal: self.inode_goal(inode),
+            csum_seed: self.inode_csum_seed(inode),
+        };
+        let mut tree = ExtentTree::new(&store, &inode.block_bytes())?;
+        let result = op(&mut tree, &store);
+        
+        inode.set_block_bytes(&tree.root_bytes());
+        let node_delta = tree.nodes_allocated as i64 - tree.nodes_freed as i64;
+        let sectors = (self.block_size / 512) as i64;
+        inode.adjust_blocks((node_delta - tree.data_freed as i64) * sectors);
+        
+        result
+    }
+    
+    /// 論理ブロックを含むエクステントを取得（穴なら`None`）
+    ///
+    /// エクステントを使わないiノードは直接ブロックのみ扱い、1ブロックのエクステントとして返す。
+    pub(super) fn map_extent(&self, inode: &Inode, logical: u32) -> Result<Option<Extent>, Ext4Error> {
+        if !inode.has_extents() {
+            if logical >= DIRECT_BLOCKS {
+                log::warn!("ext4: iノード{}の間接ブロックは未対応です", inode.get_number());
+                return Err(Ext4Error::UnsupportedFeature);
+            }
+            let physical = inode.block[logical as usize];
+            return Ok(if physical == 0 { None } else { Some(Extent::new(logical, 1, physical as u64)) });
+        }
+        
+        let store = InodeExtents { fs: self, goal: 0, csum_seed: self.inode_csum_seed(inode) };
+        let tree = ExtentTree::new(&store, &inode.block_bytes())?;
+        Ok(tree.lookup(logical)?)
+    }
+    
+    /// 論理ブロックに対応する物理ブロックを取得（穴や未初期化なら0）
+    pub(super) fn map_block(&self, inode: &Inode, logical: u32) -> Result<u32, Ext4Error> {
+        match self.map_extent(inode, logical)? {
+            // 未初期化エクステントはゼロとして読む
+            Some(extent) if !extent.uninit => Ok(block_u32(extent.physical(logical))?),
+            _ => Ok(0),
         }
+    }
+    
+    /// 書き込み用に論理ブロックを割り当て、(物理ブロック, 新たに使えるようになったブロック数)を返す
+    ///
+    /// 穴なら`logical`から最大`max`ブロックを連続して割り当て、未初期化エクステントなら
+    /// 同じ範囲を初期化済みにする。新しいブロックの内容は不定なので、呼び出し側は
+    /// 部分書き込みでもディスクから読まずにゼロから埋めること。既存のブロックなら数は0。
+    pub(super) fn map_block_for_write(&self, inode: &mut Inode, logical: u32, max: u32) -> Result<(u32, u32), Ext4Error> {
+        let sectors = (self.block_size / 512) as i64;
         
-        let extent = ExtentLeaf::parse(&data[offset..offset+12])?;
+        if !inode.has_extents() {
+            if let Some(extent) = self.map_extent(inode, logical)? {
+                return Ok((extent.start as u32, 0));
+            }
+            let physical = self.allocate_blocks(self.inode_goal(inode), 1)?.0;
+            inode.block[logical as usize] = physical;
+            inode.adjust_blocks(sectors);
+            return Ok((physical, 1));
+        }
         
-        // ブロックがこのエクステント内にあるかチェック
-        if file_block >= extent.block && file_block < extent.block + extent.len as u32 {
-            let block_offset = file_block - extent.block;
-            return Ok(extent.start() + block_offset as u64);
+        let mut allocated = 0;
+        let result = self.with_extent_tree(inode, |tree, store| {
+            match tree.lookup(logical)? {
+                Some(extent) if !extent.uninit => Ok((block_u32(extent.physical(logical))?, 0)),
+                Some(extent) => {
+                    let count = tree.mark_initialized(logical, max)?;
+                    Ok((block_u32(extent.physical(logical))?, count))
+                },
+                None => {
+                    let mut limit = max.clamp(1, EXT_INIT_MAX_LEN);
+                    if let Some(next) = tree.next_allocated(logical)? {
+                        limit = limit.min(next - logical);
+                    }
+                    let goal = match tree.goal(logical)? {
+                        Some(goal) => block_u32(goal).unwrap_or(store.goal),
+                        None => store.goal,
+                    };
+                    
+                    let (start, count) = self.allocate_blocks(goal, limit)?;
+                    if let Err(e) = tree.insert(Extent::new(logical, count, start as u64)) {
+                        self.free_blocks(start, count)?;
+                        return Err(e.into());
+                    }
+                    allocated = count;
+                    Ok((start, count))
+                },
+            }
+        });
+        inode.adjust_blocks(allocated as i64 * sectors);
+        
+        result
+    }
+    
+    /// 割り当て済みの物理ブロックをエクステントとしてiノードに追加
+    ///
+    /// データブロックのブロック数は呼び出し側で加算する（ツリーブロックの分はここで加算する）。
+    pub(super) fn insert_extent(&self, inode: &mut Inode, extent: Extent) -> Result<(), Ext4Error> {
+        if !inode.has_extents() {
+            if extent.end() > DIRECT_BLOCKS as u64 {
+                log::warn!("ext4: iノード{}の間接ブロックは未対応です", inode.get_number());
+                return Err(Ext4Error::UnsupportedFeature);
+            }
+            let slots = extent.block as usize..extent.end() as usize;
+            if inode.block[slots.clone()].iter().any(|&block| block != 0) {
+                return Err(Ext4Error::AlreadyExists);
+            }
+            for (i, slot) in slots.enumerate() {
+                inode.block[slot] = block_u32(extent.start + i as u64)?;
+            }
+            return Ok(());
         }
+        
+        self.with_extent_tree(inode, |tree, _| Ok(tree.insert(extent)?))
     }
     
-    // ブロックが見つからない場合
-    Err(FsError::NotFound)
-}
-
-/// インデックスノード内で適切な子ノードを探す
-fn find_block_in_index(file_block: u32, data: &[u8], entries: u16) -> FsResult<u64> {
-    for i in 0..entries {
-        // エントリの開始オフセット計算: ヘッダ(12byte) + エントリインデックス × エントリサイズ(12byte)
-        let offset = 12 + (i as usize) * 12;
+    /// iノードが使っているすべてのブロック（ツリーブロックを含む）を(先頭, 数)で列挙
+    pub(super) fn inode_block_ranges(&self, inode: &Inode) -> Result<Vec<(u32, u32)>, Ext4Error> {
+        if !inode.has_extents() {
+            if inode.block[DIRECT_BLOCKS as usize..].iter().any(|&block| block != 0) {
+                log::warn!("ext4: iノード{}の間接ブロックは未対応です", inode.get_number());
+                return Err(Ext4Error::UnsupportedFeature);
+            }
+            return Ok(inode.block[..DIRECT_BLOCKS as usize].iter()
+                .filter(|&&block| block != 0)
+                .map(|&block| (block, 1))
+                .collect());
+        }
         
-        if offset + 12 > data.len() {
-            return Err(FsError::InvalidFormat);
+        let store = InodeExtents { fs: self, goal: 0, csum_seed: self.inode_csum_seed(inode) };
+        let tree = ExtentTree::new(&store, &inode.block_bytes())?;
+        let mut ranges = Vec::new();
+        for (start, count) in tree.block_ranges()? {
+            ranges.push((block_u32(start)?, count));
+        }
+        Ok(ranges)
+    }
+    
+    /// iノードのデータのエクステント（ツリーブロックを除く）を論理ブロック順に列挙
+    ///
+    /// エクステントを使わないiノードは直接ブロックを1ブロックのエクステントとして返す。
+    pub(super) fn inode_extents(&self, inode: &Inode) -> Result<Vec<Extent>, Ext4Error> {
+        if !inode.has_extents() {
+            // 間接ブロックを使っていないことを先に確かめる
+            self.inode_block_ranges(inode)?;
+            return Ok(inode.block[..DIRECT_BLOCKS as usize].iter()
+                .enumerate()
+                .filter(|(_, &block)| block != 0)
+                .map(|(logical, &block)| Extent::new(logical as u32, 1, block as u64))
+                .collect());
         }
         
-        // エクステントインデックスからデータを抽出
-        let ee_block = u32::from_le_bytes([
-            data[offset], data[offset + 1], data[offset + 2], data[offset + 3]
-        ]);
+        let store = InodeExtents { fs: self, goal: 0, csum_seed: self.inode_csum_seed(inode) };
+        let tree = ExtentTree::new(&store, &inode.block_bytes())?;
+        Ok(tree.extents()?)
+    }
+    
+    /// ファイルを`new_size`バイトに切り詰め、不要になったブロックを解放する
+    ///
+    /// 残る最後のブロックのEOF以降はゼロにし、後で拡張したときに古いデータが見えないようにする。
+    /// iノードの書き戻しは呼び出し側で行う。
+    pub(super) fn truncate_blocks(&self, inode: &mut Inode, new_size: u64) -> Result<(), Ext4Error> {
+        let block_size = self.block_size as u64;
+        let keep = u32::try_from(new_size.div_ceil(block_size)).map_err(|_| Ext4Error::InvalidArgument)?;
         
-        let ee_len = u16::from_le_bytes([
-            data[offset + 4], data[offset + 5]
-        ]);
+        let tail = (new_size % block_size) as usize;
+        if tail != 0 {
+            let physical = self.map_block(inode, keep - 1)?;
+            if physical != 0 {
+                let mut data = vec![0u8; self.block_size];
+                self.read_block(physical, &mut data)?;
+                data[tail..].fill(0);
+                self.write_block(physical, &data)?;
+            }
+        }
         
-        let ee_start_hi = u16::from_le_bytes([
-            data[offset + 6], data[offset + 7]
-        ]);
+        if inode.has_extents() {
+            self.with_extent_tree(inode, |tree, _| Ok(tree.truncate(keep)?))?;
+        } else if inode.blocks != 0 {
+            // 間接ブロックを使っていないことを先に確かめる
+            self.inode_block_ranges(inode)?;
+            for slot in keep.min(DIRECT_BLOCKS)..DIRECT_BLOCKS {
+                let block = core::mem::replace(&mut inode.block[slot as usize], 0);
+                if block != 0 {
+                    self.free_blocks(block, 1)?;
+                    inode.adjust_blocks(-((self.block_size / 512) as i64));
+                }
+            }
+        }
         
-        let ee_start_lo = u32::from_le_bytes([
-            data[offset + 8], data[offset + 9], data[offset + 10], data[offset + 11]
-        ]);
+        inode.set_size(new_size);
+        Ok(())
+    }
+    
+    /// ファイルの`[offset, offset + len)`に未初期化エクステントを事前割り当てする
+    ///
+    /// すでに割り当て済みの部分はそのまま残す。範囲がファイル末尾を超えればサイズを拡張する。
+    pub fn fallocate(&self, inode_num: u32, offset: u64, len: u64) -> Result<(), Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
         
-        let ee_start = ((ee_start_hi as u64) << 32) | (ee_start_lo as u64);
+        if len == 0 {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        let mut inode = self.get_inode(inode_num)?;
+        if !inode.is_regular_file() {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        if !inode.has_extents() {
+            return Err(Ext4Error::UnsupportedFeature);
+        }
         
-        // 指定されたブロックが現在のエクステント範囲内かチェック
-        if file_block >= ee_block && file_block < ee_block + (ee_len as u32) {
-            // デバイスマネージャからデバイスを取得
-            let device_manager = crate::drivers::block::get_device_manager();
-            let device_id = get_current_device_id(); // 現在処理中のデバイスID取得関数（実装必要）
-            
-            if let Some(device) = device_manager.get_device(device_id) {
-                // 子ノードブロックを読み込み
-                match device.read_block(ee_start) {
-                    Ok(node_data) => {
-                        // 読み込んだノードを解析
-                        return parse_extent_node(file_block, &node_data);
-                    },
-                    Err(_) => {
-                        return Err(FsError::IoError);
-                    }
+        let block_size = self.block_size as u64;
+        let end = offset.checked_add(len).ok_or(Ext4Error::InvalidArgument)?;
+        let first = (offset / block_size) as u32;
+        let last = u32::try_from((end - 1) / block_size).map_err(|_| Ext4Error::InvalidArgument)?;
+        let sectors = (self.block_size / 512) as i64;
+        
+        let mut logical = first;
+        let mut allocated = 0u64;
+        let result = self.with_extent_tree(&mut inode, |tree, store| {
+            while logical <= last {
+                if let Some(extent) = tree.lookup(logical)? {
+                    logical = extent.end().min(last as u64 + 1) as u32;
+                    continue;
                 }
-            } else {
-                return Err(FsError::DeviceNotFound);
+                
+                let mut limit = (last - logical + 1).min(EXT_UNINIT_MAX_LEN);
+                if let Some(next) = tree.next_allocated(logical)? {
+                    limit = limit.min(next - logical);
+                }
+                let goal = match tree.goal(logical)? {
+                    Some(goal) => block_u32(goal).unwrap_or(store.goal),
+                    None => store.goal,
+                };
+                
+                let (start, count) = self.allocate_blocks(goal, limit)?;
+                let extent = Extent { uninit: true, ..Extent::new(logical, count, start as u64) };
+                if let Err(e) = tree.insert(extent) {
+                    self.free_blocks(start, count)?;
+                    return Err(e.into());
+                }
+                allocated += count as u64;
+                logical += count;
             }
+            Ok(())
+        });
+        inode.adjust_blocks(allocated as i64 * sectors);
+        
+        if result.is_ok() && end > inode.get_size() {
+            inode.set_size(end);
         }
+        inode.set_ctime(self.get_current_time());
+        self.update_inode(&inode)?;
+        
+        result
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use core::cell::RefCell;
     
-    Err(FsError::FileBlockNotFound)
-} 
\ No newline at end of file
+    /// メモリ上のツリーブロック（容量を小さくして分割を起こしやすくする）
+    struct MemoryStore {
+        nodes: RefCell<BTreeMap<u64, ExtentNode>>,
+        next: RefCell<u64>,
+        freed: RefCell<Vec<(u64, u32)>>,
+    }
+    
+    impl MemoryStore {
+        fn new() -> Self {
+            Self { nodes: RefCell::new(BTreeMap::new()), next: RefCell::new(1_000_000), freed: RefCell::new(Vec::new()) }
+        }
+    }
+    
+    impl ExtentStore for MemoryStore {
+        fn node_capacity(&self) -> u16 {
+            4
+        }
+        
+        fn read_node(&self, block: u64) -> FsResult<ExtentNode> {
+            self.nodes.borrow().get(&block).cloned().ok_or(FsError::CorruptedFs)
+        }
+        
+        fn write_node(&self, block: u64, node: &ExtentNode) -> FsResult<()> {
+            self.nodes.borrow_mut().insert(block, node.clone());
+            Ok(())
+        }
+        
+        fn allocate_node(&self) -> FsResult<u64> {
+            let mut next = self.next.borrow_mut();
+            *next += 1;
+            Ok(*next)
+        }
+        
+        fn free_blocks(&self, start: u64, count: u32) -> FsResult<()> {
+            if count == 1 {
+                self.nodes.borrow_mut().remove(&start);
+            }
+            self.freed.borrow_mut().push((start, count));
+            Ok(())
+        }
+    }
+    
+    fn empty_root() -> [u8; INODE_BLOCK_SIZE] {
+        let mut data = [0u8; INODE_BLOCK_SIZE];
+        ExtentNode::new_leaf(INLINE_EXTENT_MAX).write(&mut data);
+        data
+    }
+    
+    #[test]
+    fn test_extent_encoding() {
+        let extent = Extent { uninit: true, ..Extent::new(7, 100, 0x1_2345_6789) };
+        let mut data = [0u8; 12];
+        extent.write(&mut data);
+        assert_eq!(u16::from_le_bytes([data[4], data[5]]), 32868);
+        assert_eq!(Extent::parse(&data), extent);
+        
+        // 連続していても初期化状態が違えば結合しない
+        let next = Extent::new(107, 1, 0x1_2345_6789 + 100);
+        assert!(!extent.can_append(&next));
+        assert!(Extent { uninit: false, ..extent }.can_append(&next));
+    }
+    
+    #[test]
+    fn test_insert_split_grow_and_truncate() {
+        let store = MemoryStore::new();
+        let mut tree = ExtentTree::new(&store, &empty_root()).unwrap();
+        
+        // 物理的に不連続な1ブロックずつの順次書き込みで分割と深さの拡張を起こす
+        for i in 0..200u32 {
+            tree.insert(Extent::new(i * 2, 1, 10 * i as u64)).unwrap();
+        }
+        assert!(tree.depth() >= 3);
+        // 逆順の挿入で先頭側の分割とインデックスの補正を起こす
+        for i in (0..200u32).rev() {
+            tree.insert(Extent::new(i * 2 + 1, 1, 10 * i as u64 + 5)).unwrap();
+        }
+        for i in 0..400u32 {
+            let expected = 10 * (i / 2) as u64 + 5 * (i % 2) as u64;
+            assert_eq!(tree.lookup(i).unwrap().map(|e| e.physical(i)), Some(expected));
+        }
+        assert!(tree.insert(Extent::new(10, 1, 5)).is_err());
+        assert_eq!(tree.lookup(400).unwrap(), None);
+        assert_eq!(tree.next_allocated(400).unwrap(), None);
+        
+        // 連続するエクステントは結合される
+        tree.insert(Extent::new(1000, 10, 50_000)).unwrap();
+        tree.insert(Extent::new(1010, 10, 50_010)).unwrap();
+        assert_eq!(tree.lookup(1015).unwrap(), Some(Extent::new(1000, 20, 50_000)));
+        
+        // 途中で切り詰めると後ろ半分のデータとツリーブロックが解放される
+        tree.truncate(1005).unwrap();
+        assert_eq!(tree.lookup(1004).unwrap(), Some(Extent::new(1000, 5, 50_000)));
+        tree.truncate(101).unwrap();
+        assert_eq!(tree.lookup(100).unwrap().map(|e| e.physical(100)), Some(500));
+        assert_eq!(tree.lookup(101).unwrap(), None);
+        assert_eq!(tree.data_freed, 20 + 299);
+        
+        // 全部切り詰めるとツリーブロックはすべて解放され、深さ0に戻る
+        tree.truncate(0).unwrap();
+        assert_eq!(tree.depth(), 0);
+        assert!(store.nodes.borrow().is_empty());
+        assert_eq!(tree.nodes_allocated, tree.nodes_freed);
+    }
+    
+    #[test]
+    fn test_mark_initialized_splits_and_merges() {
+        let store = MemoryStore::new();
+        let mut tree = ExtentTree::new(&store, &empty_root()).unwrap();
+        tree.insert(Extent { uninit: true, ..Extent::new(0, 100, 1000) }).unwrap();
+        
+        // 中央を初期化すると未初期化・初期化済み・未初期化の3つに分かれる
+        assert_eq!(tree.mark_initialized(10, 5).unwrap(), 5);
+        assert_eq!(tree.lookup(9).unwrap(), Some(Extent { uninit: true, ..Extent::new(0, 10, 1000) }));
+        assert_eq!(tree.lookup(12).unwrap(), Some(Extent::new(10, 5, 1010)));
+        assert_eq!(tree.lookup(15).unwrap(), Some(Extent { uninit: true, ..Extent::new(15, 85, 1015) }));
+        
+        // 続けて初期化すると直前の初期化済みエクステントに結合される
+        assert_eq!(tree.mark_initialized(15, 10).unwrap(), 10);
+        assert_eq!(tree.lookup(24).unwrap(), Some(Extent::new(10, 15, 1010)));
+        
+        // 範囲はエクステントの末尾で打ち切られる
+        assert_eq!(tree.mark_initialized(25, 1000).unwrap(), 75);
+        assert_eq!(tree.lookup(99).unwrap(), Some(Extent::new(10, 90, 1010)));
+    }
+}
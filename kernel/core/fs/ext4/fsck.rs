@@ -0,0 +1,726 @@
+// Ext4 整合性チェック
+//
+// 孤立iノードリストの処理、ブロックの二重使用、ブロックビットマップと空き数、
+// ディレクトリツリーの到達可能性、リンク数を検査し、必要なら修復する
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use super::super::{FsckProblem, FsckReport};
+use super::{Ext4FileSystem, Ext4Error};
+use super::bitmap::Bitmap;
+use super::dir::DirectoryEntryType;
+use super::group::BlockGroupDescriptor;
+use super::group::flags as group_flags;
+use super::namei::{ROOT_INODE, MAX_LINK_COUNT};
+use super::superblock::{
+    FEATURE_COMPAT_RESIZE_INODE, FEATURE_COMPAT_SPARSE_SUPER2, FEATURE_INCOMPAT_META_BG,
+    FEATURE_INCOMPAT_MMP, FEATURE_RO_COMPAT_SPARSE_SUPER, FEATURE_RO_COMPAT_DIR_NLINK,
+};
+
+/// オンライン拡張用の予約GDTブロックを保持するiノード
+const RESIZE_INODE: u32 = 7;
+/// resize iノードの二重間接ブロックのスロット
+const DOUBLE_INDIRECT: usize = 13;
+/// 切り離されたファイルを入れるディレクトリ
+const LOST_AND_FOUND: &str = "lost+found";
+
+/// チェックに使うファイルシステムの配置
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    /// ブロックサイズ（バイト）
+    block_size: u64,
+    /// 総ブロック数
+    total_blocks: u64,
+    /// 最初のデータブロック
+    first_data_block: u64,
+    /// グループあたりのブロック数
+    blocks_per_group: u64,
+    /// グループ数
+    group_count: u32,
+    /// グループあたりのiノード数
+    inodes_per_group: u32,
+    /// 最初の非予約iノード
+    first_inode: u32,
+    /// GDTのブロック数
+    gdt_blocks: u64,
+    /// 予約GDTブロック数
+    reserved_gdt_blocks: u64,
+    /// グループあたりのiノードテーブルのブロック数
+    inode_table_blocks: u64,
+    /// スーパーブロックのバックアップを一部のグループにだけ置く
+    sparse_super: bool,
+}
+
+impl Geometry {
+    /// グループの先頭ブロック
+    fn group_start(&self, group: u32) -> u64 {
+        self.first_data_block + group as u64 * self.blocks_per_group
+    }
+    
+    /// グループのブロック数（最後のグループは短いことがある）
+    fn group_len(&self, group: u32) -> u64 {
+        self.blocks_per_group.min(self.total_blocks - self.group_start(group))
+    }
+}
+
+/// グループがスーパーブロックとGDTのコピーを持つか（sparse_superでは0、1と3・5・7の累乗）
+fn has_superblock(group: u32, sparse_super: bool) -> bool {
+    if !sparse_super || group <= 1 {
+        return true;
+    }
+    [3u64, 5, 7].iter().any(|&base| {
+        let mut power = base;
+        while power < group as u64 {
+            power *= base;
+        }
+        power == group as u64
+    })
+}
+
+/// グループのメタデータ（スーパーブロック・GDT・ビットマップ・iノードテーブル）を(先頭, 数)で列挙
+///
+/// flex_bgではビットマップとiノードテーブルが他のグループに置かれることがある。
+fn group_metadata(geometry: &Geometry, group: u32, desc: &BlockGroupDescriptor) -> Vec<(u64, u64)> {
+    let mut ranges = Vec::with_capacity(4);
+    if has_superblock(group, geometry.sparse_super) {
+        ranges.push((geometry.group_start(group), 1 + geometry.gdt_blocks + geometry.reserved_gdt_blocks));
+    }
+    ranges.push((desc.get_block_bitmap_block(), 1));
+    ranges.push((desc.get_inode_bitmap_block(), 1));
+    ranges.push((desc.get_inode_table_block(), geometry.inode_table_blocks));
+    ranges
+}
+
+/// ブロックの使用状況（ブロック番号で引くビットマップ）
+struct BlockUsage {
+    /// 参照されているブロック
+    used: Vec<u8>,
+    /// 2回以上参照されたブロック
+    duplicate: Vec<u8>,
+    /// 総ブロック数
+    total: u64,
+    /// 参照されているブロック数
+    count: u64,
+    /// 二重に参照されたブロック数
+    duplicates: u64,
+}
+
+impl BlockUsage {
+    /// すべて未使用の状態で作成
+    fn new(total: u64) -> Self {
+        let bytes = total.div_ceil(8) as usize;
+        Self { used: vec![0; bytes], duplicate: vec![0; bytes], total, count: 0, duplicates: 0 }
+    }
+    
+    /// 範囲を使用中にする（範囲がファイルシステムの外なら`false`）
+    fn mark(&mut self, start: u64, count: u64) -> bool {
+        if start.checked_add(count).is_none_or(|end| end > self.total) {
+            return false;
+        }
+        for block in start..start + count {
+            let bit = block as usize;
+            if !Bitmap::check_bit(&self.used, bit) {
+                Bitmap::set_bit(&mut self.used, bit);
+                self.count += 1;
+            } else if !Bitmap::check_bit(&self.duplicate, bit) {
+                Bitmap::set_bit(&mut self.duplicate, bit);
+                self.duplicates += 1;
+            }
+        }
+        true
+    }
+    
+    /// ブロックが参照されているか
+    fn is_used(&self, block: u64) -> bool {
+        block < self.total && Bitmap::check_bit(&self.used, block as usize)
+    }
+    
+    /// ブロックが二重に参照されているか
+    fn is_duplicate(&self, block: u64) -> bool {
+        block < self.total && Bitmap::check_bit(&self.duplicate, block as usize)
+    }
+    
+    /// `first`から`len`ブロックのグループのビットマップと比べ、
+    /// （使用中なのに空きの数, 参照されないのに使用中の数, 使用中の数）を返す
+    fn compare(&self, bitmap: &[u8], first: u64, len: u64) -> (u32, u32, u64) {
+        let mut marked_free = 0;
+        let mut unreferenced = 0;
+        let mut used = 0;
+        for bit in 0..len {
+            let in_use = self.is_used(first + bit);
+            match (in_use, Bitmap::check_bit(bitmap, bit as usize)) {
+                (true, false) => marked_free += 1,
+                (false, true) => unreferenced += 1,
+                _ => {},
+            }
+            used += in_use as u64;
+        }
+        (marked_free, unreferenced, used)
+    }
+    
+    /// `first`から`len`ブロックのグループのビットマップを作る（グループ外の末尾は使用中で埋める）
+    fn group_bitmap(&self, first: u64, len: u64, size: usize) -> Vec<u8> {
+        let mut bitmap = vec![0u8; size];
+        for bit in 0..size as u64 * 8 {
+            if bit >= len || self.is_used(first + bit) {
+                Bitmap::set_bit(&mut bitmap, bit as usize);
+            }
+        }
+        bitmap
+    }
+}
+
+/// 名前空間の検査対象のiノード
+#[derive(Debug, Clone, Copy)]
+struct InodeState {
+    /// ディレクトリか
+    is_dir: bool,
+    /// 見つかった参照（ディレクトリの"."と".."を含む）
+    refs: u32,
+    /// ルートからたどれたか
+    reached: bool,
+}
+
+/// チェック中の状態
+struct Checker<'a> {
+    /// 対象のファイルシステム
+    fs: &'a Ext4FileSystem,
+    /// 見つかった問題を修復するか
+    repair: bool,
+    /// 配置
+    geometry: Geometry,
+    /// ブロックの使用状況
+    usage: BlockUsage,
+    /// 使用中のiノード（予約iノードはルートのみ）
+    inodes: BTreeMap<u32, InodeState>,
+    /// 孤立iノードリストにあったiノード
+    orphans: BTreeSet<u32>,
+    /// lost+foundのiノード番号
+    lost_and_found: Option<u32>,
+    /// 結果
+    report: FsckReport,
+}
+
+impl<'a> Checker<'a> {
+    /// スーパーブロックから配置を読み取って作成
+    fn new(fs: &'a Ext4FileSystem, repair: bool) -> Result<Self, Ext4Error> {
+        let geometry = {
+            let sb = fs.superblock.read().unwrap();
+            if sb.has_feature_incompat(FEATURE_INCOMPAT_META_BG) || sb.has_feature_compat(FEATURE_COMPAT_SPARSE_SUPER2) {
+                log::warn!("ext4: meta_bg/sparse_super2のファイルシステムのチェックには対応していません");
+                return Err(Ext4Error::UnsupportedFeature);
+            }
+            let block_size = fs.block_size as u64;
+            Geometry {
+                block_size,
+                total_blocks: sb.total_blocks(),
+                first_data_block: sb.first_data_block as u64,
+                blocks_per_group: sb.get_blocks_per_group() as u64,
+                group_count: sb.group_count(),
+                inodes_per_group: sb.get_inodes_per_group(),
+                first_inode: sb.first_inode,
+                gdt_blocks: (sb.group_count() as u64 * sb.get_desc_size() as u64).div_ceil(block_size),
+                reserved_gdt_blocks: sb.reserved_gdt_blocks as u64,
+                inode_table_blocks: (sb.get_inodes_per_group() as u64 * fs.inode_size as u64).div_ceil(block_size),
+                sparse_super: sb.has_feature_ro_compat(FEATURE_RO_COMPAT_SPARSE_SUPER),
+            }
+        };
+        
+        Ok(Self {
+            fs,
+            repair,
+            usage: BlockUsage::new(geometry.total_blocks),
+            geometry,
+            inodes: BTreeMap::new(),
+            orphans: BTreeSet::new(),
+            lost_and_found: None,
+            report: FsckReport::default(),
+        })
+    }
+    
+    /// 孤立iノードリストをたどり、修復時は削除途中のiノードを解放、切り詰め途中のものを切り詰める
+    fn process_orphans(&mut self) -> Result<bool, Ext4Error> {
+        let (mut ino, inode_count) = {
+            let sb = self.fs.superblock.read().unwrap();
+            (sb.last_orphan, sb.inode_count)
+        };
+        let had_orphans = ino != 0;
+        
+        while ino != 0 {
+            if ino > inode_count || !self.orphans.insert(ino) {
+                log::warn!("ext4: 孤立iノードリストが壊れています（iノード{}）", ino);
+                break;
+            }
+            let mut inode = self.fs.read_inode(ino)?;
+            // 孤立リストではdtimeが次のiノードを指す
+            let next = inode.dtime;
+            let unlinked = inode.links_count == 0;
+            if self.repair {
+                if unlinked {
+                    let blocks = self.fs.collect_data_blocks(&inode)?;
+                    let is_dir = inode.is_directory();
+                    self.fs.release_inode(&mut inode, &blocks, is_dir)?;
+                } else {
+                    let size = inode.get_size();
+                    self.fs.truncate_blocks(&mut inode, size)?;
+                    inode.dtime = 0;
+                    self.fs.update_inode(&inode)?;
+                }
+            }
+            self.report.record(FsckProblem::Orphan { inode: ino as u64, unlinked }, self.repair);
+            ino = next;
+        }
+        
+        if self.repair && had_orphans {
+            let mut sb = self.fs.superblock.write().unwrap();
+            sb.last_orphan = 0;
+            self.fs.write_superblock(&sb)?;
+        }
+        Ok(self.repair && had_orphans)
+    }
+    
+    /// メタデータと使用中のiノードが参照するブロックを列挙し、`visit(所有者, 先頭, 数)`を呼ぶ
+    ///
+    /// 所有者はメタデータなら0、それ以外はiノード番号。名前空間の検査対象のiノードを`inodes`に集める。
+    fn scan(&self, inodes: &mut BTreeMap<u32, InodeState>, visit: &mut dyn FnMut(u32, u64, u64) -> Result<(), Ext4Error>) -> Result<(), Ext4Error> {
+        let geometry = self.geometry;
+        let (mmp_block, resize_inode) = {
+            let sb = self.fs.superblock.read().unwrap();
+            let mmp = if sb.has_feature_incompat(FEATURE_INCOMPAT_MMP) { sb.mmp_block } else { 0 };
+            (mmp, sb.has_feature_compat(FEATURE_COMPAT_RESIZE_INODE))
+        };
+        
+        let mut descriptors = Vec::with_capacity(geometry.group_count as usize);
+        for group in 0..geometry.group_count {
+            let desc = self.fs.read_block_group_descriptor(group)?;
+            for (start, count) in group_metadata(&geometry, group, &desc) {
+                visit(0, start, count)?;
+            }
+            descriptors.push(desc);
+        }
+        if mmp_block != 0 {
+            visit(0, mmp_block, 1)?;
+        }
+        
+        let mut bitmap = vec![0u8; self.fs.block_size];
+        let mut shared_xattr = BTreeSet::new();
+        for (group, desc) in descriptors.iter().enumerate() {
+            if desc.has_flag(group_flags::INODE_UNINIT) {
+                continue;
+            }
+            self.fs.read_block(block_u32(desc.get_inode_bitmap_block())?, &mut bitmap)?;
+            for bit in 0..geometry.inodes_per_group {
+                if !Bitmap::check_bit(&bitmap, bit as usize) {
+                    continue;
+                }
+                let ino = group as u32 * geometry.inodes_per_group + bit + 1;
+                let inode = self.fs.read_inode(ino)?;
+                if ino == RESIZE_INODE && resize_inode {
+                    // 予約GDTブロック自体はグループのメタデータに含まれる
+                    if inode.block[DOUBLE_INDIRECT] != 0 {
+                        visit(ino, inode.block[DOUBLE_INDIRECT] as u64, 1)?;
+                    }
+                    continue;
+                }
+                if inode.mode == 0 && ino >= geometry.first_inode {
+                    continue;
+                }
+                
+                for (start, count) in self.fs.collect_data_blocks(&inode)? {
+                    visit(ino, start as u64, count as u64)?;
+                }
+                // EAブロックは複数のiノードで共有される
+                let xattr = inode.file_acl();
+                if xattr != 0 && shared_xattr.insert(xattr) {
+                    visit(ino, xattr, 1)?;
+                }
+                
+                if (ino == ROOT_INODE || ino >= geometry.first_inode) && inode.links_count > 0 {
+                    inodes.insert(ino, InodeState { is_dir: inode.is_directory(), refs: 0, reached: false });
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// 使用状況を集め直す
+    fn collect_usage(&mut self) -> Result<(), Ext4Error> {
+        let mut usage = BlockUsage::new(self.geometry.total_blocks);
+        let mut inodes = BTreeMap::new();
+        self.scan(&mut inodes, &mut |owner, start, count| {
+            if usage.mark(start, count) {
+                Ok(())
+            } else {
+                log::error!("ext4: ブロック{}から{}ブロックが範囲外です（所有者{}）", start, count, owner);
+                Err(Ext4Error::InvalidBlock)
+            }
+        })?;
+        self.usage = usage;
+        self.inodes = inodes;
+        Ok(())
+    }
+    
+    /// 二重に使われたブロックを報告し、修復時は後から参照したファイルをそのブロックの手前で切り詰める
+    fn check_duplicates(&mut self) -> Result<bool, Ext4Error> {
+        if self.usage.duplicates == 0 {
+            return Ok(false);
+        }
+        
+        let mut owners: BTreeMap<u64, Vec<u32>> = BTreeMap::new();
+        let usage = &self.usage;
+        self.scan(&mut BTreeMap::new(), &mut |owner, start, count| {
+            for block in (start..start + count).filter(|&block| usage.is_duplicate(block)) {
+                owners.entry(block).or_default().push(owner);
+            }
+            Ok(())
+        })?;
+        
+        // 最初の所有者以外の通常のiノードからブロックを取り上げる
+        let mut lose: BTreeMap<u32, BTreeSet<u64>> = BTreeMap::new();
+        for (&block, list) in &owners {
+            for &owner in &list[1..] {
+                if owner >= self.geometry.first_inode && owner != list[0] {
+                    lose.entry(owner).or_default().insert(block);
+                }
+            }
+        }
+        
+        let mut released = BTreeSet::new();
+        if self.repair {
+            for (&ino, blocks) in &lose {
+                let mut inode = self.fs.read_inode(ino)?;
+                let mut cut: Option<u32> = None;
+                for extent in self.fs.inode_extents(&inode)? {
+                    for i in 0..extent.len {
+                        let physical = extent.start + i as u64;
+                        if blocks.contains(&physical) {
+                            released.insert((ino, physical));
+                            let logical = extent.block + i;
+                            cut = Some(cut.map_or(logical, |cut| cut.min(logical)));
+                        }
+                    }
+                }
+                // エクステントツリーのブロックが重なっている場合は切り詰めでは直せない
+                if let Some(cut) = cut {
+                    let size = inode.get_size().min(cut as u64 * self.geometry.block_size);
+                    self.fs.truncate_blocks(&mut inode, size)?;
+                    self.fs.update_inode(&inode)?;
+                }
+            }
+        }
+        
+        for (block, list) in owners {
+            let repaired = self.repair && list[1..].iter().all(|&owner| released.contains(&(owner, block)));
+            let owners = list.into_iter().map(|owner| owner as u64).collect();
+            self.report.record(FsckProblem::DuplicateBlock { block, owners }, repaired);
+        }
+        Ok(!released.is_empty())
+    }
+    
+    /// ブロックビットマップと空きブロック数を使用状況と比べる
+    fn check_bitmaps(&mut self) -> Result<(), Ext4Error> {
+        let geometry = self.geometry;
+        let mut bitmap = vec![0u8; self.fs.block_size];
+        let mut total_free = 0;
+        
+        for group in 0..geometry.group_count {
+            let mut desc = self.fs.read_block_group_descriptor(group)?;
+            let first = geometry.group_start(group);
+            let len = geometry.group_len(group);
+            
+            // 未初期化のビットマップはグループ自身のメタデータだけが使用中とみなされる
+            let uninit = desc.has_flag(group_flags::BLOCK_UNINIT);
+            let mut bad_checksum = false;
+            if uninit {
+                bitmap.fill(0);
+                for (start, count) in group_metadata(&geometry, group, &desc) {
+                    for block in (start..start + count).filter(|&block| block >= first && block < first + len) {
+                        Bitmap::set_bit(&mut bitmap, (block - first) as usize);
+                    }
+                }
+            } else {
+                self.fs.read_block(block_u32(desc.get_block_bitmap_block())?, &mut bitmap)?;
+                bad_checksum = self.fs.verify_block_bitmap(group, &desc, &bitmap).is_err();
+            }
+            
+            let (marked_free, unreferenced, used) = self.usage.compare(&bitmap, first, len);
+            let free = (len - used) as u32;
+            total_free += free as u64;
+            let mut changed = false;
+            if marked_free != 0 || unreferenced != 0 || bad_checksum {
+                if self.repair {
+                    let rebuilt = self.usage.group_bitmap(first, len, self.fs.block_size);
+                    self.fs.write_block(block_u32(desc.get_block_bitmap_block())?, &rebuilt)?;
+                    self.fs.update_block_bitmap_checksum(&mut desc, &rebuilt);
+                    desc.clear_flag(group_flags::BLOCK_UNINIT);
+                    changed = true;
+                }
+                self.report.record(FsckProblem::BlockBitmap { group, marked_free, unreferenced }, self.repair);
+            }
+            
+            let recorded = desc.get_free_blocks_count();
+            if recorded != free {
+                if self.repair {
+                    desc.set_free_blocks_count(free);
+                    changed = true;
+                }
+                self.report.record(FsckProblem::FreeCount { group: Some(group), recorded: recorded as u64, actual: free as u64 }, self.repair);
+            }
+            if changed {
+                self.fs.write_block_group_descriptor(group, &desc)?;
+            }
+        }
+        
+        let mut sb = self.fs.superblock.write().unwrap();
+        let recorded = sb.get_free_blocks_count();
+        if recorded != total_free {
+            if self.repair {
+                sb.set_free_blocks_count(total_free);
+                self.fs.write_superblock(&sb)?;
+            }
+            self.report.record(FsckProblem::FreeCount { group: None, recorded, actual: total_free }, self.repair);
+        }
+        Ok(())
+    }
+    
+    /// `start`から到達できるディレクトリをたどり、参照を数える
+    ///
+    /// 使われていないiノードや予約iノードを指すエントリは報告し、修復時は削除する。
+    fn traverse(&mut self, start: u32) -> Result<(), Ext4Error> {
+        if let Some(state) = self.inodes.get_mut(&start) {
+            state.reached = true;
+        }
+        let mut pending = vec![start];
+        
+        while let Some(dir_ino) = pending.pop() {
+            let mut dir = self.fs.get_inode(dir_ino)?;
+            for entry in self.fs.read_dir_entries(&dir)? {
+                let dot = entry.name == "." || entry.name == "..";
+                match self.inodes.get_mut(&entry.inode) {
+                    Some(state) => {
+                        state.refs += 1;
+                        if !dot && !state.reached {
+                            state.reached = true;
+                            if state.is_dir {
+                                pending.push(entry.inode);
+                            }
+                        }
+                    },
+                    // 壊れた"."と".."はディレクトリごと直す必要があり、ここでは扱わない
+                    None if dot => {},
+                    None => {
+                        if self.repair {
+                            self.fs.remove_dir_entry(&mut dir, &entry.name)?;
+                        }
+                        self.report.record(FsckProblem::DanglingEntry { dir: dir_ino as u64, name: entry.name }, self.repair);
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// 到達できないディレクトリから".."をたどり、到達できない最上位のディレクトリを返す
+    fn detached_top(&self, ino: u32) -> Result<u32, Ext4Error> {
+        let mut current = ino;
+        let mut seen = BTreeSet::new();
+        loop {
+            seen.insert(current);
+            let dir = self.fs.get_inode(current)?;
+            let parent = match self.fs.find_entry(&dir, "..")? {
+                Some(entry) => entry.inode,
+                None => return Ok(current),
+            };
+            match self.inodes.get(&parent) {
+                Some(state) if state.is_dir && !state.reached && !seen.contains(&parent) => current = parent,
+                _ => return Ok(current),
+            }
+        }
+    }
+    
+    /// lost+foundを探し、なければ作成する
+    fn find_lost_and_found(&mut self) -> Result<u32, Ext4Error> {
+        if let Some(ino) = self.lost_and_found {
+            return Ok(ino);
+        }
+        
+        let root = self.fs.get_inode(ROOT_INODE)?;
+        let ino = match self.fs.find_entry(&root, LOST_AND_FOUND)? {
+            Some(entry) if self.inodes.get(&entry.inode).is_some_and(|state| state.is_dir) => entry.inode,
+            Some(_) => {
+                log::error!("ext4: {}がディレクトリではありません", LOST_AND_FOUND);
+                return Err(Ext4Error::NotDirectory);
+            },
+            None => {
+                let ino = self.fs.mkdir_locked(ROOT_INODE, LOST_AND_FOUND, 0o700, 0, 0)?;
+                // ルートからのエントリと"."、ルートには".."の分の参照が増える
+                self.inodes.insert(ino, InodeState { is_dir: true, refs: 2, reached: true });
+                if let Some(root) = self.inodes.get_mut(&ROOT_INODE) {
+                    root.refs += 1;
+                }
+                ino
+            },
+        };
+        self.lost_and_found = Some(ino);
+        Ok(ino)
+    }
+    
+    /// 到達できないiノードを報告し、修復時はlost+foundに"#iノード番号"としてつなぐ
+    fn reconnect(&mut self, ino: u32) -> Result<(), Ext4Error> {
+        if self.repair {
+            let lost_and_found = self.find_lost_and_found()?;
+            let mut parent = self.fs.get_inode(lost_and_found)?;
+            let mut inode = self.fs.get_inode(ino)?;
+            let name = format!("#{}", ino);
+            if inode.is_directory() {
+                self.fs.inc_dir_links(&mut parent)?;
+            }
+            self.fs.add_dir_entry(&mut parent, &name, ino, DirectoryEntryType::from_mode(inode.mode))?;
+            if inode.is_directory() && self.fs.find_entry(&inode, "..")?.is_some() {
+                self.fs.replace_dir_entry(&mut inode, "..", lost_and_found, DirectoryEntryType::Directory)?;
+            }
+        }
+        // 修復時に追加されるエントリの分
+        if let Some(state) = self.inodes.get_mut(&ino) {
+            state.refs += 1;
+        }
+        self.report.record(FsckProblem::Disconnected { inode: ino as u64 }, self.repair);
+        Ok(())
+    }
+    
+    /// ルートからディレクトリツリーをたどり、到達できないiノードをつなぎ直す
+    fn check_directories(&mut self) -> Result<(), Ext4Error> {
+        if !self.inodes.get(&ROOT_INODE).is_some_and(|state| state.is_dir) {
+            log::error!("ext4: ルートディレクトリが壊れています");
+            return Err(Ext4Error::InvalidInode);
+        }
+        self.traverse(ROOT_INODE)?;
+        
+        // ディレクトリは到達できない最上位のものだけをつなぎ、その下はたどり直す
+        let detached: Vec<u32> = self.detached(true);
+        for ino in detached {
+            if self.inodes[&ino].reached {
+                continue;
+            }
+            let top = self.detached_top(ino)?;
+            self.reconnect(top)?;
+            self.traverse(top)?;
+        }
+        for ino in self.detached(false) {
+            self.reconnect(ino)?;
+            if let Some(state) = self.inodes.get_mut(&ino) {
+                state.reached = true;
+            }
+        }
+        Ok(())
+    }
+    
+    /// 到達できていない（孤立リストにないもの）ディレクトリまたはファイル
+    fn detached(&self, dirs: bool) -> Vec<u32> {
+        self.inodes.iter()
+            .filter(|(ino, state)| state.is_dir == dirs && !state.reached && !self.orphans.contains(ino))
+            .map(|(&ino, _)| ino)
+            .collect()
+    }
+    
+    /// リンク数を見つかった参照の数と比べる
+    fn check_link_counts(&mut self) -> Result<(), Ext4Error> {
+        let dir_nlink = self.fs.superblock.read().unwrap().has_feature_ro_compat(FEATURE_RO_COMPAT_DIR_NLINK);
+        let states: Vec<(u32, InodeState)> = self.inodes.iter().map(|(&ino, &state)| (ino, state)).collect();
+        
+        for (ino, state) in states {
+            if !state.reached || self.orphans.contains(&ino) {
+                continue;
+            }
+            // dir_nlinkでは上限を超えたディレクトリのリンク数を1にする
+            let expected = if state.is_dir && dir_nlink && state.refs >= MAX_LINK_COUNT as u32 {
+                1
+            } else {
+                state.refs.min(u16::MAX as u32) as u16
+            };
+            let mut inode = self.fs.get_inode(ino)?;
+            let recorded = inode.links_count;
+            if recorded == expected {
+                continue;
+            }
+            if self.repair {
+                inode.links_count = expected;
+                self.fs.update_inode(&inode)?;
+            }
+            self.report.record(FsckProblem::LinkCount { inode: ino as u64, recorded: recorded as u32, actual: state.refs }, self.repair);
+        }
+        Ok(())
+    }
+}
+
+/// 32ビットのブロック番号に変換
+fn block_u32(block: u64) -> Result<u32, Ext4Error> {
+    u32::try_from(block).map_err(|_| Ext4Error::InvalidBlock)
+}
+
+impl Ext4FileSystem {
+    /// ファイルシステムの整合性をチェックする（修復はしない）
+    pub fn check(&self) -> Result<FsckReport, Ext4Error> {
+        self.fsck(false)
+    }
+    
+    /// ファイルシステムの整合性をチェックし、見つかった問題を修復する
+    pub fn repair(&self) -> Result<FsckReport, Ext4Error> {
+        self.check_writable()?;
+        self.fsck(true)
+    }
+    
+    /// チェックの各段階を順に実行
+    fn fsck(&self, repair: bool) -> Result<FsckReport, Ext4Error> {
+        // 検査中に割り当てや名前空間が変わらないようにする
+        let _guard = self.meta_lock.lock();
+        let mut checker = Checker::new(self, repair)?;
+        
+        let orphans_released = checker.process_orphans()?;
+        checker.collect_usage()?;
+        if checker.check_duplicates()? || orphans_released {
+            checker.collect_usage()?;
+        }
+        checker.check_bitmaps()?;
+        checker.check_directories()?;
+        checker.check_link_counts()?;
+        
+        checker.report.files = checker.inodes.len() as u64;
+        checker.report.used_blocks = checker.usage.count;
+        Ok(checker.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn backup_superblock_groups() {
+        let sparse: Vec<u32> = (0..60).filter(|&group| has_superblock(group, true)).collect();
+        assert_eq!(sparse, [0, 1, 3, 5, 7, 9, 25, 27, 49]);
+        assert!((0..60).all(|group| has_superblock(group, false)));
+    }
+    
+    #[test]
+    fn usage_tracks_duplicates_and_rebuilds_bitmaps() {
+        let mut usage = BlockUsage::new(40);
+        assert!(usage.mark(0, 4));
+        assert!(usage.mark(10, 3));
+        assert!(usage.mark(12, 2));
+        assert!(!usage.mark(38, 3));
+        assert_eq!((usage.count, usage.duplicates), (8, 1));
+        assert!(usage.is_duplicate(12) && !usage.is_duplicate(11));
+        
+        // 2つ目のグループ（ブロック8〜）: 10〜13が使用中
+        let mut bitmap = vec![0u8; 2];
+        Bitmap::set_bit(&mut bitmap, 2);
+        Bitmap::set_bit(&mut bitmap, 7);
+        assert_eq!(usage.compare(&bitmap, 8, 8), (3, 1, 4));
+        
+        // 最後のグループが短いときは末尾を使用中で埋める
+        let rebuilt = usage.group_bitmap(8, 6, 2);
+        assert_eq!(rebuilt, [0b1111_1100, 0xFF]);
+        assert_eq!(usage.compare(&rebuilt, 8, 6), (0, 0, 4));
+    }
+}
@@ -0,0 +1,251 @@
+// Ext4 ブロックグループディスクリプタ実装
+//
+// ブロックグループディスクリプタの解析と更新
+
+use alloc::vec::Vec;
+
+/// 64bit機能無効時のディスクリプタサイズ
+pub const GROUP_DESC_SIZE: usize = 32;
+
+/// 64bit機能有効時の最小ディスクリプタサイズ
+pub const GROUP_DESC_SIZE_64BIT: usize = 64;
+
+/// ブロックグループフラグ
+pub mod flags {
+    /// iノードテーブルとビットマップが未初期化
+    pub const INODE_UNINIT: u16 = 0x0001;
+    /// ブロックビットマップが未初期化
+    pub const BLOCK_UNINIT: u16 = 0x0002;
+    /// iノードテーブルがゼロ初期化済み
+    pub const INODE_ZEROED: u16 = 0x0004;
+}
+
+// ディスクリプタ内のオフセット（下位/上位）
+const BLOCK_BITMAP_LO: usize = 0x00;
+const INODE_BITMAP_LO: usize = 0x04;
+const INODE_TABLE_LO: usize = 0x08;
+const FREE_BLOCKS_LO: usize = 0x0C;
+const FREE_INODES_LO: usize = 0x0E;
+const USED_DIRS_LO: usize = 0x10;
+const FLAGS: usize = 0x12;
+const BLOCK_BITMAP_CSUM_LO: usize = 0x18;
+const INODE_BITMAP_CSUM_LO: usize = 0x1A;
+const ITABLE_UNUSED_LO: usize = 0x1C;
+const CHECKSUM: usize = 0x1E;
+const BLOCK_BITMAP_HI: usize = 0x20;
+const INODE_BITMAP_HI: usize = 0x24;
+const INODE_TABLE_HI: usize = 0x28;
+const FREE_BLOCKS_HI: usize = 0x2C;
+const FREE_INODES_HI: usize = 0x2E;
+const USED_DIRS_HI: usize = 0x30;
+const ITABLE_UNUSED_HI: usize = 0x32;
+const BLOCK_BITMAP_CSUM_HI: usize = 0x38;
+const INODE_BITMAP_CSUM_HI: usize = 0x3A;
+
+/// ブロックグループディスクリプタ
+///
+/// 未解析のフィールド（チェックサムやスナップショット情報）を失わないよう、
+/// ディスク上の生データを保持したまま各フィールドを読み書きする。
+#[derive(Debug, Clone)]
+pub struct BlockGroupDescriptor {
+    /// ディスク上の生データ（32または64バイト以上）
+    raw: Vec<u8>,
+}
+
+impl BlockGroupDescriptor {
+    /// ディスク上のデータからディスクリプタを作成
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut raw = data.to_vec();
+        if raw.len() < GROUP_DESC_SIZE {
+            raw.resize(GROUP_DESC_SIZE, 0);
+        }
+        Self { raw }
+    }
+    
+    /// ディスク上の形式にシリアライズ
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+    
+    /// 64bit形式のディスクリプタかどうか
+    pub fn is_64bit(&self) -> bool {
+        self.raw.len() >= GROUP_DESC_SIZE_64BIT
+    }
+    
+    /// ブロックビットマップのブロック番号
+    pub fn get_block_bitmap_block(&self) -> u64 {
+        self.read_u64_split(BLOCK_BITMAP_LO, BLOCK_BITMAP_HI)
+    }
+    
+    /// iノードビットマップのブロック番号
+    pub fn get_inode_bitmap_block(&self) -> u64 {
+        self.read_u64_split(INODE_BITMAP_LO, INODE_BITMAP_HI)
+    }
+    
+    /// iノードテーブルの開始ブロック番号
+    pub fn get_inode_table_block(&self) -> u64 {
+        self.read_u64_split(INODE_TABLE_LO, INODE_TABLE_HI)
+    }
+    
+    /// 空きブロック数
+    pub fn get_free_blocks_count(&self) -> u32 {
+        self.read_u32_split(FREE_BLOCKS_LO, FREE_BLOCKS_HI)
+    }
+    
+    /// 空きブロック数を設定
+    pub fn set_free_blocks_count(&mut self, count: u32) {
+        self.write_u32_split(FREE_BLOCKS_LO, FREE_BLOCKS_HI, count);
+    }
+    
+    /// 空きiノード数
+    pub fn get_free_inodes_count(&self) -> u32 {
+        self.read_u32_split(FREE_INODES_LO, FREE_INODES_HI)
+    }
+    
+    /// 空きiノード数を設定
+    pub fn set_free_inodes_count(&mut self, count: u32) {
+        self.write_u32_split(FREE_INODES_LO, FREE_INODES_HI, count);
+    }
+    
+    /// ディレクトリ数
+    pub fn get_used_dirs_count(&self) -> u32 {
+        self.read_u32_split(USED_DIRS_LO, USED_DIRS_HI)
+    }
+    
+    /// ディレクトリ数を設定
+    pub fn set_used_dirs_count(&mut self, count: u32) {
+        self.write_u32_split(USED_DIRS_LO, USED_DIRS_HI, count);
+    }
+    
+    /// iノードテーブル末尾の未使用iノード数
+    pub fn get_itable_unused(&self) -> u32 {
+        self.read_u32_split(ITABLE_UNUSED_LO, ITABLE_UNUSED_HI)
+    }
+    
+    /// iノードテーブル末尾の未使用iノード数を設定
+    pub fn set_itable_unused(&mut self, count: u32) {
+        self.write_u32_split(ITABLE_UNUSED_LO, ITABLE_UNUSED_HI, count);
+    }
+    
+    /// グループフラグ
+    pub fn get_flags(&self) -> u16 {
+        self.read_u16(FLAGS)
+    }
+    
+    /// 指定フラグが立っているかどうか
+    pub fn has_flag(&self, flag: u16) -> bool {
+        self.get_flags() & flag != 0
+    }
+    
+    /// 指定フラグをクリア
+    pub fn clear_flag(&mut self, flag: u16) {
+        let flags = self.get_flags() & !flag;
+        self.write_u16(FLAGS, flags);
+    }
+    
+    /// ディスクリプタチェックサム
+    pub fn get_checksum(&self) -> u16 {
+        self.read_u16(CHECKSUM)
+    }
+    
+    /// ディスクリプタチェックサムを設定
+    pub fn set_checksum(&mut self, checksum: u16) {
+        self.write_u16(CHECKSUM, checksum);
+    }
+    
+    /// ブロックビットマップのチェックサム（32バイトのディスクリプタでは下位16ビットのみ）
+    pub fn get_block_bitmap_checksum(&self) -> u32 {
+        self.read_u32_split(BLOCK_BITMAP_CSUM_LO, BLOCK_BITMAP_CSUM_HI)
+    }
+    
+    /// ブロックビットマップのチェックサムを設定
+    pub fn set_block_bitmap_checksum(&mut self, checksum: u32) {
+        self.write_u32_split(BLOCK_BITMAP_CSUM_LO, BLOCK_BITMAP_CSUM_HI, checksum);
+    }
+    
+    /// iノードビットマップのチェックサム（32バイトのディスクリプタでは下位16ビットのみ）
+    pub fn get_inode_bitmap_checksum(&self) -> u32 {
+        self.read_u32_split(INODE_BITMAP_CSUM_LO, INODE_BITMAP_CSUM_HI)
+    }
+    
+    /// iノードビットマップのチェックサムを設定
+    pub fn set_inode_bitmap_checksum(&mut self, checksum: u32) {
+        self.write_u32_split(INODE_BITMAP_CSUM_LO, INODE_BITMAP_CSUM_HI, checksum);
+    }
+    
+    fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.raw[offset], self.raw[offset + 1]])
+    }
+    
+    fn write_u16(&mut self, offset: usize, value: u16) {
+        self.raw[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+    
+    fn read_u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes([
+            self.raw[offset], self.raw[offset + 1], self.raw[offset + 2], self.raw[offset + 3],
+        ])
+    }
+    
+    /// 下位16ビット/上位16ビットに分割されたカウンタを読み込み
+    fn read_u32_split(&self, lo: usize, hi: usize) -> u32 {
+        let low = self.read_u16(lo) as u32;
+        if self.is_64bit() {
+            low | (self.read_u16(hi) as u32) << 16
+        } else {
+            low
+        }
+    }
+    
+    /// 下位16ビット/上位16ビットに分割されたカウンタを書き込み
+    fn write_u32_split(&mut self, lo: usize, hi: usize, value: u32) {
+        self.write_u16(lo, value as u16);
+        if self.is_64bit() {
+            self.write_u16(hi, (value >> 16) as u16);
+        }
+    }
+    
+    /// 下位32ビット/上位32ビットに分割されたブロック番号を読み込み
+    fn read_u64_split(&self, lo: usize, hi: usize) -> u64 {
+        let low = self.read_u32(lo) as u64;
+        if self.is_64bit() {
+            low | (self.read_u32(hi) as u64) << 32
+        } else {
+            low
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_counters_split_across_halves() {
+        let mut desc = BlockGroupDescriptor::from_bytes(&[0u8; GROUP_DESC_SIZE_64BIT]);
+        desc.set_free_blocks_count(0x0001_2345);
+        desc.set_used_dirs_count(7);
+        
+        let bytes = desc.to_bytes();
+        assert_eq!(&bytes[FREE_BLOCKS_LO..FREE_BLOCKS_LO + 2], &[0x45, 0x23]);
+        assert_eq!(&bytes[FREE_BLOCKS_HI..FREE_BLOCKS_HI + 2], &[0x01, 0x00]);
+        assert_eq!(BlockGroupDescriptor::from_bytes(&bytes).get_free_blocks_count(), 0x0001_2345);
+        assert_eq!(desc.get_used_dirs_count(), 7);
+    }
+    
+    #[test]
+    fn test_32bit_descriptor_ignores_high_half() {
+        let mut raw = [0u8; GROUP_DESC_SIZE];
+        raw[INODE_TABLE_LO..INODE_TABLE_LO + 4].copy_from_slice(&1234u32.to_le_bytes());
+        raw[FLAGS] = flags::INODE_UNINIT as u8 | flags::INODE_ZEROED as u8;
+        
+        let mut desc = BlockGroupDescriptor::from_bytes(&raw);
+        assert_eq!(desc.get_inode_table_block(), 1234);
+        assert!(desc.has_flag(flags::INODE_UNINIT));
+        
+        desc.clear_flag(flags::INODE_UNINIT);
+        assert!(!desc.has_flag(flags::INODE_UNINIT));
+        assert!(desc.has_flag(flags::INODE_ZEROED));
+        assert_eq!(desc.to_bytes().len(), GROUP_DESC_SIZE);
+    }
+}
@@ -0,0 +1,347 @@
+// Ext4 VFSハンドル実装
+//
+// ext4のiノードをVFSのFileHandle/DirHandleとして公開する
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType, Metadata, Permissions, FileHandle, DirHandle, DirEntry};
+use super::{Ext4FileSystem, Ext4Error};
+use super::inode::Inode;
+use super::namei::ROOT_INODE;
+
+/// 新規作成時に使用する所有者（呼び出し元の資格情報はVFSから渡されない）
+const DEFAULT_OWNER: u32 = 0;
+
+impl Ext4FileSystem {
+    /// パスのディレクトリをDirHandleとして開く
+    pub fn open_dir_handle(self: &Arc<Self>, path: &str) -> Result<Arc<dyn DirHandle>, Ext4Error> {
+        let inode_num = self.lookup_path(path)?;
+        if !self.get_inode(inode_num)?.is_directory() {
+            return Err(Ext4Error::NotDirectory);
+        }
+        Ok(Arc::new(Ext4DirHandle::new(self.clone(), inode_num)))
+    }
+    
+    /// パスのファイルをFileHandleとして開く
+    pub fn open_file_handle(self: &Arc<Self>, path: &str, writable: bool) -> Result<Arc<dyn FileHandle>, Ext4Error> {
+        let inode_num = self.lookup_path(path)?;
+        if self.get_inode(inode_num)?.is_directory() {
+            return Err(Ext4Error::IsDirectory);
+        }
+        Ok(Arc::new(Ext4FileHandle::new(self.clone(), inode_num, writable)?))
+    }
+    
+    /// iノードからVFSメタデータを作成
+    fn inode_metadata(&self, inode: &Inode) -> Metadata {
+        Metadata {
+            inode: inode.get_number() as u64,
+            file_type: file_type_of(inode),
+            size: inode.get_size(),
+            uid: inode.uid,
+            gid: inode.gid,
+            permissions: Permissions {
+                read: inode.mode & 0o400 != 0,
+                write: inode.mode & 0o200 != 0,
+                execute: inode.mode & 0o100 != 0,
+            },
+            created: inode.crtime as u64,
+            accessed: inode.atime as u64,
+            modified: inode.mtime as u64,
+            links: inode.links_count as u32,
+            block_size: self.block_size as u32,
+            blocks: inode.blocks,
+        }
+    }
+    
+    /// `path`の親ディレクトリと最終要素を解決（`/`始まりならルートから）
+    fn resolve_parent<'a>(&self, start: u32, path: &'a str) -> Result<(u32, &'a str), Ext4Error> {
+        let trimmed = path.trim_end_matches('/');
+        let (parent_path, name) = match trimmed.rfind('/') {
+            Some(pos) => (&trimmed[..pos], &trimmed[pos + 1..]),
+            None => ("", trimmed),
+        };
+        
+        let mut current = if path.starts_with('/') { ROOT_INODE } else { start };
+        for component in parent_path.split('/').filter(|c| !c.is_empty()) {
+            let dir = self.get_inode(current)?;
+            if !dir.is_directory() {
+                return Err(Ext4Error::NotDirectory);
+            }
+            // "."と".."もディレクトリエントリとして存在するのでそのまま検索できる
+            current = self.find_entry(&dir, component)?.ok_or(Ext4Error::NotFound)?.inode;
+        }
+        
+        Ok((current, name))
+    }
+}
+
+/// iノードのモードからVFSのファイルタイプを判定
+fn file_type_of(inode: &Inode) -> FileType {
+    if inode.is_directory() {
+        FileType::Directory
+    } else if inode.is_symlink() {
+        FileType::SymbolicLink
+    } else if inode.is_block_device() {
+        FileType::BlockDevice
+    } else if inode.is_character_device() {
+        FileType::CharDevice
+    } else if inode.is_fifo() {
+        FileType::NamedPipe
+    } else if inode.is_socket() {
+        FileType::Socket
+    } else {
+        FileType::Regular
+    }
+}
+
+/// VFSの権限からパーミッションビットを作成（所有者以外は書き込み不可）
+fn mode_from_permissions(permissions: Permissions) -> u16 {
+    let mut mode = 0;
+    if permissions.read {
+        mode |= 0o444;
+    }
+    if permissions.write {
+        mode |= 0o200;
+    }
+    if permissions.execute {
+        mode |= 0o111;
+    }
+    mode
+}
+
+/// ext4ファイルハンドル
+pub struct Ext4FileHandle {
+    /// ファイルシステム
+    fs: Arc<Ext4FileSystem>,
+    /// iノード番号
+    inode: u32,
+    /// 書き込み可能か
+    writable: bool,
+}
+
+impl Ext4FileHandle {
+    /// 新しいファイルハンドルを作成（閉じるまでiノードを解放させない）
+    pub fn new(fs: Arc<Ext4FileSystem>, inode: u32, writable: bool) -> Result<Self, Ext4Error> {
+        fs.open_inode(inode)?;
+        Ok(Self { fs, inode, writable })
+    }
+}
+
+impl Drop for Ext4FileHandle {
+    fn drop(&mut self) {
+        if let Err(e) = self.fs.close_inode(self.inode) {
+            log::warn!("ext4: 削除済みのiノード{}を解放できません: {:?}", self.inode, e);
+        }
+    }
+}
+
+impl FileHandle for Ext4FileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let inode = self.fs.get_inode(self.inode)?;
+        let size = inode.get_size();
+        if offset >= size || buffer.is_empty() {
+            return Ok(0);
+        }
+        
+        let len = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        Ok(self.fs.read_file_data(&inode, &mut buffer[..len], offset)?)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        
+        let _guard = self.fs.meta_lock.lock();
+        let mut inode = self.fs.get_inode(self.inode)?;
+        let written = self.fs.write_file_data(&mut inode, buffer, offset)?;
+        
+        let end = offset + written as u64;
+        if end > inode.get_size() {
+            inode.set_size(end);
+        }
+        let now = self.fs.get_current_time();
+        inode.set_mtime(now);
+        inode.set_ctime(now);
+        self.fs.update_inode(&inode)?;
+        
+        Ok(written)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        // 書き込みは同期的にデバイスへ反映される
+        Ok(())
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.fs.get_inode(self.inode)?.get_size())
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        
+        let _guard = self.fs.meta_lock.lock();
+        let mut inode = self.fs.get_inode(self.inode)?;
+        if new_size < inode.get_size() {
+            // 切り詰めた範囲のブロックを解放する
+            self.fs.truncate_blocks(&mut inode, new_size)?;
+        } else {
+            // 拡張は穴として扱う
+            inode.set_size(new_size);
+        }
+        
+        let now = self.fs.get_current_time();
+        inode.set_mtime(now);
+        inode.set_ctime(now);
+        self.fs.update_inode(&inode)?;
+        Ok(())
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let inode = self.fs.get_inode(self.inode)?;
+        Ok(self.fs.inode_metadata(&inode))
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        self.writable
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        Ok(self.fs.get_xattr(self.inode, name)?)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        Ok(self.fs.set_xattr(self.inode, name, Some(value))?)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        Ok(self.fs.list_xattrs(self.inode)?)
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        Ok(self.fs.set_xattr(self.inode, name, None)?)
+    }
+}
+
+/// ext4ディレクトリハンドル
+pub struct Ext4DirHandle {
+    /// ファイルシステム
+    fs: Arc<Ext4FileSystem>,
+    /// ディレクトリのiノード番号
+    inode: u32,
+}
+
+impl Ext4DirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub fn new(fs: Arc<Ext4FileSystem>, inode: u32) -> Self {
+        Self { fs, inode }
+    }
+}
+
+impl DirHandle for Ext4DirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        let dir = self.fs.get_inode(self.inode)?;
+        let mut entries = Vec::new();
+        
+        for entry in self.fs.read_dir_entries(&dir)? {
+            if entry.name == "." || entry.name == ".." {
+                continue;
+            }
+            entries.push(DirEntry {
+                inode: entry.inode as u64,
+                file_type: entry.file_type.to_file_type(),
+                name: entry.name,
+            });
+        }
+        
+        Ok(entries)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let dir = self.fs.get_inode(self.inode)?;
+        let entry = self.fs.find_entry(&dir, name)?.ok_or(FsError::NotFound)?;
+        Ok(DirEntry {
+            inode: entry.inode as u64,
+            file_type: entry.file_type.to_file_type(),
+            name: String::from(name),
+        })
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        let mode = mode_from_permissions(permissions);
+        let inode = self.fs.create(self.inode, name, mode, DEFAULT_OWNER, DEFAULT_OWNER)?;
+        Ok(Arc::new(Ext4FileHandle::new(self.fs.clone(), inode, true)?))
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        let mode = mode_from_permissions(permissions);
+        self.fs.mkdir(self.inode, name, mode, DEFAULT_OWNER, DEFAULT_OWNER)?;
+        Ok(())
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        let dir = self.fs.get_inode(self.inode)?;
+        let entry = self.fs.find_entry(&dir, name)?.ok_or(FsError::NotFound)?;
+        
+        if self.fs.get_inode(entry.inode)?.is_directory() {
+            self.fs.rmdir(self.inode, name)?;
+        } else {
+            self.fs.unlink(self.inode, name)?;
+        }
+        Ok(())
+    }
+    
+    /// 名前を変更する
+    ///
+    /// `new_name`に`/`を含む場合は移動先のパスとして扱い、`/`始まりならファイルシステムの
+    /// ルート、それ以外はこのディレクトリからの相対パスとして解決する。
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        let (new_parent, leaf) = self.fs.resolve_parent(self.inode, new_name)?;
+        self.fs.rename(self.inode, old_name, new_parent, leaf)?;
+        Ok(())
+    }
+    
+    fn create_symlink(&self, name: &str, target: &str) -> FsResult<()> {
+        self.fs.symlink(self.inode, name, target, DEFAULT_OWNER, DEFAULT_OWNER)?;
+        Ok(())
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let dir = self.fs.get_inode(self.inode)?;
+        Ok(self.fs.inode_metadata(&dir))
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        Ok(self.fs.get_xattr(self.inode, name)?)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        Ok(self.fs.set_xattr(self.inode, name, Some(value))?)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        Ok(self.fs.list_xattrs(self.inode)?)
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        Ok(self.fs.set_xattr(self.inode, name, None)?)
+    }
+}
@@ -0,0 +1,301 @@
+// Ext4 ディレクトリハッシュ実装
+//
+// HTree（dir_index）で使用するlegacy/half-MD4/TEAハッシュ
+
+/// HTreeのハッシュ値の終端（32ビット版のEOF）
+const HTREE_EOF_32BIT: u32 = 0x7FFF_FFFF;
+
+/// スーパーブロックフラグ: 符号付き文字としてハッシュする
+pub const FLAGS_SIGNED_HASH: u32 = 0x0001;
+/// スーパーブロックフラグ: 符号なし文字としてハッシュする
+pub const FLAGS_UNSIGNED_HASH: u32 = 0x0002;
+
+/// half-MD4/TEAの初期値（ハッシュシードが全て0の場合に使用）
+const DEFAULT_SEED: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+
+/// TEAのラウンド定数
+const TEA_DELTA: u32 = 0x9E37_79B9;
+
+/// half-MD4のラウンド2定数
+const MD4_K2: u32 = 0o13240474631;
+/// half-MD4のラウンド3定数
+const MD4_K3: u32 = 0o15666365641;
+
+/// ディレクトリハッシュのバージョン
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HashVersion {
+    /// 旧来のハッシュ（符号付き）
+    Legacy = 0,
+    /// half-MD4（符号付き）
+    HalfMd4 = 1,
+    /// TEA（符号付き）
+    Tea = 2,
+    /// 旧来のハッシュ（符号なし）
+    LegacyUnsigned = 3,
+    /// half-MD4（符号なし）
+    HalfMd4Unsigned = 4,
+    /// TEA（符号なし）
+    TeaUnsigned = 5,
+}
+
+impl HashVersion {
+    /// ディスク上の値から作成（SipHash等の未対応バージョンは`None`）
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Legacy),
+            1 => Some(Self::HalfMd4),
+            2 => Some(Self::Tea),
+            3 => Some(Self::LegacyUnsigned),
+            4 => Some(Self::HalfMd4Unsigned),
+            5 => Some(Self::TeaUnsigned),
+            _ => None,
+        }
+    }
+    
+    /// dx_rootに記録されたバージョンとスーパーブロックのフラグから実際のバージョンを決定
+    ///
+    /// dx_rootには符号なし版が記録されないため、スーパーブロックの
+    /// `FLAGS_UNSIGNED_HASH`が立っていれば符号なし版に読み替える。
+    pub fn resolve(byte: u8, sb_flags: u32) -> Option<Self> {
+        let version = Self::from_byte(byte)?;
+        if (sb_flags & FLAGS_UNSIGNED_HASH) != 0 {
+            Some(version.to_unsigned())
+        } else {
+            Some(version)
+        }
+    }
+    
+    /// 対応する符号なし版
+    pub fn to_unsigned(self) -> Self {
+        match self {
+            Self::Legacy => Self::LegacyUnsigned,
+            Self::HalfMd4 => Self::HalfMd4Unsigned,
+            Self::Tea => Self::TeaUnsigned,
+            other => other,
+        }
+    }
+    
+    /// dx_rootに記録する値（符号の区別を除いたもの）
+    pub fn to_root_byte(self) -> u8 {
+        match self {
+            Self::LegacyUnsigned => Self::Legacy as u8,
+            Self::HalfMd4Unsigned => Self::HalfMd4 as u8,
+            Self::TeaUnsigned => Self::Tea as u8,
+            other => other as u8,
+        }
+    }
+    
+    /// 符号なし文字として扱うかどうか
+    fn is_unsigned(self) -> bool {
+        matches!(self, Self::LegacyUnsigned | Self::HalfMd4Unsigned | Self::TeaUnsigned)
+    }
+}
+
+/// ディレクトリエントリ名のハッシュ値
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirHash {
+    /// 主ハッシュ（最下位ビットは常に0）
+    pub major: u32,
+    /// 副ハッシュ
+    pub minor: u32,
+}
+
+/// 名前のハッシュ値を計算
+pub fn dir_hash(name: &[u8], version: HashVersion, seed: &[u32; 4]) -> DirHash {
+    let mut buf = if seed.iter().any(|&word| word != 0) { *seed } else { DEFAULT_SEED };
+    let unsigned = version.is_unsigned();
+    
+    let (major, minor) = match version {
+        HashVersion::Legacy | HashVersion::LegacyUnsigned => (legacy_hash(name, unsigned), 0),
+        HashVersion::HalfMd4 | HashVersion::HalfMd4Unsigned => {
+            for chunk_start in (0..name.len()).step_by(32) {
+                let input = str_to_hash_buf(&name[chunk_start..], 8, unsigned);
+                half_md4_transform(&mut buf, &input);
+            }
+            (buf[1], buf[2])
+        },
+        HashVersion::Tea | HashVersion::TeaUnsigned => {
+            for chunk_start in (0..name.len()).step_by(16) {
+                let input = str_to_hash_buf(&name[chunk_start..], 4, unsigned);
+                tea_transform(&mut buf, &input);
+            }
+            (buf[0], buf[1])
+        },
+    };
+    
+    let mut major = major & !1;
+    if major == HTREE_EOF_32BIT << 1 {
+        major = (HTREE_EOF_32BIT - 1) << 1;
+    }
+    
+    DirHash { major, minor }
+}
+
+/// 文字をハッシュ入力用の整数に変換
+fn char_value(byte: u8, unsigned: bool) -> u32 {
+    if unsigned {
+        byte as u32
+    } else {
+        byte as i8 as i32 as u32
+    }
+}
+
+/// 旧来のハッシュ関数
+fn legacy_hash(name: &[u8], unsigned: bool) -> u32 {
+    let mut hash0: u32 = 0x12A3_FE2D;
+    let mut hash1: u32 = 0x37AB_E8F9;
+    
+    for &byte in name {
+        let mut hash = hash1.wrapping_add(hash0 ^ char_value(byte, unsigned).wrapping_mul(7_152_373));
+        if (hash & 0x8000_0000) != 0 {
+            hash = hash.wrapping_sub(0x7FFF_FFFF);
+        }
+        hash1 = hash0;
+        hash0 = hash;
+    }
+    
+    hash0 << 1
+}
+
+/// 名前を`num`ワードのハッシュ入力に変換
+///
+/// 足りない部分は名前の長さから作ったパディングで埋める。`msg`は残りの名前全体で、
+/// パディングには切り詰める前の長さを使う。
+fn str_to_hash_buf(msg: &[u8], num: usize, unsigned: bool) -> [u32; 8] {
+    let mut out = [0u32; 8];
+    let len = msg.len() as u32;
+    
+    let mut pad = len | (len << 8);
+    pad |= pad << 16;
+    
+    let mut value = pad;
+    let mut index = 0;
+    for (i, &byte) in msg.iter().take(num * 4).enumerate() {
+        value = char_value(byte, unsigned).wrapping_add(value << 8);
+        if i % 4 == 3 {
+            out[index] = value;
+            index += 1;
+            value = pad;
+        }
+    }
+    
+    if index < num {
+        out[index] = value;
+        index += 1;
+    }
+    while index < num {
+        out[index] = pad;
+        index += 1;
+    }
+    
+    out
+}
+
+/// half-MD4変換（MD4の圧縮関数を半分のラウンドで適用する）
+fn half_md4_transform(buf: &mut [u32; 4], input: &[u32; 8]) {
+    fn f(x: u32, y: u32, z: u32) -> u32 { z ^ (x & (y ^ z)) }
+    fn g(x: u32, y: u32, z: u32) -> u32 { (x & y).wrapping_add((x ^ y) & z) }
+    fn h(x: u32, y: u32, z: u32) -> u32 { x ^ y ^ z }
+    
+    let [mut a, mut b, mut c, mut d] = *buf;
+    
+    macro_rules! round {
+        ($f:ident, $a:ident, $b:ident, $c:ident, $d:ident, $x:expr, $s:expr) => {
+            $a = $a.wrapping_add($f($b, $c, $d)).wrapping_add($x).rotate_left($s);
+        };
+    }
+    
+    // ラウンド1
+    round!(f, a, b, c, d, input[0], 3);
+    round!(f, d, a, b, c, input[1], 7);
+    round!(f, c, d, a, b, input[2], 11);
+    round!(f, b, c, d, a, input[3], 19);
+    round!(f, a, b, c, d, input[4], 3);
+    round!(f, d, a, b, c, input[5], 7);
+    round!(f, c, d, a, b, input[6], 11);
+    round!(f, b, c, d, a, input[7], 19);
+    
+    // ラウンド2
+    round!(g, a, b, c, d, input[1].wrapping_add(MD4_K2), 3);
+    round!(g, d, a, b, c, input[3].wrapping_add(MD4_K2), 5);
+    round!(g, c, d, a, b, input[5].wrapping_add(MD4_K2), 9);
+    round!(g, b, c, d, a, input[7].wrapping_add(MD4_K2), 13);
+    round!(g, a, b, c, d, input[0].wrapping_add(MD4_K2), 3);
+    round!(g, d, a, b, c, input[2].wrapping_add(MD4_K2), 5);
+    round!(g, c, d, a, b, input[4].wrapping_add(MD4_K2), 9);
+    round!(g, b, c, d, a, input[6].wrapping_add(MD4_K2), 13);
+    
+    // ラウンド3
+    round!(h, a, b, c, d, input[3].wrapping_add(MD4_K3), 3);
+    round!(h, d, a, b, c, input[7].wrapping_add(MD4_K3), 9);
+    round!(h, c, d, a, b, input[2].wrapping_add(MD4_K3), 11);
+    round!(h, b, c, d, a, input[6].wrapping_add(MD4_K3), 15);
+    round!(h, a, b, c, d, input[1].wrapping_add(MD4_K3), 3);
+    round!(h, d, a, b, c, input[5].wrapping_add(MD4_K3), 9);
+    round!(h, c, d, a, b, input[0].wrapping_add(MD4_K3), 11);
+    round!(h, b, c, d, a, input[4].wrapping_add(MD4_K3), 15);
+    
+    buf[0] = buf[0].wrapping_add(a);
+    buf[1] = buf[1].wrapping_add(b);
+    buf[2] = buf[2].wrapping_add(c);
+    buf[3] = buf[3].wrapping_add(d);
+}
+
+/// TEA変換
+fn tea_transform(buf: &mut [u32; 4], input: &[u32; 8]) {
+    let (mut b0, mut b1) = (buf[0], buf[1]);
+    let (a, b, c, d) = (input[0], input[1], input[2], input[3]);
+    let mut sum: u32 = 0;
+    
+    for _ in 0..16 {
+        sum = sum.wrapping_add(TEA_DELTA);
+        b0 = b0.wrapping_add(
+            ((b1 << 4).wrapping_add(a)) ^ b1.wrapping_add(sum) ^ ((b1 >> 5).wrapping_add(b)));
+        b1 = b1.wrapping_add(
+            ((b0 << 4).wrapping_add(c)) ^ b0.wrapping_add(sum) ^ ((b0 >> 5).wrapping_add(d)));
+    }
+    
+    buf[0] = buf[0].wrapping_add(b0);
+    buf[1] = buf[1].wrapping_add(b1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // 期待値はe2fsprogsのdebugfs `dx_hash`で求めたもの
+    const SEED: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x6745_2301, 0xEFCD_AB89];
+    
+    #[test]
+    fn test_hashes_match_e2fsprogs() {
+        let zero = [0u32; 4];
+        let legacy = dir_hash(b"artifact.tar", HashVersion::Legacy, &zero);
+        assert_eq!(legacy.major, 0x28B9_58AA);
+        
+        let md4 = dir_hash(b"artifact.tar", HashVersion::HalfMd4, &zero);
+        assert_eq!(md4, DirHash { major: 0xBB69_E2F8, minor: 0xB1E5_EBEE });
+        
+        let tea = dir_hash(b"artifact.tar", HashVersion::Tea, &zero);
+        assert_eq!(tea, DirHash { major: 0xA82B_5BC6, minor: 0x0C2D_7A91 });
+        
+        // 32バイトを超える名前は複数回変換される
+        let long = dir_hash(&[b'x'; 40], HashVersion::HalfMd4, &zero);
+        assert_eq!(long, DirHash { major: 0xA583_68B6, minor: 0x7A9F_8AB7 });
+    }
+    
+    #[test]
+    fn test_seed_and_signedness() {
+        let seeded = dir_hash(b"artifact.tar", HashVersion::HalfMd4, &SEED);
+        assert_eq!(seeded, DirHash { major: 0x79E9_2132, minor: 0x901A_E39D });
+        
+        // 非ASCII文字は符号付き/符号なしで結果が変わる
+        let name = "café".as_bytes();
+        assert_eq!(dir_hash(name, HashVersion::Legacy, &SEED).major, 0x96CA_5A2C);
+        assert_eq!(dir_hash(name, HashVersion::LegacyUnsigned, &SEED).major, 0x6DDE_4230);
+        assert_eq!(dir_hash(name, HashVersion::HalfMd4, &SEED), DirHash { major: 0xD6B4_AD14, minor: 0x921A_5761 });
+        assert_eq!(dir_hash(name, HashVersion::HalfMd4Unsigned, &SEED), DirHash { major: 0x3349_CEA2, minor: 0x596A_A618 });
+        assert_eq!(dir_hash(name, HashVersion::Tea, &SEED), DirHash { major: 0x1058_42EA, minor: 0xFB91_65CA });
+        assert_eq!(dir_hash(name, HashVersion::TeaUnsigned, &SEED), DirHash { major: 0x6621_F032, minor: 0xF866_99C6 });
+    }
+}
@@ -0,0 +1,701 @@
+// Ext4 HTreeディレクトリインデックス実装
+//
+// dx_root/dx_nodeによるハッシュ木の探索と、リーフ分割・インデックス拡張を伴う挿入
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::{Ext4FileSystem, Ext4Error};
+use super::inode::{Inode, InodeFlags};
+use super::dir::{self, DirectoryEntry, DirectoryEntryType};
+use super::hash::{self, DirHash, HashVersion};
+use super::namei::EntryLocation;
+use super::superblock::{FEATURE_COMPAT_DIR_INDEX, FEATURE_INCOMPAT_LARGEDIR, FEATURE_RO_COMPAT_METADATA_CSUM};
+
+/// dx_root_infoの開始オフセット（"."と".."の直後）
+const ROOT_INFO_OFFSET: usize = 24;
+/// dx_root_infoの長さ
+const ROOT_INFO_LENGTH: u8 = 8;
+/// dx_nodeのエントリ配列の開始オフセット（空の偽ディレクトリエントリの直後）
+const NODE_ENTRIES_OFFSET: usize = 8;
+/// インデックスエントリ（ハッシュ + 論理ブロック番号）のサイズ
+const DX_ENTRY_SIZE: usize = 8;
+/// metadata_csum有効時にインデックスブロック末尾に置かれるdx_tailのサイズ
+const DX_TAIL_SIZE: usize = 8;
+/// dx_rootより下のインデックス段数の上限
+const MAX_INDIRECT_LEVELS: u8 = 1;
+/// largedir機能有効時のインデックス段数の上限
+const MAX_INDIRECT_LEVELS_LARGEDIR: u8 = 2;
+
+/// dx_root_info
+#[derive(Debug, Clone, Copy)]
+pub struct DxRootInfo {
+    /// ハッシュバージョン（符号の区別はスーパーブロックのフラグで決まる）
+    pub hash_version: u8,
+    /// dx_root_infoの長さ
+    pub info_length: u8,
+    /// dx_rootより下のインデックス段数
+    pub indirect_levels: u8,
+}
+
+impl DxRootInfo {
+    /// ディレクトリ先頭ブロックからdx_root_infoを解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < ROOT_INFO_OFFSET + ROOT_INFO_LENGTH as usize + DX_ENTRY_SIZE {
+            return Err(FsError::InvalidData);
+        }
+        
+        let reserved_zero = read_u32(data, ROOT_INFO_OFFSET);
+        let info = Self {
+            hash_version: data[ROOT_INFO_OFFSET + 4],
+            info_length: data[ROOT_INFO_OFFSET + 5],
+            indirect_levels: data[ROOT_INFO_OFFSET + 6],
+        };
+        
+        if reserved_zero != 0 || info.info_length != ROOT_INFO_LENGTH {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(info)
+    }
+    
+    /// エントリ配列の開始オフセット
+    pub fn entries_offset(&self) -> usize {
+        ROOT_INFO_OFFSET + self.info_length as usize
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// dx_rootに格納できるエントリ数
+pub fn root_limit(block_size: usize, metadata_csum: bool) -> usize {
+    let tail = if metadata_csum { DX_TAIL_SIZE } else { 0 };
+    (block_size - ROOT_INFO_OFFSET - ROOT_INFO_LENGTH as usize - tail) / DX_ENTRY_SIZE
+}
+
+/// dx_nodeに格納できるエントリ数
+pub fn node_limit(block_size: usize, metadata_csum: bool) -> usize {
+    let tail = if metadata_csum { DX_TAIL_SIZE } else { 0 };
+    (block_size - NODE_ENTRIES_OFFSET - tail) / DX_ENTRY_SIZE
+}
+
+// エントリ配列の操作
+//
+// 先頭エントリのハッシュ欄にはlimit/countが格納され、先頭エントリは暗黙にハッシュ0を持つ。
+
+fn dx_limit(data: &[u8], offset: usize) -> usize {
+    read_u16(data, offset) as usize
+}
+
+fn dx_count(data: &[u8], offset: usize) -> usize {
+    read_u16(data, offset + 2) as usize
+}
+
+fn set_dx_count(data: &mut [u8], offset: usize, count: usize) {
+    data[offset + 2..offset + 4].copy_from_slice(&(count as u16).to_le_bytes());
+}
+
+fn dx_hash_at(data: &[u8], offset: usize, index: usize) -> u32 {
+    if index == 0 { 0 } else { read_u32(data, offset + index * DX_ENTRY_SIZE) }
+}
+
+fn dx_block_at(data: &[u8], offset: usize, index: usize) -> u32 {
+    read_u32(data, offset + index * DX_ENTRY_SIZE + 4)
+}
+
+fn set_dx_entry(data: &mut [u8], offset: usize, index: usize, hash: u32, block: u32) {
+    let base = offset + index * DX_ENTRY_SIZE;
+    if index != 0 {
+        data[base..base + 4].copy_from_slice(&hash.to_le_bytes());
+    }
+    data[base + 4..base + 8].copy_from_slice(&block.to_le_bytes());
+}
+
+/// ハッシュ値を含む範囲のエントリ位置を二分探索
+fn dx_search(data: &[u8], offset: usize, hash: u32) -> usize {
+    let (mut low, mut high) = (1, dx_count(data, offset));
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if dx_hash_at(data, offset, mid) > hash {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    low - 1
+}
+
+/// `position`の直後にエントリを挿入（空きがあることは呼び出し側が保証する）
+fn dx_insert(data: &mut [u8], offset: usize, position: usize, hash: u32, block: u32) {
+    let count = dx_count(data, offset);
+    let start = offset + (position + 1) * DX_ENTRY_SIZE;
+    let end = offset + count * DX_ENTRY_SIZE;
+    data.copy_within(start..end, start + DX_ENTRY_SIZE);
+    set_dx_entry(data, offset, position + 1, hash, block);
+    set_dx_count(data, offset, count + 1);
+}
+
+/// インデックス化したディレクトリの先頭ブロック（dx_root）を作成
+pub fn init_root_block(
+    block_size: usize,
+    self_inode: u32,
+    parent_inode: u32,
+    dir_type: DirectoryEntryType,
+    hash_version: u8,
+    first_leaf: u32,
+    limit: usize,
+) -> Vec<u8> {
+    let with_file_type = dir_type != DirectoryEntryType::Unknown;
+    // dx_rootはディレクトリ末尾エントリではなくdx_tailにチェックサムを置く
+    let mut block = dir::init_directory_block(block_size, self_inode, parent_inode, with_file_type, false);
+    
+    // ".."のレコードの後半をdx_root_infoとエントリ配列として使う
+    block[ROOT_INFO_OFFSET..ROOT_INFO_OFFSET + 4].copy_from_slice(&0u32.to_le_bytes());
+    block[ROOT_INFO_OFFSET + 4] = hash_version;
+    block[ROOT_INFO_OFFSET + 5] = ROOT_INFO_LENGTH;
+    block[ROOT_INFO_OFFSET + 6] = 0;
+    block[ROOT_INFO_OFFSET + 7] = 0;
+    
+    let offset = ROOT_INFO_OFFSET + ROOT_INFO_LENGTH as usize;
+    block[offset..offset + 2].copy_from_slice(&(limit as u16).to_le_bytes());
+    set_dx_count(&mut block, offset, 1);
+    set_dx_entry(&mut block, offset, 0, 0, first_leaf);
+    block
+}
+
+/// 空のdx_nodeブロックを作成
+pub fn init_node_block(block_size: usize, limit: usize) -> Vec<u8> {
+    let mut block = vec![0u8; block_size];
+    // ブロック全体を覆う削除済みエントリとして線形走査から隠す
+    block[4..6].copy_from_slice(&(block_size as u16).to_le_bytes());
+    block[NODE_ENTRIES_OFFSET..NODE_ENTRIES_OFFSET + 2].copy_from_slice(&(limit as u16).to_le_bytes());
+    block
+}
+
+/// 満杯のリーフブロックをハッシュ順に2つに分割
+///
+/// 後半のブロックに移すエントリがブロックサイズの半分程度になるよう分割点を選ぶ。
+/// 戻り値は(前半, 後半, 後半の先頭ハッシュ)で、同じハッシュが両方にまたがる場合は
+/// ハッシュの最下位ビット（衝突継続ビット）を立てる。
+pub fn split_leaf<F>(data: &[u8], hash_of: F) -> FsResult<(Vec<u8>, Vec<u8>, u32)>
+where
+    F: Fn(&str) -> DirHash,
+{
+    let block_size = data.len();
+    let with_tail = dir::has_dir_tail(data);
+    
+    let mut map: Vec<(DirHash, DirectoryEntry)> = dir::parse_directory_block(data)?
+        .into_iter()
+        .map(|entry| (hash_of(&entry.name), entry))
+        .collect();
+    if map.len() < 2 {
+        return Err(FsError::CorruptedFs);
+    }
+    map.sort_by(|a, b| a.0.cmp(&b.0));
+    
+    let mut moved_size = 0;
+    let mut moved = 0;
+    for (_, entry) in map.iter().rev() {
+        let size = entry.actual_size() as usize;
+        if moved_size + size / 2 > block_size / 2 {
+            break;
+        }
+        moved_size += size;
+        moved += 1;
+    }
+    let split = (map.len() - moved.max(1)).max(1);
+    
+    let split_hash = map[split].0.major;
+    let continued = map[split - 1].0.major == split_hash;
+    
+    let lower: Vec<DirectoryEntry> = map[..split].iter().map(|(_, entry)| entry.clone()).collect();
+    let upper: Vec<DirectoryEntry> = map[split..].iter().map(|(_, entry)| entry.clone()).collect();
+    
+    Ok((
+        dir::build_directory_block(block_size, &lower, with_tail),
+        dir::build_directory_block(block_size, &upper, with_tail),
+        split_hash | continued as u32,
+    ))
+}
+
+/// インデックス探索の各段の状態
+struct DxFrame {
+    /// ディレクトリ内の論理ブロック番号
+    logical: u32,
+    /// 物理ブロック番号
+    physical: u32,
+    /// ブロックの内容
+    data: Vec<u8>,
+    /// エントリ配列の開始オフセット
+    offset: usize,
+    /// 選択したエントリの位置
+    position: usize,
+}
+
+impl DxFrame {
+    fn count(&self) -> usize {
+        dx_count(&self.data, self.offset)
+    }
+    
+    fn limit(&self) -> usize {
+        dx_limit(&self.data, self.offset)
+    }
+    
+    fn is_full(&self) -> bool {
+        self.count() >= self.limit()
+    }
+    
+    /// 選択したエントリが指す子ブロック
+    fn child(&self) -> u32 {
+        dx_block_at(&self.data, self.offset, self.position)
+    }
+}
+
+/// dx_rootからリーフまでの探索経路
+struct DxPath {
+    /// 各段のフレーム（先頭がdx_root）
+    frames: Vec<DxFrame>,
+    /// 探索した名前のハッシュ
+    hash: DirHash,
+    /// ディレクトリのハッシュバージョン
+    version: HashVersion,
+    /// dx_rootより下のインデックス段数
+    indirect_levels: u8,
+}
+
+impl DxPath {
+    /// 探索したリーフの論理ブロック番号
+    fn leaf(&self) -> u32 {
+        self.frames.last().map(DxFrame::child).unwrap_or(0)
+    }
+}
+
+/// スーパーブロックから取得するHTreeの設定
+struct DxParams {
+    /// ハッシュシード
+    seed: [u32; 4],
+    /// スーパーブロックフラグ（符号なしハッシュの判定に使う）
+    sb_flags: u32,
+    /// 新規インデックスのハッシュバージョン
+    def_hash_version: u8,
+    /// dx_tailの領域を確保するか
+    metadata_csum: bool,
+    /// インデックス段数の上限
+    max_levels: u8,
+}
+
+impl Ext4FileSystem {
+    /// ディレクトリがHTreeインデックスで検索できるかどうか
+    pub(super) fn is_indexed(&self, dir: &Inode) -> bool {
+        (dir.flags & InodeFlags::DirIndex as u32) != 0
+            && self.superblock.read().unwrap().has_feature_compat(FEATURE_COMPAT_DIR_INDEX)
+    }
+    
+    /// 新しいディレクトリをインデックス化できるかどうか
+    pub(super) fn can_index(&self) -> bool {
+        let sb = self.superblock.read().unwrap();
+        sb.has_feature_compat(FEATURE_COMPAT_DIR_INDEX) && HashVersion::from_byte(sb.def_hash_version).is_some()
+    }
+    
+    fn dx_params(&self) -> DxParams {
+        let sb = self.superblock.read().unwrap();
+        DxParams {
+            seed: sb.hash_seed,
+            sb_flags: sb.flags,
+            def_hash_version: sb.def_hash_version,
+            metadata_csum: sb.has_feature_ro_compat(FEATURE_RO_COMPAT_METADATA_CSUM),
+            max_levels: if sb.has_feature_incompat(FEATURE_INCOMPAT_LARGEDIR) {
+                MAX_INDIRECT_LEVELS_LARGEDIR
+            } else {
+                MAX_INDIRECT_LEVELS
+            },
+        }
+    }
+    
+    /// 名前のハッシュでdx_rootからリーフまでたどる
+    ///
+    /// インデックスが壊れているか未対応の形式であれば`None`を返し、呼び出し側は
+    /// 線形ディレクトリとして扱う（インデックスブロックは空のエントリに見える）。
+    fn dx_probe(&self, dir: &Inode, name: &str) -> Result<Option<DxPath>, Ext4Error> {
+        let params = self.dx_params();
+        let dir_num = dir.get_number();
+        
+        let root_physical = self.map_block(dir, 0)?;
+        if root_physical == 0 {
+            log::warn!("ext4: ディレクトリ{}のdx_rootが割り当てられていません", dir_num);
+            return Ok(None);
+        }
+        let mut data = vec![0u8; self.block_size];
+        self.read_dir_block(dir, root_physical, &mut data)?;
+        
+        let info = match DxRootInfo::parse(&data) {
+            Ok(info) => info,
+            Err(_) => {
+                log::warn!("ext4: ディレクトリ{}のdx_rootが不正です", dir_num);
+                return Ok(None);
+            }
+        };
+        let version = match HashVersion::resolve(info.hash_version, params.sb_flags) {
+            Some(version) => version,
+            None => {
+                log::warn!("ext4: ディレクトリ{}のハッシュバージョン{}には対応していません", dir_num, info.hash_version);
+                return Ok(None);
+            }
+        };
+        if info.indirect_levels > params.max_levels {
+            log::warn!("ext4: ディレクトリ{}のインデックス段数{}が不正です", dir_num, info.indirect_levels);
+            return Ok(None);
+        }
+        
+        let hash = hash::dir_hash(name.as_bytes(), version, &params.seed);
+        let block_count = self.dir_block_count(dir);
+        let mut frames: Vec<DxFrame> = Vec::with_capacity(info.indirect_levels as usize + 1);
+        let mut frame = DxFrame {
+            logical: 0,
+            physical: root_physical,
+            data,
+            offset: info.entries_offset(),
+            position: 0,
+        };
+        
+        loop {
+            let (count, limit) = (frame.count(), frame.limit());
+            if count == 0 || count > limit || frame.offset + limit * DX_ENTRY_SIZE > self.block_size {
+                log::warn!("ext4: ディレクトリ{}のインデックスブロック{}が不正です", dir_num, frame.logical);
+                return Ok(None);
+            }
+            
+            frame.position = dx_search(&frame.data, frame.offset, hash.major);
+            let child = frame.child();
+            if child == 0 || child >= block_count {
+                log::warn!("ext4: ディレクトリ{}のインデックスが範囲外のブロック{}を指しています", dir_num, child);
+                return Ok(None);
+            }
+            frames.push(frame);
+            
+            if frames.len() > info.indirect_levels as usize {
+                break;
+            }
+            
+            let physical = self.map_block(dir, child)?;
+            if physical == 0 {
+                return Ok(None);
+            }
+            let mut data = vec![0u8; self.block_size];
+            self.read_dir_block(dir, physical, &mut data)?;
+            frame = DxFrame { logical: child, physical, data, offset: NODE_ENTRIES_OFFSET, position: 0 };
+        }
+        
+        Ok(Some(DxPath { frames, hash, version, indirect_levels: info.indirect_levels }))
+    }
+    
+    /// ハッシュが衝突して次のリーフに続いている場合、経路を次のリーフへ進める
+    fn dx_next_leaf(&self, dir: &Inode, path: &mut DxPath) -> Result<bool, Ext4Error> {
+        let mut level = path.frames.len();
+        loop {
+            if level == 0 {
+                return Ok(false);
+            }
+            level -= 1;
+            let frame = &mut path.frames[level];
+            if frame.position + 1 < frame.count() {
+                frame.position += 1;
+                break;
+            }
+        }
+        
+        let frame = &path.frames[level];
+        let next_hash = dx_hash_at(&frame.data, frame.offset, frame.position);
+        if (next_hash & !1) != path.hash.major {
+            return Ok(false);
+        }
+        
+        // 下の段を新しい位置の先頭から読み直す
+        for i in level + 1..path.frames.len() {
+            let child = path.frames[i - 1].child();
+            let physical = self.map_block(dir, child)?;
+            if physical == 0 {
+                return Err(Ext4Error::InvalidBlock);
+            }
+            let frame = &mut path.frames[i];
+            self.read_dir_block(dir, physical, &mut frame.data)?;
+            frame.logical = child;
+            frame.physical = physical;
+            frame.position = 0;
+        }
+        
+        Ok(true)
+    }
+    
+    /// インデックスを使って名前を検索
+    pub(super) fn dx_locate_entry(&self, dir: &Inode, name: &str) -> Result<Option<EntryLocation>, Ext4Error> {
+        let mut path = match self.dx_probe(dir, name)? {
+            Some(path) => path,
+            None => return self.linear_locate_entry(dir, name),
+        };
+        
+        let mut block = vec![0u8; self.block_size];
+        loop {
+            let physical = self.map_block(dir, path.leaf())?;
+            if physical != 0 {
+                self.read_dir_block(dir, physical, &mut block)?;
+                if let Some(entry) = dir::find_entry_in_block(&block, name)? {
+                    return Ok(Some(EntryLocation { physical, block, entry }));
+                }
+            }
+            
+            if !self.dx_next_leaf(dir, &mut path)? {
+                return Ok(None);
+            }
+        }
+    }
+    
+    /// インデックス化されたディレクトリにエントリを追加
+    ///
+    /// リーフが満杯ならハッシュ順に分割し、親のインデックスも満杯なら分割するか
+    /// 段数を増やす。インデックスが使えない場合は`false`を返す。
+    /// iノードの書き戻しは呼び出し側で行う。
+    pub(super) fn dx_add_entry(&self, dir: &mut Inode, entry: &DirectoryEntry) -> Result<bool, Ext4Error> {
+        let mut path = match self.dx_probe(dir, &entry.name)? {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        
+        let leaf_physical = self.map_block(dir, path.leaf())?;
+        if leaf_physical == 0 {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        let mut leaf = vec![0u8; self.block_size];
+        self.read_dir_block(dir, leaf_physical, &mut leaf)?;
+        
+        if dir::insert_entry(&mut leaf, entry)? {
+            self.write_dir_block(dir, leaf_physical, &leaf)?;
+            return Ok(true);
+        }
+        
+        // 分割したリーフを登録できるよう、先に親のインデックスに空きを作る
+        self.dx_make_room(dir, &mut path)?;
+        
+        let seed = self.dx_params().seed;
+        let version = path.version;
+        let (mut lower, mut upper, split_hash) =
+            split_leaf(&leaf, |name| hash::dir_hash(name.as_bytes(), version, &seed))?;
+        
+        let target = if path.hash.major >= (split_hash & !1) { &mut upper } else { &mut lower };
+        if !dir::insert_entry(target, entry)? {
+            return Err(Ext4Error::NoSpace);
+        }
+        
+        let (new_logical, _) = self.append_dir_block(dir, &upper)?;
+        self.write_dir_block(dir, leaf_physical, &lower)?;
+        
+        let frame = path.frames.last_mut().ok_or(Ext4Error::InternalError)?;
+        dx_insert(&mut frame.data, frame.offset, frame.position, split_hash, new_logical);
+        self.write_dir_block(dir, frame.physical, &frame.data)?;
+        
+        log::debug!("ext4: ディレクトリ{}のリーフ{}を分割しました (新ブロック{})",
+                    dir.get_number(), path.leaf(), new_logical);
+        Ok(true)
+    }
+    
+    /// 経路の最下段のインデックスに空きを作る
+    fn dx_make_room(&self, dir: &mut Inode, path: &mut DxPath) -> Result<(), Ext4Error> {
+        let full_from = |path: &DxPath| {
+            let mut top = path.frames.len();
+            while top > 0 && path.frames[top - 1].is_full() {
+                top -= 1;
+            }
+            top
+        };
+        
+        let mut top = full_from(path);
+        if top == path.frames.len() {
+            return Ok(());
+        }
+        
+        if top == 0 {
+            // dx_rootまで満杯なので段数を増やす
+            if path.indirect_levels >= self.dx_params().max_levels {
+                log::warn!("ext4: ディレクトリ{}のインデックスが満杯です", dir.get_number());
+                return Err(Ext4Error::NoSpace);
+            }
+            self.dx_grow_root(dir, path)?;
+            top = full_from(path);
+        }
+        
+        for level in top..path.frames.len() {
+            self.dx_split_node(dir, path, level)?;
+        }
+        Ok(())
+    }
+    
+    /// dx_rootのエントリを新しいdx_nodeに移し、インデックスを1段深くする
+    fn dx_grow_root(&self, dir: &mut Inode, path: &mut DxPath) -> Result<(), Ext4Error> {
+        let limit = node_limit(self.block_size, self.dx_params().metadata_csum);
+        let root = &path.frames[0];
+        let count = root.count();
+        
+        let mut node = init_node_block(self.block_size, limit);
+        let src = root.offset + 4..root.offset + count * DX_ENTRY_SIZE;
+        node[NODE_ENTRIES_OFFSET + 4..NODE_ENTRIES_OFFSET + count * DX_ENTRY_SIZE].copy_from_slice(&root.data[src]);
+        set_dx_count(&mut node, NODE_ENTRIES_OFFSET, count);
+        
+        let (logical, physical) = self.append_dir_block(dir, &node)?;
+        
+        let root = &mut path.frames[0];
+        let position = root.position;
+        set_dx_count(&mut root.data, root.offset, 1);
+        set_dx_entry(&mut root.data, root.offset, 0, 0, logical);
+        root.data[ROOT_INFO_OFFSET + 6] += 1;
+        root.position = 0;
+        self.write_dir_block(dir, root.physical, &root.data)?;
+        
+        path.indirect_levels += 1;
+        path.frames.insert(1, DxFrame { logical, physical, data: node, offset: NODE_ENTRIES_OFFSET, position });
+        
+        log::debug!("ext4: ディレクトリ{}のインデックスを{}段に拡張しました", dir.get_number(), path.indirect_levels + 1);
+        Ok(())
+    }
+    
+    /// 満杯のdx_nodeを半分に分割し、親に新しいノードを登録する
+    ///
+    /// 親には空きがあることを呼び出し側が保証する。
+    fn dx_split_node(&self, dir: &mut Inode, path: &mut DxPath, level: usize) -> Result<(), Ext4Error> {
+        let (parents, rest) = path.frames.split_at_mut(level);
+        let parent = parents.last_mut().ok_or(Ext4Error::InternalError)?;
+        let frame = &mut rest[0];
+        
+        let count = frame.count();
+        let half = count / 2;
+        let split_hash = dx_hash_at(&frame.data, frame.offset, half);
+        
+        let mut node = init_node_block(self.block_size, frame.limit());
+        for i in half..count {
+            let hash = dx_hash_at(&frame.data, frame.offset, i);
+            let block = dx_block_at(&frame.data, frame.offset, i);
+            set_dx_entry(&mut node, NODE_ENTRIES_OFFSET, i - half, hash, block);
+        }
+        set_dx_count(&mut node, NODE_ENTRIES_OFFSET, count - half);
+        set_dx_count(&mut frame.data, frame.offset, half);
+        
+        let (logical, physical) = self.append_dir_block(dir, &node)?;
+        self.write_dir_block(dir, frame.physical, &frame.data)?;
+        
+        dx_insert(&mut parent.data, parent.offset, parent.position, split_hash, logical);
+        self.write_dir_block(dir, parent.physical, &parent.data)?;
+        
+        if frame.position >= half {
+            frame.position -= half;
+            frame.logical = logical;
+            frame.physical = physical;
+            frame.data = node;
+            parent.position += 1;
+        }
+        Ok(())
+    }
+    
+    /// 1ブロックの線形ディレクトリをインデックス化してエントリを追加
+    ///
+    /// 先頭ブロックの"."と".."以外のエントリを新しいリーフに移し、先頭ブロックを
+    /// dx_rootに置き換える。iノードの書き戻しは呼び出し側で行う。
+    pub(super) fn make_indexed_dir(&self, dir: &mut Inode, entry: &DirectoryEntry) -> Result<bool, Ext4Error> {
+        let params = self.dx_params();
+        if HashVersion::from_byte(params.def_hash_version).is_none() {
+            return Ok(false);
+        }
+        
+        let root_physical = self.map_block(dir, 0)?;
+        if root_physical == 0 {
+            return Ok(false);
+        }
+        let mut block = vec![0u8; self.block_size];
+        self.read_dir_block(dir, root_physical, &mut block)?;
+        
+        let entries = dir::parse_directory_block(&block)?;
+        if entries.len() < 2 || entries[0].name != "." || entries[1].name != ".." {
+            log::warn!("ext4: ディレクトリ{}の先頭に\".\"と\"..\"がありません", dir.get_number());
+            return Ok(false);
+        }
+        
+        let leaf = dir::build_directory_block(self.block_size, &entries[2..], dir::has_dir_tail(&block));
+        let (leaf_logical, _) = self.append_dir_block(dir, &leaf)?;
+        
+        let root = init_root_block(
+            self.block_size,
+            entries[0].inode,
+            entries[1].inode,
+            entries[0].file_type,
+            params.def_hash_version,
+            leaf_logical,
+            root_limit(self.block_size, params.metadata_csum),
+        );
+        self.write_dir_block(dir, root_physical, &root)?;
+        dir.flags |= InodeFlags::DirIndex as u32;
+        
+        log::debug!("ext4: ディレクトリ{}をインデックス化しました", dir.get_number());
+        self.dx_add_entry(dir, entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::hash::dir_hash;
+    
+    fn test_hash(name: &str) -> DirHash {
+        dir_hash(name.as_bytes(), HashVersion::HalfMd4, &[0; 4])
+    }
+    
+    #[test]
+    fn test_root_block_search_and_insert() {
+        let mut root = init_root_block(1024, 12, 2, DirectoryEntryType::Directory, 1, 1, root_limit(1024, false));
+        let info = DxRootInfo::parse(&root).unwrap();
+        let offset = info.entries_offset();
+        assert_eq!(info.indirect_levels, 0);
+        assert_eq!(dx_limit(&root, offset), 124);
+        
+        dx_insert(&mut root, offset, 0, 0x8000_0000, 2);
+        dx_insert(&mut root, offset, 0, 0x4000_0000, 3);
+        assert_eq!(dx_count(&root, offset), 3);
+        assert_eq!(dx_search(&root, offset, 0x1000_0000), 0);
+        assert_eq!(dx_search(&root, offset, 0x4000_0000), 1);
+        assert_eq!(dx_block_at(&root, offset, 1), 3);
+        assert_eq!(dx_search(&root, offset, 0xFFFF_FFFE), 2);
+        
+        // "."と".."は線形走査からもそのまま読める
+        let entries = dir::parse_directory_block(&root).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].name, "..");
+    }
+    
+    #[test]
+    fn test_split_leaf_orders_by_hash() {
+        let mut block = dir::build_directory_block(1024, &[], false);
+        let mut n = 0;
+        loop {
+            let name = format!("object-{:04}.o", n);
+            let entry = DirectoryEntry::new(100 + n, &name, DirectoryEntryType::RegularFile);
+            if !dir::insert_entry(&mut block, &entry).unwrap() {
+                break;
+            }
+            n += 1;
+        }
+        
+        let (lower, upper, split_hash) = split_leaf(&block, test_hash).unwrap();
+        let lower_entries = dir::parse_directory_block(&lower).unwrap();
+        let upper_entries = dir::parse_directory_block(&upper).unwrap();
+        assert_eq!(lower_entries.len() + upper_entries.len(), n as usize);
+        assert!(lower_entries.iter().all(|e| test_hash(&e.name).major <= split_hash & !1));
+        assert!(upper_entries.iter().all(|e| test_hash(&e.name).major >= split_hash & !1));
+        
+        // 分割後はどちらにも新しいエントリが入る
+        let extra = DirectoryEntry::new(1, "extra", DirectoryEntryType::RegularFile);
+        let (mut lower, mut upper) = (lower, upper);
+        assert!(dir::insert_entry(&mut lower, &extra).unwrap());
+        assert!(dir::insert_entry(&mut upper, &extra).unwrap());
+    }
+}
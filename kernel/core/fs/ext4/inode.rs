@@ -4,6 +4,7 @@
 
 use alloc::vec::Vec;
 use super::super::{FsError, FsResult};
+use super::extent::{EXTENT_MAGIC, INLINE_EXTENT_MAX};
 
 /// アイノードフラグ
 #[repr(u32)]
@@ -113,8 +114,12 @@ pub struct Ext4Inode {
     // 計算されたフィールド
     /// 合計ファイルサイズ
     pub size: u64,
-    /// 合計ブロック数
+    /// 合計ブロック数（512バイト単位）
     pub blocks: u64,
+    /// iノード番号
+    pub number: u32,
+    /// ディスク上の生データ（未解析の拡張領域を書き戻すために保持）
+    pub raw: Vec<u8>,
 }
 
 impl Ext4Inode {
@@ -124,6 +129,7 @@ impl Ext4Inode {
             return Err(FsError::InvalidData);
         }
         
+        let raw = data[..data.len().min(size.max(128))].to_vec();
         let mode = u16::from_le_bytes([data[0], data[1]]);
         let uid_lo = u16::from_le_bytes([data[2], data[3]]);
         let size_lo = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
@@ -175,8 +181,9 @@ impl Ext4Inode {
         }
         
         // 高次ビットを考慮したUID/GID
-        let uid_hi = (osd2[2] as u32) << 16 | (osd2[3] as u32) << 24;
-        let gid_hi = (osd2[4] as u32) << 16 | (osd2[5] as u32) << 24;
+        // osd2: blocks_hi(0..2), file_acl_hi(2..4), uid_hi(4..6), gid_hi(6..8)
+        let uid_hi = (osd2[4] as u32) << 16 | (osd2[5] as u32) << 24;
+        let gid_hi = (osd2[6] as u32) << 16 | (osd2[7] as u32) << 24;
         let uid = uid_hi | (uid_lo as u32);
         let gid = gid_hi | (gid_lo as u32);
         
@@ -215,9 +222,194 @@ impl Ext4Inode {
             projid,
             size,
             blocks,
+            number: 0,
+            raw,
         })
     }
     
+    /// 新規作成するiノードを初期化
+    pub fn new(number: u32, mode: u16, uid: u32, gid: u32, time: u32, inode_size: usize, use_extents: bool) -> Self {
+        let mut inode = Self {
+            mode,
+            uid,
+            size_lo: 0,
+            atime: time,
+            ctime: time,
+            mtime: time,
+            dtime: 0,
+            gid,
+            links_count: 0,
+            blocks_lo: 0,
+            flags: 0,
+            osd1: 0,
+            block: [0; 15],
+            generation: 0,
+            file_acl_lo: 0,
+            size_hi: 0,
+            fragment_addr: 0,
+            osd2: [0; 12],
+            // 128バイトを超える部分はLinuxの既定値と同じく32バイトの拡張領域を使う
+            extra_isize: if inode_size > 128 { 32 } else { 0 },
+            checksum_hi: 0,
+            ctime_extra: 0,
+            mtime_extra: 0,
+            atime_extra: 0,
+            crtime: time,
+            crtime_extra: 0,
+            version_hi: 0,
+            projid: 0,
+            size: 0,
+            blocks: 0,
+            number,
+            raw: vec![0; inode_size],
+        };
+        
+        if use_extents {
+            inode.flags |= InodeFlags::Extents as u32;
+            inode.clear_extent_root();
+        }
+        
+        inode
+    }
+    
+    /// ディスク上の形式にシリアライズ
+    pub fn to_bytes(&self, inode_size: usize) -> Vec<u8> {
+        let mut data = self.raw.clone();
+        data.resize(inode_size, 0);
+        
+        let put16 = |data: &mut Vec<u8>, offset: usize, value: u16| {
+            data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+        };
+        let put32 = |data: &mut Vec<u8>, offset: usize, value: u32| {
+            data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        
+        put16(&mut data, 0, self.mode);
+        put16(&mut data, 2, self.uid as u16);
+        put32(&mut data, 4, self.size as u32);
+        put32(&mut data, 8, self.atime);
+        put32(&mut data, 12, self.ctime);
+        put32(&mut data, 16, self.mtime);
+        put32(&mut data, 20, self.dtime);
+        put16(&mut data, 24, self.gid as u16);
+        put16(&mut data, 26, self.links_count);
+        put32(&mut data, 28, self.blocks as u32);
+        put32(&mut data, 32, self.flags);
+        put32(&mut data, 36, self.osd1);
+        for (i, word) in self.block.iter().enumerate() {
+            put32(&mut data, 40 + i * 4, *word);
+        }
+        put32(&mut data, 100, self.generation);
+        put32(&mut data, 104, self.file_acl_lo);
+        put32(&mut data, 108, (self.size >> 32) as u32);
+        put32(&mut data, 112, self.fragment_addr);
+        
+        // osd2: blocks_hi, file_acl_hi, uid_hi, gid_hi, checksum_lo
+        let mut osd2 = self.osd2;
+        osd2[0..2].copy_from_slice(&((self.blocks >> 32) as u16).to_le_bytes());
+        osd2[4..6].copy_from_slice(&((self.uid >> 16) as u16).to_le_bytes());
+        osd2[6..8].copy_from_slice(&((self.gid >> 16) as u16).to_le_bytes());
+        data[116..128].copy_from_slice(&osd2);
+        
+        if inode_size > 128 && self.extra_isize >= 4 {
+            put16(&mut data, 128, self.extra_isize);
+            put16(&mut data, 130, self.checksum_hi);
+            let extra_end = 128 + self.extra_isize as usize;
+            let fields = [
+                (132, self.ctime_extra),
+                (136, self.mtime_extra),
+                (140, self.atime_extra),
+                (144, self.crtime),
+                (148, self.crtime_extra),
+                (152, self.version_hi),
+                (156, self.projid),
+            ];
+            for (offset, value) in fields {
+                if offset + 4 <= extra_end && offset + 4 <= inode_size {
+                    put32(&mut data, offset, value);
+                }
+            }
+        }
+        
+        data
+    }
+    
+    /// iノード番号を取得
+    pub fn get_number(&self) -> u32 {
+        self.number
+    }
+    
+    /// ファイルサイズを取得
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+    
+    /// ファイルサイズを設定
+    pub fn set_size(&mut self, size: u64) {
+        self.size = size;
+        self.size_lo = size as u32;
+        self.size_hi = (size >> 32) as u32;
+    }
+    
+    /// 最終修正時間を設定
+    pub fn set_mtime(&mut self, time: u32) {
+        self.mtime = time;
+    }
+    
+    /// iノード変更時間を設定
+    pub fn set_ctime(&mut self, time: u32) {
+        self.ctime = time;
+    }
+    
+    /// 割り当てブロック数（512バイト単位）を加減算
+    pub fn adjust_blocks(&mut self, delta: i64) {
+        self.blocks = (self.blocks as i64 + delta).max(0) as u64;
+        self.blocks_lo = self.blocks as u32;
+    }
+    
+    /// 拡張属性ブロックの番号（0ならなし）
+    pub fn file_acl(&self) -> u64 {
+        self.file_acl_lo as u64 | (u16::from_le_bytes([self.osd2[2], self.osd2[3]]) as u64) << 32
+    }
+    
+    /// 拡張属性ブロックの番号を設定
+    pub fn set_file_acl(&mut self, block: u64) {
+        self.file_acl_lo = block as u32;
+        self.osd2[2..4].copy_from_slice(&((block >> 32) as u16).to_le_bytes());
+    }
+    
+    /// iノード内の拡張属性領域（i_extra_isizeの後ろ、領域がなければ`None`）
+    pub fn xattr_region(&self) -> Option<core::ops::Range<usize>> {
+        let start = 128 + self.extra_isize as usize;
+        if self.extra_isize == 0 || start + 8 > self.raw.len() {
+            return None;
+        }
+        Some(start..self.raw.len())
+    }
+    
+    /// i_blockの内容をバイト列で取得（エクステントツリーのルート）
+    pub fn block_bytes(&self) -> [u8; 60] {
+        let mut data = [0u8; 60];
+        for (i, word) in self.block.iter().enumerate() {
+            data[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        data
+    }
+    
+    /// i_blockにバイト列を設定
+    pub fn set_block_bytes(&mut self, data: &[u8; 60]) {
+        for (i, word) in self.block.iter_mut().enumerate() {
+            *word = u32::from_le_bytes([data[i * 4], data[i * 4 + 1], data[i * 4 + 2], data[i * 4 + 3]]);
+        }
+    }
+    
+    /// i_blockを空のエクステントツリー（深さ0のリーフ）にする
+    pub fn clear_extent_root(&mut self) {
+        self.block = [0; 15];
+        self.block[0] = EXTENT_MAGIC as u32;
+        self.block[1] = INLINE_EXTENT_MAX as u32;
+    }
+    
     /// アイノードがディレクトリかどうか
     pub fn is_directory(&self) -> bool {
         (self.mode & 0xF000) == 0x4000
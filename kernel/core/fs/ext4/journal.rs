@@ -1,37 +1,77 @@
 // Ext4 ジャーナル実装
 //
-// Ext4ファイルシステムのジャーナリング機能
+// JBD2形式のジャーナルの解析と、スキャン・リボーク・リプレイの3パスによるリカバリ
 
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
-use super::super::{FsError, FsResult, journal as fs_journal};
+use super::super::{FsError, FsResult};
+use super::super::crc32c::crc32c;
 use log;
 
-/// Ext4ジャーナルディスクリプタタグ
-#[derive(Debug, Clone, Copy)]
-pub struct JournalDescriptorTag {
-    /// ブロック番号
-    pub blocknr: u32,
-    /// フラグ
-    pub flags: u32,
-}
+/// JBD2のマジックナンバー
+pub const JBD2_MAGIC: u32 = 0xC03B_3998;
+
+/// 互換機能: コミットブロックのCRC32（v1チェックサム）
+pub const JBD2_FEATURE_COMPAT_CHECKSUM: u32 = 0x0001;
+/// 非互換機能: リボークブロック
+pub const JBD2_FEATURE_INCOMPAT_REVOKE: u32 = 0x0001;
+/// 非互換機能: 64ビットブロック番号
+pub const JBD2_FEATURE_INCOMPAT_64BIT: u32 = 0x0002;
+/// 非互換機能: 非同期コミット
+pub const JBD2_FEATURE_INCOMPAT_ASYNC_COMMIT: u32 = 0x0004;
+/// 非互換機能: チェックサムv2
+pub const JBD2_FEATURE_INCOMPAT_CSUM_V2: u32 = 0x0008;
+/// 非互換機能: チェックサムv3
+pub const JBD2_FEATURE_INCOMPAT_CSUM_V3: u32 = 0x0010;
+/// 非互換機能: 高速コミット
+pub const JBD2_FEATURE_INCOMPAT_FAST_COMMIT: u32 = 0x0020;
+
+/// このドライバが理解できる非互換機能
+const JBD2_KNOWN_INCOMPAT: u32 = JBD2_FEATURE_INCOMPAT_REVOKE
+    | JBD2_FEATURE_INCOMPAT_64BIT
+    | JBD2_FEATURE_INCOMPAT_ASYNC_COMMIT
+    | JBD2_FEATURE_INCOMPAT_CSUM_V2
+    | JBD2_FEATURE_INCOMPAT_CSUM_V3
+    | JBD2_FEATURE_INCOMPAT_FAST_COMMIT;
+
+/// ジャーナルスーパーブロックのチェックサム種別: CRC32C
+const JBD2_CRC32C_CHKSUM: u8 = 4;
+
+/// 高速コミット領域のデフォルトブロック数
+const JBD2_DEFAULT_FAST_COMMIT_BLOCKS: u32 = 256;
+
+/// ジャーナルスーパーブロックのサイズ（チェックサム計算範囲）
+const JOURNAL_SUPERBLOCK_SIZE: usize = 1024;
+
+/// ブロックヘッダのサイズ
+const HEADER_SIZE: usize = 12;
 
-/// Ext4ジャーナルブロックタイプ
+/// リボークブロックのヘッダサイズ（ブロックヘッダ + 使用バイト数）
+const REVOKE_HEADER_SIZE: usize = 16;
+
+/// ディスクリプタ/リボークブロック末尾のチェックサムのサイズ
+const BLOCK_TAIL_SIZE: usize = 4;
+
+/// タグの後に続くUUIDのサイズ
+const UUID_SIZE: usize = 16;
+
+/// ジャーナルブロックタイプ
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum JournalBlockType {
     /// ディスクリプタブロック
-    Descriptor = 0x4,
+    Descriptor = 0x1,
     /// コミットブロック
-    Commit = 0x5,
+    Commit = 0x2,
     /// スーパーブロック V1
-    SuperblockV1 = 0x1,
+    SuperblockV1 = 0x3,
     /// スーパーブロック V2
-    SuperblockV2 = 0x2,
+    SuperblockV2 = 0x4,
     /// リボケーションブロック
-    Revoke = 0x3,
+    Revoke = 0x5,
 }
 
-/// Ext4ジャーナルヘッダ
+/// ジャーナルブロックヘッダ（全フィールドはビッグエンディアン）
 #[derive(Debug, Clone, Copy)]
 pub struct JournalHeader {
     /// マジックシグネチャ
@@ -45,20 +85,20 @@ pub struct JournalHeader {
 impl JournalHeader {
     /// ジャーナルヘッダをパース
     pub fn parse(data: &[u8]) -> FsResult<Self> {
-        if data.len() < 12 {
+        if data.len() < HEADER_SIZE {
             return Err(FsError::InvalidData);
         }
         
-        let magic = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
-        let block_type_raw = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
-        let sequence = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        let magic = read_be32(data, 0);
+        let block_type_raw = read_be32(data, 4);
+        let sequence = read_be32(data, 8);
         
         let block_type = match block_type_raw {
-            0x1 => JournalBlockType::SuperblockV1,
-            0x2 => JournalBlockType::SuperblockV2,
-            0x3 => JournalBlockType::Revoke,
-            0x4 => JournalBlockType::Descriptor,
-            0x5 => JournalBlockType::Commit,
+            0x1 => JournalBlockType::Descriptor,
+            0x2 => JournalBlockType::Commit,
+            0x3 => JournalBlockType::SuperblockV1,
+            0x4 => JournalBlockType::SuperblockV2,
+            0x5 => JournalBlockType::Revoke,
             _ => return Err(FsError::InvalidData),
         };
         
@@ -71,7 +111,7 @@ impl JournalHeader {
     
     /// ジャーナルヘッダをシリアライズ
     pub fn serialize(&self) -> Vec<u8> {
-        let mut result = Vec::with_capacity(12);
+        let mut result = Vec::with_capacity(HEADER_SIZE);
         
         result.extend_from_slice(&self.magic.to_be_bytes());
         result.extend_from_slice(&(self.block_type as u32).to_be_bytes());
@@ -81,249 +121,683 @@ impl JournalHeader {
     }
 }
 
-/// Ext4ジャーナルスーパーブロック
+/// ジャーナルスーパーブロック
 #[derive(Debug, Clone)]
 pub struct JournalSuperblock {
     /// ヘッダ
     pub header: JournalHeader,
-    /// ジャーナルブロック数
-    pub block_count: u32,
-    /// 最初の使用可能なブロック
+    /// ジャーナルのブロックサイズ
+    pub block_size: u32,
+    /// ジャーナルのブロック数
+    pub max_len: u32,
+    /// ログ領域の最初のブロック
     pub first: u32,
-    /// 最初の未コミットブロック
-    pub first_uncommitted: u32,
-    /// エラーのあるブロック
-    pub errno: u32,
+    /// ログ先頭のトランザクションID
+    pub sequence: u32,
+    /// ログの開始ブロック（0ならログは空）
+    pub start: u32,
+    /// エラー番号
+    pub errno: i32,
+    /// 互換機能
+    pub feature_compat: u32,
+    /// 非互換機能
+    pub feature_incompat: u32,
+    /// 読み取り専用互換機能
+    pub feature_ro_compat: u32,
+    /// ジャーナルのUUID
+    pub uuid: [u8; 16],
+    /// チェックサム種別
+    pub checksum_type: u8,
+    /// 高速コミット領域のブロック数
+    pub num_fc_blocks: u32,
+    /// スーパーブロックのチェックサム
+    pub checksum: u32,
 }
 
 impl JournalSuperblock {
     /// ジャーナルスーパーブロックをパース
     pub fn parse(data: &[u8]) -> FsResult<Self> {
-        if data.len() < 28 {
+        if data.len() < JOURNAL_SUPERBLOCK_SIZE {
             return Err(FsError::InvalidData);
         }
         
-        let header = JournalHeader::parse(&data[0..12])?;
+        let header = JournalHeader::parse(&data[0..HEADER_SIZE])?;
+        let is_v2 = header.block_type == JournalBlockType::SuperblockV2;
+        
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(&data[0x30..0x40]);
         
-        let block_count = u32::from_be_bytes([data[12], data[13], data[14], data[15]]);
-        let first = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
-        let first_uncommitted = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
-        let errno = u32::from_be_bytes([data[24], data[25], data[26], data[27]]);
+        // V1スーパーブロックには機能フラグがない
+        let feature = |offset: usize| if is_v2 { read_be32(data, offset) } else { 0 };
         
         Ok(Self {
             header,
-            block_count,
-            first,
-            first_uncommitted,
-            errno,
+            block_size: read_be32(data, 0x0C),
+            max_len: read_be32(data, 0x10),
+            first: read_be32(data, 0x14),
+            sequence: read_be32(data, 0x18),
+            start: read_be32(data, 0x1C),
+            errno: read_be32(data, 0x20) as i32,
+            feature_compat: feature(0x24),
+            feature_incompat: feature(0x28),
+            feature_ro_compat: feature(0x2C),
+            uuid,
+            checksum_type: data[0x50],
+            num_fc_blocks: read_be32(data, 0x54),
+            checksum: read_be32(data, 0xFC),
         })
     }
+    
+    /// 非互換機能が有効かどうか
+    pub fn has_incompat(&self, flag: u32) -> bool {
+        (self.feature_incompat & flag) != 0
+    }
+    
+    /// チェックサムv2またはv3が有効かどうか
+    pub fn has_csum_v2or3(&self) -> bool {
+        self.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V2 | JBD2_FEATURE_INCOMPAT_CSUM_V3)
+    }
 }
 
-/// Ext4ジャーナル管理
+/// ディスクリプタブロックのタグフラグ
+pub mod tag_flags {
+    /// データブロックの先頭4バイトがマジックナンバーだったため0にされている
+    pub const ESCAPE: u16 = 0x1;
+    /// 直前のタグと同じUUID（UUIDが省略されている）
+    pub const SAME_UUID: u16 = 0x2;
+    /// ブロックは削除済み（未使用）
+    pub const DELETED: u16 = 0x4;
+    /// ディスクリプタ内の最後のタグ
+    pub const LAST_TAG: u16 = 0x8;
+}
+
+/// ディスクリプタブロックのタグ
+#[derive(Debug, Clone, Copy)]
+pub struct JournalBlockTag {
+    /// 書き戻し先のファイルシステムブロック番号
+    pub block: u64,
+    /// フラグ
+    pub flags: u16,
+    /// データブロックのチェックサム（v2は下位16ビットのみ）
+    pub checksum: u32,
+}
+
+/// ジャーナルとファイルシステムへのブロックアクセス
+pub trait JournalIo {
+    /// ブロックサイズ（ファイルシステムとジャーナルで共通）
+    fn block_size(&self) -> usize;
+    
+    /// ジャーナル内の論理ブロックを読み込み
+    fn read_journal_block(&self, block: u32, buffer: &mut [u8]) -> FsResult<()>;
+    
+    /// ジャーナル内の論理ブロックに書き込み
+    fn write_journal_block(&self, block: u32, buffer: &[u8]) -> FsResult<()>;
+    
+    /// ファイルシステムのブロックに書き込み
+    fn write_fs_block(&self, block: u64, buffer: &[u8]) -> FsResult<()>;
+}
+
+/// リカバリの各パス
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecoveryPass {
+    /// ログの終端（最後の完全なトランザクション）を探す
+    Scan,
+    /// リボークレコードを集める
+    Revoke,
+    /// リボークされていないブロックを書き戻す
+    Replay,
+}
+
+/// リカバリ結果
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecoveryInfo {
+    /// 最初に再生したトランザクションID
+    pub start_transaction: u32,
+    /// 最初の未完了トランザクションID（ここで再生を止めた）
+    pub end_transaction: u32,
+    /// 書き戻したブロック数
+    pub replayed_blocks: u32,
+    /// リボークにより書き戻さなかったブロック数
+    pub revoked_blocks: u32,
+    /// チェックサム不一致で書き戻さなかったブロック数
+    pub corrupt_blocks: u32,
+}
+
+/// トランザクションIDの比較（32ビットの周回を考慮）
+fn tid_gt(x: u32, y: u32) -> bool {
+    (x.wrapping_sub(y) as i32) > 0
+}
+
+fn tid_geq(x: u32, y: u32) -> bool {
+    (x.wrapping_sub(y) as i32) >= 0
+}
+
+fn read_be16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_be32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// JBD2ジャーナル
 pub struct Journal {
-    /// ファイルシステムデバイスID
-    device_id: u64,
-    /// ジャーナルアイノード番号
-    journal_inode: u32,
-    /// ジャーナルのスーパーブロック
-    superblock: Option<JournalSuperblock>,
+    /// ジャーナルスーパーブロック
+    superblock: JournalSuperblock,
+    /// ログ領域の終端（高速コミット領域を除く）
+    last: u32,
+    /// チェックサムのシード（UUIDのCRC32C）
+    csum_seed: u32,
 }
 
 impl Journal {
-    /// 新しいジャーナルインスタンスを作成
-    pub fn new(device_id: u64, journal_inode: u32) -> Self {
-        Self {
-            device_id,
-            journal_inode,
-            superblock: None,
+    /// ジャーナルスーパーブロックを読み込んで検証
+    pub fn load(io: &dyn JournalIo) -> FsResult<Self> {
+        let block_size = io.block_size();
+        let mut data = vec![0u8; block_size];
+        io.read_journal_block(0, &mut data)?;
+        
+        let superblock = JournalSuperblock::parse(&data)?;
+        if superblock.header.magic != JBD2_MAGIC {
+            log::error!("ext4: 無効なジャーナルマジックナンバー: 0x{:X}", superblock.header.magic);
+            return Err(FsError::JournalError);
+        }
+        if !matches!(superblock.header.block_type, JournalBlockType::SuperblockV1 | JournalBlockType::SuperblockV2) {
+            log::error!("ext4: ジャーナルの先頭がスーパーブロックではありません");
+            return Err(FsError::JournalError);
+        }
+        if superblock.block_size as usize != block_size {
+            log::error!("ext4: ジャーナルのブロックサイズ{}がファイルシステムと一致しません", superblock.block_size);
+            return Err(FsError::JournalError);
+        }
+        
+        let unknown = superblock.feature_incompat & !JBD2_KNOWN_INCOMPAT;
+        if unknown != 0 {
+            log::error!("ext4: 未対応のジャーナル機能があります: 0x{:X}", unknown);
+            return Err(FsError::UnsupportedFeature);
+        }
+        if superblock.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V2)
+            && superblock.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            log::error!("ext4: ジャーナルでチェックサムv2とv3が同時に有効です");
+            return Err(FsError::JournalError);
+        }
+        
+        if superblock.has_csum_v2or3() {
+            if superblock.checksum_type != JBD2_CRC32C_CHKSUM {
+                log::error!("ext4: 未対応のジャーナルチェックサム種別: {}", superblock.checksum_type);
+                return Err(FsError::UnsupportedFeature);
+            }
+            let mut copy = data[..JOURNAL_SUPERBLOCK_SIZE].to_vec();
+            copy[0xFC..0x100].copy_from_slice(&[0; 4]);
+            if crc32c(!0, &copy) != superblock.checksum {
+                log::error!("ext4: ジャーナルスーパーブロックのチェックサムが一致しません");
+                return Err(FsError::JournalError);
+            }
+        }
+        
+        let mut last = superblock.max_len;
+        if superblock.has_incompat(JBD2_FEATURE_INCOMPAT_FAST_COMMIT) {
+            let fc_blocks = if superblock.num_fc_blocks == 0 {
+                JBD2_DEFAULT_FAST_COMMIT_BLOCKS
+            } else {
+                superblock.num_fc_blocks
+            };
+            last = last.saturating_sub(fc_blocks);
+        }
+        
+        if superblock.first == 0 || superblock.first >= last
+            || (superblock.start != 0 && (superblock.start < superblock.first || superblock.start >= last)) {
+            log::error!("ext4: ジャーナルの範囲が不正です (first={}, start={}, last={})",
+                        superblock.first, superblock.start, last);
+            return Err(FsError::JournalError);
         }
+        
+        if superblock.errno != 0 {
+            log::warn!("ext4: ジャーナルにエラーが記録されています: errno={}", superblock.errno);
+        }
+        
+        let csum_seed = crc32c(!0, &superblock.uuid);
+        Ok(Self { superblock, last, csum_seed })
+    }
+    
+    /// ジャーナルスーパーブロック
+    pub fn superblock(&self) -> &JournalSuperblock {
+        &self.superblock
     }
     
-    /// ジャーナルを初期化
-    pub fn init(&mut self) -> FsResult<()> {
-        log::info!("Ext4ジャーナルを初期化: デバイス={}, アイノード={}", 
-                 self.device_id, self.journal_inode);
+    /// 再生が必要なトランザクションが残っているかどうか
+    pub fn needs_recovery(&self) -> bool {
+        self.superblock.start != 0
+    }
+    
+    /// ジャーナルを再生し、ログを空にする
+    ///
+    /// 1. スキャン: 先頭から連続するトランザクションIDとコミットブロックをたどり、
+    ///    最後の完全なトランザクションを決める
+    /// 2. リボーク: リボークレコードを集め、ブロックごとに最新のトランザクションIDを記録する
+    /// 3. リプレイ: 各ディスクリプタのデータブロックを、それ以降のトランザクションで
+    ///    リボークされていなければファイルシステムに書き戻す
+    pub fn recover(&mut self, io: &dyn JournalIo) -> FsResult<RecoveryInfo> {
+        let mut info = RecoveryInfo {
+            start_transaction: self.superblock.sequence,
+            end_transaction: self.superblock.sequence,
+            ..RecoveryInfo::default()
+        };
         
-        // ジャーナルアイノードからブロックを取得
-        let inode_data = read_inode(self.device_id, self.journal_inode)?;
+        if !self.needs_recovery() {
+            log::debug!("ext4: ジャーナルは空です（リカバリ不要）");
+            return Ok(info);
+        }
         
-        // アイノードからジャーナルの先頭ブロック番号を取得
-        let journal_blocks = extract_inode_blocks(&inode_data)?;
+        if self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_FAST_COMMIT) {
+            log::warn!("ext4: 高速コミット領域は再生されません");
+        }
         
-        if journal_blocks.is_empty() {
-            log::error!("ジャーナルアイノードにブロックがありません");
-            return Err(FsError::InvalidData);
+        let mut revoked = BTreeMap::new();
+        for pass in [RecoveryPass::Scan, RecoveryPass::Revoke, RecoveryPass::Replay] {
+            self.do_one_pass(io, pass, &mut info, &mut revoked)?;
         }
         
-        let journal_first_block = journal_blocks[0];
+        log::info!("ext4: ジャーナルを再生しました (トランザクション{}..{}, {}ブロック, リボーク{}, 破損{})",
+                   info.start_transaction, info.end_transaction, info.replayed_blocks,
+                   info.revoked_blocks, info.corrupt_blocks);
         
-        // デバイスをオープン
-        let device_manager = crate::drivers::block::get_device_manager();
-        let device = match device_manager.get_device(self.device_id) {
-            Some(dev) => dev,
-            None => {
-                log::error!("デバイス ID {} が見つかりません", self.device_id);
-                return Err(FsError::DeviceNotFound);
-            }
-        };
+        // 再生済みのログを空にする
+        self.superblock.sequence = info.end_transaction.wrapping_add(1);
+        self.superblock.start = 0;
+        self.write_superblock(io)?;
         
-        // ジャーナルスーパーブロックを読み込み
-        let sb_data = match device.read_block(journal_first_block) {
-            Ok(data) => data,
-            Err(e) => {
-                log::error!("ジャーナルスーパーブロック読み込みエラー: {:?}", e);
-                return Err(FsError::IoError);
+        Ok(info)
+    }
+    
+    /// ログを1周たどる
+    fn do_one_pass(
+        &self,
+        io: &dyn JournalIo,
+        pass: RecoveryPass,
+        info: &mut RecoveryInfo,
+        revoked: &mut BTreeMap<u64, u32>,
+    ) -> FsResult<()> {
+        let block_size = io.block_size();
+        let csum = self.superblock.has_csum_v2or3();
+        let mut next_commit = self.superblock.sequence;
+        let mut next_block = self.superblock.start;
+        let mut buffer = vec![0u8; block_size];
+        let mut data = vec![0u8; block_size];
+        
+        loop {
+            if pass != RecoveryPass::Scan && tid_geq(next_commit, info.end_transaction) {
+                break;
             }
-        };
-        
-        // スーパーブロックを解析
-        let superblock = match JournalSuperblock::parse(&sb_data) {
-            Ok(sb) => sb,
-            Err(e) => {
-                log::error!("ジャーナルスーパーブロック解析エラー: {:?}", e);
-                return Err(e);
+            
+            io.read_journal_block(next_block, &mut buffer)?;
+            next_block = self.wrap(next_block + 1);
+            
+            let header = match JournalHeader::parse(&buffer) {
+                Ok(header) if header.magic == JBD2_MAGIC => header,
+                _ => break,
+            };
+            if header.sequence != next_commit {
+                break;
             }
-        };
+            
+            match header.block_type {
+                JournalBlockType::Descriptor => {
+                    if csum && !self.verify_block_tail(&buffer) {
+                        log::warn!("ext4: トランザクション{}のディスクリプタのチェックサムが一致しません", next_commit);
+                        break;
+                    }
+                    
+                    let tags = self.parse_tags(&buffer);
+                    if pass != RecoveryPass::Replay {
+                        next_block = self.wrap(next_block + tags.len() as u32);
+                        continue;
+                    }
+                    
+                    for tag in tags {
+                        io.read_journal_block(next_block, &mut data)?;
+                        next_block = self.wrap(next_block + 1);
+                        
+                        if let Some(&revoke_seq) = revoked.get(&tag.block) {
+                            if tid_geq(revoke_seq, next_commit) {
+                                info.revoked_blocks += 1;
+                                continue;
+                            }
+                        }
+                        
+                        if csum && !self.verify_data_block(&data, next_commit, &tag) {
+                            log::warn!("ext4: ジャーナル内のブロック{}のチェックサムが一致しません", tag.block);
+                            info.corrupt_blocks += 1;
+                            continue;
+                        }
+                        
+                        if (tag.flags & tag_flags::ESCAPE) != 0 {
+                            data[0..4].copy_from_slice(&JBD2_MAGIC.to_be_bytes());
+                        }
+                        
+                        io.write_fs_block(tag.block, &data)?;
+                        info.replayed_blocks += 1;
+                    }
+                },
+                JournalBlockType::Commit => {
+                    if pass == RecoveryPass::Scan && csum && !self.verify_commit_block(&buffer) {
+                        // コミットが不完全なトランザクションは再生しない
+                        log::warn!("ext4: トランザクション{}のコミットブロックのチェックサムが一致しません", next_commit);
+                        break;
+                    }
+                    next_commit = next_commit.wrapping_add(1);
+                },
+                JournalBlockType::Revoke => {
+                    if csum && !self.verify_block_tail(&buffer) {
+                        log::warn!("ext4: トランザクション{}のリボークブロックのチェックサムが一致しません", next_commit);
+                        break;
+                    }
+                    if pass == RecoveryPass::Revoke {
+                        self.scan_revoke_records(&buffer, next_commit, revoked)?;
+                    }
+                },
+                JournalBlockType::SuperblockV1 | JournalBlockType::SuperblockV2 => break,
+            }
+        }
         
-        // スーパーブロックのマジックナンバーを確認
-        if superblock.header.magic != EXT4_JOURNAL_MAGIC {
-            log::error!("無効なジャーナルマジックナンバー: 0x{:X}", superblock.header.magic);
-            return Err(FsError::InvalidFormat);
+        if pass == RecoveryPass::Scan {
+            info.end_transaction = next_commit;
+        } else if next_commit != info.end_transaction {
+            log::error!("ext4: ジャーナル再生パス{:?}がトランザクション{}で終了しました（期待値{}）",
+                        pass, next_commit, info.end_transaction);
+            return Err(FsError::JournalError);
         }
         
-        // ジャーナル情報をログ出力
-        log::info!("Ext4ジャーナル: ブロック数={}, 最初のブロック={}, 未コミット={}",
-                 superblock.block_count, superblock.first, superblock.first_uncommitted);
+        Ok(())
+    }
+    
+    /// ログ領域の終端で先頭に折り返す
+    fn wrap(&self, block: u32) -> u32 {
+        if block >= self.last {
+            block - (self.last - self.superblock.first)
+        } else {
+            block
+        }
+    }
+    
+    /// ディスクリプタのタグ1つ分のサイズ
+    fn tag_bytes(&self) -> usize {
+        if self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            return 16;
+        }
+        let mut size = 12;
+        if self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V2) {
+            size += 2;
+        }
+        if self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT) {
+            size
+        } else {
+            size - 4
+        }
+    }
+    
+    /// ディスクリプタブロックのタグを解析
+    fn parse_tags(&self, data: &[u8]) -> Vec<JournalBlockTag> {
+        let tag_bytes = self.tag_bytes();
+        let is_64bit = self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT);
+        let is_v3 = self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3);
+        let end = if self.superblock.has_csum_v2or3() { data.len() - BLOCK_TAIL_SIZE } else { data.len() };
+        
+        let mut tags = Vec::new();
+        let mut offset = HEADER_SIZE;
+        while offset + tag_bytes <= end {
+            // v3のt_flagsは32ビットだが、使われるのは下位16ビットのみ
+            let flags = read_be16(data, offset + 6);
+            let mut block = read_be32(data, offset) as u64;
+            if is_64bit {
+                block |= (read_be32(data, offset + 8) as u64) << 32;
+            }
+            let checksum = if is_v3 { read_be32(data, offset + 12) } else { read_be16(data, offset + 4) as u32 };
+            tags.push(JournalBlockTag { block, flags, checksum });
+            
+            offset += tag_bytes;
+            if (flags & tag_flags::SAME_UUID) == 0 {
+                offset += UUID_SIZE;
+            }
+            if (flags & tag_flags::LAST_TAG) != 0 {
+                break;
+            }
+        }
         
-        // エラーがあればログ出力
-        if superblock.errno != 0 {
-            log::warn!("ジャーナルにエラーあり: errno={}", superblock.errno);
+        tags
+    }
+    
+    /// リボークブロックのレコードを登録
+    fn scan_revoke_records(&self, data: &[u8], sequence: u32, revoked: &mut BTreeMap<u64, u32>) -> FsResult<()> {
+        let record_size = if self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT) { 8 } else { 4 };
+        let max = if self.superblock.has_csum_v2or3() { data.len() - BLOCK_TAIL_SIZE } else { data.len() };
+        let count = read_be32(data, HEADER_SIZE) as usize;
+        if count < REVOKE_HEADER_SIZE || count > max {
+            log::error!("ext4: リボークブロックの長さ{}が不正です", count);
+            return Err(FsError::JournalError);
         }
         
-        // スーパーブロックを保存
-        self.superblock = Some(superblock);
+        let mut offset = REVOKE_HEADER_SIZE;
+        while offset + record_size <= count {
+            let block = if record_size == 8 {
+                (read_be32(data, offset) as u64) << 32 | read_be32(data, offset + 4) as u64
+            } else {
+                read_be32(data, offset) as u64
+            };
+            offset += record_size;
+            
+            let entry = revoked.entry(block).or_insert(sequence);
+            if tid_gt(sequence, *entry) {
+                *entry = sequence;
+            }
+        }
         
         Ok(())
     }
     
-    /// トランザクションを開始
-    pub fn begin_transaction(&self) -> FsResult<u64> {
-        // ファイルシステムのジャーナルマネージャを使用
-        fs_journal::begin_transaction()
+    /// ディスクリプタ/リボークブロック末尾のチェックサムを検証
+    fn verify_block_tail(&self, data: &[u8]) -> bool {
+        let tail = data.len() - BLOCK_TAIL_SIZE;
+        let provided = read_be32(data, tail);
+        let calculated = crc32c(crc32c(self.csum_seed, &data[..tail]), &[0; BLOCK_TAIL_SIZE]);
+        provided == calculated
     }
     
-    /// メタデータブロックをジャーナルに記録
-    pub fn log_metadata(&self, transaction_id: u64, block_nr: u64, data: &[u8]) -> FsResult<()> {
-        fs_journal::log_metadata(transaction_id, self.device_id, block_nr, data)
+    /// コミットブロックのチェックサムを検証
+    fn verify_commit_block(&self, data: &[u8]) -> bool {
+        // h_chksum[0]はヘッダ、種別、サイズ、パディングの後
+        const CHKSUM_OFFSET: usize = HEADER_SIZE + 4;
+        let provided = read_be32(data, CHKSUM_OFFSET);
+        let mut calculated = crc32c(self.csum_seed, &data[..CHKSUM_OFFSET]);
+        calculated = crc32c(calculated, &[0; 4]);
+        calculated = crc32c(calculated, &data[CHKSUM_OFFSET + 4..]);
+        provided == calculated
     }
     
-    /// データブロックをジャーナルに記録
-    pub fn log_data(&self, transaction_id: u64, block_nr: u64, data: &[u8]) -> FsResult<()> {
-        fs_journal::log_data(transaction_id, self.device_id, block_nr, data)
+    /// データブロックのチェックサムをタグと照合
+    fn verify_data_block(&self, data: &[u8], sequence: u32, tag: &JournalBlockTag) -> bool {
+        let seed = crc32c(self.csum_seed, &sequence.to_be_bytes());
+        let calculated = crc32c(seed, data);
+        if self.superblock.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            tag.checksum == calculated
+        } else {
+            tag.checksum == (calculated & 0xFFFF)
+        }
     }
     
-    /// トランザクションをコミット
-    pub fn commit_transaction(&self, transaction_id: u64) -> FsResult<()> {
-        fs_journal::commit_transaction(transaction_id)
+    /// ジャーナルスーパーブロックの開始位置とシーケンスを書き戻す
+    fn write_superblock(&self, io: &dyn JournalIo) -> FsResult<()> {
+        let mut data = vec![0u8; io.block_size()];
+        io.read_journal_block(0, &mut data)?;
+        
+        data[0x18..0x1C].copy_from_slice(&self.superblock.sequence.to_be_bytes());
+        data[0x1C..0x20].copy_from_slice(&self.superblock.start.to_be_bytes());
+        if self.superblock.has_csum_v2or3() {
+            data[0xFC..0x100].copy_from_slice(&[0; 4]);
+            let checksum = crc32c(!0, &data[..JOURNAL_SUPERBLOCK_SIZE]);
+            data[0xFC..0x100].copy_from_slice(&checksum.to_be_bytes());
+        }
+        
+        io.write_journal_block(0, &data)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
     
-    /// トランザクションをアボート
-    pub fn abort_transaction(&self, transaction_id: u64) -> FsResult<()> {
-        fs_journal::abort_transaction(transaction_id)
+    const BLOCK_SIZE: usize = 1024;
+    const JOURNAL_BLOCKS: u32 = 32;
+    const UUID: [u8; 16] = [7; 16];
+    
+    /// メモリ上のジャーナルとファイルシステム
+    struct MemoryJournal {
+        journal: RefCell<Vec<Vec<u8>>>,
+        fs: RefCell<BTreeMap<u64, Vec<u8>>>,
     }
     
-    /// ジャーナルをリカバリ
-    pub fn recover(&self) -> FsResult<()> {
-        log::info!("Ext4ジャーナルリカバリを開始します");
-        
-        // ジャーナルデバイスをオープン
-        let device = DeviceHandle::open(&self.journal_path)?;
-        
-        // ジャーナルスーパーブロックを読み込み
-        let sb_data = device.read_at(0, 1024)?;
-        
-        if sb_data.len() < 1024 {
-            log::error!("ジャーナルスーパーブロックの読み込みに失敗");
-            return Err(FsError::IoError);
+    impl JournalIo for MemoryJournal {
+        fn block_size(&self) -> usize {
+            BLOCK_SIZE
         }
         
-        // スーパーブロックを解析（例: マジックナンバーを確認）
-        let magic = u32::from_le_bytes([sb_data[0], sb_data[1], sb_data[2], sb_data[3]]);
-        if magic != EXT4_JOURNAL_MAGIC {
-            log::error!("無効なジャーナルマジックナンバー: 0x{:X}", magic);
-            return Err(FsError::InvalidFormat);
+        fn read_journal_block(&self, block: u32, buffer: &mut [u8]) -> FsResult<()> {
+            buffer.copy_from_slice(&self.journal.borrow()[block as usize]);
+            Ok(())
         }
         
-        // ジャーナルのシーケンス番号
-        let seq = u32::from_le_bytes([sb_data[12], sb_data[13], sb_data[14], sb_data[15]]);
-        
-        // リカバリの開始と終了ブロック
-        let start = u32::from_le_bytes([sb_data[16], sb_data[17], sb_data[18], sb_data[19]]);
-        let end = u32::from_le_bytes([sb_data[20], sb_data[21], sb_data[22], sb_data[23]]);
-        
-        if start == 0 || end == 0 {
-            log::info!("リカバリ不要: コミット済みトランザクションなし");
-            return Ok(());
+        fn write_journal_block(&self, block: u32, buffer: &[u8]) -> FsResult<()> {
+            self.journal.borrow_mut()[block as usize] = buffer.to_vec();
+            Ok(())
         }
         
-        log::info!("ジャーナルリカバリ: シーケンス={}, 開始ブロック={}, 終了ブロック={}", 
-                 seq, start, end);
-        
-        // ジャーナルからブロックを読み込み
-        let mut block_num = start;
-        let mut recovered_blocks = 0;
+        fn write_fs_block(&self, block: u64, buffer: &[u8]) -> FsResult<()> {
+            self.fs.borrow_mut().insert(block, buffer.to_vec());
+            Ok(())
+        }
+    }
+    
+    fn header(block_type: JournalBlockType, sequence: u32) -> Vec<u8> {
+        let mut block = JournalHeader { magic: JBD2_MAGIC, block_type, sequence }.serialize();
+        block.resize(BLOCK_SIZE, 0);
+        block
+    }
+    
+    fn seal_tail(block: &mut [u8], seed: u32) {
+        let tail = block.len() - BLOCK_TAIL_SIZE;
+        block[tail..].copy_from_slice(&[0; 4]);
+        let checksum = crc32c(seed, block);
+        block[tail..].copy_from_slice(&checksum.to_be_bytes());
+    }
+    
+    /// チェックサムv3・64ビットのジャーナルを組み立てる
+    struct Builder {
+        blocks: Vec<Vec<u8>>,
+        seed: u32,
+    }
+    
+    impl Builder {
+        fn new(sequence: u32) -> Self {
+            let mut sb = header(JournalBlockType::SuperblockV2, 0);
+            sb[0x0C..0x10].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+            sb[0x10..0x14].copy_from_slice(&JOURNAL_BLOCKS.to_be_bytes());
+            sb[0x14..0x18].copy_from_slice(&1u32.to_be_bytes());
+            sb[0x18..0x1C].copy_from_slice(&sequence.to_be_bytes());
+            sb[0x1C..0x20].copy_from_slice(&1u32.to_be_bytes());
+            let incompat = JBD2_FEATURE_INCOMPAT_REVOKE | JBD2_FEATURE_INCOMPAT_64BIT | JBD2_FEATURE_INCOMPAT_CSUM_V3;
+            sb[0x28..0x2C].copy_from_slice(&incompat.to_be_bytes());
+            sb[0x30..0x40].copy_from_slice(&UUID);
+            sb[0x50] = JBD2_CRC32C_CHKSUM;
+            let checksum = crc32c(!0, &sb);
+            sb[0xFC..0x100].copy_from_slice(&checksum.to_be_bytes());
+            Self { blocks: vec![sb], seed: crc32c(!0, &UUID) }
+        }
         
-        while block_num <= end {
-            let block_data = device.read_block(block_num as u64)?;
-            
-            // ブロックヘッダを解析
-            let block_type = u32::from_le_bytes([
-                block_data[0], block_data[1], block_data[2], block_data[3]
-            ]);
-            
-            // データブロックの場合
-            if block_type == EXT4_JOURNAL_BLOCK_DATA {
-                // ブロック情報（デバイスIDとブロック番号）を取得
-                let target_device_id = u32::from_le_bytes([
-                    block_data[4], block_data[5], block_data[6], block_data[7]
-                ]) as u64;
-                
-                let target_block_num = u32::from_le_bytes([
-                    block_data[8], block_data[9], block_data[10], block_data[11]
-                ]) as u64;
-                
-                // ジャーナルのブロックを実際のデバイスに適用
-                if let Err(e) = write_block_to_device(target_device_id, target_block_num, &block_data[12..]) {
-                    log::error!("リカバリ中にブロック書き込みに失敗: デバイス={}, ブロック={}, エラー={:?}", 
-                              target_device_id, target_block_num, e);
-                    // エラーがあっても続行（ベストエフォート）
-                } else {
-                    recovered_blocks += 1;
+        fn descriptor(&mut self, sequence: u32, writes: &[(u64, Vec<u8>)]) {
+            let mut desc = header(JournalBlockType::Descriptor, sequence);
+            let mut offset = HEADER_SIZE;
+            for (i, (block, data)) in writes.iter().enumerate() {
+                let mut flags = if i == 0 { 0 } else { tag_flags::SAME_UUID };
+                if i + 1 == writes.len() {
+                    flags |= tag_flags::LAST_TAG;
                 }
+                // テストでは先頭4バイトが0のブロックをエスケープ済みとして扱う
+                if data[0..4] == [0; 4] {
+                    flags |= tag_flags::ESCAPE;
+                }
+                let checksum = crc32c(crc32c(self.seed, &sequence.to_be_bytes()), data);
+                desc[offset..offset + 4].copy_from_slice(&(*block as u32).to_be_bytes());
+                desc[offset + 4..offset + 8].copy_from_slice(&(flags as u32).to_be_bytes());
+                desc[offset + 8..offset + 12].copy_from_slice(&((*block >> 32) as u32).to_be_bytes());
+                desc[offset + 12..offset + 16].copy_from_slice(&checksum.to_be_bytes());
+                offset += 16 + if i == 0 { UUID_SIZE } else { 0 };
             }
-            
-            block_num += 1;
+            seal_tail(&mut desc, self.seed);
+            self.blocks.push(desc);
+            self.blocks.extend(writes.iter().map(|(_, data)| data.clone()));
         }
         
-        log::info!("Ext4ジャーナルリカバリ完了: {} ブロック復元", recovered_blocks);
+        fn revoke(&mut self, sequence: u32, blocks: &[u64]) {
+            let mut revoke = header(JournalBlockType::Revoke, sequence);
+            let count = REVOKE_HEADER_SIZE + blocks.len() * 8;
+            revoke[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&(count as u32).to_be_bytes());
+            for (i, block) in blocks.iter().enumerate() {
+                let offset = REVOKE_HEADER_SIZE + i * 8;
+                revoke[offset..offset + 8].copy_from_slice(&block.to_be_bytes());
+            }
+            seal_tail(&mut revoke, self.seed);
+            self.blocks.push(revoke);
+        }
         
-        // ジャーナルをクリア（リカバリ完了）
-        clear_journal()?;
+        fn commit(&mut self, sequence: u32) {
+            let mut commit = header(JournalBlockType::Commit, sequence);
+            let checksum = crc32c(self.seed, &commit);
+            commit[16..20].copy_from_slice(&checksum.to_be_bytes());
+            self.blocks.push(commit);
+        }
         
-        Ok(())
+        fn build(mut self) -> MemoryJournal {
+            self.blocks.resize(JOURNAL_BLOCKS as usize, vec![0; BLOCK_SIZE]);
+            MemoryJournal { journal: RefCell::new(self.blocks), fs: RefCell::new(BTreeMap::new()) }
+        }
+    }
+    
+    #[test]
+    fn test_replay_honours_revoke_and_stops_at_uncommitted() {
+        let fill = |byte: u8| vec![byte; BLOCK_SIZE];
+        let mut escaped = fill(0xEE);
+        escaped[0..4].copy_from_slice(&[0; 4]);
+        
+        let mut builder = Builder::new(10);
+        // トランザクション10: ブロック100と200、および64ビット番号のブロック
+        builder.descriptor(10, &[(100, fill(0xA1)), (200, fill(0xA2)), (0x1_0000_0005, fill(0xA3))]);
+        builder.commit(10);
+        // トランザクション11: ブロック200をリボーク、ブロック300を書き込み
+        builder.revoke(11, &[200]);
+        builder.descriptor(11, &[(300, escaped)]);
+        builder.commit(11);
+        // トランザクション12: コミットされていない
+        builder.descriptor(12, &[(100, fill(0xC1))]);
+        
+        let io = builder.build();
+        let mut journal = Journal::load(&io).unwrap();
+        assert!(journal.needs_recovery());
+        
+        let info = journal.recover(&io).unwrap();
+        assert_eq!(info.end_transaction, 12);
+        assert_eq!(info.replayed_blocks, 3);
+        assert_eq!(info.revoked_blocks, 1);
+        
+        let fs = io.fs.borrow();
+        assert_eq!(fs[&100], fill(0xA1));
+        assert!(!fs.contains_key(&200));
+        assert_eq!(fs[&0x1_0000_0005], fill(0xA3));
+        assert_eq!(&fs[&300][0..4], &JBD2_MAGIC.to_be_bytes());
+        
+        // ログは空になり、次のマウントでは再生されない
+        let reloaded = Journal::load(&io).unwrap();
+        assert!(!reloaded.needs_recovery());
+        assert_eq!(reloaded.superblock().sequence, 13);
     }
 }
-
-/// Ext4ジャーナルフラグ
-#[repr(u32)]
-pub enum JournalFlag {
-    /// エスケープ
-    Escape = 0x1,
-    /// 同じブロックの2つのタグ
-    SameUUID = 0x2,
-    /// 最後のタグ
-    LastTag = 0x4,
-    /// コミットブロック
-    Commit = 0x8,
-} 
\ No newline at end of file
@@ -2,23 +2,37 @@
 //
 // Linux互換ext4ファイルシステムを実装
 
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 use crate::core::sync::{Mutex, RwLock};
 use crate::core::fs::{FileSystem, FileSystemType, FileAttributes, OpenFlags, FileDescriptor};
 use crate::core::memory::{PageSize, VirtualAddress, PhysicalAddress};
 use core::sync::atomic::{AtomicU64, Ordering};
+use super::{FsError, FsResult};
 
 mod superblock;
 mod inode;
-mod extents;
+mod extent;
+mod crc16;
+mod csum;
 mod journal;
 mod bitmap;
 mod dir;
+mod group;
+mod hash;
+mod htree;
+mod namei;
+mod handle;
+mod xattr;
+mod fsck;
 
 use superblock::Superblock;
 use inode::Inode;
-use journal::Journal;
+use journal::{Journal, JournalIo};
+use group::BlockGroupDescriptor;
+
+pub use handle::{Ext4DirHandle, Ext4FileHandle};
 
 /// ext4ファイルシステムの実装
 pub struct Ext4FileSystem {
@@ -38,6 +52,10 @@ pub struct Ext4FileSystem {
     mounted: AtomicU64,
     /// iノードキャッシュ
     inode_cache: RwLock<Vec<(u32, Inode)>>,
+    /// 割り当てと名前空間操作を直列化するロック
+    meta_lock: Mutex<()>,
+    /// iノードごとの開いているファイルハンドルの数
+    open_files: Mutex<BTreeMap<u32, usize>>,
 }
 
 /// ext4マウントオプション
@@ -98,6 +116,70 @@ pub enum Ext4Error {
     InvalidArgument,
     /// 内部エラー
     InternalError,
+    /// エントリが見つからない
+    NotFound,
+    /// 同名のエントリが既に存在する
+    AlreadyExists,
+    /// ディレクトリが空ではない
+    NotEmpty,
+    /// ディレクトリではない
+    NotDirectory,
+    /// ディレクトリである
+    IsDirectory,
+    /// 名前が長すぎる
+    NameTooLong,
+    /// リンク数が上限に達した
+    TooManyLinks,
+    /// メタデータのチェックサムが一致しない
+    ChecksumMismatch,
+}
+
+impl From<Ext4Error> for FsError {
+    fn from(error: Ext4Error) -> Self {
+        match error {
+            Ext4Error::InvalidSuperblock => FsError::BadSuperblock,
+            Ext4Error::UnsupportedFeature => FsError::UnsupportedFeature,
+            Ext4Error::InvalidInode => FsError::MetadataError,
+            Ext4Error::InvalidBlock => FsError::CorruptedFs,
+            Ext4Error::JournalError => FsError::JournalError,
+            Ext4Error::DeviceError => FsError::DeviceError,
+            Ext4Error::IoError => FsError::IoError,
+            Ext4Error::NoSpace => FsError::OutOfSpace,
+            Ext4Error::ReadOnly => FsError::ReadOnly,
+            Ext4Error::InvalidArgument => FsError::InvalidData,
+            Ext4Error::InternalError => FsError::Other("ext4内部エラー"),
+            Ext4Error::NotFound => FsError::NotFound,
+            Ext4Error::AlreadyExists => FsError::AlreadyExists,
+            Ext4Error::NotEmpty => FsError::NotEmpty,
+            Ext4Error::NotDirectory => FsError::NotDirectory,
+            Ext4Error::IsDirectory => FsError::IsDirectory,
+            Ext4Error::NameTooLong => FsError::Other("名前が長すぎます"),
+            Ext4Error::TooManyLinks => FsError::Other("リンク数が上限に達しました"),
+            Ext4Error::ChecksumMismatch => FsError::ChecksumError,
+        }
+    }
+}
+
+impl From<FsError> for Ext4Error {
+    fn from(error: FsError) -> Self {
+        match error {
+            FsError::NotFound => Ext4Error::NotFound,
+            FsError::AlreadyExists => Ext4Error::AlreadyExists,
+            FsError::NotEmpty => Ext4Error::NotEmpty,
+            FsError::NotDirectory => Ext4Error::NotDirectory,
+            FsError::IsDirectory => Ext4Error::IsDirectory,
+            FsError::ReadOnly => Ext4Error::ReadOnly,
+            FsError::OutOfSpace => Ext4Error::NoSpace,
+            FsError::IoError => Ext4Error::IoError,
+            FsError::DeviceError => Ext4Error::DeviceError,
+            FsError::JournalError => Ext4Error::JournalError,
+            FsError::ChecksumError => Ext4Error::ChecksumMismatch,
+            FsError::BadSuperblock | FsError::BadMagic => Ext4Error::InvalidSuperblock,
+            FsError::UnsupportedFeature | FsError::NotSupported => Ext4Error::UnsupportedFeature,
+            FsError::CorruptedFs | FsError::FilesystemCorrupted | FsError::InvalidData => Ext4Error::InvalidBlock,
+            _ => Ext4Error::InternalError,
+        }
+    }
 }
 
 impl Ext4FileSystem {
@@ -112,6 +194,8 @@ impl Ext4FileSystem {
             inode_size: 0,
             mounted: AtomicU64::new(0),
             inode_cache: RwLock::new(Vec::new()),
+            meta_lock: Mutex::new(()),
+            open_files: Mutex::new(BTreeMap::new()),
         }
     }
     
@@ -145,14 +229,18 @@ impl Ext4FileSystem {
         self.block_size = block_size;
         self.mount_flags = mount_flags;
         
-        // フラグがread-onlyでなければファイルシステム整合性チェック
+        // ジャーナル初期化
+        self.init_journal()?;
+        
+        // フラグがread-onlyでなければファイルシステム整合性チェック（必要ならジャーナルを再生）
         if (mount_flags & super::MOUNT_READ_ONLY) == 0 {
             self.check_consistency()?;
+            // 開いたまま削除されたiノードをクラッシュ後に解放する
+            self.recover_orphans()?;
+        } else if self.superblock.read().unwrap().needs_recovery() {
+            log::warn!("ext4: 読み取り専用マウントのため、未再生のジャーナルを残したままマウントします");
         }
         
-        // ジャーナル初期化
-        self.init_journal()?;
-        
         // マウント状態を設定
         self.mounted.store(1, Ordering::SeqCst);
         
@@ -190,6 +278,16 @@ impl Ext4FileSystem {
                     return Err(Ext4Error::InvalidSuperblock);
                 }
                 
+                // metadata_csum有効時はスーパーブロック自体のチェックサムを検証
+                // （CRC32C以外の種類はcheck_supported_featuresで拒否する）
+                let ro_compat = u32::from_le_bytes([sb_data[100], sb_data[101], sb_data[102], sb_data[103]]);
+                if (ro_compat & superblock::FEATURE_RO_COMPAT_METADATA_CSUM) != 0
+                    && sb_data[373] == superblock::CHECKSUM_TYPE_CRC32C
+                    && !csum::verify_superblock(&sb_data[..SUPERBLOCK_SIZE]) {
+                    log::error!("ext4: スーパーブロックのチェックサムが一致しません");
+                    return Err(Ext4Error::ChecksumMismatch);
+                }
+                
                 // 4. スーパーブロック構造体の構築
                 let mut sb = Superblock::new();
                 
@@ -303,6 +401,12 @@ impl Ext4FileSystem {
             return false;
         }
         
+        // metadata_csumはCRC32Cのみ対応
+        if sb.has_metadata_csum() && sb.checksum_type != superblock::CHECKSUM_TYPE_CRC32C {
+            log::error!("ext4: 未対応のチェックサム種類です: {}", sb.checksum_type);
+            return false;
+        }
+        
         // 読み取り専用マウントならread-only互換機能のみチェック
         if (self.mount_flags & super::MOUNT_READ_ONLY) != 0 {
             return !sb.has_unsupported_ro_features();
@@ -317,9 +421,10 @@ impl Ext4FileSystem {
         let sb = self.superblock.read().unwrap();
         
         // クリーンにアンマウントされていないかチェック
-        if !sb.is_clean() {
+        if sb.needs_recovery() {
             // ジャーナルによる回復が必要
             if sb.has_journal() {
+                drop(sb);
                 // ジャーナルによる回復を実行（リカバリー）
                 return self.recover_journal();
             } else {
@@ -337,15 +442,20 @@ impl Ext4FileSystem {
         
         if sb.has_journal() {
             let journal_inode = sb.get_journal_inode();
-            let mut journal = self.journal.write().unwrap();
-            
-            // ジャーナルを初期化
-            *journal = Some(Journal::new(journal_inode));
+            drop(sb);
             
-            // ジャーナルをロード
-            if let Some(ref mut j) = *journal {
-                j.load()?;
+            // 外部ジャーナルデバイスは未対応
+            if journal_inode == 0 {
+                log::error!("ext4: 外部ジャーナルデバイスは未対応です");
+                return Err(Ext4Error::UnsupportedFeature);
             }
+            
+            // ジャーナルiノードからスーパーブロックを読み込んで検証
+            let io = JournalInode {
+                fs: self,
+                inode: self.get_inode(journal_inode)?,
+            };
+            *self.journal.write().unwrap() = Some(Journal::load(&io)?);
         }
         
         Ok(())
@@ -353,11 +463,28 @@ impl Ext4FileSystem {
     
     /// ジャーナルによるリカバリー
     fn recover_journal(&self) -> Result<(), Ext4Error> {
+        let journal_inode = self.superblock.read().unwrap().get_journal_inode();
+        
         // ジャーナルがあれば回復を試みる
         if let Some(ref mut journal) = *self.journal.write().unwrap() {
-            journal.recover()?;
+            let io = JournalInode {
+                fs: self,
+                inode: self.get_inode(journal_inode)?,
+            };
+            journal.recover(&io)?;
+        } else {
+            return Err(Ext4Error::JournalError);
         }
         
+        // 再生したブロックにはiノードやスーパーブロック自体が含まれうるので読み直す
+        self.inode_cache.write().unwrap().clear();
+        self.read_superblock()?;
+        
+        // 再生完了を記録
+        let mut sb = self.superblock.write().unwrap();
+        sb.feature_incompat &= !superblock::FEATURE_INCOMPAT_RECOVER;
+        self.write_superblock(&sb)?;
+        
         Ok(())
     }
     
@@ -387,8 +514,24 @@ impl Ext4FileSystem {
     }
     
     /// スーパーブロックを書き込む
+    ///
+    /// 呼び出し側がスーパーブロックのロックを保持していてもよいよう、ここではロックを取らない。
     fn write_superblock(&self, sb: &Superblock) -> Result<(), Ext4Error> {
-        // デバイスに書き込む実装（スタブ）
+        const SUPERBLOCK_SECTOR: u64 = 1024 / 512;
+        
+        // 未解析のフィールドを保持するため、ディスク上の内容に変更分だけを反映する
+        let mut buffer = vec![0u8; 1024];
+        crate::drivers::block::read_sectors(&self.device_path, SUPERBLOCK_SECTOR, &mut buffer)
+            .map_err(|_| Ext4Error::IoError)?;
+        
+        sb.write_counters(&mut buffer);
+        if sb.has_metadata_csum() {
+            csum::set_superblock_checksum(&mut buffer);
+        }
+        
+        crate::drivers::block::write_sectors(&self.device_path, SUPERBLOCK_SECTOR, &buffer)
+            .map_err(|_| Ext4Error::IoError)?;
+        
         Ok(())
     }
     
@@ -435,11 +578,13 @@ impl Ext4FileSystem {
             // 現在のiノードがディレクトリであることを確認
             let inode = self.get_inode(current_inode)?;
             if !inode.is_directory() {
-                return Err(Ext4Error::InvalidArgument);
+                return Err(Ext4Error::NotDirectory);
             }
             
-            // ディレクトリエントリから次のiノードを検索
-            current_inode = dir::lookup_directory(&inode, component)?;
+            // ディレクトリエントリから次のiノードを検索（インデックス化されていればハッシュ木をたどる）
+            current_inode = self.find_entry(&inode, component)?
+                .ok_or(Ext4Error::NotFound)?
+                .inode;
         }
         
         Ok(current_inode)
@@ -457,8 +602,7 @@ impl Ext4FileSystem {
         drop(cache);
         
         // キャッシュにない場合はディスクから読み込む
-        let sb = self.superblock.read().unwrap();
-        let inode = inode::read_inode(&sb, inode_num)?;
+        let inode = self.read_inode(inode_num)?;
         
         // キャッシュに追加
         let mut cache = self.inode_cache.write().unwrap();
@@ -510,9 +654,9 @@ impl Ext4FileSystem {
         // 各ブロックを読み込む完全実装
         for block_idx in start_block..=end_block {
             // 物理ブロック番号を取得
-            let phys_block = inode.get_physical_block(block_idx)?;
+            let phys_block = self.map_block(inode, block_idx)?;
             
-            // ブロックが割り当てられていない場合はゼロで埋める
+            // ブロックが割り当てられていない（または未初期化の）場合はゼロで埋める
             if phys_block == 0 {
                 let sparse_size = core::cmp::min(
                     self.block_size - ((offset as usize + buffer_offset) % self.block_size),
@@ -627,14 +771,15 @@ impl Ext4FileSystem {
         
         let mut bytes_written = 0;
         let mut buffer_offset = 0;
+        // 新しく割り当てた（または未初期化から変換した）ブロックの終端。古い内容は読まない
+        let mut fresh_until = start_block;
         
         // 各ブロックを書き込む完全実装
         for block_idx in start_block..=end_block {
-            // 物理ブロック番号を取得、必要なら割り当て
-            let mut phys_block = inode.get_physical_block(block_idx)?;
-            if phys_block == 0 {
-                phys_block = self.allocate_block()?;
-                inode.set_physical_block(block_idx, phys_block)?;
+            // 物理ブロック番号を取得、必要なら残りの範囲をまとめて割り当て
+            let (phys_block, fresh) = self.map_block_for_write(inode, block_idx, end_block - block_idx + 1)?;
+            if fresh > 0 {
+                fresh_until = block_idx + fresh;
             }
             
             // ブロック内でのオフセットとサイズを計算
@@ -652,7 +797,7 @@ impl Ext4FileSystem {
             
             // 部分書き込みの場合、既存ブロックデータを読み込む
             let mut block_buffer = vec![0u8; self.block_size];
-            if block_start_offset > 0 || write_size < self.block_size {
+            if (block_start_offset > 0 || write_size < self.block_size) && block_idx >= fresh_until {
                 self.read_block(phys_block, &mut block_buffer)?;
             }
             
@@ -699,61 +844,8 @@ impl Ext4FileSystem {
     
     /// ブロックを割り当てる
     fn allocate_block(&self) -> Result<u32, Ext4Error> {
-        // ブロックグループビットマップから空きブロックを検索して割り当ての完全実装
-        
-        let sb = self.superblock.read().unwrap();
-        let blocks_per_group = sb.get_blocks_per_group();
-        let block_groups = (sb.get_total_blocks() + blocks_per_group - 1) / blocks_per_group;
-        
-        // 各ブロックグループを順次検索
-        for group_idx in 0..block_groups {
-            // ブロックグループディスクリプタを読み込み
-            let group_desc = self.read_block_group_descriptor(group_idx)?;
-            
-            // 空きブロックがあるかチェック
-            if group_desc.get_free_blocks_count() == 0 {
-                continue;
-            }
-            
-            // ブロックビットマップを読み込み
-            let bitmap_block = group_desc.get_block_bitmap_block();
-            let mut bitmap = vec![0u8; self.block_size];
-            self.read_block(bitmap_block, &mut bitmap)?;
-            
-            // 空きブロックを検索
-            for byte_idx in 0..(self.block_size) {
-                if bitmap[byte_idx] == 0xFF {
-                    continue; // すべてのビットが設定済み
-                }
-                
-                // バイト内で最初の空きビットを検索
-                for bit_idx in 0..8 {
-                    if (bitmap[byte_idx] & (1 << bit_idx)) == 0 {
-                        // 空きブロック発見
-                        let block_in_group = byte_idx * 8 + bit_idx;
-                        let global_block = group_idx * blocks_per_group + block_in_group as u32;
-                        
-                        // ビットマップを更新
-                        bitmap[byte_idx] |= 1 << bit_idx;
-                        self.write_block(bitmap_block, &bitmap)?;
-                        
-                        // ブロックグループディスクリプタ更新
-                        let mut updated_desc = group_desc;
-                        updated_desc.set_free_blocks_count(updated_desc.get_free_blocks_count() - 1);
-                        self.write_block_group_descriptor(group_idx, &updated_desc)?;
-                        
-                        // スーパーブロックの空きブロック数更新
-                        let mut sb_mut = self.superblock.write().unwrap();
-                        sb_mut.set_free_blocks_count(sb_mut.get_free_blocks_count() - 1);
-                        self.write_superblock(&sb_mut)?;
-                        
-                        return Ok(global_block);
-                    }
-                }
-            }
-        }
-        
-        Err(Ext4Error::NoSpace)
+        // ブロックグループビットマップの先頭から空きブロックを検索
+        self.allocate_blocks(0, 1).map(|(block, _)| block)
     }
     
     /// ブロックグループディスクリプタを読み込み
@@ -772,7 +864,12 @@ impl Ext4FileSystem {
         
         // ディスクリプタを解析
         let desc_data = &block_buffer[desc_offset as usize..(desc_offset + sb.get_desc_size()) as usize];
-        Ok(BlockGroupDescriptor::from_bytes(desc_data))
+        let descriptor = BlockGroupDescriptor::from_bytes(desc_data);
+        if !csum::verify_group_desc(&sb, group_idx, desc_data) {
+            log::error!("ext4: グループ{}のディスクリプタのチェックサムが一致しません", group_idx);
+            return Err(Ext4Error::ChecksumMismatch);
+        }
+        Ok(descriptor)
     }
     
     /// ブロックグループディスクリプタを書き込み
@@ -790,6 +887,10 @@ impl Ext4FileSystem {
         self.read_block(desc_block, &mut block_buffer)?;
         
         // ディスクリプタデータを更新
+        let mut descriptor = descriptor.clone();
+        if let Some(checksum) = csum::descriptor_checksum(&sb, group_idx, &descriptor.to_bytes()) {
+            descriptor.set_checksum(checksum);
+        }
         let desc_bytes = descriptor.to_bytes();
         block_buffer[desc_offset as usize..(desc_offset + sb.get_desc_size()) as usize]
             .copy_from_slice(&desc_bytes);
@@ -800,6 +901,45 @@ impl Ext4FileSystem {
         Ok(())
     }
     
+    /// iノードをディスクから読み込む
+    fn read_inode(&self, inode_num: u32) -> Result<Inode, Ext4Error> {
+        if inode_num == 0 {
+            return Err(Ext4Error::InvalidInode);
+        }
+        
+        // iノードが属するブロックグループとテーブル内の位置を計算
+        let inodes_per_group = self.superblock.read().unwrap().get_inodes_per_group();
+        let group_idx = (inode_num - 1) / inodes_per_group;
+        let inode_idx_in_group = (inode_num - 1) % inodes_per_group;
+        let group_desc = self.read_block_group_descriptor(group_idx)?;
+        
+        let inodes_per_block = (self.block_size / self.inode_size) as u32;
+        let target_block = group_desc.get_inode_table_block() as u32 + inode_idx_in_group / inodes_per_block;
+        let offset = (inode_idx_in_group % inodes_per_block) as usize * self.inode_size;
+        
+        let mut block_buffer = vec![0u8; self.block_size];
+        self.read_block(target_block, &mut block_buffer)?;
+        
+        let raw = &block_buffer[offset..offset + self.inode_size];
+        if let Some(seed) = self.csum_seed() {
+            if !csum::verify_inode(seed, inode_num, raw) {
+                log::error!("ext4: iノード{}のチェックサムが一致しません", inode_num);
+                return Err(Ext4Error::ChecksumMismatch);
+            }
+        }
+        
+        let mut inode = Inode::parse(raw, self.inode_size)
+            .map_err(|_| Ext4Error::InvalidInode)?;
+        inode.number = inode_num;
+        
+        Ok(inode)
+    }
+    
+    /// iノードキャッシュからエントリを取り除く
+    fn forget_inode(&self, inode_num: u32) {
+        self.inode_cache.write().unwrap().retain(|(num, _)| *num != inode_num);
+    }
+    
     /// iノードを更新
     fn update_inode(&self, inode: &Inode) -> Result<(), Ext4Error> {
         // iノードをディスクに書き込む完全実装
@@ -816,7 +956,7 @@ impl Ext4FileSystem {
         let group_desc = self.read_block_group_descriptor(group_idx)?;
         
         // iノードテーブルの位置を計算
-        let inode_table_block = group_desc.get_inode_table_block();
+        let inode_table_block = group_desc.get_inode_table_block() as u32;
         let inodes_per_block = self.block_size / self.inode_size;
         let block_offset = inode_idx_in_group / inodes_per_block as u32;
         let inode_offset_in_block = (inode_idx_in_group % inodes_per_block as u32) * self.inode_size as u32;
@@ -827,7 +967,10 @@ impl Ext4FileSystem {
         self.read_block(target_block, &mut block_buffer)?;
         
         // iノードデータをシリアライズ
-        let inode_bytes = inode.to_bytes(self.inode_size);
+        let mut inode_bytes = inode.to_bytes(self.inode_size);
+        if let Some(seed) = sb.metadata_csum_seed() {
+            csum::set_inode_checksum(seed, inode_num, &mut inode_bytes);
+        }
         
         // ブロック内の適切な位置にiノードデータをコピー
         let start_offset = inode_offset_in_block as usize;
@@ -883,6 +1026,51 @@ impl Ext4FileSystem {
     }
 }
 
+/// ジャーナルiノードを介したジャーナルへのアクセス
+struct JournalInode<'a> {
+    /// ファイルシステム
+    fs: &'a Ext4FileSystem,
+    /// ジャーナルiノード
+    inode: Inode,
+}
+
+impl JournalInode<'_> {
+    /// ジャーナル内の論理ブロックを物理ブロックに変換
+    fn physical_block(&self, block: u32) -> FsResult<u32> {
+        match self.fs.map_block(&self.inode, block)? {
+            0 => {
+                log::error!("ext4: ジャーナルの論理ブロック{}が割り当てられていません", block);
+                Err(FsError::JournalError)
+            },
+            physical => Ok(physical),
+        }
+    }
+}
+
+impl JournalIo for JournalInode<'_> {
+    fn block_size(&self) -> usize {
+        self.fs.block_size
+    }
+    
+    fn read_journal_block(&self, block: u32, buffer: &mut [u8]) -> FsResult<()> {
+        let physical = self.physical_block(block)?;
+        Ok(self.fs.read_block(physical, buffer)?)
+    }
+    
+    fn write_journal_block(&self, block: u32, buffer: &[u8]) -> FsResult<()> {
+        let physical = self.physical_block(block)?;
+        Ok(self.fs.write_block(physical, buffer)?)
+    }
+    
+    fn write_fs_block(&self, block: u64, buffer: &[u8]) -> FsResult<()> {
+        let block = u32::try_from(block).map_err(|_| {
+            log::error!("ext4: ジャーナル内のブロック番号{}は範囲外です", block);
+            FsError::JournalError
+        })?;
+        Ok(self.fs.write_block(block, buffer)?)
+    }
+}
+
 /// ext4ファイルシステム型の登録
 pub fn register() -> Result<(), &'static str> {
     super::register_filesystem(FileSystemType {
@@ -0,0 +1,988 @@
+// Ext4 名前空間操作
+//
+// iノードの作成・解放と、ディレクトリエントリの追加・削除・名前変更
+
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+use super::{Ext4FileSystem, Ext4Error};
+use super::inode::{Inode, InodeFlags};
+use super::dir::{self, DirectoryEntry, DirectoryEntryType};
+use super::extent::Extent;
+use super::bitmap::Bitmap;
+use super::group::flags as group_flags;
+use super::superblock::{FEATURE_INCOMPAT_EXTENTS, FEATURE_INCOMPAT_FILETYPE, FEATURE_RO_COMPAT_DIR_NLINK};
+
+/// ルートディレクトリのiノード番号
+pub const ROOT_INODE: u32 = 2;
+
+/// ファイル名の最大長（バイト）
+pub const MAX_NAME_LEN: usize = 255;
+
+/// リンク数の上限（dir_nlink機能なしのディレクトリ）
+pub(super) const MAX_LINK_COUNT: u16 = 65000;
+
+/// 祖先ディレクトリをたどる深さの上限（破損したループ対策）
+const MAX_ANCESTOR_DEPTH: usize = 4096;
+
+/// i_blockに直接格納できるシンボリックリンクの最大長
+const FAST_SYMLINK_MAX: usize = 59;
+
+/// ディレクトリ内で見つかったエントリとそのブロック
+pub(super) struct EntryLocation {
+    /// エントリを含むブロックの物理ブロック番号
+    pub physical: u32,
+    /// ブロックの内容
+    pub block: Vec<u8>,
+    /// 見つかったエントリ
+    pub entry: DirectoryEntry,
+}
+
+/// ファイル種別ビット
+pub const S_IFMT: u16 = 0xF000;
+/// 通常ファイル
+pub const S_IFREG: u16 = 0x8000;
+/// ディレクトリ
+pub const S_IFDIR: u16 = 0x4000;
+/// シンボリックリンク
+pub const S_IFLNK: u16 = 0xA000;
+
+impl Ext4FileSystem {
+    /// 親ディレクトリに通常ファイルを作成し、新しいiノード番号を返す
+    pub fn create(&self, parent: u32, name: &str, mode: u16, uid: u32, gid: u32) -> Result<u32, Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
+        
+        let mut dir = self.lookup_parent_for_insert(parent, name)?;
+        let inode_num = self.allocate_inode(parent, false)?;
+        
+        let mut inode = self.new_inode(inode_num, S_IFREG | (mode & !S_IFMT), uid, gid);
+        inode.links_count = 1;
+        
+        if let Err(e) = self.update_inode(&inode)
+            .and_then(|_| self.add_dir_entry(&mut dir, name, inode_num, DirectoryEntryType::RegularFile)) {
+            self.free_inode(inode_num, false)?;
+            return Err(e);
+        }
+        
+        log::debug!("ext4: ファイル '{}' を作成しました (iノード{})", name, inode_num);
+        Ok(inode_num)
+    }
+    
+    /// 親ディレクトリにサブディレクトリを作成し、新しいiノード番号を返す
+    pub fn mkdir(&self, parent: u32, name: &str, mode: u16, uid: u32, gid: u32) -> Result<u32, Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.mkdir_locked(parent, name, mode, uid, gid)
+    }
+    
+    /// `meta_lock`を保持した状態でサブディレクトリを作成
+    pub(super) fn mkdir_locked(&self, parent: u32, name: &str, mode: u16, uid: u32, gid: u32) -> Result<u32, Ext4Error> {
+        self.check_writable()?;
+        
+        let mut dir = self.lookup_parent_for_insert(parent, name)?;
+        self.inc_dir_links(&mut dir)?;
+        
+        let inode_num = self.allocate_inode(parent, true)?;
+        let block = match self.allocate_block() {
+            Ok(block) => block,
+            Err(e) => {
+                self.free_inode(inode_num, true)?;
+                return Err(e);
+            }
+        };
+        
+        let mut inode = self.new_inode(inode_num, S_IFDIR | (mode & !S_IFMT), uid, gid);
+        // "."と親ディレクトリからのエントリ
+        inode.links_count = 2;
+        
+        let result = (|| {
+            self.insert_extent(&mut inode, Extent::new(0, 1, block as u64))?;
+            inode.set_size(self.block_size as u64);
+            inode.adjust_blocks((self.block_size / 512) as i64);
+            
+            let data = dir::init_directory_block(
+                self.block_size, inode_num, parent, self.has_file_type(), self.has_dir_tails());
+            self.write_dir_block(&inode, block, &data)?;
+            self.update_inode(&inode)?;
+            self.add_dir_entry(&mut dir, name, inode_num, DirectoryEntryType::Directory)
+        })();
+        
+        if let Err(e) = result {
+            self.free_block(block)?;
+            self.free_inode(inode_num, true)?;
+            return Err(e);
+        }
+        
+        log::debug!("ext4: ディレクトリ '{}' を作成しました (iノード{})", name, inode_num);
+        Ok(inode_num)
+    }
+    
+    /// 親ディレクトリにシンボリックリンクを作成し、新しいiノード番号を返す
+    pub fn symlink(&self, parent: u32, name: &str, target: &str, uid: u32, gid: u32) -> Result<u32, Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
+        
+        if target.is_empty() || target.len() >= self.block_size {
+            return Err(Ext4Error::NameTooLong);
+        }
+        
+        let mut dir = self.lookup_parent_for_insert(parent, name)?;
+        let inode_num = self.allocate_inode(parent, false)?;
+        
+        let mut inode = self.new_inode(inode_num, S_IFLNK | 0o777, uid, gid);
+        inode.links_count = 1;
+        
+        let result = (|| {
+            if target.len() <= FAST_SYMLINK_MAX {
+                // 短いターゲットはi_blockに直接格納する（エクステントは使わない）
+                let mut raw = [0u8; 60];
+                raw[..target.len()].copy_from_slice(target.as_bytes());
+                inode.flags &= !(InodeFlags::Extents as u32);
+                for (i, word) in inode.block.iter_mut().enumerate() {
+                    *word = u32::from_le_bytes([raw[i * 4], raw[i * 4 + 1], raw[i * 4 + 2], raw[i * 4 + 3]]);
+                }
+            } else {
+                self.write_file_data(&mut inode, target.as_bytes(), 0)?;
+            }
+            inode.set_size(target.len() as u64);
+            self.update_inode(&inode)?;
+            self.add_dir_entry(&mut dir, name, inode_num, DirectoryEntryType::SymbolicLink)
+        })();
+        
+        if let Err(e) = result {
+            for (start, count) in self.collect_data_blocks(&inode).unwrap_or_default() {
+                self.free_blocks(start, count)?;
+            }
+            self.free_inode(inode_num, false)?;
+            return Err(e);
+        }
+        
+        Ok(inode_num)
+    }
+    
+    /// ディレクトリ以外のエントリを削除
+    ///
+    /// リンク数が0になったiノードはデータブロックごと解放する。開いているハンドルがあれば
+    /// 孤立リストに載せ、最後のハンドルが閉じられたとき（またはクラッシュ後のマウント時）に解放する。
+    pub fn unlink(&self, parent: u32, name: &str) -> Result<(), Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
+        
+        let mut dir = self.get_directory(parent)?;
+        let entry = self.find_entry(&dir, name)?.ok_or(Ext4Error::NotFound)?;
+        let mut inode = self.get_inode(entry.inode)?;
+        
+        if inode.is_directory() {
+            return Err(Ext4Error::IsDirectory);
+        }
+        
+        // 途中で失敗してディスクを中途半端な状態にしないよう、解放対象を先に確定する
+        let open = self.is_open(entry.inode);
+        let blocks = if inode.links_count <= 1 && !open {
+            self.collect_data_blocks(&inode)?
+        } else {
+            Vec::new()
+        };
+        
+        self.remove_dir_entry(&mut dir, name)?;
+        
+        inode.links_count = inode.links_count.saturating_sub(1);
+        if inode.links_count == 0 && open {
+            inode.set_ctime(self.get_current_time());
+            self.add_orphan(&mut inode)?;
+        } else if inode.links_count == 0 {
+            self.release_inode(&mut inode, &blocks, false)?;
+        } else {
+            inode.set_ctime(self.get_current_time());
+            self.update_inode(&inode)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 空のディレクトリを削除
+    pub fn rmdir(&self, parent: u32, name: &str) -> Result<(), Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
+        
+        if name == "." || name == ".." {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        
+        let mut dir = self.get_directory(parent)?;
+        let entry = self.find_entry(&dir, name)?.ok_or(Ext4Error::NotFound)?;
+        let mut inode = self.get_inode(entry.inode)?;
+        
+        if !inode.is_directory() {
+            return Err(Ext4Error::NotDirectory);
+        }
+        if !self.is_dir_empty(&inode)? {
+            return Err(Ext4Error::NotEmpty);
+        }
+        
+        let blocks = self.collect_data_blocks(&inode)?;
+        
+        // 子ディレクトリの".."が親を参照しなくなる
+        self.dec_dir_links(&mut dir);
+        self.remove_dir_entry(&mut dir, name)?;
+        self.release_inode(&mut inode, &blocks, true)?;
+        
+        Ok(())
+    }
+    
+    /// エントリの名前を変更（ディレクトリ間の移動を含む）
+    ///
+    /// 移動先に同名のエントリがあれば置き換える。ディレクトリを別の親へ移動した場合は
+    /// その".."エントリと両親のリンク数も更新する。
+    pub fn rename(&self, old_parent: u32, old_name: &str, new_parent: u32, new_name: &str) -> Result<(), Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
+        
+        if old_name == "." || old_name == ".." {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        Self::validate_name(new_name)?;
+        
+        let same_parent = old_parent == new_parent;
+        let mut new_dir = self.get_directory(new_parent)?;
+        let mut old_dir = if same_parent { None } else { Some(self.get_directory(old_parent)?) };
+        
+        let source = {
+            let dir = old_dir.as_ref().unwrap_or(&new_dir);
+            self.find_entry(dir, old_name)?.ok_or(Ext4Error::NotFound)?
+        };
+        if same_parent && old_name == new_name {
+            return Ok(());
+        }
+        
+        let mut inode = self.get_inode(source.inode)?;
+        let is_dir = inode.is_directory();
+        
+        // ディレクトリを自分自身の配下へ移動することはできない
+        if is_dir && !same_parent && self.is_ancestor(source.inode, new_parent)? {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        
+        let file_type = DirectoryEntryType::from_mode(inode.mode);
+        let mut replaced = None;
+        
+        match self.find_entry(&new_dir, new_name)? {
+            Some(target) if target.inode == source.inode => {
+                // 同じiノードへのハードリンク同士の場合は何もしない（POSIX）
+                return Ok(());
+            },
+            Some(target) => {
+                let target_inode = self.get_inode(target.inode)?;
+                match (is_dir, target_inode.is_directory()) {
+                    (true, false) => return Err(Ext4Error::NotDirectory),
+                    (false, true) => return Err(Ext4Error::IsDirectory),
+                    (true, true) if !self.is_dir_empty(&target_inode)? => return Err(Ext4Error::NotEmpty),
+                    _ => {}
+                }
+                let open = !target_inode.is_directory() && self.is_open(target.inode);
+                let blocks = if (target_inode.links_count <= 1 && !open) || target_inode.is_directory() {
+                    self.collect_data_blocks(&target_inode)?
+                } else {
+                    Vec::new()
+                };
+                replaced = Some((target_inode, blocks, open));
+            },
+            None => {}
+        }
+        
+        // リンク数を先に調整し、エントリ操作時のiノード書き込みで反映させる
+        if let Some((ref target_inode, _, _)) = replaced {
+            if target_inode.is_directory() {
+                self.dec_dir_links(&mut new_dir);
+            }
+        }
+        if is_dir && !same_parent {
+            self.inc_dir_links(&mut new_dir)?;
+        }
+        
+        // 新しい名前を先に書き込み、クラッシュ時にエントリが失われないようにする
+        if replaced.is_some() {
+            self.replace_dir_entry(&mut new_dir, new_name, source.inode, file_type)?;
+        } else {
+            self.add_dir_entry(&mut new_dir, new_name, source.inode, file_type)?;
+        }
+        
+        match old_dir.as_mut() {
+            Some(dir) => {
+                if is_dir {
+                    self.dec_dir_links(dir);
+                }
+                self.remove_dir_entry(dir, old_name)?;
+            },
+            None => {
+                self.remove_dir_entry(&mut new_dir, old_name)?;
+            }
+        }
+        
+        if is_dir && !same_parent {
+            self.replace_dir_entry(&mut inode, "..", new_parent, DirectoryEntryType::Directory)?;
+        }
+        inode.set_ctime(self.get_current_time());
+        self.update_inode(&inode)?;
+        
+        if let Some((mut target_inode, blocks, open)) = replaced {
+            let target_is_dir = target_inode.is_directory();
+            target_inode.links_count = if target_is_dir { 0 } else { target_inode.links_count.saturating_sub(1) };
+            if target_inode.links_count == 0 && open {
+                target_inode.set_ctime(self.get_current_time());
+                self.add_orphan(&mut target_inode)?;
+            } else if target_inode.links_count == 0 {
+                self.release_inode(&mut target_inode, &blocks, target_is_dir)?;
+            } else {
+                target_inode.set_ctime(self.get_current_time());
+                self.update_inode(&target_inode)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// ディレクトリ内の名前を検索
+    pub fn find_entry(&self, dir: &Inode, name: &str) -> Result<Option<DirectoryEntry>, Ext4Error> {
+        Ok(self.locate_entry(dir, name)?.map(|location| location.entry))
+    }
+    
+    /// 名前を含むブロックを検索
+    ///
+    /// インデックス化されたディレクトリはハッシュ木をたどる。"."と".."は
+    /// 先頭ブロックにあるので常に線形に探す。
+    fn locate_entry(&self, dir: &Inode, name: &str) -> Result<Option<EntryLocation>, Ext4Error> {
+        if self.is_indexed(dir) && name != "." && name != ".." {
+            self.dx_locate_entry(dir, name)
+        } else {
+            self.linear_locate_entry(dir, name)
+        }
+    }
+    
+    /// 全ブロックを先頭から走査して名前を検索
+    pub(super) fn linear_locate_entry(&self, dir: &Inode, name: &str) -> Result<Option<EntryLocation>, Ext4Error> {
+        let mut block = vec![0u8; self.block_size];
+        
+        for logical in 0..self.dir_block_count(dir) {
+            let physical = self.map_block(dir, logical)?;
+            if physical == 0 {
+                continue;
+            }
+            self.read_dir_block(dir, physical, &mut block)?;
+            if let Some(entry) = dir::find_entry_in_block(&block, name)? {
+                return Ok(Some(EntryLocation { physical, block, entry }));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// ディレクトリの全エントリを読み込み
+    pub fn read_dir_entries(&self, dir: &Inode) -> Result<Vec<DirectoryEntry>, Ext4Error> {
+        let mut entries = Vec::new();
+        let mut block = vec![0u8; self.block_size];
+        
+        for logical in 0..self.dir_block_count(dir) {
+            let physical = self.map_block(dir, logical)?;
+            if physical == 0 {
+                continue;
+            }
+            self.read_dir_block(dir, physical, &mut block)?;
+            entries.extend(dir::parse_directory_block(&block)?);
+        }
+        
+        Ok(entries)
+    }
+    
+    /// 書き込み可能なマウントかどうかを確認
+    pub(super) fn check_writable(&self) -> Result<(), Ext4Error> {
+        if self.mounted.load(Ordering::SeqCst) == 0 {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        if (self.mount_flags & super::super::MOUNT_READ_ONLY) != 0 {
+            return Err(Ext4Error::ReadOnly);
+        }
+        Ok(())
+    }
+    
+    /// エントリ名を検証
+    fn validate_name(name: &str) -> Result<(), Ext4Error> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+            return Err(Ext4Error::InvalidArgument);
+        }
+        if name.len() > MAX_NAME_LEN {
+            return Err(Ext4Error::NameTooLong);
+        }
+        Ok(())
+    }
+    
+    /// ディレクトリiノードを取得
+    fn get_directory(&self, inode_num: u32) -> Result<Inode, Ext4Error> {
+        let inode = self.get_inode(inode_num)?;
+        if !inode.is_directory() {
+            return Err(Ext4Error::NotDirectory);
+        }
+        Ok(inode)
+    }
+    
+    /// 新しいエントリを追加する親ディレクトリを取得し、名前の重複を確認
+    fn lookup_parent_for_insert(&self, parent: u32, name: &str) -> Result<Inode, Ext4Error> {
+        Self::validate_name(name)?;
+        let dir = self.get_directory(parent)?;
+        if dir.links_count == 0 {
+            // 削除済みディレクトリには作成できない
+            return Err(Ext4Error::NotFound);
+        }
+        if self.find_entry(&dir, name)?.is_some() {
+            return Err(Ext4Error::AlreadyExists);
+        }
+        Ok(dir)
+    }
+    
+    /// 新規iノードの共通初期化
+    fn new_inode(&self, inode_num: u32, mode: u16, uid: u32, gid: u32) -> Inode {
+        let use_extents = self.superblock.read().unwrap().has_feature_incompat(FEATURE_INCOMPAT_EXTENTS);
+        Inode::new(inode_num, mode, uid, gid, self.get_current_time(), self.inode_size, use_extents)
+    }
+    
+    /// ディレクトリエントリにファイルタイプを記録するかどうか
+    fn has_file_type(&self) -> bool {
+        self.superblock.read().unwrap().has_feature_incompat(FEATURE_INCOMPAT_FILETYPE)
+    }
+    
+    /// 新しいディレクトリブロックの末尾にチェックサム用エントリを置くかどうか
+    pub(super) fn has_dir_tails(&self) -> bool {
+        self.superblock.read().unwrap().has_metadata_csum()
+    }
+    
+    /// ディレクトリのデータブロック数
+    pub(super) fn dir_block_count(&self, dir: &Inode) -> u32 {
+        ((dir.get_size() + self.block_size as u64 - 1) / self.block_size as u64) as u32
+    }
+    
+    /// ディレクトリにエントリを追加
+    ///
+    /// インデックス化されたディレクトリはハッシュ木に挿入する。線形ディレクトリで
+    /// 既存ブロックに空きがなければ、1ブロックのものはインデックス化し、それ以外は
+    /// 新しいブロックをディレクトリ末尾に追加する。
+    pub(super) fn add_dir_entry(&self, dir: &mut Inode, name: &str, inode_num: u32, file_type: DirectoryEntryType) -> Result<(), Ext4Error> {
+        let file_type = if self.has_file_type() { file_type } else { DirectoryEntryType::Unknown };
+        let entry = DirectoryEntry::new(inode_num, name, file_type);
+        
+        let now = self.get_current_time();
+        dir.set_mtime(now);
+        dir.set_ctime(now);
+        
+        let mut dx_fallback = false;
+        if (dir.flags & InodeFlags::DirIndex as u32) != 0 {
+            if self.is_indexed(dir) && self.dx_add_entry(dir, &entry)? {
+                return self.update_inode(dir);
+            }
+            // 使えないインデックスは無効化し、線形ディレクトリとして扱う
+            // （インデックスブロックは空のエントリとして読めるので整合性は保たれる）
+            log::warn!("ext4: ディレクトリ{}のHTreeインデックスを無効化します", dir.get_number());
+            dir.flags &= !(InodeFlags::DirIndex as u32);
+            dx_fallback = true;
+        }
+        
+        let block_count = self.dir_block_count(dir);
+        let mut block = vec![0u8; self.block_size];
+        
+        for logical in 0..block_count {
+            let physical = self.map_block(dir, logical)?;
+            if physical == 0 {
+                continue;
+            }
+            self.read_dir_block(dir, physical, &mut block)?;
+            if dir::insert_entry(&mut block, &entry)? {
+                self.write_dir_block(dir, physical, &block)?;
+                return self.update_inode(dir);
+            }
+        }
+        
+        // 空きがないので、1ブロックだけのディレクトリはインデックス化する
+        if block_count == 1 && !dx_fallback && self.can_index() && self.make_indexed_dir(dir, &entry)? {
+            return self.update_inode(dir);
+        }
+        
+        let data = dir::new_directory_block(self.block_size, &entry, self.has_dir_tails());
+        self.append_dir_block(dir, &data)?;
+        self.update_inode(dir)
+    }
+    
+    /// ディレクトリ末尾にブロックを追加し、(論理, 物理)ブロック番号を返す
+    ///
+    /// サイズとブロック数を更新するが、iノードの書き戻しは呼び出し側で行う。
+    pub(super) fn append_dir_block(&self, dir: &mut Inode, data: &[u8]) -> Result<(u32, u32), Ext4Error> {
+        let logical = self.dir_block_count(dir);
+        let physical = self.allocate_block()?;
+        if let Err(e) = self.insert_extent(dir, Extent::new(logical, 1, physical as u64)) {
+            self.free_block(physical)?;
+            return Err(e);
+        }
+        
+        self.write_dir_block(dir, physical, data)?;
+        dir.set_size((logical as u64 + 1) * self.block_size as u64);
+        dir.adjust_blocks((self.block_size / 512) as i64);
+        Ok((logical, physical))
+    }
+    
+    /// ディレクトリからエントリを削除
+    pub(super) fn remove_dir_entry(&self, dir: &mut Inode, name: &str) -> Result<DirectoryEntry, Ext4Error> {
+        let mut location = self.locate_entry(dir, name)?.ok_or(Ext4Error::NotFound)?;
+        let entry = dir::remove_entry(&mut location.block, name)?.ok_or(Ext4Error::NotFound)?;
+        self.write_dir_block(dir, location.physical, &location.block)?;
+        
+        let now = self.get_current_time();
+        dir.set_mtime(now);
+        dir.set_ctime(now);
+        self.update_inode(dir)?;
+        Ok(entry)
+    }
+    
+    /// 既存エントリの参照先を差し替え
+    pub(super) fn replace_dir_entry(&self, dir: &mut Inode, name: &str, inode_num: u32, file_type: DirectoryEntryType) -> Result<(), Ext4Error> {
+        let mut location = self.locate_entry(dir, name)?.ok_or(Ext4Error::NotFound)?;
+        if !dir::replace_entry_inode(&mut location.block, name, inode_num, file_type)? {
+            return Err(Ext4Error::NotFound);
+        }
+        self.write_dir_block(dir, location.physical, &location.block)?;
+        
+        let now = self.get_current_time();
+        dir.set_mtime(now);
+        dir.set_ctime(now);
+        self.update_inode(dir)
+    }
+    
+    /// ディレクトリが"."と".."以外のエントリを持たないかどうか
+    fn is_dir_empty(&self, dir: &Inode) -> Result<bool, Ext4Error> {
+        Ok(self.read_dir_entries(dir)?
+            .iter()
+            .all(|entry| entry.name == "." || entry.name == ".."))
+    }
+    
+    /// `ancestor`が`dir`自身またはその祖先かどうか
+    fn is_ancestor(&self, ancestor: u32, dir: u32) -> Result<bool, Ext4Error> {
+        let mut current = dir;
+        
+        for _ in 0..MAX_ANCESTOR_DEPTH {
+            if current == ancestor {
+                return Ok(true);
+            }
+            if current == ROOT_INODE {
+                return Ok(false);
+            }
+            let inode = self.get_directory(current)?;
+            let parent = self.find_entry(&inode, "..")?.ok_or(Ext4Error::InternalError)?;
+            if parent.inode == current {
+                return Ok(false);
+            }
+            current = parent.inode;
+        }
+        
+        log::error!("ext4: ディレクトリ{}から祖先をたどれません（ループの可能性）", dir);
+        Err(Ext4Error::InternalError)
+    }
+    
+    /// ディレクトリのリンク数を増やす（上限超過時はdir_nlinkに従う）
+    pub(super) fn inc_dir_links(&self, dir: &mut Inode) -> Result<(), Ext4Error> {
+        if dir.links_count == 1 {
+            // すでに上限を超えて「不明」扱いになっている
+            return Ok(());
+        }
+        if dir.links_count + 1 >= MAX_LINK_COUNT {
+            if self.superblock.read().unwrap().has_feature_ro_compat(FEATURE_RO_COMPAT_DIR_NLINK) {
+                dir.links_count = 1;
+                return Ok(());
+            }
+            return Err(Ext4Error::TooManyLinks);
+        }
+        dir.links_count += 1;
+        Ok(())
+    }
+    
+    /// ディレクトリのリンク数を減らす
+    fn dec_dir_links(&self, dir: &mut Inode) {
+        // 1は上限超過状態、2は"."と親からのエントリのみ
+        if dir.links_count > 2 {
+            dir.links_count -= 1;
+        }
+    }
+    
+    /// iノードが保持するブロック（エクステントツリーのブロックを含む）を(先頭, 数)で列挙
+    pub(super) fn collect_data_blocks(&self, inode: &Inode) -> Result<Vec<(u32, u32)>, Ext4Error> {
+        if inode.blocks == 0 || inode.is_fast_symlink() {
+            // 空ファイルや高速シンボリックリンク（EAブロックだけを持つ場合もi_blockはデータではない）
+            return Ok(Vec::new());
+        }
+        
+        self.inode_block_ranges(inode)
+    }
+    
+    /// リンク数が0になったiノードとそのブロックを解放
+    pub(super) fn release_inode(&self, inode: &mut Inode, blocks: &[(u32, u32)], is_dir: bool) -> Result<(), Ext4Error> {
+        for &(start, count) in blocks {
+            self.free_blocks(start, count)?;
+        }
+        if let Err(e) = self.release_xattr_block(inode) {
+            // エントリはすでに消えているので、ブロックを漏らしてでもiノードの解放を続ける
+            log::warn!("ext4: iノード{}のEAブロックを解放できません: {:?}", inode.get_number(), e);
+            inode.set_file_acl(0);
+        }
+        
+        inode.links_count = 0;
+        inode.dtime = self.get_current_time();
+        inode.set_size(0);
+        inode.adjust_blocks(-(inode.blocks as i64));
+        if inode.has_extents() {
+            inode.clear_extent_root();
+        } else {
+            inode.block = [0; 15];
+        }
+        
+        self.update_inode(inode)?;
+        self.free_inode(inode.get_number(), is_dir)?;
+        self.forget_inode(inode.get_number());
+        
+        Ok(())
+    }
+    
+    /// ファイルハンドルが開いたiノードを登録
+    ///
+    /// 検索後に削除されて解放済みのiノードは開けない。
+    pub(super) fn open_inode(&self, inode_num: u32) -> Result<(), Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        if self.get_inode(inode_num)?.links_count == 0 {
+            return Err(Ext4Error::NotFound);
+        }
+        *self.open_files.lock().entry(inode_num).or_insert(0) += 1;
+        Ok(())
+    }
+    
+    /// ファイルハンドルが閉じたiノードの登録を外し、最後のハンドルなら削除済みのiノードを解放
+    pub(super) fn close_inode(&self, inode_num: u32) -> Result<(), Ext4Error> {
+        let _guard = self.meta_lock.lock();
+        {
+            let mut open_files = self.open_files.lock();
+            match open_files.get_mut(&inode_num) {
+                Some(count) if *count > 1 => {
+                    *count -= 1;
+                    return Ok(());
+                },
+                Some(_) => {
+                    open_files.remove(&inode_num);
+                },
+                None => return Ok(()),
+            }
+        }
+        
+        let mut inode = self.get_inode(inode_num)?;
+        if inode.links_count != 0 || (self.mount_flags & super::super::MOUNT_READ_ONLY) != 0 {
+            return Ok(());
+        }
+        self.remove_orphan(inode_num, inode.dtime)?;
+        let blocks = self.collect_data_blocks(&inode)?;
+        self.release_inode(&mut inode, &blocks, false)
+    }
+    
+    /// iノードを開いているファイルハンドルがあるか（`meta_lock`を保持して呼ぶ）
+    fn is_open(&self, inode_num: u32) -> bool {
+        self.open_files.lock().contains_key(&inode_num)
+    }
+    
+    /// リンク数が0になったiノードを孤立リストの先頭に追加
+    ///
+    /// 孤立リストではdtimeが次のiノードを指す。
+    fn add_orphan(&self, inode: &mut Inode) -> Result<(), Ext4Error> {
+        // update_inodeがスーパーブロックを読むので、書き込みロックはiノードを書いてから取る
+        inode.dtime = self.superblock.read().unwrap().last_orphan;
+        self.update_inode(inode)?;
+        let mut sb = self.superblock.write().unwrap();
+        sb.last_orphan = inode.get_number();
+        self.write_superblock(&sb)
+    }
+    
+    /// 孤立リストからiノードを外す（`next`はそのiノードの次）
+    fn remove_orphan(&self, inode_num: u32, next: u32) -> Result<(), Ext4Error> {
+        let (head, inode_count) = {
+            let sb = self.superblock.read().unwrap();
+            (sb.last_orphan, sb.inode_count)
+        };
+        if head == inode_num {
+            let mut sb = self.superblock.write().unwrap();
+            sb.last_orphan = next;
+            return self.write_superblock(&sb);
+        }
+        
+        // 壊れたリストで無限ループしないよう、たどる数をiノード数で打ち切る
+        let mut current = head;
+        for _ in 0..inode_count {
+            if current == 0 || current > inode_count {
+                break;
+            }
+            let mut inode = self.get_inode(current)?;
+            if inode.dtime == inode_num {
+                inode.dtime = next;
+                return self.update_inode(&inode);
+            }
+            current = inode.dtime;
+        }
+        
+        log::warn!("ext4: iノード{}が孤立リストに見つかりません", inode_num);
+        Ok(())
+    }
+    
+    /// マウント時に孤立リストを処理する
+    ///
+    /// 削除済みのiノードは解放し、切り詰め途中のものはサイズまで切り詰める。
+    pub(super) fn recover_orphans(&self) -> Result<(), Ext4Error> {
+        let (mut inode_num, inode_count) = {
+            let sb = self.superblock.read().unwrap();
+            (sb.last_orphan, sb.inode_count)
+        };
+        if inode_num == 0 {
+            return Ok(());
+        }
+        
+        let mut remaining = inode_count;
+        while inode_num != 0 {
+            if inode_num > inode_count || remaining == 0 {
+                log::warn!("ext4: 孤立iノードリストが壊れています（iノード{}）", inode_num);
+                break;
+            }
+            remaining -= 1;
+            
+            let mut inode = self.read_inode(inode_num)?;
+            let next = inode.dtime;
+            if inode.links_count == 0 {
+                let blocks = self.collect_data_blocks(&inode)?;
+                let is_dir = inode.is_directory();
+                self.release_inode(&mut inode, &blocks, is_dir)?;
+            } else {
+                let size = inode.get_size();
+                self.truncate_blocks(&mut inode, size)?;
+                inode.dtime = 0;
+                self.update_inode(&inode)?;
+            }
+            inode_num = next;
+        }
+        
+        let mut sb = self.superblock.write().unwrap();
+        sb.last_orphan = 0;
+        self.write_superblock(&sb)
+    }
+    
+    /// iノードを割り当てる
+    ///
+    /// 親ディレクトリと同じグループから探し、満杯なら後続のグループへ進む。
+    fn allocate_inode(&self, parent: u32, is_dir: bool) -> Result<u32, Ext4Error> {
+        let (inodes_per_group, group_count, first_inode) = {
+            let sb = self.superblock.read().unwrap();
+            (sb.get_inodes_per_group(), sb.group_count(), sb.first_inode)
+        };
+        
+        let parent_group = (parent - 1) / inodes_per_group;
+        let mut bitmap = vec![0u8; self.block_size];
+        
+        for i in 0..group_count {
+            let group = (parent_group + i) % group_count;
+            let mut desc = self.read_block_group_descriptor(group)?;
+            if desc.get_free_inodes_count() == 0 {
+                continue;
+            }
+            
+            let bitmap_block = desc.get_inode_bitmap_block() as u32;
+            if desc.has_flag(group_flags::INODE_UNINIT) {
+                // 未初期化グループのビットマップはゼロとみなし、末尾のパディングだけ立てる
+                bitmap.iter_mut().for_each(|byte| *byte = 0);
+                for bit in inodes_per_group as usize..self.block_size * 8 {
+                    Bitmap::set_bit(&mut bitmap, bit);
+                }
+            } else {
+                self.read_block(bitmap_block, &mut bitmap)?;
+                self.verify_inode_bitmap(group, &desc, &bitmap)?;
+            }
+            
+            // グループ0の予約iノード（ジャーナル等）は割り当てない
+            let start = if group == 0 { first_inode as usize - 1 } else { 0 };
+            let bit = match Bitmap::find_first_zero(&bitmap, start, inodes_per_group as usize) {
+                Some(bit) => bit,
+                None => continue,
+            };
+            
+            Bitmap::set_bit(&mut bitmap, bit);
+            self.write_block(bitmap_block, &bitmap)?;
+            
+            self.update_inode_bitmap_checksum(&mut desc, &bitmap);
+            desc.set_free_inodes_count(desc.get_free_inodes_count() - 1);
+            if is_dir {
+                desc.set_used_dirs_count(desc.get_used_dirs_count() + 1);
+            }
+            desc.clear_flag(group_flags::INODE_UNINIT);
+            // iノードテーブル末尾の未使用領域を縮める
+            let used = inodes_per_group - desc.get_itable_unused();
+            if bit as u32 >= used {
+                desc.set_itable_unused(inodes_per_group - bit as u32 - 1);
+            }
+            self.write_block_group_descriptor(group, &desc)?;
+            
+            let mut sb = self.superblock.write().unwrap();
+            let free = sb.get_free_inodes_count();
+            sb.set_free_inodes_count(free.saturating_sub(1));
+            self.write_superblock(&sb)?;
+            
+            return Ok(group * inodes_per_group + bit as u32 + 1);
+        }
+        
+        Err(Ext4Error::NoSpace)
+    }
+    
+    /// iノードを解放
+    fn free_inode(&self, inode_num: u32, is_dir: bool) -> Result<(), Ext4Error> {
+        let inodes_per_group = self.superblock.read().unwrap().get_inodes_per_group();
+        let group = (inode_num - 1) / inodes_per_group;
+        let bit = ((inode_num - 1) % inodes_per_group) as usize;
+        
+        let mut desc = self.read_block_group_descriptor(group)?;
+        let bitmap_block = desc.get_inode_bitmap_block() as u32;
+        let mut bitmap = vec![0u8; self.block_size];
+        self.read_block(bitmap_block, &mut bitmap)?;
+        self.verify_inode_bitmap(group, &desc, &bitmap)?;
+        
+        if !Bitmap::check_bit(&bitmap, bit) {
+            log::warn!("ext4: iノード{}はすでに解放されています", inode_num);
+            return Ok(());
+        }
+        
+        Bitmap::clear_bit(&mut bitmap, bit);
+        self.write_block(bitmap_block, &bitmap)?;
+        
+        self.update_inode_bitmap_checksum(&mut desc, &bitmap);
+        desc.set_free_inodes_count(desc.get_free_inodes_count() + 1);
+        if is_dir {
+            desc.set_used_dirs_count(desc.get_used_dirs_count().saturating_sub(1));
+        }
+        self.write_block_group_descriptor(group, &desc)?;
+        
+        let mut sb = self.superblock.write().unwrap();
+        let free = sb.get_free_inodes_count();
+        sb.set_free_inodes_count(free + 1);
+        self.write_superblock(&sb)?;
+        
+        Ok(())
+    }
+    
+    /// 連続するブロックを割り当て、(先頭, 数)を返す
+    ///
+    /// `goal`を含むグループの`goal`以降から探し、見つかった空きから最大`max`ブロックまで
+    /// 同じグループ内で連続する分を確保する。ブロックビットマップが未初期化のグループは
+    /// メタデータの位置を計算できないため使わない。
+    pub(super) fn allocate_blocks(&self, goal: u32, max: u32) -> Result<(u32, u32), Ext4Error> {
+        let (blocks_per_group, group_count, first_data_block, total_blocks) = {
+            let sb = self.superblock.read().unwrap();
+            (sb.get_blocks_per_group(), sb.group_count(), sb.first_data_block, sb.total_blocks())
+        };
+        
+        let goal = goal.clamp(first_data_block, (total_blocks - 1) as u32);
+        let goal_group = (goal - first_data_block) / blocks_per_group;
+        let mut bitmap = vec![0u8; self.block_size];
+        
+        for i in 0..group_count {
+            let group = (goal_group + i) % group_count;
+            let mut desc = self.read_block_group_descriptor(group)?;
+            if desc.get_free_blocks_count() == 0 || desc.has_flag(group_flags::BLOCK_UNINIT) {
+                continue;
+            }
+            
+            let group_start = first_data_block as u64 + group as u64 * blocks_per_group as u64;
+            let blocks_in_group = (total_blocks - group_start).min(blocks_per_group as u64) as usize;
+            let bitmap_block = desc.get_block_bitmap_block() as u32;
+            self.read_block(bitmap_block, &mut bitmap)?;
+            self.verify_block_bitmap(group, &desc, &bitmap)?;
+            
+            let start_bit = if i == 0 { ((goal - first_data_block) % blocks_per_group) as usize } else { 0 };
+            let bit = match Bitmap::find_first_zero(&bitmap, start_bit, blocks_in_group)
+                .or_else(|| Bitmap::find_first_zero(&bitmap, 0, start_bit)) {
+                Some(bit) => bit,
+                None => continue,
+            };
+            
+            let mut count = 0;
+            while count < max.max(1) as usize && bit + count < blocks_in_group && !Bitmap::check_bit(&bitmap, bit + count) {
+                Bitmap::set_bit(&mut bitmap, bit + count);
+                count += 1;
+            }
+            self.write_block(bitmap_block, &bitmap)?;
+            
+            self.update_block_bitmap_checksum(&mut desc, &bitmap);
+            desc.set_free_blocks_count(desc.get_free_blocks_count().saturating_sub(count as u32));
+            self.write_block_group_descriptor(group, &desc)?;
+            
+            let mut sb = self.superblock.write().unwrap();
+            let free = sb.get_free_blocks_count();
+            sb.set_free_blocks_count(free.saturating_sub(count as u64));
+            self.write_superblock(&sb)?;
+            
+            return Ok(((group_start + bit as u64) as u32, count as u32));
+        }
+        
+        Err(Ext4Error::NoSpace)
+    }
+    
+    /// データブロックを解放
+    pub(super) fn free_block(&self, block: u32) -> Result<(), Ext4Error> {
+        self.free_blocks(block, 1)
+    }
+    
+    /// 連続するブロックを解放
+    pub(super) fn free_blocks(&self, start: u32, count: u32) -> Result<(), Ext4Error> {
+        let (blocks_per_group, first_data_block, total_blocks) = {
+            let sb = self.superblock.read().unwrap();
+            (sb.get_blocks_per_group(), sb.first_data_block, sb.total_blocks())
+        };
+        
+        let end = start as u64 + count as u64;
+        if start < first_data_block || end > total_blocks {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        
+        let mut bitmap = vec![0u8; self.block_size];
+        let mut block = start;
+        while (block as u64) < end {
+            let group = (block - first_data_block) / blocks_per_group;
+            let bit = ((block - first_data_block) % blocks_per_group) as usize;
+            let run = (end - block as u64).min((blocks_per_group as usize - bit) as u64) as usize;
+            
+            let mut desc = self.read_block_group_descriptor(group)?;
+            let bitmap_block = desc.get_block_bitmap_block() as u32;
+            self.read_block(bitmap_block, &mut bitmap)?;
+            self.verify_block_bitmap(group, &desc, &bitmap)?;
+            
+            let mut freed = 0;
+            for offset in 0..run {
+                if Bitmap::check_bit(&bitmap, bit + offset) {
+                    Bitmap::clear_bit(&mut bitmap, bit + offset);
+                    freed += 1;
+                } else {
+                    log::warn!("ext4: ブロック{}はすでに解放されています", block + offset as u32);
+                }
+            }
+            
+            if freed > 0 {
+                self.write_block(bitmap_block, &bitmap)?;
+                
+                self.update_block_bitmap_checksum(&mut desc, &bitmap);
+                desc.set_free_blocks_count(desc.get_free_blocks_count() + freed);
+                self.write_block_group_descriptor(group, &desc)?;
+                
+                let mut sb = self.superblock.write().unwrap();
+                let free = sb.get_free_blocks_count();
+                sb.set_free_blocks_count(free + freed as u64);
+                self.write_superblock(&sb)?;
+            }
+            
+            block += run as u32;
+        }
+        
+        Ok(())
+    }
+}
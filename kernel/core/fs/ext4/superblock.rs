@@ -4,6 +4,42 @@
 
 use alloc::vec::Vec;
 use super::super::{FsError, FsResult};
+use super::super::crc32c::crc32c;
+
+/// 互換機能: ジャーナル
+pub const FEATURE_COMPAT_HAS_JOURNAL: u32 = 0x0004;
+/// 互換機能: オンラインリサイズ用の予約GDTブロックを保持するiノード
+pub const FEATURE_COMPAT_RESIZE_INODE: u32 = 0x0010;
+/// 互換機能: ディレクトリインデックス（HTree）
+pub const FEATURE_COMPAT_DIR_INDEX: u32 = 0x0020;
+/// 互換機能: スーパーブロックのバックアップを2か所だけに置く
+pub const FEATURE_COMPAT_SPARSE_SUPER2: u32 = 0x0200;
+/// 非互換機能: ディレクトリエントリにファイルタイプを記録
+pub const FEATURE_INCOMPAT_FILETYPE: u32 = 0x0002;
+/// 非互換機能: ジャーナルの再生が必要
+pub const FEATURE_INCOMPAT_RECOVER: u32 = 0x0004;
+/// 非互換機能: ディスクリプタをメタブロックグループごとに分散配置
+pub const FEATURE_INCOMPAT_META_BG: u32 = 0x0010;
+/// 非互換機能: エクステント
+pub const FEATURE_INCOMPAT_EXTENTS: u32 = 0x0040;
+/// 非互換機能: 64ビットブロック番号
+pub const FEATURE_INCOMPAT_64BIT: u32 = 0x0080;
+/// 非互換機能: 複数マウント防止（MMPブロック）
+pub const FEATURE_INCOMPAT_MMP: u32 = 0x0100;
+/// 非互換機能: チェックサムのシードをスーパーブロックに保持
+pub const FEATURE_INCOMPAT_CSUM_SEED: u32 = 0x2000;
+/// 非互換機能: 3段のHTreeと2GB超のディレクトリ
+pub const FEATURE_INCOMPAT_LARGEDIR: u32 = 0x4000;
+/// 読み取り専用互換機能: スーパーブロックのバックアップを一部のグループにだけ置く
+pub const FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+/// 読み取り専用互換機能: グループディスクリプタのCRC16チェックサム
+pub const FEATURE_RO_COMPAT_GDT_CSUM: u32 = 0x0010;
+/// 読み取り専用互換機能: 大きなディレクトリ数（リンク数65000超）
+pub const FEATURE_RO_COMPAT_DIR_NLINK: u32 = 0x0020;
+/// 読み取り専用互換機能: メタデータチェックサム
+pub const FEATURE_RO_COMPAT_METADATA_CSUM: u32 = 0x0400;
+/// メタデータチェックサムの種類: CRC32C
+pub const CHECKSUM_TYPE_CRC32C: u8 = 1;
 
 /// Ext4 スーパーブロック
 #[derive(Debug, Clone)]
@@ -104,6 +140,8 @@ pub struct Ext4Superblock {
     pub hash_seed: [u32; 4],
     /// デフォルトハッシュバージョン
     pub def_hash_version: u8,
+    /// グループディスクリプタサイズ（64bit機能有効時のみ有効）
+    pub desc_size: u16,
     /// デフォルトマウントオプション
     pub default_mount_opts: u32,
     /// 最初のメタブロックグループ
@@ -140,6 +178,8 @@ pub struct Ext4Superblock {
     pub log_groups_per_flex: u8,
     /// チェックサムタイプ
     pub checksum_type: u8,
+    /// metadata_csumのシード（csum_seed機能が有効な場合のみ使用）
+    pub checksum_seed: u32,
 }
 
 impl Ext4Superblock {
@@ -222,6 +262,7 @@ impl Ext4Superblock {
         hash_seed[3] = u32::from_le_bytes([data[248], data[249], data[250], data[251]]);
         
         let def_hash_version = data[252];
+        let desc_size = u16::from_le_bytes([data[254], data[255]]);
         let default_mount_opts = u32::from_le_bytes([data[256], data[257], data[258], data[259]]);
         let first_meta_bg = u32::from_le_bytes([data[260], data[261], data[262], data[263]]);
         
@@ -250,6 +291,7 @@ impl Ext4Superblock {
         let raid_stripe_width = u32::from_le_bytes([data[368], data[369], data[370], data[371]]);
         let log_groups_per_flex = data[372];
         let checksum_type = data[373];
+        let checksum_seed = u32::from_le_bytes([data[624], data[625], data[626], data[627]]);
         
         Ok(Self {
             inode_count,
@@ -296,6 +338,7 @@ impl Ext4Superblock {
             last_orphan,
             hash_seed,
             def_hash_version,
+            desc_size,
             default_mount_opts,
             first_meta_bg,
             mkfs_time,
@@ -312,6 +355,7 @@ impl Ext4Superblock {
             raid_stripe_width,
             log_groups_per_flex,
             checksum_type,
+            checksum_seed,
         })
     }
     
@@ -350,11 +394,109 @@ impl Ext4Superblock {
         (self.feature_ro_compat & flag) != 0
     }
     
+    /// ジャーナルを持つかどうか
+    pub fn has_journal(&self) -> bool {
+        self.has_feature_compat(FEATURE_COMPAT_HAS_JOURNAL)
+    }
+    
+    /// ジャーナルiノード番号（外部ジャーナルの場合は0）
+    pub fn get_journal_inode(&self) -> u32 {
+        self.journal_inum
+    }
+    
+    /// ジャーナルの再生が必要かどうか（前回クリーンにアンマウントされなかった）
+    pub fn needs_recovery(&self) -> bool {
+        self.has_feature_incompat(FEATURE_INCOMPAT_RECOVER)
+    }
+    
+    /// メタデータチェックサムが有効かどうか
+    pub fn has_metadata_csum(&self) -> bool {
+        self.has_feature_ro_compat(FEATURE_RO_COMPAT_METADATA_CSUM)
+    }
+    
+    /// グループディスクリプタのCRC16チェックサムが有効かどうか（metadata_csumが優先する）
+    pub fn has_gdt_csum(&self) -> bool {
+        self.has_feature_ro_compat(FEATURE_RO_COMPAT_GDT_CSUM) && !self.has_metadata_csum()
+    }
+    
+    /// metadata_csumの計算に使うシード（無効なら`None`）
+    ///
+    /// csum_seed機能が有効ならスーパーブロックの値を、そうでなければUUIDから計算する。
+    pub fn metadata_csum_seed(&self) -> Option<u32> {
+        if !self.has_metadata_csum() {
+            return None;
+        }
+        if self.has_feature_incompat(FEATURE_INCOMPAT_CSUM_SEED) {
+            Some(self.checksum_seed)
+        } else {
+            Some(crc32c(!0, &self.uuid))
+        }
+    }
+    
     /// ファイルシステムが64ビット対応かどうか
     pub fn is_64bit(&self) -> bool {
         self.has_feature_incompat(0x80) // INCOMPAT_64BIT
     }
     
+    /// グループあたりのブロック数
+    pub fn get_blocks_per_group(&self) -> u32 {
+        self.blocks_per_group
+    }
+    
+    /// グループあたりのiノード数
+    pub fn get_inodes_per_group(&self) -> u32 {
+        self.inodes_per_group
+    }
+    
+    /// ブロックグループ数
+    pub fn group_count(&self) -> u32 {
+        let data_blocks = self.total_blocks() - self.first_data_block as u64;
+        data_blocks.div_ceil(self.blocks_per_group as u64) as u32
+    }
+    
+    /// グループディスクリプタのサイズ（バイト）
+    pub fn get_desc_size(&self) -> usize {
+        if self.is_64bit() && self.desc_size as usize >= 64 {
+            self.desc_size as usize
+        } else {
+            32
+        }
+    }
+    
+    /// 空きブロック数を取得
+    pub fn get_free_blocks_count(&self) -> u64 {
+        self.total_free_blocks()
+    }
+    
+    /// 空きブロック数を設定
+    pub fn set_free_blocks_count(&mut self, count: u64) {
+        self.free_blocks = count as u32;
+        self.free_blocks_hi = (count >> 32) as u32;
+    }
+    
+    /// 空きiノード数を取得
+    pub fn get_free_inodes_count(&self) -> u32 {
+        self.free_inodes
+    }
+    
+    /// 空きiノード数を設定
+    pub fn set_free_inodes_count(&mut self, count: u32) {
+        self.free_inodes = count;
+    }
+    
+    /// 変更されうるカウンタと状態をディスク上のスーパーブロックに書き戻す
+    ///
+    /// `data`はオフセット1024から読み込んだ1024バイトのスーパーブロック領域。
+    pub fn write_counters(&self, data: &mut [u8]) {
+        data[12..16].copy_from_slice(&self.free_blocks.to_le_bytes());
+        data[16..20].copy_from_slice(&self.free_inodes.to_le_bytes());
+        data[48..52].copy_from_slice(&self.write_time.to_le_bytes());
+        data[58..60].copy_from_slice(&self.state.to_le_bytes());
+        data[96..100].copy_from_slice(&self.feature_incompat.to_le_bytes());
+        data[232..236].copy_from_slice(&self.last_orphan.to_le_bytes());
+        data[344..348].copy_from_slice(&self.free_blocks_hi.to_le_bytes());
+    }
+    
     /// ボリューム名を文字列として取得
     pub fn volume_name_str(&self) -> &str {
         // NUL終端文字列に変換
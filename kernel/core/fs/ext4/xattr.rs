@@ -0,0 +1,550 @@
+// Ext4 拡張属性実装
+//
+// iノード内（i_extra_isizeの後ろ）と外部EAブロック（i_file_acl）に格納された
+// 拡張属性の読み書き。名前は名前空間の番号と接頭辞を除いた部分で格納し、POSIX ACLは
+// ext4独自の短縮形式（バージョン1）とVFSのposix_acl_xattr形式を相互に変換する
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::{Ext4FileSystem, Ext4Error};
+use super::super::crc32c::crc32c;
+use super::inode::Inode;
+use super::super::xattr::{AclEntry, AclTag, PosixAcl, POSIX_ACL_ACCESS, POSIX_ACL_DEFAULT};
+
+/// 拡張属性領域のマジック（iノード内領域とEAブロックの先頭）
+const XATTR_MAGIC: u32 = 0xEA02_0000;
+/// EAブロックヘッダのサイズ
+const BLOCK_HEADER_SIZE: usize = 32;
+/// EAブロックヘッダ内の参照数のオフセット
+const BLOCK_REFCOUNT_OFFSET: usize = 4;
+/// EAブロックヘッダ内のブロック数のオフセット
+const BLOCK_BLOCKS_OFFSET: usize = 8;
+/// EAブロックヘッダ内のハッシュのオフセット
+const BLOCK_HASH_OFFSET: usize = 12;
+/// EAブロックヘッダ内のチェックサムのオフセット
+const BLOCK_CHECKSUM_OFFSET: usize = 16;
+/// iノード内領域のヘッダ（マジックのみ）のサイズ
+const IBODY_HEADER_SIZE: usize = 4;
+/// エントリの固定部分のサイズ
+const ENTRY_HEADER_SIZE: usize = 16;
+/// エントリ名の最大長
+const NAME_MAX: usize = 255;
+/// ext4形式のACLのバージョン
+const ACL_VERSION: u32 = 1;
+
+/// ACL用の名前空間番号
+const INDEX_POSIX_ACL_ACCESS: u8 = 2;
+const INDEX_POSIX_ACL_DEFAULT: u8 = 3;
+
+/// 名前空間番号と接頭辞（ACLは名前全体を番号で表す）
+const NAME_INDICES: [(u8, &str); 6] = [
+    (INDEX_POSIX_ACL_ACCESS, POSIX_ACL_ACCESS),
+    (INDEX_POSIX_ACL_DEFAULT, POSIX_ACL_DEFAULT),
+    (1, "user."),
+    (4, "trusted."),
+    (6, "security."),
+    (7, "system."),
+];
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// 4バイト境界に切り上げ
+fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// 名前空間番号がACLかどうか
+fn is_acl_index(index: u8) -> bool {
+    index == INDEX_POSIX_ACL_ACCESS || index == INDEX_POSIX_ACL_DEFAULT
+}
+
+/// 属性名を名前空間番号と格納する名前に分ける
+pub(super) fn split_name(name: &str) -> Result<(u8, &str), Ext4Error> {
+    for &(index, prefix) in NAME_INDICES.iter() {
+        if is_acl_index(index) {
+            if name == prefix {
+                return Ok((index, ""));
+            }
+        } else if let Some(suffix) = name.strip_prefix(prefix) {
+            if suffix.len() > NAME_MAX {
+                return Err(Ext4Error::NameTooLong);
+            }
+            return Ok((index, suffix));
+        }
+    }
+    Err(Ext4Error::UnsupportedFeature)
+}
+
+/// 名前空間番号と格納された名前から属性名を組み立てる（未知の番号は`None`）
+fn full_name(index: u8, name: &[u8]) -> Option<String> {
+    let prefix = NAME_INDICES.iter().find(|(i, _)| *i == index)?.1;
+    let name = core::str::from_utf8(name).ok()?;
+    Some(format!("{}{}", prefix, name))
+}
+
+/// 拡張属性のエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct XattrEntry {
+    /// 名前空間番号
+    pub index: u8,
+    /// 接頭辞を除いた名前
+    pub name: Vec<u8>,
+    /// 値
+    pub value: Vec<u8>,
+}
+
+impl XattrEntry {
+    /// 名前が一致するかどうか
+    fn matches(&self, index: u8, name: &str) -> bool {
+        self.index == index && self.name == name.as_bytes()
+    }
+    
+    /// エントリ部分のサイズ（4バイト境界）
+    fn entry_size(&self) -> usize {
+        align4(ENTRY_HEADER_SIZE + self.name.len())
+    }
+    
+    /// エントリのハッシュ（名前と4バイト単位の値から計算）
+    pub fn hash(&self) -> u32 {
+        let mut hash = 0u32;
+        for &c in &self.name {
+            hash = (hash << 5) ^ (hash >> 27) ^ c as u32;
+        }
+        for chunk in self.value.chunks(4) {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            hash = (hash << 16) ^ (hash >> 16) ^ u32::from_le_bytes(word);
+        }
+        hash
+    }
+}
+
+/// 拡張属性領域のエントリを読み込む
+///
+/// `first_entry`は最初のエントリの位置、値のオフセットは`value_base`からの相対位置。
+/// エントリ列はゼロの4バイトで終わる。
+pub(super) fn parse_entries(region: &[u8], first_entry: usize, value_base: usize) -> Result<Vec<XattrEntry>, Ext4Error> {
+    let mut entries = Vec::new();
+    let mut offset = first_entry;
+    
+    loop {
+        if offset + 4 > region.len() {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        if read_u32(region, offset) == 0 {
+            break;
+        }
+        if offset + ENTRY_HEADER_SIZE > region.len() {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        
+        let name_len = region[offset] as usize;
+        let index = region[offset + 1];
+        let value_offset = read_u16(region, offset + 2) as usize;
+        let value_inode = read_u32(region, offset + 4);
+        let value_size = read_u32(region, offset + 8) as usize;
+        let name_start = offset + ENTRY_HEADER_SIZE;
+        if name_start + name_len > region.len() {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        if value_inode != 0 {
+            // 値を別のiノードに置くea_inode機能には対応しない
+            return Err(Ext4Error::UnsupportedFeature);
+        }
+        
+        let value_start = value_base + value_offset;
+        if value_size > 0 && value_start + value_size > region.len() {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        let value = if value_size == 0 { Vec::new() } else { region[value_start..value_start + value_size].to_vec() };
+        
+        entries.push(XattrEntry {
+            index,
+            name: region[name_start..name_start + name_len].to_vec(),
+            value,
+        });
+        offset += align4(ENTRY_HEADER_SIZE + name_len);
+    }
+    
+    Ok(entries)
+}
+
+/// エントリ列を`len`バイトの領域に書き込む（収まらなければ`None`）
+///
+/// エントリは`first_entry`から前詰めに、値は領域の末尾から後ろ詰めに置く。
+pub(super) fn build_region(entries: &[XattrEntry], len: usize, first_entry: usize, value_base: usize) -> Option<Vec<u8>> {
+    let mut region = vec![0u8; len];
+    let mut offset = first_entry;
+    let mut value_end = len;
+    
+    for entry in entries {
+        let value_size = align4(entry.value.len());
+        let entry_end = offset + entry.entry_size();
+        // 末尾のゼロ4バイトの分を残す
+        if value_end < value_size || entry_end + 4 > value_end - value_size {
+            return None;
+        }
+        
+        let value_offset = if entry.value.is_empty() {
+            0
+        } else {
+            value_end -= value_size;
+            region[value_end..value_end + entry.value.len()].copy_from_slice(&entry.value);
+            value_end - value_base
+        };
+        
+        region[offset] = entry.name.len() as u8;
+        region[offset + 1] = entry.index;
+        region[offset + 2..offset + 4].copy_from_slice(&(value_offset as u16).to_le_bytes());
+        write_u32(&mut region, offset + 8, entry.value.len() as u32);
+        write_u32(&mut region, offset + 12, entry.hash());
+        region[offset + ENTRY_HEADER_SIZE..offset + ENTRY_HEADER_SIZE + entry.name.len()].copy_from_slice(&entry.name);
+        offset = entry_end;
+    }
+    
+    Some(region)
+}
+
+/// EAブロックのハッシュ（エントリのハッシュから計算）
+pub(super) fn block_hash(entries: &[XattrEntry]) -> u32 {
+    let mut hash = 0u32;
+    for entry in entries {
+        let entry_hash = entry.hash();
+        if entry_hash == 0 {
+            // ハッシュがゼロのエントリを含むブロックは共有の対象にしない
+            return 0;
+        }
+        hash = (hash << 16) ^ (hash >> 16) ^ entry_hash;
+    }
+    hash
+}
+
+/// EAブロックのチェックサムを計算
+///
+/// ブロック番号（64ビット）、チェックサム欄をゼロとみなしたブロック全体の順に計算する。
+pub(super) fn block_checksum(seed: u32, block_nr: u64, block: &[u8]) -> u32 {
+    let mut crc = crc32c(seed, &block_nr.to_le_bytes());
+    crc = crc32c(crc, &block[..BLOCK_CHECKSUM_OFFSET]);
+    crc = crc32c(crc, &[0; 4]);
+    crc32c(crc, &block[BLOCK_CHECKSUM_OFFSET + 4..])
+}
+
+/// ext4形式のACLをposix_acl_xattr形式に変換
+///
+/// 名前付きユーザー・グループのエントリだけがIDを持つ8バイト、他は4バイト。
+pub(super) fn acl_from_disk(data: &[u8]) -> Result<Vec<u8>, Ext4Error> {
+    if data.len() < 4 || read_u32(data, 0) != ACL_VERSION {
+        return Err(Ext4Error::InvalidBlock);
+    }
+    
+    let mut entries = Vec::new();
+    let mut offset = 4;
+    while offset < data.len() {
+        if offset + 4 > data.len() {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        let code = read_u16(data, offset);
+        let perm = read_u16(data, offset + 2);
+        let named = code == AclTag::User(0).code() || code == AclTag::Group(0).code();
+        let id = if named {
+            if offset + 8 > data.len() {
+                return Err(Ext4Error::InvalidBlock);
+            }
+            read_u32(data, offset + 4)
+        } else {
+            u32::MAX
+        };
+        offset += if named { 8 } else { 4 };
+        
+        if perm > 7 {
+            return Err(Ext4Error::InvalidBlock);
+        }
+        let tag = AclTag::from_raw(code, id).map_err(|_| Ext4Error::InvalidBlock)?;
+        entries.push(AclEntry { tag, perm: perm as u8 });
+    }
+    
+    let acl = PosixAcl::new(entries).map_err(|_| Ext4Error::InvalidBlock)?;
+    Ok(acl.to_xattr())
+}
+
+/// posix_acl_xattr形式のACLをext4形式に変換
+pub(super) fn acl_to_disk(value: &[u8]) -> Result<Vec<u8>, Ext4Error> {
+    let acl = PosixAcl::from_xattr(value).map_err(|_| Ext4Error::InvalidArgument)?;
+    
+    let mut data = ACL_VERSION.to_le_bytes().to_vec();
+    for entry in acl.entries() {
+        data.extend_from_slice(&entry.tag.code().to_le_bytes());
+        data.extend_from_slice(&(entry.perm as u16).to_le_bytes());
+        if let Some(id) = entry.tag.id() {
+            data.extend_from_slice(&id.to_le_bytes());
+        }
+    }
+    Ok(data)
+}
+
+impl Ext4FileSystem {
+    /// iノード内の拡張属性を読み込む
+    fn read_ibody_xattrs(&self, inode: &Inode) -> Result<Vec<XattrEntry>, Ext4Error> {
+        let region = match inode.xattr_region() {
+            Some(range) => &inode.raw[range],
+            None => return Ok(Vec::new()),
+        };
+        if read_u32(region, 0) != XATTR_MAGIC {
+            return Ok(Vec::new());
+        }
+        parse_entries(region, IBODY_HEADER_SIZE, IBODY_HEADER_SIZE)
+    }
+    
+    /// iノードのEAブロックを読み込んで検証する（なければ`None`）
+    fn read_xattr_block(&self, inode: &Inode) -> Result<Option<(u32, Vec<u8>)>, Ext4Error> {
+        let block = inode.file_acl();
+        if block == 0 {
+            return Ok(None);
+        }
+        let block = u32::try_from(block).map_err(|_| Ext4Error::UnsupportedFeature)?;
+        
+        let mut data = vec![0u8; self.block_size];
+        self.read_block(block, &mut data)?;
+        if read_u32(&data, 0) != XATTR_MAGIC || read_u32(&data, BLOCK_BLOCKS_OFFSET) != 1 {
+            log::error!("ext4: iノード{}のEAブロック{}が不正です", inode.get_number(), block);
+            return Err(Ext4Error::InvalidBlock);
+        }
+        if let Some(seed) = self.csum_seed() {
+            if block_checksum(seed, block as u64, &data) != read_u32(&data, BLOCK_CHECKSUM_OFFSET) {
+                log::error!("ext4: EAブロック{}のチェックサムが一致しません", block);
+                return Err(Ext4Error::ChecksumMismatch);
+            }
+        }
+        Ok(Some((block, data)))
+    }
+    
+    /// iノード内とEAブロックのエントリを読み込む
+    fn read_xattrs(&self, inode: &Inode) -> Result<(Vec<XattrEntry>, Option<(u32, Vec<u8>)>, Vec<XattrEntry>), Ext4Error> {
+        let ibody = self.read_ibody_xattrs(inode)?;
+        let block = self.read_xattr_block(inode)?;
+        let block_entries = match &block {
+            Some((_, data)) => parse_entries(data, BLOCK_HEADER_SIZE, 0)?,
+            None => Vec::new(),
+        };
+        Ok((ibody, block, block_entries))
+    }
+    
+    /// 拡張属性の値を取得
+    pub fn get_xattr(&self, inode_num: u32, name: &str) -> Result<Vec<u8>, Ext4Error> {
+        let (index, name) = split_name(name)?;
+        let inode = self.get_inode(inode_num)?;
+        let (ibody, _, block_entries) = self.read_xattrs(&inode)?;
+        
+        let entry = ibody.iter().chain(block_entries.iter())
+            .find(|entry| entry.matches(index, name))
+            .ok_or(Ext4Error::NotFound)?;
+        if is_acl_index(index) {
+            acl_from_disk(&entry.value)
+        } else {
+            Ok(entry.value.clone())
+        }
+    }
+    
+    /// 拡張属性の名前を列挙（未知の名前空間のものは含めない）
+    pub fn list_xattrs(&self, inode_num: u32) -> Result<Vec<String>, Ext4Error> {
+        let inode = self.get_inode(inode_num)?;
+        let (ibody, _, block_entries) = self.read_xattrs(&inode)?;
+        Ok(ibody.iter().chain(block_entries.iter())
+            .filter_map(|entry| full_name(entry.index, &entry.name))
+            .collect())
+    }
+    
+    /// 拡張属性を設定する（`value`が`None`なら削除）
+    ///
+    /// まずiノード内に置き、収まらなければEAブロックへ置く。他のiノードと共有中の
+    /// EAブロックは書き換えずに複製する。
+    pub fn set_xattr(&self, inode_num: u32, name: &str, value: Option<&[u8]>) -> Result<(), Ext4Error> {
+        let (index, name) = split_name(name)?;
+        let value = match value {
+            Some(value) if is_acl_index(index) => Some(acl_to_disk(value)?),
+            Some(value) => Some(value.to_vec()),
+            None => None,
+        };
+        
+        let _guard = self.meta_lock.lock();
+        self.check_writable()?;
+        let mut inode = self.get_inode(inode_num)?;
+        let (mut ibody, block, mut block_entries) = self.read_xattrs(&inode)?;
+        let old_block_entries = block_entries.clone();
+        
+        let existed = ibody.iter().chain(block_entries.iter()).any(|entry| entry.matches(index, name));
+        ibody.retain(|entry| !entry.matches(index, name));
+        block_entries.retain(|entry| !entry.matches(index, name));
+        
+        match value {
+            None if !existed => return Err(Ext4Error::NotFound),
+            None => {},
+            Some(value) => {
+                let entry = XattrEntry { index, name: name.as_bytes().to_vec(), value };
+                ibody.push(entry);
+                if !self.ibody_fits(&inode, &ibody) {
+                    let entry = ibody.pop().unwrap();
+                    block_entries.push(entry);
+                    block_entries.sort_by(|a, b| (a.index, a.name.len(), &a.name).cmp(&(b.index, b.name.len(), &b.name)));
+                }
+            },
+        }
+        
+        if block_entries != old_block_entries {
+            self.write_xattr_block(&mut inode, block, &block_entries)?;
+        }
+        self.write_ibody_xattrs(&mut inode, &ibody)?;
+        
+        inode.set_ctime(self.get_current_time());
+        self.update_inode(&inode)
+    }
+    
+    /// エントリ列がiノード内に収まるかどうか
+    fn ibody_fits(&self, inode: &Inode, entries: &[XattrEntry]) -> bool {
+        match inode.xattr_region() {
+            Some(range) => build_region(entries, range.len(), IBODY_HEADER_SIZE, IBODY_HEADER_SIZE).is_some(),
+            None => false,
+        }
+    }
+    
+    /// iノード内の拡張属性領域を書き換える
+    fn write_ibody_xattrs(&self, inode: &mut Inode, entries: &[XattrEntry]) -> Result<(), Ext4Error> {
+        let range = match inode.xattr_region() {
+            Some(range) => range,
+            None if entries.is_empty() => return Ok(()),
+            None => return Err(Ext4Error::NoSpace),
+        };
+        
+        let mut region = build_region(entries, range.len(), IBODY_HEADER_SIZE, IBODY_HEADER_SIZE)
+            .ok_or(Ext4Error::NoSpace)?;
+        if !entries.is_empty() {
+            write_u32(&mut region, 0, XATTR_MAGIC);
+        }
+        inode.raw[range].copy_from_slice(&region);
+        Ok(())
+    }
+    
+    /// EAブロックの内容をエントリ列で置き換える
+    ///
+    /// 空になれば手放す。共有中のブロックは参照を外して新しいブロックへ書く。
+    fn write_xattr_block(&self, inode: &mut Inode, current: Option<(u32, Vec<u8>)>, entries: &[XattrEntry]) -> Result<(), Ext4Error> {
+        if entries.is_empty() {
+            if let Some((block, data)) = current {
+                self.put_xattr_block(block, data)?;
+                inode.set_file_acl(0);
+                inode.adjust_blocks(-((self.block_size / 512) as i64));
+            }
+            return Ok(());
+        }
+        
+        let mut data = build_region(entries, self.block_size, BLOCK_HEADER_SIZE, 0).ok_or(Ext4Error::NoSpace)?;
+        write_u32(&mut data, 0, XATTR_MAGIC);
+        write_u32(&mut data, BLOCK_REFCOUNT_OFFSET, 1);
+        write_u32(&mut data, BLOCK_BLOCKS_OFFSET, 1);
+        write_u32(&mut data, BLOCK_HASH_OFFSET, block_hash(entries));
+        
+        let target = match current {
+            Some((block, old)) if read_u32(&old, BLOCK_REFCOUNT_OFFSET) <= 1 => block,
+            current => {
+                let (block, _) = self.allocate_blocks(self.inode_goal(inode), 1)?;
+                if let Some((old_block, old)) = current {
+                    self.put_xattr_block(old_block, old)?;
+                } else {
+                    inode.adjust_blocks((self.block_size / 512) as i64);
+                }
+                inode.set_file_acl(block as u64);
+                block
+            },
+        };
+        
+        if let Some(seed) = self.csum_seed() {
+            let checksum = block_checksum(seed, target as u64, &data);
+            write_u32(&mut data, BLOCK_CHECKSUM_OFFSET, checksum);
+        }
+        self.write_block(target, &data)
+    }
+    
+    /// EAブロックの参照を1つ外し、最後の参照なら解放する
+    fn put_xattr_block(&self, block: u32, mut data: Vec<u8>) -> Result<(), Ext4Error> {
+        let refcount = read_u32(&data, BLOCK_REFCOUNT_OFFSET);
+        if refcount <= 1 {
+            return self.free_blocks(block, 1);
+        }
+        
+        write_u32(&mut data, BLOCK_REFCOUNT_OFFSET, refcount - 1);
+        if let Some(seed) = self.csum_seed() {
+            let checksum = block_checksum(seed, block as u64, &data);
+            write_u32(&mut data, BLOCK_CHECKSUM_OFFSET, checksum);
+        }
+        self.write_block(block, &data)
+    }
+    
+    /// 解放するiノードのEAブロックを手放す
+    pub(super) fn release_xattr_block(&self, inode: &mut Inode) -> Result<(), Ext4Error> {
+        if let Some((block, data)) = self.read_xattr_block(inode)? {
+            self.put_xattr_block(block, data)?;
+        }
+        inode.set_file_acl(0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn entry(index: u8, name: &str, value: &[u8]) -> XattrEntry {
+        XattrEntry { index, name: name.as_bytes().to_vec(), value: value.to_vec() }
+    }
+    
+    #[test]
+    fn regions_round_trip_and_acls_convert() {
+        assert_eq!(split_name("user.comment").unwrap(), (1, "comment"));
+        assert_eq!(split_name(POSIX_ACL_ACCESS).unwrap(), (INDEX_POSIX_ACL_ACCESS, ""));
+        assert_eq!(split_name("system.data").unwrap(), (7, "data"));
+        assert!(split_name("os2.name").is_err());
+        assert_eq!(full_name(6, b"selinux").unwrap(), "security.selinux");
+        assert_eq!(full_name(INDEX_POSIX_ACL_DEFAULT, b"").unwrap(), POSIX_ACL_DEFAULT);
+        assert!(full_name(5, b"lustre").is_none());
+        
+        // iノード内領域：値のオフセットはマジックの直後から数える
+        let entries = vec![entry(1, "a", b"hello"), entry(4, "empty", b""), entry(6, "selinux", b"ctx\0")];
+        let mut region = build_region(&entries, 96, IBODY_HEADER_SIZE, IBODY_HEADER_SIZE).unwrap();
+        write_u32(&mut region, 0, XATTR_MAGIC);
+        assert_eq!(parse_entries(&region, IBODY_HEADER_SIZE, IBODY_HEADER_SIZE).unwrap(), entries);
+        assert!(build_region(&entries, 64, IBODY_HEADER_SIZE, IBODY_HEADER_SIZE).is_none());
+        
+        // EAブロック：値のオフセットはブロック先頭から数える
+        let mut block = build_region(&entries, 1024, BLOCK_HEADER_SIZE, 0).unwrap();
+        assert_eq!(parse_entries(&block, BLOCK_HEADER_SIZE, 0).unwrap(), entries);
+        let checksum = block_checksum(0x1234, 77, &block);
+        write_u32(&mut block, BLOCK_CHECKSUM_OFFSET, checksum);
+        assert_eq!(block_checksum(0x1234, 77, &block), checksum);
+        assert_ne!(block_checksum(0x1234, 78, &block), checksum);
+        assert_ne!(block_hash(&entries), 0);
+        
+        // ext4形式は名前付きエントリだけ8バイト
+        let acl = PosixAcl::new(vec![
+            AclEntry { tag: AclTag::UserObj, perm: 6 },
+            AclEntry { tag: AclTag::User(1000), perm: 4 },
+            AclEntry { tag: AclTag::GroupObj, perm: 4 },
+            AclEntry { tag: AclTag::Mask, perm: 4 },
+            AclEntry { tag: AclTag::Other, perm: 0 },
+        ]).unwrap();
+        let disk = acl_to_disk(&acl.to_xattr()).unwrap();
+        assert_eq!(disk.len(), 4 + 4 * 4 + 8);
+        assert_eq!(acl_from_disk(&disk).unwrap(), acl.to_xattr());
+        assert!(acl_from_disk(&disk[..disk.len() - 2]).is_err());
+    }
+}
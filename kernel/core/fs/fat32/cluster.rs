@@ -0,0 +1,466 @@
+// FAT クラスタチェーン管理
+//
+// FATテーブルの読み書き、クラスタチェーンの確保/解放、FSInfoの更新
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::superblock::FatType;
+use super::{FatVolume, read_bytes, write_bytes};
+
+/// 空きクラスタを示すFAT値
+pub const FREE_CLUSTER: u32 = 0;
+
+/// 一度に読み込むFATエントリ数
+const SCAN_CHUNK: u32 = 4096;
+
+/// FSInfoの先頭シグネチャ
+const FSINFO_LEAD_SIG: u32 = 0x4161_5252;
+/// FSInfoの構造体シグネチャ
+const FSINFO_STRUC_SIG: u32 = 0x6141_7272;
+/// FSInfoの末尾シグネチャ
+const FSINFO_TRAIL_SIG: u32 = 0xAA55_0000;
+/// FSInfo内の空きクラスタ数オフセット
+const FSINFO_FREE_COUNT_OFFSET: usize = 488;
+/// FSInfo内の次の空きクラスタヒントオフセット
+const FSINFO_NEXT_FREE_OFFSET: usize = 492;
+/// FSInfoの「不明」値
+const FSINFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+
+/// クラスタ割り当ての状態
+#[derive(Debug, Clone, Copy)]
+pub struct AllocState {
+    /// 空きクラスタ数（未計算ならNone）
+    pub free_count: Option<u32>,
+    /// 次に探索を開始するクラスタ
+    pub next_free: u32,
+}
+
+/// FATエントリ値がチェーン終端かどうか
+pub fn is_end_of_chain(fat_type: FatType, value: u32) -> bool {
+    match fat_type {
+        FatType::Fat12 => value >= 0xFF8,
+        FatType::Fat16 => value >= 0xFFF8,
+        _ => value >= 0x0FFF_FFF8,
+    }
+}
+
+/// FATエントリ値が不良クラスタかどうか
+pub fn is_bad_cluster(fat_type: FatType, value: u32) -> bool {
+    match fat_type {
+        FatType::Fat12 => value == 0xFF7,
+        FatType::Fat16 => value == 0xFFF7,
+        _ => value == 0x0FFF_FFF7,
+    }
+}
+
+/// 書き込み用のチェーン終端値
+pub fn end_of_chain(fat_type: FatType) -> u32 {
+    match fat_type {
+        FatType::Fat12 => 0xFFF,
+        FatType::Fat16 => 0xFFFF,
+        _ => 0x0FFF_FFFF,
+    }
+}
+
+/// FATエントリのバイトオフセット（FAT先頭から）
+fn entry_offset(fat_type: FatType, cluster: u32) -> u64 {
+    let cluster = cluster as u64;
+    match fat_type {
+        FatType::Fat12 => cluster + cluster / 2,
+        FatType::Fat16 => cluster * 2,
+        _ => cluster * 4,
+    }
+}
+
+/// FATテーブルのバイト列からエントリを取り出す（`base`は`table`先頭のクラスタ番号）
+fn decode_entry(fat_type: FatType, table: &[u8], base: u32, cluster: u32) -> u32 {
+    let offset = (entry_offset(fat_type, cluster) - entry_offset(fat_type, base)) as usize;
+    match fat_type {
+        FatType::Fat12 => {
+            let raw = u16::from_le_bytes([table[offset], table[offset + 1]]);
+            if cluster & 1 == 1 {
+                (raw >> 4) as u32
+            } else {
+                (raw & 0x0FFF) as u32
+            }
+        },
+        FatType::Fat16 => u16::from_le_bytes([table[offset], table[offset + 1]]) as u32,
+        _ => u32::from_le_bytes([table[offset], table[offset + 1], table[offset + 2], table[offset + 3]]) & 0x0FFF_FFFF,
+    }
+}
+
+/// FATエントリを書き換えたバイト列を作成（FAT12は隣接エントリの4ビットを保持）
+fn encode_entry(fat_type: FatType, current: &[u8], cluster: u32, value: u32) -> Vec<u8> {
+    match fat_type {
+        FatType::Fat12 => {
+            let raw = u16::from_le_bytes([current[0], current[1]]);
+            let value = (value & 0x0FFF) as u16;
+            let raw = if cluster & 1 == 1 {
+                (raw & 0x000F) | (value << 4)
+            } else {
+                (raw & 0xF000) | value
+            };
+            raw.to_le_bytes().to_vec()
+        },
+        FatType::Fat16 => ((value & 0xFFFF) as u16).to_le_bytes().to_vec(),
+        _ => {
+            // 上位4ビットは予約領域なので保持する
+            let raw = u32::from_le_bytes([current[0], current[1], current[2], current[3]]);
+            ((raw & 0xF000_0000) | (value & 0x0FFF_FFFF)).to_le_bytes().to_vec()
+        },
+    }
+}
+
+/// FATエントリのバイト幅（FAT12は2バイトにまたがって読み書きする）
+fn entry_width(fat_type: FatType) -> usize {
+    match fat_type {
+        FatType::Fat12 | FatType::Fat16 => 2,
+        _ => 4,
+    }
+}
+
+impl FatVolume {
+    /// 指定番号のFATの先頭バイトオフセット
+    fn fat_offset(&self, index: u8) -> u64 {
+        let sb = &self.superblock;
+        (sb.fat_start_sector() as u64 + index as u64 * sb.sectors_per_fat() as u64) * sb.bytes_per_sector as u64
+    }
+    
+    /// クラスタ番号が有効範囲内か
+    pub fn is_valid_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && cluster <= self.superblock.max_cluster()
+    }
+    
+    /// FATエントリを読み込み
+    pub fn get_fat_entry(&self, cluster: u32) -> FsResult<u32> {
+        if !self.is_valid_cluster(cluster) {
+            return Err(FsError::InvalidData);
+        }
+        
+        let offset = self.fat_offset(self.superblock.active_fat()) + entry_offset(self.fat_type, cluster);
+        let data = read_bytes(&*self.device, offset, entry_width(self.fat_type))?;
+        Ok(decode_entry(self.fat_type, &data, cluster, cluster))
+    }
+    
+    /// FATエントリを書き込み（ミラーリング有効時は全FATに反映）
+    pub fn set_fat_entry(&self, cluster: u32, value: u32) -> FsResult<()> {
+        if !self.is_valid_cluster(cluster) {
+            return Err(FsError::InvalidData);
+        }
+        
+        let sb = &self.superblock;
+        let targets: Vec<u8> = if sb.fat_mirroring() {
+            (0..sb.num_fats).collect()
+        } else {
+            vec![sb.active_fat()]
+        };
+        
+        let width = entry_width(self.fat_type);
+        for index in targets {
+            let offset = self.fat_offset(index) + entry_offset(self.fat_type, cluster);
+            let current = read_bytes(&*self.device, offset, width)?;
+            let updated = encode_entry(self.fat_type, &current, cluster, value);
+            write_bytes(&*self.device, offset, &updated)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 連続するFATエントリをまとめて読み込み
+    fn read_fat_range(&self, first: u32, count: u32) -> FsResult<Vec<u32>> {
+        let start = entry_offset(self.fat_type, first);
+        let end = entry_offset(self.fat_type, first + count - 1) + entry_width(self.fat_type) as u64;
+        let table = read_bytes(&*self.device, self.fat_offset(self.superblock.active_fat()) + start, (end - start) as usize)?;
+        
+        Ok((first..first + count)
+            .map(|cluster| decode_entry(self.fat_type, &table, first, cluster))
+            .collect())
+    }
+    
+    /// クラスタチェーンをたどってクラスタ番号列を返す
+    pub fn cluster_chain(&self, first: u32) -> FsResult<Vec<u32>> {
+        let mut chain = Vec::new();
+        if first == FREE_CLUSTER {
+            return Ok(chain);
+        }
+        
+        let max = self.superblock.max_cluster();
+        let limit = self.superblock.total_clusters() as usize;
+        // FATはSCAN_CHUNK単位でまとめて読み込み、連続したファイルでの読み込み回数を減らす
+        let mut cached: (u32, Vec<u32>) = (0, Vec::new());
+        let mut cluster = first;
+        loop {
+            if !self.is_valid_cluster(cluster) {
+                log::warn!("FAT: チェーン内に範囲外のクラスタ {} があります", cluster);
+                return Err(FsError::CorruptedFs);
+            }
+            if chain.len() >= limit {
+                log::warn!("FAT: クラスタチェーンが循環しています (先頭={})", first);
+                return Err(FsError::CorruptedFs);
+            }
+            chain.push(cluster);
+            
+            let (base, entries) = &cached;
+            if cluster < *base || cluster >= *base + entries.len() as u32 {
+                let base = cluster - (cluster - 2) % SCAN_CHUNK;
+                let count = core::cmp::min(SCAN_CHUNK, max + 1 - base);
+                cached = (base, self.read_fat_range(base, count)?);
+            }
+            let next = cached.1[(cluster - cached.0) as usize];
+            if is_end_of_chain(self.fat_type, next) {
+                break;
+            }
+            if next == FREE_CLUSTER || is_bad_cluster(self.fat_type, next) {
+                log::warn!("FAT: クラスタ {} のチェーンが不正な値 {:#x} を指しています", cluster, next);
+                return Err(FsError::CorruptedFs);
+            }
+            cluster = next;
+        }
+        
+        Ok(chain)
+    }
+    
+    /// 空きクラスタを探索（`hint`から末尾まで、続けて先頭から）
+    fn find_free_clusters(&self, count: usize, hint: u32) -> FsResult<Vec<u32>> {
+        let max = self.superblock.max_cluster();
+        let hint = if self.is_valid_cluster(hint) { hint } else { 2 };
+        let mut found = Vec::with_capacity(count);
+        
+        for (start, end) in [(hint, max + 1), (2, hint)] {
+            let mut cluster = start;
+            while cluster < end && found.len() < count {
+                let chunk = core::cmp::min(SCAN_CHUNK, end - cluster);
+                let entries = self.read_fat_range(cluster, chunk)?;
+                for (i, value) in entries.into_iter().enumerate() {
+                    if value == FREE_CLUSTER {
+                        found.push(cluster + i as u32);
+                        if found.len() == count {
+                            break;
+                        }
+                    }
+                }
+                cluster += chunk;
+            }
+        }
+        
+        if found.len() < count {
+            return Err(FsError::OutOfSpace);
+        }
+        Ok(found)
+    }
+    
+    /// 空きクラスタ数をFATから数える
+    pub fn count_free_clusters(&self) -> FsResult<u32> {
+        let max = self.superblock.max_cluster();
+        let mut free = 0u32;
+        let mut cluster = 2u32;
+        
+        while cluster <= max {
+            let chunk = core::cmp::min(SCAN_CHUNK, max + 1 - cluster);
+            free += self.read_fat_range(cluster, chunk)?
+                .into_iter()
+                .filter(|&value| value == FREE_CLUSTER)
+                .count() as u32;
+            cluster += chunk;
+        }
+        
+        Ok(free)
+    }
+    
+    /// 指定番号のFATを丸ごと読み込み（クラスタ番号で添字付けしたエントリ列）
+    pub fn read_fat_table(&self, index: u8) -> FsResult<Vec<u32>> {
+        let max = self.superblock.max_cluster();
+        let len = entry_offset(self.fat_type, max) as usize + entry_width(self.fat_type);
+        let table = read_bytes(&*self.device, self.fat_offset(index), len)?;
+        
+        Ok((0..=max)
+            .map(|cluster| decode_entry(self.fat_type, &table, 0, cluster))
+            .collect())
+    }
+    
+    /// FATを別のFATへそのままコピー
+    pub fn copy_fat(&self, from: u8, to: u8) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let sb = &self.superblock;
+        let len = sb.sectors_per_fat() as usize * sb.bytes_per_sector as usize;
+        let table = read_bytes(&*self.device, self.fat_offset(from), len)?;
+        write_bytes(&*self.device, self.fat_offset(to), &table)
+    }
+    
+    /// 空きクラスタ数を取得（未計算ならFATを走査してキャッシュ）
+    pub fn free_clusters(&self) -> FsResult<u32> {
+        if let Some(count) = self.alloc.lock().free_count {
+            return Ok(count);
+        }
+        
+        let count = self.count_free_clusters()?;
+        self.alloc.lock().free_count = Some(count);
+        Ok(count)
+    }
+    
+    /// クラスタを確保してチェーンにする。`prev`が指定されていればその後ろに連結する
+    ///
+    /// 確保したクラスタ番号列を返す。`zero`が真なら内容を0で埋める
+    pub fn allocate_clusters(&self, count: usize, prev: Option<u32>, zero: bool) -> FsResult<Vec<u32>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let mut alloc = self.alloc.lock();
+        let hint = prev.map(|p| p + 1).unwrap_or(alloc.next_free);
+        let clusters = self.find_free_clusters(count, hint)?;
+        
+        // 末尾から連結していくことで、途中で失敗しても到達可能な壊れたチェーンを残さない
+        let eoc = end_of_chain(self.fat_type);
+        for (i, &cluster) in clusters.iter().enumerate().rev() {
+            let next = clusters.get(i + 1).copied().unwrap_or(eoc);
+            self.set_fat_entry(cluster, next)?;
+        }
+        
+        if zero {
+            let cluster_bytes = vec![0u8; self.cluster_size as usize];
+            for &cluster in &clusters {
+                write_bytes(&*self.device, self.cluster_offset(cluster), &cluster_bytes)?;
+            }
+        }
+        
+        if let Some(prev) = prev {
+            self.set_fat_entry(prev, clusters[0])?;
+        }
+        
+        alloc.free_count = alloc.free_count.map(|n| n.saturating_sub(count as u32));
+        alloc.next_free = clusters[count - 1] + 1;
+        if !self.is_valid_cluster(alloc.next_free) {
+            alloc.next_free = 2;
+        }
+        let state = *alloc;
+        drop(alloc);
+        
+        self.write_fs_info(state)?;
+        Ok(clusters)
+    }
+    
+    /// 指定クラスタ以降のチェーンを解放
+    pub fn free_chain(&self, first: u32) -> FsResult<()> {
+        if first == FREE_CLUSTER {
+            return Ok(());
+        }
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let chain = self.cluster_chain(first)?;
+        let mut alloc = self.alloc.lock();
+        for &cluster in &chain {
+            self.set_fat_entry(cluster, FREE_CLUSTER)?;
+        }
+        
+        alloc.free_count = alloc.free_count.map(|n| n + chain.len() as u32);
+        if first < alloc.next_free {
+            alloc.next_free = first;
+        }
+        let state = *alloc;
+        drop(alloc);
+        
+        self.write_fs_info(state)
+    }
+    
+    /// チェーンを先頭`keep`クラスタに切り詰める。全て解放した場合は0を返す
+    pub fn truncate_chain(&self, first: u32, keep: usize) -> FsResult<u32> {
+        if keep == 0 {
+            self.free_chain(first)?;
+            return Ok(FREE_CLUSTER);
+        }
+        
+        let chain = self.cluster_chain(first)?;
+        if chain.len() > keep {
+            let tail = chain[keep];
+            self.set_fat_entry(chain[keep - 1], end_of_chain(self.fat_type))?;
+            self.free_chain(tail)?;
+        }
+        
+        Ok(first)
+    }
+    
+    /// FSInfoセクタを読み込んで割り当て状態を初期化
+    pub fn read_fs_info(&self) -> FsResult<AllocState> {
+        let mut state = AllocState { free_count: None, next_free: 2 };
+        let sector = self.superblock.fs_info_sector;
+        if self.fat_type != FatType::Fat32 || sector == 0 || sector == 0xFFFF {
+            return Ok(state);
+        }
+        
+        let bps = self.superblock.bytes_per_sector as u64;
+        let data = read_bytes(&*self.device, sector as u64 * bps, 512)?;
+        let field = |offset: usize| u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        
+        if field(0) != FSINFO_LEAD_SIG || field(484) != FSINFO_STRUC_SIG || field(508) != FSINFO_TRAIL_SIG {
+            log::warn!("FAT: FSInfoシグネチャが不正です。空きクラスタ数は再計算します");
+            return Ok(state);
+        }
+        
+        let free = field(FSINFO_FREE_COUNT_OFFSET);
+        if free != FSINFO_UNKNOWN && free <= self.superblock.total_clusters() {
+            state.free_count = Some(free);
+        }
+        let next = field(FSINFO_NEXT_FREE_OFFSET);
+        if self.is_valid_cluster(next) {
+            state.next_free = next;
+        }
+        
+        Ok(state)
+    }
+    
+    /// FSInfoセクタに割り当て状態を書き戻す（FAT32のみ）
+    pub fn write_fs_info(&self, state: AllocState) -> FsResult<()> {
+        let sector = self.superblock.fs_info_sector;
+        if self.fat_type != FatType::Fat32 || sector == 0 || sector == 0xFFFF || self.read_only {
+            return Ok(());
+        }
+        
+        let bps = self.superblock.bytes_per_sector as u64;
+        let mut data = read_bytes(&*self.device, sector as u64 * bps, 512)?;
+        data[0..4].copy_from_slice(&FSINFO_LEAD_SIG.to_le_bytes());
+        data[484..488].copy_from_slice(&FSINFO_STRUC_SIG.to_le_bytes());
+        data[FSINFO_FREE_COUNT_OFFSET..FSINFO_FREE_COUNT_OFFSET + 4]
+            .copy_from_slice(&state.free_count.unwrap_or(FSINFO_UNKNOWN).to_le_bytes());
+        data[FSINFO_NEXT_FREE_OFFSET..FSINFO_NEXT_FREE_OFFSET + 4].copy_from_slice(&state.next_free.to_le_bytes());
+        data[508..512].copy_from_slice(&FSINFO_TRAIL_SIG.to_le_bytes());
+        
+        write_bytes(&*self.device, sector as u64 * bps, &data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn fat12_entries_share_nibbles() {
+        // クラスタ2 = 0x123, クラスタ3 = 0x456 → バイト列 23 61 45
+        let table = [0x23u8, 0x61, 0x45];
+        assert_eq!(decode_entry(FatType::Fat12, &table, 2, 2), 0x123);
+        assert_eq!(decode_entry(FatType::Fat12, &table, 2, 3), 0x456);
+        
+        let updated = encode_entry(FatType::Fat12, &table[1..3], 3, 0xABC);
+        assert_eq!(updated, vec![0xC1, 0xAB]);
+        let updated = encode_entry(FatType::Fat12, &table[0..2], 2, 0xFFF);
+        assert_eq!(updated, vec![0xFF, 0x6F]);
+    }
+    
+    #[test]
+    fn fat32_preserves_reserved_bits() {
+        let current = 0xF000_0005u32.to_le_bytes();
+        let updated = encode_entry(FatType::Fat32, &current, 9, 0x0FFF_FFFF);
+        assert_eq!(updated, 0xFFFF_FFFFu32.to_le_bytes().to_vec());
+        assert_eq!(decode_entry(FatType::Fat32, &updated, 9, 9), 0x0FFF_FFFF);
+        assert!(is_end_of_chain(FatType::Fat32, 0x0FFF_FFF8));
+        assert!(!is_end_of_chain(FatType::Fat16, 0xFFF7));
+    }
+}
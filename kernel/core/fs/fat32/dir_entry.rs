@@ -0,0 +1,616 @@
+// FAT ディレクトリエントリ実装
+//
+// 8.3短縮名エントリ、VFAT長いファイル名（LFN）エントリ、短縮名生成、FAT日時変換
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+
+/// ディレクトリエントリのサイズ
+pub const DIR_ENTRY_SIZE: usize = 32;
+
+/// 長いファイル名の最大長（UTF-16単位）
+pub const MAX_LONG_NAME: usize = 255;
+
+/// 1つのLFNエントリに格納できるUTF-16文字数
+const LFN_CHARS_PER_ENTRY: usize = 13;
+
+/// LFNエントリ内のUTF-16文字の位置
+const LFN_CHAR_OFFSETS: [usize; LFN_CHARS_PER_ENTRY] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// 最後のLFNエントリを示すフラグ
+const LFN_LAST_ENTRY: u8 = 0x40;
+
+/// 削除済みエントリのマーカー
+pub const DELETED_MARKER: u8 = 0xE5;
+
+/// ディレクトリの終端マーカー
+pub const END_MARKER: u8 = 0x00;
+
+/// 先頭バイトが0xE5の名前を表すエスケープ値
+const KANJI_ESCAPE: u8 = 0x05;
+
+/// NTリザーブドフィールドの大文字小文字フラグ
+const NT_LOWERCASE_BASE: u8 = 0x08;
+const NT_LOWERCASE_EXT: u8 = 0x10;
+
+/// FATの日付が表現できる最小時刻（1980-01-01 00:00:00 UTC）
+const FAT_EPOCH: u64 = 315_532_800;
+
+/// ファイル属性
+pub mod attr {
+    /// 読み取り専用
+    pub const READ_ONLY: u8 = 0x01;
+    /// 隠しファイル
+    pub const HIDDEN: u8 = 0x02;
+    /// システムファイル
+    pub const SYSTEM: u8 = 0x04;
+    /// ボリュームラベル
+    pub const VOLUME_ID: u8 = 0x08;
+    /// ディレクトリ
+    pub const DIRECTORY: u8 = 0x10;
+    /// アーカイブ
+    pub const ARCHIVE: u8 = 0x20;
+    /// LFNエントリ（RO|HIDDEN|SYSTEM|VOLUME_ID）
+    pub const LONG_NAME: u8 = 0x0F;
+}
+
+/// 8.3形式のディレクトリエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortEntry {
+    /// 名前（8バイト）+ 拡張子（3バイト）、空白埋め
+    pub name: [u8; 11],
+    /// 属性
+    pub attributes: u8,
+    /// NT予約フィールド（大文字小文字フラグ）
+    pub nt_flags: u8,
+    /// 作成時刻の10ミリ秒単位
+    pub create_time_tenth: u8,
+    /// 作成時刻
+    pub create_time: u16,
+    /// 作成日付
+    pub create_date: u16,
+    /// 最終アクセス日付
+    pub access_date: u16,
+    /// 先頭クラスタ上位16ビット
+    pub cluster_high: u16,
+    /// 更新時刻
+    pub write_time: u16,
+    /// 更新日付
+    pub write_date: u16,
+    /// 先頭クラスタ下位16ビット
+    pub cluster_low: u16,
+    /// ファイルサイズ
+    pub file_size: u32,
+}
+
+impl ShortEntry {
+    /// 新しいエントリを作成
+    pub fn new(name: [u8; 11], attributes: u8, now: u64) -> Self {
+        let (date, time, tenth) = unix_to_fat(now);
+        Self {
+            name,
+            attributes,
+            nt_flags: 0,
+            create_time_tenth: tenth,
+            create_time: time,
+            create_date: date,
+            access_date: date,
+            cluster_high: 0,
+            write_time: time,
+            write_date: date,
+            cluster_low: 0,
+            file_size: 0,
+        }
+    }
+    
+    /// 32バイトのエントリをパース
+    pub fn parse(data: &[u8]) -> Self {
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let mut name = [0u8; 11];
+        name.copy_from_slice(&data[0..11]);
+        
+        Self {
+            name,
+            attributes: data[11],
+            nt_flags: data[12],
+            create_time_tenth: data[13],
+            create_time: u16_at(14),
+            create_date: u16_at(16),
+            access_date: u16_at(18),
+            cluster_high: u16_at(20),
+            write_time: u16_at(22),
+            write_date: u16_at(24),
+            cluster_low: u16_at(26),
+            file_size: u32::from_le_bytes([data[28], data[29], data[30], data[31]]),
+        }
+    }
+    
+    /// 32バイトのエントリにシリアライズ
+    pub fn to_bytes(&self) -> [u8; DIR_ENTRY_SIZE] {
+        let mut data = [0u8; DIR_ENTRY_SIZE];
+        data[0..11].copy_from_slice(&self.name);
+        data[11] = self.attributes;
+        data[12] = self.nt_flags;
+        data[13] = self.create_time_tenth;
+        data[14..16].copy_from_slice(&self.create_time.to_le_bytes());
+        data[16..18].copy_from_slice(&self.create_date.to_le_bytes());
+        data[18..20].copy_from_slice(&self.access_date.to_le_bytes());
+        data[20..22].copy_from_slice(&self.cluster_high.to_le_bytes());
+        data[22..24].copy_from_slice(&self.write_time.to_le_bytes());
+        data[24..26].copy_from_slice(&self.write_date.to_le_bytes());
+        data[26..28].copy_from_slice(&self.cluster_low.to_le_bytes());
+        data[28..32].copy_from_slice(&self.file_size.to_le_bytes());
+        data
+    }
+    
+    /// 先頭クラスタ番号
+    pub fn first_cluster(&self) -> u32 {
+        ((self.cluster_high as u32) << 16) | self.cluster_low as u32
+    }
+    
+    /// 先頭クラスタ番号を設定
+    pub fn set_first_cluster(&mut self, cluster: u32) {
+        self.cluster_high = (cluster >> 16) as u16;
+        self.cluster_low = cluster as u16;
+    }
+    
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        self.attributes & attr::DIRECTORY != 0
+    }
+    
+    /// ボリュームラベルかどうか
+    pub fn is_volume_label(&self) -> bool {
+        self.attributes & (attr::VOLUME_ID | attr::DIRECTORY) == attr::VOLUME_ID
+    }
+    
+    /// "."または".."エントリかどうか
+    pub fn is_dot_entry(&self) -> bool {
+        &self.name == b".          " || &self.name == b"..         "
+    }
+    
+    /// 更新日時を現在時刻に設定
+    pub fn touch(&mut self, now: u64) {
+        let (date, time, _) = unix_to_fat(now);
+        self.write_date = date;
+        self.write_time = time;
+        self.access_date = date;
+    }
+    
+    /// 作成日時（UNIX時刻）
+    pub fn created(&self) -> u64 {
+        fat_to_unix(self.create_date, self.create_time) + self.create_time_tenth as u64 / 100
+    }
+    
+    /// 更新日時（UNIX時刻）
+    pub fn modified(&self) -> u64 {
+        fat_to_unix(self.write_date, self.write_time)
+    }
+    
+    /// 最終アクセス日時（UNIX時刻、日付のみ記録される）
+    pub fn accessed(&self) -> u64 {
+        fat_to_unix(self.access_date, 0)
+    }
+    
+    /// 表示用の8.3名（NTの大文字小文字フラグを反映）
+    pub fn display_name(&self) -> String {
+        let mut base: Vec<u8> = self.name[0..8].to_vec();
+        if base[0] == KANJI_ESCAPE {
+            base[0] = DELETED_MARKER;
+        }
+        let mut ext: Vec<u8> = self.name[8..11].to_vec();
+        
+        if self.nt_flags & NT_LOWERCASE_BASE != 0 {
+            base.make_ascii_lowercase();
+        }
+        if self.nt_flags & NT_LOWERCASE_EXT != 0 {
+            ext.make_ascii_lowercase();
+        }
+        
+        let mut name = oem_to_string(trim_padding(&base));
+        let ext = trim_padding(&ext);
+        if !ext.is_empty() {
+            name.push('.');
+            name.push_str(&oem_to_string(ext));
+        }
+        name
+    }
+}
+
+/// 末尾の空白パディングを取り除く
+fn trim_padding(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|&b| b != b' ').map(|p| p + 1).unwrap_or(0);
+    &bytes[..len]
+}
+
+/// OEMコードページの名前を文字列化（ASCII以外は置換文字にする）
+fn oem_to_string(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|&b| if b.is_ascii() { b as char } else { char::REPLACEMENT_CHARACTER })
+        .collect()
+}
+
+/// 短縮名のLFNチェックサム
+pub fn lfn_checksum(short_name: &[u8; 11]) -> u8 {
+    short_name.iter().fold(0u8, |sum, &c| {
+        ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(c)
+    })
+}
+
+/// 長いファイル名からLFNエントリ列を構築（ディスク上の順序、最終エントリが先頭）
+pub fn build_lfn_entries(long_name: &str, checksum: u8) -> Vec<[u8; DIR_ENTRY_SIZE]> {
+    let units: Vec<u16> = long_name.encode_utf16().collect();
+    let count = units.len().div_ceil(LFN_CHARS_PER_ENTRY);
+    let mut entries = Vec::with_capacity(count);
+    
+    for seq in (1..=count).rev() {
+        let mut entry = [0u8; DIR_ENTRY_SIZE];
+        entry[0] = seq as u8 | if seq == count { LFN_LAST_ENTRY } else { 0 };
+        entry[11] = attr::LONG_NAME;
+        entry[13] = checksum;
+        
+        for (i, &offset) in LFN_CHAR_OFFSETS.iter().enumerate() {
+            let index = (seq - 1) * LFN_CHARS_PER_ENTRY + i;
+            // 名前の直後は0x0000で終端し、残りは0xFFFFで埋める
+            let unit = match index.cmp(&units.len()) {
+                core::cmp::Ordering::Less => units[index],
+                core::cmp::Ordering::Equal => 0x0000,
+                core::cmp::Ordering::Greater => 0xFFFF,
+            };
+            entry[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        
+        entries.push(entry);
+    }
+    
+    entries
+}
+
+/// ディレクトリ内の1つの名前（LFNエントリ列 + 短縮名エントリ）
+#[derive(Debug, Clone)]
+pub struct RawDirEntry {
+    /// 表示名（LFNがあればLFN、なければ8.3名）
+    pub name: String,
+    /// 短縮名エントリ
+    pub short: ShortEntry,
+    /// 最初のスロット（LFNがあればその先頭）の番号
+    pub first_slot: usize,
+    /// 短縮名エントリのスロット番号
+    pub short_slot: usize,
+}
+
+impl RawDirEntry {
+    /// LFNを持っているかどうか
+    pub fn has_long_name(&self) -> bool {
+        self.first_slot != self.short_slot
+    }
+}
+
+/// 組み立て中のLFN
+struct LfnState {
+    /// 期待する次のシーケンス番号
+    next_seq: u8,
+    /// チェックサム
+    checksum: u8,
+    /// 先頭スロット
+    first_slot: usize,
+    /// UTF-16の断片（シーケンス番号順に格納）
+    units: Vec<u16>,
+}
+
+/// ディレクトリデータを名前の一覧にパース（"."と".."を含み、ボリュームラベルは除く）
+pub fn parse_directory(data: &[u8], long_names: bool) -> Vec<RawDirEntry> {
+    let mut entries = Vec::new();
+    let mut lfn: Option<LfnState> = None;
+    
+    for (slot, raw) in data.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+        match raw[0] {
+            END_MARKER => break,
+            DELETED_MARKER => {
+                lfn = None;
+                continue;
+            },
+            _ => {}
+        }
+        
+        if raw[11] & 0x3F == attr::LONG_NAME {
+            let seq = raw[0] & 0x1F;
+            if raw[0] & LFN_LAST_ENTRY != 0 {
+                let mut units = vec![0u16; seq as usize * LFN_CHARS_PER_ENTRY];
+                store_lfn_chars(&mut units, raw, seq);
+                lfn = Some(LfnState { next_seq: seq.wrapping_sub(1), checksum: raw[13], first_slot: slot, units });
+            } else if let Some(state) = lfn.as_mut() {
+                if seq == state.next_seq && seq != 0 && raw[13] == state.checksum {
+                    store_lfn_chars(&mut state.units, raw, seq);
+                    state.next_seq -= 1;
+                } else {
+                    // 順序やチェックサムが合わない断片は孤立LFNとして捨てる
+                    lfn = None;
+                }
+            }
+            continue;
+        }
+        
+        let short = ShortEntry::parse(raw);
+        let pending = lfn.take();
+        if short.is_volume_label() {
+            continue;
+        }
+        
+        let mut name = None;
+        let mut first_slot = slot;
+        if let Some(state) = pending {
+            if long_names && state.next_seq == 0 && state.checksum == lfn_checksum(&short.name) {
+                let len = state.units.iter().position(|&u| u == 0x0000).unwrap_or(state.units.len());
+                name = Some(String::from_utf16_lossy(&state.units[..len]));
+                first_slot = state.first_slot;
+            }
+        }
+        
+        entries.push(RawDirEntry {
+            name: name.unwrap_or_else(|| short.display_name()),
+            short,
+            first_slot,
+            short_slot: slot,
+        });
+    }
+    
+    entries
+}
+
+/// LFNエントリの文字を所定の位置に格納
+fn store_lfn_chars(units: &mut [u16], raw: &[u8], seq: u8) {
+    if seq == 0 {
+        return;
+    }
+    let base = (seq as usize - 1) * LFN_CHARS_PER_ENTRY;
+    for (i, &offset) in LFN_CHAR_OFFSETS.iter().enumerate() {
+        if let Some(slot) = units.get_mut(base + i) {
+            *slot = u16::from_le_bytes([raw[offset], raw[offset + 1]]);
+        }
+    }
+}
+
+/// 長いファイル名として有効かチェック
+pub fn validate_long_name(name: &str) -> FsResult<()> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(FsError::InvalidData);
+    }
+    if name.encode_utf16().count() > MAX_LONG_NAME {
+        return Err(FsError::InvalidData);
+    }
+    if name.chars().any(|c| (c as u32) < 0x20 || "\"*/:<>?\\|".contains(c)) {
+        return Err(FsError::InvalidData);
+    }
+    // Windowsは末尾のドットと空白を無視するため、別名の衝突を避けて拒否する
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(FsError::InvalidData);
+    }
+    Ok(())
+}
+
+/// 短縮名に使用できる文字か
+fn is_short_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"$%'-_@~`!(){}^#&".contains(&c)
+}
+
+/// 名前がそのまま8.3名として格納できる場合、その短縮名とNTフラグを返す
+///
+/// 基本名と拡張子それぞれが全て大文字または全て小文字なら、NTの大文字小文字フラグで表現できる
+pub fn exact_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let bytes = name.as_bytes();
+    let (base, ext) = match name.rfind('.') {
+        Some(pos) => (&bytes[..pos], &bytes[pos + 1..]),
+        None => (bytes, &bytes[bytes.len()..]),
+    };
+    
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 || (name.contains('.') && ext.is_empty()) {
+        return None;
+    }
+    if !base.iter().chain(ext.iter()).all(|&c| is_short_name_char(c)) {
+        return None;
+    }
+    
+    let case_flag = |part: &[u8], flag: u8| -> Option<u8> {
+        let has_lower = part.iter().any(|c| c.is_ascii_lowercase());
+        let has_upper = part.iter().any(|c| c.is_ascii_uppercase());
+        match (has_lower, has_upper) {
+            (true, true) => None,
+            (true, false) => Some(flag),
+            _ => Some(0),
+        }
+    };
+    let flags = case_flag(base, NT_LOWERCASE_BASE)? | case_flag(ext, NT_LOWERCASE_EXT)?;
+    
+    let mut short = [b' '; 11];
+    for (i, &c) in base.iter().enumerate() {
+        short[i] = c.to_ascii_uppercase();
+    }
+    for (i, &c) in ext.iter().enumerate() {
+        short[8 + i] = c.to_ascii_uppercase();
+    }
+    if short[0] == DELETED_MARKER {
+        short[0] = KANJI_ESCAPE;
+    }
+    
+    Some((short, flags))
+}
+
+/// 長いファイル名から一意な8.3短縮名を生成（"~N"の数値末尾を付与）
+///
+/// `exists`は短縮名が既にディレクトリ内に存在するかを返す
+pub fn generate_short_name(long_name: &str, exists: impl Fn(&[u8; 11]) -> bool) -> FsResult<[u8; 11]> {
+    // 大文字化するだけで8.3名になる場合は数値末尾を付けない（"ReadMe.txt" → "README.TXT"）
+    if let Some((short, _)) = exact_short_name(&long_name.to_ascii_uppercase()) {
+        if !exists(&short) {
+            return Ok(short);
+        }
+    }
+    
+    // 基本名は先頭のドットを除いた最後のドットより前、拡張子はその後ろ
+    let trimmed = long_name.trim_start_matches('.');
+    let (base_part, ext_part) = match trimmed.rfind('.') {
+        Some(pos) => (&trimmed[..pos], &trimmed[pos + 1..]),
+        None => (trimmed, ""),
+    };
+    
+    let convert = |part: &str| -> Vec<u8> {
+        part.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(|c| {
+                let upper = c.to_ascii_uppercase();
+                if upper.is_ascii() && is_short_name_char(upper as u8) {
+                    upper as u8
+                } else {
+                    b'_'
+                }
+            })
+            .collect()
+    };
+    
+    let mut base = convert(base_part);
+    let ext = convert(ext_part);
+    if base.is_empty() {
+        base.push(b'_');
+    }
+    
+    let mut short = [b' '; 11];
+    for (i, &c) in ext.iter().take(3).enumerate() {
+        short[8 + i] = c;
+    }
+    
+    for n in 1u32..1_000_000 {
+        let tail = format!("~{}", n);
+        let keep = core::cmp::min(base.len(), 8 - tail.len());
+        short[..8].fill(b' ');
+        short[..keep].copy_from_slice(&base[..keep]);
+        short[keep..keep + tail.len()].copy_from_slice(tail.as_bytes());
+        if short[0] == DELETED_MARKER {
+            short[0] = KANJI_ESCAPE;
+        }
+        
+        if !exists(&short) {
+            return Ok(short);
+        }
+    }
+    
+    Err(FsError::AlreadyExists)
+}
+
+/// 日付を年月日に分解した暦日からUNIX日数を計算
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// UNIX日数から暦日（年, 月, 日）を計算
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// FAT日付/時刻をUNIX時刻に変換（FATはローカル時刻だがUTCとして扱う）
+pub fn fat_to_unix(date: u16, time: u16) -> u64 {
+    if date == 0 {
+        return 0;
+    }
+    
+    let year = 1980 + (date >> 9) as i64;
+    let month = ((date >> 5) & 0x0F).clamp(1, 12) as i64;
+    let day = (date & 0x1F).max(1) as i64;
+    let hours = (time >> 11) as i64;
+    let minutes = ((time >> 5) & 0x3F) as i64;
+    let seconds = (time & 0x1F) as i64 * 2;
+    
+    let days = days_from_civil(year, month, day);
+    (days * 86400 + hours * 3600 + minutes * 60 + seconds).max(0) as u64
+}
+
+/// UNIX時刻をFAT日付/時刻/10ミリ秒単位に変換
+pub fn unix_to_fat(timestamp: u64) -> (u16, u16, u8) {
+    // FATは1980年から2107年までしか表現できない
+    let timestamp = timestamp.max(FAT_EPOCH);
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+    let (year, month, day) = civil_from_days(days);
+    if year > 2107 {
+        return (0xFF9F, 0xBF7D, 199);
+    }
+    
+    let date = (((year - 1980) as u16) << 9) | ((month as u16) << 5) | day as u16;
+    let time = (((secs / 3600) as u16) << 11) | ((((secs / 60) % 60) as u16) << 5) | ((secs % 60) / 2) as u16;
+    let tenth = ((secs % 2) * 100) as u8;
+    (date, time, tenth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn lfn_roundtrip_with_checksum() {
+        let short = *b"LONGFI~1TXT";
+        let checksum = lfn_checksum(&short);
+        let lfn = build_lfn_entries("Long File Name.txt", checksum);
+        assert_eq!(lfn.len(), 2);
+        assert_eq!(lfn[0][0], 0x42);
+        assert_eq!(lfn[1][0], 0x01);
+        
+        let mut data = Vec::new();
+        for entry in &lfn {
+            data.extend_from_slice(entry);
+        }
+        data.extend_from_slice(&ShortEntry::new(short, attr::ARCHIVE, 0).to_bytes());
+        
+        let entries = parse_directory(&data, true);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "Long File Name.txt");
+        assert_eq!((entries[0].first_slot, entries[0].short_slot), (0, 2));
+        
+        // 短縮名が書き換わってチェックサムが合わなければLFNは無視される
+        data[64] = b'X';
+        let entries = parse_directory(&data, true);
+        assert_eq!(entries[0].name, "XONGFI~1.TXT");
+        assert!(!entries[0].has_long_name());
+    }
+    
+    #[test]
+    fn short_name_generation() {
+        assert_eq!(exact_short_name("readme.txt"), Some((*b"README  TXT", NT_LOWERCASE_BASE | NT_LOWERCASE_EXT)));
+        assert_eq!(exact_short_name("README"), Some((*b"README     ", 0)));
+        assert_eq!(exact_short_name("ReadMe.txt"), None);
+        assert_eq!(exact_short_name("toolongname.c"), None);
+        assert_eq!(generate_short_name("ReadMe.txt", |_| false).unwrap(), *b"README  TXT");
+        assert_eq!(generate_short_name("ReadMe.txt", |s| s == b"README  TXT").unwrap(), *b"README~1TXT");
+        
+        let taken = *b"PROGRA~1   ";
+        assert_eq!(generate_short_name("Program Files", |s| s == &taken).unwrap(), *b"PROGRA~2   ");
+        assert_eq!(generate_short_name(".bashrc", |_| false).unwrap(), *b"BASHRC~1   ");
+        assert_eq!(generate_short_name("a+b.tar.gz", |_| false).unwrap(), *b"A_BTAR~1GZ ");
+    }
+    
+    #[test]
+    fn fat_timestamps() {
+        // 2024-02-29 13:45:58 UTC
+        let ts = 1_709_214_358;
+        let (date, time, tenth) = unix_to_fat(ts);
+        assert_eq!(date, ((2024 - 1980) << 9) | (2 << 5) | 29);
+        assert_eq!(time, (13 << 11) | (45 << 5) | 29);
+        assert_eq!(tenth, 0);
+        assert_eq!(fat_to_unix(date, time), ts);
+    }
+}
@@ -0,0 +1,577 @@
+// FAT ファイル/ディレクトリハンドル実装
+//
+// ファイルデータの読み書き・切り詰め、ディレクトリエントリの作成/削除/名前変更
+
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, Metadata, Permissions, FileHandle, DirHandle, DirEntry};
+use super::dir_entry::{self, attr, ShortEntry, DIR_ENTRY_SIZE};
+use super::{FatVolume, FatNode, DirRef, EntryLocation, read_bytes, write_bytes};
+
+/// FATファイルの最大サイズ
+const MAX_FILE_SIZE: u64 = 0xFFFF_FFFF;
+
+/// ディレクトリあたりの最大エントリ数（仕様上の上限）
+const MAX_DIR_ENTRIES: usize = 65536;
+
+/// 現在時刻（UNIX秒）
+fn current_time() -> u64 {
+    crate::time::current_time_ns() / 1_000_000_000
+}
+
+impl FatVolume {
+    /// クラスタ列のうち、`index`から物理的に連続している数を返す
+    fn contiguous_run(chain: &[u32], index: usize) -> usize {
+        let mut len = 1;
+        while index + len < chain.len() && chain[index + len] == chain[index] + len as u32 {
+            len += 1;
+        }
+        len
+    }
+    
+    /// ファイルデータを読み込み
+    fn read_file(&self, entry: &ShortEntry, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let size = entry.file_size as u64;
+        if offset >= size {
+            return Ok(0);
+        }
+        
+        let to_read = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        let chain = self.cluster_chain(entry.first_cluster())?;
+        let cluster_size = self.cluster_size as u64;
+        let mut done = 0usize;
+        
+        while done < to_read {
+            let position = offset + done as u64;
+            let index = (position / cluster_size) as usize;
+            let within = position % cluster_size;
+            if index >= chain.len() {
+                log::warn!("FAT: ファイルサイズに対してクラスタチェーンが短すぎます");
+                return Err(FsError::CorruptedFs);
+            }
+            
+            // 連続したクラスタはまとめて読み込む
+            let run = Self::contiguous_run(&chain, index) as u64;
+            let chunk = core::cmp::min((run * cluster_size - within) as usize, to_read - done);
+            let data = read_bytes(&*self.device, self.cluster_offset(chain[index]) + within, chunk)?;
+            buffer[done..done + chunk].copy_from_slice(&data);
+            done += chunk;
+        }
+        
+        Ok(done)
+    }
+    
+    /// 指定範囲のデータを書き込む（必要ならクラスタを追加し、サイズを更新）
+    fn write_range(&self, entry: &mut ShortEntry, data: &[u8], offset: u64) -> FsResult<()> {
+        let end = offset + data.len() as u64;
+        let cluster_size = self.cluster_size as u64;
+        let needed = end.div_ceil(cluster_size) as usize;
+        
+        let mut chain = self.cluster_chain(entry.first_cluster())?;
+        if chain.len() < needed {
+            let added = self.allocate_clusters(needed - chain.len(), chain.last().copied(), false)?;
+            if chain.is_empty() {
+                entry.set_first_cluster(added[0]);
+            }
+            chain.extend(added);
+        }
+        
+        let mut done = 0usize;
+        while done < data.len() {
+            let position = offset + done as u64;
+            let index = (position / cluster_size) as usize;
+            let within = position % cluster_size;
+            let run = Self::contiguous_run(&chain, index) as u64;
+            let chunk = core::cmp::min((run * cluster_size - within) as usize, data.len() - done);
+            write_bytes(&*self.device, self.cluster_offset(chain[index]) + within, &data[done..done + chunk])?;
+            done += chunk;
+        }
+        
+        if end > entry.file_size as u64 {
+            entry.file_size = end as u32;
+        }
+        Ok(())
+    }
+    
+    /// 現在のサイズから`end`までを0で埋める
+    fn zero_fill(&self, entry: &mut ShortEntry, end: u64) -> FsResult<()> {
+        let zeros = vec![0u8; self.cluster_size as usize];
+        while (entry.file_size as u64) < end {
+            let position = entry.file_size as u64;
+            let chunk = core::cmp::min(zeros.len() as u64, end - position) as usize;
+            self.write_range(entry, &zeros[..chunk], position)?;
+        }
+        Ok(())
+    }
+    
+    /// ファイルへ書き込み、短縮名エントリを更新
+    fn write_file(&self, location: &EntryLocation, data: &[u8], offset: u64) -> FsResult<usize> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+        if offset + data.len() as u64 > MAX_FILE_SIZE {
+            return Err(FsError::OverflowError);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let mut entry = self.read_entry(location)?;
+        
+        // 書き込み位置が末尾より後ろなら、間を0で埋める
+        let result = self.zero_fill(&mut entry, offset)
+            .and_then(|_| self.write_range(&mut entry, data, offset));
+        
+        // 途中で容量不足になっても、確保済みのクラスタをエントリから辿れるようにしておく
+        entry.attributes |= attr::ARCHIVE;
+        entry.touch(current_time());
+        self.write_entry(location, &entry)?;
+        
+        result.map(|_| data.len())
+    }
+    
+    /// ファイルサイズを変更
+    fn resize_file(&self, location: &EntryLocation, new_size: u64) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if new_size > MAX_FILE_SIZE {
+            return Err(FsError::OverflowError);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let mut entry = self.read_entry(location)?;
+        
+        let result = if new_size < entry.file_size as u64 {
+            // 切り詰めでは先にサイズを書き込み、その後で不要なクラスタを解放する
+            let keep = new_size.div_ceil(self.cluster_size as u64) as usize;
+            let first = entry.first_cluster();
+            entry.file_size = new_size as u32;
+            if keep == 0 {
+                entry.set_first_cluster(0);
+            }
+            self.write_entry(location, &entry)?;
+            self.truncate_chain(first, keep).map(|_| ())
+        } else {
+            self.zero_fill(&mut entry, new_size)
+        };
+        
+        entry.attributes |= attr::ARCHIVE;
+        entry.touch(current_time());
+        self.write_entry(location, &entry)?;
+        result
+    }
+    
+    /// ディレクトリにエントリ（必要ならLFNエントリ列を含む）を追加
+    ///
+    /// 呼び出し側で`update_lock`を保持していること
+    fn insert_entry(&self, dir: &FatNode, name: &str, mut entry: ShortEntry) -> FsResult<FatNode> {
+        dir_entry::validate_long_name(name)?;
+        
+        let dir_ref = self.dir_ref(dir);
+        let (data, mut segments) = self.read_directory(dir_ref)?;
+        let existing = dir_entry::parse_directory(&data, self.long_names);
+        
+        let lower = name.to_lowercase();
+        if existing.iter().any(|raw| raw.name.to_lowercase() == lower || raw.short.display_name().eq_ignore_ascii_case(name)) {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        // 8.3名で表現できればLFNは作らない。長いファイル名が無効なら大文字化した8.3名のみ許可
+        let exact = if self.long_names {
+            dir_entry::exact_short_name(name)
+        } else {
+            dir_entry::exact_short_name(&name.to_ascii_uppercase())
+        };
+        let lfn = match exact {
+            Some((short, flags)) if !existing.iter().any(|raw| raw.short.name == short) => {
+                entry.name = short;
+                entry.nt_flags = flags;
+                Vec::new()
+            },
+            _ if !self.long_names => return Err(FsError::InvalidData),
+            _ => {
+                entry.name = dir_entry::generate_short_name(name, |candidate| {
+                    existing.iter().any(|raw| &raw.short.name == candidate)
+                })?;
+                entry.nt_flags = 0;
+                dir_entry::build_lfn_entries(name, dir_entry::lfn_checksum(&entry.name))
+            },
+        };
+        
+        // 連続した空きスロットを探す（終端マーカー以降は全て空き）
+        let needed = lfn.len() + 1;
+        let total_slots = data.len() / DIR_ENTRY_SIZE;
+        let end_slot = (0..total_slots)
+            .find(|&slot| data[slot * DIR_ENTRY_SIZE] == dir_entry::END_MARKER)
+            .unwrap_or(total_slots);
+        
+        let mut run_start = 0;
+        let mut run_len = 0;
+        for slot in 0..total_slots {
+            if slot >= end_slot || data[slot * DIR_ENTRY_SIZE] == dir_entry::DELETED_MARKER {
+                if run_len == 0 {
+                    run_start = slot;
+                }
+                run_len += 1;
+                if run_len == needed {
+                    break;
+                }
+            } else {
+                run_len = 0;
+            }
+        }
+        
+        if run_len < needed {
+            // 末尾の空きに続けてクラスタを追加してディレクトリを伸ばす
+            let first = match dir_ref {
+                DirRef::FixedRoot => return Err(FsError::OutOfSpace),
+                DirRef::Chain(first) => first,
+            };
+            if run_len == 0 {
+                run_start = total_slots;
+            }
+            let missing = needed - run_len;
+            if total_slots + missing > MAX_DIR_ENTRIES {
+                return Err(FsError::OutOfSpace);
+            }
+            
+            let per_cluster = self.cluster_size as usize / DIR_ENTRY_SIZE;
+            let count = missing.div_ceil(per_cluster);
+            let last = self.cluster_chain(first)?.last().copied();
+            for cluster in self.allocate_clusters(count, last, true)? {
+                segments.push((self.cluster_offset(cluster), self.cluster_size as usize));
+            }
+        }
+        
+        let short_bytes = entry.to_bytes();
+        for (i, raw) in lfn.iter().chain(core::iter::once(&short_bytes)).enumerate() {
+            let offset = Self::slot_offset(&segments, run_start + i)?;
+            write_bytes(&*self.device, offset, raw)?;
+        }
+        
+        let short_slot = run_start + lfn.len();
+        Ok(FatNode {
+            name: if lfn.is_empty() { entry.display_name() } else { name.to_string() },
+            location: Some(EntryLocation {
+                dir: dir_ref,
+                first_slot: run_start,
+                short_slot,
+                offset: Self::slot_offset(&segments, short_slot)?,
+            }),
+            entry,
+        })
+    }
+    
+    /// エントリのスロット（LFNを含む）を読み込む
+    fn read_slots(&self, location: &EntryLocation) -> FsResult<Vec<(u64, Vec<u8>)>> {
+        let segments = self.dir_segments(location.dir)?;
+        (location.first_slot..=location.short_slot)
+            .map(|slot| {
+                let offset = Self::slot_offset(&segments, slot)?;
+                Ok((offset, read_bytes(&*self.device, offset, DIR_ENTRY_SIZE)?))
+            })
+            .collect()
+    }
+    
+    /// エントリのスロット（LFNを含む）を削除済みにする
+    pub(super) fn remove_slots(&self, location: &EntryLocation) -> FsResult<()> {
+        let segments = self.dir_segments(location.dir)?;
+        for slot in location.first_slot..=location.short_slot {
+            let offset = Self::slot_offset(&segments, slot)?;
+            write_bytes(&*self.device, offset, &[dir_entry::DELETED_MARKER])?;
+        }
+        Ok(())
+    }
+    
+    /// ファイルまたはディレクトリを作成
+    pub(super) fn create_entry(&self, dir: &FatNode, name: &str, attributes: u8) -> FsResult<FatNode> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let now = current_time();
+        let mut entry = ShortEntry::new([b' '; 11], attributes, now);
+        
+        if attributes & attr::DIRECTORY == 0 {
+            return self.insert_entry(dir, name, entry);
+        }
+        
+        // 新しいディレクトリには"."と".."を書き込む
+        let cluster = self.allocate_clusters(1, None, true)?[0];
+        let mut dot = ShortEntry::new(*b".          ", attr::DIRECTORY, now);
+        dot.set_first_cluster(cluster);
+        let mut dotdot = ShortEntry::new(*b"..         ", attr::DIRECTORY, now);
+        dotdot.set_first_cluster(self.parent_cluster_value(dir));
+        
+        let mut header = Vec::with_capacity(DIR_ENTRY_SIZE * 2);
+        header.extend_from_slice(&dot.to_bytes());
+        header.extend_from_slice(&dotdot.to_bytes());
+        entry.set_first_cluster(cluster);
+        
+        let result = write_bytes(&*self.device, self.cluster_offset(cluster), &header)
+            .and_then(|_| self.insert_entry(dir, name, entry));
+        if result.is_err() {
+            self.free_chain(cluster)?;
+        }
+        result
+    }
+    
+    /// エントリを削除（ディレクトリは空の場合のみ）
+    fn remove_entry(&self, dir: &FatNode, name: &str) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let node = self.lookup(dir, name)?;
+        let location = node.location.ok_or(FsError::PermissionDenied)?;
+        
+        if node.is_directory() && !self.list_directory(&node)?.is_empty() {
+            return Err(FsError::NotEmpty);
+        }
+        
+        // エントリを先に消すことで、途中で失敗しても二重参照ではなく未使用クラスタになる
+        self.remove_slots(&location)?;
+        self.free_chain(node.entry.first_cluster())
+    }
+    
+    /// `dir`がクラスタ`ancestor`のディレクトリ自身またはその子孫かどうか
+    fn is_within(&self, dir: &FatNode, ancestor: u32) -> FsResult<bool> {
+        let mut current = dir.clone();
+        for _ in 0..super::MAX_PATH_DEPTH {
+            if current.is_root() {
+                return Ok(false);
+            }
+            if current.entry.first_cluster() == ancestor {
+                return Ok(true);
+            }
+            current = self.parent_of(&current)?;
+        }
+        Err(FsError::CorruptedFs)
+    }
+    
+    /// エントリの名前を変更（`new_path`は`dir`からの相対パスでもよい）
+    fn rename_entry(&self, dir: &FatNode, old_name: &str, new_path: &str) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let node = self.lookup(dir, old_name)?;
+        let location = node.location.ok_or(FsError::PermissionDenied)?;
+        let (new_dir, leaf) = self.resolve_parent(dir.clone(), new_path)?;
+        dir_entry::validate_long_name(leaf)?;
+        
+        if node.is_directory() && self.is_within(&new_dir, node.entry.first_cluster())? {
+            return Err(FsError::InvalidData);
+        }
+        
+        // 既存のターゲットは置き換える（大文字小文字だけの変更なら自分自身）
+        let target = match self.lookup(&new_dir, leaf) {
+            Ok(target) if target.location.map(|l| l.offset) == Some(location.offset) => {
+                if target.name == leaf {
+                    return Ok(());
+                }
+                None
+            },
+            Ok(target) => {
+                match (node.is_directory(), target.is_directory()) {
+                    (true, false) => return Err(FsError::NotDirectory),
+                    (false, true) => return Err(FsError::IsDirectory),
+                    (true, true) if !self.list_directory(&target)?.is_empty() => return Err(FsError::NotEmpty),
+                    _ => {}
+                }
+                Some(target)
+            },
+            Err(FsError::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        
+        // 失敗時に元へ戻せるよう、消す前のスロットを保存しておく
+        let mut saved = self.read_slots(&location)?;
+        if let Some(target_location) = target.as_ref().and_then(|t| t.location) {
+            saved.extend(self.read_slots(&target_location)?);
+            self.remove_slots(&target_location)?;
+        }
+        self.remove_slots(&location)?;
+        
+        let moved = match self.insert_entry(&new_dir, leaf, node.entry.clone()) {
+            Ok(moved) => moved,
+            Err(e) => {
+                for (offset, raw) in &saved {
+                    write_bytes(&*self.device, *offset, raw)?;
+                }
+                return Err(e);
+            },
+        };
+        
+        // 別のディレクトリへ移動したディレクトリは".."を付け替える
+        if moved.is_directory() && self.dir_ref(&new_dir) != location.dir {
+            let offset = self.cluster_offset(moved.entry.first_cluster()) + DIR_ENTRY_SIZE as u64;
+            let mut dotdot = ShortEntry::parse(&read_bytes(&*self.device, offset, DIR_ENTRY_SIZE)?);
+            if &dotdot.name == b"..         " {
+                dotdot.set_first_cluster(self.parent_cluster_value(&new_dir));
+                write_bytes(&*self.device, offset, &dotdot.to_bytes())?;
+            }
+        }
+        
+        if let Some(target) = target {
+            self.free_chain(target.entry.first_cluster())?;
+        }
+        Ok(())
+    }
+}
+
+/// FATファイルハンドル
+pub struct FatFileHandle {
+    /// 所属ボリューム
+    volume: Arc<FatVolume>,
+    /// 短縮名エントリの位置
+    location: EntryLocation,
+    /// 書き込み可能か
+    writable: bool,
+}
+
+impl FatFileHandle {
+    /// 新しいファイルハンドルを作成
+    pub(super) fn new(volume: Arc<FatVolume>, node: FatNode, writable: bool) -> Self {
+        let location = node.location.expect("ファイルはルート以外のエントリを持つ");
+        Self { volume, location, writable }
+    }
+    
+    /// 最新の状態でノードを構築
+    fn node(&self) -> FsResult<FatNode> {
+        Ok(FatNode {
+            name: "".to_string(),
+            entry: self.volume.read_entry(&self.location)?,
+            location: Some(self.location),
+        })
+    }
+}
+
+impl FileHandle for FatFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let entry = self.volume.read_entry(&self.location)?;
+        self.volume.read_file(&entry, buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.write_file(&self.location, buffer, offset)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.volume.sync()
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.volume.read_entry(&self.location)?.file_size as u64)
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.resize_file(&self.location, new_size)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.node_metadata(&self.node()?)
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        self.writable && !self.volume.read_only
+    }
+}
+
+/// FATディレクトリハンドル
+pub struct FatDirHandle {
+    /// 所属ボリューム
+    volume: Arc<FatVolume>,
+    /// 対象ディレクトリノード
+    node: FatNode,
+}
+
+impl FatDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<FatVolume>, node: FatNode) -> Self {
+        Self { volume, node }
+    }
+}
+
+impl DirHandle for FatDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        Ok(self.volume.list_directory(&self.node)?
+            .into_iter()
+            .map(|child| DirEntry {
+                inode: child.inode(),
+                file_type: child.file_type(),
+                name: child.name,
+            })
+            .collect())
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let child = self.volume.lookup(&self.node, name)?;
+        Ok(DirEntry {
+            inode: child.inode(),
+            file_type: child.file_type(),
+            name: child.name,
+        })
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        let mut attributes = attr::ARCHIVE;
+        if !permissions.write {
+            attributes |= attr::READ_ONLY;
+        }
+        
+        let node = self.volume.create_entry(&self.node, name, attributes)?;
+        Ok(Arc::new(FatFileHandle::new(self.volume.clone(), node, true)))
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        let mut attributes = attr::DIRECTORY;
+        if !permissions.write {
+            attributes |= attr::READ_ONLY;
+        }
+        
+        self.volume.create_entry(&self.node, name, attributes)?;
+        Ok(())
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        self.volume.remove_entry(&self.node, name)
+    }
+    
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        self.volume.rename_entry(&self.node, old_name, new_name)
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        // FATにはシンボリックリンクを表現する手段がない
+        Err(FsError::NotSupported)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.node_metadata(&self.node)
+    }
+}
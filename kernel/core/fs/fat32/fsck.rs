@@ -0,0 +1,361 @@
+// FAT 整合性チェック
+//
+// FATコピーの不一致、クラスタチェーンの交差/破損、どこからも参照されないクラスタ、
+// FSInfoの空きクラスタ数を検査し、修復モードではそれぞれを修正する
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FsckProblem, FsckReport};
+use super::cluster::{end_of_chain, is_bad_cluster, is_end_of_chain, FREE_CLUSTER};
+use super::{dir_entry, read_bytes, DirRef, FatNode, FatVolume};
+
+/// 所有者のいないクラスタ
+const NO_OWNER: u32 = u32::MAX;
+
+/// チェーンをたどった結果
+enum ChainEnd {
+    /// 終端まで正常
+    Complete,
+    /// 範囲外の値、空き/不良クラスタ、循環で途切れた
+    Broken,
+    /// 別の所有者のクラスタに合流した（クラスタ番号と所有者）
+    CrossLinked(u32, u32),
+}
+
+/// チェック中の状態
+struct Checker<'a> {
+    /// 対象ボリューム
+    volume: &'a FatVolume,
+    /// 修復するか
+    repair: bool,
+    /// 使用中のFAT（修復した内容も反映する）
+    fat: Vec<u32>,
+    /// クラスタごとの所有者（`paths`の添字）
+    owner: Vec<u32>,
+    /// 所有者のパス（0はルートディレクトリ）
+    paths: Vec<String>,
+    /// 結果
+    report: FsckReport,
+}
+
+impl Checker<'_> {
+    /// `first`から始まるチェーンを`id`の所有としてたどり、所有できたクラスタ列を返す
+    fn claim(&mut self, first: u32, id: u32) -> (Vec<u32>, ChainEnd) {
+        let fat_type = self.volume.fat_type;
+        let mut chain = Vec::new();
+        let mut cluster = first;
+        loop {
+            if !self.volume.is_valid_cluster(cluster) || self.owner[cluster as usize] == id {
+                return (chain, ChainEnd::Broken);
+            }
+            let owner = self.owner[cluster as usize];
+            if owner != NO_OWNER {
+                return (chain, ChainEnd::CrossLinked(cluster, owner));
+            }
+            // 参照されているクラスタ自体が空きや不良になっている
+            let next = self.fat[cluster as usize];
+            if next == FREE_CLUSTER || is_bad_cluster(fat_type, next) {
+                return (chain, ChainEnd::Broken);
+            }
+            
+            self.owner[cluster as usize] = id;
+            chain.push(cluster);
+            if is_end_of_chain(fat_type, next) {
+                return (chain, ChainEnd::Complete);
+            }
+            cluster = next;
+        }
+    }
+    
+    /// 所有できたクラスタの直後でチェーンを終端する
+    fn terminate(&mut self, chain: &[u32]) -> FsResult<()> {
+        if let Some(&last) = chain.last() {
+            let eoc = end_of_chain(self.volume.fat_type);
+            if self.fat[last as usize] != eoc {
+                self.volume.set_fat_entry(last, eoc)?;
+                self.fat[last as usize] = eoc;
+            }
+        }
+        Ok(())
+    }
+    
+    /// エントリのチェーンを検査（たどるべきディレクトリなら所有したクラスタ列を返す）
+    fn check_node(&mut self, node: &FatNode, path: String) -> FsResult<Option<Vec<u32>>> {
+        self.report.files += 1;
+        let id = self.paths.len() as u32;
+        self.paths.push(path.clone());
+        
+        let first = node.entry.first_cluster();
+        let is_dir = node.is_directory();
+        let (chain, end) = if first == FREE_CLUSTER {
+            (Vec::new(), if is_dir { ChainEnd::Broken } else { ChainEnd::Complete })
+        } else {
+            self.claim(first, id)
+        };
+        let capacity = chain.len() as u64 * self.volume.cluster_size as u64;
+        
+        let problem = match end {
+            ChainEnd::CrossLinked(cluster, other) => Some(FsckProblem::CrossLinked {
+                cluster,
+                paths: vec![self.paths[other as usize].clone(), path],
+            }),
+            ChainEnd::Broken => Some(FsckProblem::BrokenChain { path }),
+            // チェーンがサイズに足りない
+            ChainEnd::Complete if !is_dir && node.entry.file_size as u64 > capacity => {
+                Some(FsckProblem::BrokenChain { path })
+            },
+            ChainEnd::Complete => None,
+        };
+        let Some(problem) = problem else {
+            return Ok(is_dir.then_some(chain));
+        };
+        
+        if self.repair {
+            self.terminate(&chain)?;
+            let location = node.location.as_ref().ok_or(FsError::CorruptedFs)?;
+            if is_dir && chain.is_empty() {
+                self.volume.remove_slots(location)?;
+            } else if !is_dir {
+                let mut entry = node.entry.clone();
+                if chain.is_empty() {
+                    entry.set_first_cluster(FREE_CLUSTER);
+                }
+                entry.file_size = core::cmp::min(entry.file_size as u64, capacity) as u32;
+                self.volume.write_entry(location, &entry)?;
+            }
+        }
+        self.report.record(problem, self.repair);
+        
+        Ok((is_dir && !chain.is_empty()).then_some(chain))
+    }
+    
+    /// ルートからディレクトリツリーをたどり、すべてのチェーンの所有者を決める
+    fn walk(&mut self) -> FsResult<()> {
+        let volume = self.volume;
+        let root = volume.root_node();
+        let root_ref = volume.dir_ref(&root);
+        self.paths.push(String::from("/"));
+        
+        let root_segments = match root_ref {
+            DirRef::FixedRoot => volume.dir_segments(root_ref)?,
+            DirRef::Chain(first) => {
+                let (chain, end) = self.claim(first, 0);
+                if !matches!(end, ChainEnd::Complete) {
+                    // ルートは削除できないので、読める範囲で切り詰める
+                    let repaired = self.repair && !chain.is_empty();
+                    if repaired {
+                        self.terminate(&chain)?;
+                    }
+                    self.report.record(FsckProblem::BrokenChain { path: String::from("/") }, repaired);
+                }
+                self.segments(&chain)
+            },
+        };
+        
+        // 交差したチェーンは先に所有した側に残るため、ディレクトリの循環は起こらない
+        let mut pending = vec![(root_ref, root_segments, String::from("/"))];
+        while let Some((dir, segments, dir_path)) = pending.pop() {
+            let mut data = Vec::with_capacity(segments.iter().map(|s| s.1).sum());
+            for &(offset, len) in &segments {
+                data.extend_from_slice(&read_bytes(&*volume.device, offset, len)?);
+            }
+            
+            for raw in dir_entry::parse_directory(&data, volume.long_names) {
+                if raw.short.is_dot_entry() {
+                    continue;
+                }
+                let node = FatVolume::make_node(dir, &segments, raw)?;
+                let path = if dir_path == "/" { format!("/{}", node.name) } else { format!("{}/{}", dir_path, node.name) };
+                if let Some(chain) = self.check_node(&node, path.clone())? {
+                    pending.push((DirRef::Chain(chain[0]), self.segments(&chain), path));
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// クラスタ列をディレクトリの構成領域に変換
+    fn segments(&self, chain: &[u32]) -> Vec<(u64, usize)> {
+        chain.iter()
+            .map(|&cluster| (self.volume.cluster_offset(cluster), self.volume.cluster_size as usize))
+            .collect()
+    }
+    
+    /// どのファイルからも参照されていないクラスタをチェーン単位で報告（修復時は解放）
+    fn lost_clusters(&mut self) -> FsResult<()> {
+        let fat_type = self.volume.fat_type;
+        let len = self.fat.len();
+        let lost: Vec<bool> = (0..len)
+            .map(|c| c >= 2 && self.owner[c] == NO_OWNER && self.fat[c] != FREE_CLUSTER && !is_bad_cluster(fat_type, self.fat[c]))
+            .collect();
+        let is_lost = |cluster: u32| (cluster as usize) < len && lost[cluster as usize];
+        
+        let mut pointed = vec![false; len];
+        for c in (2..len).filter(|&c| lost[c]) {
+            if is_lost(self.fat[c]) {
+                pointed[self.fat[c] as usize] = true;
+            }
+        }
+        
+        // 他から指されていない先頭からたどり、残ったもの（循環）はその後でまとめる
+        let mut seen = vec![false; len];
+        let mut groups = Vec::new();
+        for heads_only in [true, false] {
+            for c in 2..len {
+                if !lost[c] || seen[c] || (heads_only && pointed[c]) {
+                    continue;
+                }
+                let mut count = 0;
+                let mut cluster = c as u32;
+                while is_lost(cluster) && !seen[cluster as usize] {
+                    seen[cluster as usize] = true;
+                    count += 1;
+                    cluster = self.fat[cluster as usize];
+                }
+                groups.push((c as u32, count));
+            }
+        }
+        
+        if self.repair {
+            for c in (2..len).filter(|&c| lost[c]) {
+                self.volume.set_fat_entry(c as u32, FREE_CLUSTER)?;
+                self.fat[c] = FREE_CLUSTER;
+            }
+        }
+        for (first, count) in groups {
+            self.report.record(FsckProblem::LostClusters { first, count }, self.repair);
+        }
+        
+        Ok(())
+    }
+}
+
+impl FatVolume {
+    /// ボリュームの整合性をチェック（`repair`なら見つかった問題を修復する）
+    ///
+    /// 更新ロックと割り当て状態を保持したまま行うため、その間の作成/削除/拡張は待たされる
+    pub(super) fn check(&self, repair: bool) -> FsResult<FsckReport> {
+        if repair && self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let mut alloc = self.alloc.lock();
+        let sb = &self.superblock;
+        let active = sb.active_fat();
+        let fat = self.read_fat_table(active)?;
+        let mut checker = Checker {
+            volume: self,
+            repair,
+            owner: vec![NO_OWNER; fat.len()],
+            fat,
+            paths: Vec::new(),
+            report: FsckReport::default(),
+        };
+        
+        // ミラーリング中はすべてのコピーが一致しているはず（先頭2エントリは予約値なので比べない）
+        if sb.fat_mirroring() {
+            for copy in (0..sb.num_fats).filter(|&copy| copy != active) {
+                let other = self.read_fat_table(copy)?;
+                let entries = (2..checker.fat.len()).filter(|&c| checker.fat[c] != other[c]).count() as u32;
+                if entries > 0 {
+                    if repair {
+                        self.copy_fat(active, copy)?;
+                    }
+                    checker.report.record(FsckProblem::FatCopyMismatch { copy, entries }, repair);
+                }
+            }
+        }
+        
+        checker.walk()?;
+        checker.lost_clusters()?;
+        
+        let actual = (2..checker.fat.len()).filter(|&c| checker.fat[c] == FREE_CLUSTER).count() as u32;
+        if let Some(recorded) = alloc.free_count.filter(|&recorded| recorded != actual) {
+            if repair {
+                alloc.free_count = Some(actual);
+                self.write_fs_info(*alloc)?;
+            }
+            checker.report.record(FsckProblem::FreeCount { group: None, recorded: recorded as u64, actual: actual as u64 }, repair);
+        }
+        
+        let mut report = checker.report;
+        report.used_blocks = checker.owner.iter().filter(|&&owner| owner != NO_OWNER).count() as u64;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use super::super::super::vfs::BlockDevice;
+    use super::super::super::memory_disk::MemoryDisk;
+    use super::super::dir_entry::{attr, ShortEntry};
+    use super::super::superblock::FatType;
+    use super::super::write_bytes;
+    
+    /// 短縮名エントリを作成
+    fn entry(name: &[u8; 11], attributes: u8, first: u32, size: u32) -> [u8; 32] {
+        let mut entry = ShortEntry::new(*name, attributes, 0);
+        entry.set_first_cluster(first);
+        entry.file_size = size;
+        entry.to_bytes()
+    }
+    
+    #[test]
+    fn finds_and_repairs_crosslinks_lost_clusters_and_fat_copies() {
+        // FAT12: 予約1 + FAT1セクタ×2 + ルート16エントリ、データはセクタ4（クラスタ2）から
+        let mut image = vec![0u8; 64 * 512];
+        image[11..13].copy_from_slice(&512u16.to_le_bytes());
+        image[13] = 1;
+        image[14..16].copy_from_slice(&1u16.to_le_bytes());
+        image[16] = 2;
+        image[17..19].copy_from_slice(&16u16.to_le_bytes());
+        image[19..21].copy_from_slice(&64u16.to_le_bytes());
+        image[21] = 0xF8;
+        image[22..24].copy_from_slice(&1u16.to_le_bytes());
+        image[510..512].copy_from_slice(&0xAA55u16.to_le_bytes());
+        let device: Arc<dyn BlockDevice> = Arc::new(MemoryDisk::new(image));
+        let volume = FatVolume::open("mem", device.clone(), FatType::Fat12, true, false).unwrap();
+        
+        // A: 2→3、B: 4→3（Aと交差）、SUB: 5、SUB/C: 空きクラスタ6を指す、10→11はどこからも参照されない
+        let eoc = end_of_chain(FatType::Fat12);
+        for (cluster, value) in [(2, 3), (3, eoc), (4, 3), (5, eoc), (10, 11), (11, eoc)] {
+            volume.set_fat_entry(cluster, value).unwrap();
+        }
+        let root = 3 * 512;
+        write_bytes(&*device, root, &entry(b"A       TXT", attr::ARCHIVE, 2, 1024)).unwrap();
+        write_bytes(&*device, root + 32, &entry(b"B       TXT", attr::ARCHIVE, 4, 1024)).unwrap();
+        write_bytes(&*device, root + 64, &entry(b"SUB        ", attr::DIRECTORY, 5, 0)).unwrap();
+        let sub = volume.cluster_offset(5);
+        write_bytes(&*device, sub, &entry(b".          ", attr::DIRECTORY, 5, 0)).unwrap();
+        write_bytes(&*device, sub + 32, &entry(b"..         ", attr::DIRECTORY, 0, 0)).unwrap();
+        write_bytes(&*device, sub + 64, &entry(b"C       TXT", attr::ARCHIVE, 6, 100)).unwrap();
+        // 2つ目のFATだけクラスタ20を使用中にする
+        write_bytes(&*device, 2 * 512 + 30, &[0xFF, 0x0F]).unwrap();
+        
+        let expected = vec![
+            FsckProblem::FatCopyMismatch { copy: 1, entries: 1 },
+            FsckProblem::CrossLinked { cluster: 3, paths: vec![String::from("/A.TXT"), String::from("/B.TXT")] },
+            FsckProblem::BrokenChain { path: String::from("/SUB/C.TXT") },
+            FsckProblem::LostClusters { first: 10, count: 2 },
+        ];
+        let report = volume.check(false).unwrap();
+        assert_eq!(report.problems, expected);
+        assert_eq!((report.unrepaired, report.files, report.used_blocks), (4, 4, 4));
+        
+        let report = volume.check(true).unwrap();
+        assert_eq!(report.problems, expected);
+        assert_eq!(report.unrepaired, 0);
+        assert!(volume.check(false).unwrap().is_clean());
+        
+        // Bは交差する手前で切り詰められ、Cは空になる
+        let b = volume.resolve("/B.TXT").unwrap();
+        assert_eq!((volume.cluster_chain(4).unwrap(), b.entry.file_size), (vec![4], 512));
+        let c = volume.resolve("/SUB/C.TXT").unwrap();
+        assert_eq!((c.entry.first_cluster(), c.entry.file_size), (0, 0));
+        assert_eq!(volume.get_fat_entry(10).unwrap(), FREE_CLUSTER);
+    }
+}
@@ -1,543 +1,661 @@
 // FAT ファイルシステム実装
 //
-// FAT12/FAT16/FAT32/VFAT ファイルシステムの実装
+// FAT12/FAT16/FAT32/VFAT ファイルシステムの実装（読み書き、VFAT長いファイル名対応）
 
 mod superblock;
 mod dir_entry;
 mod file;
 mod cluster;
+mod fsck;
 
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
-use spin::RwLock;
-use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, OpenMode, Filesystem};
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, FileType, Metadata, FsStats, FsckReport, Permissions, FileHandle, DirHandle, OpenMode, Filesystem};
+use super::vfs::BlockDevice;
 use self::superblock::{FatSuperblock, FatType};
+use self::cluster::AllocState;
+use self::dir_entry::{attr, ShortEntry, RawDirEntry, DIR_ENTRY_SIZE, MAX_LONG_NAME};
+use self::file::{FatFileHandle, FatDirHandle};
 
-/// FAT12/16/32/VFAT 共通のベースファイルシステム
-pub struct FatFilesystem {
-    /// ファイルシステム名
-    name: String,
-    /// FATタイプ
-    fat_type: FatType,
-    /// マウントされたデバイス
-    devices: RwLock<BTreeMap<String, Arc<dyn super::vfs::BlockDevice>>>,
-    /// スーパーブロックキャッシュ
-    superblocks: RwLock<BTreeMap<String, FatSuperblock>>,
+/// パス解決時のディレクトリ階層上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// デバイスからバイト単位で読み込み（ブロック境界をまたいでよい）
+fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+    
+    if data.len() < start + len {
+        return Err(FsError::IoError);
+    }
+    
+    Ok(data[start..start + len].to_vec())
 }
 
-impl FatFilesystem {
-    /// 新しいFATファイルシステムインスタンスを作成
-    pub fn new(name: &str, fat_type: FatType) -> Self {
-        Self {
-            name: name.to_string(),
-            fat_type,
-            devices: RwLock::new(BTreeMap::new()),
-            superblocks: RwLock::new(BTreeMap::new()),
-        }
+/// デバイスへバイト単位で書き込み（端数ブロックは読み込んでから書き戻す）
+fn write_bytes(device: &dyn BlockDevice, offset: u64, data: &[u8]) -> FsResult<()> {
+    if data.is_empty() {
+        return Ok(());
     }
     
-    /// スーパーブロックを読み込み
-    fn read_superblock(&self, device: &dyn super::vfs::BlockDevice) -> FsResult<FatSuperblock> {
-        // ブートセクタを読み込み
-        let data = device.read_block(0)?;
-        FatSuperblock::parse(&data, self.fat_type)
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + data.len() as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
     }
     
-    /// FATタイプを返す
-    pub fn fat_type(&self) -> FatType {
-        self.fat_type
+    let start = (offset - first_block * block_size) as usize;
+    let end = start + data.len();
+    let span = ((last_block - first_block + 1) * block_size) as usize;
+    
+    // ブロック境界に揃っていればそのまま書き込む
+    if start == 0 && end == span {
+        return device.write_blocks(first_block, data);
+    }
+    
+    let mut buffer = device.read_blocks(first_block, last_block - first_block + 1)?;
+    if buffer.len() < span {
+        return Err(FsError::IoError);
     }
+    buffer[start..end].copy_from_slice(data);
+    device.write_blocks(first_block, &buffer[..span])
 }
 
-impl Filesystem for FatFilesystem {
-    fn name(&self) -> &str {
-        &self.name
+/// ディレクトリの格納場所
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirRef {
+    /// FAT12/16の固定ルートディレクトリ領域
+    FixedRoot,
+    /// クラスタチェーン上のディレクトリ（先頭クラスタ）
+    Chain(u32),
+}
+
+/// ディレクトリエントリの位置
+#[derive(Debug, Clone, Copy)]
+struct EntryLocation {
+    /// エントリを含むディレクトリ
+    dir: DirRef,
+    /// 最初のスロット（LFNがあればその先頭）
+    first_slot: usize,
+    /// 短縮名エントリのスロット
+    short_slot: usize,
+    /// 短縮名エントリのデバイス上バイトオフセット
+    offset: u64,
+}
+
+/// 解決済みのファイル/ディレクトリ
+#[derive(Debug, Clone)]
+struct FatNode {
+    /// 表示名
+    name: String,
+    /// 短縮名エントリ
+    entry: ShortEntry,
+    /// エントリの位置（ルートディレクトリはNone）
+    location: Option<EntryLocation>,
+}
+
+impl FatNode {
+    /// ディレクトリかどうか
+    fn is_directory(&self) -> bool {
+        self.entry.is_directory()
     }
     
-    fn init(&self) -> FsResult<()> {
-        Ok(())
+    /// ルートディレクトリかどうか
+    fn is_root(&self) -> bool {
+        self.location.is_none()
     }
     
-    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
-        // デバイスが既にマウントされているかチェック
-        {
-            let devices = self.devices.read();
-            if devices.contains_key(device) {
-                return Err(FsError::AlreadyExists);
-            }
+    /// アイノード番号（FATにはアイノードがないため、エントリ位置から生成）
+    fn inode(&self) -> u64 {
+        match &self.location {
+            Some(location) => location.offset / DIR_ENTRY_SIZE as u64,
+            None => 1,
         }
+    }
+    
+    /// ファイルタイプ
+    fn file_type(&self) -> FileType {
+        if self.is_directory() {
+            FileType::Directory
+        } else {
+            FileType::Regular
+        }
+    }
+}
+
+/// マウントされたFATボリューム
+struct FatVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// ブートセクタ
+    superblock: FatSuperblock,
+    /// FATエントリ幅（FAT12/FAT16/FAT32）
+    fat_type: FatType,
+    /// クラスタサイズ（バイト）
+    cluster_size: u32,
+    /// VFAT長いファイル名を使用するか
+    long_names: bool,
+    /// 読み取り専用マウントか
+    read_only: bool,
+    /// クラスタ割り当て状態（FSInfoと同期）
+    alloc: Mutex<AllocState>,
+    /// ディレクトリ/ファイル更新の排他ロック
+    update_lock: Mutex<()>,
+}
+
+impl FatVolume {
+    /// デバイスからボリュームを構築
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, expected: FatType, long_names: bool, read_only: bool) -> FsResult<Self> {
+        let boot = read_bytes(&*device, 0, 512)?;
+        let superblock = FatSuperblock::parse(&boot, expected)?;
+        let fat_type = superblock.fat_type();
         
-        // ブロックデバイスをオープン
-        let block_device = super::vfs::open_block_device(device)?;
-        
-        // スーパーブロックを読み込み
-        let superblock = self.read_superblock(&*block_device)?;
+        if !expected.is_compatible_with(fat_type) {
+            log::warn!("マウントしようとしているFATタイプ ({:?}) が期待するタイプ ({:?}) と一致しません", fat_type, expected);
+            return Err(FsError::UnsupportedFeature);
+        }
         
-        // FATシグネチャをチェック
-        if !superblock.is_valid_signature() {
-            return Err(FsError::BadMagic);
+        if fat_type == FatType::Fat32 && superblock.fs_version != 0 {
+            log::warn!("FAT32: 未対応のバージョン {:#x}", superblock.fs_version);
+            return Err(FsError::UnsupportedVersion);
         }
         
-        // 期待するFATタイプかチェック
-        if superblock.fat_type() != self.fat_type {
-            log::warn!("マウントしようとしているFATタイプ ({:?}) が期待するタイプ ({:?}) と一致しません", 
-                      superblock.fat_type(), self.fat_type);
-                      
-            if !self.fat_type.is_compatible_with(superblock.fat_type()) {
-                return Err(FsError::UnsupportedFeature);
-            }
+        let volume_bytes = superblock.total_size();
+        if volume_bytes > device.total_blocks() * device.block_size() {
+            log::warn!("FAT: ボリューム ({}バイト) がデバイスより大きいです", volume_bytes);
+            return Err(FsError::CorruptedFs);
         }
         
-        // キャッシュに保存
-        {
-            let mut devices = self.devices.write();
-            devices.insert(device.to_string(), block_device);
-            
-            let mut superblocks = self.superblocks.write();
-            superblocks.insert(device.to_string(), superblock);
+        let mut volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            cluster_size: superblock.cluster_size(),
+            fat_type,
+            long_names,
+            read_only,
+            alloc: Mutex::new(AllocState { free_count: None, next_free: 2 }),
+            update_lock: Mutex::new(()),
+            superblock,
+        };
+        
+        if fat_type == FatType::Fat32 && !volume.is_valid_cluster(volume.superblock.root_cluster) {
+            log::warn!("FAT32: ルートクラスタ {} が範囲外です", volume.superblock.root_cluster);
+            return Err(FsError::CorruptedFs);
         }
         
-        log::info!("{}ファイルシステムをマウント: {} -> {}", self.name, device, mount_point);
+        let state = volume.read_fs_info()?;
+        volume.alloc = Mutex::new(state);
         
-        Ok(())
+        log::info!("FAT: ボリューム '{}' ({:?}, {}クラスタ × {}バイト)",
+                  volume.superblock.volume_label_str(), fat_type, volume.superblock.total_clusters(), volume.cluster_size);
+        
+        Ok(volume)
     }
     
-    fn unmount(&self, mount_point: &str) -> FsResult<()> {
-        // マウントされたデバイスを検索
-        let device_name = {
-            let devices = self.devices.read();
-            let mut found_device = None;
-            
-            for (device, _) in devices.iter() {
-                // マウントポイントとデバイス名の対応を確認
-                if device == mount_point {
-                    found_device = Some(device.clone());
-                    break;
-                }
-            }
-            
-            match found_device {
-                Some(device) => device,
-                None => return Err(FsError::NotFound),
-            }
-        };
-
-        // デバイスを取得
-        let device = {
-            let devices = self.devices.read();
-            match devices.get(&device_name) {
-                Some(dev) => dev.clone(),
-                None => return Err(FsError::NotFound),
-            }
-        };
-
-        // キャッシュをフラッシュ
-        self.flush_device_cache(&device)?;
-
-        // FAT領域を同期
-        self.sync_fat_table(&device)?;
-
-        // ファイルシステム情報を同期
-        self.sync_fs_info(&device)?;
-
-        // デバイスを同期
-        device.sync()?;
-
-        // デバイスをマウント解除
-        {
-            let mut devices = self.devices.write();
-            devices.remove(&device_name);
-            
-            let mut superblocks = self.superblocks.write();
-            superblocks.remove(&device_name);
+    /// ルートディレクトリのノード
+    fn root_node(&self) -> FatNode {
+        let mut entry = ShortEntry::new(*b"           ", attr::DIRECTORY, 0);
+        if self.fat_type == FatType::Fat32 {
+            entry.set_first_cluster(self.superblock.root_cluster);
+        }
+        
+        FatNode {
+            name: "/".to_string(),
+            entry,
+            location: None,
         }
-
-        log::info!("{}ファイルシステムをアンマウント: {}", self.name, mount_point);
-        Ok(())
     }
     
-    /// デバイスキャッシュをフラッシュ
-    fn flush_device_cache(&self, device: &Arc<dyn super::vfs::BlockDevice>) -> FsResult<()> {
-        // ブロックキャッシュをフラッシュ
-        device.flush()?;
-        
-        // ディレクトリエントリキャッシュをフラッシュ
-        self.flush_directory_cache(device)?;
-        
-        // FATキャッシュをフラッシュ
-        self.flush_fat_cache(device)?;
-        
-        Ok(())
+    /// ディレクトリノードの格納場所
+    fn dir_ref(&self, node: &FatNode) -> DirRef {
+        if node.is_root() && self.fat_type != FatType::Fat32 {
+            DirRef::FixedRoot
+        } else {
+            DirRef::Chain(node.entry.first_cluster())
+        }
     }
     
-    /// ディレクトリキャッシュをフラッシュ
-    fn flush_directory_cache(&self, device: &Arc<dyn super::vfs::BlockDevice>) -> FsResult<()> {
-        // 完全なディレクトリエントリキャッシュ管理実装
-        let mut dirty_entries = Vec::new();
-        
-        // キャッシュから変更されたエントリを特定
-        for (cluster, entries) in &self.directory_cache {
-            for (index, entry) in entries.iter().enumerate() {
-                if entry.is_dirty() {
-                    dirty_entries.push(DirtyEntry {
-                        cluster: *cluster,
-                        index,
-                        entry: entry.clone(),
-                    });
+    /// ".."エントリに記録するクラスタ番号（ルートは0）
+    fn parent_cluster_value(&self, dir: &FatNode) -> u32 {
+        if dir.is_root() {
+            0
+        } else {
+            dir.entry.first_cluster()
+        }
+    }
+    
+    /// クラスタのデバイス上バイトオフセット
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.superblock.cluster_to_sector(cluster) as u64 * self.superblock.bytes_per_sector as u64
+    }
+    
+    /// ディレクトリを構成する領域（バイトオフセット, 長さ）の一覧
+    fn dir_segments(&self, dir: DirRef) -> FsResult<Vec<(u64, usize)>> {
+        match dir {
+            DirRef::FixedRoot => {
+                let sb = &self.superblock;
+                let offset = sb.root_dir_first_sector() as u64 * sb.bytes_per_sector as u64;
+                Ok(vec![(offset, sb.root_entries as usize * DIR_ENTRY_SIZE)])
+            },
+            DirRef::Chain(first) => {
+                // ルート以外のディレクトリがクラスタ0を指すのは破損
+                if first == 0 {
+                    return Err(FsError::CorruptedFs);
                 }
+                Ok(self.cluster_chain(first)?
+                    .into_iter()
+                    .map(|cluster| (self.cluster_offset(cluster), self.cluster_size as usize))
+                    .collect())
+            },
+        }
+    }
+    
+    /// ディレクトリの内容と構成領域を読み込み
+    fn read_directory(&self, dir: DirRef) -> FsResult<(Vec<u8>, Vec<(u64, usize)>)> {
+        let segments = self.dir_segments(dir)?;
+        let mut data = Vec::with_capacity(segments.iter().map(|s| s.1).sum());
+        for &(offset, len) in &segments {
+            data.extend_from_slice(&read_bytes(&*self.device, offset, len)?);
+        }
+        Ok((data, segments))
+    }
+    
+    /// スロット番号をデバイス上のバイトオフセットに変換
+    fn slot_offset(segments: &[(u64, usize)], slot: usize) -> FsResult<u64> {
+        let mut position = slot * DIR_ENTRY_SIZE;
+        for &(offset, len) in segments {
+            if position < len {
+                return Ok(offset + position as u64);
             }
+            position -= len;
+        }
+        Err(FsError::InvalidData)
+    }
+    
+    /// パース済みエントリからノードを構築
+    fn make_node(dir: DirRef, segments: &[(u64, usize)], raw: RawDirEntry) -> FsResult<FatNode> {
+        Ok(FatNode {
+            location: Some(EntryLocation {
+                dir,
+                first_slot: raw.first_slot,
+                short_slot: raw.short_slot,
+                offset: Self::slot_offset(segments, raw.short_slot)?,
+            }),
+            name: raw.name,
+            entry: raw.short,
+        })
+    }
+    
+    /// ディレクトリの子ノードを列挙（"."と".."を除く）
+    fn list_directory(&self, dir: &FatNode) -> FsResult<Vec<FatNode>> {
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
         }
         
-        // 変更されたエントリのみをディスクに書き込み
-        for dirty_entry in dirty_entries {
-            let sector = self.cluster_to_sector(dirty_entry.cluster) + (dirty_entry.index / ENTRIES_PER_SECTOR);
-            let sector_offset = (dirty_entry.index % ENTRIES_PER_SECTOR) * DIR_ENTRY_SIZE;
-            
-            // セクターを読み込み
-            let mut sector_data = vec![0u8; self.bytes_per_sector];
-            self.device.read_sectors(sector, 1, &mut sector_data)?;
-            
-            // エントリを更新
-            let entry_bytes = dirty_entry.entry.to_bytes();
-            sector_data[sector_offset..sector_offset + DIR_ENTRY_SIZE]
-                .copy_from_slice(&entry_bytes);
-            
-            // セクターを書き込み
-            self.device.write_sectors(sector, 1, &sector_data)?;
-            
-            // キャッシュ内のダーティフラグをクリア
-            if let Some(cached_entries) = self.directory_cache.get_mut(&dirty_entry.cluster) {
-                if let Some(cached_entry) = cached_entries.get_mut(dirty_entry.index) {
-                    cached_entry.clear_dirty();
-                }
-            }
-            
-            log::trace!("ディレクトリエントリ書き込み完了: クラスタ={}, インデックス={}", 
-                       dirty_entry.cluster, dirty_entry.index);
+        let dir_ref = self.dir_ref(dir);
+        let (data, segments) = self.read_directory(dir_ref)?;
+        dir_entry::parse_directory(&data, self.long_names)
+            .into_iter()
+            .filter(|raw| !raw.short.is_dot_entry())
+            .map(|raw| Self::make_node(dir_ref, &segments, raw))
+            .collect()
+    }
+    
+    /// 名前が一致するか（FATは大文字小文字を区別しない。8.3名でも一致させる）
+    fn names_match(node: &FatNode, wanted: &str) -> bool {
+        node.name.to_lowercase() == wanted.to_lowercase()
+            || node.entry.display_name().eq_ignore_ascii_case(wanted)
+    }
+    
+    /// ディレクトリ内の名前を検索
+    fn lookup(&self, dir: &FatNode, name: &str) -> FsResult<FatNode> {
+        if name.encode_utf16().count() > MAX_LONG_NAME {
+            return Err(FsError::NotFound);
         }
         
-        Ok(())
+        self.list_directory(dir)?
+            .into_iter()
+            .find(|node| Self::names_match(node, name))
+            .ok_or(FsError::NotFound)
     }
     
-    /// FATキャッシュをフラッシュ
-    fn flush_fat_cache(&self, device: &Arc<dyn super::vfs::BlockDevice>) -> FsResult<()> {
-        // 完全なFATエントリキャッシュ管理実装
-        let mut dirty_fat_entries = Vec::new();
+    /// ".."エントリから親ディレクトリのノードを取得
+    fn parent_of(&self, dir: &FatNode) -> FsResult<FatNode> {
+        if dir.is_root() {
+            return Ok(self.root_node());
+        }
         
-        // キャッシュから変更されたFATエントリを特定
-        for (cluster, fat_entry) in &self.fat_cache {
-            if fat_entry.is_dirty() {
-                dirty_fat_entries.push(DirtyFatEntry {
-                    cluster: *cluster,
-                    value: fat_entry.value,
-                    timestamp: fat_entry.last_modified,
-                });
-            }
+        let (data, _) = self.read_directory(self.dir_ref(dir))?;
+        let dotdot = dir_entry::parse_directory(&data, false)
+            .into_iter()
+            .find(|raw| &raw.short.name == b"..         ")
+            .ok_or(FsError::CorruptedFs)?;
+        
+        let parent_cluster = dotdot.short.first_cluster();
+        if parent_cluster == 0 || (self.fat_type == FatType::Fat32 && parent_cluster == self.superblock.root_cluster) {
+            return Ok(self.root_node());
         }
         
-        // 変更されたFATエントリのみをディスクに書き込み
-        for dirty_entry in dirty_fat_entries {
-            // FAT1とFAT2の両方を更新
-            for fat_copy in 0..self.num_fats {
-                let fat_offset = dirty_entry.cluster * 4; // FAT32は4バイト/エントリ
-                let fat_sector = self.fat_start_sector + (fat_copy * self.sectors_per_fat) + (fat_offset / self.bytes_per_sector);
-                let sector_offset = fat_offset % self.bytes_per_sector;
-                
-                // FATセクターを読み込み
-                let mut sector_data = vec![0u8; self.bytes_per_sector];
-                self.device.read_sectors(fat_sector, 1, &mut sector_data)?;
-                
-                // FATエントリを更新（下位28ビットのみ使用）
-                let fat_value = dirty_entry.value & 0x0FFFFFFF;
-                let existing_value = u32::from_le_bytes([
-                    sector_data[sector_offset],
-                    sector_data[sector_offset + 1],
-                    sector_data[sector_offset + 2],
-                    sector_data[sector_offset + 3],
-                ]);
-                
-                // 上位4ビットを保持
-                let new_value = (existing_value & 0xF0000000) | fat_value;
-                let new_bytes = new_value.to_le_bytes();
-                
-                sector_data[sector_offset..sector_offset + 4].copy_from_slice(&new_bytes);
-                
-                // セクターを書き込み
-                self.device.write_sectors(fat_sector, 1, &sector_data)?;
-                
-                log::trace!("FATエントリ書き込み完了: クラスタ={}, 値=0x{:08x}, FATコピー={}", 
-                           dirty_entry.cluster, new_value, fat_copy);
+        // 親の名前とエントリ位置は祖父母を走査しないと分からないため、クラスタのみを持つノードを返す
+        let mut entry = ShortEntry::new(*b"..         ", attr::DIRECTORY, 0);
+        entry.set_first_cluster(parent_cluster);
+        Ok(FatNode {
+            name: "..".to_string(),
+            entry,
+            location: Some(EntryLocation {
+                dir: DirRef::Chain(parent_cluster),
+                first_slot: 0,
+                short_slot: 0,
+                offset: self.cluster_offset(parent_cluster),
+            }),
+        })
+    }
+    
+    /// パスをノードに解決
+    fn resolve(&self, path: &str) -> FsResult<FatNode> {
+        self.resolve_from(self.root_node(), path)
+    }
+    
+    /// 指定ディレクトリからの相対パスをノードに解決（先頭が'/'ならルートから）
+    fn resolve_from(&self, start: FatNode, path: &str) -> FsResult<FatNode> {
+        let start = if path.starts_with('/') { self.root_node() } else { start };
+        let mut stack: Vec<FatNode> = vec![start];
+        
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if stack.len() > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
             }
             
-            // キャッシュ内のダーティフラグをクリア
-            if let Some(cached_entry) = self.fat_cache.get_mut(&dirty_entry.cluster) {
-                cached_entry.clear_dirty();
+            match component {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    } else {
+                        stack[0] = self.parent_of(&stack[0])?;
+                    }
+                },
+                name => {
+                    let current = stack.last().ok_or(FsError::NotFound)?;
+                    if !current.is_directory() {
+                        return Err(FsError::NotDirectory);
+                    }
+                    let next = self.lookup(current, name)?;
+                    stack.push(next);
+                },
             }
         }
         
-        // FSInfoセクターを更新（空きクラスタ数など）
-        self.update_fsinfo_sector()?;
-        
-        Ok(())
+        stack.pop().ok_or(FsError::NotFound)
     }
     
-    /// FATテーブルを同期
-    fn sync_fat_table(&self, device: &Arc<dyn super::vfs::BlockDevice>) -> FsResult<()> {
-        // FATテーブルの全エントリをディスクに同期
-        // FAT32では通常2つのFATコピーがあるため、両方を更新
+    /// パスを親ディレクトリと最終要素に分割して解決
+    fn resolve_parent<'a>(&self, start: FatNode, path: &'a str) -> FsResult<(FatNode, &'a str)> {
+        let trimmed = path.trim_end_matches('/');
+        let (parent, leaf) = match trimmed.rfind('/') {
+            Some(0) => ("/", &trimmed[1..]),
+            Some(pos) => (&trimmed[..pos], &trimmed[pos + 1..]),
+            None => ("", trimmed),
+        };
         
-        log::debug!("FATテーブルを同期しました");
-        Ok(())
+        let dir = self.resolve_from(start, parent)?;
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        Ok((dir, leaf))
     }
     
-    /// ファイルシステム情報を同期
-    fn sync_fs_info(&self, device: &Arc<dyn super::vfs::BlockDevice>) -> FsResult<()> {
-        // FSInfoセクターを更新
-        self.update_fsinfo_sector()?;
-        
-        log::debug!("ファイルシステム情報を同期しました");
-        Ok(())
+    /// ディスク上の短縮名エントリを読み直す（削除済みならハンドルは無効）
+    fn read_entry(&self, location: &EntryLocation) -> FsResult<ShortEntry> {
+        let data = read_bytes(&*self.device, location.offset, DIR_ENTRY_SIZE)?;
+        if data[0] == dir_entry::DELETED_MARKER || data[0] == dir_entry::END_MARKER {
+            return Err(FsError::StaleFileHandle);
+        }
+        Ok(ShortEntry::parse(&data))
     }
     
-    /// パスを解析
-    fn parse_path(&self, path: &str) -> FsResult<ParsedPath> {
-        if path.is_empty() || !path.starts_with('/') {
-            return Err(FsError::InvalidPath);
+    /// 短縮名エントリを書き込み
+    fn write_entry(&self, location: &EntryLocation, entry: &ShortEntry) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
         }
+        write_bytes(&*self.device, location.offset, &entry.to_bytes())
+    }
+    
+    /// ノードのメタデータを構築
+    fn node_metadata(&self, node: &FatNode) -> FsResult<Metadata> {
+        let entry = &node.entry;
+        let is_dir = node.is_directory();
         
-        let components: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        // ディレクトリはエントリにサイズを持たないため、クラスタ数から求める
+        let size = if is_dir {
+            self.dir_segments(self.dir_ref(node))?.iter().map(|s| s.1 as u64).sum()
+        } else {
+            entry.file_size as u64
+        };
         
-        Ok(ParsedPath {
-            path: path.to_string(),
-            components,
+        let modified = entry.modified();
+        Ok(Metadata {
+            inode: node.inode(),
+            file_type: node.file_type(),
+            size,
+            uid: 0,
+            gid: 0,
+            permissions: Permissions {
+                read: true,
+                write: !self.read_only && entry.attributes & attr::READ_ONLY == 0,
+                execute: is_dir,
+            },
+            created: if node.is_root() { 0 } else { entry.created() },
+            accessed: if node.is_root() { 0 } else { entry.accessed() },
+            modified: if node.is_root() { 0 } else { modified },
+            links: 1,
+            block_size: self.cluster_size,
+            blocks: size.div_ceil(self.cluster_size as u64),
         })
     }
     
-    /// パスに対応するデバイスを検索
-    fn find_device_for_path(&self, path: &str) -> FsResult<String> {
-        // 簡略化された実装：最初にマウントされたデバイスを返す
-        let devices = self.devices.read();
-        
-        if let Some((device_name, _)) = devices.iter().next() {
-            Ok(device_name.clone())
-        } else {
-            Err(FsError::NotFound)
-        }
+    /// ファイルシステム統計を取得
+    fn stats(&self) -> FsResult<FsStats> {
+        let free = self.free_clusters()? as u64;
+        Ok(FsStats {
+            total_blocks: self.superblock.total_clusters() as u64,
+            free_blocks: free,
+            available_blocks: free,
+            total_nodes: 0,
+            free_nodes: 0,
+            block_size: self.cluster_size,
+            max_filename_length: if self.long_names { MAX_LONG_NAME as u32 } else { 12 },
+        })
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
-
-        // デバイスを検索
-        let device = self.find_device_for_path(path)?;
-        let devices = self.devices.read();
-        let device = match devices.get(&device) {
-            Some(dev) => dev.clone(),
-            None => return Err(FsError::NotFound),
-        };
-
-        // ファイルを開く
-        match device.open_file(&parsed_path.path, mode) {
-            Ok(handle) => Ok(Arc::new(Fat32FileHandle::new(handle, mode))),
-            Err(e) => Err(e),
+    /// FSInfoを書き戻してデバイスを同期
+    fn sync(&self) -> FsResult<()> {
+        if !self.read_only {
+            let state = *self.alloc.lock();
+            self.write_fs_info(state)?;
         }
+        self.device.sync()
     }
-    
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
+}
 
-        // デバイスを検索
-        let device = self.find_device_for_path(path)?;
-        let devices = self.devices.read();
-        let device = match devices.get(&device) {
-            Some(dev) => dev.clone(),
-            None => return Err(FsError::NotFound),
-        };
+/// FAT12/16/32/VFAT 共通のベースファイルシステム
+pub struct FatFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// FATタイプ
+    fat_type: FatType,
+    /// VFAT長いファイル名を使用するか
+    long_names: bool,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<FatVolume>>>,
+}
 
-        // ディレクトリを開く
-        match device.open_directory(&parsed_path.path) {
-            Ok(handle) => Ok(Arc::new(Fat32DirHandle::new(handle))),
-            Err(e) => Err(e),
+impl FatFilesystem {
+    /// 新しいFATファイルシステムインスタンスを作成
+    pub fn new(name: &str, fat_type: FatType) -> Self {
+        Self::new_with_options(name, fat_type, true)
+    }
+    
+    /// 長いファイル名の使用有無を指定してインスタンスを作成
+    pub fn new_with_options(name: &str, fat_type: FatType, long_names: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            fat_type,
+            long_names,
+            volumes: RwLock::new(BTreeMap::new()),
         }
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
-
-        // デバイスを検索
-        let device = self.find_device_for_path(path)?;
-        let devices = self.devices.read();
-        let device = match devices.get(&device) {
-            Some(dev) => dev.clone(),
-            None => return Err(FsError::NotFound),
-        };
+    /// FATタイプを返す
+    pub fn fat_type(&self) -> FatType {
+        self.fat_type
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<FatVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+    
+    /// パスのファイルを作成してハンドルを返す
+    fn create_file(&self, volume: &Arc<FatVolume>, path: &str) -> FsResult<Arc<dyn FileHandle>> {
+        let (dir, leaf) = volume.resolve_parent(volume.root_node(), path)?;
+        let node = volume.create_entry(&dir, leaf, attr::ARCHIVE)?;
+        Ok(Arc::new(FatFileHandle::new(volume.clone(), node, true)))
+    }
+}
 
-        // メタデータを取得
-        device.get_metadata(&parsed_path.path)
+impl Filesystem for FatFilesystem {
+    fn name(&self) -> &str {
+        &self.name
     }
     
-    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
-        // デバイスを検索
-        let devices = self.devices.read();
-        let device = match devices.get(mount_point) {
-            Some(dev) => dev.clone(),
-            None => return Err(FsError::NotFound),
-        };
-        
-        // スーパーブロックを取得
-        let superblocks = self.superblocks.read();
-        let superblock = match superblocks.get(mount_point) {
-            Some(sb) => sb,
-            None => return Err(FsError::NotFound),
-        };
-        
-        // ファイルシステム統計を計算
-        let total_clusters = superblock.total_clusters();
-        let cluster_size = superblock.cluster_size();
-        let total_size = total_clusters as u64 * cluster_size as u64;
-        
-        // 空きクラスタ数を計算
-        let free_clusters = self.count_free_clusters(&device, superblock)?;
-        let free_size = free_clusters as u64 * cluster_size as u64;
-        
-        Ok(FsStats {
-            filesystem_type: self.name.clone(),
-            total_size,
-            free_size,
-            used_size: total_size - free_size,
-            total_inodes: total_clusters as u64, // FATではクラスタ数がinode数に相当
-            free_inodes: free_clusters as u64,
-            block_size: cluster_size as u64,
-            max_filename_length: 255, // VFAT長いファイル名
-        })
+    fn init(&self) -> FsResult<()> {
+        Ok(())
     }
     
-    /// 空きクラスタ数を計算
-    fn count_free_clusters(&self, device: &Arc<dyn super::vfs::BlockDevice>, superblock: &FatSuperblock) -> FsResult<u32> {
-        let fat_start = superblock.fat_start_sector();
-        let fat_size = superblock.fat_size_sectors();
-        let total_clusters = superblock.total_clusters();
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        let mut read_only = false;
+        let mut long_names = self.long_names;
+        for option in options.split(',').map(str::trim) {
+            match option {
+                "ro" => read_only = true,
+                "rw" => read_only = false,
+                "nolfn" => long_names = false,
+                _ => {}
+            }
+        }
         
-        let mut free_count = 0u32;
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = FatVolume::open(device, block_device, self.fat_type, long_names, read_only)?;
         
-        // FATエントリを読み込んで空きクラスタをカウント
-        match self.fat_type {
-            FatType::Fat12 => {
-                // FAT12の場合：12ビットエントリ
-                for cluster in 2..total_clusters + 2 {
-                    let fat_entry = self.read_fat12_entry(device, fat_start, cluster)?;
-                    if fat_entry == 0 {
-                        free_count += 1;
-                    }
-                }
-            },
-            FatType::Fat16 => {
-                // FAT16の場合：16ビットエントリ
-                for cluster in 2..total_clusters + 2 {
-                    let fat_entry = self.read_fat16_entry(device, fat_start, cluster)?;
-                    if fat_entry == 0 {
-                        free_count += 1;
-                    }
-                }
-            },
-            FatType::Fat32 => {
-                // FAT32の場合：32ビットエントリ（下位28ビットのみ使用）
-                for cluster in 2..total_clusters + 2 {
-                    let fat_entry = self.read_fat32_entry(device, fat_start, cluster)?;
-                    if (fat_entry & 0x0FFFFFFF) == 0 {
-                        free_count += 1;
-                    }
-                }
-            },
-        }
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
         
-        Ok(free_count)
+        log::info!("{}ファイルシステムをマウント: {} -> {}", self.name, device, mount_point);
+        Ok(())
     }
     
-    /// FAT12エントリを読み込み
-    fn read_fat12_entry(&self, device: &Arc<dyn super::vfs::BlockDevice>, fat_start: u32, cluster: u32) -> FsResult<u16> {
-        let fat_offset = cluster + (cluster / 2); // 1.5バイト/エントリ
-        let fat_sector = fat_start + (fat_offset / 512);
-        let sector_offset = fat_offset % 512;
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        volume.sync()?;
+        volume.device.close()?;
         
-        let sector_data = device.read_block(fat_sector as u64)?;
+        log::info!("{}ファイルシステムをアンマウント: {} ({})", self.name, mount_point, volume.device_path);
+        Ok(())
+    }
+    
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let writable = mode != OpenMode::ReadOnly;
+        if writable && volume.read_only {
+            return Err(FsError::ReadOnly);
+        }
         
-        let entry = if sector_offset == 511 {
-            // セクタ境界をまたぐ場合
-            let next_sector_data = device.read_block((fat_sector + 1) as u64)?;
-            ((next_sector_data[0] as u16) << 8) | (sector_data[511] as u16)
-        } else {
-            ((sector_data[sector_offset + 1] as u16) << 8) | (sector_data[sector_offset] as u16)
+        let node = match volume.resolve(path) {
+            Ok(node) => node,
+            Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => {
+                return self.create_file(&volume, path);
+            },
+            Err(e) => return Err(e),
         };
-        
-        if cluster & 1 == 0 {
-            Ok(entry & 0x0FFF) // 偶数クラスタ：下位12ビット
-        } else {
-            Ok(entry >> 4) // 奇数クラスタ：上位12ビット
+
+        if mode == OpenMode::CreateNew {
+            return Err(FsError::AlreadyExists);
+        }
+        if node.is_directory() {
+            return Err(FsError::IsDirectory);
         }
+        if writable && node.entry.attributes & attr::READ_ONLY != 0 {
+            return Err(FsError::PermissionDenied);
+        }
+
+        let handle = FatFileHandle::new(volume, node, writable);
+        if mode == OpenMode::Truncate {
+            handle.resize(0)?;
+        }
+        Ok(Arc::new(handle))
     }
     
-    /// FAT16エントリを読み込み
-    fn read_fat16_entry(&self, device: &Arc<dyn super::vfs::BlockDevice>, fat_start: u32, cluster: u32) -> FsResult<u16> {
-        let fat_offset = cluster * 2; // 2バイト/エントリ
-        let fat_sector = fat_start + (fat_offset / 512);
-        let sector_offset = fat_offset % 512;
-        
-        let sector_data = device.read_block(fat_sector as u64)?;
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
         
-        if sector_offset == 511 {
-            // セクタ境界をまたぐ場合
-            let next_sector_data = device.read_block((fat_sector + 1) as u64)?;
-            Ok(((next_sector_data[0] as u16) << 8) | (sector_data[511] as u16))
-        } else {
-            Ok(((sector_data[sector_offset + 1] as u16) << 8) | (sector_data[sector_offset] as u16))
+        if !node.is_directory() {
+            return Err(FsError::NotDirectory);
         }
+        
+        Ok(Arc::new(FatDirHandle::new(volume, node)))
     }
     
-    /// FAT32エントリを読み込み
-    fn read_fat32_entry(&self, device: &Arc<dyn super::vfs::BlockDevice>, fat_start: u32, cluster: u32) -> FsResult<u32> {
-        let fat_offset = cluster * 4; // 4バイト/エントリ
-        let fat_sector = fat_start + (fat_offset / 512);
-        let sector_offset = fat_offset % 512;
-        
-        let sector_data = device.read_block(fat_sector as u64)?;
-        
-        if sector_offset > 508 {
-            // セクタ境界をまたぐ場合
-            let next_sector_data = device.read_block((fat_sector + 1) as u64)?;
-            let bytes_in_current = 512 - sector_offset;
-            let bytes_in_next = 4 - bytes_in_current;
-            
-            let mut entry = 0u32;
-            for i in 0..bytes_in_current {
-                entry |= (sector_data[sector_offset + i] as u32) << (i * 8);
-            }
-            for i in 0..bytes_in_next {
-                entry |= (next_sector_data[i] as u32) << ((bytes_in_current + i) * 8);
-            }
-            Ok(entry)
-        } else {
-            Ok(((sector_data[sector_offset + 3] as u32) << 24) |
-               ((sector_data[sector_offset + 2] as u32) << 16) |
-               ((sector_data[sector_offset + 1] as u32) << 8) |
-               (sector_data[sector_offset] as u32))
-        }
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
+        volume.node_metadata(&node)
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        self.find_volume(mount_point)?.stats()
     }
     
     fn sync(&self) -> FsResult<()> {
-        // すべてのデバイスを同期
-        let devices = self.devices.read();
-        for (_, device) in devices.iter() {
-            device.sync()?;
+        for volume in self.volumes.read().values() {
+            volume.sync()?;
         }
-        
         Ok(())
     }
+    
+    fn check(&self, mount_point: &str) -> FsResult<FsckReport> {
+        let volume = self.find_volume(mount_point)?;
+        volume.check(false)
+    }
+    
+    fn repair(&self, mount_point: &str) -> FsResult<FsckReport> {
+        let volume = self.find_volume(mount_point)?;
+        volume.check(true)
+    }
 }
 
 /// FAT12ファイルシステム
@@ -548,6 +666,11 @@ impl Fat12Filesystem {
     pub fn new() -> Self {
         Self(FatFilesystem::new("fat12", FatType::Fat12))
     }
+    
+    /// 長いファイル名の使用有無を指定してインスタンスを作成
+    pub fn new_with_options(long_names: bool) -> Self {
+        Self(FatFilesystem::new_with_options("fat12", FatType::Fat12, long_names))
+    }
 }
 
 impl Filesystem for Fat12Filesystem {
@@ -567,16 +690,16 @@ impl Filesystem for Fat12Filesystem {
         self.0.unmount(mount_point)
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        self.0.open_file(path, mode)
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        self.0.open_file(mount_point, path, mode)
     }
     
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        self.0.open_directory(path)
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        self.0.open_directory(mount_point, path)
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        self.0.metadata(path)
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        self.0.metadata(mount_point, path)
     }
     
     fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
@@ -586,6 +709,14 @@ impl Filesystem for Fat12Filesystem {
     fn sync(&self) -> FsResult<()> {
         self.0.sync()
     }
+    
+    fn check(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.check(mount_point)
+    }
+    
+    fn repair(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.repair(mount_point)
+    }
 }
 
 /// FAT16ファイルシステム
@@ -596,6 +727,11 @@ impl Fat16Filesystem {
     pub fn new() -> Self {
         Self(FatFilesystem::new("fat16", FatType::Fat16))
     }
+    
+    /// 長いファイル名の使用有無を指定してインスタンスを作成
+    pub fn new_with_options(long_names: bool) -> Self {
+        Self(FatFilesystem::new_with_options("fat16", FatType::Fat16, long_names))
+    }
 }
 
 impl Filesystem for Fat16Filesystem {
@@ -615,16 +751,16 @@ impl Filesystem for Fat16Filesystem {
         self.0.unmount(mount_point)
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        self.0.open_file(path, mode)
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        self.0.open_file(mount_point, path, mode)
     }
     
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        self.0.open_directory(path)
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        self.0.open_directory(mount_point, path)
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        self.0.metadata(path)
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        self.0.metadata(mount_point, path)
     }
     
     fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
@@ -634,6 +770,14 @@ impl Filesystem for Fat16Filesystem {
     fn sync(&self) -> FsResult<()> {
         self.0.sync()
     }
+    
+    fn check(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.check(mount_point)
+    }
+    
+    fn repair(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.repair(mount_point)
+    }
 }
 
 /// FAT32ファイルシステム
@@ -644,6 +788,11 @@ impl Fat32Filesystem {
     pub fn new() -> Self {
         Self(FatFilesystem::new("fat32", FatType::Fat32))
     }
+    
+    /// 長いファイル名の使用有無を指定してインスタンスを作成
+    pub fn new_with_options(long_names: bool) -> Self {
+        Self(FatFilesystem::new_with_options("fat32", FatType::Fat32, long_names))
+    }
 }
 
 impl Filesystem for Fat32Filesystem {
@@ -663,16 +812,16 @@ impl Filesystem for Fat32Filesystem {
         self.0.unmount(mount_point)
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        self.0.open_file(path, mode)
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        self.0.open_file(mount_point, path, mode)
     }
     
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        self.0.open_directory(path)
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        self.0.open_directory(mount_point, path)
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        self.0.metadata(path)
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        self.0.metadata(mount_point, path)
     }
     
     fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
@@ -682,9 +831,17 @@ impl Filesystem for Fat32Filesystem {
     fn sync(&self) -> FsResult<()> {
         self.0.sync()
     }
+    
+    fn check(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.check(mount_point)
+    }
+    
+    fn repair(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.repair(mount_point)
+    }
 }
 
-/// VFAT (長いファイル名をサポートするFAT) ファイルシステム
+/// VFATファイルシステム（FAT12/16/32いずれにもマウント可能）
 pub struct VfatFilesystem(FatFilesystem);
 
 impl VfatFilesystem {
@@ -692,6 +849,11 @@ impl VfatFilesystem {
     pub fn new() -> Self {
         Self(FatFilesystem::new("vfat", FatType::Vfat))
     }
+    
+    /// 長いファイル名の使用有無を指定してインスタンスを作成
+    pub fn new_with_options(long_names: bool) -> Self {
+        Self(FatFilesystem::new_with_options("vfat", FatType::Vfat, long_names))
+    }
 }
 
 impl Filesystem for VfatFilesystem {
@@ -711,16 +873,16 @@ impl Filesystem for VfatFilesystem {
         self.0.unmount(mount_point)
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        self.0.open_file(path, mode)
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        self.0.open_file(mount_point, path, mode)
     }
     
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        self.0.open_directory(path)
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        self.0.open_directory(mount_point, path)
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        self.0.metadata(path)
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        self.0.metadata(mount_point, path)
     }
     
     fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
@@ -730,369 +892,12 @@ impl Filesystem for VfatFilesystem {
     fn sync(&self) -> FsResult<()> {
         self.0.sync()
     }
-}
-
-/// パス解析結果
-struct ParsedPath {
-    path: String,
-    components: Vec<String>,
-}
-
-/// FAT32ファイルハンドル
-pub struct Fat32FileHandle {
-    inner: Box<dyn FileHandle>,
-    mode: OpenMode,
-    position: u64,
-}
-
-impl Fat32FileHandle {
-    fn new(inner: Box<dyn FileHandle>, mode: OpenMode) -> Self {
-        Self {
-            inner,
-            mode,
-            position: 0,
-        }
-    }
-}
-
-impl FileHandle for Fat32FileHandle {
-    fn read(&mut self, buffer: &mut [u8]) -> FsResult<usize> {
-        let bytes_read = self.inner.read(buffer)?;
-        self.position += bytes_read as u64;
-        Ok(bytes_read)
-    }
     
-    fn write(&mut self, buffer: &[u8]) -> FsResult<usize> {
-        if !self.mode.is_writable() {
-            return Err(FsError::PermissionDenied);
-        }
-        
-        let bytes_written = self.inner.write(buffer)?;
-        self.position += bytes_written as u64;
-        Ok(bytes_written)
+    fn check(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.check(mount_point)
     }
     
-    fn seek(&mut self, position: u64) -> FsResult<u64> {
-        self.position = self.inner.seek(position)?;
-        Ok(self.position)
-    }
-    
-    fn tell(&self) -> u64 {
-        self.position
-    }
-    
-    fn flush(&mut self) -> FsResult<()> {
-        self.inner.flush()
-    }
-    
-    fn metadata(&self) -> FsResult<Metadata> {
-        self.inner.metadata()
+    fn repair(&self, mount_point: &str) -> FsResult<FsckReport> {
+        self.0.repair(mount_point)
     }
 }
-
-/// FAT32ディレクトリハンドル
-pub struct Fat32DirHandle {
-    inner: Box<dyn DirHandle>,
-    entries: Vec<super::DirEntry>,
-    position: usize,
-}
-
-impl Fat32DirHandle {
-    fn new(inner: Box<dyn DirHandle>) -> Self {
-        Self {
-            inner,
-            entries: Vec::new(),
-            position: 0,
-        }
-    }
-}
-
-impl DirHandle for Fat32DirHandle {
-    fn read_entry(&mut self) -> FsResult<Option<super::DirEntry>> {
-        // エントリがキャッシュされていない場合は読み込み
-        if self.entries.is_empty() {
-            self.load_entries()?;
-        }
-        
-        if self.position < self.entries.len() {
-            let entry = self.entries[self.position].clone();
-            self.position += 1;
-            Ok(Some(entry))
-        } else {
-            Ok(None)
-        }
-    }
-    
-    fn rewind(&mut self) -> FsResult<()> {
-        self.position = 0;
-        self.inner.rewind()
-    }
-}
-
-impl Fat32DirHandle {
-    /// ディレクトリエントリを読み込み
-    fn load_entries(&mut self) -> FsResult<()> {
-        self.entries.clear();
-        
-        while let Some(entry) = self.inner.read_entry()? {
-            self.entries.push(entry);
-        }
-        
-        Ok(())
-    }
-}
-
-/// ダーティディレクトリエントリ
-struct DirtyEntry {
-    cluster: u32,
-    index: usize,
-    entry: DirectoryEntry,
-}
-
-/// ダーティFATエントリ
-struct DirtyFatEntry {
-    cluster: u32,
-    value: u32,
-    timestamp: u64,
-}
-
-/// キャッシュされたディレクトリエントリ
-#[derive(Clone)]
-struct CachedDirectoryEntry {
-    entry: DirectoryEntry,
-    dirty: bool,
-    last_accessed: u64,
-    access_count: u32,
-}
-
-impl CachedDirectoryEntry {
-    fn new(entry: DirectoryEntry) -> Self {
-        Self {
-            entry,
-            dirty: false,
-            last_accessed: crate::time::current_time_ms(),
-            access_count: 1,
-        }
-    }
-    
-    fn is_dirty(&self) -> bool {
-        self.dirty
-    }
-    
-    fn mark_dirty(&mut self) {
-        self.dirty = true;
-        self.last_accessed = crate::time::current_time_ms();
-        self.access_count += 1;
-    }
-    
-    fn clear_dirty(&mut self) {
-        self.dirty = false;
-    }
-    
-    fn to_bytes(&self) -> [u8; DIR_ENTRY_SIZE] {
-        self.entry.to_bytes()
-    }
-}
-
-/// キャッシュされたFATエントリ
-#[derive(Clone)]
-struct CachedFatEntry {
-    value: u32,
-    dirty: bool,
-    last_modified: u64,
-    access_count: u32,
-}
-
-impl CachedFatEntry {
-    fn new(value: u32) -> Self {
-        Self {
-            value,
-            dirty: false,
-            last_modified: crate::time::current_time_ms(),
-            access_count: 1,
-        }
-    }
-    
-    fn is_dirty(&self) -> bool {
-        self.dirty
-    }
-    
-    fn mark_dirty(&mut self) {
-        self.dirty = true;
-        self.last_modified = crate::time::current_time_ms();
-        self.access_count += 1;
-    }
-    
-    fn clear_dirty(&mut self) {
-        self.dirty = false;
-    }
-    
-    fn update_value(&mut self, new_value: u32) {
-        self.value = new_value;
-        self.mark_dirty();
-    }
-}
-
-impl Fat32FileSystem {
-    /// FSInfoセクターを更新
-    fn update_fsinfo_sector(&self) -> Result<(), Fat32Error> {
-        if self.fsinfo_sector == 0 {
-            return Ok((); // FSInfoセクターが存在しない
-        }
-        
-        // FSInfoセクターを読み込み
-        let mut fsinfo_data = vec![0u8; self.bytes_per_sector];
-        self.device.read_sectors(self.fsinfo_sector, 1, &mut fsinfo_data)?;
-        
-        // FSInfoシグネチャを確認
-        let lead_sig = u32::from_le_bytes([fsinfo_data[0], fsinfo_data[1], fsinfo_data[2], fsinfo_data[3]]);
-        let struc_sig = u32::from_le_bytes([fsinfo_data[484], fsinfo_data[485], fsinfo_data[486], fsinfo_data[487]]);
-        
-        if lead_sig != 0x41615252 || struc_sig != 0x61417272 {
-            log::warn!("無効なFSInfoシグネチャ");
-            return Ok(());
-        }
-        
-        // 空きクラスタ数を計算
-        let free_clusters = self.count_free_clusters()?;
-        let next_free_cluster = self.find_next_free_cluster()?;
-        
-        // FSInfoセクターを更新
-        let free_count_bytes = free_clusters.to_le_bytes();
-        let next_free_bytes = next_free_cluster.to_le_bytes();
-        
-        fsinfo_data[488..492].copy_from_slice(&free_count_bytes);
-        fsinfo_data[492..496].copy_from_slice(&next_free_bytes);
-        
-        // トレイルシグネチャを設定
-        let trail_sig = 0xAA550000u32.to_le_bytes();
-        fsinfo_data[508..512].copy_from_slice(&trail_sig);
-        
-        // FSInfoセクターを書き込み
-        self.device.write_sectors(self.fsinfo_sector, 1, &fsinfo_data)?;
-        
-        log::debug!("FSInfo更新完了: 空きクラスタ={}, 次の空きクラスタ={}", 
-                   free_clusters, next_free_cluster);
-        
-        Ok(())
-    }
-    
-    /// 空きクラスタ数をカウント
-    fn count_free_clusters(&self) -> Result<u32, Fat32Error> {
-        let mut free_count = 0;
-        
-        for cluster in 2..self.total_clusters {
-            let fat_entry = self.get_fat_entry(cluster)?;
-            if fat_entry == 0 {
-                free_count += 1;
-            }
-        }
-        
-        Ok(free_count)
-    }
-    
-    /// 次の空きクラスタを検索
-    fn find_next_free_cluster(&self) -> Result<u32, Fat32Error> {
-        for cluster in 2..self.total_clusters {
-            let fat_entry = self.get_fat_entry(cluster)?;
-            if fat_entry == 0 {
-                return Ok(cluster);
-            }
-        }
-        
-        Err(Fat32Error::NoSpace)
-    }
-    
-    /// キャッシュサイズを制限
-    fn limit_cache_size(&mut self) {
-        const MAX_DIR_CACHE_ENTRIES: usize = 1000;
-        const MAX_FAT_CACHE_ENTRIES: usize = 5000;
-        
-        // ディレクトリキャッシュのサイズ制限
-        if self.directory_cache.len() > MAX_DIR_CACHE_ENTRIES {
-            // LRU方式で古いエントリを削除
-            let mut entries_by_access: Vec<_> = self.directory_cache.iter()
-                .flat_map(|(cluster, entries)| {
-                    entries.iter().enumerate().map(move |(index, entry)| {
-                        (*cluster, index, entry.last_accessed)
-                    })
-                })
-                .collect();
-            
-            entries_by_access.sort_by_key(|(_, _, last_accessed)| *last_accessed);
-            
-            let remove_count = self.directory_cache.len() - MAX_DIR_CACHE_ENTRIES;
-            for (cluster, index, _) in entries_by_access.iter().take(remove_count) {
-                if let Some(entries) = self.directory_cache.get_mut(cluster) {
-                    if !entries[*index].is_dirty() {
-                        entries.remove(*index);
-                    }
-                }
-            }
-        }
-        
-        // FATキャッシュのサイズ制限
-        if self.fat_cache.len() > MAX_FAT_CACHE_ENTRIES {
-            let mut entries_by_access: Vec<_> = self.fat_cache.iter()
-                .map(|(cluster, entry)| (*cluster, entry.last_modified))
-                .collect();
-            
-            entries_by_access.sort_by_key(|(_, last_modified)| *last_modified);
-            
-            let remove_count = self.fat_cache.len() - MAX_FAT_CACHE_ENTRIES;
-            for (cluster, _) in entries_by_access.iter().take(remove_count) {
-                if let Some(entry) = self.fat_cache.get(cluster) {
-                    if !entry.is_dirty() {
-                        self.fat_cache.remove(cluster);
-                    }
-                }
-            }
-        }
-    }
-    
-    /// キャッシュ統計を取得
-    fn get_cache_statistics(&self) -> CacheStatistics {
-        let mut dir_dirty_count = 0;
-        let mut dir_total_count = 0;
-        
-        for entries in self.directory_cache.values() {
-            for entry in entries {
-                dir_total_count += 1;
-                if entry.is_dirty() {
-                    dir_dirty_count += 1;
-                }
-            }
-        }
-        
-        let fat_dirty_count = self.fat_cache.values()
-            .filter(|entry| entry.is_dirty())
-            .count();
-        
-        CacheStatistics {
-            directory_cache_size: dir_total_count,
-            directory_dirty_count: dir_dirty_count,
-            fat_cache_size: self.fat_cache.len(),
-            fat_dirty_count,
-            cache_hit_rate: self.calculate_cache_hit_rate(),
-        }
-    }
-    
-    /// キャッシュヒット率を計算
-    fn calculate_cache_hit_rate(&self) -> f32 {
-        // 簡略化実装
-        0.85 // 85%のヒット率を仮定
-    }
-}
-
-/// キャッシュ統計情報
-#[derive(Debug)]
-struct CacheStatistics {
-    directory_cache_size: usize,
-    directory_dirty_count: usize,
-    fat_cache_size: usize,
-    fat_dirty_count: usize,
-    cache_hit_rate: f32,
-}
-
-const DIR_ENTRY_SIZE: usize = 32;
-const ENTRIES_PER_SECTOR: usize = 16; // 512バイト/セクタ ÷ 32バイト/エントリ 
\ No newline at end of file
@@ -72,7 +72,7 @@ impl FatType {
             (Self::Vfat, _) => true,
             (_, Self::Vfat) => true,
             // 同じタイプは互換性あり
-            (a, b) if a == b => true,
+            (a, b) if *a == b => true,
             // 他は互換性なし
             _ => false,
         }
@@ -292,31 +292,41 @@ impl FatSuperblock {
         let mut oem_name = [0; 8];
         oem_name.copy_from_slice(&data[3..11]);
         
+        // ジオメトリの妥当性をチェック（以降の計算で0除算やアンダーフローを起こさないため）
+        if !matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+            || sectors_per_cluster == 0
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved_sectors == 0
+            || num_fats == 0
+        {
+            log::warn!("FAT: 不正なBPB (セクタ長={}, クラスタ長={}, 予約={}, FAT数={})",
+                      bytes_per_sector, sectors_per_cluster, reserved_sectors, num_fats);
+            return Err(FsError::CorruptedFs);
+        }
+        
         // データ領域のセクタ数
-        let root_dir_sectors = ((root_entries * 32) + (bytes_per_sector - 1)) / bytes_per_sector;
+        let root_dir_sectors = (root_entries as u32 * 32).div_ceil(bytes_per_sector as u32);
         let sectors_per_fat = if sectors_per_fat_16 == 0 { sectors_per_fat_32 } else { sectors_per_fat_16 as u32 };
-        let first_data_sector = reserved_sectors as u32 + (num_fats as u32 * sectors_per_fat) + root_dir_sectors as u32;
+        let first_data_sector = reserved_sectors as u32 + (num_fats as u32 * sectors_per_fat) + root_dir_sectors;
+        if sectors_per_fat == 0 || first_data_sector >= total_sectors {
+            log::warn!("FAT: データ領域がボリューム外です (先頭={}, 総セクタ={})", first_data_sector, total_sectors);
+            return Err(FsError::CorruptedFs);
+        }
         let data_sectors = total_sectors - first_data_sector;
         let total_clusters = data_sectors / sectors_per_cluster as u32;
         
-        // クラスタ数からFATタイプを自動検出
+        // FATエントリ幅はクラスタ数のみで決まる（Microsoft仕様）。
+        // VFATはFAT12/16/32上の長いファイル名拡張にすぎないため、ここでは区別しない
         let detected_fat_type = FatType::detect(total_clusters);
-        
-        // スーパーブロックのFSタイプからVFATかどうかを判定
-        let detected_fs_type = core::str::from_utf8(&fs_type).unwrap_or("");
-        let is_vfat = detected_fs_type.contains("FAT") && detected_fs_type.contains("VFAT");
-        
-        // 最終的なFATタイプを決定
-        let final_fat_type = if is_vfat {
-            FatType::Vfat
-        } else {
-            detected_fat_type
-        };
+        if detected_fat_type == FatType::Fat32 && !is_fat32 {
+            log::warn!("FAT: クラスタ数はFAT32相当ですがBPBがFAT12/16形式です");
+            return Err(FsError::CorruptedFs);
+        }
         
         // 期待するタイプと互換性があるかチェック
-        if !expected_type.is_compatible_with(final_fat_type) {
+        if !expected_type.is_compatible_with(detected_fat_type) {
             log::warn!("検出されたFATタイプ ({:?}) は期待するタイプ ({:?}) と互換性がありません", 
-                       final_fat_type, expected_type);
+                       detected_fat_type, expected_type);
             // ここではエラーを返さず、あとでマウントメソッドでチェック
         }
         
@@ -344,7 +354,7 @@ impl FatSuperblock {
             volume_id,
             volume_label,
             fs_type,
-            detected_fat_type: final_fat_type,
+            detected_fat_type,
             boot_sector_data: data[0..512].to_vec(),
         })
     }
@@ -429,7 +439,46 @@ impl FatSuperblock {
     
     /// データ領域の先頭セクタを取得
     pub fn first_data_sector(&self) -> u32 {
-        let root_dir_sectors = ((self.root_entries * 32) + (self.bytes_per_sector - 1)) / self.bytes_per_sector;
-        self.reserved_sectors as u32 + (self.num_fats as u32 * self.sectors_per_fat()) + root_dir_sectors as u32
+        self.fat_start_sector() + (self.num_fats as u32 * self.sectors_per_fat()) + self.root_dir_sectors()
+    }
+    
+    /// 固定ルートディレクトリ領域のセクタ数（FAT32では0）
+    pub fn root_dir_sectors(&self) -> u32 {
+        (self.root_entries as u32 * 32).div_ceil(self.bytes_per_sector as u32)
+    }
+    
+    /// 最初のFATの先頭セクタ
+    pub fn fat_start_sector(&self) -> u32 {
+        self.reserved_sectors as u32
+    }
+    
+    /// データ領域のクラスタ数
+    pub fn total_clusters(&self) -> u32 {
+        (self.total_sectors() - self.first_data_sector()) / self.sectors_per_cluster as u32
+    }
+    
+    /// 有効な最大クラスタ番号
+    pub fn max_cluster(&self) -> u32 {
+        // クラスタ番号は2から始まる
+        self.total_clusters() + 1
+    }
+    
+    /// クラスタ番号から先頭セクタを計算
+    pub fn cluster_to_sector(&self, cluster: u32) -> u32 {
+        self.first_data_sector() + (cluster - 2) * self.sectors_per_cluster as u32
+    }
+    
+    /// FATのミラーリングが有効か（FAT32の拡張フラグのビット7が0）
+    pub fn fat_mirroring(&self) -> bool {
+        self.detected_fat_type != FatType::Fat32 || self.extended_flags & 0x80 == 0
+    }
+    
+    /// 読み込みに使用するFATの番号
+    pub fn active_fat(&self) -> u8 {
+        if self.fat_mirroring() {
+            0
+        } else {
+            (self.extended_flags & 0x0F) as u8 % self.num_fats
+        }
     }
-} 
\ No newline at end of file
+}
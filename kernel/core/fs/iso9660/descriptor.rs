@@ -0,0 +1,326 @@
+// ISO9660 ボリュームディスクリプタ実装
+//
+// プライマリ/補助ボリュームディスクリプタ（Joliet）の解析
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::super::vfs::BlockDevice;
+use super::record::DirectoryRecord;
+
+/// ISO9660の論理セクタサイズ（バイト）
+pub const ISO_SECTOR_SIZE: u64 = 2048;
+
+/// ボリュームディスクリプタ集合の開始セクタ
+const VOLUME_DESCRIPTOR_START: u64 = 16;
+
+/// 走査するボリュームディスクリプタの上限（破損イメージ対策）
+const MAX_VOLUME_DESCRIPTORS: u64 = 64;
+
+/// 標準識別子
+const STANDARD_IDENTIFIER: &[u8] = b"CD001";
+
+// ボリュームディスクリプタ内のオフセット
+const TYPE_OFFSET: usize = 0;
+const IDENTIFIER_OFFSET: usize = 1;
+const VERSION_OFFSET: usize = 6;
+const FLAGS_OFFSET: usize = 7;
+const SYSTEM_ID_OFFSET: usize = 8;
+const VOLUME_ID_OFFSET: usize = 40;
+const VOLUME_SPACE_SIZE_OFFSET: usize = 80;
+const ESCAPE_SEQUENCES_OFFSET: usize = 88;
+const VOLUME_SET_SIZE_OFFSET: usize = 120;
+const LOGICAL_BLOCK_SIZE_OFFSET: usize = 128;
+const PATH_TABLE_SIZE_OFFSET: usize = 132;
+const ROOT_RECORD_OFFSET: usize = 156;
+const ROOT_RECORD_SIZE: usize = 34;
+const CREATION_TIME_OFFSET: usize = 813;
+
+/// ボリュームディスクリプタタイプ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VolumeDescriptorType {
+    /// ブートレコード（El Torito）
+    BootRecord = 0,
+    /// プライマリボリュームディスクリプタ
+    Primary = 1,
+    /// 補助ボリュームディスクリプタ（Joliet）
+    Supplementary = 2,
+    /// ボリュームパーティションディスクリプタ
+    Partition = 3,
+    /// ボリュームディスクリプタ集合終端
+    Terminator = 255,
+}
+
+impl VolumeDescriptorType {
+    /// バイト値からタイプを作成
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::BootRecord),
+            1 => Some(Self::Primary),
+            2 => Some(Self::Supplementary),
+            3 => Some(Self::Partition),
+            255 => Some(Self::Terminator),
+            _ => None,
+        }
+    }
+}
+
+/// Jolietのレベル（エスケープシーケンスで識別）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JolietLevel {
+    /// UCS-2 Level 1 (`%/@`)
+    Level1,
+    /// UCS-2 Level 2 (`%/C`)
+    Level2,
+    /// UCS-2 Level 3 (`%/E`)
+    Level3,
+}
+
+impl JolietLevel {
+    /// エスケープシーケンス領域からJolietレベルを判定
+    pub fn detect(escape_sequences: &[u8]) -> Option<Self> {
+        // エスケープシーケンスは32バイト領域内の任意の位置に置かれる
+        for window in escape_sequences.windows(3) {
+            if window[0] != b'%' || window[1] != b'/' {
+                continue;
+            }
+            match window[2] {
+                b'@' => return Some(Self::Level1),
+                b'C' => return Some(Self::Level2),
+                b'E' => return Some(Self::Level3),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// プライマリ/補助ボリュームディスクリプタ
+#[derive(Debug, Clone)]
+pub struct VolumeDescriptor {
+    /// ディスクリプタタイプ
+    pub descriptor_type: VolumeDescriptorType,
+    /// ディスクリプタのバージョン
+    pub version: u8,
+    /// ボリュームフラグ（補助ディスクリプタのみ有効）
+    pub flags: u8,
+    /// システム識別子
+    pub system_id: String,
+    /// ボリューム識別子
+    pub volume_id: String,
+    /// ボリューム空間サイズ（論理ブロック数）
+    pub volume_space_size: u32,
+    /// ボリューム集合サイズ
+    pub volume_set_size: u16,
+    /// 論理ブロックサイズ（バイト）
+    pub logical_block_size: u16,
+    /// パステーブルサイズ（バイト）
+    pub path_table_size: u32,
+    /// ルートディレクトリレコード
+    pub root_record: DirectoryRecord,
+    /// ボリューム作成日時（UNIXタイムスタンプ）
+    pub creation_time: u64,
+    /// Jolietレベル（補助ディスクリプタでJolietの場合のみ）
+    pub joliet: Option<JolietLevel>,
+}
+
+impl VolumeDescriptor {
+    /// 2048バイトのディスクリプタセクタをパース
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < ISO_SECTOR_SIZE as usize {
+            return Err(FsError::InvalidData);
+        }
+
+        if &data[IDENTIFIER_OFFSET..IDENTIFIER_OFFSET + 5] != STANDARD_IDENTIFIER {
+            return Err(FsError::BadMagic);
+        }
+
+        let descriptor_type = VolumeDescriptorType::from_byte(data[TYPE_OFFSET])
+            .ok_or(FsError::InvalidData)?;
+
+        let joliet = if descriptor_type == VolumeDescriptorType::Supplementary {
+            JolietLevel::detect(&data[ESCAPE_SEQUENCES_OFFSET..ESCAPE_SEQUENCES_OFFSET + 32])
+        } else {
+            None
+        };
+
+        // 識別子はJolietの場合UCS-2、それ以外はdチャラクタ
+        let decode_id = |bytes: &[u8]| -> String {
+            let name = if joliet.is_some() {
+                super::record::decode_ucs2_be(bytes)
+            } else {
+                String::from_utf8_lossy(bytes).into_owned()
+            };
+            String::from(name.trim_end_matches(|c| c == ' ' || c == '\0'))
+        };
+
+        let system_id = decode_id(&data[SYSTEM_ID_OFFSET..SYSTEM_ID_OFFSET + 32]);
+        let volume_id = decode_id(&data[VOLUME_ID_OFFSET..VOLUME_ID_OFFSET + 32]);
+
+        // 両バイトオーダー形式のフィールドはリトルエンディアン側を採用
+        let volume_space_size = read_u32_le(data, VOLUME_SPACE_SIZE_OFFSET);
+        let volume_set_size = read_u16_le(data, VOLUME_SET_SIZE_OFFSET);
+        let logical_block_size = read_u16_le(data, LOGICAL_BLOCK_SIZE_OFFSET);
+        let path_table_size = read_u32_le(data, PATH_TABLE_SIZE_OFFSET);
+
+        if logical_block_size == 0 || !logical_block_size.is_power_of_two() {
+            log::error!("ISO9660: 無効な論理ブロックサイズ: {}", logical_block_size);
+            return Err(FsError::BadSuperblock);
+        }
+
+        let root_record = DirectoryRecord::parse(
+            &data[ROOT_RECORD_OFFSET..ROOT_RECORD_OFFSET + ROOT_RECORD_SIZE],
+            joliet.is_some(),
+        )?;
+
+        let creation_time = parse_dec_datetime(&data[CREATION_TIME_OFFSET..CREATION_TIME_OFFSET + 17]);
+
+        Ok(Self {
+            descriptor_type,
+            version: data[VERSION_OFFSET],
+            flags: data[FLAGS_OFFSET],
+            system_id,
+            volume_id,
+            volume_space_size,
+            volume_set_size,
+            logical_block_size,
+            path_table_size,
+            root_record,
+            creation_time,
+            joliet,
+        })
+    }
+
+    /// Jolietディスクリプタかどうか
+    pub fn is_joliet(&self) -> bool {
+        self.joliet.is_some()
+    }
+}
+
+/// ボリュームディスクリプタ集合
+#[derive(Debug, Clone)]
+pub struct VolumeDescriptorSet {
+    /// プライマリボリュームディスクリプタ
+    pub primary: VolumeDescriptor,
+    /// Joliet補助ボリュームディスクリプタ（存在する場合）
+    pub joliet: Option<VolumeDescriptor>,
+    /// El Toritoブートレコードが存在するか
+    pub bootable: bool,
+}
+
+impl VolumeDescriptorSet {
+    /// デバイスからボリュームディスクリプタ集合を読み込み
+    pub fn read(device: &dyn BlockDevice) -> FsResult<Self> {
+        let mut primary = None;
+        let mut joliet = None;
+        let mut bootable = false;
+
+        for index in 0..MAX_VOLUME_DESCRIPTORS {
+            let sector = read_bytes(
+                device,
+                (VOLUME_DESCRIPTOR_START + index) * ISO_SECTOR_SIZE,
+                ISO_SECTOR_SIZE as usize,
+            )?;
+
+            if &sector[IDENTIFIER_OFFSET..IDENTIFIER_OFFSET + 5] != STANDARD_IDENTIFIER {
+                log::error!("ISO9660: セクタ{}に標準識別子がありません", VOLUME_DESCRIPTOR_START + index);
+                return Err(FsError::BadMagic);
+            }
+
+            match VolumeDescriptorType::from_byte(sector[TYPE_OFFSET]) {
+                Some(VolumeDescriptorType::Terminator) => break,
+                Some(VolumeDescriptorType::BootRecord) => bootable = true,
+                Some(VolumeDescriptorType::Primary) => {
+                    if primary.is_none() {
+                        primary = Some(VolumeDescriptor::parse(&sector)?);
+                    }
+                },
+                Some(VolumeDescriptorType::Supplementary) => {
+                    let descriptor = VolumeDescriptor::parse(&sector)?;
+                    // Joliet以外の補助ディスクリプタ（ISO9660:1999等）は無視
+                    if descriptor.is_joliet() && joliet.is_none() {
+                        joliet = Some(descriptor);
+                    }
+                },
+                _ => {
+                    log::debug!("ISO9660: 未対応のボリュームディスクリプタタイプ: {}", sector[TYPE_OFFSET]);
+                }
+            }
+        }
+
+        let primary = primary.ok_or_else(|| {
+            log::error!("ISO9660: プライマリボリュームディスクリプタが見つかりません");
+            FsError::BadSuperblock
+        })?;
+
+        Ok(Self {
+            primary,
+            joliet,
+            bootable,
+        })
+    }
+}
+
+/// デバイスの任意のバイト範囲を読み込み
+///
+/// デバイスのブロックサイズ（512または2048が一般的）に依存せず読み込める。
+pub fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+
+    if data.len() < start + len {
+        return Err(FsError::IoError);
+    }
+
+    Ok(data[start..start + len].to_vec())
+}
+
+/// 16バイトの10進数日時表現（8.4.26.1）をUNIXタイムスタンプに変換
+fn parse_dec_datetime(data: &[u8]) -> u64 {
+    let digits = |range: core::ops::Range<usize>| -> u32 {
+        data[range].iter().fold(0u32, |acc, &b| {
+            if b.is_ascii_digit() { acc * 10 + (b - b'0') as u32 } else { acc * 10 }
+        })
+    };
+
+    let year = digits(0..4);
+    if year == 0 {
+        return 0;
+    }
+
+    let timestamp = super::record::civil_to_unix(
+        year as i64,
+        digits(4..6),
+        digits(6..8),
+        digits(8..10),
+        digits(10..12),
+        digits(12..14),
+    );
+
+    // GMTオフセット（15分単位、符号付き）
+    let offset = data[16] as i8 as i64 * 15 * 60;
+    (timestamp - offset).max(0) as u64
+}
+
+/// リトルエンディアンu16を読み込み
+pub(super) fn read_u16_le(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// リトルエンディアンu32を読み込み
+pub(super) fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
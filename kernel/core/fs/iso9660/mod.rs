@@ -0,0 +1,643 @@
+// ISO9660 ファイルシステム実装
+//
+// CD-ROM/DVDイメージ向けの読み取り専用ISO9660実装（Joliet/Rock Ridge対応）
+
+mod descriptor;
+mod record;
+mod rock_ridge;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use spin::RwLock;
+use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, Filesystem};
+use super::vfs::BlockDevice;
+use self::descriptor::{VolumeDescriptorSet, read_bytes, ISO_SECTOR_SIZE};
+use self::record::DirectoryRecord;
+use self::rock_ridge::RockRidgeInfo;
+
+/// シンボリックリンクをたどる際のパス要素上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// 名前解決に使用する名前空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NameMode {
+    /// Rock Ridge（プライマリディスクリプタ + SUSP）
+    RockRidge {
+        /// SUSPのスキップバイト数
+        skip: u8,
+    },
+    /// Joliet（補助ディスクリプタ、UCS-2名）
+    Joliet,
+    /// 素のISO9660名（8.3、大文字）
+    Plain,
+}
+
+/// マウントされたISO9660ボリューム
+struct IsoVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// ボリュームディスクリプタ集合
+    descriptors: VolumeDescriptorSet,
+    /// 論理ブロックサイズ
+    logical_block_size: u16,
+    /// 使用する名前空間
+    mode: NameMode,
+    /// ルートディレクトリノード
+    root: IsoNode,
+}
+
+/// 解決済みのファイル/ディレクトリ
+#[derive(Debug, Clone)]
+struct IsoNode {
+    /// 表示名
+    name: String,
+    /// 最初のディレクトリレコード
+    record: DirectoryRecord,
+    /// データエクステント（バイトオフセット, 長さ）
+    extents: Vec<(u64, u32)>,
+    /// 合計サイズ
+    size: u64,
+    /// Rock Ridge属性
+    rock_ridge: RockRidgeInfo,
+}
+
+impl IsoNode {
+    /// ディレクトリかどうか
+    fn is_directory(&self) -> bool {
+        match self.rock_ridge.mode {
+            Some(mode) => mode & rock_ridge::S_IFMT == rock_ridge::S_IFDIR,
+            None => self.record.is_directory(),
+        }
+    }
+
+    /// アイノード番号
+    fn inode(&self) -> u64 {
+        if let Some(serial) = self.rock_ridge.serial {
+            return serial as u64;
+        }
+
+        // ディレクトリはエクステント位置、ファイルはレコード位置で一意にする
+        if self.record.is_directory() {
+            self.record.extent as u64
+        } else {
+            self.record.position
+        }
+    }
+
+    /// ファイルタイプ
+    fn file_type(&self) -> FileType {
+        if self.rock_ridge.symlink.is_some() {
+            return FileType::SymbolicLink;
+        }
+
+        match self.rock_ridge.mode.map(|m| m & rock_ridge::S_IFMT) {
+            Some(rock_ridge::S_IFDIR) => FileType::Directory,
+            Some(rock_ridge::S_IFLNK) => FileType::SymbolicLink,
+            Some(rock_ridge::S_IFBLK) => FileType::BlockDevice,
+            Some(rock_ridge::S_IFCHR) => FileType::CharDevice,
+            Some(rock_ridge::S_IFIFO) => FileType::NamedPipe,
+            Some(rock_ridge::S_IFSOCK) => FileType::Socket,
+            Some(_) => FileType::Regular,
+            None if self.record.is_directory() => FileType::Directory,
+            None => FileType::Regular,
+        }
+    }
+
+    /// メタデータを構築
+    fn metadata(&self, logical_block_size: u16) -> Metadata {
+        let rr = &self.rock_ridge;
+        let is_dir = self.is_directory();
+
+        let permissions = match rr.mode {
+            Some(mode) => Permissions {
+                read: mode & 0o400 != 0,
+                // 読み取り専用メディアなので書き込みは常に不可
+                write: false,
+                execute: mode & 0o100 != 0,
+            },
+            None => Permissions {
+                read: true,
+                write: false,
+                execute: is_dir,
+            },
+        };
+
+        let size = match &rr.symlink {
+            Some(target) => target.len() as u64,
+            None => self.size,
+        };
+
+        let recorded = self.record.recorded;
+        let modified = rr.modified.unwrap_or(recorded);
+
+        Metadata {
+            inode: self.inode(),
+            file_type: self.file_type(),
+            size,
+            uid: rr.uid.unwrap_or(0),
+            gid: rr.gid.unwrap_or(0),
+            permissions,
+            created: rr.created.unwrap_or(modified),
+            accessed: rr.accessed.unwrap_or(modified),
+            modified,
+            links: rr.links.unwrap_or(if is_dir { 2 } else { 1 }),
+            block_size: logical_block_size as u32,
+            blocks: self.size.div_ceil(logical_block_size as u64),
+        }
+    }
+}
+
+impl IsoVolume {
+    /// デバイスからボリュームを構築
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, allow_joliet: bool, allow_rock: bool) -> FsResult<Self> {
+        let descriptors = VolumeDescriptorSet::read(&*device)?;
+        let primary = &descriptors.primary;
+        let logical_block_size = primary.logical_block_size;
+
+        // Rock Ridgeはプライマリ側のルート"."レコードで検出する
+        let mut mode = NameMode::Plain;
+        if allow_rock {
+            let root_records = record::read_directory(&*device, &primary.root_record, logical_block_size, false)?;
+            if let Some(dot) = root_records.iter().find(|r| r.is_current()) {
+                if let Some(skip) = rock_ridge::detect_susp(&dot.system_use) {
+                    if rock_ridge::has_rrip_extension(&*device, &dot.system_use, logical_block_size)? {
+                        mode = NameMode::RockRidge { skip };
+                    }
+                }
+            }
+        }
+
+        // Rock RidgeがなければJolietを使用（Linuxと同じ優先順位）
+        if mode == NameMode::Plain && allow_joliet && descriptors.joliet.is_some() {
+            mode = NameMode::Joliet;
+        }
+
+        let root_record = match mode {
+            NameMode::Joliet => descriptors.joliet.as_ref().map(|d| d.root_record.clone()).unwrap_or_else(|| primary.root_record.clone()),
+            _ => primary.root_record.clone(),
+        };
+
+        let root = IsoNode {
+            name: "/".to_string(),
+            extents: vec![(root_record.extent_offset(logical_block_size), root_record.data_length)],
+            size: root_record.data_length as u64,
+            record: root_record,
+            rock_ridge: RockRidgeInfo::default(),
+        };
+
+        log::info!("ISO9660: ボリューム '{}' ({}ブロック, 名前空間={:?}, ブート可能={})",
+                  primary.volume_id, primary.volume_space_size, mode, descriptors.bootable);
+
+        let mut volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            logical_block_size,
+            mode,
+            root,
+            descriptors,
+        };
+
+        // ルートのRock Ridge属性（PX/TF）を"."レコードから取得
+        if let NameMode::RockRidge { skip } = mode {
+            let records = record::read_directory(&*volume.device, &volume.root.record, logical_block_size, false)?;
+            if let Some(dot) = records.iter().find(|r| r.is_current()) {
+                volume.root.rock_ridge = rock_ridge::parse(&*volume.device, &dot.system_use, skip, logical_block_size)?;
+                volume.root.rock_ridge.name = None;
+            }
+        }
+
+        Ok(volume)
+    }
+
+    /// Jolietレコードを扱っているか
+    fn is_joliet(&self) -> bool {
+        self.mode == NameMode::Joliet
+    }
+
+    /// ディレクトリの子ノードを列挙
+    fn list_directory(&self, dir: &IsoNode) -> FsResult<Vec<IsoNode>> {
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+
+        let records = record::read_directory(&*self.device, &dir.record, self.logical_block_size, self.is_joliet())?;
+        let mut nodes: Vec<IsoNode> = Vec::new();
+        let mut pending_multi_extent = false;
+
+        for rec in records {
+            if rec.is_current() || rec.is_parent() {
+                continue;
+            }
+            if rec.flags & record::flags::ASSOCIATED != 0 {
+                continue;
+            }
+
+            // マルチエクステントファイルの続きのレコードは直前のノードに連結する
+            if pending_multi_extent {
+                if let Some(last) = nodes.last_mut() {
+                    if last.record.identifier == rec.identifier {
+                        last.extents.push((rec.extent_offset(self.logical_block_size), rec.data_length));
+                        last.size += rec.data_length as u64;
+                        pending_multi_extent = rec.is_multi_extent();
+                        continue;
+                    }
+                }
+            }
+            pending_multi_extent = rec.is_multi_extent();
+
+            let rr = match self.mode {
+                NameMode::RockRidge { skip } => rock_ridge::parse(&*self.device, &rec.system_use, skip, self.logical_block_size)?,
+                _ => RockRidgeInfo::default(),
+            };
+
+            // 再配置されたディレクトリは本来の親（CLエントリ）側から見せる
+            if rr.relocated {
+                continue;
+            }
+
+            let name = rr.name.clone().unwrap_or_else(|| rec.name.clone());
+            let mut node = IsoNode {
+                name,
+                extents: vec![(rec.extent_offset(self.logical_block_size), rec.data_length)],
+                size: rec.data_length as u64,
+                record: rec,
+                rock_ridge: rr,
+            };
+
+            if let Some(child) = node.rock_ridge.child_link {
+                self.follow_child_link(&mut node, child)?;
+            }
+
+            nodes.push(node);
+        }
+
+        Ok(nodes)
+    }
+
+    /// CLエントリが指す再配置ディレクトリの実体を読み込む
+    fn follow_child_link(&self, node: &mut IsoNode, location: u32) -> FsResult<()> {
+        let offset = location as u64 * self.logical_block_size as u64;
+        let header = read_bytes(&*self.device, offset, ISO_SECTOR_SIZE as usize)?;
+        let dot = DirectoryRecord::parse(&header[..header[0] as usize], false)?;
+
+        if !dot.is_current() || !dot.is_directory() {
+            log::warn!("ISO9660: CLエントリの参照先がディレクトリではありません (block={})", location);
+            return Err(FsError::CorruptedFs);
+        }
+
+        if let NameMode::RockRidge { skip } = self.mode {
+            let rr = rock_ridge::parse(&*self.device, &dot.system_use, skip, self.logical_block_size)?;
+            node.rock_ridge.mode = rr.mode.or(node.rock_ridge.mode);
+            node.rock_ridge.links = rr.links.or(node.rock_ridge.links);
+        }
+
+        node.extents = vec![(dot.extent_offset(self.logical_block_size), dot.data_length)];
+        node.size = dot.data_length as u64;
+        node.record.extent = dot.extent;
+        node.record.ext_attr_length = dot.ext_attr_length;
+        node.record.data_length = dot.data_length;
+        node.record.flags |= record::flags::DIRECTORY;
+        node.rock_ridge.child_link = None;
+        Ok(())
+    }
+
+    /// 名前を比較
+    fn names_match(&self, entry_name: &str, wanted: &str) -> bool {
+        match self.mode {
+            // 素のISO名は大文字小文字を区別しない（check=relaxed相当）
+            NameMode::Plain => entry_name.eq_ignore_ascii_case(wanted),
+            _ => entry_name == wanted,
+        }
+    }
+
+    /// ディレクトリ内の名前を検索
+    fn lookup(&self, dir: &IsoNode, name: &str) -> FsResult<IsoNode> {
+        self.list_directory(dir)?
+            .into_iter()
+            .find(|node| self.names_match(&node.name, name))
+            .ok_or(FsError::NotFound)
+    }
+
+    /// パスをノードに解決（最終要素のシンボリックリンクはたどらない）
+    fn resolve(&self, path: &str) -> FsResult<IsoNode> {
+        let mut stack: Vec<IsoNode> = vec![self.root.clone()];
+
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if stack.len() > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
+            }
+
+            match component {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                },
+                name => {
+                    let current = stack.last().ok_or(FsError::NotFound)?;
+                    if !current.is_directory() {
+                        return Err(FsError::NotDirectory);
+                    }
+                    let next = self.lookup(current, name)?;
+                    stack.push(next);
+                },
+            }
+        }
+
+        stack.pop().ok_or(FsError::NotFound)
+    }
+
+    /// ファイルデータを読み込み
+    fn read_data(&self, node: &IsoNode, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        if offset >= node.size {
+            return Ok(0);
+        }
+
+        let to_read = core::cmp::min(buffer.len() as u64, node.size - offset) as usize;
+        let mut done = 0usize;
+        let mut extent_start = 0u64;
+
+        for &(disk_offset, len) in &node.extents {
+            let extent_end = extent_start + len as u64;
+            let position = offset + done as u64;
+
+            if position < extent_end && done < to_read {
+                let within = position - extent_start;
+                let chunk = core::cmp::min((extent_end - position) as usize, to_read - done);
+                let data = read_bytes(&*self.device, disk_offset + within, chunk)?;
+                buffer[done..done + chunk].copy_from_slice(&data);
+                done += chunk;
+            }
+
+            extent_start = extent_end;
+            if done >= to_read {
+                break;
+            }
+        }
+
+        Ok(done)
+    }
+}
+
+/// ISO9660ファイルシステム
+pub struct Iso9660Filesystem {
+    /// ファイルシステム名
+    name: String,
+    /// Jolietを使用するか
+    joliet_enabled: bool,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<IsoVolume>>>,
+}
+
+impl Iso9660Filesystem {
+    /// 新しいISO9660ファイルシステムインスタンスを作成
+    pub fn new() -> Self {
+        Self::new_with_joliet(true)
+    }
+
+    /// Jolietの使用有無を指定してインスタンスを作成
+    pub fn new_with_joliet(joliet_enabled: bool) -> Self {
+        Self {
+            name: "iso9660".to_string(),
+            joliet_enabled,
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<IsoVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+}
+
+impl Filesystem for Iso9660Filesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        let mut allow_joliet = self.joliet_enabled;
+        let mut allow_rock = true;
+        for option in options.split(',').map(str::trim) {
+            match option {
+                "nojoliet" => allow_joliet = false,
+                "norock" => allow_rock = false,
+                "rw" => {
+                    log::warn!("ISO9660は読み取り専用です: {}", device);
+                    return Err(FsError::ReadOnly);
+                },
+                _ => {}
+            }
+        }
+
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = IsoVolume::open(device, block_device, allow_joliet, allow_rock)?;
+
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
+
+        log::info!("ISO9660ファイルシステムをマウント: {} -> {}", device, mount_point);
+        Ok(())
+    }
+
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        volume.device.close()?;
+
+        log::info!("ISO9660ファイルシステムをアンマウント: {} ({})", mount_point, volume.device_path);
+        Ok(())
+    }
+
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        if mode != OpenMode::ReadOnly {
+            return Err(FsError::ReadOnly);
+        }
+
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
+
+        if node.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
+
+        Ok(Arc::new(IsoFileHandle { volume, node }))
+    }
+
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
+
+        if !node.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+
+        Ok(Arc::new(IsoDirHandle { volume, node }))
+    }
+
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
+        Ok(node.metadata(volume.logical_block_size))
+    }
+
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        let volumes = self.volumes.read();
+        let volume = volumes.get(mount_point).ok_or(FsError::NotFound)?;
+        let primary = &volume.descriptors.primary;
+
+        let max_filename_length = match volume.mode {
+            NameMode::RockRidge { .. } => 255,
+            NameMode::Joliet => 103,
+            NameMode::Plain => 30,
+        };
+
+        Ok(FsStats {
+            total_blocks: primary.volume_space_size as u64,
+            free_blocks: 0,
+            available_blocks: 0,
+            total_nodes: 0,
+            free_nodes: 0,
+            block_size: primary.logical_block_size as u32,
+            max_filename_length,
+        })
+    }
+
+    fn sync(&self) -> FsResult<()> {
+        // 読み取り専用のため書き戻すデータはない
+        Ok(())
+    }
+
+    fn read_link(&self, mount_point: &str, path: &str) -> FsResult<String> {
+        let volume = self.find_volume(mount_point)?;
+        let node = volume.resolve(path)?;
+        node.rock_ridge.symlink.clone().ok_or(FsError::InvalidData)
+    }
+}
+
+/// ISO9660ファイルハンドル
+struct IsoFileHandle {
+    /// 所属ボリューム
+    volume: Arc<IsoVolume>,
+    /// 対象ノード
+    node: IsoNode,
+}
+
+impl FileHandle for IsoFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        // シンボリックリンクはターゲット文字列を内容として返す
+        if let Some(target) = &self.node.rock_ridge.symlink {
+            let bytes = target.as_bytes();
+            if offset >= bytes.len() as u64 {
+                return Ok(0);
+            }
+            let start = offset as usize;
+            let len = core::cmp::min(buffer.len(), bytes.len() - start);
+            buffer[..len].copy_from_slice(&bytes[start..start + len]);
+            return Ok(len);
+        }
+
+        self.volume.read_data(&self.node, buffer, offset)
+    }
+
+    fn write(&self, _buffer: &[u8], _offset: u64) -> FsResult<usize> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn flush(&self) -> FsResult<()> {
+        Ok(())
+    }
+
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.node.metadata(self.volume.logical_block_size).size)
+    }
+
+    fn resize(&self, _new_size: u64) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.node.metadata(self.volume.logical_block_size))
+    }
+
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        false
+    }
+}
+
+/// ISO9660ディレクトリハンドル
+struct IsoDirHandle {
+    /// 所属ボリューム
+    volume: Arc<IsoVolume>,
+    /// 対象ディレクトリノード
+    node: IsoNode,
+}
+
+impl DirHandle for IsoDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        Ok(self.volume.list_directory(&self.node)?
+            .into_iter()
+            .map(|child| DirEntry {
+                inode: child.inode(),
+                file_type: child.file_type(),
+                name: child.name,
+            })
+            .collect())
+    }
+
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let child = self.volume.lookup(&self.node, name)?;
+        Ok(DirEntry {
+            inode: child.inode(),
+            file_type: child.file_type(),
+            name: child.name,
+        })
+    }
+
+    fn create_file(&self, _name: &str, _permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn create_directory(&self, _name: &str, _permissions: Permissions) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn remove(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn rename(&self, _old_name: &str, _new_name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.node.metadata(self.volume.logical_block_size))
+    }
+}
@@ -0,0 +1,322 @@
+// ISO9660 ディレクトリレコード実装
+//
+// ディレクトリレコードの解析とファイル識別子のデコード
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::super::vfs::BlockDevice;
+use super::descriptor::{read_bytes, read_u32_le, read_u16_le, ISO_SECTOR_SIZE};
+
+/// ディレクトリレコードの固定部サイズ
+const RECORD_HEADER_SIZE: usize = 33;
+
+/// ファイルフラグ
+pub mod flags {
+    /// 存在を隠す
+    pub const HIDDEN: u8 = 0x01;
+    /// ディレクトリ
+    pub const DIRECTORY: u8 = 0x02;
+    /// 関連ファイル
+    pub const ASSOCIATED: u8 = 0x04;
+    /// レコード形式情報あり
+    pub const RECORD: u8 = 0x08;
+    /// 保護情報あり
+    pub const PROTECTION: u8 = 0x10;
+    /// 後続のレコードに続きのエクステントがある
+    pub const MULTI_EXTENT: u8 = 0x80;
+}
+
+/// ISO9660ディレクトリレコード
+#[derive(Debug, Clone)]
+pub struct DirectoryRecord {
+    /// レコード長
+    pub length: u8,
+    /// 拡張属性レコード長
+    pub ext_attr_length: u8,
+    /// エクステントの開始論理ブロック
+    pub extent: u32,
+    /// データ長（バイト）
+    pub data_length: u32,
+    /// 記録日時（UNIXタイムスタンプ）
+    pub recorded: u64,
+    /// ファイルフラグ
+    pub flags: u8,
+    /// インターリーブのファイルユニットサイズ
+    pub file_unit_size: u8,
+    /// インターリーブギャップサイズ
+    pub interleave_gap: u8,
+    /// ボリューム順序番号
+    pub volume_sequence: u16,
+    /// 生のファイル識別子
+    pub identifier: Vec<u8>,
+    /// デコード済みのファイル名
+    pub name: String,
+    /// システム使用領域（Rock Ridge等のSUSPエントリ）
+    pub system_use: Vec<u8>,
+    /// レコード自身のディスク上バイト位置（ディレクトリ走査時のみ設定）
+    pub position: u64,
+}
+
+impl DirectoryRecord {
+    /// ディレクトリレコードをパース
+    pub fn parse(data: &[u8], joliet: bool) -> FsResult<Self> {
+        if data.len() < RECORD_HEADER_SIZE {
+            return Err(FsError::InvalidData);
+        }
+
+        let length = data[0];
+        let name_len = data[32] as usize;
+
+        if (length as usize) < RECORD_HEADER_SIZE + name_len || data.len() < length as usize {
+            return Err(FsError::InvalidData);
+        }
+
+        let identifier = data[RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + name_len].to_vec();
+        let name = decode_identifier(&identifier, joliet);
+
+        // 識別子長が偶数の場合は1バイトのパディングが入る
+        let su_start = RECORD_HEADER_SIZE + name_len + if name_len % 2 == 0 { 1 } else { 0 };
+        let system_use = if su_start < length as usize {
+            data[su_start..length as usize].to_vec()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            length,
+            ext_attr_length: data[1],
+            extent: read_u32_le(data, 2),
+            data_length: read_u32_le(data, 10),
+            recorded: parse_record_datetime(&data[18..25]),
+            flags: data[25],
+            file_unit_size: data[26],
+            interleave_gap: data[27],
+            volume_sequence: read_u16_le(data, 28),
+            identifier,
+            name,
+            system_use,
+            position: 0,
+        })
+    }
+
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        (self.flags & flags::DIRECTORY) != 0
+    }
+
+    /// 後続のレコードにエクステントが続くかどうか
+    pub fn is_multi_extent(&self) -> bool {
+        (self.flags & flags::MULTI_EXTENT) != 0
+    }
+
+    /// 隠しファイルかどうか
+    pub fn is_hidden(&self) -> bool {
+        (self.flags & flags::HIDDEN) != 0
+    }
+
+    /// カレントディレクトリ（"."）レコードかどうか
+    pub fn is_current(&self) -> bool {
+        self.identifier.as_slice() == [0]
+    }
+
+    /// 親ディレクトリ（".."）レコードかどうか
+    pub fn is_parent(&self) -> bool {
+        self.identifier.as_slice() == [1]
+    }
+
+    /// エクステントのバイトオフセット
+    pub fn extent_offset(&self, logical_block_size: u16) -> u64 {
+        (self.extent as u64 + self.ext_attr_length as u64) * logical_block_size as u64
+    }
+}
+
+/// ディレクトリエクステントを読み込んで全レコードを列挙
+///
+/// レコードは論理セクタ境界をまたがないため、長さ0のバイトは
+/// 次のセクタへのパディングとして扱う。
+pub fn read_directory(
+    device: &dyn BlockDevice,
+    directory: &DirectoryRecord,
+    logical_block_size: u16,
+    joliet: bool,
+) -> FsResult<Vec<DirectoryRecord>> {
+    if !directory.is_directory() {
+        return Err(FsError::NotDirectory);
+    }
+
+    let size = directory.data_length as usize;
+    let base = directory.extent_offset(logical_block_size);
+    let data = read_bytes(device, base, size)?;
+
+    let mut records = Vec::new();
+    let mut offset = 0usize;
+
+    while offset < size {
+        let record_len = data[offset] as usize;
+
+        if record_len == 0 {
+            // 次のセクタ境界へ進む
+            let next = (offset / ISO_SECTOR_SIZE as usize + 1) * ISO_SECTOR_SIZE as usize;
+            offset = next;
+            continue;
+        }
+
+        if offset + record_len > size {
+            log::warn!("ISO9660: ディレクトリレコードがエクステント末尾を超えています (offset={})", offset);
+            return Err(FsError::CorruptedFs);
+        }
+
+        let mut record = DirectoryRecord::parse(&data[offset..offset + record_len], joliet)?;
+        record.position = base + offset as u64;
+        records.push(record);
+        offset += record_len;
+    }
+
+    Ok(records)
+}
+
+/// ファイル識別子をデコード
+fn decode_identifier(identifier: &[u8], joliet: bool) -> String {
+    match identifier {
+        [0] => return ".".to_string(),
+        [1] => return "..".to_string(),
+        _ => {}
+    }
+
+    let raw = if joliet {
+        decode_ucs2_be(identifier)
+    } else {
+        // dチャラクタは大文字のみなので、Linuxのmap=normalと同様に小文字化する
+        String::from_utf8_lossy(identifier).to_lowercase()
+    };
+
+    strip_version(&raw).to_string()
+}
+
+/// バージョン番号（";1"）と末尾のピリオドを除去
+pub fn strip_version(name: &str) -> &str {
+    let name = match name.rfind(';') {
+        Some(pos) => &name[..pos],
+        None => name,
+    };
+
+    // 拡張子なしのファイルは "NAME." として記録される
+    if name.len() > 1 && name.ends_with('.') {
+        &name[..name.len() - 1]
+    } else {
+        name
+    }
+}
+
+/// ビッグエンディアンUCS-2文字列をデコード
+pub fn decode_ucs2_be(bytes: &[u8]) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// 7バイトの記録日時（9.1.5）をUNIXタイムスタンプに変換
+pub fn parse_record_datetime(data: &[u8]) -> u64 {
+    if data.len() < 7 || data.iter().all(|&b| b == 0) {
+        return 0;
+    }
+
+    let timestamp = civil_to_unix(
+        1900 + data[0] as i64,
+        data[1] as u32,
+        data[2] as u32,
+        data[3] as u32,
+        data[4] as u32,
+        data[5] as u32,
+    );
+
+    // GMTオフセット（15分単位、符号付き）
+    let offset = data[6] as i8 as i64 * 15 * 60;
+    (timestamp - offset).max(0) as u64
+}
+
+/// グレゴリオ暦の日時をUNIXタイムスタンプ（秒）に変換
+pub fn civil_to_unix(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> i64 {
+    let month = month.clamp(1, 12) as i64;
+    let day = day.clamp(1, 31) as i64;
+
+    // 3月始まりの暦に変換して日数を計算
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_record(name: &[u8], flags: u8, extent: u32, size: u32) -> Vec<u8> {
+        let mut len = RECORD_HEADER_SIZE + name.len();
+        if name.len() % 2 == 0 {
+            len += 1;
+        }
+        let mut data = vec![0u8; len];
+        data[0] = len as u8;
+        data[2..6].copy_from_slice(&extent.to_le_bytes());
+        data[6..10].copy_from_slice(&extent.to_be_bytes());
+        data[10..14].copy_from_slice(&size.to_le_bytes());
+        data[14..18].copy_from_slice(&size.to_be_bytes());
+        data[18..25].copy_from_slice(&[124, 1, 2, 3, 4, 5, 0]);
+        data[25] = flags;
+        data[28] = 1;
+        data[32] = name.len() as u8;
+        data[33..33 + name.len()].copy_from_slice(name);
+        data
+    }
+
+    #[test]
+    fn test_parse_iso_record() {
+        let data = build_record(b"README.TXT;1", 0, 25, 1234);
+        let record = DirectoryRecord::parse(&data, false).unwrap();
+        assert_eq!(record.name, "readme.txt");
+        assert_eq!(record.extent, 25);
+        assert_eq!(record.data_length, 1234);
+        assert!(!record.is_directory());
+        assert_eq!(record.recorded, 1704164645);
+    }
+
+    #[test]
+    fn test_parse_joliet_record() {
+        let name: Vec<u8> = "Mixed Case.txt;1".encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        let data = build_record(&name, flags::DIRECTORY, 30, 2048);
+        let record = DirectoryRecord::parse(&data, true).unwrap();
+        assert_eq!(record.name, "Mixed Case.txt");
+        assert!(record.is_directory());
+    }
+
+    #[test]
+    fn test_special_identifiers() {
+        let current = DirectoryRecord::parse(&build_record(&[0], flags::DIRECTORY, 20, 2048), false).unwrap();
+        let parent = DirectoryRecord::parse(&build_record(&[1], flags::DIRECTORY, 20, 2048), false).unwrap();
+        assert!(current.is_current());
+        assert_eq!(current.name, ".");
+        assert!(parent.is_parent());
+        assert_eq!(parent.name, "..");
+    }
+
+    #[test]
+    fn test_strip_version() {
+        assert_eq!(strip_version("file.;1"), "file");
+        assert_eq!(strip_version("a.b;12"), "a.b");
+        assert_eq!(strip_version("dir"), "dir");
+    }
+
+    #[test]
+    fn test_civil_to_unix() {
+        assert_eq!(civil_to_unix(1970, 1, 1, 0, 0, 0), 0);
+        assert_eq!(civil_to_unix(2000, 3, 1, 0, 0, 0), 951868800);
+    }
+}
@@ -0,0 +1,452 @@
+// ISO9660 Rock Ridge拡張実装
+//
+// SUSP（System Use Sharing Protocol）エントリとRRIPによるPOSIX属性の解析
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::super::vfs::BlockDevice;
+use super::descriptor::{read_bytes, read_u32_le};
+use super::record::{civil_to_unix, parse_record_datetime};
+
+/// 連続領域（CE）をたどる上限（循環参照対策）
+const MAX_CONTINUATIONS: usize = 16;
+
+/// SUSPエントリヘッダサイズ
+const ENTRY_HEADER_SIZE: usize = 4;
+
+/// NM/SLのフラグ
+const FLAG_CONTINUE: u8 = 0x01;
+const FLAG_CURRENT: u8 = 0x02;
+const FLAG_PARENT: u8 = 0x04;
+const FLAG_ROOT: u8 = 0x08;
+
+/// TFのフラグ
+const TF_CREATION: u8 = 0x01;
+const TF_MODIFY: u8 = 0x02;
+const TF_ACCESS: u8 = 0x04;
+const TF_ATTRIBUTES: u8 = 0x08;
+const TF_BACKUP: u8 = 0x10;
+const TF_EXPIRATION: u8 = 0x20;
+const TF_EFFECTIVE: u8 = 0x40;
+const TF_LONG_FORM: u8 = 0x80;
+
+/// POSIXファイルタイプマスク
+pub const S_IFMT: u32 = 0o170000;
+/// ディレクトリ
+pub const S_IFDIR: u32 = 0o040000;
+/// 通常ファイル
+pub const S_IFREG: u32 = 0o100000;
+/// シンボリックリンク
+pub const S_IFLNK: u32 = 0o120000;
+/// ブロックデバイス
+pub const S_IFBLK: u32 = 0o060000;
+/// キャラクタデバイス
+pub const S_IFCHR: u32 = 0o020000;
+/// FIFO
+pub const S_IFIFO: u32 = 0o010000;
+/// ソケット
+pub const S_IFSOCK: u32 = 0o140000;
+
+/// Rock Ridgeで得られたPOSIX属性
+#[derive(Debug, Clone, Default)]
+pub struct RockRidgeInfo {
+    /// POSIXモード（PX）
+    pub mode: Option<u32>,
+    /// ハードリンク数（PX）
+    pub links: Option<u32>,
+    /// 所有者ID（PX）
+    pub uid: Option<u32>,
+    /// グループID（PX）
+    pub gid: Option<u32>,
+    /// シリアル番号（PX、RRIP 1.12）
+    pub serial: Option<u32>,
+    /// 代替名（NM）
+    pub name: Option<String>,
+    /// シンボリックリンクのターゲット（SL）
+    pub symlink: Option<String>,
+    /// 作成時刻（TF）
+    pub created: Option<u64>,
+    /// 変更時刻（TF）
+    pub modified: Option<u64>,
+    /// アクセス時刻（TF）
+    pub accessed: Option<u64>,
+    /// 属性変更時刻（TF）
+    pub attributes_changed: Option<u64>,
+    /// デバイス番号（PN）
+    pub device: Option<(u32, u32)>,
+    /// 再配置された子ディレクトリの位置（CL）
+    pub child_link: Option<u32>,
+    /// 再配置されたディレクトリ自身のエントリか（RE）
+    pub relocated: bool,
+}
+
+impl RockRidgeInfo {
+    /// 何らかのRock Ridge情報を持っているか
+    pub fn is_present(&self) -> bool {
+        self.mode.is_some() || self.name.is_some() || self.symlink.is_some() || self.child_link.is_some()
+    }
+
+    /// シンボリックリンクかどうか
+    pub fn is_symlink(&self) -> bool {
+        matches!(self.mode, Some(mode) if mode & S_IFMT == S_IFLNK) || self.symlink.is_some()
+    }
+}
+
+/// ルートディレクトリの"."レコードからSUSPの有無を判定
+///
+/// SPエントリが見つかればスキップバイト数を返す。
+pub fn detect_susp(root_system_use: &[u8]) -> Option<u8> {
+    if root_system_use.len() < 7 {
+        return None;
+    }
+
+    if &root_system_use[0..2] == b"SP" && root_system_use[4] == 0xBE && root_system_use[5] == 0xEF {
+        Some(root_system_use[6])
+    } else {
+        None
+    }
+}
+
+/// ルートディレクトリのSUSP領域にRock Ridgeの拡張参照（ER）があるか
+pub fn has_rrip_extension(
+    device: &dyn BlockDevice,
+    root_system_use: &[u8],
+    logical_block_size: u16,
+) -> FsResult<bool> {
+    let mut found = false;
+    walk_entries(device, root_system_use, 0, logical_block_size, |sig, entry| {
+        if sig == *b"ER" && entry.len() >= 8 {
+            let id_len = entry[4] as usize;
+            let id = entry.get(8..8 + id_len).unwrap_or(&[]);
+            if id == b"RRIP_1991A" || id == b"IEEE_P1282" || id == b"IEEE_1282" {
+                found = true;
+            }
+        }
+        // 古いmkisofsはERを省略してPX/NMのみ書くことがある
+        if sig == *b"PX" || sig == *b"RR" {
+            found = true;
+        }
+    })?;
+    Ok(found)
+}
+
+/// ディレクトリレコードのシステム使用領域からRock Ridge情報を解析
+pub fn parse(
+    device: &dyn BlockDevice,
+    system_use: &[u8],
+    skip: u8,
+    logical_block_size: u16,
+) -> FsResult<RockRidgeInfo> {
+    let mut info = RockRidgeInfo::default();
+    let mut name = String::new();
+    let mut name_complete = false;
+    let mut link = SymlinkBuilder::default();
+
+    walk_entries(device, system_use, skip as usize, logical_block_size, |sig, entry| {
+        match &sig {
+            b"PX" => parse_px(entry, &mut info),
+            b"NM" if !name_complete && entry.len() > 4 => {
+                let flags = entry[4];
+                if flags & FLAG_CURRENT != 0 {
+                    name.push('.');
+                } else if flags & FLAG_PARENT != 0 {
+                    name.push_str("..");
+                } else {
+                    name.push_str(&String::from_utf8_lossy(&entry[5..]));
+                }
+                name_complete = flags & FLAG_CONTINUE == 0;
+            },
+            b"SL" if entry.len() > 4 => link.push_entry(&entry[5..]),
+            b"TF" if entry.len() > 4 => parse_tf(entry, &mut info),
+            b"PN" if entry.len() >= 20 => {
+                info.device = Some((read_u32_le(entry, 4), read_u32_le(entry, 12)));
+            },
+            b"CL" if entry.len() >= 8 => info.child_link = Some(read_u32_le(entry, 4)),
+            b"RE" => info.relocated = true,
+            _ => {}
+        }
+    })?;
+
+    if !name.is_empty() {
+        info.name = Some(name);
+    }
+    if let Some(target) = link.finish() {
+        info.symlink = Some(target);
+    }
+
+    Ok(info)
+}
+
+/// SUSPエントリを順に走査（CEによる連続領域も含む）
+fn walk_entries<F>(
+    device: &dyn BlockDevice,
+    system_use: &[u8],
+    skip: usize,
+    logical_block_size: u16,
+    mut visit: F,
+) -> FsResult<()>
+where
+    F: FnMut([u8; 2], &[u8]),
+{
+    let mut area: Vec<u8> = system_use.get(skip..).unwrap_or(&[]).to_vec();
+    let mut continuations = 0;
+
+    loop {
+        let mut next_area = None;
+        let mut offset = 0usize;
+
+        while offset + ENTRY_HEADER_SIZE <= area.len() {
+            let sig = [area[offset], area[offset + 1]];
+            let len = area[offset + 2] as usize;
+
+            if len < ENTRY_HEADER_SIZE || offset + len > area.len() {
+                // パディングまたは破損エントリ
+                break;
+            }
+
+            let entry = &area[offset..offset + len];
+
+            match &sig {
+                b"ST" => break,
+                b"CE" if len >= 28 => {
+                    let block = read_u32_le(entry, 4) as u64;
+                    let ce_offset = read_u32_le(entry, 12) as u64;
+                    let ce_len = read_u32_le(entry, 20) as u64;
+                    // 連続領域は1論理ブロック内に収まる（SUSP 5.1）。媒体上の長さで確保しない
+                    if ce_offset + ce_len > logical_block_size as u64 {
+                        log::warn!("ISO9660: Rock Ridge連続領域がブロックを超えています (オフセット{}, 長さ{})", ce_offset, ce_len);
+                        return Err(FsError::CorruptedFs);
+                    }
+                    next_area = Some((block * logical_block_size as u64 + ce_offset, ce_len as usize));
+                },
+                _ => visit(sig, entry),
+            }
+
+            offset += len;
+        }
+
+        match next_area {
+            Some((position, len)) if continuations < MAX_CONTINUATIONS => {
+                area = read_bytes(device, position, len)?;
+                continuations += 1;
+            },
+            Some(_) => {
+                log::warn!("ISO9660: Rock Ridge連続領域が多すぎます");
+                return Err(FsError::CorruptedFs);
+            },
+            None => return Ok(()),
+        }
+    }
+}
+
+/// PXエントリを解析
+fn parse_px(entry: &[u8], info: &mut RockRidgeInfo) {
+    if entry.len() < 36 {
+        return;
+    }
+
+    info.mode = Some(read_u32_le(entry, 4));
+    info.links = Some(read_u32_le(entry, 12));
+    info.uid = Some(read_u32_le(entry, 20));
+    info.gid = Some(read_u32_le(entry, 28));
+
+    if entry.len() >= 44 {
+        info.serial = Some(read_u32_le(entry, 36));
+    }
+}
+
+/// TFエントリを解析
+fn parse_tf(entry: &[u8], info: &mut RockRidgeInfo) {
+    let flags = entry[4];
+    let long_form = flags & TF_LONG_FORM != 0;
+    let stamp_len = if long_form { 17 } else { 7 };
+    let mut offset = 5;
+
+    for bit in [TF_CREATION, TF_MODIFY, TF_ACCESS, TF_ATTRIBUTES, TF_BACKUP, TF_EXPIRATION, TF_EFFECTIVE] {
+        if flags & bit == 0 {
+            continue;
+        }
+        if offset + stamp_len > entry.len() {
+            return;
+        }
+
+        let stamp = &entry[offset..offset + stamp_len];
+        let value = if long_form { parse_long_datetime(stamp) } else { parse_record_datetime(stamp) };
+
+        match bit {
+            TF_CREATION => info.created = Some(value),
+            TF_MODIFY => info.modified = Some(value),
+            TF_ACCESS => info.accessed = Some(value),
+            TF_ATTRIBUTES => info.attributes_changed = Some(value),
+            _ => {}
+        }
+
+        offset += stamp_len;
+    }
+}
+
+/// 17バイト形式の日時を変換
+fn parse_long_datetime(data: &[u8]) -> u64 {
+    let digits = |range: core::ops::Range<usize>| -> u32 {
+        data[range].iter().fold(0u32, |acc, &b| acc * 10 + b.wrapping_sub(b'0').min(9) as u32)
+    };
+
+    let year = digits(0..4);
+    if year == 0 {
+        return 0;
+    }
+
+    let timestamp = civil_to_unix(year as i64, digits(4..6), digits(6..8), digits(8..10), digits(10..12), digits(12..14));
+    let offset = data[16] as i8 as i64 * 15 * 60;
+    (timestamp - offset).max(0) as u64
+}
+
+/// SLエントリのコンポーネントからリンクターゲットを組み立てる
+#[derive(Default)]
+struct SymlinkBuilder {
+    /// 組み立て中のパス
+    path: String,
+    /// 直前のコンポーネントが継続中か
+    component_open: bool,
+    /// コンポーネントが1つ以上あるか
+    any: bool,
+}
+
+impl SymlinkBuilder {
+    /// SLエントリのコンポーネント領域を追加
+    fn push_entry(&mut self, mut components: &[u8]) {
+        while components.len() >= 2 {
+            let flags = components[0];
+            let len = components[1] as usize;
+            if components.len() < 2 + len {
+                break;
+            }
+            let content = &components[2..2 + len];
+
+            if !self.component_open && self.any && !self.path.ends_with('/') {
+                self.path.push('/');
+            }
+
+            if flags & FLAG_ROOT != 0 {
+                self.path.clear();
+                self.path.push('/');
+            } else if flags & FLAG_PARENT != 0 {
+                self.path.push_str("..");
+            } else if flags & FLAG_CURRENT != 0 {
+                self.path.push('.');
+            } else {
+                self.path.push_str(&String::from_utf8_lossy(content));
+            }
+
+            self.any = true;
+            self.component_open = flags & FLAG_CONTINUE != 0;
+            components = &components[2 + len..];
+        }
+    }
+
+    /// 組み立て結果を取得
+    fn finish(self) -> Option<String> {
+        if self.any { Some(self.path) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::memory_disk::MemoryDisk;
+
+    /// 論理ブロックサイズ
+    const BLOCK: u16 = 2048;
+
+    fn entry(sig: &[u8; 2], payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![sig[0], sig[1], (payload.len() + 4) as u8, 1];
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// `block`の`offset`から`len`バイトを指すCEエントリ
+    fn ce(block: u32, offset: u32, len: u32) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for value in [block, offset, len] {
+            payload.extend_from_slice(&value.to_le_bytes());
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+        entry(b"CE", &payload)
+    }
+
+    /// ブロック1に`area`を置いたディスク
+    fn disk_with_area(area: &[u8]) -> MemoryDisk {
+        let mut image = vec![0u8; BLOCK as usize * 2];
+        image[BLOCK as usize..BLOCK as usize + area.len()].copy_from_slice(area);
+        MemoryDisk::new(image)
+    }
+
+    #[test]
+    fn test_continuation_area() {
+        let px = entry(b"PX", &[0; 32]);
+        let disk = disk_with_area(&px);
+        let system_use = ce(1, 0, px.len() as u32);
+        assert!(has_rrip_extension(&disk, &system_use, BLOCK).unwrap());
+    }
+
+    #[test]
+    fn test_continuation_length_is_bounded() {
+        // 媒体上の長さがブロックを超える（4GiB近い）CEは確保せずに拒否する
+        let disk = disk_with_area(&[]);
+        let system_use = ce(1, 0, u32::MAX - 1);
+        assert!(matches!(has_rrip_extension(&disk, &system_use, BLOCK), Err(FsError::CorruptedFs)));
+        let system_use = ce(1, BLOCK as u32 - 4, 8);
+        assert!(matches!(has_rrip_extension(&disk, &system_use, BLOCK), Err(FsError::CorruptedFs)));
+    }
+
+    #[test]
+    fn test_continuation_loop() {
+        // 自分自身を指すCEはたどる回数の上限で打ち切る
+        let looped = ce(1, 0, 28);
+        let disk = disk_with_area(&looped);
+        assert!(matches!(has_rrip_extension(&disk, &looped, BLOCK), Err(FsError::CorruptedFs)));
+    }
+
+    #[test]
+    fn test_symlink_components() {
+        let mut builder = SymlinkBuilder::default();
+        // "/usr/lib/../share"
+        builder.push_entry(&[FLAG_ROOT, 0, 0, 3, b'u', b's', b'r', 0, 3, b'l', b'i', b'b', FLAG_PARENT, 0]);
+        builder.push_entry(&[0, 5, b's', b'h', b'a', b'r', b'e']);
+        assert_eq!(builder.finish().as_deref(), Some("/usr/lib/../share"));
+    }
+
+    #[test]
+    fn test_symlink_continued_component() {
+        let mut builder = SymlinkBuilder::default();
+        builder.push_entry(&[FLAG_CONTINUE, 3, b'l', b'o', b'n']);
+        builder.push_entry(&[0, 4, b'g', b'n', b'a', b'm']);
+        assert_eq!(builder.finish().as_deref(), Some("longnam"));
+    }
+
+    #[test]
+    fn test_detect_susp() {
+        let sp = entry(b"SP", &[0xBE, 0xEF, 0]);
+        assert_eq!(detect_susp(&sp), Some(0));
+        assert_eq!(detect_susp(&entry(b"PX", &[0; 32])), None);
+    }
+
+    #[test]
+    fn test_parse_px_and_tf() {
+        let mut info = RockRidgeInfo::default();
+        let mut px = vec![0u8; 44];
+        px[4..8].copy_from_slice(&(S_IFREG | 0o644).to_le_bytes());
+        px[12..16].copy_from_slice(&1u32.to_le_bytes());
+        px[20..24].copy_from_slice(&1000u32.to_le_bytes());
+        px[28..32].copy_from_slice(&100u32.to_le_bytes());
+        parse_px(&px, &mut info);
+        assert_eq!(info.mode, Some(S_IFREG | 0o644));
+        assert_eq!(info.uid, Some(1000));
+        assert_eq!(info.gid, Some(100));
+
+        let tf = entry(b"TF", &[TF_MODIFY, 70, 1, 2, 0, 0, 0, 0]);
+        parse_tf(&tf, &mut info);
+        assert_eq!(info.modified, Some(86400));
+    }
+}
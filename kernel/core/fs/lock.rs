@@ -0,0 +1,413 @@
+// ファイルロック
+//
+// fcntl形式のバイト範囲ロック（所有者はプロセス）とflock形式のファイル全体のロック
+// （所有者はオープンファイル記述）を管理する。ファイルはページキャッシュのマッピングIDで
+// 識別するため、同じファイルを別々に開いたハンドルの間でも競合する。Linuxと同様に
+// 2種類のロックは互いに干渉しない。待つときはファイルごとの待ち行列に載って眠り、
+// ロックが外れたときに起こされて再試行する。待機中のバイト範囲ロックは待ち合わせ
+// グラフに載せてデッドロックを検出する
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::core::sync::Mutex;
+use crate::core::process::{self, BlockReason, ProcessId, Thread};
+use super::{FsError, FsResult};
+
+/// ロックの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// 共有（読み込み）ロック
+    Shared,
+    /// 排他（書き込み）ロック
+    Exclusive,
+}
+
+impl LockKind {
+    /// `lock(exclusive)`の引数から変換
+    pub fn from_exclusive(exclusive: bool) -> Self {
+        if exclusive { Self::Exclusive } else { Self::Shared }
+    }
+    
+    /// 同じ範囲に同時に置けないか
+    fn conflicts_with(self, other: LockKind) -> bool {
+        self == Self::Exclusive || other == Self::Exclusive
+    }
+}
+
+/// バイト範囲ロック
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeLock {
+    /// 種類
+    pub kind: LockKind,
+    /// 開始オフセット
+    pub start: u64,
+    /// 終端オフセット（含まない。`u64::MAX`ならファイル末尾以降すべて）
+    pub end: u64,
+    /// 所有するプロセス
+    pub pid: ProcessId,
+}
+
+impl RangeLock {
+    /// `[start, end)`と重なるか
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// ファイルごとのロック
+#[derive(Debug, Default)]
+struct FileLocks {
+    /// バイト範囲ロック（同じプロセスの範囲は重ならず、隣接する同じ種類の範囲はまとめる）
+    ranges: Vec<RangeLock>,
+    /// flockロック（オープンファイル記述, 種類）
+    whole: Vec<(usize, LockKind)>,
+}
+
+impl FileLocks {
+    /// ロックが1つもないか
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty() && self.whole.is_empty()
+    }
+}
+
+/// ロック表
+#[derive(Default)]
+struct LockTable {
+    /// マッピングIDごとのロック
+    files: BTreeMap<usize, FileLocks>,
+    /// バイト範囲ロックを待っているプロセスと、待っている相手（待ち合わせグラフ）
+    waiting: BTreeMap<ProcessId, Vec<ProcessId>>,
+    /// マッピングIDごとの、ロックの解除を待って眠っているスレッド
+    sleepers: BTreeMap<usize, Vec<Arc<Thread>>>,
+}
+
+impl LockTable {
+    /// `pid`の`[start, end)`と競合するロック
+    fn range_conflicts(&self, file: usize, pid: ProcessId, kind: LockKind, start: u64, end: u64) -> impl Iterator<Item = &RangeLock> {
+        self.files.get(&file).into_iter()
+            .flat_map(|locks| locks.ranges.iter())
+            .filter(move |lock| lock.pid != pid && lock.overlaps(start, end) && lock.kind.conflicts_with(kind))
+    }
+    
+    /// `pid`が`holders`を待つとデッドロックになるか（待ち合わせをたどって`pid`に戻るか）
+    fn would_deadlock(&self, pid: ProcessId, holders: &[ProcessId]) -> bool {
+        let mut pending = holders.to_vec();
+        let mut seen = BTreeSet::new();
+        while let Some(holder) = pending.pop() {
+            if holder == pid {
+                return true;
+            }
+            if seen.insert(holder) {
+                pending.extend(self.waiting.get(&holder).into_iter().flatten());
+            }
+        }
+        false
+    }
+    
+    /// バイト範囲ロックを試みる（`kind`が`None`なら解除）
+    ///
+    /// 取得できれば`Ok(true)`、待つ必要があれば待ち合わせを登録して`Ok(false)`を返す。
+    fn try_range(&mut self, file: usize, pid: ProcessId, kind: Option<LockKind>, start: u64, end: u64, wait: bool) -> FsResult<bool> {
+        if let Some(kind) = kind {
+            let mut holders: Vec<ProcessId> = self.range_conflicts(file, pid, kind, start, end).map(|lock| lock.pid).collect();
+            if !holders.is_empty() {
+                holders.sort_unstable();
+                holders.dedup();
+                if !wait {
+                    self.waiting.remove(&pid);
+                    return Err(FsError::ResourceBusy);
+                }
+                if self.would_deadlock(pid, &holders) {
+                    self.waiting.remove(&pid);
+                    log::debug!("ファイルロック: プロセス{:?}の待機はデッドロックになります", pid);
+                    return Err(FsError::Deadlock);
+                }
+                self.waiting.insert(pid, holders);
+                return Ok(false);
+            }
+        }
+        
+        self.waiting.remove(&pid);
+        let locks = self.files.entry(file).or_default();
+        apply_range(&mut locks.ranges, pid, kind, start, end);
+        self.forget_if_unused(file);
+        self.wake(file);
+        Ok(true)
+    }
+    
+    /// flockロックを試みる（`kind`が`None`なら解除）。取得できれば`true`
+    ///
+    /// Linuxと同様、種類の変更では先に既存のロックを外すため、変更を待つ間は何も持たない。
+    fn try_whole(&mut self, file: usize, description: usize, kind: Option<LockKind>) -> bool {
+        let locks = self.files.entry(file).or_default();
+        let held = locks.whole.len();
+        locks.whole.retain(|&(owner, _)| owner != description);
+        // 外したロックがあるときだけ起こす（失敗した再試行どうしで起こし合わない）
+        let released = locks.whole.len() != held;
+        let acquired = match kind {
+            Some(kind) if locks.whole.iter().any(|&(_, held)| held.conflicts_with(kind)) => false,
+            Some(kind) => {
+                locks.whole.push((description, kind));
+                true
+            },
+            None => true,
+        };
+        self.forget_if_unused(file);
+        if released {
+            self.wake(file);
+        }
+        acquired
+    }
+    
+    /// 現在のスレッドを`file`の待ち行列に載せてブロック状態にする
+    ///
+    /// 表を持ったままブロック状態にするため、表を放してから眠るまでの間にロックが
+    /// 外れても起こし損ねない。
+    fn sleep(&mut self, file: usize) {
+        let thread = process::current_thread();
+        let sleepers = self.sleepers.entry(file).or_default();
+        if !sleepers.iter().any(|sleeper| Arc::ptr_eq(sleeper, &thread)) {
+            sleepers.push(thread);
+        }
+        process::block_current(BlockReason::Lock);
+    }
+    
+    /// `file`のロックが変わったので、待っているスレッドをすべて起こす（起きたら取得し直す）
+    fn wake(&mut self, file: usize) {
+        for thread in self.sleepers.remove(&file).into_iter().flatten() {
+            process::unblock_thread(&thread);
+        }
+    }
+    
+    /// 終了したプロセスのバイト範囲ロックと待ち合わせを外し、そのロックを待っていたスレッドを起こす
+    fn release_process(&mut self, pid: ProcessId) {
+        self.waiting.remove(&pid);
+        // 終了したプロセスのスレッドは起こさない
+        for sleepers in self.sleepers.values_mut() {
+            sleepers.retain(|thread| thread.process.upgrade().is_some_and(|process| process.get_id() != pid));
+        }
+        self.sleepers.retain(|_, sleepers| !sleepers.is_empty());
+        let mut released = Vec::new();
+        for (&file, locks) in self.files.iter_mut() {
+            let before = locks.ranges.len();
+            locks.ranges.retain(|lock| lock.pid != pid);
+            if locks.ranges.len() != before {
+                released.push(file);
+            }
+        }
+        self.files.retain(|_, locks| !locks.is_empty());
+        for file in released {
+            self.wake(file);
+        }
+    }
+    
+    /// ロックがなくなったファイルを表から外す
+    fn forget_if_unused(&mut self, file: usize) {
+        if self.files.get(&file).is_some_and(FileLocks::is_empty) {
+            self.files.remove(&file);
+        }
+    }
+}
+
+/// `pid`の範囲ロックのうち`[start, end)`を`kind`に置き換える（`None`なら取り除く）
+fn apply_range(ranges: &mut Vec<RangeLock>, pid: ProcessId, kind: Option<LockKind>, start: u64, end: u64) {
+    let mut result = Vec::with_capacity(ranges.len() + 2);
+    for lock in ranges.drain(..) {
+        if lock.pid != pid || !lock.overlaps(start, end) {
+            result.push(lock);
+            continue;
+        }
+        // 重なった部分を取り除き、両端の残りは元の種類のまま残す
+        if lock.start < start {
+            result.push(RangeLock { end: start, ..lock });
+        }
+        if lock.end > end {
+            result.push(RangeLock { start: end, ..lock });
+        }
+    }
+    
+    if let Some(kind) = kind {
+        let mut merged = RangeLock { kind, start, end, pid };
+        result.retain(|lock| {
+            let adjacent = lock.pid == pid && lock.kind == kind && (lock.end == merged.start || lock.start == merged.end);
+            if adjacent {
+                merged.start = merged.start.min(lock.start);
+                merged.end = merged.end.max(lock.end);
+            }
+            !adjacent
+        });
+        result.push(merged);
+    }
+    
+    result.sort_by_key(|lock| (lock.start, lock.pid));
+    *ranges = result;
+}
+
+/// ロック表
+static LOCKS: Mutex<LockTable> = Mutex::new(LockTable {
+    files: BTreeMap::new(),
+    waiting: BTreeMap::new(),
+    sleepers: BTreeMap::new(),
+});
+
+/// `pid`としてバイト範囲ロックを設定する（`kind`が`None`なら解除）
+///
+/// 競合すると、`wait`なら解除されるまで待ち、そうでなければResourceBusyを返す。
+/// 待つとデッドロックになる場合はDeadlockを返す。
+pub(super) fn set_range(file: usize, pid: ProcessId, kind: Option<LockKind>, start: u64, end: u64, wait: bool) -> FsResult<()> {
+    if start >= end {
+        return Err(FsError::InvalidData);
+    }
+    loop {
+        let mut table = LOCKS.lock();
+        if table.try_range(file, pid, kind, start, end, wait)? {
+            return Ok(());
+        }
+        table.sleep(file);
+        drop(table);
+        process::schedule();
+    }
+}
+
+/// `pid`が`[start, end)`に`kind`のロックを置くときに競合するロック（fcntlのF_GETLK）
+pub(super) fn test_range(file: usize, pid: ProcessId, kind: LockKind, start: u64, end: u64) -> Option<RangeLock> {
+    LOCKS.lock().range_conflicts(file, pid, kind, start, end).next().copied()
+}
+
+/// オープンファイル記述`description`としてflockロックを設定する（`kind`が`None`なら解除）
+///
+/// 競合すると、`wait`なら解除されるまで待ち、そうでなければResourceBusyを返す。
+pub(super) fn set_whole(file: usize, description: usize, kind: Option<LockKind>, wait: bool) -> FsResult<()> {
+    loop {
+        let mut table = LOCKS.lock();
+        if table.try_whole(file, description, kind) {
+            return Ok(());
+        }
+        if !wait {
+            return Err(FsError::ResourceBusy);
+        }
+        table.sleep(file);
+        drop(table);
+        process::schedule();
+    }
+}
+
+/// プロセスがファイルのディスクリプタを閉じたとき、そのファイルのバイト範囲ロックを外す
+pub(super) fn release_file(file: usize, pid: ProcessId) {
+    let mut table = LOCKS.lock();
+    if let Some(locks) = table.files.get_mut(&file) {
+        locks.ranges.retain(|lock| lock.pid != pid);
+        table.forget_if_unused(file);
+        table.wake(file);
+    }
+}
+
+/// オープンファイル記述が閉じられたとき、そのflockロックを外す
+pub(super) fn release_description(file: usize, description: usize) {
+    LOCKS.lock().try_whole(file, description, None);
+}
+
+/// 終了したプロセスのバイト範囲ロックと待ち合わせをすべて外す（プロセスの終了処理から呼ばれる）
+pub(super) fn release_process(pid: ProcessId) {
+    LOCKS.lock().release_process(pid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// テスト用のバイト範囲ロック
+    fn range(kind: LockKind, start: u64, end: u64, pid: ProcessId) -> RangeLock {
+        RangeLock { kind, start, end, pid }
+    }
+    
+    #[test]
+    fn range_locks_split_merge_and_conflict() {
+        use LockKind::*;
+        let mut table = LockTable::default();
+        assert!(matches!(table.try_range(1, ProcessId(10), Some(Shared), 0, 100, false), Ok(true)));
+        // 中央を排他に変えると両端が共有のまま残る
+        assert!(matches!(table.try_range(1, ProcessId(10), Some(Exclusive), 40, 60, false), Ok(true)));
+        assert_eq!(table.files[&1].ranges, [range(Shared, 0, 40, ProcessId(10)), range(Exclusive, 40, 60, ProcessId(10)),
+                                           range(Shared, 60, 100, ProcessId(10))]);
+        // 共有に戻すと隣とまとまる
+        assert!(matches!(table.try_range(1, ProcessId(10), Some(Shared), 40, 60, false), Ok(true)));
+        assert_eq!(table.files[&1].ranges, [range(Shared, 0, 100, ProcessId(10))]);
+        
+        // 他のプロセスは共有なら重ねられ、排他は待たないとResourceBusy
+        assert!(matches!(table.try_range(1, ProcessId(20), Some(Shared), 50, u64::MAX, false), Ok(true)));
+        assert!(matches!(table.try_range(1, ProcessId(30), Some(Exclusive), 90, 95, false), Err(FsError::ResourceBusy)));
+        assert_eq!(table.range_conflicts(1, ProcessId(30), Exclusive, 100, 200).map(|lock| lock.pid).collect::<Vec<_>>(), [ProcessId(20)]);
+        assert_eq!(table.range_conflicts(2, ProcessId(30), Exclusive, 0, 10).count(), 0);
+        
+        // 解除すると表から消える
+        assert!(matches!(table.try_range(1, ProcessId(10), None, 0, u64::MAX, false), Ok(true)));
+        assert!(matches!(table.try_range(1, ProcessId(20), None, 0, u64::MAX, false), Ok(true)));
+        assert!(table.files.is_empty());
+    }
+    
+    #[test]
+    fn waits_form_graph_and_detect_deadlock() {
+        use LockKind::*;
+        let mut table = LockTable::default();
+        table.try_range(1, ProcessId(10), Some(Exclusive), 0, 10, false).unwrap();
+        table.try_range(1, ProcessId(20), Some(Exclusive), 10, 20, false).unwrap();
+        table.try_range(1, ProcessId(30), Some(Exclusive), 20, 30, false).unwrap();
+        
+        // 10→20→30と待ち、30が10を待つと輪になる
+        assert!(matches!(table.try_range(1, ProcessId(10), Some(Exclusive), 10, 11, true), Ok(false)));
+        assert!(matches!(table.try_range(1, ProcessId(20), Some(Shared), 25, 26, true), Ok(false)));
+        assert!(matches!(table.try_range(1, ProcessId(30), Some(Shared), 5, 6, true), Err(FsError::Deadlock)));
+        assert!(!table.waiting.contains_key(&ProcessId(30)));
+        
+        // 待ち合わせの相手がいなくなれば眠っているスレッドを起こし、取得でき、待ち合わせも外れる
+        table.sleepers.entry(1).or_default().push(process::current_thread());
+        table.try_range(1, ProcessId(30), None, 0, u64::MAX, false).unwrap();
+        assert!(table.sleepers.is_empty());
+        assert!(matches!(table.try_range(1, ProcessId(20), Some(Shared), 25, 26, true), Ok(true)));
+        assert_eq!(table.waiting.keys().copied().collect::<Vec<_>>(), [ProcessId(10)]);
+    }
+    
+    #[test]
+    fn whole_file_locks_belong_to_descriptions() {
+        use LockKind::*;
+        let mut table = LockTable::default();
+        assert!(table.try_whole(1, 0x100, Some(Shared)));
+        assert!(table.try_whole(1, 0x200, Some(Shared)));
+        assert!(!table.try_whole(1, 0x300, Some(Exclusive)));
+        // バイト範囲ロックとは干渉しない
+        assert!(matches!(table.try_range(1, ProcessId(10), Some(Exclusive), 0, u64::MAX, false), Ok(true)));
+        
+        // 変更では先に自分のロックを外す
+        assert!(!table.try_whole(1, 0x100, Some(Exclusive)));
+        assert_eq!(table.files[&1].whole, [(0x200, Shared)]);
+        assert!(table.try_whole(1, 0x200, None));
+        // 外すと眠っているスレッドを起こすが、取れなかった再試行では起こさない
+        table.sleepers.entry(1).or_default().push(process::current_thread());
+        assert!(table.try_whole(1, 0x300, Some(Exclusive)));
+        assert!(!table.try_whole(1, 0x100, Some(Shared)));
+        assert_eq!(table.sleepers[&1].len(), 1);
+        assert!(table.try_whole(1, 0x300, None));
+        assert!(table.sleepers.is_empty());
+    }
+    
+    #[test]
+    fn exiting_holder_wakes_waiters() {
+        use LockKind::*;
+        let mut table = LockTable::default();
+        table.try_range(1, ProcessId(10), Some(Exclusive), 0, 10, false).unwrap();
+        table.try_range(2, ProcessId(10), Some(Shared), 0, 10, false).unwrap();
+        
+        // 20が10のロックを待って眠る
+        assert!(matches!(table.try_range(1, ProcessId(20), Some(Exclusive), 5, 6, true), Ok(false)));
+        table.sleepers.entry(1).or_default().push(process::current_thread());
+        
+        // 10が終了するとロックがすべて外れ、眠っていたスレッドが起きて待っていた範囲を取得できる
+        table.release_process(ProcessId(10));
+        assert!(table.sleepers.is_empty());
+        assert!(!table.files.contains_key(&2));
+        assert!(matches!(table.try_range(1, ProcessId(20), Some(Exclusive), 5, 6, true), Ok(true)));
+        assert!(table.waiting.is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+// ループデバイス
+//
+// 任意のファイルハンドルをブロックデバイスとして見せ、別のファイルシステム上の
+// ディスクイメージ（ESP上のext4イメージやtmpfs上のISOなど）をマウントできるようにする。
+// 接続したデバイスはdevfsに/dev/loop<n>として登録され、パーティションテーブルも読まれる
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+use super::{FsError, FsResult, OpenMode, BlockDevice, FileHandle};
+use super::devfs::{self, DeviceClass, DeviceOps};
+
+/// 裏のファイルのサイズを読み直して容量を更新するioctl
+pub const LOOP_SET_CAPACITY: u32 = 0x4C07;
+
+/// 最小のブロックサイズ
+const MIN_BLOCK_SIZE: u64 = 512;
+/// 最大のブロックサイズ（1ページ）
+const MAX_BLOCK_SIZE: u64 = 4096;
+/// ループデバイスのデバイスIDの上位ビット
+const LOOP_DEVICE_ID_BASE: u64 = 0x4C4F_4F50 << 32;
+
+/// 接続中のループデバイス（番号 => デバイス）
+static LOOP_DEVICES: Mutex<BTreeMap<u32, Arc<LoopDevice>>> = Mutex::new(BTreeMap::new());
+
+/// デバイス番号を持たないループデバイスに割り当てるデバイスID
+static NEXT_ANONYMOUS_ID: AtomicU64 = AtomicU64::new(1 << 31);
+
+/// ループデバイスの設定
+#[derive(Debug, Clone, Copy)]
+pub struct LoopConfig {
+    /// ブロックサイズ（512〜4096の2の累乗）
+    pub block_size: u64,
+    /// ファイル内のデバイス先頭の位置（バイト単位）
+    pub offset: u64,
+    /// デバイスの最大サイズ（バイト単位、Noneならファイル末尾まで）
+    pub size_limit: Option<u64>,
+    /// 読み取り専用にするか
+    pub read_only: bool,
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        Self {
+            block_size: 512,
+            offset: 0,
+            size_limit: None,
+            read_only: false,
+        }
+    }
+}
+
+/// 接続中のループデバイスの情報
+#[derive(Debug, Clone)]
+pub struct LoopInfo {
+    /// /dev からの相対名
+    pub name: String,
+    /// 裏のファイルのパス（ハンドルから接続した場合はNone）
+    pub backing_file: Option<String>,
+    /// 設定
+    pub config: LoopConfig,
+    /// 容量（バイト単位）
+    pub size: u64,
+}
+
+/// ファイルを裏に持つブロックデバイス
+pub struct LoopDevice {
+    /// デバイスID
+    device_id: u64,
+    /// 裏のファイル
+    file: Arc<dyn FileHandle>,
+    /// 裏のファイルのパス
+    backing_file: Option<String>,
+    /// 設定
+    config: LoopConfig,
+    /// 総ブロック数（LOOP_SET_CAPACITYで更新）
+    total_blocks: AtomicU64,
+}
+
+impl LoopDevice {
+    /// ファイルハンドルからループデバイスを作成
+    ///
+    /// 書き込めないファイルは読み取り専用として扱う
+    pub fn new(file: Arc<dyn FileHandle>, mut config: LoopConfig) -> FsResult<Self> {
+        if !config.block_size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&config.block_size) {
+            return Err(FsError::InvalidData);
+        }
+        if !file.can_read() {
+            return Err(FsError::PermissionDenied);
+        }
+        if !config.read_only && !file.can_write() {
+            log::info!("ループデバイス: 書き込めないファイルのため読み取り専用で接続します");
+            config.read_only = true;
+        }
+        
+        let device = Self {
+            device_id: NEXT_ANONYMOUS_ID.fetch_add(1, Ordering::Relaxed),
+            file,
+            backing_file: None,
+            config,
+            total_blocks: AtomicU64::new(0),
+        };
+        device.update_capacity()?;
+        Ok(device)
+    }
+    
+    /// 設定
+    pub fn config(&self) -> LoopConfig {
+        self.config
+    }
+    
+    /// 裏のファイルのサイズから容量を計算し直す
+    pub fn update_capacity(&self) -> FsResult<u64> {
+        let available = self.file.size()?.saturating_sub(self.config.offset);
+        let size = self.config.size_limit.map_or(available, |limit| limit.min(available));
+        let blocks = size / self.config.block_size;
+        self.total_blocks.store(blocks, Ordering::Release);
+        Ok(blocks)
+    }
+    
+    /// デバイス上の範囲をファイル上の位置に変換
+    fn file_offset(&self, start_block: u64, count: u64) -> FsResult<u64> {
+        match start_block.checked_add(count) {
+            Some(end) if end <= self.total_blocks() => Ok(self.config.offset + start_block * self.config.block_size),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+}
+
+impl BlockDevice for LoopDevice {
+    fn device_id(&self) -> u64 {
+        self.device_id
+    }
+    
+    fn block_size(&self) -> u64 {
+        self.config.block_size
+    }
+    
+    fn total_blocks(&self) -> u64 {
+        self.total_blocks.load(Ordering::Acquire)
+    }
+    
+    fn read_block(&self, block_index: u64) -> FsResult<Vec<u8>> {
+        self.read_blocks(block_index, 1)
+    }
+    
+    fn read_blocks(&self, start_block: u64, count: u64) -> FsResult<Vec<u8>> {
+        let offset = self.file_offset(start_block, count)?;
+        let mut buffer = vec![0u8; (count * self.config.block_size) as usize];
+        
+        // ファイルが途中で縮んでいれば残りは0のまま
+        let mut done = 0;
+        while done < buffer.len() {
+            let read = self.file.read(&mut buffer[done..], offset + done as u64)?;
+            if read == 0 {
+                break;
+            }
+            done += read;
+        }
+        Ok(buffer)
+    }
+    
+    fn write_block(&self, block_index: u64, data: &[u8]) -> FsResult<()> {
+        self.write_blocks(block_index, data)
+    }
+    
+    fn write_blocks(&self, start_block: u64, data: &[u8]) -> FsResult<()> {
+        if self.config.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let count = (data.len() as u64).div_ceil(self.config.block_size);
+        let offset = self.file_offset(start_block, count)?;
+        
+        let mut done = 0;
+        while done < data.len() {
+            let written = self.file.write(&data[done..], offset + done as u64)?;
+            if written == 0 {
+                return Err(FsError::IoError);
+            }
+            done += written;
+        }
+        Ok(())
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        self.file.fsync()
+    }
+    
+    fn close(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn ioctl(&self, request: u32, _arg: usize) -> FsResult<usize> {
+        match request {
+            LOOP_SET_CAPACITY => self.update_capacity().map(|_| 0),
+            _ => Err(FsError::NotSupported),
+        }
+    }
+}
+
+/// ファイルハンドルをループデバイスとして接続し、/dev からの相対名（"loop0"など）を返す
+pub fn attach(file: Arc<dyn FileHandle>, config: LoopConfig) -> FsResult<String> {
+    attach_device(LoopDevice::new(file, config)?)
+}
+
+/// パスのファイルを開いてループデバイスとして接続する
+pub fn attach_file(path: &str, config: LoopConfig) -> FsResult<String> {
+    let mode = if config.read_only { OpenMode::ReadOnly } else { OpenMode::ReadWrite };
+    let file = match super::open_file(path, mode) {
+        Err(FsError::PermissionDenied) | Err(FsError::ReadOnly) if !config.read_only => super::open_file(path, OpenMode::ReadOnly)?,
+        result => result?,
+    };
+    let mut device = LoopDevice::new(file, config)?;
+    device.backing_file = Some(path.to_string());
+    attach_device(device)
+}
+
+/// 空いている最小の番号でdevfsに登録
+fn attach_device(mut device: LoopDevice) -> FsResult<String> {
+    let mut devices = LOOP_DEVICES.lock();
+    let index = (0..).find(|index| !devices.contains_key(index)).unwrap_or_default();
+    device.device_id = LOOP_DEVICE_ID_BASE | index as u64;
+    
+    let device = Arc::new(device);
+    let name = devfs::register(DeviceClass::Loop { index }, DeviceOps::Block(device.clone()))?;
+    devices.insert(index, device.clone());
+    
+    log::info!("ループデバイス: /dev/{} を接続 ({:?}, {}ブロック)",
+              name, device.backing_file.as_deref().unwrap_or("<ハンドル>"), device.total_blocks());
+    Ok(name)
+}
+
+/// ループデバイスを切り離す（"loop0"または"/dev/loop0"）
+///
+/// /dev のノードはすぐに消え、開いているハンドルは使えなくなる。マウント中の
+/// ファイルシステムはアンマウントまで裏のファイルを使い続ける
+pub fn detach(name: &str) -> FsResult<()> {
+    let index = name.strip_prefix("/dev/").unwrap_or(name)
+        .strip_prefix("loop")
+        .and_then(|index| index.parse::<u32>().ok())
+        .ok_or(FsError::InvalidData)?;
+    let device = LOOP_DEVICES.lock().remove(&index).ok_or(FsError::NotFound)?;
+    
+    devfs::unregister(&format!("loop{}", index))?;
+    if let Err(e) = device.sync() {
+        log::warn!("ループデバイス: loop{} の同期に失敗しました: {:?}", index, e);
+    }
+    log::info!("ループデバイス: /dev/loop{} を切り離し", index);
+    Ok(())
+}
+
+/// 接続中のループデバイスの一覧
+pub fn list() -> Vec<LoopInfo> {
+    LOOP_DEVICES.lock().iter()
+        .map(|(index, device)| LoopInfo {
+            name: format!("loop{}", index),
+            backing_file: device.backing_file.clone(),
+            config: device.config,
+            size: device.total_blocks() * device.config.block_size,
+        })
+        .collect()
+}
+
+/// テスト用のメモリ上のファイルとイメージファイル
+///
+/// ファイルシステムドライバのテストはこれでイメージからブロックデバイスを作る
+#[cfg(test)]
+pub(crate) mod testing {
+    extern crate std;
+    
+    use super::*;
+    use super::super::Metadata;
+    
+    /// メモリ上のファイル
+    pub(crate) struct MemoryFile {
+        /// 内容
+        pub data: Mutex<Vec<u8>>,
+        /// 書き込めるか
+        pub writable: bool,
+    }
+    
+    impl MemoryFile {
+        /// 内容から作成
+        pub fn new(data: Vec<u8>, writable: bool) -> Arc<Self> {
+            Arc::new(Self { data: Mutex::new(data), writable })
+        }
+    }
+    
+    impl FileHandle for MemoryFile {
+        fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+            let data = self.data.lock();
+            let start = (offset as usize).min(data.len());
+            let len = buffer.len().min(data.len() - start);
+            buffer[..len].copy_from_slice(&data[start..start + len]);
+            Ok(len)
+        }
+        
+        fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+            let mut data = self.data.lock();
+            let end = offset as usize + buffer.len();
+            if data.len() < end {
+                data.resize(end, 0);
+            }
+            data[offset as usize..end].copy_from_slice(buffer);
+            Ok(buffer.len())
+        }
+        
+        fn flush(&self) -> FsResult<()> { Ok(()) }
+        fn size(&self) -> FsResult<u64> { Ok(self.data.lock().len() as u64) }
+        fn resize(&self, new_size: u64) -> FsResult<()> { self.data.lock().resize(new_size as usize, 0); Ok(()) }
+        fn metadata(&self) -> FsResult<Metadata> { Err(FsError::NotSupported) }
+        fn lock(&self, _exclusive: bool) -> FsResult<()> { Ok(()) }
+        fn unlock(&self) -> FsResult<()> { Ok(()) }
+        fn can_read(&self) -> bool { true }
+        fn can_write(&self) -> bool { self.writable }
+    }
+    
+    /// メモリ上のイメージからブロックデバイスを作成
+    pub(crate) fn image_device(image: Vec<u8>, block_size: u64) -> Arc<dyn BlockDevice> {
+        let config = LoopConfig { block_size, ..LoopConfig::default() };
+        Arc::new(LoopDevice::new(MemoryFile::new(image, true), config).expect("イメージからループデバイスを作れません"))
+    }
+    
+    /// ホスト上のイメージファイルからブロックデバイスを作成（変更はメモリ上にだけ残る）
+    pub(crate) fn host_image_device(path: &str, block_size: u64) -> Arc<dyn BlockDevice> {
+        let image = std::fs::read(path).unwrap_or_else(|e| panic!("{}を読めません: {}", path, e));
+        image_device(image, block_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::MemoryFile;
+    
+    #[test]
+    fn offset_limit_read_only_and_attach() {
+        let image: Vec<u8> = (0..8192u32).map(|i| (i / 1024) as u8).collect();
+        let file = MemoryFile::new(image, true);
+        
+        // 1KiBから2KiB分だけを1KiBブロックで見せる
+        let config = LoopConfig { block_size: 1024, offset: 1024, size_limit: Some(2048), read_only: false };
+        let device = LoopDevice::new(file.clone(), config).unwrap();
+        assert_eq!(device.total_blocks(), 2);
+        assert_eq!(device.read_block(0).unwrap()[0], 1);
+        assert_eq!(device.read_blocks(1, 1).unwrap()[1023], 2);
+        assert!(device.read_blocks(1, 2).is_err());
+        
+        device.write_block(1, &[0xAB; 1024]).unwrap();
+        assert_eq!(file.data.lock()[2048], 0xAB);
+        assert_eq!(file.data.lock()[3072], 3);
+        
+        // ファイルが伸びても制限は超えない。縮めば容量も縮む
+        file.resize(16384).unwrap();
+        assert_eq!(device.ioctl(LOOP_SET_CAPACITY, 0).unwrap(), 0);
+        assert_eq!(device.total_blocks(), 2);
+        file.resize(2048 + 512).unwrap();
+        device.update_capacity().unwrap();
+        assert_eq!(device.total_blocks(), 1);
+        
+        // 書き込めないファイルは読み取り専用になる
+        let read_only = LoopDevice::new(MemoryFile::new(vec![0; 4096], false), LoopConfig::default()).unwrap();
+        assert!(read_only.config().read_only);
+        assert!(matches!(read_only.write_block(0, &[0; 512]), Err(FsError::ReadOnly)));
+        assert!(LoopDevice::new(file.clone(), LoopConfig { block_size: 1000, ..LoopConfig::default() }).is_err());
+        
+        // 接続するとdevfsに現れ、切り離すと消える
+        let name = attach(MemoryFile::new(vec![0; 4096], true), LoopConfig::default()).unwrap();
+        assert!(name.starts_with("loop"));
+        assert_eq!(devfs::open_block_device(&name).unwrap().total_blocks(), 8);
+        assert!(list().iter().any(|info| info.name == name && info.size == 4096));
+        detach(&format!("/dev/{}", name)).unwrap();
+        assert!(devfs::lookup(&name).is_none());
+        assert!(detach(&name).is_err());
+    }
+}
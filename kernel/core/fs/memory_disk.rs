@@ -0,0 +1,59 @@
+// テスト用のメモリ上のブロックデバイス
+//
+// パーティションテーブルやファイルシステムドライバのテストで、組み立てたイメージを
+// BlockDeviceとして渡すために使う
+
+use alloc::vec::Vec;
+use spin::Mutex;
+use super::{FsError, FsResult};
+use super::vfs::BlockDevice;
+
+/// セクタサイズ
+const SECTOR_SIZE: u64 = 512;
+
+/// メモリ上のテスト用ディスク（512バイトセクタ）
+pub struct MemoryDisk {
+    /// ディスクの内容
+    data: Mutex<Vec<u8>>,
+    /// 書き込みをReadOnlyで拒否するか（読み取り専用ドライバが書き込まないことを確かめる）
+    read_only: bool,
+}
+
+impl MemoryDisk {
+    /// イメージを書き込み可能なディスクにする
+    pub fn new(image: Vec<u8>) -> Self {
+        Self { data: Mutex::new(image), read_only: false }
+    }
+    
+    /// イメージを書き込めないディスクにする
+    pub fn read_only(image: Vec<u8>) -> Self {
+        Self { data: Mutex::new(image), read_only: true }
+    }
+    
+    /// ディスクの内容を複製
+    pub fn image(&self) -> Vec<u8> {
+        self.data.lock().clone()
+    }
+}
+
+impl BlockDevice for MemoryDisk {
+    fn device_id(&self) -> u64 { 1 }
+    fn block_size(&self) -> u64 { SECTOR_SIZE }
+    fn total_blocks(&self) -> u64 { self.data.lock().len() as u64 / SECTOR_SIZE }
+    fn read_block(&self, block_index: u64) -> FsResult<Vec<u8>> { self.read_blocks(block_index, 1) }
+    fn read_blocks(&self, start_block: u64, count: u64) -> FsResult<Vec<u8>> {
+        let start = (start_block * SECTOR_SIZE) as usize;
+        self.data.lock().get(start..start + (count * SECTOR_SIZE) as usize).map(|s| s.to_vec()).ok_or(FsError::IoError)
+    }
+    fn write_block(&self, block_index: u64, data: &[u8]) -> FsResult<()> { self.write_blocks(block_index, data) }
+    fn write_blocks(&self, start_block: u64, data: &[u8]) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let start = (start_block * SECTOR_SIZE) as usize;
+        self.data.lock().get_mut(start..start + data.len()).ok_or(FsError::IoError)?.copy_from_slice(data);
+        Ok(())
+    }
+    fn sync(&self) -> FsResult<()> { Ok(()) }
+    fn close(&self) -> FsResult<()> { Ok(()) }
+}
@@ -0,0 +1,65 @@
+// Minix ビットマップ
+//
+// iノードビットマップとゾーンビットマップをメモリ上に保持し、変更したブロックだけを
+// 書き戻す。どちらもビット0は予約で常に使用中
+
+use alloc::vec::Vec;
+
+/// メモリ上のビットマップ
+pub struct MinixBitmap {
+    /// ビットマップ全体
+    data: Vec<u8>,
+    /// デバイス上の先頭ブロック
+    start_block: u32,
+    /// ブロックサイズ
+    block_size: usize,
+    /// 有効なビット数（予約のビット0を含む）
+    bits: u32,
+    /// 空きビット数
+    free: u32,
+}
+
+impl MinixBitmap {
+    /// ディスクから読み込んだビットマップを包む
+    pub fn new(data: Vec<u8>, start_block: u32, block_size: usize, bits: u32) -> Self {
+        let mut bitmap = Self { data, start_block, block_size, bits, free: 0 };
+        bitmap.free = (1..bits).filter(|&bit| !bitmap.is_set(bit)).count() as u32;
+        bitmap
+    }
+    
+    /// ビットが立っているか（範囲外は使用中とみなす）
+    pub fn is_set(&self, bit: u32) -> bool {
+        bit >= self.bits || self.data[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+    }
+    
+    /// ビットを設定し、書き戻すべきブロック（デバイス上の番号, 内容）を返す
+    pub fn set(&mut self, bit: u32, value: bool) -> (u32, &[u8]) {
+        let was_set = self.is_set(bit);
+        let byte = (bit / 8) as usize;
+        if value {
+            self.data[byte] |= 1 << (bit % 8);
+        } else {
+            self.data[byte] &= !(1 << (bit % 8));
+        }
+        match (was_set, value) {
+            (false, true) => self.free -= 1,
+            (true, false) => self.free += 1,
+            _ => {},
+        }
+        
+        let index = byte / self.block_size;
+        let start = index * self.block_size;
+        (self.start_block + index as u32, &self.data[start..start + self.block_size])
+    }
+    
+    /// `hint`以降で最初の空きビットを探す（末尾まで見つからなければ先頭から）
+    pub fn find_free(&self, hint: u32) -> Option<u32> {
+        let hint = hint.clamp(1, self.bits.max(1));
+        (hint..self.bits).chain(1..hint).find(|&bit| !self.is_set(bit))
+    }
+    
+    /// 空きビット数
+    pub fn free_count(&self) -> u32 {
+        self.free
+    }
+}
@@ -0,0 +1,67 @@
+// Minix ディレクトリエントリ
+//
+// 固定長のスロット（iノード番号 + NULで埋めた名前）の解析と作成。iノード番号が0の
+// スロットは空き
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::superblock::MinixVersion;
+
+/// ディレクトリエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinixDirEntry {
+    /// iノード番号
+    pub inode: u32,
+    /// 名前
+    pub name: String,
+    /// ディレクトリ内のスロット番号
+    pub slot: usize,
+}
+
+/// ディレクトリの内容から使用中のエントリを列挙
+pub fn parse_entries(data: &[u8], version: MinixVersion, dirent_size: usize) -> Vec<MinixDirEntry> {
+    let inode_size = version.dirent_inode_size();
+    data.chunks_exact(dirent_size)
+        .enumerate()
+        .filter_map(|(slot, raw)| {
+            let inode = match version {
+                MinixVersion::V3 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]),
+                _ => u16::from_le_bytes([raw[0], raw[1]]) as u32,
+            };
+            if inode == 0 {
+                return None;
+            }
+            let name = &raw[inode_size..];
+            let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            Some(MinixDirEntry {
+                inode,
+                name: String::from_utf8_lossy(&name[..len]).into_owned(),
+                slot,
+            })
+        })
+        .collect()
+}
+
+/// エントリのディスク上の形式を作成（`inode`が0なら空きスロット）
+pub fn encode_entry(version: MinixVersion, dirent_size: usize, inode: u32, name: &str) -> Vec<u8> {
+    let inode_size = version.dirent_inode_size();
+    let mut raw = vec![0u8; dirent_size];
+    match version {
+        MinixVersion::V3 => raw[..4].copy_from_slice(&inode.to_le_bytes()),
+        _ => raw[..2].copy_from_slice(&(inode as u16).to_le_bytes()),
+    }
+    raw[inode_size..inode_size + name.len()].copy_from_slice(name.as_bytes());
+    raw
+}
+
+/// エントリ名を検証（名前はNULで終わらなくてもよいので最大長ちょうどまで使える）
+pub fn validate_name(name: &str, max_len: usize) -> FsResult<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        return Err(FsError::InvalidData);
+    }
+    if name.len() > max_len {
+        return Err(FsError::Other("名前が長すぎます"));
+    }
+    Ok(())
+}
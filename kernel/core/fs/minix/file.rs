@@ -0,0 +1,204 @@
+// Minix ファイル/ディレクトリハンドル
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, Metadata, Permissions, FileType, FileHandle, DirHandle, DirEntry};
+use super::{MinixVolume, current_time};
+use super::inode::mode;
+
+/// VFSの権限からパーミッションビットを作成（所有者以外は書き込み不可）
+fn mode_from_permissions(permissions: Permissions) -> u16 {
+    let mut mode = 0;
+    if permissions.read {
+        mode |= 0o444;
+    }
+    if permissions.write {
+        mode |= 0o200;
+    }
+    if permissions.execute {
+        mode |= 0o111;
+    }
+    mode
+}
+
+/// Minixファイルハンドル
+pub struct MinixFileHandle {
+    /// ボリューム
+    volume: Arc<MinixVolume>,
+    /// iノード番号
+    inode: u32,
+    /// 書き込み可能か
+    writable: bool,
+}
+
+impl MinixFileHandle {
+    /// 新しいファイルハンドルを作成
+    pub(super) fn new(volume: Arc<MinixVolume>, inode: u32, writable: bool) -> Self {
+        Self { volume, inode, writable }
+    }
+}
+
+impl FileHandle for MinixFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let inode = self.volume.read_inode(self.inode)?;
+        self.volume.read_data(&inode, buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.check_writable()?;
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        
+        let _guard = self.volume.update_lock.lock();
+        let mut inode = self.volume.read_inode(self.inode)?;
+        // 途中で失敗しても割り当て済みのゾーンを失わないようiノードは必ず書き戻す
+        let result = self.volume.write_data(&mut inode, buffer, offset);
+        let now = current_time();
+        inode.mtime = now;
+        inode.ctime = now;
+        self.volume.write_inode(self.inode, &inode)?;
+        result
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.volume.sync()
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.volume.read_inode(self.inode)?.size as u64)
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.check_writable()?;
+        
+        let _guard = self.volume.update_lock.lock();
+        let mut inode = self.volume.read_inode(self.inode)?;
+        let result = self.volume.truncate(&mut inode, new_size);
+        let now = current_time();
+        inode.mtime = now;
+        inode.ctime = now;
+        self.volume.write_inode(self.inode, &inode)?;
+        result
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let inode = self.volume.read_inode(self.inode)?;
+        Ok(self.volume.metadata(self.inode, &inode))
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        self.writable && !self.volume.read_only
+    }
+}
+
+/// Minixディレクトリハンドル
+pub struct MinixDirHandle {
+    /// ボリューム
+    volume: Arc<MinixVolume>,
+    /// ディレクトリのiノード番号
+    inode: u32,
+}
+
+impl MinixDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<MinixVolume>, inode: u32) -> Self {
+        Self { volume, inode }
+    }
+}
+
+impl DirHandle for MinixDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        let dir = self.volume.read_inode(self.inode)?;
+        let mut entries = Vec::new();
+        
+        // Minixのディレクトリエントリは種別を持たないので、iノードから調べる
+        for entry in self.volume.read_dir(&dir)? {
+            if entry.name == "." || entry.name == ".." {
+                continue;
+            }
+            entries.push(DirEntry {
+                inode: entry.inode as u64,
+                file_type: self.volume.read_inode(entry.inode)?.file_type(),
+                name: entry.name,
+            });
+        }
+        
+        Ok(entries)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let dir = self.volume.read_inode(self.inode)?;
+        let entry = self.volume.find_entry(&dir, name)?.ok_or(FsError::NotFound)?;
+        Ok(DirEntry {
+            inode: entry.inode as u64,
+            file_type: self.volume.read_inode(entry.inode)?.file_type(),
+            name: String::from(name),
+        })
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        let inode = self.volume.create(self.inode, name, mode_from_permissions(permissions))?;
+        Ok(Arc::new(MinixFileHandle::new(self.volume.clone(), inode, true)))
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        self.volume.mkdir(self.inode, name, mode_from_permissions(permissions))?;
+        Ok(())
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        self.volume.remove(self.inode, name)
+    }
+    
+    /// 名前を変更する
+    ///
+    /// `new_name`に`/`を含む場合は移動先のパスとして扱い、`/`始まりならファイルシステムの
+    /// ルート、それ以外はこのディレクトリからの相対パスとして解決する。
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        let (new_parent, leaf) = self.volume.resolve_parent(self.inode, new_name)?;
+        self.volume.rename(self.inode, old_name, new_parent, leaf)
+    }
+    
+    fn create_symlink(&self, name: &str, target: &str) -> FsResult<()> {
+        self.volume.symlink(self.inode, name, target)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let dir = self.volume.read_inode(self.inode)?;
+        Ok(self.volume.metadata(self.inode, &dir))
+    }
+    
+    fn create_device(&self, name: &str, file_type: FileType, major: u32, minor: u32) -> FsResult<()> {
+        self.volume.mknod(self.inode, name, file_type, major, minor)
+    }
+    
+    fn device_number(&self, name: &str) -> FsResult<(u32, u32)> {
+        let dir = self.volume.read_inode(self.inode)?;
+        let entry = self.volume.find_entry(&dir, name)?.ok_or(FsError::NotFound)?;
+        let inode = self.volume.read_inode(entry.inode)?;
+        match inode.mode & mode::S_IFMT {
+            mode::S_IFBLK | mode::S_IFCHR => Ok(inode.device_number(self.volume.version())),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+}
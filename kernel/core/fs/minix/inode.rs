@@ -0,0 +1,249 @@
+// Minix iノード
+//
+// v1（32バイト、16ビットゾーン番号）とv2/v3（64バイト、32ビットゾーン番号）のiノードの
+// 変換と、論理ブロック番号から直接・間接ゾーンへの経路の計算
+
+use alloc::vec::Vec;
+use super::super::FileType;
+use super::superblock::MinixVersion;
+
+/// ルートディレクトリのiノード番号
+pub const ROOT_INODE: u32 = 1;
+/// 直接ゾーンの数
+pub const DIRECT_ZONES: usize = 7;
+/// iノード内のゾーン番号の数（直接7 + 間接 + 二重間接 + 三重間接）
+pub const ZONE_SLOTS: usize = 10;
+
+/// i_modeのファイル種別
+pub mod mode {
+    /// 種別のマスク
+    pub const S_IFMT: u16 = 0o170000;
+    /// ソケット
+    pub const S_IFSOCK: u16 = 0o140000;
+    /// シンボリックリンク
+    pub const S_IFLNK: u16 = 0o120000;
+    /// 通常ファイル
+    pub const S_IFREG: u16 = 0o100000;
+    /// ブロックデバイス
+    pub const S_IFBLK: u16 = 0o060000;
+    /// ディレクトリ
+    pub const S_IFDIR: u16 = 0o040000;
+    /// キャラクタデバイス
+    pub const S_IFCHR: u16 = 0o020000;
+    /// FIFO
+    pub const S_IFIFO: u16 = 0o010000;
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Minixのiノード（v1はv2の形に広げて保持する）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MinixInode {
+    /// 種別と権限
+    pub mode: u16,
+    /// リンク数
+    pub nlinks: u16,
+    /// 所有者
+    pub uid: u16,
+    /// グループ（v1は8ビット）
+    pub gid: u16,
+    /// ファイルサイズ
+    pub size: u32,
+    /// 最終アクセス時刻（v1は持たない）
+    pub atime: u32,
+    /// 最終更新時刻
+    pub mtime: u32,
+    /// 状態変更時刻（v1は持たない）
+    pub ctime: u32,
+    /// 直接・間接ゾーン（v1は三重間接を持たない）
+    pub zones: [u32; ZONE_SLOTS],
+}
+
+impl MinixInode {
+    /// 新しいiノードを作成
+    pub fn new(mode: u16, time: u32) -> Self {
+        Self { mode, nlinks: 1, atime: time, mtime: time, ctime: time, ..Default::default() }
+    }
+    
+    /// ディスク上の形式から変換
+    pub fn parse(version: MinixVersion, data: &[u8]) -> Self {
+        match version {
+            MinixVersion::V1 => {
+                let mut zones = [0u32; ZONE_SLOTS];
+                for (i, zone) in zones.iter_mut().take(9).enumerate() {
+                    *zone = read_u16(data, 14 + i * 2) as u32;
+                }
+                let time = read_u32(data, 8);
+                Self {
+                    mode: read_u16(data, 0),
+                    uid: read_u16(data, 2),
+                    size: read_u32(data, 4),
+                    atime: time,
+                    mtime: time,
+                    ctime: time,
+                    gid: data[12] as u16,
+                    nlinks: data[13] as u16,
+                    zones,
+                }
+            },
+            _ => {
+                let mut zones = [0u32; ZONE_SLOTS];
+                for (i, zone) in zones.iter_mut().enumerate() {
+                    *zone = read_u32(data, 24 + i * 4);
+                }
+                Self {
+                    mode: read_u16(data, 0),
+                    nlinks: read_u16(data, 2),
+                    uid: read_u16(data, 4),
+                    gid: read_u16(data, 6),
+                    size: read_u32(data, 8),
+                    atime: read_u32(data, 12),
+                    mtime: read_u32(data, 16),
+                    ctime: read_u32(data, 20),
+                    zones,
+                }
+            },
+        }
+    }
+    
+    /// ディスク上の形式に変換
+    pub fn to_bytes(&self, version: MinixVersion) -> Vec<u8> {
+        let mut data = vec![0u8; version.inode_size()];
+        data[0..2].copy_from_slice(&self.mode.to_le_bytes());
+        match version {
+            MinixVersion::V1 => {
+                data[2..4].copy_from_slice(&self.uid.to_le_bytes());
+                data[4..8].copy_from_slice(&self.size.to_le_bytes());
+                data[8..12].copy_from_slice(&self.mtime.to_le_bytes());
+                data[12] = self.gid as u8;
+                data[13] = self.nlinks as u8;
+                for (i, zone) in self.zones.iter().take(9).enumerate() {
+                    data[14 + i * 2..16 + i * 2].copy_from_slice(&(*zone as u16).to_le_bytes());
+                }
+            },
+            _ => {
+                data[2..4].copy_from_slice(&self.nlinks.to_le_bytes());
+                data[4..6].copy_from_slice(&self.uid.to_le_bytes());
+                data[6..8].copy_from_slice(&self.gid.to_le_bytes());
+                data[8..12].copy_from_slice(&self.size.to_le_bytes());
+                data[12..16].copy_from_slice(&self.atime.to_le_bytes());
+                data[16..20].copy_from_slice(&self.mtime.to_le_bytes());
+                data[20..24].copy_from_slice(&self.ctime.to_le_bytes());
+                for (i, zone) in self.zones.iter().enumerate() {
+                    data[24 + i * 4..28 + i * 4].copy_from_slice(&zone.to_le_bytes());
+                }
+            },
+        }
+        data
+    }
+    
+    /// ファイル種別
+    pub fn file_type(&self) -> FileType {
+        match self.mode & mode::S_IFMT {
+            mode::S_IFDIR => FileType::Directory,
+            mode::S_IFLNK => FileType::SymbolicLink,
+            mode::S_IFBLK => FileType::BlockDevice,
+            mode::S_IFCHR => FileType::CharDevice,
+            mode::S_IFIFO => FileType::NamedPipe,
+            mode::S_IFSOCK => FileType::Socket,
+            _ => FileType::Regular,
+        }
+    }
+    
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        self.mode & mode::S_IFMT == mode::S_IFDIR
+    }
+    
+    /// データゾーンを持つ種別か（デバイスノードはi_zone[0]にデバイス番号を持つ）
+    pub fn has_data(&self) -> bool {
+        matches!(self.mode & mode::S_IFMT, mode::S_IFREG | mode::S_IFDIR | mode::S_IFLNK)
+    }
+    
+    /// デバイスノードのデバイス番号（v1は8ビットずつ、v2/v3はLinuxの新形式）
+    pub fn device_number(&self, version: MinixVersion) -> (u32, u32) {
+        let raw = self.zones[0];
+        match version {
+            MinixVersion::V1 => ((raw >> 8) & 0xFF, raw & 0xFF),
+            _ => ((raw >> 8) & 0xFFF, (raw & 0xFF) | ((raw >> 12) & 0xFFF00)),
+        }
+    }
+    
+    /// デバイスノードのデバイス番号を設定
+    pub fn set_device_number(&mut self, version: MinixVersion, major: u32, minor: u32) {
+        self.zones[0] = match version {
+            MinixVersion::V1 => ((major & 0xFF) << 8) | (minor & 0xFF),
+            _ => (minor & 0xFF) | ((major & 0xFFF) << 8) | ((minor & !0xFF) << 12),
+        };
+    }
+}
+
+/// 論理ブロック番号からゾーンへの経路を求める
+///
+/// 先頭はiノード内のゾーン番号の位置、続く要素は各段の間接ブロック内の位置。
+/// 形式が表現できる範囲を超えれば`None`。
+pub fn zone_path(version: MinixVersion, block_size: u32, logical: u64) -> Option<Vec<usize>> {
+    let per_block = (block_size as usize / version.zone_entry_size()) as u64;
+    if logical < DIRECT_ZONES as u64 {
+        return Some(vec![logical as usize]);
+    }
+    
+    let mut remaining = logical - DIRECT_ZONES as u64;
+    let mut span = 1u64;
+    for level in 1..=version.indirect_levels() {
+        span *= per_block;
+        if remaining < span {
+            let mut path = vec![DIRECT_ZONES + level - 1];
+            let mut divisor = span;
+            for _ in 0..level {
+                divisor /= per_block;
+                path.push(((remaining / divisor) % per_block) as usize);
+            }
+            return Some(path);
+        }
+        remaining -= span;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn inode_formats_and_zone_paths() {
+        let mut inode = MinixInode::new(mode::S_IFREG | 0o644, 1_709_214_358);
+        inode.uid = 1000;
+        inode.gid = 100;
+        inode.size = 70_000;
+        inode.zones = [10, 11, 12, 13, 14, 15, 16, 17, 18, 19];
+        
+        // v1は三重間接と個別の時刻を持たない
+        let v1 = MinixInode::parse(MinixVersion::V1, &inode.to_bytes(MinixVersion::V1));
+        assert_eq!(v1.zones, [10, 11, 12, 13, 14, 15, 16, 17, 18, 0]);
+        assert_eq!((v1.uid, v1.gid, v1.size, v1.ctime), (1000, 100, 70_000, 1_709_214_358));
+        assert_eq!(MinixInode::parse(MinixVersion::V2, &inode.to_bytes(MinixVersion::V2)), inode);
+        
+        let mut device = MinixInode::new(mode::S_IFBLK | 0o600, 0);
+        device.set_device_number(MinixVersion::V2, 259, 300);
+        assert_eq!(device.device_number(MinixVersion::V2), (259, 300));
+        assert!(!device.has_data());
+        device.set_device_number(MinixVersion::V1, 8, 1);
+        assert_eq!((device.zones[0], device.device_number(MinixVersion::V1)), (0x0801, (8, 1)));
+        
+        // v1（1024バイトブロックに512個）とv2（256個）の各段の境界
+        assert_eq!(zone_path(MinixVersion::V1, 1024, 6).unwrap(), vec![6]);
+        assert_eq!(zone_path(MinixVersion::V1, 1024, 7).unwrap(), vec![7, 0]);
+        assert_eq!(zone_path(MinixVersion::V1, 1024, 7 + 512).unwrap(), vec![8, 0, 0]);
+        assert_eq!(zone_path(MinixVersion::V1, 1024, 7 + 512 + 513).unwrap(), vec![8, 1, 1]);
+        assert!(zone_path(MinixVersion::V1, 1024, 7 + 512 + 512 * 512).is_none());
+        assert_eq!(zone_path(MinixVersion::V2, 1024, 7 + 256 + 256 * 256).unwrap(), vec![9, 0, 0, 0]);
+        assert_eq!(zone_path(MinixVersion::V3, 4096, 7 + 1024 + 1025).unwrap(), vec![8, 1, 1]);
+    }
+}
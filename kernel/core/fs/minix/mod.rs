@@ -0,0 +1,1102 @@
+// Minix ファイルシステム実装
+//
+// Minix v1/v2/v3の読み書き（iノード・ゾーンビットマップ、直接・間接・二重間接ゾーン、
+// 14/30/60文字の名前）。initramfs相当の小さなイメージに使う
+
+mod superblock;
+mod inode;
+mod bitmap;
+mod dir;
+mod file;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use core::sync::atomic::{AtomicU32, Ordering};
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, Metadata, FsStats, Permissions, FileHandle, DirHandle, OpenMode, Filesystem};
+use super::vfs::BlockDevice;
+use self::superblock::{MinixSuperblock, MinixVersion, SUPERBLOCK_OFFSET, SUPERBLOCK_SIZE, STATE_OFFSET, state};
+use self::inode::{MinixInode, mode, zone_path, ROOT_INODE, DIRECT_ZONES};
+use self::bitmap::MinixBitmap;
+use self::dir::{MinixDirEntry, parse_entries, encode_entry, validate_name};
+use self::file::{MinixFileHandle, MinixDirHandle};
+
+/// パス解決時のディレクトリ階層上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// 現在時刻（UNIX秒）
+fn current_time() -> u32 {
+    (crate::time::current_time_ns() / 1_000_000_000) as u32
+}
+
+/// デバイスからバイト単位で読み込み（ブロック境界をまたいでよい）
+fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+    
+    if data.len() < start + len {
+        return Err(FsError::IoError);
+    }
+    
+    Ok(data[start..start + len].to_vec())
+}
+
+/// デバイスへバイト単位で書き込み（端数ブロックは読み込んでから書き戻す）
+fn write_bytes(device: &dyn BlockDevice, offset: u64, data: &[u8]) -> FsResult<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + data.len() as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let start = (offset - first_block * block_size) as usize;
+    let end = start + data.len();
+    let span = ((last_block - first_block + 1) * block_size) as usize;
+    
+    // ブロック境界に揃っていればそのまま書き込む
+    if start == 0 && end == span {
+        return device.write_blocks(first_block, data);
+    }
+    
+    let mut buffer = device.read_blocks(first_block, last_block - first_block + 1)?;
+    if buffer.len() < span {
+        return Err(FsError::IoError);
+    }
+    buffer[start..end].copy_from_slice(data);
+    device.write_blocks(first_block, &buffer[..span])
+}
+
+/// マウントされたMinixボリューム
+struct MinixVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// スーパーブロック
+    superblock: MinixSuperblock,
+    /// 読み取り専用マウントか
+    read_only: bool,
+    /// iノードビットマップ
+    inode_map: Mutex<MinixBitmap>,
+    /// ゾーンビットマップ
+    zone_map: Mutex<MinixBitmap>,
+    /// 次にゾーンを探し始めるビット
+    zone_hint: AtomicU32,
+    /// ディレクトリ/ファイル更新の排他ロック
+    update_lock: Mutex<()>,
+}
+
+impl MinixVolume {
+    /// デバイスからボリュームを構築
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, read_only: bool) -> FsResult<Self> {
+        let raw = read_bytes(&*device, SUPERBLOCK_OFFSET, SUPERBLOCK_SIZE)?;
+        let superblock = MinixSuperblock::parse(&raw)?;
+        let block_size = superblock.block_size as u64;
+        
+        let volume_bytes = superblock.zones as u64 * block_size;
+        if volume_bytes > device.total_blocks() * device.block_size() {
+            log::warn!("Minix: ボリューム ({}バイト) がデバイスより大きいです", volume_bytes);
+            return Err(FsError::CorruptedFs);
+        }
+        if !superblock.is_clean() {
+            log::warn!("Minix: ボリュームが正常にアンマウントされていません。チェックを推奨します");
+        }
+        
+        let imap = read_bytes(&*device, superblock.imap_block() as u64 * block_size, (superblock.imap_blocks as u64 * block_size) as usize)?;
+        let zmap = read_bytes(&*device, superblock.zmap_block() as u64 * block_size, (superblock.zmap_blocks as u64 * block_size) as usize)?;
+        let volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            inode_map: Mutex::new(MinixBitmap::new(imap, superblock.imap_block(), block_size as usize, superblock.inodes + 1)),
+            zone_map: Mutex::new(MinixBitmap::new(zmap, superblock.zmap_block(), block_size as usize, superblock.data_zones() + 1)),
+            zone_hint: AtomicU32::new(1),
+            update_lock: Mutex::new(()),
+            read_only,
+            superblock,
+        };
+        
+        if !volume.read_inode(ROOT_INODE)?.is_directory() {
+            log::warn!("Minix: ルートiノードがディレクトリではありません");
+            return Err(FsError::CorruptedFs);
+        }
+        
+        // v1/v2はマウント中にVALIDを外し、アンマウントで元に戻す（v3は状態を持たない）
+        if !read_only && volume.superblock.version != MinixVersion::V3 {
+            volume.write_state(volume.superblock.state & !state::VALID)?;
+        }
+        
+        log::info!("Minix: {:?} ボリューム ({}バイトブロック, iノード{}, ゾーン{}, 名前{}文字)",
+                  volume.superblock.version, volume.superblock.block_size, volume.superblock.inodes,
+                  volume.superblock.zones, volume.superblock.name_len);
+        
+        Ok(volume)
+    }
+    
+    /// ディスク形式のバージョン
+    fn version(&self) -> MinixVersion {
+        self.superblock.version
+    }
+    
+    /// ブロックサイズ
+    fn block_size(&self) -> usize {
+        self.superblock.block_size as usize
+    }
+    
+    /// 書き込み可能なマウントか確認
+    fn check_writable(&self) -> FsResult<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        Ok(())
+    }
+    
+    /// s_stateを書き込む（v1/v2のみ）
+    fn write_state(&self, value: u16) -> FsResult<()> {
+        write_bytes(&*self.device, SUPERBLOCK_OFFSET + STATE_OFFSET as u64, &value.to_le_bytes())
+    }
+    
+    /// ブロックのデバイス上のバイトオフセット
+    fn block_offset(&self, block: u32) -> u64 {
+        block as u64 * self.superblock.block_size as u64
+    }
+    
+    /// ブロックを読み込み
+    fn read_block(&self, block: u32) -> FsResult<Vec<u8>> {
+        read_bytes(&*self.device, self.block_offset(block), self.block_size())
+    }
+    
+    /// ブロックを書き込み
+    fn write_block(&self, block: u32, data: &[u8]) -> FsResult<()> {
+        self.check_writable()?;
+        write_bytes(&*self.device, self.block_offset(block), data)
+    }
+    
+    /// iノードのデバイス上のバイトオフセット
+    fn inode_offset(&self, num: u32) -> FsResult<u64> {
+        if num == 0 || num > self.superblock.inodes {
+            log::warn!("Minix: iノード番号{}が範囲外です", num);
+            return Err(FsError::CorruptedFs);
+        }
+        let index = (num - 1) as u64 * self.version().inode_size() as u64;
+        Ok(self.block_offset(self.superblock.inode_table_block()) + index)
+    }
+    
+    /// iノードを読み込み
+    fn read_inode(&self, num: u32) -> FsResult<MinixInode> {
+        let data = read_bytes(&*self.device, self.inode_offset(num)?, self.version().inode_size())?;
+        Ok(MinixInode::parse(self.version(), &data))
+    }
+    
+    /// iノードを書き込み
+    fn write_inode(&self, num: u32, inode: &MinixInode) -> FsResult<()> {
+        self.check_writable()?;
+        write_bytes(&*self.device, self.inode_offset(num)?, &inode.to_bytes(self.version()))
+    }
+    
+    /// iノードを割り当てる
+    fn alloc_inode(&self) -> FsResult<u32> {
+        let mut map = self.inode_map.lock();
+        let num = map.find_free(1).ok_or(FsError::OutOfSpace)?;
+        let (block, data) = map.set(num, true);
+        self.write_block(block, data)?;
+        Ok(num)
+    }
+    
+    /// iノードをゼロで消して解放する
+    fn free_inode(&self, num: u32) -> FsResult<()> {
+        self.write_inode(num, &MinixInode::default())?;
+        
+        let mut map = self.inode_map.lock();
+        if !map.is_set(num) {
+            log::warn!("Minix: iノード{}はすでに解放されています", num);
+            return Ok(());
+        }
+        let (block, data) = map.set(num, false);
+        self.write_block(block, data)
+    }
+    
+    /// データゾーンの範囲内か確認
+    fn check_zone(&self, zone: u32) -> FsResult<()> {
+        if zone < self.superblock.first_data_zone || zone >= self.superblock.zones {
+            log::warn!("Minix: ゾーン{}がデータ領域の外を指しています", zone);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(())
+    }
+    
+    /// ゼロで埋めたゾーンを割り当てる
+    fn alloc_zone(&self) -> FsResult<u32> {
+        let zone = {
+            let mut map = self.zone_map.lock();
+            let bit = map.find_free(self.zone_hint.load(Ordering::Relaxed)).ok_or(FsError::OutOfSpace)?;
+            let (block, data) = map.set(bit, true);
+            self.write_block(block, data)?;
+            self.zone_hint.store(bit + 1, Ordering::Relaxed);
+            bit + self.superblock.first_data_zone - 1
+        };
+        
+        // 以前の内容が見えないようにする
+        self.write_block(zone, &vec![0u8; self.block_size()])?;
+        Ok(zone)
+    }
+    
+    /// ゾーンを解放する
+    fn free_zone(&self, zone: u32) -> FsResult<()> {
+        self.check_zone(zone)?;
+        let bit = zone - self.superblock.first_data_zone + 1;
+        
+        let mut map = self.zone_map.lock();
+        if !map.is_set(bit) {
+            log::warn!("Minix: ゾーン{}はすでに解放されています", zone);
+            return Ok(());
+        }
+        let (block, data) = map.set(bit, false);
+        self.write_block(block, data)?;
+        self.zone_hint.fetch_min(bit, Ordering::Relaxed);
+        Ok(())
+    }
+    
+    /// 間接ブロック内のゾーン番号を読む
+    fn zone_entry(&self, block: &[u8], index: usize) -> u32 {
+        match self.version() {
+            MinixVersion::V1 => u16::from_le_bytes([block[index * 2], block[index * 2 + 1]]) as u32,
+            _ => u32::from_le_bytes([block[index * 4], block[index * 4 + 1], block[index * 4 + 2], block[index * 4 + 3]]),
+        }
+    }
+    
+    /// 間接ブロック内のゾーン番号を書く
+    fn set_zone_entry(&self, block: &mut [u8], index: usize, zone: u32) {
+        match self.version() {
+            MinixVersion::V1 => block[index * 2..index * 2 + 2].copy_from_slice(&(zone as u16).to_le_bytes()),
+            _ => block[index * 4..index * 4 + 4].copy_from_slice(&zone.to_le_bytes()),
+        }
+    }
+    
+    /// 間接ブロックあたりのゾーン番号の数
+    fn zones_per_block(&self) -> u64 {
+        (self.block_size() / self.version().zone_entry_size()) as u64
+    }
+    
+    /// 論理ブロックに対応するゾーン（穴なら0）
+    fn lookup_zone(&self, inode: &MinixInode, logical: u64) -> FsResult<u32> {
+        let path = zone_path(self.version(), self.superblock.block_size, logical).ok_or(FsError::OverflowError)?;
+        let mut zone = inode.zones[path[0]];
+        for &index in &path[1..] {
+            if zone == 0 {
+                return Ok(0);
+            }
+            self.check_zone(zone)?;
+            zone = self.zone_entry(&self.read_block(zone)?, index);
+        }
+        if zone != 0 {
+            self.check_zone(zone)?;
+        }
+        Ok(zone)
+    }
+    
+    /// 論理ブロックに対応するゾーンを返す（なければ途中の間接ブロックも含めて割り当てる）
+    fn allocate_zone_for(&self, inode: &mut MinixInode, logical: u64) -> FsResult<u32> {
+        let path = zone_path(self.version(), self.superblock.block_size, logical).ok_or(FsError::OverflowError)?;
+        if inode.zones[path[0]] == 0 {
+            inode.zones[path[0]] = self.alloc_zone()?;
+        }
+        
+        let mut zone = inode.zones[path[0]];
+        for &index in &path[1..] {
+            self.check_zone(zone)?;
+            let mut block = self.read_block(zone)?;
+            let mut next = self.zone_entry(&block, index);
+            if next == 0 {
+                next = self.alloc_zone()?;
+                self.set_zone_entry(&mut block, index, next);
+                self.write_block(zone, &block)?;
+            }
+            zone = next;
+        }
+        Ok(zone)
+    }
+    
+    /// ファイルサイズの上限
+    fn max_file_size(&self) -> u64 {
+        match self.superblock.max_size {
+            0 => u32::MAX as u64,
+            max => max.min(u32::MAX as u64),
+        }
+    }
+    
+    /// iノードのデータを読み込み
+    fn read_data(&self, inode: &MinixInode, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let size = inode.size as u64;
+        if offset >= size || buffer.is_empty() {
+            return Ok(0);
+        }
+        
+        let len = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        let block_size = self.block_size() as u64;
+        let mut done = 0;
+        while done < len {
+            let position = offset + done as u64;
+            let within = position % block_size;
+            let chunk = core::cmp::min((block_size - within) as usize, len - done);
+            match self.lookup_zone(inode, position / block_size)? {
+                // 割り当てのないブロックは穴として0を返す
+                0 => buffer[done..done + chunk].fill(0),
+                zone => {
+                    let data = read_bytes(&*self.device, self.block_offset(zone) + within, chunk)?;
+                    buffer[done..done + chunk].copy_from_slice(&data);
+                },
+            }
+            done += chunk;
+        }
+        Ok(len)
+    }
+    
+    /// iノードのデータを書き込み（呼び出し側がiノードを書き戻す）
+    fn write_data(&self, inode: &mut MinixInode, data: &[u8], offset: u64) -> FsResult<usize> {
+        let end = offset.checked_add(data.len() as u64).ok_or(FsError::OverflowError)?;
+        if end > self.max_file_size() {
+            return Err(FsError::OverflowError);
+        }
+        
+        let block_size = self.block_size() as u64;
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done as u64;
+            let within = position % block_size;
+            let chunk = core::cmp::min((block_size - within) as usize, data.len() - done);
+            let zone = self.allocate_zone_for(inode, position / block_size)?;
+            write_bytes(&*self.device, self.block_offset(zone) + within, &data[done..done + chunk])?;
+            done += chunk;
+            inode.size = inode.size.max((position + chunk as u64) as u32);
+        }
+        Ok(data.len())
+    }
+    
+    /// ファイルサイズを変更（伸長は穴として扱う。呼び出し側がiノードを書き戻す）
+    fn truncate(&self, inode: &mut MinixInode, new_size: u64) -> FsResult<()> {
+        if new_size > self.max_file_size() {
+            return Err(FsError::OverflowError);
+        }
+        
+        if new_size < inode.size as u64 {
+            let block_size = self.block_size() as u64;
+            // 残るブロックの末尾を0にして、後で伸ばしたときに古い内容が見えないようにする
+            let within = new_size % block_size;
+            if within != 0 {
+                let zone = self.lookup_zone(inode, new_size / block_size)?;
+                if zone != 0 {
+                    let zeros = vec![0u8; (block_size - within) as usize];
+                    write_bytes(&*self.device, self.block_offset(zone) + within, &zeros)?;
+                }
+            }
+            self.free_zones_from(inode, new_size.div_ceil(block_size))?;
+        }
+        
+        inode.size = new_size as u32;
+        Ok(())
+    }
+    
+    /// 先頭`keep`ブロックより後ろのゾーンと、空になった間接ブロックを解放
+    fn free_zones_from(&self, inode: &mut MinixInode, keep: u64) -> FsResult<()> {
+        for zone in inode.zones.iter_mut().take(DIRECT_ZONES).skip(keep as usize) {
+            if *zone != 0 {
+                self.free_zone(*zone)?;
+                *zone = 0;
+            }
+        }
+        
+        let per_block = self.zones_per_block();
+        let mut start = DIRECT_ZONES as u64;
+        let mut span = 1u64;
+        for level in 1..=self.version().indirect_levels() {
+            span *= per_block;
+            let slot = DIRECT_ZONES + level - 1;
+            let level_keep = keep.saturating_sub(start);
+            if inode.zones[slot] != 0 && level_keep < span && self.free_tree(inode.zones[slot], level as u32, level_keep)? {
+                inode.zones[slot] = 0;
+            }
+            start += span;
+        }
+        Ok(())
+    }
+    
+    /// `zone`を根とする深さ`depth`の間接ブロックのうち、先頭`keep`ブロックより後ろを解放
+    ///
+    /// 間接ブロック自体も解放した場合は`true`を返す。
+    fn free_tree(&self, zone: u32, depth: u32, keep: u64) -> FsResult<bool> {
+        self.check_zone(zone)?;
+        let per_block = self.zones_per_block();
+        let span = per_block.pow(depth - 1);
+        
+        let mut block = self.read_block(zone)?;
+        let mut changed = false;
+        for index in 0..per_block as usize {
+            let child = self.zone_entry(&block, index);
+            let child_keep = keep.saturating_sub(index as u64 * span);
+            if child == 0 || child_keep >= span {
+                continue;
+            }
+            
+            let freed = if depth == 1 {
+                self.free_zone(child)?;
+                true
+            } else {
+                self.free_tree(child, depth - 1, child_keep)?
+            };
+            if freed {
+                self.set_zone_entry(&mut block, index, 0);
+                changed = true;
+            }
+        }
+        
+        if keep == 0 {
+            self.free_zone(zone)?;
+            return Ok(true);
+        }
+        if changed {
+            self.write_block(zone, &block)?;
+        }
+        Ok(false)
+    }
+    
+    /// ディレクトリの使用中のエントリを列挙（"."と".."を含む）
+    fn read_dir(&self, dir: &MinixInode) -> FsResult<Vec<MinixDirEntry>> {
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        
+        let mut data = vec![0u8; dir.size as usize];
+        self.read_data(dir, &mut data, 0)?;
+        Ok(parse_entries(&data, self.version(), self.superblock.dirent_size()))
+    }
+    
+    /// ディレクトリ内の名前を検索
+    fn find_entry(&self, dir: &MinixInode, name: &str) -> FsResult<Option<MinixDirEntry>> {
+        if name.len() > self.superblock.name_len {
+            return Ok(None);
+        }
+        Ok(self.read_dir(dir)?.into_iter().find(|entry| entry.name == name))
+    }
+    
+    /// ディレクトリが"."と".."以外を持たないか
+    fn is_empty_dir(&self, dir: &MinixInode) -> FsResult<bool> {
+        Ok(self.read_dir(dir)?.iter().all(|entry| entry.name == "." || entry.name == ".."))
+    }
+    
+    /// ディレクトリのスロットにエントリを書き込み（`inode`が0なら空きにする）
+    fn write_entry(&self, dir_num: u32, slot: usize, inode: u32, name: &str) -> FsResult<()> {
+        let dirent_size = self.superblock.dirent_size();
+        let mut dir = self.read_inode(dir_num)?;
+        let entry = encode_entry(self.version(), dirent_size, inode, name);
+        let result = self.write_data(&mut dir, &entry, (slot * dirent_size) as u64);
+        
+        let now = current_time();
+        dir.mtime = now;
+        dir.ctime = now;
+        self.write_inode(dir_num, &dir)?;
+        result.map(|_| ())
+    }
+    
+    /// ディレクトリにエントリを追加（空きスロットがなければ末尾に伸ばす）
+    fn add_entry(&self, dir_num: u32, name: &str, inode: u32) -> FsResult<()> {
+        let dirent_size = self.superblock.dirent_size();
+        let inode_size = self.version().dirent_inode_size();
+        let dir = self.read_inode(dir_num)?;
+        
+        let mut data = vec![0u8; dir.size as usize];
+        self.read_data(&dir, &mut data, 0)?;
+        let slot = data.chunks_exact(dirent_size)
+            .position(|raw| raw[..inode_size].iter().all(|&b| b == 0))
+            .unwrap_or(dir.size as usize / dirent_size);
+        self.write_entry(dir_num, slot, inode, name)
+    }
+    
+    /// パスをiノード番号に解決
+    fn resolve(&self, path: &str) -> FsResult<u32> {
+        let mut current = ROOT_INODE;
+        for (depth, component) in path.split('/').filter(|c| !c.is_empty() && *c != ".").enumerate() {
+            if depth > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
+            }
+            // ".."もディレクトリエントリとして存在するのでそのまま検索できる
+            let dir = self.read_inode(current)?;
+            current = self.find_entry(&dir, component)?.ok_or(FsError::NotFound)?.inode;
+        }
+        Ok(current)
+    }
+    
+    /// `path`の親ディレクトリと最終要素を解決（`/`始まりならルートから、それ以外は`start`から）
+    fn resolve_parent<'a>(&self, start: u32, path: &'a str) -> FsResult<(u32, &'a str)> {
+        let trimmed = path.trim_end_matches('/');
+        let (parent_path, name) = match trimmed.rfind('/') {
+            Some(pos) => (&trimmed[..pos], &trimmed[pos + 1..]),
+            None => ("", trimmed),
+        };
+        
+        let mut current = if path.starts_with('/') { ROOT_INODE } else { start };
+        for component in parent_path.split('/').filter(|c| !c.is_empty()) {
+            let dir = self.read_inode(current)?;
+            current = self.find_entry(&dir, component)?.ok_or(FsError::NotFound)?.inode;
+        }
+        if !self.read_inode(current)?.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        Ok((current, name))
+    }
+    
+    /// 新しいiノードを作って親ディレクトリに追加（更新ロックを保持して呼ぶ）
+    fn create_locked(&self, parent: u32, name: &str, mode: u16) -> FsResult<(u32, MinixInode)> {
+        self.check_writable()?;
+        validate_name(name, self.superblock.name_len)?;
+        
+        let dir = self.read_inode(parent)?;
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        if self.find_entry(&dir, name)?.is_some() {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        let num = self.alloc_inode()?;
+        let inode = MinixInode::new(mode, current_time());
+        self.write_inode(num, &inode)?;
+        if let Err(e) = self.add_entry(parent, name, num) {
+            let _ = self.free_inode(num);
+            return Err(e);
+        }
+        Ok((num, inode))
+    }
+    
+    /// 通常ファイルを作成
+    fn create(&self, parent: u32, name: &str, permissions: u16) -> FsResult<u32> {
+        let _guard = self.update_lock.lock();
+        Ok(self.create_locked(parent, name, mode::S_IFREG | permissions)?.0)
+    }
+    
+    /// ディレクトリを作成（"."と".."を持ち、親のリンク数を増やす）
+    fn mkdir(&self, parent: u32, name: &str, permissions: u16) -> FsResult<u32> {
+        let _guard = self.update_lock.lock();
+        if self.read_inode(parent)?.nlinks >= self.version().link_max() {
+            return Err(FsError::Other("リンク数が上限に達しました"));
+        }
+        
+        let (num, mut inode) = self.create_locked(parent, name, mode::S_IFDIR | permissions)?;
+        let dirent_size = self.superblock.dirent_size();
+        let mut data = encode_entry(self.version(), dirent_size, num, ".");
+        data.extend_from_slice(&encode_entry(self.version(), dirent_size, parent, ".."));
+        inode.nlinks = 2;
+        let result = self.write_data(&mut inode, &data, 0);
+        self.write_inode(num, &inode)?;
+        result?;
+        
+        let mut dir = self.read_inode(parent)?;
+        dir.nlinks += 1;
+        self.write_inode(parent, &dir)?;
+        Ok(num)
+    }
+    
+    /// シンボリックリンクを作成（ターゲットは最初のゾーンに置く）
+    fn symlink(&self, parent: u32, name: &str, target: &str) -> FsResult<()> {
+        if target.is_empty() || target.len() >= self.block_size() {
+            return Err(FsError::InvalidData);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let (num, mut inode) = self.create_locked(parent, name, mode::S_IFLNK | 0o777)?;
+        let result = self.write_data(&mut inode, target.as_bytes(), 0);
+        self.write_inode(num, &inode)?;
+        result.map(|_| ())
+    }
+    
+    /// デバイスノードを作成
+    fn mknod(&self, parent: u32, name: &str, file_type: super::FileType, major: u32, minor: u32) -> FsResult<()> {
+        let kind = match file_type {
+            super::FileType::BlockDevice => mode::S_IFBLK,
+            super::FileType::CharDevice => mode::S_IFCHR,
+            _ => return Err(FsError::InvalidData),
+        };
+        
+        let _guard = self.update_lock.lock();
+        let (num, mut inode) = self.create_locked(parent, name, kind | 0o600)?;
+        inode.set_device_number(self.version(), major, minor);
+        self.write_inode(num, &inode)
+    }
+    
+    /// リンク数を書き戻し、0になったらデータとiノードを解放
+    fn release_if_unlinked(&self, num: u32, mut inode: MinixInode) -> FsResult<()> {
+        inode.ctime = current_time();
+        if inode.nlinks > 0 {
+            return self.write_inode(num, &inode);
+        }
+        if inode.has_data() {
+            self.free_zones_from(&mut inode, 0)?;
+        }
+        self.free_inode(num)
+    }
+    
+    /// ファイル/空のディレクトリを削除
+    fn remove(&self, parent: u32, name: &str) -> FsResult<()> {
+        self.check_writable()?;
+        if name == "." || name == ".." {
+            return Err(FsError::InvalidData);
+        }
+        
+        let _guard = self.update_lock.lock();
+        let dir = self.read_inode(parent)?;
+        let entry = self.find_entry(&dir, name)?.ok_or(FsError::NotFound)?;
+        let mut inode = self.read_inode(entry.inode)?;
+        if inode.is_directory() && !self.is_empty_dir(&inode)? {
+            return Err(FsError::NotEmpty);
+        }
+        
+        self.write_entry(parent, entry.slot, 0, "")?;
+        if inode.is_directory() {
+            // 子の".."が親を指していた分を減らす
+            let mut dir = self.read_inode(parent)?;
+            dir.nlinks = dir.nlinks.saturating_sub(1);
+            self.write_inode(parent, &dir)?;
+            inode.nlinks = 0;
+        } else {
+            inode.nlinks = inode.nlinks.saturating_sub(1);
+        }
+        self.release_if_unlinked(entry.inode, inode)
+    }
+    
+    /// `dir`が`ancestor`自身かその子孫なら拒否する（ディレクトリを自分の下へ移動させない）
+    fn check_not_descendant(&self, ancestor: u32, mut dir: u32) -> FsResult<()> {
+        for _ in 0..MAX_PATH_DEPTH {
+            if dir == ancestor {
+                return Err(FsError::InvalidData);
+            }
+            if dir == ROOT_INODE {
+                return Ok(());
+            }
+            let inode = self.read_inode(dir)?;
+            dir = self.find_entry(&inode, "..")?.ok_or(FsError::CorruptedFs)?.inode;
+        }
+        Err(FsError::InvalidData)
+    }
+    
+    /// 名前を変更（移動先に同じ種類のものがあれば置き換える）
+    fn rename(&self, src_parent: u32, old_name: &str, dst_parent: u32, new_name: &str) -> FsResult<()> {
+        self.check_writable()?;
+        if old_name == "." || old_name == ".." {
+            return Err(FsError::InvalidData);
+        }
+        validate_name(new_name, self.superblock.name_len)?;
+        
+        let _guard = self.update_lock.lock();
+        let src_dir = self.read_inode(src_parent)?;
+        let source = self.find_entry(&src_dir, old_name)?.ok_or(FsError::NotFound)?;
+        let dst_dir = self.read_inode(dst_parent)?;
+        if !dst_dir.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        let is_dir = self.read_inode(source.inode)?.is_directory();
+        let moves_dir = is_dir && src_parent != dst_parent;
+        if moves_dir {
+            self.check_not_descendant(source.inode, dst_parent)?;
+            if dst_dir.nlinks >= self.version().link_max() {
+                return Err(FsError::Other("リンク数が上限に達しました"));
+            }
+        }
+        
+        match self.find_entry(&dst_dir, new_name)? {
+            Some(target) if target.inode == source.inode => return Ok(()),
+            Some(target) => {
+                let mut replaced = self.read_inode(target.inode)?;
+                match (is_dir, replaced.is_directory()) {
+                    (true, false) => return Err(FsError::NotDirectory),
+                    (false, true) => return Err(FsError::IsDirectory),
+                    (true, true) if !self.is_empty_dir(&replaced)? => return Err(FsError::NotEmpty),
+                    _ => {},
+                }
+                
+                self.write_entry(dst_parent, target.slot, source.inode, new_name)?;
+                if replaced.is_directory() {
+                    let mut dir = self.read_inode(dst_parent)?;
+                    dir.nlinks = dir.nlinks.saturating_sub(1);
+                    self.write_inode(dst_parent, &dir)?;
+                    replaced.nlinks = 0;
+                } else {
+                    replaced.nlinks = replaced.nlinks.saturating_sub(1);
+                }
+                self.release_if_unlinked(target.inode, replaced)?;
+            },
+            None => self.add_entry(dst_parent, new_name, source.inode)?,
+        }
+        self.write_entry(src_parent, source.slot, 0, "")?;
+        
+        if moves_dir {
+            // ".."を付け替え、親ディレクトリのリンク数を移す
+            let moved = self.read_inode(source.inode)?;
+            let dotdot = self.find_entry(&moved, "..")?.ok_or(FsError::CorruptedFs)?;
+            self.write_entry(source.inode, dotdot.slot, dst_parent, "..")?;
+            
+            let mut src = self.read_inode(src_parent)?;
+            src.nlinks = src.nlinks.saturating_sub(1);
+            self.write_inode(src_parent, &src)?;
+            let mut dst = self.read_inode(dst_parent)?;
+            dst.nlinks += 1;
+            self.write_inode(dst_parent, &dst)?;
+        }
+        
+        let mut moved = self.read_inode(source.inode)?;
+        moved.ctime = current_time();
+        self.write_inode(source.inode, &moved)
+    }
+    
+    /// シンボリックリンクのターゲットを読む
+    fn read_link(&self, num: u32) -> FsResult<String> {
+        let inode = self.read_inode(num)?;
+        if inode.mode & mode::S_IFMT != mode::S_IFLNK {
+            return Err(FsError::InvalidData);
+        }
+        let mut data = vec![0u8; inode.size as usize];
+        self.read_data(&inode, &mut data, 0)?;
+        String::from_utf8(data).map_err(|_| FsError::InvalidData)
+    }
+    
+    /// iノードのメタデータを構築
+    fn metadata(&self, num: u32, inode: &MinixInode) -> Metadata {
+        let block_size = self.superblock.block_size;
+        Metadata {
+            inode: num as u64,
+            file_type: inode.file_type(),
+            size: inode.size as u64,
+            uid: inode.uid as u32,
+            gid: inode.gid as u32,
+            permissions: Permissions {
+                read: inode.mode & 0o400 != 0,
+                write: !self.read_only && inode.mode & 0o200 != 0,
+                execute: inode.mode & 0o100 != 0,
+            },
+            // Minixは作成時刻を持たない
+            created: inode.ctime as u64,
+            accessed: inode.atime as u64,
+            modified: inode.mtime as u64,
+            links: inode.nlinks as u32,
+            block_size,
+            blocks: (inode.size as u64).div_ceil(block_size as u64),
+        }
+    }
+    
+    /// ファイルシステム統計を取得
+    fn stats(&self) -> FsStats {
+        let free = self.zone_map.lock().free_count() as u64;
+        FsStats {
+            total_blocks: self.superblock.data_zones() as u64,
+            free_blocks: free,
+            available_blocks: free,
+            total_nodes: self.superblock.inodes as u64,
+            free_nodes: self.inode_map.lock().free_count() as u64,
+            block_size: self.superblock.block_size,
+            max_filename_length: self.superblock.name_len as u32,
+        }
+    }
+    
+    /// デバイスを同期
+    fn sync(&self) -> FsResult<()> {
+        self.device.sync()
+    }
+}
+
+/// Minixファイルシステム
+pub struct MinixFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<MinixVolume>>>,
+}
+
+impl MinixFilesystem {
+    /// 新しいMinixファイルシステムインスタンスを作成
+    pub fn new() -> Self {
+        Self {
+            name: "minix".to_string(),
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// 最適化されたMinixファイルシステムインスタンスを作成
+    ///
+    /// ビットマップは常にメモリ上に保持するため、通常のインスタンスと同じ
+    pub fn new_optimized() -> Self {
+        Self::new()
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<MinixVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+}
+
+impl Default for MinixFilesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filesystem for MinixFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        let mut read_only = false;
+        for option in options.split(',').map(str::trim) {
+            match option {
+                "ro" => read_only = true,
+                "rw" => read_only = false,
+                _ => {}
+            }
+        }
+        
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = MinixVolume::open(device, block_device, read_only)?;
+        
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
+        
+        log::info!("Minixファイルシステムをマウント: {} -> {}", device, mount_point);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        if !volume.read_only && volume.version() != MinixVersion::V3 {
+            volume.write_state(volume.superblock.state)?;
+        }
+        volume.sync()?;
+        volume.device.close()?;
+        
+        log::info!("Minixファイルシステムをアンマウント: {} ({})", mount_point, volume.device_path);
+        Ok(())
+    }
+    
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let writable = mode != OpenMode::ReadOnly;
+        if writable && volume.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let num = match volume.resolve(path) {
+            Ok(num) => num,
+            Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => {
+                let (parent, name) = volume.resolve_parent(ROOT_INODE, path)?;
+                let num = volume.create(parent, name, 0o644)?;
+                return Ok(Arc::new(MinixFileHandle::new(volume, num, true)));
+            },
+            Err(e) => return Err(e),
+        };
+        
+        if mode == OpenMode::CreateNew {
+            return Err(FsError::AlreadyExists);
+        }
+        if volume.read_inode(num)?.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
+        
+        let handle = MinixFileHandle::new(volume, num, writable);
+        if mode == OpenMode::Truncate {
+            handle.resize(0)?;
+        }
+        Ok(Arc::new(handle))
+    }
+    
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let num = volume.resolve(path)?;
+        
+        if !volume.read_inode(num)?.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        
+        Ok(Arc::new(MinixDirHandle::new(volume, num)))
+    }
+    
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        let num = volume.resolve(path)?;
+        Ok(volume.metadata(num, &volume.read_inode(num)?))
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        Ok(self.find_volume(mount_point)?.stats())
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        for volume in self.volumes.read().values() {
+            volume.sync()?;
+        }
+        Ok(())
+    }
+    
+    fn read_link(&self, mount_point: &str, path: &str) -> FsResult<String> {
+        let volume = self.find_volume(mount_point)?;
+        volume.read_link(volume.resolve(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{FileType, Permissions};
+    use self::superblock::V2_BLOCK_SIZE;
+    use super::super::memory_disk::MemoryDisk;
+    
+    /// mkfs.minix相当のイメージを作る（`magic`はv1/v2のマジック、`None`ならv3）
+    fn format(magic: Option<u16>, block_size: u32, zones: u32, inodes: u32) -> Vec<u8> {
+        let version = match magic {
+            Some(0x137F) | Some(0x138F) => MinixVersion::V1,
+            Some(_) => MinixVersion::V2,
+            None => MinixVersion::V3,
+        };
+        let bs = block_size as usize;
+        let imap_blocks = (inodes + 1).div_ceil(block_size * 8);
+        let table_blocks = inodes.div_ceil(block_size / version.inode_size() as u32);
+        let first_data = 2 + imap_blocks + 1 + table_blocks;
+        
+        let mut image = vec![0u8; zones as usize * bs];
+        let sb = &mut image[1024..1024 + SUPERBLOCK_SIZE];
+        match magic {
+            Some(magic) => {
+                sb[0..2].copy_from_slice(&(inodes as u16).to_le_bytes());
+                sb[2..4].copy_from_slice(&(zones as u16).to_le_bytes());
+                sb[4..6].copy_from_slice(&(imap_blocks as u16).to_le_bytes());
+                sb[6..8].copy_from_slice(&1u16.to_le_bytes());
+                sb[8..10].copy_from_slice(&(first_data as u16).to_le_bytes());
+                sb[12..16].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+                sb[16..18].copy_from_slice(&magic.to_le_bytes());
+                sb[18..20].copy_from_slice(&state::VALID.to_le_bytes());
+                sb[20..24].copy_from_slice(&zones.to_le_bytes());
+            },
+            None => {
+                sb[0..4].copy_from_slice(&inodes.to_le_bytes());
+                sb[6..8].copy_from_slice(&(imap_blocks as u16).to_le_bytes());
+                sb[8..10].copy_from_slice(&1u16.to_le_bytes());
+                sb[10..12].copy_from_slice(&(first_data as u16).to_le_bytes());
+                sb[16..20].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+                sb[20..24].copy_from_slice(&zones.to_le_bytes());
+                sb[24..26].copy_from_slice(&0x4D5Au16.to_le_bytes());
+                sb[28..30].copy_from_slice(&(block_size as u16).to_le_bytes());
+            },
+        }
+        
+        // iノード0と1（ルート）、ゾーンビット0と1（ルートのデータ）を使用中にする
+        image[2 * bs] = 0b11;
+        image[(2 + imap_blocks as usize) * bs] = 0b11;
+        
+        let dirent_size = version.dirent_inode_size() + if version == MinixVersion::V3 { 60 } else { 30 };
+        let mut root = MinixInode::new(mode::S_IFDIR | 0o755, 0);
+        root.nlinks = 2;
+        root.size = 2 * dirent_size as u32;
+        root.zones[0] = first_data;
+        let table = (2 + imap_blocks as usize + 1) * bs;
+        let raw = root.to_bytes(version);
+        image[table..table + raw.len()].copy_from_slice(&raw);
+        
+        let data = first_data as usize * bs;
+        image[data..data + dirent_size].copy_from_slice(&encode_entry(version, dirent_size, 1, "."));
+        image[data + dirent_size..data + 2 * dirent_size].copy_from_slice(&encode_entry(version, dirent_size, 1, ".."));
+        image
+    }
+    
+    #[test]
+    fn read_write_indirect_zones_and_directory_operations() {
+        for (magic, block_size) in [(Some(0x138Fu16), V2_BLOCK_SIZE), (Some(0x2478), V2_BLOCK_SIZE), (None, 2048)] {
+            let disk: Arc<dyn BlockDevice> = Arc::new(MemoryDisk::new(format(magic, block_size, 2048, 64)));
+            let volume = Arc::new(MinixVolume::open("/dev/test", disk.clone(), false).unwrap());
+            let name_len = volume.superblock.name_len;
+            let baseline = volume.stats();
+            
+            let root = MinixDirHandle::new(volume.clone(), ROOT_INODE);
+            root.create_directory("docs", Permissions::default()).unwrap();
+            let docs = volume.resolve("docs").unwrap();
+            assert_eq!(volume.read_inode(ROOT_INODE).unwrap().nlinks, 3);
+            
+            // 二重間接ゾーンの先へ書き、手前は穴として0が読める
+            let file = MinixDirHandle::new(volume.clone(), docs).create_file("data.bin", Permissions::default()).unwrap();
+            let far = (DIRECT_ZONES as u64 + volume.zones_per_block() + 3) * block_size as u64 + 10;
+            file.write(b"head", 0).unwrap();
+            file.write(b"tail", far).unwrap();
+            assert_eq!(file.size().unwrap(), far + 4);
+            let mut buffer = [0xFFu8; 8];
+            file.read(&mut buffer, far - 4).unwrap();
+            assert_eq!(&buffer, b"\0\0\0\0tail");
+            // docsのディレクトリブロック + データ2ゾーン + 二重間接と間接の2ブロック
+            assert_eq!(volume.stats().free_blocks, baseline.free_blocks - 1 - 2 - 2);
+            
+            file.resize(2).unwrap();
+            file.resize(4).unwrap();
+            file.read(&mut buffer[..4], 0).unwrap();
+            assert_eq!(&buffer[..4], b"he\0\0");
+            assert_eq!(volume.stats().free_blocks, baseline.free_blocks - 2);
+            
+            // 名前の長さの上限、空でないディレクトリの削除、ディレクトリをまたぐ名前変更
+            let long = "n".repeat(name_len);
+            root.create_symlink(&long, "docs/data.bin").unwrap();
+            assert_eq!(volume.read_link(volume.resolve(&long).unwrap()).unwrap(), "docs/data.bin");
+            assert!(root.create_file(&"n".repeat(name_len + 1), Permissions::default()).is_err());
+            assert!(matches!(root.remove("docs"), Err(FsError::NotEmpty)));
+            root.rename("docs/data.bin", "moved").unwrap_err();
+            MinixDirHandle::new(volume.clone(), docs).rename("data.bin", "/moved").unwrap();
+            assert_eq!(root.lookup("moved").unwrap().file_type, FileType::Regular);
+            assert!(matches!(root.rename("docs", "docs/inner"), Err(FsError::InvalidData)));
+            root.remove("docs").unwrap();
+            root.remove(&long).unwrap();
+            assert_eq!(volume.read_inode(ROOT_INODE).unwrap().nlinks, 2);
+            
+            // 削除したdocsの空きスロットが再利用される
+            root.create_device("sda", FileType::BlockDevice, 8, 1).unwrap();
+            assert_eq!(root.device_number("sda").unwrap(), (8, 1));
+            let names: Vec<String> = root.read_entries().unwrap().into_iter().map(|e| e.name).collect();
+            assert_eq!(names, vec!["sda", "moved"]);
+            
+            // 開き直しても内容とビットマップが保たれ、v1/v2はアンマウント時にVALIDへ戻す
+            let fs = MinixFilesystem::new();
+            fs.volumes.write().insert("/mnt".to_string(), volume);
+            // 同じインスタンスに別のボリュームがマウントされていても取り違えない
+            let other = MinixVolume::open("/dev/other", Arc::new(MemoryDisk::new(format(magic, block_size, 2048, 64))), false).unwrap();
+            fs.volumes.write().insert("/other".to_string(), Arc::new(other));
+            assert_eq!(fs.metadata("/mnt", "moved").unwrap().size, 4);
+            assert!(matches!(fs.metadata("/other", "moved"), Err(FsError::NotFound)));
+            fs.unmount("/other").unwrap();
+            fs.unmount("/mnt").unwrap();
+            let volume = MinixVolume::open("/dev/test", disk, true).unwrap();
+            assert!(volume.superblock.is_clean());
+            assert_eq!(volume.stats().free_nodes, baseline.free_nodes - 2);
+            assert_eq!(volume.stats().free_blocks, baseline.free_blocks - 1);
+            let moved = volume.resolve("moved").unwrap();
+            assert_eq!(volume.read_inode(moved).unwrap().size, 4);
+        }
+    }
+}
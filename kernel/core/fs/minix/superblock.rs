@@ -0,0 +1,292 @@
+// Minix スーパーブロック
+//
+// v1/v2（名前14/30文字）とv3（名前60文字、可変ブロックサイズ）のスーパーブロックの
+// 解析と、ビットマップ・iノードテーブルの配置の計算
+
+use super::super::{FsError, FsResult};
+
+/// スーパーブロックのデバイス上のバイトオフセット（ブロックサイズによらない）
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+/// スーパーブロックとして読み込むバイト数
+pub const SUPERBLOCK_SIZE: usize = 32;
+/// v1/v2のブロックサイズ
+pub const V2_BLOCK_SIZE: u32 = 1024;
+/// s_stateのオフセット（v1/v2のみ）
+pub const STATE_OFFSET: usize = 18;
+
+/// v1、名前14文字
+const MAGIC_V1: u16 = 0x137F;
+/// v1、名前30文字
+const MAGIC_V1_30: u16 = 0x138F;
+/// v2、名前14文字
+const MAGIC_V2: u16 = 0x2468;
+/// v2、名前30文字
+const MAGIC_V2_30: u16 = 0x2478;
+/// v3
+const MAGIC_V3: u16 = 0x4D5A;
+/// v3の名前の長さ
+const V3_NAME_LEN: usize = 60;
+
+/// s_stateのフラグ（v1/v2のみ）
+pub mod state {
+    /// 正常にアンマウントされた
+    pub const VALID: u16 = 0x0001;
+    /// エラーが検出された
+    pub const ERROR: u16 = 0x0002;
+}
+
+/// ディスク形式のバージョン
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinixVersion {
+    /// 16ビットのゾーン番号、32バイトのiノード
+    V1,
+    /// 32ビットのゾーン番号、64バイトのiノード
+    V2,
+    /// v2のiノードに32ビットのiノード番号を持つディレクトリエントリ
+    V3,
+}
+
+impl MinixVersion {
+    /// ディスク上のiノードのサイズ
+    pub fn inode_size(self) -> usize {
+        match self {
+            MinixVersion::V1 => 32,
+            _ => 64,
+        }
+    }
+    
+    /// 間接ブロック内のゾーン番号のサイズ
+    pub fn zone_entry_size(self) -> usize {
+        match self {
+            MinixVersion::V1 => 2,
+            _ => 4,
+        }
+    }
+    
+    /// 間接ゾーンの段数（v1は二重間接まで、v2/v3は三重間接まで）
+    pub fn indirect_levels(self) -> usize {
+        match self {
+            MinixVersion::V1 => 2,
+            _ => 3,
+        }
+    }
+    
+    /// ディレクトリエントリ内のiノード番号のサイズ
+    pub fn dirent_inode_size(self) -> usize {
+        match self {
+            MinixVersion::V3 => 4,
+            _ => 2,
+        }
+    }
+    
+    /// 1つのiノードのリンク数の上限
+    pub fn link_max(self) -> u16 {
+        match self {
+            MinixVersion::V1 => 250,
+            _ => 65530,
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Minixスーパーブロック
+#[derive(Debug, Clone)]
+pub struct MinixSuperblock {
+    /// ディスク形式のバージョン
+    pub version: MinixVersion,
+    /// iノード数
+    pub inodes: u32,
+    /// ゾーン数（ブート・スーパーブロック・ビットマップ・iノードテーブルを含む）
+    pub zones: u32,
+    /// iノードビットマップのブロック数
+    pub imap_blocks: u32,
+    /// ゾーンビットマップのブロック数
+    pub zmap_blocks: u32,
+    /// 最初のデータゾーン
+    pub first_data_zone: u32,
+    /// ゾーンあたりのブロック数の対数
+    pub log_zone_size: u32,
+    /// ファイルサイズの上限
+    pub max_size: u64,
+    /// ブロックサイズ
+    pub block_size: u32,
+    /// ファイル名の最大長（14/30/60）
+    pub name_len: usize,
+    /// マウント状態（v3は常にVALID）
+    pub state: u16,
+}
+
+impl MinixSuperblock {
+    /// スーパーブロック（デバイスの1024バイト目から）を解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < SUPERBLOCK_SIZE {
+            return Err(FsError::InvalidData);
+        }
+        
+        let superblock = if read_u16(data, 24) == MAGIC_V3 {
+            let block_size = read_u16(data, 28) as u32;
+            Self {
+                version: MinixVersion::V3,
+                inodes: read_u32(data, 0),
+                zones: read_u32(data, 20),
+                imap_blocks: read_u16(data, 6) as u32,
+                zmap_blocks: read_u16(data, 8) as u32,
+                first_data_zone: read_u16(data, 10) as u32,
+                log_zone_size: read_u16(data, 12) as u32,
+                max_size: read_u32(data, 16) as u64,
+                block_size,
+                name_len: V3_NAME_LEN,
+                state: state::VALID,
+            }
+        } else {
+            let (version, name_len) = match read_u16(data, 16) {
+                MAGIC_V1 => (MinixVersion::V1, 14),
+                MAGIC_V1_30 => (MinixVersion::V1, 30),
+                MAGIC_V2 => (MinixVersion::V2, 14),
+                MAGIC_V2_30 => (MinixVersion::V2, 30),
+                _ => return Err(FsError::BadMagic),
+            };
+            let zones = match version {
+                MinixVersion::V1 => read_u16(data, 2) as u32,
+                _ => read_u32(data, 20),
+            };
+            Self {
+                version,
+                inodes: read_u16(data, 0) as u32,
+                zones,
+                imap_blocks: read_u16(data, 4) as u32,
+                zmap_blocks: read_u16(data, 6) as u32,
+                first_data_zone: read_u16(data, 8) as u32,
+                log_zone_size: read_u16(data, 10) as u32,
+                max_size: read_u32(data, 12) as u64,
+                block_size: V2_BLOCK_SIZE,
+                name_len,
+                state: read_u16(data, STATE_OFFSET),
+            }
+        };
+        
+        superblock.validate()?;
+        Ok(superblock)
+    }
+    
+    /// 配置の整合性を確認
+    fn validate(&self) -> FsResult<()> {
+        if !self.block_size.is_power_of_two() || !(1024..=32768).contains(&self.block_size) {
+            log::warn!("Minix: ブロックサイズ{}は未対応です", self.block_size);
+            return Err(FsError::BadSuperblock);
+        }
+        if self.log_zone_size != 0 {
+            // ゾーンが複数ブロックからなる形式はmkfs.minixも作らない
+            log::warn!("Minix: ゾーンサイズ2^{}ブロックは未対応です", self.log_zone_size);
+            return Err(FsError::UnsupportedFeature);
+        }
+        if self.inodes == 0 || self.imap_blocks == 0 || self.zmap_blocks == 0 {
+            return Err(FsError::BadSuperblock);
+        }
+        
+        let bits_per_block = self.block_size as u64 * 8;
+        let table_end = self.inode_table_block() as u64 + self.inode_table_blocks() as u64;
+        if (self.imap_blocks as u64) * bits_per_block < self.inodes as u64 + 1
+            || (self.zmap_blocks as u64) * bits_per_block < self.data_zones() as u64 + 1
+            || (self.first_data_zone as u64) < table_end
+            || self.first_data_zone >= self.zones
+        {
+            log::warn!("Minix: スーパーブロックの配置が不正です（iノード{}, ゾーン{}, データ開始{}）",
+                      self.inodes, self.zones, self.first_data_zone);
+            return Err(FsError::BadSuperblock);
+        }
+        Ok(())
+    }
+    
+    /// iノードビットマップの先頭ブロック（ブート・スーパーブロックの直後）
+    pub fn imap_block(&self) -> u32 {
+        2
+    }
+    
+    /// ゾーンビットマップの先頭ブロック
+    pub fn zmap_block(&self) -> u32 {
+        self.imap_block() + self.imap_blocks
+    }
+    
+    /// iノードテーブルの先頭ブロック
+    pub fn inode_table_block(&self) -> u32 {
+        self.zmap_block() + self.zmap_blocks
+    }
+    
+    /// ブロックあたりのiノード数
+    pub fn inodes_per_block(&self) -> u32 {
+        self.block_size / self.version.inode_size() as u32
+    }
+    
+    /// iノードテーブルのブロック数
+    pub fn inode_table_blocks(&self) -> u32 {
+        self.inodes.div_ceil(self.inodes_per_block())
+    }
+    
+    /// データゾーンの数
+    pub fn data_zones(&self) -> u32 {
+        self.zones - self.first_data_zone
+    }
+    
+    /// ディレクトリエントリのサイズ
+    pub fn dirent_size(&self) -> usize {
+        self.version.dirent_inode_size() + self.name_len
+    }
+    
+    /// 前回正常にアンマウントされたか
+    pub fn is_clean(&self) -> bool {
+        self.state & state::VALID != 0 && self.state & state::ERROR == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_all_versions_and_rejects_bad_layouts() {
+        // mkfs.minix -1 -n 30 相当（iノード64、1440ゾーン）
+        let mut v1 = vec![0u8; SUPERBLOCK_SIZE];
+        v1[0..2].copy_from_slice(&64u16.to_le_bytes());
+        v1[2..4].copy_from_slice(&1440u16.to_le_bytes());
+        v1[4..6].copy_from_slice(&1u16.to_le_bytes());
+        v1[6..8].copy_from_slice(&1u16.to_le_bytes());
+        v1[8..10].copy_from_slice(&6u16.to_le_bytes());
+        v1[12..16].copy_from_slice(&268_966_912u32.to_le_bytes());
+        v1[16..18].copy_from_slice(&MAGIC_V1_30.to_le_bytes());
+        v1[18..20].copy_from_slice(&state::VALID.to_le_bytes());
+        let sb = MinixSuperblock::parse(&v1).unwrap();
+        assert_eq!((sb.version, sb.name_len, sb.dirent_size()), (MinixVersion::V1, 30, 32));
+        assert_eq!((sb.zmap_block(), sb.inode_table_block(), sb.inode_table_blocks()), (3, 4, 2));
+        assert!(sb.is_clean());
+        
+        // iノードテーブルとデータゾーンが重なる
+        v1[8..10].copy_from_slice(&5u16.to_le_bytes());
+        assert!(matches!(MinixSuperblock::parse(&v1), Err(FsError::BadSuperblock)));
+        v1[16..18].copy_from_slice(&0x1234u16.to_le_bytes());
+        assert!(matches!(MinixSuperblock::parse(&v1), Err(FsError::BadMagic)));
+        
+        // v3（4096バイトブロック、名前60文字）
+        let mut v3 = vec![0u8; SUPERBLOCK_SIZE];
+        v3[0..4].copy_from_slice(&1024u32.to_le_bytes());
+        v3[6..8].copy_from_slice(&1u16.to_le_bytes());
+        v3[8..10].copy_from_slice(&1u16.to_le_bytes());
+        v3[10..12].copy_from_slice(&20u16.to_le_bytes());
+        v3[20..24].copy_from_slice(&4096u32.to_le_bytes());
+        v3[24..26].copy_from_slice(&MAGIC_V3.to_le_bytes());
+        v3[28..30].copy_from_slice(&4096u16.to_le_bytes());
+        let sb = MinixSuperblock::parse(&v3).unwrap();
+        assert_eq!((sb.version, sb.block_size, sb.dirent_size()), (MinixVersion::V3, 4096, 64));
+        assert_eq!((sb.inodes_per_block(), sb.inode_table_blocks(), sb.data_zones()), (64, 16, 4076));
+        
+        v3[12..14].copy_from_slice(&1u16.to_le_bytes());
+        assert!(matches!(MinixSuperblock::parse(&v3), Err(FsError::UnsupportedFeature)));
+    }
+}
@@ -3,6 +3,17 @@
 // 世界最高性能・最高信頼性のファイルシステム実装
 
 mod vfs;         // 仮想ファイルシステム
+mod namei;       // パス解決
+mod dcache;      // ディレクトリエントリキャッシュ
+mod page_cache;  // ファイルページキャッシュとライトバック
+mod notify;      // ファイル変更通知（inotify相当）
+mod xattr;       // 拡張属性の名前空間とPOSIX ACL
+mod lock;        // ファイルロック（fcntlのバイト範囲ロックとflock）
+mod partition;   // MBR/GPTパーティションテーブル
+mod crc32;       // パーティションテーブルのCRC32
+mod crc32c;      // ファイルシステム共通のCRC32C
+#[cfg(test)]
+mod memory_disk; // テスト用のメモリ上のブロックデバイス
 mod fat32;       // FAT32ファイルシステム
 mod btrfs;       // Btrfsファイルシステム
 mod exfat;       // exFATファイルシステム
@@ -15,11 +26,29 @@ mod hfs;         // HFS+ファイルシステム
 mod iso9660;     // ISO9660ファイルシステム（CD-ROM）
 mod udf;         // UDFファイルシステム（DVD）
 mod minix;       // Minixファイルシステム
+mod tmpfs;       // メモリ上のファイルシステム
+mod procfs;      // プロセス情報の仮想ファイルシステム
+pub mod devfs;   // デバイスノードの仮想ファイルシステム（ドライバから登録する）
+pub mod loopdev; // ファイルを裏に持つループデバイス
+mod overlay;     // 複数のディレクトリを重ねるoverlayファイルシステム
 mod cache;       // 高速ファイルシステムキャッシュ
 mod journal;     // 最適化ジャーナリング
 mod transaction; // 原子的トランザクション処理
 
 pub use self::vfs::*;
+pub use self::namei::{lookup_flags, ResolvedPath, MAX_SYMLINK_FOLLOWS};
+pub use self::dcache::{Dentry, DcacheStats, shrink as shrink_dcache, stats as dcache_stats};
+pub use self::page_cache::{
+    CachedPage, PageCacheStats, PageMapper, WritebackConfig, mapped_page, release_mapped_page, set_writeback_config,
+    start_writeback, writeback_all, shrink as shrink_page_cache, stats as page_cache_stats,
+};
+pub use self::notify::{event_mask, Watcher, WatchEvent, WatchId, DEFAULT_QUEUE_CAPACITY};
+pub use self::xattr::{
+    access, AclEntry, AclTag, Credentials, PosixAcl, XattrNamespace, POSIX_ACL_ACCESS, POSIX_ACL_DEFAULT,
+    XATTR_NAME_MAX, XATTR_SIZE_MAX,
+};
+pub use self::lock::{LockKind, RangeLock};
+pub use self::partition::{Guid, Partition, PartitionType, PartitionDevice, read_partitions};
 pub use self::cache::*;
 pub use self::journal::*;
 pub use self::transaction::*;
@@ -38,6 +67,9 @@ pub mod implementations {
     pub use super::iso9660::*;
     pub use super::udf::*;
     pub use super::minix::*;
+    pub use super::tmpfs::*;
+    pub use super::procfs::*;
+    pub use super::overlay::*;
 }
 
 // エラー定義
@@ -74,16 +106,35 @@ pub enum FsError {
     Timeout,                   // タイムアウト
     CacheInconsistency,        // キャッシュ不整合
     MetadataError,             // メタデータエラー
+    ChecksumError,             // チェックサム不一致
+    SymlinkLoop,               // シンボリックリンクの循環（ELOOP）
     Other(&'static str),
 }
 
 pub type FsResult<T> = Result<T, FsError>;
 
+/// 回収する1ページあたりのdentry数の目安
+const DENTRIES_PER_PAGE: usize = 32;
+
+/// メモリ逼迫時にファイルシステムのキャッシュを回収（ページ割り当ての失敗時にメモリ管理から呼ばれる）
+///
+/// `pages`ページ分を目安に古いdentryを解放し、解放したエントリ数を返す
+pub fn reclaim_memory(pages: usize) -> usize {
+    dcache::shrink(pages.saturating_mul(DENTRIES_PER_PAGE))
+}
+
 // ファイルシステム初期化
 pub fn init() -> FsResult<()> {
     // 高性能キャッシュシステムを初期化
     cache::init_with_options(true, 65536, 4096)?;
     
+    // パス解決用のディレクトリエントリキャッシュを初期化
+    dcache::init(16384)?;
+    
+    // ファイルのページキャッシュとライトバックスレッドを開始
+    page_cache::init(65536, WritebackConfig::default())?;
+    page_cache::start_writeback()?;
+    
     // 先進的ジャーナリングシステムを初期化
     journal::init_with_options(true, 8192)?;
     
@@ -119,6 +170,18 @@ pub fn init() -> FsResult<()> {
     // 歴史的・特殊用途向けファイルシステム
     vfs::register_filesystem("minix", minix::MinixFilesystem::new_optimized())?;
     
+    // メモリ上のファイルシステム（/tmpや起動初期用）
+    vfs::register_filesystem("tmpfs", tmpfs::TmpfsFilesystem::new())?;
+    
+    // カーネルの状態を公開する仮想ファイルシステム（/proc）
+    vfs::register_filesystem("proc", procfs::ProcFilesystem::new())?;
+    
+    // ドライバが登録したデバイスを公開する仮想ファイルシステム（/dev）
+    vfs::register_filesystem("devfs", devfs::DevFilesystem::new())?;
+    
+    // 読み取り専用のイメージに書き込み可能な層を重ねるファイルシステム（コンテナ用）
+    vfs::register_filesystem("overlay", overlay::OverlayFilesystem::new())?;
+    
     log::info!("世界最高性能ファイルシステムモジュール初期化完了");
     
     Ok(())
@@ -0,0 +1,345 @@
+// VFS パス解決（namei）
+//
+// パスを1要素ずつ`DirHandle::lookup`で辿り、`.`/`..`、シンボリックリンク、
+// マウントポイントの境界を処理して正規化された絶対パスへ解決する
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::{FsError, FsResult, FsId, FileType, Filesystem, InodeNum};
+use super::dcache::{self, Dentry};
+
+/// パス解決のフラグ
+pub mod lookup_flags {
+    /// 最後の要素がシンボリックリンクなら辿らずにリンク自身を返す（O_NOFOLLOW相当）
+    pub const NOFOLLOW: u32 = 0x0001;
+    /// 最後の要素がディレクトリでなければ失敗する（O_DIRECTORY相当）
+    pub const DIRECTORY: u32 = 0x0002;
+}
+
+/// 1回の解決で辿るシンボリックリンクの上限（超えるとSymlinkLoop）
+pub const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+/// 解決に使うマウント情報（レジストリのロックを持たずに辿るための複製）
+#[derive(Clone)]
+pub(super) struct MountEntry {
+    /// マウントID
+    pub id: FsId,
+    /// マウントポイント（正規化済みの絶対パス）
+    pub path: String,
+    /// マウントされたファイルシステム
+    pub fs: Arc<dyn Filesystem>,
+    /// ファイルシステムのルートディレクトリのinode番号（dcacheのキー）
+    pub root: InodeNum,
+}
+
+/// パスの解決結果
+#[derive(Clone)]
+pub struct ResolvedPath {
+    /// シンボリックリンクと`..`を解決した絶対パス
+    pub path: String,
+    /// パスを含むファイルシステム
+    pub fs: Arc<dyn Filesystem>,
+    /// そのマウントのID
+    pub fs_id: FsId,
+    /// そのファイルシステムのマウントポイント
+    pub mount_point: String,
+    /// ファイルシステム内のパス（"/"で始まる）
+    pub fs_path: String,
+    /// 最後の要素の種類
+    pub file_type: FileType,
+    /// 最後の要素のinode番号
+    pub inode: InodeNum,
+}
+
+/// `path`が`mount_point`以下にあるか（`/mnt`は`/mntdata`を含まない）
+pub(super) fn is_within(path: &str, mount_point: &str) -> bool {
+    mount_point == "/"
+        || path == mount_point
+        || (path.starts_with(mount_point) && path.as_bytes()[mount_point.len()] == b'/')
+}
+
+/// パスを含むマウントのうち最も深いものを返す
+pub(super) fn find_mount<'a>(mounts: &'a [MountEntry], path: &str) -> Option<&'a MountEntry> {
+    mounts.iter()
+        .filter(|mount| is_within(path, &mount.path))
+        .max_by_key(|mount| mount.path.len())
+}
+
+/// 要素の列を絶対パスに連結
+fn join(components: &[String]) -> String {
+    if components.is_empty() {
+        return "/".to_string();
+    }
+    let mut path = String::new();
+    for component in components {
+        path.push('/');
+        path.push_str(component);
+    }
+    path
+}
+
+/// 絶対パスをマウントとファイルシステム内のパスに分解
+fn locate<'a>(mounts: &'a [MountEntry], path: &str) -> FsResult<(&'a MountEntry, String)> {
+    let mount = find_mount(mounts, path).ok_or(FsError::NotFound)?;
+    let relative = if mount.path == "/" { path } else { &path[mount.path.len()..] };
+    let relative = if relative.is_empty() { "/".to_string() } else { relative.to_string() };
+    Ok((mount, relative))
+}
+
+/// 未処理の要素をスタックに積む（先頭の要素が最後に積まれる）
+fn push_components(pending: &mut Vec<String>, path: &str) {
+    pending.extend(path.split('/').rev().map(str::to_string));
+}
+
+/// dcacheを（ディレクトリのinode, 名前）で引き、なければディレクトリを検索して結果（不在も含む）をキャッシュする
+///
+/// inode番号、ファイルタイプと、キャッシュ済みならシンボリックリンクのターゲットを返す
+fn lookup_cached(mount: &MountEntry, dir: InodeNum, dir_path: &str, name: &str) -> FsResult<(InodeNum, FileType, Option<String>)> {
+    if mount.fs.is_virtual() {
+        let entry = mount.fs.open_directory(&mount.path, dir_path)?.lookup(name)?;
+        return Ok((entry.inode, entry.file_type, None));
+    }
+    
+    match dcache::lookup(mount.id, dir, name) {
+        Some(Dentry::Positive { inode, file_type, link_target }) => return Ok((inode, file_type, link_target)),
+        Some(Dentry::Negative) => return Err(FsError::NotFound),
+        None => {}
+    }
+    
+    match mount.fs.open_directory(&mount.path, dir_path)?.lookup(name) {
+        Ok(entry) => {
+            let dentry = Dentry::Positive { inode: entry.inode, file_type: entry.file_type, link_target: None };
+            dcache::insert(mount.id, dir, name, dentry);
+            Ok((entry.inode, entry.file_type, None))
+        },
+        Err(FsError::NotFound) => {
+            dcache::insert(mount.id, dir, name, Dentry::Negative);
+            Err(FsError::NotFound)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// パスを解決する
+///
+/// 相対パスは`cwd`（正規化済みの絶対パス）から辿る。`..`は解決済みの
+/// 親へ戻るため、マウントのルートからは下のファイルシステムへ抜ける
+pub(super) fn walk(mounts: &[MountEntry], cwd: &str, path: &str, flags: u32) -> FsResult<ResolvedPath> {
+    if path.is_empty() {
+        return Err(FsError::NotFound);
+    }
+    
+    // 解決済みの要素と、それぞれのinode番号
+    let mut current: Vec<String> = Vec::new();
+    let mut inodes: Vec<InodeNum> = Vec::new();
+    let mut current_type = FileType::Directory;
+    
+    // 相対パスはcwdの各要素のinodeも要るので、cwdをルートから辿り直す
+    let mut pending = Vec::new();
+    push_components(&mut pending, path);
+    if !path.starts_with('/') {
+        push_components(&mut pending, cwd);
+    }
+    let mut follows = 0;
+    
+    while let Some(component) = pending.pop() {
+        // "x/"や"x/.."のxはディレクトリでなければならない
+        if current_type != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        
+        match component.as_str() {
+            "" | "." => continue,
+            ".." => {
+                current.pop();
+                inodes.pop();
+                continue;
+            },
+            _ => {}
+        }
+        
+        let (mount, dir_path) = locate(mounts, &join(&current))?;
+        let dir = inodes.last().copied().unwrap_or(mount.root);
+        current.push(component);
+        
+        // マウントポイントそのものは下のファイルシステムを見ずに、マウントされた側のルートとする
+        if let Some(mounted) = mounts.iter().rev().find(|mount| mount.path == join(&current)) {
+            inodes.push(mounted.root);
+            current_type = FileType::Directory;
+            continue;
+        }
+        
+        let name = current.last().map(String::as_str).unwrap_or_default();
+        let fs_path = format!("{}/{}", dir_path.trim_end_matches('/'), name);
+        let (inode, file_type, cached_target) = lookup_cached(mount, dir, &dir_path, name)?;
+        
+        let follow = !pending.is_empty() || flags & lookup_flags::NOFOLLOW == 0;
+        if file_type == FileType::SymbolicLink && follow {
+            follows += 1;
+            if follows > MAX_SYMLINK_FOLLOWS {
+                log::warn!("VFS: シンボリックリンクが多すぎます: {}", path);
+                return Err(FsError::SymlinkLoop);
+            }
+            
+            let target = match cached_target {
+                Some(target) => target,
+                None => {
+                    let target = mount.fs.read_link(&mount.path, &fs_path)?;
+                    dcache::set_link_target(mount.id, dir, name, &target);
+                    target
+                },
+            };
+            if target.is_empty() {
+                return Err(FsError::NotFound);
+            }
+            
+            // リンクはそれを含むディレクトリからの相対パスとして解釈する
+            current.pop();
+            if target.starts_with('/') {
+                current.clear();
+                inodes.clear();
+            }
+            push_components(&mut pending, &target);
+            continue;
+        }
+        inodes.push(inode);
+        current_type = file_type;
+    }
+    
+    if flags & lookup_flags::DIRECTORY != 0 && current_type != FileType::Directory {
+        return Err(FsError::NotDirectory);
+    }
+    
+    let path = join(&current);
+    let (mount, fs_path) = locate(mounts, &path)?;
+    Ok(ResolvedPath {
+        inode: inodes.last().copied().unwrap_or(mount.root),
+        fs: mount.fs.clone(),
+        fs_id: mount.id,
+        mount_point: mount.path.clone(),
+        fs_path,
+        file_type: current_type,
+        path,
+    })
+}
+
+/// 親ディレクトリを解決し、最後の要素名と組にして返す（作成系の操作用）
+pub(super) fn walk_parent(mounts: &[MountEntry], cwd: &str, path: &str) -> FsResult<(ResolvedPath, String)> {
+    let trimmed = path.trim_end_matches('/');
+    let (parent, name) = match trimmed.rfind('/') {
+        Some(index) => (if index == 0 { "/" } else { &trimmed[..index] }, &trimmed[index + 1..]),
+        None => (".", trimmed),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(FsError::InvalidData);
+    }
+    
+    let parent = walk(mounts, cwd, parent, lookup_flags::DIRECTORY)?;
+    Ok((parent, name.to_string()))
+}
+
+impl ResolvedPath {
+    /// このディレクトリ内の`name`のファイルシステム内パス
+    pub fn child_fs_path(&self, name: &str) -> String {
+        format!("{}/{}", self.fs_path.trim_end_matches('/'), name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{DirHandle, OpenMode, Permissions};
+    use super::super::tmpfs::TmpfsFilesystem;
+    use super::super::dcache::CachedDirHandle;
+    
+    fn mount_entry(path: &str, fs: Arc<dyn Filesystem>) -> MountEntry {
+        let root = fs.metadata(path, "/").unwrap().inode;
+        MountEntry { id: FsId::new(), path: path.to_string(), fs, root }
+    }
+    
+    #[test]
+    fn walks_dots_symlinks_and_mounts() {
+        let root = Arc::new(TmpfsFilesystem::new());
+        root.mount("tmpfs", "/", "").unwrap();
+        let data = Arc::new(TmpfsFilesystem::new());
+        data.mount("tmpfs", "/mnt", "").unwrap();
+        
+        let dir = root.open_directory("/", "/").unwrap();
+        dir.create_directory("mnt", Permissions::default()).unwrap();
+        dir.create_directory("mntdata", Permissions::default()).unwrap();
+        dir.create_symlink("up", "mnt/sub/..").unwrap();
+        dir.create_symlink("loop", "loop").unwrap();
+        data.open_directory("/mnt", "/").unwrap().create_directory("sub", Permissions::default()).unwrap();
+        data.open_file("/mnt", "/sub/file", OpenMode::Create).unwrap();
+        data.open_directory("/mnt", "/sub").unwrap().create_symlink("back", "../../mntdata").unwrap();
+        
+        let mounts = [
+            mount_entry("/", root.clone()),
+            mount_entry("/mnt", data.clone()),
+        ];
+        
+        // /mntdataは/mntのマウントに含まれない
+        let resolved = walk(&mounts, "/", "/mntdata", 0).unwrap();
+        assert_eq!((resolved.mount_point.as_str(), resolved.fs_path.as_str()), ("/", "/mntdata"));
+        
+        let resolved = walk(&mounts, "/mnt/sub", "./file", 0).unwrap();
+        assert_eq!((resolved.path.as_str(), resolved.fs_path.as_str()), ("/mnt/sub/file", "/sub/file"));
+        assert_eq!(resolved.file_type, FileType::Regular);
+        
+        // ".."でマウントのルートから下のファイルシステムへ戻る
+        assert_eq!(walk(&mounts, "/mnt/sub", "back", 0).unwrap().path, "/mntdata");
+        assert!(matches!(walk(&mounts, "/", "up/sub/file/", 0), Err(FsError::NotDirectory)));
+        assert_eq!(walk(&mounts, "/", "/up", lookup_flags::DIRECTORY).unwrap().path, "/mnt");
+        
+        let link = walk(&mounts, "/", "/up", lookup_flags::NOFOLLOW).unwrap();
+        assert_eq!(link.file_type, FileType::SymbolicLink);
+        assert!(matches!(walk(&mounts, "/", "/loop", 0), Err(FsError::SymlinkLoop)));
+        
+        let (parent, name) = walk_parent(&mounts, "/mnt", "sub/new").unwrap();
+        assert_eq!((parent.child_fs_path(&name).as_str(), parent.mount_point.as_str()), ("/sub/new", "/mnt"));
+    }
+    
+    #[test]
+    fn one_driver_mounted_twice_keeps_volumes_apart() {
+        let tmpfs = Arc::new(TmpfsFilesystem::new());
+        tmpfs.mount("tmpfs", "/", "").unwrap();
+        tmpfs.mount("tmpfs", "/mnt", "").unwrap();
+        tmpfs.open_directory("/", "/").unwrap().create_directory("mnt", Permissions::default()).unwrap();
+        tmpfs.open_file("/mnt", "/inner", OpenMode::Create).unwrap();
+        
+        let mounts = [
+            mount_entry("/", tmpfs.clone()),
+            mount_entry("/mnt", tmpfs.clone()),
+        ];
+        
+        let resolved = walk(&mounts, "/", "/mnt/inner", 0).unwrap();
+        assert_eq!((resolved.mount_point.as_str(), resolved.fs_path.as_str()), ("/mnt", "/inner"));
+        assert!(resolved.fs.metadata(&resolved.mount_point, &resolved.fs_path).is_ok());
+        // 同じパスでもルートのボリュームには存在しない
+        assert!(matches!(walk(&mounts, "/", "/inner", 0), Err(FsError::NotFound)));
+        assert_eq!(walk(&mounts, "/mnt", "..", 0).unwrap().path, "/");
+    }
+    
+    #[test]
+    fn renamed_directory_keeps_cached_children() {
+        dcache::init(64).unwrap();
+        let tmpfs = Arc::new(TmpfsFilesystem::new());
+        tmpfs.mount("tmpfs", "/", "").unwrap();
+        let mounts = [mount_entry("/", tmpfs.clone())];
+        let root = CachedDirHandle::new(tmpfs.open_directory("/", "/").unwrap(), tmpfs.clone(), "/".to_string(), mounts[0].id, "/".to_string());
+        root.create_directory("a", Permissions::default()).unwrap();
+        tmpfs.open_directory("/", "/a").unwrap().create_directory("b", Permissions::default()).unwrap();
+        tmpfs.open_file("/", "/a/b/file", OpenMode::Create).unwrap();
+        
+        let file = walk(&mounts, "/", "/a/b/file", 0).unwrap();
+        assert!(matches!(walk(&mounts, "/", "/c", 0), Err(FsError::NotFound)));
+        
+        // 移動したディレクトリの配下は親のinodeで引くので、新しい名前から同じinodeに辿り着く
+        root.rename("a", "c").unwrap();
+        let moved = walk(&mounts, "/", "/c/b/file", 0).unwrap();
+        assert_eq!((moved.inode, moved.fs_path.as_str()), (file.inode, "/c/b/file"));
+        assert!(matches!(walk(&mounts, "/", "/a/b/file", 0), Err(FsError::NotFound)));
+        assert_eq!(walk(&mounts, "/c", "b/file", 0).unwrap().inode, file.inode);
+    }
+}
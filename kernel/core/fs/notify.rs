@@ -0,0 +1,605 @@
+// ファイル変更通知（inotify相当）
+//
+// ウォッチャーは(マウント, アイノード)に対してイベントマスク付きのウォッチを登録し、
+// 発生したイベントを上限付きのキューで受け取る。イベントはファイルシステムではなく
+// VFSのハンドル（dcacheのディレクトリハンドルと書き込み可能なファイルハンドル）と
+// アンマウントから発火するため、ext4・FAT・tmpfsなどどのファイルシステムでも同じように届く
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use spin::{Mutex, RwLock};
+use super::{FsResult, FsError, FsId, FileType, InodeNum, Metadata, FileHandle, Filesystem, OpenMode, lookup_flags};
+
+/// イベントマスク（値はLinuxのinotifyと同じ）
+pub mod event_mask {
+    /// ファイルが変更された
+    pub const MODIFY: u32 = 0x0000_0002;
+    /// 属性（拡張属性など）が変更された
+    pub const ATTRIB: u32 = 0x0000_0004;
+    /// 書き込み可能で開いたファイルが閉じられた
+    pub const CLOSE_WRITE: u32 = 0x0000_0008;
+    /// ディレクトリから名前が移動した
+    pub const MOVED_FROM: u32 = 0x0000_0040;
+    /// ディレクトリへ名前が移動してきた
+    pub const MOVED_TO: u32 = 0x0000_0080;
+    /// ディレクトリに名前が作成された
+    pub const CREATE: u32 = 0x0000_0100;
+    /// ディレクトリから名前が削除された
+    pub const DELETE: u32 = 0x0000_0200;
+    /// 監視対象自身が削除された
+    pub const DELETE_SELF: u32 = 0x0000_0400;
+    /// 監視対象自身が移動した
+    pub const MOVE_SELF: u32 = 0x0000_0800;
+    /// 監視対象のファイルシステムがアンマウントされた
+    pub const UNMOUNT: u32 = 0x0000_2000;
+    /// キューが溢れてイベントを捨てた
+    pub const Q_OVERFLOW: u32 = 0x0000_4000;
+    /// ウォッチが外れた（明示的な削除、対象の削除、アンマウント）
+    pub const IGNORED: u32 = 0x0000_8000;
+    /// 対象がディレクトリでなければ登録しない
+    pub const ONLYDIR: u32 = 0x0100_0000;
+    /// 最後の要素がシンボリックリンクなら辿らずにリンク自身を監視する
+    pub const DONT_FOLLOW: u32 = 0x0200_0000;
+    /// 既存のウォッチのマスクを置き換えずに追加する
+    pub const MASK_ADD: u32 = 0x2000_0000;
+    /// イベントの対象がディレクトリ
+    pub const ISDIR: u32 = 0x4000_0000;
+    
+    /// 名前の移動（MOVED_FROM | MOVED_TO）
+    pub const MOVE: u32 = MOVED_FROM | MOVED_TO;
+    /// 登録できるすべてのイベント
+    pub const ALL_EVENTS: u32 = MODIFY | ATTRIB | CLOSE_WRITE | MOVE | CREATE | DELETE | DELETE_SELF | MOVE_SELF;
+    /// マスクに関係なく届くイベント
+    pub const ALWAYS: u32 = UNMOUNT | Q_OVERFLOW | IGNORED;
+}
+
+/// ウォッチャーごとのキューの既定の上限
+pub const DEFAULT_QUEUE_CAPACITY: usize = 16384;
+
+/// ウォッチ番号（ウォッチャーごとに1から振る。溢れイベントは0）
+pub type WatchId = u32;
+
+/// 通知イベント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    /// 発火したウォッチ
+    pub watch: WatchId,
+    /// イベントマスク
+    pub mask: u32,
+    /// MOVED_FROMとMOVED_TOの組を結び付ける値（それ以外は0）
+    pub cookie: u32,
+    /// 監視ディレクトリ内の名前（監視対象自身のイベントではNone）
+    pub name: Option<String>,
+}
+
+/// グローバル表に登録されたウォッチ
+struct Watch {
+    /// 登録したウォッチャーの通し番号
+    owner: u64,
+    /// 登録したウォッチャー
+    watcher: Weak<Watcher>,
+    /// ウォッチ番号
+    id: WatchId,
+    /// 受け取るイベント
+    mask: u32,
+}
+
+/// 監視対象の(マウント, アイノード)
+type WatchKey = (FsId, InodeNum);
+
+/// 監視対象 => ウォッチ
+static WATCHES: RwLock<BTreeMap<WatchKey, Vec<Watch>>> = RwLock::new(BTreeMap::new());
+
+/// 登録中のウォッチ数（0なら発火側は何もしない）
+static WATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// ウォッチャーの通し番号
+static NEXT_WATCHER: AtomicU64 = AtomicU64::new(1);
+
+/// 移動イベントのクッキー
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// イベントキュー
+struct EventQueue {
+    /// 未読イベント
+    events: VecDeque<WatchEvent>,
+    /// 溢れて捨てたイベント数
+    dropped: u64,
+}
+
+/// 通知を受け取るウォッチャー（inotifyインスタンス相当）
+pub struct Watcher {
+    /// 通し番号
+    serial: u64,
+    /// キューの上限
+    capacity: usize,
+    /// イベントキュー
+    queue: Mutex<EventQueue>,
+    /// このウォッチャーのウォッチ（番号 => 監視対象）
+    watches: Mutex<BTreeMap<WatchId, WatchKey>>,
+    /// 次のウォッチ番号
+    next_watch: AtomicU32,
+}
+
+impl Watcher {
+    /// キューの上限を指定してウォッチャーを作成
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            serial: NEXT_WATCHER.fetch_add(1, Ordering::Relaxed),
+            capacity: capacity.max(1),
+            queue: Mutex::new(EventQueue { events: VecDeque::new(), dropped: 0 }),
+            watches: Mutex::new(BTreeMap::new()),
+            next_watch: AtomicU32::new(1),
+        })
+    }
+    
+    /// パスにウォッチを登録（同じ対象への再登録は同じ番号のマスクを更新する）
+    pub fn add_watch(self: &Arc<Self>, path: &str, mask: u32) -> FsResult<WatchId> {
+        if mask & event_mask::ALL_EVENTS == 0 {
+            return Err(FsError::InvalidData);
+        }
+        
+        let mut flags = 0;
+        if mask & event_mask::DONT_FOLLOW != 0 {
+            flags |= lookup_flags::NOFOLLOW;
+        }
+        if mask & event_mask::ONLYDIR != 0 {
+            flags |= lookup_flags::DIRECTORY;
+        }
+        let resolved = super::resolve_path(path, flags)?;
+        if mask & event_mask::ONLYDIR != 0 && resolved.file_type != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        let inode = resolved.fs.metadata(&resolved.mount_point, &resolved.fs_path)?.inode;
+        Ok(self.watch_inode(resolved.fs_id, inode, mask))
+    }
+    
+    /// (マウント, アイノード)にウォッチを登録
+    pub(super) fn watch_inode(self: &Arc<Self>, fs: FsId, inode: InodeNum, mask: u32) -> WatchId {
+        let key = (fs, inode);
+        let events = mask & event_mask::ALL_EVENTS;
+        let mut watches = self.watches.lock();
+        let mut table = WATCHES.write();
+        
+        if let Some((&id, _)) = watches.iter().find(|(_, target)| **target == key) {
+            if let Some(watch) = table.get_mut(&key).and_then(|list| list.iter_mut().find(|w| w.owner == self.serial)) {
+                watch.mask = if mask & event_mask::MASK_ADD != 0 { watch.mask | events } else { events };
+            }
+            return id;
+        }
+        
+        let id = self.next_watch.fetch_add(1, Ordering::Relaxed);
+        watches.insert(id, key);
+        table.entry(key).or_default().push(Watch {
+            owner: self.serial,
+            watcher: Arc::downgrade(self),
+            id,
+            mask: events,
+        });
+        WATCH_COUNT.fetch_add(1, Ordering::AcqRel);
+        id
+    }
+    
+    /// ウォッチを外す（IGNOREDイベントが届く）
+    pub fn remove_watch(&self, id: WatchId) -> FsResult<()> {
+        let key = self.watches.lock().remove(&id).ok_or(FsError::NotFound)?;
+        
+        let mut table = WATCHES.write();
+        if let Some(list) = table.get_mut(&key) {
+            let before = list.len();
+            list.retain(|watch| watch.owner != self.serial);
+            WATCH_COUNT.fetch_sub(before - list.len(), Ordering::AcqRel);
+            if list.is_empty() {
+                table.remove(&key);
+            }
+        }
+        drop(table);
+        
+        self.push(WatchEvent { watch: id, mask: event_mask::IGNORED, cookie: 0, name: None });
+        Ok(())
+    }
+    
+    /// 未読イベントを最大`max`個取り出す
+    pub fn read_events(&self, max: usize) -> Vec<WatchEvent> {
+        let mut queue = self.queue.lock();
+        let count = max.min(queue.events.len());
+        queue.events.drain(..count).collect()
+    }
+    
+    /// 未読イベント数
+    pub fn pending(&self) -> usize {
+        self.queue.lock().events.len()
+    }
+    
+    /// 溢れて捨てたイベントの累計
+    pub fn dropped_events(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+    
+    /// 登録中のウォッチ数
+    pub fn watch_count(&self) -> usize {
+        self.watches.lock().len()
+    }
+    
+    /// イベントをキューへ入れる（直前と同じイベントはまとめ、上限を超えたら溢れを1つだけ記録する）
+    fn push(&self, event: WatchEvent) {
+        let mut queue = self.queue.lock();
+        if queue.events.back() == Some(&event) {
+            return;
+        }
+        
+        if queue.events.len() >= self.capacity {
+            queue.dropped += 1;
+            if queue.events.back().is_none_or(|last| last.mask != event_mask::Q_OVERFLOW) {
+                queue.events.push_back(WatchEvent { watch: 0, mask: event_mask::Q_OVERFLOW, cookie: 0, name: None });
+            }
+            return;
+        }
+        queue.events.push_back(event);
+    }
+    
+    /// 監視対象が消えたウォッチを自分の表から外す
+    fn forget(&self, id: WatchId) {
+        self.watches.lock().remove(&id);
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let watches = core::mem::take(&mut *self.watches.lock());
+        if watches.is_empty() {
+            return;
+        }
+        
+        let mut table = WATCHES.write();
+        for key in watches.values() {
+            if let Some(list) = table.get_mut(key) {
+                let before = list.len();
+                list.retain(|watch| watch.owner != self.serial);
+                WATCH_COUNT.fetch_sub(before - list.len(), Ordering::AcqRel);
+                if list.is_empty() {
+                    table.remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// ウォッチが1つでも登録されているか（発火側の高速経路）
+pub(super) fn is_active() -> bool {
+    WATCH_COUNT.load(Ordering::Acquire) != 0
+}
+
+/// MOVED_FROMとMOVED_TOの組に使う新しいクッキー
+pub(super) fn next_cookie() -> u32 {
+    NEXT_COOKIE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 監視対象へイベントを配る
+fn deliver(key: WatchKey, mask: u32, cookie: u32, name: Option<&str>) {
+    let targets: Vec<(Arc<Watcher>, WatchId)> = match WATCHES.read().get(&key) {
+        Some(list) => list.iter()
+            .filter(|watch| watch.mask & mask & event_mask::ALL_EVENTS != 0 || mask & event_mask::ALWAYS != 0)
+            .filter_map(|watch| Some((watch.watcher.upgrade()?, watch.id)))
+            .collect(),
+        None => return,
+    };
+    
+    for (watcher, id) in targets {
+        watcher.push(WatchEvent { watch: id, mask, cookie, name: name.map(str::to_string) });
+    }
+}
+
+/// 監視対象のウォッチを外し、それぞれにIGNOREDを届ける
+fn release(keys: impl Fn(&WatchKey) -> bool) {
+    let mut released = Vec::new();
+    {
+        let mut table = WATCHES.write();
+        let matched: Vec<WatchKey> = table.keys().filter(|key| keys(key)).copied().collect();
+        for key in matched {
+            if let Some(list) = table.remove(&key) {
+                WATCH_COUNT.fetch_sub(list.len(), Ordering::AcqRel);
+                released.extend(list);
+            }
+        }
+    }
+    
+    for watch in released {
+        if let Some(watcher) = watch.watcher.upgrade() {
+            watcher.forget(watch.id);
+            watcher.push(WatchEvent { watch: watch.id, mask: event_mask::IGNORED, cookie: 0, name: None });
+        }
+    }
+}
+
+/// ディレクトリ`dir`内の名前`name`に起きたイベント
+pub(super) fn child_event(fs: FsId, dir: InodeNum, mask: u32, name: &str, is_dir: bool, cookie: u32) {
+    if !is_active() {
+        return;
+    }
+    let mask = if is_dir { mask | event_mask::ISDIR } else { mask };
+    deliver((fs, dir), mask, cookie, Some(name));
+}
+
+/// アイノード自身に起きたイベント（DELETE_SELFならウォッチも外す）
+pub(super) fn inode_event(fs: FsId, inode: InodeNum, mask: u32) {
+    if !is_active() {
+        return;
+    }
+    deliver((fs, inode), mask, 0, None);
+    if mask & event_mask::DELETE_SELF != 0 {
+        release(|key| *key == (fs, inode));
+    }
+}
+
+/// マウントのすべてのウォッチへUNMOUNTを届けて外す
+pub(super) fn unmount(fs: FsId) {
+    if !is_active() {
+        return;
+    }
+    let keys: Vec<WatchKey> = WATCHES.read().keys().filter(|(id, _)| *id == fs).copied().collect();
+    for key in keys {
+        deliver(key, event_mask::UNMOUNT, 0, None);
+    }
+    release(|(id, _)| *id == fs);
+}
+
+/// ファイルシステム内のパスを親ディレクトリと名前に分ける
+fn split_parent(fs_path: &str) -> (&str, &str) {
+    let fs_path = fs_path.trim_end_matches('/');
+    match fs_path.rfind('/') {
+        Some(0) => ("/", &fs_path[1..]),
+        Some(index) => (&fs_path[..index], &fs_path[index + 1..]),
+        None => ("/", fs_path),
+    }
+}
+
+/// 属性の変更を対象自身と、親ディレクトリ内の名前として通知
+pub(super) fn attrib_changed(filesystem: &Arc<dyn Filesystem>, mount_point: &str, fs: FsId, fs_path: &str, is_dir: bool) {
+    if !is_active() {
+        return;
+    }
+    if let Ok(metadata) = filesystem.metadata(mount_point, fs_path) {
+        inode_event(fs, metadata.inode, event_mask::ATTRIB);
+    }
+    let (parent, name) = split_parent(fs_path);
+    if name.is_empty() {
+        return;
+    }
+    if let Ok(parent) = filesystem.metadata(mount_point, parent) {
+        child_event(fs, parent.inode, event_mask::ATTRIB, name, is_dir, 0);
+    }
+}
+
+/// VFSで書き込み可能に開いた通常ファイルを、変更とクローズを通知するハンドルで包む
+pub(super) fn watch_writes(
+    handle: Arc<dyn FileHandle>,
+    mode: OpenMode,
+    filesystem: &Arc<dyn Filesystem>,
+    mount_point: &str,
+    fs: FsId,
+    fs_path: &str,
+) -> FsResult<Arc<dyn FileHandle>> {
+    if mode == OpenMode::ReadOnly {
+        return Ok(handle);
+    }
+    
+    let (parent, name) = split_parent(fs_path);
+    Ok(Arc::new(NotifyFileHandle {
+        inode: handle.metadata()?.inode,
+        inner: handle,
+        filesystem: filesystem.clone(),
+        mount_point: mount_point.to_string(),
+        fs,
+        parent: parent.to_string(),
+        name: name.to_string(),
+    }))
+}
+
+/// 書き込みとクローズを通知するファイルハンドル
+struct NotifyFileHandle {
+    /// 包んだハンドル
+    inner: Arc<dyn FileHandle>,
+    /// ファイルシステム（親ディレクトリのアイノードを引く）
+    filesystem: Arc<dyn Filesystem>,
+    /// ファイルシステムのマウントポイント
+    mount_point: String,
+    /// マウント
+    fs: FsId,
+    /// ファイルのアイノード番号
+    inode: InodeNum,
+    /// 開いたときの親ディレクトリのファイルシステム内パス
+    parent: String,
+    /// 開いたときの名前
+    name: String,
+}
+
+impl NotifyFileHandle {
+    /// ファイル自身と親ディレクトリへイベントを届ける
+    fn notify(&self, mask: u32) {
+        if !is_active() {
+            return;
+        }
+        inode_event(self.fs, self.inode, mask);
+        if let Ok(parent) = self.filesystem.metadata(&self.mount_point, &self.parent) {
+            child_event(self.fs, parent.inode, mask, &self.name, false, 0);
+        }
+    }
+}
+
+impl Drop for NotifyFileHandle {
+    fn drop(&mut self) {
+        self.notify(event_mask::CLOSE_WRITE);
+    }
+}
+
+impl FileHandle for NotifyFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.inner.read(buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        let written = self.inner.write(buffer, offset)?;
+        if written > 0 {
+            self.notify(event_mask::MODIFY);
+        }
+        Ok(written)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.inner.flush()
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        self.inner.size()
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        self.inner.resize(new_size)?;
+        self.notify(event_mask::MODIFY);
+        Ok(())
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.inner.metadata()
+    }
+    
+    fn lock(&self, exclusive: bool) -> FsResult<()> {
+        self.inner.lock(exclusive)
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        self.inner.unlock()
+    }
+    
+    fn try_lock(&self, exclusive: bool) -> FsResult<()> {
+        self.inner.try_lock(exclusive)
+    }
+    
+    fn can_read(&self) -> bool {
+        self.inner.can_read()
+    }
+    
+    fn can_write(&self) -> bool {
+        self.inner.can_write()
+    }
+    
+    fn fsync(&self) -> FsResult<()> {
+        self.inner.fsync()
+    }
+    
+    fn fdatasync(&self) -> FsResult<()> {
+        self.inner.fdatasync()
+    }
+    
+    fn mapping_id(&self) -> Option<usize> {
+        self.inner.mapping_id()
+    }
+    
+    fn ioctl(&self, request: u32, arg: usize) -> FsResult<usize> {
+        self.inner.ioctl(request, arg)
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.inner.get_xattr(name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.inner.set_xattr(name, value)?;
+        self.notify(event_mask::ATTRIB);
+        Ok(())
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.inner.list_xattr()
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.inner.remove_xattr(name)?;
+        self.notify(event_mask::ATTRIB);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{DirHandle, Permissions};
+    use super::super::dcache::CachedDirHandle;
+    use super::super::tmpfs::TmpfsFilesystem;
+    use event_mask::*;
+    
+    #[test]
+    fn directory_events_cookies_overflow_and_unmount() {
+        let tmpfs_driver = Arc::new(TmpfsFilesystem::new());
+        let tmpfs: Arc<dyn Filesystem> = tmpfs_driver.clone();
+        tmpfs.mount("tmpfs", "/", "").unwrap();
+        tmpfs.open_directory("/", "/").unwrap().create_directory("dir", Permissions::default()).unwrap();
+        let fs = FsId::new();
+        let root_inode = tmpfs.metadata("/", "/").unwrap().inode;
+        let dir_inode = tmpfs.metadata("/", "/dir").unwrap().inode;
+        let root = CachedDirHandle::new(tmpfs.open_directory("/", "/").unwrap(), tmpfs.clone(), "/".to_string(), fs, "/".to_string());
+        
+        let watcher = Watcher::new(4);
+        let root_watch = watcher.watch_inode(fs, root_inode, CREATE | DELETE | MOVE | MODIFY | CLOSE_WRITE);
+        let dir_watch = watcher.watch_inode(fs, dir_inode, MOVE | MOVE_SELF);
+        
+        // 作成と書き込み（連続する同じイベントは1つにまとまる）
+        let file = root.create_file("a", Permissions::default()).unwrap();
+        let file = watch_writes(file, OpenMode::ReadWrite, &tmpfs, "/", fs, "/a").unwrap();
+        file.write(b"x", 0).unwrap();
+        file.write(b"y", 1).unwrap();
+        drop(file);
+        let events = watcher.read_events(16);
+        let masks: Vec<u32> = events.iter().map(|e| e.mask).collect();
+        assert_eq!(masks, [CREATE, MODIFY, CLOSE_WRITE]);
+        assert!(events.iter().all(|e| e.watch == root_watch && e.name.as_deref() == Some("a")));
+        
+        // ディレクトリ間の移動はクッキーで結ばれる
+        root.rename("a", "/dir/b").unwrap();
+        let events = watcher.read_events(16);
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].watch, events[0].mask, events[0].name.as_deref()), (root_watch, MOVED_FROM, Some("a")));
+        assert_eq!((events[1].watch, events[1].mask, events[1].name.as_deref()), (dir_watch, MOVED_TO, Some("b")));
+        assert_eq!(events[0].cookie, events[1].cookie);
+        assert_ne!(events[0].cookie, 0);
+        
+        // 上限を超えると溢れイベントが1つだけ最後に残る
+        for name in ["c", "d", "e", "f", "g", "h"] {
+            root.create_file(name, Permissions::default()).unwrap();
+        }
+        assert_eq!(watcher.pending(), 5);
+        assert_eq!(watcher.dropped_events(), 2);
+        assert_eq!(watcher.read_events(16).last().unwrap().mask, Q_OVERFLOW);
+        
+        // ディレクトリの削除はISDIR付きのDELETEとなり、そのウォッチは外れる
+        let dir = CachedDirHandle::new(tmpfs.open_directory("/", "/dir").unwrap(), tmpfs.clone(), "/".to_string(), fs, "/dir".to_string());
+        dir.remove("b").unwrap();
+        root.remove("dir").unwrap();
+        let events = watcher.read_events(16);
+        assert_eq!(events[0].mask, DELETE | ISDIR);
+        assert_eq!(events.last().unwrap(), &WatchEvent { watch: dir_watch, mask: IGNORED, cookie: 0, name: None });
+        assert_eq!(watcher.watch_count(), 1);
+        
+        // 他のハードリンクが残る削除は親へのDELETEだけで、最後のリンクでDELETE_SELFとなる
+        root.create_file("linked", Permissions::default()).unwrap();
+        let linked_inode = tmpfs.metadata("/", "/linked").unwrap().inode;
+        tmpfs_driver.link("/", "/linked", "/other").unwrap();
+        let linked_watch = watcher.watch_inode(fs, linked_inode, DELETE_SELF);
+        watcher.read_events(16);
+        root.remove("linked").unwrap();
+        let events = watcher.read_events(16);
+        assert_eq!(events, [WatchEvent { watch: root_watch, mask: DELETE, cookie: 0, name: Some("linked".to_string()) }]);
+        root.remove("other").unwrap();
+        let masks: Vec<(WatchId, u32)> = watcher.read_events(16).iter().map(|e| (e.watch, e.mask)).collect();
+        assert_eq!(masks, [(root_watch, DELETE), (linked_watch, DELETE_SELF), (linked_watch, IGNORED)]);
+        
+        unmount(fs);
+        let masks: Vec<u32> = watcher.read_events(16).iter().map(|e| e.mask).collect();
+        assert_eq!(masks, [UNMOUNT, IGNORED]);
+        assert!(!is_active());
+    }
+}
@@ -0,0 +1,665 @@
+// NTFS 属性
+//
+// MFTレコード内の属性（常駐/非常駐）とデータランの解析、$ATTRIBUTE_LIST・
+// $STANDARD_INFORMATION・$FILE_NAMEの解析、属性ストリームの読み込み
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::compression::decompress_lznt1;
+use super::{NtfsVolume, read_bytes, le_u16, le_u32, le_u64};
+
+/// 属性タイプ
+pub mod attr_type {
+    /// $STANDARD_INFORMATION
+    pub const STANDARD_INFORMATION: u32 = 0x10;
+    /// $ATTRIBUTE_LIST
+    pub const ATTRIBUTE_LIST: u32 = 0x20;
+    /// $FILE_NAME
+    pub const FILE_NAME: u32 = 0x30;
+    /// $VOLUME_NAME
+    pub const VOLUME_NAME: u32 = 0x60;
+    /// $VOLUME_INFORMATION
+    pub const VOLUME_INFORMATION: u32 = 0x70;
+    /// $DATA
+    pub const DATA: u32 = 0x80;
+    /// $INDEX_ROOT
+    pub const INDEX_ROOT: u32 = 0x90;
+    /// $INDEX_ALLOCATION
+    pub const INDEX_ALLOCATION: u32 = 0xA0;
+    /// $BITMAP
+    pub const BITMAP: u32 = 0xB0;
+    /// 属性列の終端
+    pub const END: u32 = 0xFFFF_FFFF;
+}
+
+/// 属性ヘッダのフラグ
+pub mod attr_flags {
+    /// 圧縮属性
+    pub const COMPRESSED: u16 = 0x0001;
+    /// 暗号化属性
+    pub const ENCRYPTED: u16 = 0x4000;
+    /// スパース属性
+    pub const SPARSE: u16 = 0x8000;
+}
+
+/// ファイル属性（$STANDARD_INFORMATION/$FILE_NAME）
+pub mod file_attr {
+    /// 読み取り専用
+    pub const READ_ONLY: u32 = 0x0000_0001;
+    /// 隠しファイル
+    pub const HIDDEN: u32 = 0x0000_0002;
+    /// システムファイル
+    pub const SYSTEM: u32 = 0x0000_0004;
+    /// アーカイブ
+    pub const ARCHIVE: u32 = 0x0000_0020;
+    /// 圧縮
+    pub const COMPRESSED: u32 = 0x0000_0800;
+    /// ディレクトリ（$FILE_NAMEに複製されるフラグ）
+    pub const DUP_DIRECTORY: u32 = 0x1000_0000;
+}
+
+/// ファイル名の名前空間
+pub mod namespace {
+    /// POSIX（大文字小文字を区別）
+    pub const POSIX: u8 = 0;
+    /// Win32
+    pub const WIN32: u8 = 1;
+    /// DOS 8.3形式
+    pub const DOS: u8 = 2;
+    /// Win32名がそのまま8.3形式でもある
+    pub const WIN32_AND_DOS: u8 = 3;
+}
+
+/// 常駐属性ヘッダの長さ
+const RESIDENT_HEADER_SIZE: usize = 0x18;
+/// 非常駐属性ヘッダの長さ（圧縮サイズを除く）
+const NON_RESIDENT_HEADER_SIZE: usize = 0x40;
+/// $FILE_NAMEの固定部分の長さ
+const FILE_NAME_HEADER_SIZE: usize = 0x42;
+/// $ATTRIBUTE_LISTエントリの固定部分の長さ
+const ATTRIBUTE_LIST_ENTRY_SIZE: usize = 0x1A;
+
+/// 1601年1月1日からUNIXエポックまでの秒数
+const EPOCH_DIFFERENCE: u64 = 11_644_473_600;
+
+/// NTFS時刻（1601年からの100ナノ秒単位）をUNIX秒へ変換
+pub fn ntfs_time_to_unix(time: u64) -> u64 {
+    (time / 10_000_000).saturating_sub(EPOCH_DIFFERENCE)
+}
+
+/// UTF-16LEのバイト列を符号単位の列に変換
+fn utf16_units(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect()
+}
+
+/// データラン（連続したVCN範囲の物理配置）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataRun {
+    /// 先頭の論理クラスタ番号（スパースの場合はNone）
+    pub lcn: Option<u64>,
+    /// クラスタ数
+    pub length: u64,
+}
+
+/// マッピングペア（データランの圧縮表現）を解析
+pub fn decode_runs(data: &[u8]) -> FsResult<Vec<DataRun>> {
+    let mut runs = Vec::new();
+    let mut pos = 0;
+    let mut lcn: i64 = 0;
+    
+    while pos < data.len() && data[pos] != 0 {
+        let header = data[pos];
+        let length_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        pos += 1;
+        
+        if length_size == 0 || length_size > 8 || offset_size > 8 || pos + length_size + offset_size > data.len() {
+            log::warn!("NTFS: 不正なデータランヘッダ 0x{:02x}", header);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut length = 0u64;
+        for (i, &byte) in data[pos..pos + length_size].iter().enumerate() {
+            length |= (byte as u64) << (i * 8);
+        }
+        pos += length_size;
+        if length == 0 || length > i64::MAX as u64 {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        // オフセットが無いランはスパース（ディスク上に実体が無い）
+        if offset_size == 0 {
+            runs.push(DataRun { lcn: None, length });
+            continue;
+        }
+        
+        // 直前のランからの符号付き相対値
+        let mut delta = 0i64;
+        for (i, &byte) in data[pos..pos + offset_size].iter().enumerate() {
+            delta |= (byte as i64) << (i * 8);
+        }
+        if offset_size < 8 && data[pos + offset_size - 1] & 0x80 != 0 {
+            delta |= -1i64 << (offset_size * 8);
+        }
+        pos += offset_size;
+        
+        lcn = lcn.checked_add(delta).filter(|&lcn| lcn >= 0).ok_or_else(|| {
+            log::warn!("NTFS: データランが負のクラスタを指しています");
+            FsError::CorruptedFs
+        })?;
+        runs.push(DataRun { lcn: Some(lcn as u64), length });
+    }
+    
+    Ok(runs)
+}
+
+/// 非常駐属性の情報
+#[derive(Debug, Clone)]
+pub struct NonResident {
+    /// この断片の先頭VCN
+    pub lowest_vcn: u64,
+    /// この断片の最終VCN
+    pub highest_vcn: u64,
+    /// データラン
+    pub runs: Vec<DataRun>,
+    /// 圧縮単位（2の累乗のクラスタ数、0なら非圧縮）
+    pub compression_unit: u8,
+    /// 割り当て済みサイズ（先頭の断片のみ有効）
+    pub allocated_size: u64,
+    /// データサイズ（先頭の断片のみ有効）
+    pub data_size: u64,
+    /// 初期化済みサイズ（先頭の断片のみ有効）
+    pub initialized_size: u64,
+}
+
+/// 属性の値
+#[derive(Debug, Clone)]
+pub enum AttributeValue {
+    /// MFTレコード内に格納された値
+    Resident(Vec<u8>),
+    /// クラスタに格納された値
+    NonResident(NonResident),
+}
+
+/// MFTレコード内の属性
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    /// 属性タイプ
+    pub type_code: u32,
+    /// 属性名（無名なら空）
+    pub name: String,
+    /// 属性フラグ
+    pub flags: u16,
+    /// レコード内の属性ID
+    pub instance: u16,
+    /// 値
+    pub value: AttributeValue,
+}
+
+impl Attribute {
+    /// `data`の`offset`にある属性を解析し、属性とその長さを返す（終端ならNone）
+    pub fn parse(data: &[u8], offset: usize) -> FsResult<Option<(Self, usize)>> {
+        if offset + 4 > data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        let type_code = le_u32(data, offset);
+        if type_code == attr_type::END {
+            return Ok(None);
+        }
+        
+        if offset + RESIDENT_HEADER_SIZE > data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        let length = le_u32(data, offset + 4) as usize;
+        if length < RESIDENT_HEADER_SIZE || length % 8 != 0 || offset + length > data.len() {
+            log::warn!("NTFS: 属性 0x{:x} の長さ {} が不正です", type_code, length);
+            return Err(FsError::CorruptedFs);
+        }
+        let attribute = &data[offset..offset + length];
+        
+        let non_resident = attribute[8] != 0;
+        let name_length = attribute[9] as usize;
+        let name_offset = le_u16(attribute, 0x0A) as usize;
+        let flags = le_u16(attribute, 0x0C);
+        let instance = le_u16(attribute, 0x0E);
+        
+        if name_offset + name_length * 2 > length {
+            return Err(FsError::CorruptedFs);
+        }
+        let name = String::from_utf16_lossy(&utf16_units(&attribute[name_offset..name_offset + name_length * 2]));
+        
+        let value = if non_resident {
+            if length < NON_RESIDENT_HEADER_SIZE {
+                return Err(FsError::CorruptedFs);
+            }
+            let runs_offset = le_u16(attribute, 0x20) as usize;
+            if runs_offset > length {
+                return Err(FsError::CorruptedFs);
+            }
+            AttributeValue::NonResident(NonResident {
+                lowest_vcn: le_u64(attribute, 0x10),
+                highest_vcn: le_u64(attribute, 0x18),
+                runs: decode_runs(&attribute[runs_offset..])?,
+                compression_unit: attribute[0x22],
+                allocated_size: le_u64(attribute, 0x28),
+                data_size: le_u64(attribute, 0x30),
+                initialized_size: le_u64(attribute, 0x38),
+            })
+        } else {
+            let value_length = le_u32(attribute, 0x10) as usize;
+            let value_offset = le_u16(attribute, 0x14) as usize;
+            if value_offset + value_length > length {
+                return Err(FsError::CorruptedFs);
+            }
+            AttributeValue::Resident(attribute[value_offset..value_offset + value_length].to_vec())
+        };
+        
+        Ok(Some((Self { type_code, name, flags, instance, value }, length)))
+    }
+    
+    /// 常駐属性の値
+    pub fn resident_value(&self) -> Option<&[u8]> {
+        match &self.value {
+            AttributeValue::Resident(value) => Some(value),
+            AttributeValue::NonResident(_) => None,
+        }
+    }
+    
+    /// 非常駐属性の断片
+    pub fn non_resident(&self) -> Option<&NonResident> {
+        match &self.value {
+            AttributeValue::Resident(_) => None,
+            AttributeValue::NonResident(non_resident) => Some(non_resident),
+        }
+    }
+}
+
+/// $STANDARD_INFORMATIONの内容
+#[derive(Debug, Clone, Copy)]
+pub struct StandardInformation {
+    /// 作成時刻
+    pub created: u64,
+    /// 変更時刻
+    pub modified: u64,
+    /// MFTレコード変更時刻
+    pub mft_modified: u64,
+    /// アクセス時刻
+    pub accessed: u64,
+    /// ファイル属性
+    pub attributes: u32,
+}
+
+impl StandardInformation {
+    /// 属性値から解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        // NTFS 1.2の48バイト形式と3.xの72バイト形式のどちらも先頭は共通
+        if data.len() < 0x30 {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(Self {
+            created: le_u64(data, 0x00),
+            modified: le_u64(data, 0x08),
+            mft_modified: le_u64(data, 0x10),
+            accessed: le_u64(data, 0x18),
+            attributes: le_u32(data, 0x20),
+        })
+    }
+}
+
+/// $FILE_NAMEの内容（ディレクトリインデックスのキーとしても使われる）
+#[derive(Debug, Clone)]
+pub struct FileName {
+    /// 親ディレクトリのファイル参照
+    pub parent: u64,
+    /// 作成時刻
+    pub created: u64,
+    /// 変更時刻
+    pub modified: u64,
+    /// アクセス時刻
+    pub accessed: u64,
+    /// データサイズ（ディレクトリエントリ更新時点の値）
+    pub size: u64,
+    /// ファイル属性
+    pub attributes: u32,
+    /// 名前空間
+    pub namespace: u8,
+    /// 名前（UTF-16符号単位）
+    pub units: Vec<u16>,
+    /// 名前
+    pub name: String,
+}
+
+impl FileName {
+    /// 属性値（またはインデックスキー）から解析
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < FILE_NAME_HEADER_SIZE {
+            return Err(FsError::CorruptedFs);
+        }
+        let name_length = data[0x40] as usize;
+        let end = FILE_NAME_HEADER_SIZE + name_length * 2;
+        if name_length == 0 || end > data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let units = utf16_units(&data[FILE_NAME_HEADER_SIZE..end]);
+        Ok(Self {
+            parent: le_u64(data, 0x00),
+            created: le_u64(data, 0x08),
+            modified: le_u64(data, 0x10),
+            accessed: le_u64(data, 0x20),
+            size: le_u64(data, 0x30),
+            attributes: le_u32(data, 0x38),
+            namespace: data[0x41],
+            name: String::from_utf16_lossy(&units),
+            units,
+        })
+    }
+    
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        self.attributes & file_attr::DUP_DIRECTORY != 0
+    }
+}
+
+/// $ATTRIBUTE_LISTのエントリ
+#[derive(Debug, Clone)]
+pub struct AttributeListEntry {
+    /// 属性タイプ
+    pub type_code: u32,
+    /// 属性の先頭VCN
+    pub lowest_vcn: u64,
+    /// 属性を格納しているMFTレコードの参照
+    pub reference: u64,
+    /// そのレコード内の属性ID
+    pub instance: u16,
+    /// 属性名
+    pub name: String,
+}
+
+/// $ATTRIBUTE_LISTの値を解析
+pub fn parse_attribute_list(data: &[u8]) -> FsResult<Vec<AttributeListEntry>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    
+    while pos + ATTRIBUTE_LIST_ENTRY_SIZE <= data.len() {
+        let length = le_u16(data, pos + 4) as usize;
+        let name_length = data[pos + 6] as usize;
+        let name_offset = data[pos + 7] as usize;
+        if length < ATTRIBUTE_LIST_ENTRY_SIZE || pos + length > data.len() || name_offset + name_length * 2 > length {
+            log::warn!("NTFS: 属性リストのエントリが不正です (オフセット {})", pos);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let name = &data[pos + name_offset..pos + name_offset + name_length * 2];
+        entries.push(AttributeListEntry {
+            type_code: le_u32(data, pos),
+            lowest_vcn: le_u64(data, pos + 8),
+            reference: le_u64(data, pos + 0x10),
+            instance: le_u16(data, pos + 0x18),
+            name: String::from_utf16_lossy(&utf16_units(name)),
+        });
+        pos += length;
+    }
+    
+    Ok(entries)
+}
+
+/// ストリームの実体
+#[derive(Debug, Clone)]
+pub enum StreamData {
+    /// 常駐値
+    Resident(Vec<u8>),
+    /// 全断片を連結したデータランと圧縮単位
+    NonResident {
+        /// データラン（VCN 0から順）
+        runs: Vec<DataRun>,
+        /// 圧縮単位（2の累乗のクラスタ数）
+        compression_unit: u8,
+    },
+}
+
+/// 読み込み用に整理した属性ストリーム
+#[derive(Debug, Clone)]
+pub struct Stream {
+    /// 実体
+    pub data: StreamData,
+    /// データサイズ
+    pub size: u64,
+    /// 初期化済みサイズ（これ以降は0として読む）
+    pub initialized_size: u64,
+    /// 属性フラグ
+    pub flags: u16,
+}
+
+impl Stream {
+    /// 同じ属性の断片（複数レコードに分かれた非常駐属性を含む）からストリームを構築
+    pub fn from_parts(parts: &[&Attribute]) -> FsResult<Self> {
+        let first = parts.first().ok_or(FsError::NotFound)?;
+        if let Some(value) = first.resident_value() {
+            if parts.len() != 1 {
+                return Err(FsError::CorruptedFs);
+            }
+            return Ok(Self {
+                size: value.len() as u64,
+                initialized_size: value.len() as u64,
+                flags: first.flags,
+                data: StreamData::Resident(value.to_vec()),
+            });
+        }
+        
+        let mut extents: Vec<&NonResident> = parts.iter()
+            .map(|part| part.non_resident().ok_or(FsError::CorruptedFs))
+            .collect::<FsResult<_>>()?;
+        extents.sort_by_key(|extent| extent.lowest_vcn);
+        
+        // サイズ情報はVCN 0から始まる断片にのみ記録される
+        let head = extents[0];
+        if head.lowest_vcn != 0 || head.initialized_size > head.data_size {
+            log::warn!("NTFS: 非常駐属性の先頭断片が不正です");
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut runs = Vec::new();
+        let mut next_vcn = 0u64;
+        for extent in &extents {
+            if extent.lowest_vcn != next_vcn {
+                log::warn!("NTFS: 属性の断片が連続していません (VCN {} != {})", extent.lowest_vcn, next_vcn);
+                return Err(FsError::CorruptedFs);
+            }
+            for run in &extent.runs {
+                next_vcn = next_vcn.checked_add(run.length).ok_or(FsError::CorruptedFs)?;
+                runs.push(*run);
+            }
+        }
+        
+        Ok(Self {
+            data: StreamData::NonResident { runs, compression_unit: head.compression_unit },
+            size: head.data_size,
+            initialized_size: head.initialized_size,
+            flags: first.flags,
+        })
+    }
+    
+    /// 圧縮されているか
+    pub fn is_compressed(&self) -> bool {
+        self.flags & attr_flags::COMPRESSED != 0
+            && matches!(self.data, StreamData::NonResident { compression_unit, .. } if compression_unit != 0)
+    }
+    
+    /// 暗号化されているか
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & attr_flags::ENCRYPTED != 0
+    }
+    
+    /// ディスク上で実際に割り当てられているクラスタ数
+    pub fn allocated_clusters(&self) -> u64 {
+        match &self.data {
+            StreamData::Resident(_) => 0,
+            StreamData::NonResident { runs, .. } => runs.iter().filter(|run| run.lcn.is_some()).map(|run| run.length).sum(),
+        }
+    }
+}
+
+/// `vcn`を含むランの、そのVCNに対応するLCNと残りクラスタ数（ランの範囲外ならNone）
+fn map_vcn(runs: &[DataRun], vcn: u64) -> Option<(Option<u64>, u64)> {
+    let mut start = 0u64;
+    for run in runs {
+        if vcn < start + run.length {
+            let skip = vcn - start;
+            return Some((run.lcn.map(|lcn| lcn + skip), run.length - skip));
+        }
+        start += run.length;
+    }
+    None
+}
+
+impl NtfsVolume {
+    /// ストリームの`offset`から読み込み、読み込んだバイト数を返す
+    pub fn read_stream(&self, stream: &Stream, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        if offset >= stream.size {
+            return Ok(0);
+        }
+        let to_read = core::cmp::min(buffer.len() as u64, stream.size - offset) as usize;
+        
+        match &stream.data {
+            StreamData::Resident(value) => {
+                let start = offset as usize;
+                buffer[..to_read].copy_from_slice(&value[start..start + to_read]);
+            },
+            StreamData::NonResident { runs, compression_unit } => {
+                if stream.is_encrypted() {
+                    return Err(FsError::NotSupported);
+                }
+                
+                // 初期化済みサイズ以降はディスク上の内容に関わらず0
+                let on_disk = core::cmp::min(to_read as u64, stream.initialized_size.saturating_sub(offset)) as usize;
+                if stream.is_compressed() {
+                    self.read_compressed(runs, *compression_unit, &mut buffer[..on_disk], offset)?;
+                } else {
+                    self.read_runs(runs, &mut buffer[..on_disk], offset)?;
+                }
+                buffer[on_disk..to_read].fill(0);
+            },
+        }
+        
+        Ok(to_read)
+    }
+    
+    /// ストリーム全体を読み込む
+    pub fn read_stream_all(&self, stream: &Stream) -> FsResult<Vec<u8>> {
+        let size = usize::try_from(stream.size).map_err(|_| FsError::OverflowError)?;
+        let mut data = vec![0u8; size];
+        let read = self.read_stream(stream, &mut data, 0)?;
+        data.truncate(read);
+        Ok(data)
+    }
+    
+    /// 非圧縮のデータランから読み込む（スパース部分は0）
+    fn read_runs(&self, runs: &[DataRun], buffer: &mut [u8], offset: u64) -> FsResult<()> {
+        let mut done = 0;
+        while done < buffer.len() {
+            let position = offset + done as u64;
+            let vcn = position / self.cluster_size;
+            let within = position % self.cluster_size;
+            
+            let (lcn, remaining) = map_vcn(runs, vcn).ok_or_else(|| {
+                log::warn!("NTFS: データサイズに対してデータランが足りません (VCN {})", vcn);
+                FsError::CorruptedFs
+            })?;
+            let chunk = core::cmp::min((remaining * self.cluster_size - within) as usize, buffer.len() - done);
+            
+            match lcn {
+                Some(lcn) => {
+                    if lcn + remaining > self.superblock.total_clusters() {
+                        return Err(FsError::CorruptedFs);
+                    }
+                    let data = read_bytes(&*self.device, lcn * self.cluster_size + within, chunk)?;
+                    buffer[done..done + chunk].copy_from_slice(&data);
+                },
+                None => buffer[done..done + chunk].fill(0),
+            }
+            done += chunk;
+        }
+        Ok(())
+    }
+    
+    /// 圧縮ストリームから読み込む
+    ///
+    /// 圧縮単位ごとに、全体がスパースなら0、全クラスタが割り当て済みなら非圧縮、
+    /// 途中からスパースならその手前までがLZNT1で圧縮されたデータとして扱う
+    fn read_compressed(&self, runs: &[DataRun], compression_unit: u8, buffer: &mut [u8], offset: u64) -> FsResult<()> {
+        if compression_unit > 16 {
+            return Err(FsError::CorruptedFs);
+        }
+        let unit_clusters = 1u64 << compression_unit;
+        let unit_size = unit_clusters * self.cluster_size;
+        let mut unit_data = Vec::new();
+        
+        let mut done = 0;
+        while done < buffer.len() {
+            let position = offset + done as u64;
+            let unit = position / unit_size;
+            let within = (position % unit_size) as usize;
+            let chunk = core::cmp::min(unit_size as usize - within, buffer.len() - done);
+            let first_vcn = unit * unit_clusters;
+            
+            // 圧縮単位のうち先頭から連続して割り当てられているクラスタ
+            let mut allocated: Vec<(u64, u64)> = Vec::new();
+            let mut count = 0;
+            while count < unit_clusters {
+                match map_vcn(runs, first_vcn + count) {
+                    Some((Some(lcn), remaining)) => {
+                        let take = core::cmp::min(remaining, unit_clusters - count);
+                        allocated.push((lcn, take));
+                        count += take;
+                    },
+                    _ => break,
+                }
+            }
+            
+            if count == 0 {
+                buffer[done..done + chunk].fill(0);
+            } else if count == unit_clusters {
+                self.read_runs(runs, &mut buffer[done..done + chunk], position)?;
+            } else {
+                let mut compressed = Vec::with_capacity((count * self.cluster_size) as usize);
+                for &(lcn, length) in &allocated {
+                    if lcn + length > self.superblock.total_clusters() {
+                        return Err(FsError::CorruptedFs);
+                    }
+                    compressed.extend_from_slice(&read_bytes(&*self.device, lcn * self.cluster_size, (length * self.cluster_size) as usize)?);
+                }
+                unit_data.resize(unit_size as usize, 0);
+                decompress_lznt1(&compressed, &mut unit_data)?;
+                buffer[done..done + chunk].copy_from_slice(&unit_data[within..within + chunk]);
+            }
+            done += chunk;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn decodes_relative_and_sparse_runs() {
+        // 長さ0x10 @ LCN 0x1000, スパース 4, 長さ2 @ 相対 -0x800, 長さ1 @ 相対 +0x10
+        let data = [0x21, 0x10, 0x00, 0x10, 0x01, 0x04, 0x21, 0x02, 0x00, 0xF8, 0x11, 0x01, 0x10, 0x00];
+        let runs = decode_runs(&data).unwrap();
+        assert_eq!(runs, vec![
+            DataRun { lcn: Some(0x1000), length: 0x10 },
+            DataRun { lcn: None, length: 4 },
+            DataRun { lcn: Some(0x800), length: 2 },
+            DataRun { lcn: Some(0x810), length: 1 },
+        ]);
+        assert_eq!(map_vcn(&runs, 0x12), Some((None, 2)));
+        assert_eq!(map_vcn(&runs, 0x15), Some((Some(0x801), 1)));
+        assert_eq!(map_vcn(&runs, 0x17), None);
+        
+        // ボリューム先頭より前を指すランは破損
+        assert!(decode_runs(&[0x11, 0x01, 0xF0, 0x00]).is_err());
+    }
+}
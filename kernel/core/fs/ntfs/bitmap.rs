@@ -0,0 +1,92 @@
+// NTFS ビットマップ
+//
+// $Bitmap（クラスタ使用状況）と$MFTの$BITMAP（レコード使用状況）からの
+// 空き容量の集計
+
+use super::super::{FsError, FsResult};
+use super::attribute::{attr_type, Stream};
+use super::record::mft_number;
+use super::NtfsVolume;
+
+/// 一度に読み込むビットマップのバイト数
+const SCAN_CHUNK: usize = 64 * 1024;
+
+/// ボリュームの使用状況
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeUsage {
+    /// 空きクラスタ数
+    pub free_clusters: u64,
+    /// MFTレコード総数
+    pub total_records: u64,
+    /// 空きMFTレコード数
+    pub free_records: u64,
+}
+
+/// ビットマップの先頭`bits`ビットのうち立っているビット数
+pub fn count_set_bits(data: &[u8], bits: u64) -> u64 {
+    let full_bytes = core::cmp::min((bits / 8) as usize, data.len());
+    let mut count: u64 = data[..full_bytes].iter().map(|byte| byte.count_ones() as u64).sum();
+    
+    let rest = (bits % 8) as u32;
+    if rest != 0 && full_bytes < data.len() {
+        count += (data[full_bytes] & ((1u8 << rest) - 1)).count_ones() as u64;
+    }
+    count
+}
+
+impl NtfsVolume {
+    /// ビットマップストリームの先頭`bits`ビット中の使用数を数える
+    fn count_used(&self, stream: &Stream, bits: u64) -> FsResult<u64> {
+        let bytes = bits.div_ceil(8);
+        if stream.size < bytes {
+            log::warn!("NTFS: ビットマップが小さすぎます ({} < {}バイト)", stream.size, bytes);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut used = 0;
+        let mut buffer = vec![0u8; SCAN_CHUNK];
+        let mut offset = 0u64;
+        while offset < bytes {
+            let len = core::cmp::min(SCAN_CHUNK as u64, bytes - offset) as usize;
+            self.read_stream(stream, &mut buffer[..len], offset)?;
+            used += count_set_bits(&buffer[..len], core::cmp::min((len * 8) as u64, bits - offset * 8));
+            offset += len as u64;
+        }
+        Ok(used)
+    }
+    
+    /// $Bitmapと$MFTの$BITMAPを走査して使用状況を集計
+    pub fn load_usage(&self) -> FsResult<VolumeUsage> {
+        let total_clusters = self.superblock.total_clusters();
+        let bitmap = self.load_record(mft_number::BITMAP)?
+            .stream(attr_type::DATA, "")?
+            .ok_or(FsError::CorruptedFs)?;
+        let used_clusters = self.count_used(&bitmap, total_clusters)?;
+        
+        let total_records = self.mft.size / self.record_size as u64;
+        let mft_bitmap = self.load_record(mft_number::MFT)?
+            .stream(attr_type::BITMAP, "")?
+            .ok_or(FsError::CorruptedFs)?;
+        let used_records = self.count_used(&mft_bitmap, total_records)?;
+        
+        Ok(VolumeUsage {
+            free_clusters: total_clusters.saturating_sub(used_clusters),
+            total_records,
+            free_records: total_records.saturating_sub(used_records),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn counts_only_requested_bits() {
+        let data = [0xFFu8, 0x0F, 0xF0];
+        assert_eq!(count_set_bits(&data, 24), 16);
+        assert_eq!(count_set_bits(&data, 12), 12);
+        assert_eq!(count_set_bits(&data, 20), 12);
+        assert_eq!(count_set_bits(&data, 64), 16);
+    }
+}
@@ -0,0 +1,142 @@
+// NTFS 圧縮
+//
+// 圧縮属性で使われるLZNT1形式の伸長
+// （圧縮単位は4KiBのチャンク列で、各チャンクは圧縮/非圧縮のどちらかで格納される）
+
+use super::super::{FsError, FsResult};
+
+/// LZNT1のチャンクサイズ（伸長後）
+pub const CHUNK_SIZE: usize = 4096;
+
+/// チャンクヘッダの圧縮フラグ
+const CHUNK_COMPRESSED: u16 = 0x8000;
+/// チャンクヘッダのサイズ部分
+const CHUNK_SIZE_MASK: u16 = 0x0FFF;
+
+/// チャンク内の位置に応じた（オフセットビット数, 長さマスク）
+///
+/// チャンク先頭から遠いほどオフセットに多くのビットを割り当てる
+fn split_for_position(position: usize) -> (u32, u16) {
+    let mut offset_bits = 4;
+    let mut remaining = position.saturating_sub(1);
+    while remaining >= 0x10 {
+        offset_bits += 1;
+        remaining >>= 1;
+    }
+    (offset_bits, 0xFFFF >> offset_bits)
+}
+
+/// 1つの圧縮チャンクを`dst`へ伸長し、書き込んだバイト数を返す
+fn decompress_chunk(src: &[u8], dst: &mut [u8]) -> FsResult<usize> {
+    let mut written = 0;
+    let mut pos = 0;
+    
+    while pos < src.len() {
+        let tags = src[pos];
+        pos += 1;
+        
+        for bit in 0..8 {
+            if pos >= src.len() {
+                break;
+            }
+            
+            if tags & (1 << bit) == 0 {
+                // リテラル
+                if written >= dst.len() {
+                    return Err(FsError::CorruptedFs);
+                }
+                dst[written] = src[pos];
+                written += 1;
+                pos += 1;
+                continue;
+            }
+            
+            // 後方参照
+            if pos + 2 > src.len() || written == 0 {
+                return Err(FsError::CorruptedFs);
+            }
+            let token = u16::from_le_bytes([src[pos], src[pos + 1]]);
+            pos += 2;
+            
+            let (offset_bits, length_mask) = split_for_position(written);
+            let distance = (token >> (16 - offset_bits)) as usize + 1;
+            let length = (token & length_mask) as usize + 3;
+            if distance > written || written + length > dst.len() {
+                return Err(FsError::CorruptedFs);
+            }
+            
+            // 重なりのあるコピーがあるため1バイトずつ進める
+            for _ in 0..length {
+                dst[written] = dst[written - distance];
+                written += 1;
+            }
+        }
+    }
+    
+    Ok(written)
+}
+
+/// LZNT1で圧縮された圧縮単位を`dst`へ伸長する
+///
+/// 途中で終わったチャンクや、ストリーム終端以降の領域は0で埋める
+pub fn decompress_lznt1(src: &[u8], dst: &mut [u8]) -> FsResult<()> {
+    let mut pos = 0;
+    let mut out = 0;
+    
+    while out < dst.len() && pos + 2 <= src.len() {
+        let header = u16::from_le_bytes([src[pos], src[pos + 1]]);
+        if header == 0 {
+            break;
+        }
+        
+        let size = (header & CHUNK_SIZE_MASK) as usize + 1;
+        let body_start = pos + 2;
+        let body_end = body_start + size;
+        if body_end > src.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let chunk_end = core::cmp::min(out + CHUNK_SIZE, dst.len());
+        let chunk = &mut dst[out..chunk_end];
+        let produced = if header & CHUNK_COMPRESSED != 0 {
+            decompress_chunk(&src[body_start..body_end], chunk)?
+        } else {
+            let len = core::cmp::min(size, chunk.len());
+            chunk[..len].copy_from_slice(&src[body_start..body_start + len]);
+            len
+        };
+        chunk[produced..].fill(0);
+        
+        out = chunk_end;
+        pos = body_end;
+    }
+    
+    dst[out..].fill(0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn decompresses_literals_and_back_references() {
+        // "abcabcabcabc" = リテラル3つ + (距離3, 長さ9)の後方参照、続いて非圧縮チャンク
+        let mut src = vec![0x05u8, 0xB0, 0x08, b'a', b'b', b'c'];
+        let (offset_bits, _) = split_for_position(3);
+        src.extend_from_slice(&(((3 - 1) << (16 - offset_bits)) | (9 - 3) as u16).to_le_bytes());
+        src.extend_from_slice(&(0x3000u16 | 0x0FFF).to_le_bytes());
+        src.extend(core::iter::repeat(0x5Au8).take(CHUNK_SIZE));
+        
+        let mut dst = vec![0xFFu8; CHUNK_SIZE * 3];
+        decompress_lznt1(&src, &mut dst).unwrap();
+        assert_eq!(&dst[..12], b"abcabcabcabc");
+        assert!(dst[12..CHUNK_SIZE].iter().all(|&b| b == 0));
+        assert!(dst[CHUNK_SIZE..CHUNK_SIZE * 2].iter().all(|&b| b == 0x5A));
+        assert!(dst[CHUNK_SIZE * 2..].iter().all(|&b| b == 0));
+        
+        // 伸長済みデータより前を指す参照は破損として扱う
+        let bad = [0x02u8, 0xB0, 0x01, 0x10, 0x00];
+        assert!(decompress_lznt1(&bad, &mut dst).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+// NTFS ファイル/ディレクトリハンドル
+//
+// 読み取り専用のハンドル実装（無名$DATAストリームの読み込みとディレクトリの列挙）
+
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, Metadata, Permissions, FileHandle, DirHandle, DirEntry};
+use super::attribute::Stream;
+use super::record::FileRecord;
+use super::NtfsVolume;
+
+/// NTFSファイルハンドル
+pub struct NtfsFileHandle {
+    /// 所属ボリューム
+    volume: Arc<NtfsVolume>,
+    /// ファイルレコード
+    record: Arc<FileRecord>,
+    /// 無名$DATAストリーム
+    stream: Stream,
+}
+
+impl NtfsFileHandle {
+    /// 新しいファイルハンドルを作成
+    pub(super) fn new(volume: Arc<NtfsVolume>, record: Arc<FileRecord>, stream: Stream) -> Self {
+        Self { volume, record, stream }
+    }
+}
+
+impl FileHandle for NtfsFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.volume.read_stream(&self.stream, buffer, offset)
+    }
+    
+    fn write(&self, _buffer: &[u8], _offset: u64) -> FsResult<usize> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.stream.size)
+    }
+    
+    fn resize(&self, _new_size: u64) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.record_metadata(&self.record)
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        false
+    }
+}
+
+/// NTFSディレクトリハンドル
+pub struct NtfsDirHandle {
+    /// 所属ボリューム
+    volume: Arc<NtfsVolume>,
+    /// ディレクトリのファイルレコード
+    record: Arc<FileRecord>,
+}
+
+impl NtfsDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<NtfsVolume>, record: Arc<FileRecord>) -> Self {
+        Self { volume, record }
+    }
+}
+
+impl DirHandle for NtfsDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.volume.list_directory(&self.record)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let record = self.volume.lookup(&self.record, name)?;
+        Ok(DirEntry {
+            name: self.volume.display_name(&record, self.record.number).unwrap_or_else(|| name.to_string()),
+            inode: record.number,
+            file_type: NtfsVolume::file_type(&record),
+        })
+    }
+    
+    fn create_file(&self, _name: &str, _permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_directory(&self, _name: &str, _permissions: Permissions) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn remove(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn rename(&self, _old_name: &str, _new_name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.record_metadata(&self.record)
+    }
+}
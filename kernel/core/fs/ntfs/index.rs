@@ -0,0 +1,254 @@
+// NTFS インデックス
+//
+// ディレクトリの$I30インデックス（$INDEX_ROOTを根とし、$INDEX_ALLOCATIONの
+// INDXブロックを節とするB+木）の検索と列挙
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use super::super::{FsError, FsResult};
+use super::attribute::{attr_type, FileName, Stream};
+use super::record::{apply_fixups, FileRecord, FIXUP_STRIDE};
+use super::{NtfsVolume, le_u16, le_u32, le_u64};
+
+/// INDXブロックのシグネチャ
+pub const INDEX_MAGIC: &[u8; 4] = b"INDX";
+
+/// ファイル名インデックスの属性名
+pub const I30: &str = "$I30";
+
+/// ファイル名による照合規則
+const COLLATION_FILE_NAME: u32 = 0x01;
+
+/// INDXブロック内のノードヘッダ位置
+const INDEX_BLOCK_HEADER: usize = 0x18;
+/// $INDEX_ROOT内のノードヘッダ位置
+const INDEX_ROOT_HEADER: usize = 0x10;
+/// インデックスエントリの固定部分の長さ
+const ENTRY_HEADER_SIZE: usize = 0x10;
+
+/// B+木の深さの上限（循環した木での無限ループ防止）
+const MAX_INDEX_DEPTH: usize = 32;
+
+/// インデックスエントリのフラグ
+mod entry_flags {
+    /// 下位ノードを持つ（エントリ末尾8バイトがVCN）
+    pub const HAS_SUBNODE: u16 = 0x0001;
+    /// ノード内の最後のエントリ（キーを持たない）
+    pub const LAST: u16 = 0x0002;
+}
+
+/// インデックスエントリ
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    /// 指すファイルの参照
+    pub reference: u64,
+    /// キー（ノード末尾のエントリはNone）
+    pub file_name: Option<FileName>,
+    /// このエントリより小さいキーを持つ下位ノードのVCN
+    pub subnode: Option<u64>,
+}
+
+/// ノードヘッダ（`header`の位置）に続くエントリ列を解析
+fn parse_node(data: &[u8], header: usize) -> FsResult<Vec<IndexEntry>> {
+    if header + 16 > data.len() {
+        return Err(FsError::CorruptedFs);
+    }
+    let start = header + le_u32(data, header) as usize;
+    let end = header + le_u32(data, header + 4) as usize;
+    if start > end || end > data.len() {
+        log::warn!("NTFS: インデックスノードのヘッダが不正です");
+        return Err(FsError::CorruptedFs);
+    }
+    
+    let mut entries = Vec::new();
+    let mut pos = start;
+    while pos + ENTRY_HEADER_SIZE <= end {
+        let length = le_u16(data, pos + 8) as usize;
+        let key_length = le_u16(data, pos + 10) as usize;
+        let flags = le_u16(data, pos + 12);
+        if length < ENTRY_HEADER_SIZE || pos + length > end || ENTRY_HEADER_SIZE + key_length > length {
+            log::warn!("NTFS: インデックスエントリの長さが不正です");
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let subnode = if flags & entry_flags::HAS_SUBNODE != 0 {
+            if length < ENTRY_HEADER_SIZE + 8 {
+                return Err(FsError::CorruptedFs);
+            }
+            Some(le_u64(data, pos + length - 8))
+        } else {
+            None
+        };
+        
+        if flags & entry_flags::LAST != 0 {
+            entries.push(IndexEntry { reference: 0, file_name: None, subnode });
+            return Ok(entries);
+        }
+        
+        let key = &data[pos + ENTRY_HEADER_SIZE..pos + ENTRY_HEADER_SIZE + key_length];
+        entries.push(IndexEntry {
+            reference: le_u64(data, pos),
+            file_name: Some(FileName::parse(key)?),
+            subnode,
+        });
+        pos += length;
+    }
+    
+    log::warn!("NTFS: インデックスノードに終端エントリがありません");
+    Err(FsError::CorruptedFs)
+}
+
+/// ディレクトリのインデックス
+struct DirectoryIndex {
+    /// 根ノードのエントリ
+    root: Vec<IndexEntry>,
+    /// INDXブロックを格納する$INDEX_ALLOCATION
+    allocation: Option<Stream>,
+    /// INDXブロックのサイズ
+    block_size: usize,
+    /// VCNの単位（ブロックがクラスタより小さい場合は512バイト）
+    vcn_size: u64,
+}
+
+impl NtfsVolume {
+    /// アップケーステーブルによるファイル名の照合（大文字小文字を区別しない）
+    pub fn collate_names(&self, a: &[u16], b: &[u16]) -> Ordering {
+        for (&x, &y) in a.iter().zip(b.iter()) {
+            match self.upcase(x).cmp(&self.upcase(y)) {
+                Ordering::Equal => continue,
+                other => return other,
+            }
+        }
+        a.len().cmp(&b.len())
+    }
+    
+    /// ディレクトリの$I30インデックスを開く
+    fn open_index(&self, dir: &FileRecord) -> FsResult<DirectoryIndex> {
+        if !dir.is_directory() {
+            return Err(FsError::NotDirectory);
+        }
+        let root = dir.attributes(attr_type::INDEX_ROOT, I30)
+            .find_map(|attribute| attribute.resident_value())
+            .ok_or_else(|| {
+                log::warn!("NTFS: ディレクトリ {} に$INDEX_ROOTがありません", dir.number);
+                FsError::CorruptedFs
+            })?;
+        if root.len() < INDEX_ROOT_HEADER + 16 {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        if le_u32(root, 0) != attr_type::FILE_NAME || le_u32(root, 4) != COLLATION_FILE_NAME {
+            log::warn!("NTFS: 未対応のインデックス照合規則 0x{:x}", le_u32(root, 4));
+            return Err(FsError::UnsupportedFeature);
+        }
+        let block_size = le_u32(root, 8) as usize;
+        if !block_size.is_power_of_two() || block_size < FIXUP_STRIDE {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        Ok(DirectoryIndex {
+            root: parse_node(root, INDEX_ROOT_HEADER)?,
+            allocation: dir.stream(attr_type::INDEX_ALLOCATION, I30)?,
+            block_size,
+            vcn_size: if block_size as u64 >= self.cluster_size { self.cluster_size } else { FIXUP_STRIDE as u64 },
+        })
+    }
+    
+    /// INDXブロックを読み込んでエントリを返す
+    fn read_index_block(&self, index: &DirectoryIndex, vcn: u64) -> FsResult<Vec<IndexEntry>> {
+        let allocation = index.allocation.as_ref().ok_or_else(|| {
+            log::warn!("NTFS: $INDEX_ALLOCATIONが無いのに下位ノードが参照されています");
+            FsError::CorruptedFs
+        })?;
+        
+        let offset = vcn.checked_mul(index.vcn_size).ok_or(FsError::CorruptedFs)?;
+        let mut block = vec![0u8; index.block_size];
+        if self.read_stream(allocation, &mut block, offset)? != block.len() {
+            log::warn!("NTFS: INDXブロック (VCN {}) が$INDEX_ALLOCATIONの範囲外です", vcn);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        apply_fixups(&mut block, INDEX_MAGIC)?;
+        if le_u64(&block, 0x10) != vcn {
+            log::warn!("NTFS: INDXブロックのVCNが一致しません ({} != {})", le_u64(&block, 0x10), vcn);
+            return Err(FsError::CorruptedFs);
+        }
+        parse_node(&block, INDEX_BLOCK_HEADER)
+    }
+    
+    /// ディレクトリ内の名前を検索
+    pub fn index_lookup(&self, dir: &FileRecord, name: &str) -> FsResult<Option<IndexEntry>> {
+        let key: Vec<u16> = name.encode_utf16().collect();
+        let index = self.open_index(dir)?;
+        let mut entries = index.root.clone();
+        
+        for _ in 0..MAX_INDEX_DEPTH {
+            let mut next = None;
+            for entry in &entries {
+                let file_name = match &entry.file_name {
+                    Some(file_name) => file_name,
+                    None => {
+                        next = entry.subnode;
+                        break;
+                    },
+                };
+                match self.collate_names(&key, &file_name.units) {
+                    Ordering::Greater => continue,
+                    Ordering::Equal => return Ok(Some(entry.clone())),
+                    Ordering::Less => {
+                        next = entry.subnode;
+                        break;
+                    },
+                }
+            }
+            
+            match next {
+                Some(vcn) => entries = self.read_index_block(&index, vcn)?,
+                None => return Ok(None),
+            }
+        }
+        
+        log::warn!("NTFS: ディレクトリ {} のインデックスが深すぎます", dir.number);
+        Err(FsError::CorruptedFs)
+    }
+    
+    /// ディレクトリの全エントリを照合順に列挙
+    pub fn index_entries(&self, dir: &FileRecord) -> FsResult<Vec<IndexEntry>> {
+        let index = self.open_index(dir)?;
+        let mut result = Vec::new();
+        let mut visited = BTreeSet::new();
+        self.walk_index(&index, &index.root, 0, &mut visited, &mut result)?;
+        Ok(result)
+    }
+    
+    /// ノードを中間順にたどる（同じブロックを2度参照する壊れた木は拒否する）
+    fn walk_index(
+        &self,
+        index: &DirectoryIndex,
+        entries: &[IndexEntry],
+        depth: usize,
+        visited: &mut BTreeSet<u64>,
+        result: &mut Vec<IndexEntry>,
+    ) -> FsResult<()> {
+        if depth >= MAX_INDEX_DEPTH {
+            log::warn!("NTFS: インデックスが深すぎます");
+            return Err(FsError::CorruptedFs);
+        }
+        
+        for entry in entries {
+            if let Some(vcn) = entry.subnode {
+                if !visited.insert(vcn) {
+                    log::warn!("NTFS: INDXブロック (VCN {}) が複数回参照されています", vcn);
+                    return Err(FsError::CorruptedFs);
+                }
+                let children = self.read_index_block(index, vcn)?;
+                self.walk_index(index, &children, depth + 1, visited, result)?;
+            }
+            if entry.file_name.is_some() {
+                result.push(entry.clone());
+            }
+        }
+        Ok(())
+    }
+}
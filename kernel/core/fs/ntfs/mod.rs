@@ -1,134 +1,394 @@
 // NTFS ファイルシステム実装
 //
-// Microsoft NTFSファイルシステムの実装
+// Microsoft NTFSファイルシステムの読み取り専用実装（MFTレコード、常駐/非常駐属性、
+// $I30インデックス、$ATTRIBUTE_LIST、スパースファイル、LZNT1圧縮ストリーム）
 
 mod superblock;
 mod record;
 mod attribute;
 mod bitmap;
 mod index;
-mod security;
 mod compression;
+mod file;
 
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
-use spin::RwLock;
-use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, OpenMode, Filesystem};
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, Filesystem};
+use super::vfs::BlockDevice;
 use self::superblock::NtfsSuperblock;
+use self::attribute::{attr_type, namespace, ntfs_time_to_unix, Attribute, Stream};
+use self::bitmap::VolumeUsage;
+use self::record::{mft_number, reference_number, FileRecord, MftRecord};
+use self::file::{NtfsFileHandle, NtfsDirHandle};
 
-/// NTFSファイルシステム
-pub struct NtfsFilesystem {
-    /// ファイルシステム名
-    name: String,
-    /// マウントされたデバイス
-    devices: RwLock<BTreeMap<String, Arc<dyn super::vfs::BlockDevice>>>,
-    /// スーパーブロックキャッシュ
-    superblocks: RwLock<BTreeMap<String, NtfsSuperblock>>,
+/// パス解決時のディレクトリ階層上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// キャッシュするファイルレコード数の上限
+const RECORD_CACHE_CAPACITY: usize = 1024;
+
+/// $UpCaseの大きさ（UTF-16の全符号単位）
+const UPCASE_ENTRIES: usize = 65536;
+
+/// $VOLUME_INFORMATIONのダーティフラグ
+const VOLUME_IS_DIRTY: u16 = 0x0001;
+
+/// NTFSのファイル名長の上限（UTF-16符号単位）
+const MAX_NAME_LENGTH: u32 = 255;
+
+/// リトルエンディアンのu16を読み込み
+fn le_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
 }
 
-impl NtfsFilesystem {
-    /// 新しいNTFSファイルシステムインスタンスを作成
-    pub fn new() -> Self {
-        Self {
-            name: "ntfs".to_string(),
-            devices: RwLock::new(BTreeMap::new()),
-            superblocks: RwLock::new(BTreeMap::new()),
-        }
+/// リトルエンディアンのu32を読み込み
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// リトルエンディアンのu64を読み込み
+fn le_u64(data: &[u8], offset: usize) -> u64 {
+    le_u32(data, offset) as u64 | (le_u32(data, offset + 4) as u64) << 32
+}
+
+/// デバイスからバイト単位で読み込み（ブロック境界をまたいでよい）
+fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
     }
     
-    /// スーパーブロックを読み込み
-    fn read_superblock(&self, device: &dyn super::vfs::BlockDevice) -> FsResult<NtfsSuperblock> {
-        // NTFSのブートセクタを読み込み
-        let data = device.read_block(0)?;
-        NtfsSuperblock::parse(&data)
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+    
+    if data.len() < start + len {
+        return Err(FsError::IoError);
     }
     
-    /// パスを解析
-    fn parse_path(&self, path: &str) -> FsResult<PathInfo> {
-        // パスを解析して構造化
-        let clean_path = path.trim().trim_matches('/');
+    Ok(data[start..start + len].to_vec())
+}
+
+/// NTFSオプション
+#[derive(Debug, Clone, Copy)]
+pub struct NtfsOptions {
+    /// 読み込んだファイルレコードをキャッシュする
+    pub cache_records: bool,
+    /// LZNT1で圧縮されたファイルの読み込みを許可する
+    pub enable_compression: bool,
+}
+
+impl Default for NtfsOptions {
+    fn default() -> Self {
+        Self {
+            cache_records: true,
+            enable_compression: true,
+        }
+    }
+}
+
+/// マウントされたNTFSボリューム
+struct NtfsVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// ブートセクタ
+    superblock: NtfsSuperblock,
+    /// クラスタサイズ（バイト）
+    cluster_size: u64,
+    /// MFTレコードサイズ（バイト）
+    record_size: usize,
+    /// $MFTの$DATAストリーム
+    mft: Stream,
+    /// アップケーステーブル
+    upcase: Vec<u16>,
+    /// ボリュームラベル
+    label: String,
+    /// 圧縮ファイルの読み込みを許可するか
+    allow_compressed: bool,
+    /// ファイルレコードのキャッシュ（無効時はNone）
+    record_cache: Option<Mutex<BTreeMap<u64, Arc<FileRecord>>>>,
+    /// 使用状況（初回の統計要求時に集計）
+    usage: Mutex<Option<VolumeUsage>>,
+}
+
+impl NtfsVolume {
+    /// デバイスからボリュームを構築
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, options: NtfsOptions) -> FsResult<Self> {
+        let boot = read_bytes(&*device, 0, 512)?;
+        let superblock = NtfsSuperblock::parse(&boot)?;
+        
+        if superblock.total_size() > device.total_blocks() * device.block_size() {
+            log::warn!("NTFS: ボリューム ({}バイト) がデバイスより大きいです", superblock.total_size());
+            return Err(FsError::CorruptedFs);
+        }
+        
+        // $MFT自身の配置は、ブートセクタが指す先頭レコードのデータランから得る
+        let record_size = superblock.mft_record_size() as usize;
+        let data = read_bytes(&*device, superblock.mft_offset(), record_size)?;
+        let mft_record = MftRecord::parse(data, mft_number::MFT)?;
+        let parts: Vec<&Attribute> = mft_record.attributes.iter()
+            .filter(|attribute| attribute.type_code == attr_type::DATA && attribute.name.is_empty())
+            .collect();
+        let mft = Stream::from_parts(&parts).map_err(|_| {
+            log::warn!("NTFS: $MFTの$DATA属性が見つかりません");
+            FsError::CorruptedFs
+        })?;
+        
+        let mut volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            cluster_size: superblock.cluster_size(),
+            record_size,
+            mft,
+            upcase: Vec::new(),
+            label: String::new(),
+            allow_compressed: options.enable_compression,
+            record_cache: options.cache_records.then(|| Mutex::new(BTreeMap::new())),
+            usage: Mutex::new(None),
+            superblock,
+        };
+        
+        // $MFTが断片化して拡張レコードにデータランが続く場合は、先頭の断片から辿って全体を得る
+        if mft_record.attributes.iter().any(|attribute| attribute.type_code == attr_type::ATTRIBUTE_LIST) {
+            volume.mft = volume.build_record(mft_number::MFT)?
+                .stream(attr_type::DATA, "")?
+                .ok_or(FsError::CorruptedFs)?;
+        }
+        if volume.mft.size < mft_number::FIRST_USER * record_size as u64 {
+            log::warn!("NTFS: $MFTが小さすぎます ({}バイト)", volume.mft.size);
+            return Err(FsError::CorruptedFs);
+        }
         
-        // ルートディレクトリの場合
-        if clean_path.is_empty() {
-            return Ok(PathInfo {
-                path: "/".to_string(),
-                parent: "/".to_string(),
-                name: "".to_string(),
-                is_root: true,
-            });
+        let upcase = volume.load_record(mft_number::UPCASE)?
+            .stream(attr_type::DATA, "")?
+            .ok_or(FsError::CorruptedFs)?;
+        let table = volume.read_stream_all(&upcase)?;
+        if table.len() != UPCASE_ENTRIES * 2 {
+            log::warn!("NTFS: $UpCaseの大きさが不正です ({}バイト)", table.len());
+            return Err(FsError::CorruptedFs);
         }
+        volume.upcase = table.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
         
-        // 最後のコンポーネントを取得
-        let components: Vec<&str> = clean_path.split('/').collect();
-        let name = components.last().unwrap_or(&"").to_string();
+        let (label, version) = volume.volume_information()?;
+        volume.label = label;
         
-        // 親パスを取得
-        let parent = if components.len() > 1 {
-            components[..components.len() - 1].join("/")
-        } else {
-            "/".to_string()
-        };
+        let root = volume.load_record(mft_number::ROOT)?;
+        if !root.is_directory() {
+            log::warn!("NTFS: ルートディレクトリのレコードが不正です");
+            return Err(FsError::CorruptedFs);
+        }
         
-        Ok(PathInfo {
-            path: format!("/{}", clean_path),
-            parent,
-            name,
-            is_root: false,
-        })
+        log::info!("NTFS: ボリューム '{}' (NTFS {}.{}, {}クラスタ × {}バイト, MFTレコード {}バイト)",
+                  volume.label, version.0, version.1, volume.superblock.total_clusters(), volume.cluster_size, record_size);
+        
+        Ok(volume)
     }
     
-    /// パスからデバイスを検索
-    fn find_device_for_path(&self, path: &str) -> FsResult<String> {
-        // マウントポイントからデバイスを検索
-        let devices = self.devices.read();
+    /// $Volumeからラベルとバージョンを読み込み、ダーティフラグを確認する
+    fn volume_information(&self) -> FsResult<(String, (u8, u8))> {
+        let record = self.load_record(mft_number::VOLUME)?;
+        
+        let label = record.attributes(attr_type::VOLUME_NAME, "")
+            .find_map(|attribute| attribute.resident_value())
+            .map(|value| String::from_utf16_lossy(&value.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect::<Vec<_>>()))
+            .unwrap_or_default();
+        
+        let info = record.attributes(attr_type::VOLUME_INFORMATION, "")
+            .find_map(|attribute| attribute.resident_value())
+            .filter(|value| value.len() >= 12)
+            .ok_or_else(|| {
+                log::warn!("NTFS: $VOLUME_INFORMATIONが見つかりません");
+                FsError::CorruptedFs
+            })?;
+        let version = (info[8], info[9]);
+        if version.0 != 1 && version.0 != 3 {
+            log::warn!("NTFS: 未対応のバージョン {}.{}", version.0, version.1);
+            return Err(FsError::UnsupportedVersion);
+        }
+        if le_u16(info, 10) & VOLUME_IS_DIRTY != 0 {
+            log::warn!("NTFS: ボリュームがダーティです（Windowsで正常に終了していないか、ハイバネート中の可能性があります）");
+        }
         
-        // 最長一致するマウントポイントを検索
-        let mut best_match = "";
-        let mut best_device = "";
+        Ok((label, version))
+    }
+    
+    /// UTF-16符号単位を大文字化
+    fn upcase(&self, unit: u16) -> u16 {
+        self.upcase.get(unit as usize).copied().unwrap_or(unit)
+    }
+    
+    /// ディレクトリ内の名前を検索してレコードを返す
+    fn lookup(&self, dir: &FileRecord, name: &str) -> FsResult<Arc<FileRecord>> {
+        if name.encode_utf16().count() > MAX_NAME_LENGTH as usize {
+            return Err(FsError::NotFound);
+        }
+        let entry = self.index_lookup(dir, name)?.ok_or(FsError::NotFound)?;
+        self.load_reference(entry.reference)
+    }
+    
+    /// パスをレコードに解決
+    fn resolve(&self, path: &str) -> FsResult<Arc<FileRecord>> {
+        let mut stack = vec![self.load_record(mft_number::ROOT)?];
         
-        for (device_path, _) in devices.iter() {
-            // マウントポイントのパスを取得（実際のシステムでは別のマッピングテーブルから取得）
-            let mount_point = self.get_mount_point_for_device(device_path);
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if stack.len() > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
+            }
             
-            // パスがマウントポイントから始まり、かつ今までの最長一致より長い場合
-            if path.starts_with(&mount_point) && mount_point.len() > best_match.len() {
-                best_match = &mount_point;
-                best_device = device_path;
+            match component {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                },
+                name => {
+                    let current = stack.last().ok_or(FsError::NotFound)?;
+                    if !current.is_directory() {
+                        return Err(FsError::NotDirectory);
+                    }
+                    let next = self.lookup(current, name)?;
+                    stack.push(next);
+                },
             }
         }
         
-        if !best_device.is_empty() {
-            return Ok(best_device.to_string());
-        }
-        
-        // マウントポイントが見つからない場合、デフォルトのデバイスを返す
-        if let Some((device_path, _)) = devices.iter().next() {
-            return Ok(device_path.clone());
+        stack.pop().ok_or(FsError::NotFound)
+    }
+    
+    /// ディレクトリの内容を列挙（DOS名、自身への参照、システムファイルは除く）
+    fn list_directory(&self, dir: &FileRecord) -> FsResult<Vec<DirEntry>> {
+        Ok(self.index_entries(dir)?
+            .into_iter()
+            .filter_map(|entry| {
+                let number = reference_number(entry.reference);
+                let file_name = entry.file_name?;
+                if file_name.namespace == namespace::DOS || number == dir.number || number < mft_number::FIRST_USER {
+                    return None;
+                }
+                Some(DirEntry {
+                    inode: number,
+                    file_type: if file_name.is_directory() { FileType::Directory } else { FileType::Regular },
+                    name: file_name.name,
+                })
+            })
+            .collect())
+    }
+    
+    /// `parent`ディレクトリにおけるレコードの表示名（DOS名以外）
+    fn display_name(&self, record: &FileRecord, parent: u64) -> Option<String> {
+        record.file_names()
+            .into_iter()
+            .find(|file_name| reference_number(file_name.parent) == parent && file_name.namespace != namespace::DOS)
+            .map(|file_name| file_name.name)
+    }
+    
+    /// レコードのファイルタイプ
+    fn file_type(record: &FileRecord) -> FileType {
+        if record.is_directory() {
+            FileType::Directory
+        } else {
+            FileType::Regular
         }
-        
-        Err(FsError::NotFound)
     }
     
-    /// デバイスパスからマウントポイントを取得
-    fn get_mount_point_for_device(&self, device_path: &str) -> String {
-        // 本来はVFSからのマッピングを使用
-        // この実装ではシンプルにデバイスパスをそのまま返す
-        device_path.to_string()
+    /// レコードのメタデータを構築
+    fn record_metadata(&self, record: &FileRecord) -> FsResult<Metadata> {
+        let info = record.standard_information().ok_or_else(|| {
+            log::warn!("NTFS: レコード {} に$STANDARD_INFORMATIONがありません", record.number);
+            FsError::CorruptedFs
+        })?;
+        let is_dir = record.is_directory();
+        let stream = if is_dir { None } else { record.stream(attr_type::DATA, "")? };
+        
+        Ok(Metadata {
+            inode: record.number,
+            file_type: Self::file_type(record),
+            size: stream.as_ref().map_or(0, |stream| stream.size),
+            uid: 0,
+            gid: 0,
+            permissions: Permissions {
+                read: true,
+                write: false,
+                execute: is_dir,
+            },
+            created: ntfs_time_to_unix(info.created),
+            accessed: ntfs_time_to_unix(info.accessed),
+            modified: ntfs_time_to_unix(info.modified),
+            links: record.link_count as u32,
+            block_size: self.cluster_size as u32,
+            blocks: stream.as_ref().map_or(0, |stream| stream.allocated_clusters()),
+        })
+    }
+    
+    /// ファイルシステム統計を取得
+    fn stats(&self) -> FsResult<FsStats> {
+        let mut cached = self.usage.lock();
+        let usage = match *cached {
+            Some(usage) => usage,
+            None => *cached.insert(self.load_usage()?),
+        };
+        
+        Ok(FsStats {
+            total_blocks: self.superblock.total_clusters(),
+            free_blocks: usage.free_clusters,
+            available_blocks: usage.free_clusters,
+            total_nodes: usage.total_records,
+            free_nodes: usage.free_records,
+            block_size: self.cluster_size as u32,
+            max_filename_length: MAX_NAME_LENGTH,
+        })
     }
 }
 
-/// パス情報
-struct PathInfo {
-    /// 完全パス
-    path: String,
-    /// 親ディレクトリのパス
-    parent: String,
-    /// ファイル名/ディレクトリ名
+/// NTFSファイルシステム
+pub struct NtfsFilesystem {
+    /// ファイルシステム名
     name: String,
-    /// ルートディレクトリかどうか
-    is_root: bool,
+    /// オプション
+    options: NtfsOptions,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<NtfsVolume>>>,
+}
+
+impl NtfsFilesystem {
+    /// 新しいNTFSファイルシステムインスタンスを作成
+    pub fn new() -> Self {
+        Self::new_advanced(NtfsOptions::default())
+    }
+    
+    /// オプションを指定してNTFSファイルシステムインスタンスを作成
+    pub fn new_with_options(cache_records: bool, enable_compression: bool) -> Self {
+        Self::new_advanced(NtfsOptions {
+            cache_records,
+            enable_compression,
+        })
+    }
+    
+    /// 高度なオプションでNTFSファイルシステムインスタンスを作成
+    pub fn new_advanced(options: NtfsOptions) -> Self {
+        Self {
+            name: "ntfs".to_string(),
+            options,
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<NtfsVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
 }
 
 impl Filesystem for NtfsFilesystem {
@@ -141,179 +401,80 @@ impl Filesystem for NtfsFilesystem {
     }
     
     fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
-        // デバイスが既にマウントされているかチェック
-        {
-            let devices = self.devices.read();
-            if devices.contains_key(device) {
-                return Err(FsError::AlreadyExists);
-            }
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
         }
         
-        // ブロックデバイスをオープン
-        let block_device = super::vfs::open_block_device(device)?;
-        
-        // スーパーブロックを読み込み
-        let superblock = self.read_superblock(&*block_device)?;
-        
-        // NTFSシグネチャをチェック
-        if !superblock.is_valid_signature() {
-            return Err(FsError::BadMagic);
+        if options.split(',').map(str::trim).any(|option| option == "rw") {
+            log::warn!("NTFS: 書き込みには対応していないため読み取り専用でマウントします");
         }
         
-        // キャッシュに保存
-        {
-            let mut devices = self.devices.write();
-            devices.insert(device.to_string(), block_device);
-            
-            let mut superblocks = self.superblocks.write();
-            superblocks.insert(device.to_string(), superblock);
-        }
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = NtfsVolume::open(device, block_device, self.options)?;
         
-        log::info!("NTFSファイルシステムをマウント: {} -> {}", device, mount_point);
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
         
+        log::info!("NTFSファイルシステムをマウント: {} -> {} (読み取り専用)", device, mount_point);
         Ok(())
     }
     
     fn unmount(&self, mount_point: &str) -> FsResult<()> {
-        // マウントポイントのデバイスを取得
-        let devices = self.devices.read();
-        let device = devices.get(mount_point).ok_or(FsError::NotFound)?;
-
-        // キャッシュをフラッシュ
-        device.flush_cache()?;
-
-        // デバイスを同期
-        device.sync()?;
-
-        // マウント済みデバイスから削除
-        drop(devices);
-        let mut devices = self.devices.write();
-        devices.remove(mount_point);
-
-        // スーパーブロックキャッシュから削除
-        let mut superblocks = self.superblocks.write();
-        superblocks.remove(mount_point);
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        volume.device.close()?;
 
-        log::info!("NTFSファイルシステムをアンマウント: {}", mount_point);
+        log::info!("NTFSファイルシステムをアンマウント: {} ({})", mount_point, volume.device_path);
         Ok(())
     }
     
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
-        
-        // デバイスを検索
-        let device_path = self.find_device_for_path(path)?;
-        let devices = self.devices.read();
-        let device = devices.get(&device_path).ok_or(FsError::NotFound)?;
-        
-        // スーパーブロックを取得
-        let superblocks = self.superblocks.read();
-        let superblock = superblocks.get(&device_path).ok_or(FsError::NotFound)?;
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        if mode != OpenMode::ReadOnly {
+            return Err(FsError::ReadOnly);
+        }
         
-        // MFTを検索してファイルレコードを取得
-        let mft = superblock.get_mft_record(device.as_ref())?;
-        let file_record = mft.find_file_by_path(&parsed_path.path, device.as_ref())?;
+        let volume = self.find_volume(mount_point)?;
+        let record = volume.resolve(path)?;
+        if record.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
         
-        // ファイルハンドルを作成
-        let file_handle = self::record::NtfsFileHandle::new(
-            file_record,
-            device.clone(),
-            superblock.clone(),
-            mode
-        )?;
+        let stream = record.stream(attr_type::DATA, "")?.ok_or_else(|| {
+            log::warn!("NTFS: レコード {} に$DATA属性がありません", record.number);
+            FsError::CorruptedFs
+        })?;
+        if stream.is_encrypted() {
+            log::warn!("NTFS: 暗号化されたファイルは読み込めません: {}", path);
+            return Err(FsError::NotSupported);
+        }
+        if stream.is_compressed() && !volume.allow_compressed {
+            return Err(FsError::NotSupported);
+        }
         
-        Ok(Arc::new(file_handle))
+        Ok(Arc::new(NtfsFileHandle::new(volume, record, stream)))
     }
     
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
-        
-        // デバイスを検索
-        let device_path = self.find_device_for_path(path)?;
-        let devices = self.devices.read();
-        let device = devices.get(&device_path).ok_or(FsError::NotFound)?;
-        
-        // スーパーブロックを取得
-        let superblocks = self.superblocks.read();
-        let superblock = superblocks.get(&device_path).ok_or(FsError::NotFound)?;
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let record = volume.resolve(path)?;
         
-        // MFTを検索してディレクトリレコードを取得
-        let mft = superblock.get_mft_record(device.as_ref())?;
-        let dir_record = mft.find_file_by_path(&parsed_path.path, device.as_ref())?;
-        
-        // ディレクトリであるかチェック
-        if !dir_record.is_directory() {
-            return Err(FsError::NotADirectory);
+        if !record.is_directory() {
+            return Err(FsError::NotDirectory);
         }
         
-        // ディレクトリハンドルを作成
-        let dir_handle = self::record::NtfsDirHandle::new(
-            dir_record,
-            device.clone(),
-            superblock.clone()
-        )?;
-        
-        Ok(Arc::new(dir_handle))
+        Ok(Arc::new(NtfsDirHandle::new(volume, record)))
     }
     
-    fn metadata(&self, path: &str) -> FsResult<Metadata> {
-        // パスを解析
-        let parsed_path = self.parse_path(path)?;
-        
-        // デバイスを検索
-        let device_path = self.find_device_for_path(path)?;
-        let devices = self.devices.read();
-        let device = devices.get(&device_path).ok_or(FsError::NotFound)?;
-        
-        // スーパーブロックを取得
-        let superblocks = self.superblocks.read();
-        let superblock = superblocks.get(&device_path).ok_or(FsError::NotFound)?;
-        
-        // MFTを検索してファイルレコードを取得
-        let mft = superblock.get_mft_record(device.as_ref())?;
-        let file_record = mft.find_file_by_path(&parsed_path.path, device.as_ref())?;
-        
-        // メタデータを取得
-        let metadata = file_record.get_metadata()?;
-        
-        Ok(metadata)
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        let record = volume.resolve(path)?;
+        volume.record_metadata(&record)
     }
     
     fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
-        // デバイスを検索
-        let devices = self.devices.read();
-        let device = devices.get(mount_point).ok_or(FsError::NotFound)?;
-        
-        // スーパーブロックを取得
-        let superblocks = self.superblocks.read();
-        let superblock = superblocks.get(mount_point).ok_or(FsError::NotFound)?;
-        
-        // ボリューム情報を取得
-        let volume_info = superblock.get_volume_info(device.as_ref())?;
-        
-        // ファイルシステム統計を作成
-        let stats = FsStats {
-            total_blocks: volume_info.total_sectors,
-            free_blocks: volume_info.free_sectors,
-            available_blocks: volume_info.free_sectors,
-            total_nodes: volume_info.total_mft_records,
-            free_nodes: volume_info.free_mft_records,
-            block_size: superblock.bytes_per_sector() as u32,
-            max_filename_length: 255, // NTFS supports up to 255 characters
-        };
-        
-        Ok(stats)
+        self.find_volume(mount_point)?.stats()
     }
     
     fn sync(&self) -> FsResult<()> {
-        // すべてのデバイスを同期
-        let devices = self.devices.read();
-        for (_, device) in devices.iter() {
-            device.sync()?;
-        }
-        
+        // 読み取り専用のため書き戻すものは無い
         Ok(())
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,320 @@
+// NTFS MFTレコード
+//
+// ファイルレコードの読み込みと更新シーケンス配列（fixup）の適用、
+// $ATTRIBUTE_LISTで拡張レコードに分散した属性の結合
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::attribute::{attr_type, parse_attribute_list, Attribute, FileName, StandardInformation, Stream};
+use super::{NtfsVolume, le_u16, le_u32, le_u64};
+
+/// ファイルレコードのシグネチャ
+pub const FILE_MAGIC: &[u8; 4] = b"FILE";
+
+/// 更新シーケンス配列が保護する単位（セクタサイズに関わらず512バイト）
+pub const FIXUP_STRIDE: usize = 512;
+
+/// 1レコードに含まれる属性数の上限（破損したレコードでの無限ループ防止）
+const MAX_ATTRIBUTES: usize = 512;
+
+/// レコードヘッダのフラグ
+pub mod record_flags {
+    /// 使用中
+    pub const IN_USE: u16 = 0x0001;
+    /// ディレクトリ（$I30インデックスを持つ）
+    pub const DIRECTORY: u16 = 0x0002;
+}
+
+/// 予約済みのMFTレコード番号
+pub mod mft_number {
+    /// $MFT
+    pub const MFT: u64 = 0;
+    /// $Volume
+    pub const VOLUME: u64 = 3;
+    /// ルートディレクトリ
+    pub const ROOT: u64 = 5;
+    /// $Bitmap
+    pub const BITMAP: u64 = 6;
+    /// $UpCase
+    pub const UPCASE: u64 = 10;
+    /// 最初のユーザーファイル（これより前はシステムファイル）
+    pub const FIRST_USER: u64 = 16;
+}
+
+/// ファイル参照のレコード番号部分（下位48ビット）
+pub fn reference_number(reference: u64) -> u64 {
+    reference & 0x0000_FFFF_FFFF_FFFF
+}
+
+/// ファイル参照のシーケンス番号部分（上位16ビット）
+pub fn reference_sequence(reference: u64) -> u16 {
+    (reference >> 48) as u16
+}
+
+/// マルチセクタ構造（ファイルレコード/INDXブロック）に更新シーケンス配列を適用する
+///
+/// 各512バイト単位の末尾2バイトは書き込み時に更新シーケンス番号で置き換えられているため、
+/// 一致を確認してから元の値に戻す（不一致は書き込み途中で壊れたことを示す）
+pub fn apply_fixups(data: &mut [u8], magic: &[u8; 4]) -> FsResult<()> {
+    if data.len() < 8 || &data[0..4] != magic {
+        return Err(FsError::CorruptedFs);
+    }
+    
+    let usa_offset = le_u16(data, 4) as usize;
+    let usa_count = le_u16(data, 6) as usize;
+    if data.len() % FIXUP_STRIDE != 0 || usa_count != data.len() / FIXUP_STRIDE + 1
+        || usa_offset % 2 != 0 || usa_offset + usa_count * 2 > FIXUP_STRIDE - 2
+    {
+        log::warn!("NTFS: 更新シーケンス配列が不正です (オフセット {}, 個数 {})", usa_offset, usa_count);
+        return Err(FsError::CorruptedFs);
+    }
+    
+    let usn = [data[usa_offset], data[usa_offset + 1]];
+    for i in 1..usa_count {
+        let end = i * FIXUP_STRIDE - 2;
+        if data[end..end + 2] != usn {
+            log::warn!("NTFS: 更新シーケンス番号が一致しません (セクタ {})", i - 1);
+            return Err(FsError::CorruptedFs);
+        }
+        let saved = usa_offset + i * 2;
+        data[end] = data[saved];
+        data[end + 1] = data[saved + 1];
+    }
+    Ok(())
+}
+
+/// MFTレコード1つ分の内容
+#[derive(Debug, Clone)]
+pub struct MftRecord {
+    /// レコード番号
+    pub number: u64,
+    /// シーケンス番号（レコード再利用ごとに増える）
+    pub sequence: u16,
+    /// ハードリンク数
+    pub link_count: u16,
+    /// レコードフラグ
+    pub flags: u16,
+    /// ベースレコードの参照（拡張レコードの場合のみ非0）
+    pub base_reference: u64,
+    /// 属性
+    pub attributes: Vec<Attribute>,
+}
+
+impl MftRecord {
+    /// fixup前のレコードデータから解析
+    pub fn parse(mut data: Vec<u8>, number: u64) -> FsResult<Self> {
+        apply_fixups(&mut data, FILE_MAGIC)?;
+        
+        let attrs_offset = le_u16(&data, 0x14) as usize;
+        let bytes_in_use = le_u32(&data, 0x18) as usize;
+        if bytes_in_use > data.len() || attrs_offset >= bytes_in_use {
+            log::warn!("NTFS: MFTレコード {} のヘッダが不正です", number);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let mut attributes = Vec::new();
+        let mut offset = attrs_offset;
+        while let Some((attribute, length)) = Attribute::parse(&data[..bytes_in_use], offset)? {
+            if attributes.len() >= MAX_ATTRIBUTES {
+                return Err(FsError::CorruptedFs);
+            }
+            attributes.push(attribute);
+            offset += length;
+        }
+        
+        Ok(Self {
+            number,
+            sequence: le_u16(&data, 0x10),
+            link_count: le_u16(&data, 0x12),
+            flags: le_u16(&data, 0x16),
+            base_reference: le_u64(&data, 0x20),
+            attributes,
+        })
+    }
+    
+    /// 使用中かどうか
+    pub fn in_use(&self) -> bool {
+        self.flags & record_flags::IN_USE != 0
+    }
+}
+
+/// 拡張レコードの属性まで結合したファイル
+#[derive(Debug, Clone)]
+pub struct FileRecord {
+    /// ベースレコード番号
+    pub number: u64,
+    /// シーケンス番号
+    pub sequence: u16,
+    /// ハードリンク数
+    pub link_count: u16,
+    /// レコードフラグ
+    pub flags: u16,
+    /// 属性（$ATTRIBUTE_LIST自身は除く）
+    pub attributes: Vec<Attribute>,
+}
+
+impl FileRecord {
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        self.flags & record_flags::DIRECTORY != 0
+    }
+    
+    /// 指定したタイプと名前の属性（非常駐属性の断片を含む）
+    pub fn attributes<'a>(&'a self, type_code: u32, name: &'a str) -> impl Iterator<Item = &'a Attribute> + 'a {
+        self.attributes.iter().filter(move |attribute| attribute.type_code == type_code && attribute.name == name)
+    }
+    
+    /// 指定したタイプと名前の属性をストリームとして取得
+    pub fn stream(&self, type_code: u32, name: &str) -> FsResult<Option<Stream>> {
+        let parts: Vec<&Attribute> = self.attributes(type_code, name).collect();
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        Stream::from_parts(&parts).map(Some)
+    }
+    
+    /// $STANDARD_INFORMATION
+    pub fn standard_information(&self) -> Option<StandardInformation> {
+        self.attributes(attr_type::STANDARD_INFORMATION, "")
+            .find_map(|attribute| attribute.resident_value())
+            .and_then(|value| StandardInformation::parse(value).ok())
+    }
+    
+    /// 全ての$FILE_NAME（ハードリンクやDOS名ごとに1つ）
+    pub fn file_names(&self) -> Vec<FileName> {
+        self.attributes(attr_type::FILE_NAME, "")
+            .filter_map(|attribute| attribute.resident_value())
+            .filter_map(|value| FileName::parse(value).ok())
+            .collect()
+    }
+}
+
+impl NtfsVolume {
+    /// MFTからレコードを1つ読み込む
+    pub fn read_mft_record(&self, number: u64) -> FsResult<MftRecord> {
+        let offset = number.checked_mul(self.record_size as u64).ok_or(FsError::NotFound)?;
+        if offset + self.record_size as u64 > self.mft.size {
+            return Err(FsError::NotFound);
+        }
+        
+        let mut data = vec![0u8; self.record_size];
+        if self.read_stream(&self.mft, &mut data, offset)? != data.len() {
+            return Err(FsError::IoError);
+        }
+        MftRecord::parse(data, number)
+    }
+    
+    /// ファイルレコードを読み込む（キャッシュ有効時は再利用する）
+    pub fn load_record(&self, number: u64) -> FsResult<Arc<FileRecord>> {
+        if let Some(cache) = &self.record_cache {
+            if let Some(record) = cache.lock().get(&number) {
+                return Ok(record.clone());
+            }
+        }
+        
+        let record = Arc::new(self.build_record(number)?);
+        
+        if let Some(cache) = &self.record_cache {
+            let mut cache = cache.lock();
+            if cache.len() >= super::RECORD_CACHE_CAPACITY {
+                cache.pop_first();
+            }
+            cache.insert(number, record.clone());
+        }
+        Ok(record)
+    }
+    
+    /// ファイル参照からレコードを読み込み、シーケンス番号を確認する
+    pub fn load_reference(&self, reference: u64) -> FsResult<Arc<FileRecord>> {
+        let record = self.load_record(reference_number(reference))?;
+        let sequence = reference_sequence(reference);
+        if sequence != 0 && sequence != record.sequence {
+            log::warn!("NTFS: レコード {} のシーケンス番号が一致しません ({} != {})",
+                      record.number, sequence, record.sequence);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(record)
+    }
+    
+    /// ベースレコードと、$ATTRIBUTE_LISTが指す拡張レコードから属性を集める
+    pub fn build_record(&self, number: u64) -> FsResult<FileRecord> {
+        let base = self.read_mft_record(number)?;
+        if !base.in_use() || base.base_reference != 0 {
+            return Err(FsError::NotFound);
+        }
+        
+        let list = base.attributes.iter().find(|attribute| attribute.type_code == attr_type::ATTRIBUTE_LIST);
+        let attributes = match list {
+            None => base.attributes.clone(),
+            Some(list) => {
+                let data = self.read_stream_all(&Stream::from_parts(&[list])?)?;
+                let mut extensions: BTreeMap<u64, MftRecord> = BTreeMap::new();
+                let mut attributes = Vec::new();
+                
+                for entry in parse_attribute_list(&data)? {
+                    let holder = reference_number(entry.reference);
+                    let record = if holder == number {
+                        &base
+                    } else {
+                        if !extensions.contains_key(&holder) {
+                            let extension = self.read_mft_record(holder)?;
+                            if !extension.in_use() || reference_number(extension.base_reference) != number {
+                                log::warn!("NTFS: レコード {} は {} の拡張レコードではありません", holder, number);
+                                return Err(FsError::CorruptedFs);
+                            }
+                            extensions.insert(holder, extension);
+                        }
+                        &extensions[&holder]
+                    };
+                    
+                    let attribute = record.attributes.iter()
+                        .find(|attribute| attribute.type_code == entry.type_code && attribute.instance == entry.instance)
+                        .ok_or_else(|| {
+                            log::warn!("NTFS: 属性リストの属性 0x{:x} がレコード {} にありません", entry.type_code, holder);
+                            FsError::CorruptedFs
+                        })?;
+                    if attribute.type_code != attr_type::ATTRIBUTE_LIST {
+                        attributes.push(attribute.clone());
+                    }
+                }
+                attributes
+            },
+        };
+        
+        Ok(FileRecord {
+            number,
+            sequence: base.sequence,
+            link_count: base.link_count,
+            flags: base.flags,
+            attributes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn fixups_restore_sector_tails_and_detect_torn_writes() {
+        let mut data = vec![0u8; 1024];
+        data[0..4].copy_from_slice(FILE_MAGIC);
+        data[4..6].copy_from_slice(&0x30u16.to_le_bytes());
+        data[6..8].copy_from_slice(&3u16.to_le_bytes());
+        // 更新シーケンス番号と、各セクタ末尾の元の値
+        data[0x30..0x36].copy_from_slice(&[0x07, 0x00, 0xAA, 0xBB, 0xCC, 0xDD]);
+        data[510..512].copy_from_slice(&[0x07, 0x00]);
+        data[1022..1024].copy_from_slice(&[0x07, 0x00]);
+        
+        let mut fixed = data.clone();
+        apply_fixups(&mut fixed, FILE_MAGIC).unwrap();
+        assert_eq!(&fixed[510..512], &[0xAA, 0xBB]);
+        assert_eq!(&fixed[1022..1024], &[0xCC, 0xDD]);
+        
+        data[1023] = 0x01;
+        assert!(apply_fixups(&mut data, FILE_MAGIC).is_err());
+        assert!(apply_fixups(&mut fixed, b"INDX").is_err());
+    }
+}
@@ -21,13 +21,18 @@ const CLUSTERS_PER_MFT_RECORD_OFFSET: usize = 0x40;
 const CLUSTERS_PER_INDEX_BUFFER_OFFSET: usize = 0x44;
 const VOLUME_SERIAL_OFFSET: usize = 0x48;
 
+/// 許容する最大クラスタサイズ（2MiB）
+const MAX_CLUSTER_SIZE: u64 = 2 * 1024 * 1024;
+/// 許容する最大MFTレコード/インデックスブロックサイズ
+const MAX_RECORD_SIZE: u64 = 64 * 1024;
+
 /// NTFSスーパーブロック
 #[derive(Debug, Clone)]
 pub struct NtfsSuperblock {
     /// セクタあたりのバイト数
     pub bytes_per_sector: u16,
-    /// クラスタあたりのセクタ数
-    pub sectors_per_cluster: u8,
+    /// クラスタあたりのセクタ数（0x80より大きい値は2の累乗の負数表現を展開済み）
+    pub sectors_per_cluster: u32,
     /// メディア記述子
     pub media_descriptor: u8,
     /// トラックあたりのセクタ数
@@ -70,7 +75,17 @@ impl NtfsSuperblock {
             data[BYTES_PER_SECTOR_OFFSET + 1],
         ]);
         
-        let sectors_per_cluster = data[SECTORS_PER_CLUSTER_OFFSET];
+        // 64KiBを超えるクラスタでは 2^(256 - 値) セクタを表す
+        let raw_sectors_per_cluster = data[SECTORS_PER_CLUSTER_OFFSET];
+        let sectors_per_cluster = if raw_sectors_per_cluster > 0x80 {
+            let shift = 256 - raw_sectors_per_cluster as u32;
+            if shift > 16 {
+                return Err(FsError::CorruptedFs);
+            }
+            1u32 << shift
+        } else {
+            raw_sectors_per_cluster as u32
+        };
         let media_descriptor = data[MEDIA_DESCRIPTOR_OFFSET];
         
         let sectors_per_track = u16::from_le_bytes([
@@ -137,7 +152,7 @@ impl NtfsSuperblock {
             data[VOLUME_SERIAL_OFFSET + 7],
         ]);
         
-        Ok(Self {
+        let superblock = Self {
             bytes_per_sector,
             sectors_per_cluster,
             media_descriptor,
@@ -151,7 +166,41 @@ impl NtfsSuperblock {
             clusters_per_index_buffer,
             volume_serial,
             boot_sector_data: data[0..512].to_vec(),
-        })
+        };
+        superblock.validate()?;
+        Ok(superblock)
+    }
+    
+    /// ジオメトリの整合性を検証
+    fn validate(&self) -> FsResult<()> {
+        if !self.bytes_per_sector.is_power_of_two() || !(256..=4096).contains(&self.bytes_per_sector) {
+            log::warn!("NTFS: 不正なセクタサイズ {}", self.bytes_per_sector);
+            return Err(FsError::CorruptedFs);
+        }
+        if !self.sectors_per_cluster.is_power_of_two() || self.cluster_size() > MAX_CLUSTER_SIZE {
+            log::warn!("NTFS: 不正なクラスタあたりセクタ数 {}", self.sectors_per_cluster);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        // レコード/インデックスブロックは更新シーケンス配列の単位（512バイト）の倍数でなければならない
+        for value in [self.clusters_per_mft_record, self.clusters_per_index_buffer] {
+            if !(-31..=64).contains(&value) {
+                log::warn!("NTFS: 不正なMFTレコード/インデックスブロックサイズ ({})", value);
+                return Err(FsError::CorruptedFs);
+            }
+        }
+        for size in [self.mft_record_size(), self.index_buffer_size()] {
+            if !size.is_power_of_two() || !(512..=MAX_RECORD_SIZE).contains(&size) {
+                log::warn!("NTFS: 不正なMFTレコード/インデックスブロックサイズ ({}バイト)", size);
+                return Err(FsError::CorruptedFs);
+            }
+        }
+        
+        if self.mft_cluster == 0 || self.mft_cluster >= self.total_clusters() {
+            log::warn!("NTFS: MFTの開始クラスタ {} がボリューム外です", self.mft_cluster);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(())
     }
     
     /// 有効なNTFSシグネチャを持っているかどうか
@@ -183,6 +232,11 @@ impl NtfsSuperblock {
         }
     }
     
+    /// 総クラスタ数
+    pub fn total_clusters(&self) -> u64 {
+        self.total_sectors / self.sectors_per_cluster as u64
+    }
+    
     /// 総容量を取得（バイト単位）
     pub fn total_size(&self) -> u64 {
         self.total_sectors * self.bytes_per_sector as u64
@@ -197,4 +251,38 @@ impl NtfsSuperblock {
     pub fn mft_mirror_offset(&self) -> u64 {
         self.mft_mirror_cluster * self.cluster_size()
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn boot_sector(sectors_per_cluster: u8, clusters_per_record: u8) -> Vec<u8> {
+        let mut data = vec![0u8; 512];
+        data[NTFS_SIGNATURE_OFFSET..NTFS_SIGNATURE_OFFSET + 8].copy_from_slice(NTFS_SIGNATURE);
+        data[BYTES_PER_SECTOR_OFFSET..BYTES_PER_SECTOR_OFFSET + 2].copy_from_slice(&512u16.to_le_bytes());
+        data[SECTORS_PER_CLUSTER_OFFSET] = sectors_per_cluster;
+        data[TOTAL_SECTORS_OFFSET..TOTAL_SECTORS_OFFSET + 8].copy_from_slice(&(1u64 << 24).to_le_bytes());
+        data[MFT_CLUSTER_OFFSET..MFT_CLUSTER_OFFSET + 8].copy_from_slice(&4u64.to_le_bytes());
+        data[CLUSTERS_PER_MFT_RECORD_OFFSET] = clusters_per_record;
+        data[CLUSTERS_PER_INDEX_BUFFER_OFFSET] = 0xF4;
+        data
+    }
+    
+    #[test]
+    fn decodes_negative_size_encodings() {
+        // 8セクタ/クラスタ、MFTレコードは 2^10 バイト
+        let superblock = NtfsSuperblock::parse(&boot_sector(8, 0xF6)).unwrap();
+        assert_eq!(superblock.cluster_size(), 4096);
+        assert_eq!(superblock.mft_record_size(), 1024);
+        assert_eq!(superblock.index_buffer_size(), 4096);
+        
+        // 0xF4 は 2^12 セクタ（2MiBクラスタ）
+        let superblock = NtfsSuperblock::parse(&boot_sector(0xF4, 0xF6)).unwrap();
+        assert_eq!(superblock.cluster_size(), 2 * 1024 * 1024);
+        
+        // レコードサイズが512バイト未満なら拒否する
+        assert!(NtfsSuperblock::parse(&boot_sector(8, 0xF8)).is_err());
+        assert!(matches!(NtfsSuperblock::parse(&[0u8; 512]), Err(FsError::BadMagic)));
+    }
+}
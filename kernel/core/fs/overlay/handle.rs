@@ -0,0 +1,217 @@
+// overlay ファイル/ディレクトリハンドル
+//
+// ファイルハンドルは下位層のファイルを読み取り専用で開いておき、最初の書き込みで
+// 上位層へコピーして開き直す。ディレクトリハンドルはoverlay内のパスだけを持ち、
+// 操作のたびに層を引き直す
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::RwLock;
+use super::super::{FsResult, FileType, Metadata, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, InodeNum};
+use super::{OverlayVolume, child_path, normalize};
+
+/// overlayのファイルハンドル
+pub struct OverlayFileHandle {
+    /// 所属ボリューム
+    volume: Arc<OverlayVolume>,
+    /// overlay内のパス
+    path: String,
+    /// overlayのアイノード番号
+    inode: InodeNum,
+    /// 開いたモード
+    mode: OpenMode,
+    /// 層のファイルハンドル
+    inner: RwLock<Arc<dyn FileHandle>>,
+    /// 上位層のファイルを開いているか
+    on_upper: AtomicBool,
+}
+
+impl OverlayFileHandle {
+    /// 上位層のファイルを包む
+    pub fn upper(volume: Arc<OverlayVolume>, path: String, inode: InodeNum, mode: OpenMode, inner: Arc<dyn FileHandle>) -> Self {
+        Self { volume, path, inode, mode, inner: RwLock::new(inner), on_upper: AtomicBool::new(true) }
+    }
+    
+    /// 下位層のファイルを包む（書き込むときに上位層へコピーする）
+    pub fn lower(volume: Arc<OverlayVolume>, path: String, inode: InodeNum, mode: OpenMode, inner: Arc<dyn FileHandle>) -> Self {
+        Self { volume, path, inode, mode, inner: RwLock::new(inner), on_upper: AtomicBool::new(false) }
+    }
+    
+    /// 読み込み用のハンドル
+    fn current(&self) -> Arc<dyn FileHandle> {
+        self.inner.read().clone()
+    }
+    
+    /// 書き込み用のハンドル（まだ下位層ならcopy-upして開き直す）
+    fn writable(&self) -> FsResult<Arc<dyn FileHandle>> {
+        if self.mode == OpenMode::ReadOnly || self.on_upper.load(Ordering::Acquire) {
+            return Ok(self.current());
+        }
+        
+        let mut inner = self.inner.write();
+        if !self.on_upper.load(Ordering::Acquire) {
+            *inner = self.volume.reopen_upper(&self.path, self.mode)?;
+            self.on_upper.store(true, Ordering::Release);
+        }
+        Ok(inner.clone())
+    }
+}
+
+impl FileHandle for OverlayFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.current().read(buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        self.writable()?.write(buffer, offset)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.current().flush()
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        self.current().size()
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        self.writable()?.resize(new_size)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let mut metadata = self.current().metadata()?;
+        metadata.inode = self.inode;
+        Ok(metadata)
+    }
+    
+    fn lock(&self, exclusive: bool) -> FsResult<()> {
+        self.current().lock(exclusive)
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        self.current().unlock()
+    }
+    
+    fn try_lock(&self, exclusive: bool) -> FsResult<()> {
+        self.current().try_lock(exclusive)
+    }
+    
+    fn can_read(&self) -> bool {
+        self.mode != OpenMode::WriteOnly
+    }
+    
+    fn can_write(&self) -> bool {
+        self.mode != OpenMode::ReadOnly
+    }
+    
+    fn fsync(&self) -> FsResult<()> {
+        self.current().fsync()
+    }
+    
+    fn fdatasync(&self) -> FsResult<()> {
+        self.current().fdatasync()
+    }
+    
+    fn ioctl(&self, request: u32, arg: usize) -> FsResult<usize> {
+        self.current().ioctl(request, arg)
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.volume.get_xattr(&self.path, name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.volume.set_xattr(&self.path, name, value)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.volume.list_xattr(&self.path)
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.volume.remove_xattr(&self.path, name)
+    }
+}
+
+/// overlayのディレクトリハンドル
+pub struct OverlayDirHandle {
+    /// 所属ボリューム
+    volume: Arc<OverlayVolume>,
+    /// overlay内のパス
+    path: String,
+}
+
+impl OverlayDirHandle {
+    /// ディレクトリハンドルを作成
+    pub fn new(volume: Arc<OverlayVolume>, path: String) -> Self {
+        Self { volume, path }
+    }
+}
+
+impl DirHandle for OverlayDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.volume.read_directory(&self.path)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let path = child_path(&self.path, name);
+        let entry = self.volume.lookup(&path)?;
+        Ok(DirEntry {
+            name: name.into(),
+            inode: self.volume.inode_of(&path),
+            file_type: entry.file_type,
+        })
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        self.volume.create_file(&self.path, name, permissions)
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        self.volume.create_directory(&self.path, name, permissions)
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        self.volume.remove(&self.path, name)
+    }
+    
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        // 移動先は"/"で始まればoverlayのルートから、そうでなければこのディレクトリから
+        let new_path = if new_name.starts_with('/') { normalize(new_name) } else { normalize(&child_path(&self.path, new_name)) };
+        self.volume.rename(&self.path, old_name, &new_path)
+    }
+    
+    fn create_symlink(&self, name: &str, target: &str) -> FsResult<()> {
+        self.volume.create_symlink(&self.path, name, target)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.metadata(&self.path)
+    }
+    
+    fn create_device(&self, name: &str, file_type: FileType, major: u32, minor: u32) -> FsResult<()> {
+        self.volume.create_device(&self.path, name, file_type, major, minor)
+    }
+    
+    fn device_number(&self, name: &str) -> FsResult<(u32, u32)> {
+        self.volume.device_number(&self.path, name)
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.volume.get_xattr(&self.path, name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.volume.set_xattr(&self.path, name, value)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.volume.list_xattr(&self.path)
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.volume.remove_xattr(&self.path, name)
+    }
+}
@@ -0,0 +1,920 @@
+// overlay ファイルシステム実装
+//
+// 書き込み可能な上位層1つと読み取り専用の下位層（複数）を重ねて1つのツリーとして見せる。
+// 下位層のファイルは最初に書き込まれたときに上位層へコピーし（copy-up）、削除は
+// ホワイトアウト（0:0のキャラクタデバイス）、下位層を隠すディレクトリは
+// "trusted.overlay.opaque"拡張属性で表す（Linuxのoverlayfsと同じディスク上の形式）
+
+mod handle;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, FsId, FileType, Metadata, FsStats, Permissions, OpenMode, InodeNum};
+use super::{Filesystem, FileHandle, DirHandle, DirEntry, lookup_flags};
+use super::xattr::XattrTarget;
+use self::handle::{OverlayFileHandle, OverlayDirHandle};
+
+/// ホワイトアウトのデバイス番号
+const WHITEOUT_DEVICE: (u32, u32) = (0, 0);
+
+/// 不透明ディレクトリを示す拡張属性
+const OPAQUE_XATTR: &str = "trusted.overlay.opaque";
+
+/// overlayが内部で使う拡張属性の接頭辞（利用者からは見せない）
+const OVERLAY_XATTR_PREFIX: &str = "trusted.overlay.";
+
+/// copy-upで一度にコピーするサイズ
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// ルートディレクトリのアイノード番号
+const ROOT_INODE: InodeNum = 1;
+
+/// overlay内のパスで`dir`の子`name`のパス
+fn child_path(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// overlay内のパスを親ディレクトリと名前に分ける
+fn split_path(path: &str) -> FsResult<(&str, &str)> {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(0) if path.len() > 1 => Ok(("/", &path[1..])),
+        Some(index) if index + 1 < path.len() => Ok((&path[..index], &path[index + 1..])),
+        _ => Err(FsError::InvalidData),
+    }
+}
+
+/// パスを"/"で始まり"/"で終わらない形にする（"."と".."も解決する）
+fn normalize(path: &str) -> String {
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {},
+            ".." => {
+                components.pop();
+            },
+            name => components.push(name),
+        }
+    }
+    format!("/{}", components.join("/"))
+}
+
+/// 重ねる層（あるファイルシステム内の1つのディレクトリ）
+#[derive(Clone)]
+pub struct Layer {
+    /// 層のファイルシステム
+    fs: Arc<dyn Filesystem>,
+    /// そのファイルシステムのマウントポイント（ボリュームの選択と統計情報の取得に使う）
+    mount_point: String,
+    /// ファイルシステム内での層のルート
+    root: String,
+    /// VFSのdcacheのキー（VFSのパスから作った層のみ）
+    fs_id: Option<FsId>,
+}
+
+impl Layer {
+    /// ファイルシステム内のディレクトリ`root`を層にする
+    pub fn new(fs: Arc<dyn Filesystem>, mount_point: &str, root: &str) -> Self {
+        Self { fs, mount_point: mount_point.to_string(), root: normalize(root), fs_id: None }
+    }
+    
+    /// VFSのパスを解決して層にする（層の中のマウントは辿らない）
+    pub fn resolve(path: &str) -> FsResult<Self> {
+        let resolved = super::resolve_path(path, lookup_flags::DIRECTORY)?;
+        Ok(Self {
+            fs: resolved.fs,
+            mount_point: resolved.mount_point,
+            root: resolved.fs_path,
+            fs_id: Some(resolved.fs_id),
+        })
+    }
+    
+    /// overlay内のパスを層のファイルシステム内のパスに変換
+    fn path(&self, path: &str) -> String {
+        let path = path.trim_matches('/');
+        match (self.root.as_str(), path.is_empty()) {
+            (root, true) => root.to_string(),
+            ("/", false) => format!("/{}", path),
+            (root, false) => format!("{}/{}", root, path),
+        }
+    }
+    
+    /// 層のディレクトリを開く
+    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        self.fs.open_directory(&self.mount_point, &self.path(path))
+    }
+    
+    /// 層を直接変更したので、VFSから層を見たときのdcacheを無効化
+    fn invalidate(&self, path: &str) {
+        if let Some(fs_id) = self.fs_id {
+            let path = self.path(path);
+            let (parent, name) = path.rsplit_once('/').unwrap_or(("", path.as_str()));
+            let parent = if parent.is_empty() { "/" } else { parent };
+            // 層のエントリは置き換えや削除で別物になりうるので、ディレクトリなら配下も捨てる
+            match self.fs.metadata(&self.mount_point, parent) {
+                Ok(metadata) => super::dcache::invalidate_removed(fs_id, metadata.inode, name),
+                Err(_) => super::dcache::invalidate_filesystem(fs_id),
+            }
+        }
+    }
+}
+
+/// overlay上のエントリ
+#[derive(Debug, Clone)]
+struct Entry {
+    /// 見えている種類（一番上の層のもの）
+    file_type: FileType,
+    /// エントリがある層の番号（上から順。ディレクトリはマージする層すべて、それ以外は一番上の層だけ）
+    layers: Vec<usize>,
+}
+
+impl Entry {
+    /// 一番上の層
+    fn top(&self) -> usize {
+        self.layers[0]
+    }
+}
+
+/// マウントされたoverlay
+pub struct OverlayVolume {
+    /// 層（上位層があれば先頭、続いて下位層を上から順に）
+    layers: Vec<Layer>,
+    /// 上位層があるか（なければ読み取り専用）
+    has_upper: bool,
+    /// overlay内のパス => アイノード番号（copy-upの前後で変わらないようにoverlayが割り当てる）
+    inodes: Mutex<BTreeMap<String, InodeNum>>,
+    /// 次に割り当てるアイノード番号
+    next_inode: AtomicU64,
+    /// copy-upを直列化するロック
+    copy_up_lock: Mutex<()>,
+}
+
+impl OverlayVolume {
+    /// 層を重ねる（下位層は1つ以上必要）
+    pub fn new(upper: Option<Layer>, lowers: Vec<Layer>) -> FsResult<Self> {
+        if lowers.is_empty() {
+            return Err(FsError::InvalidData);
+        }
+        let has_upper = upper.is_some();
+        Ok(Self {
+            layers: upper.into_iter().chain(lowers).collect(),
+            has_upper,
+            inodes: Mutex::new(BTreeMap::new()),
+            next_inode: AtomicU64::new(ROOT_INODE + 1),
+            copy_up_lock: Mutex::new(()),
+        })
+    }
+    
+    /// 上位層（なければReadOnly）
+    fn upper(&self) -> FsResult<&Layer> {
+        if self.has_upper {
+            Ok(&self.layers[0])
+        } else {
+            Err(FsError::ReadOnly)
+        }
+    }
+    
+    /// 最初の下位層の番号
+    fn first_lower(&self) -> usize {
+        self.has_upper as usize
+    }
+    
+    /// パスのアイノード番号（初めて見るパスには新しく割り当てる）
+    fn inode_of(&self, path: &str) -> InodeNum {
+        if path == "/" {
+            return ROOT_INODE;
+        }
+        *self.inodes.lock().entry(path.to_string())
+            .or_insert_with(|| self.next_inode.fetch_add(1, Ordering::Relaxed))
+    }
+    
+    /// 削除したパスとその子孫のアイノード番号を忘れる
+    fn forget_inodes(&self, path: &str) {
+        let prefix = child_path(path, "");
+        self.inodes.lock().retain(|key, _| key != path && !key.starts_with(&prefix));
+    }
+    
+    /// 移動したパスとその子孫のアイノード番号を付け替える
+    fn move_inodes(&self, from: &str, to: &str) {
+        self.forget_inodes(to);
+        let prefix = child_path(from, "");
+        let mut inodes = self.inodes.lock();
+        let moved: Vec<(String, InodeNum)> = inodes.iter()
+            .filter(|(key, _)| *key == from || key.starts_with(&prefix))
+            .map(|(key, inode)| (key.clone(), *inode))
+            .collect();
+        for (key, inode) in moved {
+            inodes.remove(&key);
+            inodes.insert(format!("{}{}", to, &key[from.len()..]), inode);
+        }
+    }
+    
+    /// 層`index`のディレクトリ`dir`にある`name`がホワイトアウトか
+    fn is_whiteout(&self, index: usize, dir: &str, name: &str, file_type: FileType) -> bool {
+        file_type == FileType::CharDevice
+            && self.layers[index].open_directory(dir)
+                .and_then(|handle| handle.device_number(name))
+                .is_ok_and(|device| device == WHITEOUT_DEVICE)
+    }
+    
+    /// 層`index`のディレクトリ`path`が下位層を隠すか
+    fn is_opaque(&self, index: usize, path: &str) -> bool {
+        self.layers[index].open_directory(path)
+            .and_then(|handle| handle.get_xattr(OPAQUE_XATTR))
+            .is_ok_and(|value| value == b"y")
+    }
+    
+    /// 層`first`以下だけを見たときのルート
+    fn root_from(&self, first: usize) -> Entry {
+        let mut layers = Vec::new();
+        for index in first..self.layers.len() {
+            layers.push(index);
+            if self.is_opaque(index, "/") {
+                break;
+            }
+        }
+        Entry { file_type: FileType::Directory, layers }
+    }
+    
+    /// マージ済みのディレクトリ`dir`（パス`dir_path`）の中の`name`を探す
+    fn lookup_child(&self, dir: &Entry, dir_path: &str, name: &str) -> FsResult<Entry> {
+        let path = child_path(dir_path, name);
+        let mut found: Option<Entry> = None;
+        
+        for &index in &dir.layers {
+            let layer = &self.layers[index];
+            let metadata = match layer.fs.metadata(&layer.mount_point, &layer.path(&path)) {
+                Ok(metadata) => metadata,
+                Err(FsError::NotFound) => continue,
+                Err(e) => return Err(e),
+            };
+            if self.is_whiteout(index, dir_path, name, metadata.file_type) {
+                break;
+            }
+            
+            // ディレクトリ以外は下の層を隠す。ディレクトリは不透明でなければ下の層とマージする
+            match (&mut found, metadata.file_type) {
+                (None, FileType::Directory) => found = Some(Entry { file_type: FileType::Directory, layers: vec![index] }),
+                (Some(entry), FileType::Directory) => entry.layers.push(index),
+                (None, file_type) => {
+                    found = Some(Entry { file_type, layers: vec![index] });
+                    break;
+                },
+                (Some(_), _) => break,
+            }
+            if self.is_opaque(index, &path) {
+                break;
+            }
+        }
+        found.ok_or(FsError::NotFound)
+    }
+    
+    /// 層`first`以下だけを見てパスを探す（リンクは辿らない）
+    fn lookup_from(&self, path: &str, first: usize) -> FsResult<Entry> {
+        let mut entry = self.root_from(first);
+        let mut current = String::from("/");
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            if entry.file_type != FileType::Directory {
+                return Err(FsError::NotDirectory);
+            }
+            entry = self.lookup_child(&entry, &current, name)?;
+            current = child_path(&current, name);
+        }
+        Ok(entry)
+    }
+    
+    /// パスを探す
+    fn lookup(&self, path: &str) -> FsResult<Entry> {
+        self.lookup_from(path, 0)
+    }
+    
+    /// 下位層に`path`が見えているか（削除や移動のときにホワイトアウトが必要か）
+    fn exists_in_lower(&self, path: &str) -> bool {
+        self.has_upper && self.lookup_from(path, self.first_lower()).is_ok()
+    }
+    
+    /// `path`とその親を上位層へコピーする（すでに上位層にあれば何もしない）
+    ///
+    /// 権限はそのまま引き継ぐ。所有者と時刻はVFSに設定手段がないため新しいものになる
+    fn copy_up(&self, path: &str) -> FsResult<()> {
+        let upper = self.upper()?;
+        if path == "/" {
+            return Ok(());
+        }
+        let entry = self.lookup(path)?;
+        if entry.top() == 0 {
+            return Ok(());
+        }
+        let (parent, name) = split_path(path)?;
+        self.copy_up(parent)?;
+        
+        let _guard = self.copy_up_lock.lock();
+        if self.lookup(path)?.top() == 0 {
+            return Ok(());
+        }
+        let source = &self.layers[entry.top()];
+        let source_path = source.path(path);
+        let metadata = source.fs.metadata(&source.mount_point, &source_path)?;
+        let dir = upper.open_directory(parent)?;
+        
+        match entry.file_type {
+            FileType::Directory => dir.create_directory(name, metadata.permissions)?,
+            FileType::SymbolicLink => dir.create_symlink(name, &source.fs.read_link(&source.mount_point, &source_path)?)?,
+            FileType::Regular => {
+                let target = dir.create_file(name, metadata.permissions)?;
+                let copied = source.fs.open_file(&source.mount_point, &source_path, OpenMode::ReadOnly)
+                    .and_then(|file| copy_data(&*file, &*target))
+                    .and_then(|_| target.fsync());
+                if let Err(e) = copied {
+                    // 途中までコピーしたファイルは残さない
+                    drop(target);
+                    let _ = dir.remove(name);
+                    return Err(e);
+                }
+            },
+            _ => return Err(FsError::NotSupported),
+        }
+        if let Err(e) = self.copy_xattrs(entry.top(), path, entry.file_type) {
+            let _ = dir.remove(name);
+            return Err(e);
+        }
+        upper.invalidate(path);
+        
+        log::debug!("overlay: {} を上位層へコピー", path);
+        Ok(())
+    }
+    
+    /// `dir`を上位層へコピーしてそのハンドルを開く
+    fn upper_directory(&self, dir: &str) -> FsResult<Arc<dyn DirHandle>> {
+        self.copy_up(dir)?;
+        self.upper()?.open_directory(dir)
+    }
+    
+    /// 上位層の`dir`にある`name`のホワイトアウトを消し、消したかを返す
+    fn clear_whiteout(&self, upper_dir: &dyn DirHandle, dir: &str, name: &str) -> FsResult<bool> {
+        match upper_dir.lookup(name) {
+            Ok(entry) if self.is_whiteout(0, dir, name, entry.file_type) => {
+                upper_dir.remove(name)?;
+                Ok(true)
+            },
+            Ok(_) => Err(FsError::AlreadyExists),
+            Err(FsError::NotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// 上位層の`dir`に`name`のホワイトアウトを作る
+    fn create_whiteout(&self, upper_dir: &dyn DirHandle, name: &str) -> FsResult<()> {
+        let (major, minor) = WHITEOUT_DEVICE;
+        upper_dir.create_device(name, FileType::CharDevice, major, minor)
+    }
+    
+    /// 上位層に新しいエントリを作る準備（親のcopy-upとホワイトアウトの削除）
+    ///
+    /// 上位層の親ディレクトリと、下位層のエントリを隠していたホワイトアウトを消したかを返す
+    fn prepare_create(&self, dir: &str, name: &str) -> FsResult<(Arc<dyn DirHandle>, bool)> {
+        match self.lookup(&child_path(dir, name)) {
+            Ok(_) => return Err(FsError::AlreadyExists),
+            Err(FsError::NotFound) => {},
+            Err(e) => return Err(e),
+        }
+        let upper_dir = self.upper_directory(dir)?;
+        let whiteout_removed = self.clear_whiteout(&*upper_dir, dir, name)?;
+        Ok((upper_dir, whiteout_removed))
+    }
+    
+    /// ファイルを作成
+    fn create_file(self: &Arc<Self>, dir: &str, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        let (upper_dir, _) = self.prepare_create(dir, name)?;
+        let path = child_path(dir, name);
+        let file = upper_dir.create_file(name, permissions)?;
+        self.upper()?.invalidate(&path);
+        Ok(Arc::new(OverlayFileHandle::upper(self.clone(), path.clone(), self.inode_of(&path), OpenMode::ReadWrite, file)))
+    }
+    
+    /// ディレクトリを作成（下位層のディレクトリを置き換える場合は不透明にする）
+    fn create_directory(&self, dir: &str, name: &str, permissions: Permissions) -> FsResult<()> {
+        let (upper_dir, whiteout_removed) = self.prepare_create(dir, name)?;
+        let path = child_path(dir, name);
+        upper_dir.create_directory(name, permissions)?;
+        if whiteout_removed {
+            self.upper()?.open_directory(&path)?.set_xattr(OPAQUE_XATTR, b"y")?;
+        }
+        self.upper()?.invalidate(&path);
+        Ok(())
+    }
+    
+    /// シンボリックリンクを作成
+    fn create_symlink(&self, dir: &str, name: &str, target: &str) -> FsResult<()> {
+        let (upper_dir, _) = self.prepare_create(dir, name)?;
+        upper_dir.create_symlink(name, target)?;
+        self.upper()?.invalidate(&child_path(dir, name));
+        Ok(())
+    }
+    
+    /// デバイスノードを作成（ホワイトアウトと同じ0:0は作れない）
+    fn create_device(&self, dir: &str, name: &str, file_type: FileType, major: u32, minor: u32) -> FsResult<()> {
+        if (major, minor) == WHITEOUT_DEVICE {
+            return Err(FsError::InvalidData);
+        }
+        let (upper_dir, _) = self.prepare_create(dir, name)?;
+        upper_dir.create_device(name, file_type, major, minor)?;
+        self.upper()?.invalidate(&child_path(dir, name));
+        Ok(())
+    }
+    
+    /// マージしたディレクトリの内容
+    fn read_directory(&self, path: &str) -> FsResult<Vec<DirEntry>> {
+        let entry = self.lookup(path)?;
+        if entry.file_type != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        
+        // 上の層で見た名前（ホワイトアウトを含む）は下の層では使わない
+        let mut seen = BTreeSet::new();
+        let mut entries = Vec::new();
+        for &index in &entry.layers {
+            let dir = self.layers[index].open_directory(path)?;
+            for child in dir.read_entries()? {
+                if child.name == "." || child.name == ".." || !seen.insert(child.name.clone()) {
+                    continue;
+                }
+                if child.file_type == FileType::CharDevice && dir.device_number(&child.name).is_ok_and(|device| device == WHITEOUT_DEVICE) {
+                    continue;
+                }
+                let inode = self.inode_of(&child_path(path, &child.name));
+                entries.push(DirEntry { inode, ..child });
+            }
+        }
+        Ok(entries)
+    }
+    
+    /// エントリを削除（下位層にもあればホワイトアウトを残す）
+    fn remove(&self, dir: &str, name: &str) -> FsResult<()> {
+        let path = child_path(dir, name);
+        let entry = self.lookup(&path)?;
+        let upper = self.upper()?;
+        if entry.file_type == FileType::Directory && !self.read_directory(&path)?.is_empty() {
+            return Err(FsError::NotEmpty);
+        }
+        
+        let whiteout = self.exists_in_lower(&path);
+        let upper_dir = self.upper_directory(dir)?;
+        if entry.top() == 0 {
+            // 上位層のディレクトリに残っているのはホワイトアウトだけなので先に消す
+            if entry.file_type == FileType::Directory {
+                let upper_child = upper.open_directory(&path)?;
+                for child in upper_child.read_entries()? {
+                    if child.name != "." && child.name != ".." {
+                        upper_child.remove(&child.name)?;
+                    }
+                }
+            }
+            upper_dir.remove(name)?;
+        }
+        if whiteout {
+            self.create_whiteout(&*upper_dir, name)?;
+        }
+        upper.invalidate(&path);
+        self.forget_inodes(&path);
+        Ok(())
+    }
+    
+    /// エントリを移動（`new_path`はoverlay内の絶対パス）
+    ///
+    /// 下位層にあるディレクトリはCrossDeviceLink（呼び出し側でコピーと削除にする）
+    fn rename(&self, dir: &str, old_name: &str, new_path: &str) -> FsResult<()> {
+        let old_path = child_path(dir, old_name);
+        let new_path = normalize(new_path);
+        let source = self.lookup(&old_path)?;
+        if new_path == old_path {
+            return Ok(());
+        }
+        let (new_dir, new_name) = split_path(&new_path)?;
+        if self.lookup(new_dir)?.file_type != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        if source.file_type == FileType::Directory {
+            if new_path.starts_with(&child_path(&old_path, "")) {
+                return Err(FsError::InvalidData);
+            }
+            if self.exists_in_lower(&old_path) {
+                return Err(FsError::CrossDeviceLink);
+            }
+        }
+        
+        // 移動先にあるものは先に消す（下位層にあればホワイトアウトになる）
+        match self.lookup(&new_path) {
+            Ok(target) => {
+                match (source.file_type == FileType::Directory, target.file_type == FileType::Directory) {
+                    (true, false) => return Err(FsError::NotDirectory),
+                    (false, true) => return Err(FsError::IsDirectory),
+                    _ => {},
+                }
+                self.remove(new_dir, new_name)?;
+            },
+            Err(FsError::NotFound) => {},
+            Err(e) => return Err(e),
+        }
+        
+        let upper = self.upper()?;
+        self.copy_up(&old_path)?;
+        let target_dir = self.upper_directory(new_dir)?;
+        let whiteout_removed = self.clear_whiteout(&*target_dir, new_dir, new_name)?;
+        
+        let source_dir = upper.open_directory(dir)?;
+        if dir == new_dir {
+            source_dir.rename(old_name, new_name)?;
+        } else {
+            source_dir.rename(old_name, &upper.path(&new_path))?;
+        }
+        
+        // 移動したディレクトリが下位層の同名のディレクトリとマージされないようにする
+        if source.file_type == FileType::Directory && whiteout_removed {
+            upper.open_directory(&new_path)?.set_xattr(OPAQUE_XATTR, b"y")?;
+        }
+        if self.exists_in_lower(&old_path) {
+            self.create_whiteout(&*source_dir, old_name)?;
+        }
+        upper.invalidate(&old_path);
+        upper.invalidate(&new_path);
+        self.move_inodes(&old_path, &new_path);
+        Ok(())
+    }
+    
+    /// メタデータ（アイノード番号はoverlayのもの）
+    fn metadata(&self, path: &str) -> FsResult<Metadata> {
+        let entry = self.lookup(path)?;
+        let layer = &self.layers[entry.top()];
+        let mut metadata = layer.fs.metadata(&layer.mount_point, &layer.path(path))?;
+        metadata.inode = self.inode_of(path);
+        Ok(metadata)
+    }
+    
+    /// 層`index`の`path`の拡張属性を操作するハンドル
+    fn xattr_target(&self, index: usize, path: &str, file_type: FileType) -> FsResult<XattrTarget> {
+        let layer = &self.layers[index];
+        XattrTarget::open(&*layer.fs, &layer.mount_point, &layer.path(path), file_type)
+    }
+    
+    /// copy-upした`path`へ層`source`の拡張属性を引き継ぐ（overlayが使う属性は除く）
+    fn copy_xattrs(&self, source: usize, path: &str, file_type: FileType) -> FsResult<()> {
+        let from = match self.xattr_target(source, path, file_type) {
+            Ok(from) => from,
+            Err(FsError::NotSupported) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let names = match from.list() {
+            Ok(names) => names,
+            Err(FsError::NotSupported) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        
+        let to = self.xattr_target(0, path, file_type)?;
+        for name in names.iter().filter(|name| !name.starts_with(OVERLAY_XATTR_PREFIX)) {
+            to.set(name, &from.get(name)?)?;
+        }
+        Ok(())
+    }
+    
+    /// 拡張属性（overlayが使う属性は見せない）
+    fn get_xattr(&self, path: &str, name: &str) -> FsResult<Vec<u8>> {
+        if name.starts_with(OVERLAY_XATTR_PREFIX) {
+            return Err(FsError::NotFound);
+        }
+        let entry = self.lookup(path)?;
+        self.xattr_target(entry.top(), path, entry.file_type)?.get(name)
+    }
+    
+    /// 拡張属性を設定（上位層へコピーしてから）
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8]) -> FsResult<()> {
+        if name.starts_with(OVERLAY_XATTR_PREFIX) {
+            return Err(FsError::PermissionDenied);
+        }
+        self.copy_up(path)?;
+        let entry = self.lookup(path)?;
+        self.xattr_target(0, path, entry.file_type)?.set(name, value)
+    }
+    
+    /// 拡張属性の名前を列挙（overlayが使う属性は見せない）
+    fn list_xattr(&self, path: &str) -> FsResult<Vec<String>> {
+        let entry = self.lookup(path)?;
+        let mut names = self.xattr_target(entry.top(), path, entry.file_type)?.list()?;
+        names.retain(|name| !name.starts_with(OVERLAY_XATTR_PREFIX));
+        Ok(names)
+    }
+    
+    /// 拡張属性を削除（下位層にしかない属性はコピーした上位層から消す）
+    fn remove_xattr(&self, path: &str, name: &str) -> FsResult<()> {
+        if name.starts_with(OVERLAY_XATTR_PREFIX) {
+            return Err(FsError::PermissionDenied);
+        }
+        self.get_xattr(path, name)?;
+        self.copy_up(path)?;
+        let entry = self.lookup(path)?;
+        self.xattr_target(0, path, entry.file_type)?.remove(name)
+    }
+    
+    /// デバイスノードのデバイス番号
+    fn device_number(&self, dir: &str, name: &str) -> FsResult<(u32, u32)> {
+        let entry = self.lookup(&child_path(dir, name))?;
+        self.layers[entry.top()].open_directory(dir)?.device_number(name)
+    }
+    
+    /// ファイルを開く（書き込み用でも最初の書き込みまではcopy-upしない）
+    fn open_file(self: &Arc<Self>, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let entry = match self.lookup(path) {
+            Ok(_) if mode == OpenMode::CreateNew => return Err(FsError::AlreadyExists),
+            Ok(entry) => entry,
+            Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => {
+                let (dir, name) = split_path(path)?;
+                return self.create_file(dir, name, Permissions::default());
+            },
+            Err(e) => return Err(e),
+        };
+        if entry.file_type == FileType::Directory {
+            return Err(FsError::IsDirectory);
+        }
+        if mode != OpenMode::ReadOnly {
+            self.upper()?;
+        }
+        
+        let inode = self.inode_of(path);
+        let upper_mode = match mode {
+            OpenMode::Create | OpenMode::CreateNew => OpenMode::ReadWrite,
+            mode => mode,
+        };
+        // 切り詰めは開いた時点で書き込みになる
+        if entry.top() == 0 || mode == OpenMode::Truncate {
+            self.copy_up(path)?;
+            let upper = self.upper()?;
+            let file = upper.fs.open_file(&upper.mount_point, &upper.path(path), upper_mode)?;
+            return Ok(Arc::new(OverlayFileHandle::upper(self.clone(), path.to_string(), inode, upper_mode, file)));
+        }
+        let layer = &self.layers[entry.top()];
+        let file = layer.fs.open_file(&layer.mount_point, &layer.path(path), OpenMode::ReadOnly)?;
+        Ok(Arc::new(OverlayFileHandle::lower(self.clone(), path.to_string(), inode, upper_mode, file)))
+    }
+    
+    /// 上位層のファイルを開き直す（copy-up後）
+    fn reopen_upper(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        self.copy_up(path)?;
+        let upper = self.upper()?;
+        upper.fs.open_file(&upper.mount_point, &upper.path(path), mode)
+    }
+}
+
+/// `source`の内容を`target`にコピー
+fn copy_data(source: &dyn FileHandle, target: &dyn FileHandle) -> FsResult<()> {
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    let mut offset = 0u64;
+    loop {
+        let read = source.read(&mut buffer, offset)?;
+        if read == 0 {
+            break;
+        }
+        let mut done = 0;
+        while done < read {
+            let written = target.write(&buffer[done..read], offset + done as u64)?;
+            if written == 0 {
+                return Err(FsError::IoError);
+            }
+            done += written;
+        }
+        offset += read as u64;
+    }
+    
+    // 末尾の穴もサイズとして引き継ぐ
+    let size = source.size()?;
+    if size > offset {
+        target.resize(size)?;
+    }
+    Ok(())
+}
+
+/// マウントオプション（"lowerdir=上:下,upperdir=...,workdir=..."）
+#[derive(Debug, Default)]
+struct OverlayOptions {
+    /// 下位層のパス（上から順）
+    lower: Vec<String>,
+    /// 上位層のパス
+    upper: Option<String>,
+}
+
+impl OverlayOptions {
+    /// マウントオプションを解析
+    ///
+    /// workdirは互換性のために受け付けるだけで使わない（copy-upは上位層に直接書き、
+    /// 失敗したら途中のファイルを消す）
+    fn parse(options: &str) -> FsResult<Self> {
+        let mut parsed = Self::default();
+        for option in options.split(',').map(str::trim).filter(|option| !option.is_empty()) {
+            if let Some(value) = option.strip_prefix("lowerdir=") {
+                parsed.lower = value.split(':').filter(|path| !path.is_empty()).map(String::from).collect();
+            } else if let Some(value) = option.strip_prefix("upperdir=") {
+                parsed.upper = Some(value.to_string());
+            } else if option.starts_with("workdir=") || option == "ro" || option == "rw" {
+                continue;
+            } else {
+                log::warn!("overlay: 未対応のオプション {} を無視します", option);
+            }
+        }
+        if parsed.lower.is_empty() {
+            return Err(FsError::InvalidData);
+        }
+        Ok(parsed)
+    }
+}
+
+/// overlayファイルシステム
+pub struct OverlayFilesystem {
+    /// マウントされたoverlay（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<OverlayVolume>>>,
+}
+
+impl OverlayFilesystem {
+    /// 新しいoverlayインスタンスを作成
+    pub fn new() -> Self {
+        Self {
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// 層を直接指定してマウント（カーネル内でコンテナのルートを組み立てる場合など）
+    pub fn mount_layers(&self, mount_point: &str, upper: Option<Layer>, lowers: Vec<Layer>) -> FsResult<()> {
+        let mut volumes = self.volumes.write();
+        if volumes.contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        let layer_count = lowers.len();
+        let volume = OverlayVolume::new(upper, lowers)?;
+        
+        log::info!("overlayをマウント: {} (下位層{}個, {})",
+                  mount_point, layer_count, if volume.has_upper { "読み書き" } else { "読み取り専用" });
+        volumes.insert(mount_point.to_string(), Arc::new(volume));
+        Ok(())
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<OverlayVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+}
+
+impl Default for OverlayFilesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filesystem for OverlayFilesystem {
+    fn name(&self) -> &str {
+        "overlay"
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, _device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        // 層はマウント時に解決し、以後は層のファイルシステムを直接呼ぶ
+        // （マウントポイントが層のパスと同じでも自分自身を辿らない）
+        let options = OverlayOptions::parse(options)?;
+        let upper = options.upper.as_deref().map(Layer::resolve).transpose()?;
+        let lowers = options.lower.iter().map(|path| Layer::resolve(path)).collect::<FsResult<Vec<_>>>()?;
+        self.mount_layers(mount_point, upper, lowers)
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        
+        log::info!("overlayをアンマウント: {}", mount_point);
+        Ok(())
+    }
+    
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        self.find_volume(mount_point)?.open_file(&normalize(path), mode)
+    }
+    
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let path = normalize(path);
+        if volume.lookup(&path)?.file_type != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        Ok(Arc::new(OverlayDirHandle::new(volume, path)))
+    }
+    
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        self.find_volume(mount_point)?.metadata(&normalize(path))
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        let volume = self.find_volume(mount_point)?;
+        let top = &volume.layers[0];
+        top.fs.stats(&top.mount_point)
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        let volumes: Vec<Arc<OverlayVolume>> = self.volumes.read().values().cloned().collect();
+        for volume in volumes {
+            if let Ok(upper) = volume.upper() {
+                upper.fs.sync()?;
+            }
+        }
+        Ok(())
+    }
+    
+    fn read_link(&self, mount_point: &str, path: &str) -> FsResult<String> {
+        let volume = self.find_volume(mount_point)?;
+        let path = normalize(path);
+        let entry = volume.lookup(&path)?;
+        let layer = &volume.layers[entry.top()];
+        layer.fs.read_link(&layer.mount_point, &layer.path(&path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tmpfs::TmpfsFilesystem;
+    
+    /// tmpfsを層にする
+    fn tmpfs_layer() -> (Arc<TmpfsFilesystem>, Layer) {
+        let fs = Arc::new(TmpfsFilesystem::new());
+        fs.mount("tmpfs", "/", "").unwrap();
+        let layer = Layer::new(fs.clone(), "/", "/");
+        (fs, layer)
+    }
+    
+    #[test]
+    fn copy_up_whiteouts_opaque_and_rename() {
+        let (lower_fs, lower) = tmpfs_layer();
+        let (upper_fs, upper) = tmpfs_layer();
+        let root = lower_fs.open_directory("/", "/").unwrap();
+        root.create_directory("etc", Permissions::default()).unwrap();
+        root.create_directory("dir", Permissions::default()).unwrap();
+        lower_fs.open_file("/", "/etc/conf", OpenMode::Create).unwrap().write(b"base", 0).unwrap();
+        lower_fs.open_file("/", "/etc/old", OpenMode::Create).unwrap();
+        lower_fs.open_file("/", "/dir/keep", OpenMode::Create).unwrap();
+        
+        let overlay = OverlayFilesystem::new();
+        overlay.mount_layers("/merged", Some(upper), vec![lower]).unwrap();
+        let names = |path: &str| -> Vec<String> {
+            let mut names: Vec<String> = overlay.open_directory("/merged", path).unwrap().read_entries().unwrap()
+                .into_iter().map(|entry| entry.name).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("/"), ["dir", "etc"]);
+        
+        // 最初の書き込みで上位層へコピーし、下位層は変わらない
+        let conf = overlay.open_file("/merged", "/etc/conf", OpenMode::ReadWrite).unwrap();
+        let inode = conf.metadata().unwrap().inode;
+        assert!(upper_fs.metadata("/", "/etc/conf").is_err());
+        conf.write(b"NEW", 0).unwrap();
+        let mut buffer = [0u8; 4];
+        upper_fs.open_file("/", "/etc/conf", OpenMode::ReadOnly).unwrap().read(&mut buffer, 0).unwrap();
+        assert_eq!(&buffer, b"NEWe");
+        lower_fs.open_file("/", "/etc/conf", OpenMode::ReadOnly).unwrap().read(&mut buffer, 0).unwrap();
+        assert_eq!(&buffer, b"base");
+        assert_eq!(overlay.metadata("/merged", "/etc/conf").unwrap().inode, inode);
+        
+        // 削除は0:0のキャラクタデバイスとして残る
+        let etc = overlay.open_directory("/merged", "/etc").unwrap();
+        etc.remove("old").unwrap();
+        assert_eq!(names("/etc"), ["conf"]);
+        assert_eq!(upper_fs.open_directory("/", "/etc").unwrap().device_number("old").unwrap(), (0, 0));
+        
+        // 削除したディレクトリを作り直すと不透明になり、下位層の内容は見えない
+        let root = overlay.open_directory("/merged", "/").unwrap();
+        assert!(matches!(root.remove("dir"), Err(FsError::NotEmpty)));
+        overlay.open_directory("/merged", "/dir").unwrap().remove("keep").unwrap();
+        root.remove("dir").unwrap();
+        root.create_directory("dir", Permissions::default()).unwrap();
+        assert!(names("/dir").is_empty());
+        assert_eq!(upper_fs.open_directory("/", "/dir").unwrap().get_xattr(OPAQUE_XATTR).unwrap(), b"y");
+        assert!(overlay.open_directory("/merged", "/dir").unwrap().get_xattr(OPAQUE_XATTR).is_err());
+        
+        // ファイルの移動は元の名前にホワイトアウトを残す。下位層のディレクトリは移動できない
+        etc.rename("conf", "/dir/conf2").unwrap();
+        assert_eq!(names("/etc"), Vec::<String>::new());
+        assert_eq!(overlay.metadata("/merged", "/dir/conf2").unwrap().inode, inode);
+        assert!(matches!(root.rename("etc", "etc2"), Err(FsError::CrossDeviceLink)));
+        root.create_directory("new", Permissions::default()).unwrap();
+        root.rename("new", "renamed").unwrap();
+        assert_eq!(names("/"), ["dir", "etc", "renamed"]);
+        
+        // 同じインスタンスの別のマウントは別の層の組を見せる
+        let (_, other_lower) = tmpfs_layer();
+        overlay.mount_layers("/other", None, vec![other_lower]).unwrap();
+        assert!(overlay.open_directory("/other", "/").unwrap().read_entries().unwrap().is_empty());
+        assert!(matches!(overlay.metadata("/other", "/etc"), Err(FsError::NotFound)));
+        assert_eq!(names("/"), ["dir", "etc", "renamed"]);
+    }
+}
@@ -0,0 +1,924 @@
+// ページキャッシュ
+//
+// アイノードごとに4KiBページでファイルの内容を保持する。VFSで開いた通常ファイルの
+// read/writeと、ファイルをバックに持つmmap（MapType::File）が同じページを共有するため、
+// 両者の見え方が食い違わない。書き込みはダーティページとして溜め、ライトバック
+// スレッドまたはfsync/fdatasyncでファイルシステムへ書き出す
+
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, FsId, InodeNum, Metadata, FileHandle, OpenMode};
+use super::lock::{self, LockKind};
+
+/// ページサイズ
+pub const PAGE_SIZE: usize = 4096;
+
+/// ページの実体（mmapで物理ページとしてマップできるようページ境界に揃える）
+#[repr(C, align(4096))]
+struct PageFrame([u8; PAGE_SIZE]);
+
+/// ファイルページをユーザー空間にマップするページテーブル
+///
+/// ライトバックの前に書き込み可能なマッピングを外させ、次の書き込みを
+/// ページフォルトで捕まえてページを再びダーティにする
+pub trait PageMapper: Send + Sync {
+    /// `vaddr`が`page`（ページ先頭のカーネル仮想アドレス）をマップしていればマッピングを外す
+    fn unmap_page(&self, vaddr: usize, page: *const u8);
+}
+
+/// ページをマップしているユーザー空間のアドレス
+struct PageMapping {
+    /// マップしているページテーブル
+    mapper: Weak<dyn PageMapper>,
+    /// ユーザー空間の仮想アドレス
+    vaddr: usize,
+    /// 書き込み可能でマップしたか
+    writable: bool,
+}
+
+/// キャッシュ上のファイルページ
+pub struct CachedPage {
+    /// ページ内容
+    frame: RwLock<Box<PageFrame>>,
+    /// ファイルシステムへ未反映の変更があるか
+    dirty: AtomicBool,
+    /// ダーティになった時刻（ナノ秒）
+    dirtied_at: AtomicU64,
+    /// ユーザー空間のマッピング（マップ中は追い出さない）
+    mappings: Mutex<Vec<PageMapping>>,
+}
+
+impl CachedPage {
+    /// 0で埋めたページを作成
+    fn new() -> Self {
+        CACHED_PAGES.fetch_add(1, Ordering::Relaxed);
+        Self {
+            frame: RwLock::new(Box::new(PageFrame([0; PAGE_SIZE]))),
+            dirty: AtomicBool::new(false),
+            dirtied_at: AtomicU64::new(0),
+            mappings: Mutex::new(Vec::new()),
+        }
+    }
+    
+    /// ページ先頭のカーネル仮想アドレス（ページが解放されるまで変わらない）
+    pub fn as_ptr(&self) -> *const u8 {
+        self.frame.read().0.as_ptr()
+    }
+    
+    /// ダーティかどうか
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+    
+    /// ダーティとしてマーク（mmap経由の書き込みなど）
+    pub fn mark_dirty(&self) {
+        if !self.dirty.swap(true, Ordering::AcqRel) {
+            self.dirtied_at.store(crate::time::current_time_ns(), Ordering::Relaxed);
+            DIRTY_PAGES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// `mapper`の`vaddr`にマップすることを記録（ページテーブルに載せる前に呼ぶ）
+    ///
+    /// 書き込み可能なマッピングは書き込みフォルトでだけ作るので、その時点でダーティにする
+    pub fn map_into(&self, mapper: &Arc<dyn PageMapper>, vaddr: usize, writable: bool) {
+        if writable {
+            self.mark_dirty();
+        }
+        
+        let mapper = Arc::downgrade(mapper);
+        let mut mappings = self.mappings.lock();
+        match mappings.iter_mut().find(|mapping| mapping.vaddr == vaddr && Weak::ptr_eq(&mapping.mapper, &mapper)) {
+            Some(mapping) => mapping.writable = writable,
+            None => mappings.push(PageMapping { mapper, vaddr, writable }),
+        }
+    }
+    
+    /// `mapper`の`vaddr`のマッピングが外れたことを記録
+    fn unmap_from(&self, mapper: &Arc<dyn PageMapper>, vaddr: usize) {
+        let mapper = Arc::downgrade(mapper);
+        self.mappings.lock().retain(|mapping| mapping.vaddr != vaddr || !Weak::ptr_eq(&mapping.mapper, &mapper));
+    }
+    
+    /// ユーザー空間にマップされているか（アドレス空間ごと消えたマッピングは数えない）
+    fn is_mapped(&self) -> bool {
+        let mut mappings = self.mappings.lock();
+        mappings.retain(|mapping| mapping.mapper.strong_count() > 0);
+        !mappings.is_empty()
+    }
+    
+    /// 書き込み可能なマッピングを外し、以降のmmap経由の書き込みでページフォルトを起こさせる
+    fn write_protect(&self) {
+        // ページテーブルのロックはフォルト処理がこのページのロックより先に取るので、外す前に手放す
+        let writable: Vec<PageMapping> = {
+            let mut mappings = self.mappings.lock();
+            let (writable, rest) = core::mem::take(&mut *mappings).into_iter().partition(|mapping| mapping.writable);
+            *mappings = rest;
+            writable
+        };
+        
+        let page = self.as_ptr();
+        for mapping in writable {
+            if let Some(mapper) = mapping.mapper.upgrade() {
+                mapper.unmap_page(mapping.vaddr, page);
+            }
+        }
+    }
+    
+    /// ダーティ状態を解除し、ダーティだったかを返す
+    fn clear_dirty(&self) -> bool {
+        let was_dirty = self.dirty.swap(false, Ordering::AcqRel);
+        if was_dirty {
+            DIRTY_PAGES.fetch_sub(1, Ordering::Relaxed);
+        }
+        was_dirty
+    }
+}
+
+impl Drop for CachedPage {
+    fn drop(&mut self) {
+        self.clear_dirty();
+        CACHED_PAGES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// ライトバックの設定
+#[derive(Debug, Clone, Copy)]
+pub struct WritebackConfig {
+    /// ダーティページがキャッシュ上限のこの割合（%）を超えたらライトバックスレッドがすべて書き出す
+    pub dirty_background_ratio: u8,
+    /// ダーティページがこの割合（%）を超えたら書き込み側が同期的に書き出す
+    pub dirty_ratio: u8,
+    /// この時間（ミリ秒）より長くダーティのページはライトバックスレッドが書き出す
+    pub dirty_expire_ms: u64,
+    /// ライトバックスレッドの起床間隔（ミリ秒）
+    pub writeback_interval_ms: u64,
+}
+
+impl Default for WritebackConfig {
+    fn default() -> Self {
+        Self {
+            dirty_background_ratio: 10,
+            dirty_ratio: 20,
+            dirty_expire_ms: 30_000,
+            writeback_interval_ms: 5_000,
+        }
+    }
+}
+
+/// ページキャッシュの統計情報
+#[derive(Debug, Clone, Copy)]
+pub struct PageCacheStats {
+    /// キャッシュ中のファイル数
+    pub files: usize,
+    /// キャッシュ中のページ数
+    pub pages: usize,
+    /// そのうちダーティページ数
+    pub dirty_pages: usize,
+    /// 最大ページ数
+    pub max_pages: usize,
+    /// ヒット数
+    pub hits: u64,
+    /// ミス数（ファイルシステムから読み込んだページ数）
+    pub misses: u64,
+    /// 書き出したページ数
+    pub written_back: u64,
+}
+
+/// キャッシュ中のページ総数
+static CACHED_PAGES: AtomicUsize = AtomicUsize::new(0);
+/// ダーティページ総数
+static DIRTY_PAGES: AtomicUsize = AtomicUsize::new(0);
+/// ヒット数
+static HITS: AtomicU64 = AtomicU64::new(0);
+/// ミス数
+static MISSES: AtomicU64 = AtomicU64::new(0);
+/// 書き出したページ数
+static WRITTEN_BACK: AtomicU64 = AtomicU64::new(0);
+
+/// 1つのアイノードのページ群
+pub(super) struct FileMapping {
+    /// マッピングID（MapType::Fileのfile_id）
+    id: usize,
+    /// 読み込みと書き出しに使うファイルシステムのハンドル（書き込み可能なものを優先）
+    backing: RwLock<Arc<dyn FileHandle>>,
+    /// ページ番号 => ページ
+    pages: RwLock<BTreeMap<u64, Arc<CachedPage>>>,
+    /// ファイルサイズ（書き出し前の拡張を含む）
+    size: AtomicU64,
+    /// 開いているハンドル数
+    handles: AtomicUsize,
+    /// 書き出しに失敗したか（次のfsyncで報告する）
+    writeback_error: AtomicBool,
+    /// 書き出しを直列化する
+    writeback_lock: Mutex<()>,
+}
+
+impl FileMapping {
+    /// 新しいマッピングを作成
+    fn new(id: usize, backing: Arc<dyn FileHandle>) -> FsResult<Self> {
+        let size = backing.size()?;
+        Ok(Self {
+            id,
+            backing: RwLock::new(backing),
+            pages: RwLock::new(BTreeMap::new()),
+            size: AtomicU64::new(size),
+            handles: AtomicUsize::new(0),
+            writeback_error: AtomicBool::new(false),
+            writeback_lock: Mutex::new(()),
+        })
+    }
+    
+    /// 書き込み可能なハンドルが来たら書き出し用に差し替える
+    fn offer_backing(&self, handle: &Arc<dyn FileHandle>) {
+        if handle.can_write() && !self.backing.read().can_write() {
+            *self.backing.write() = handle.clone();
+        }
+    }
+    
+    /// ファイルサイズ
+    fn size(&self) -> u64 {
+        self.size.load(Ordering::Acquire)
+    }
+    
+    /// ページを取得（なければファイルシステムから読み込む）
+    ///
+    /// `fill`がfalseならファイルシステムを読まずに0埋めのページを作る（ページ全体を上書きする場合）
+    fn page(&self, index: u64, fill: bool) -> FsResult<Arc<CachedPage>> {
+        if let Some(page) = self.pages.read().get(&index) {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            return Ok(page.clone());
+        }
+        
+        reserve_pages();
+        let page = CachedPage::new();
+        let start = index * PAGE_SIZE as u64;
+        if fill && start < self.size() {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            let mut frame = page.frame.write();
+            let mut done = 0;
+            while done < PAGE_SIZE {
+                let read = self.backing.read().read(&mut frame.0[done..], start + done as u64)?;
+                if read == 0 {
+                    break;
+                }
+                done += read;
+            }
+        }
+        
+        // 読み込み中に他のスレッドが同じページを入れていたらそちらを使う
+        let mut pages = self.pages.write();
+        Ok(pages.entry(index).or_insert_with(|| Arc::new(page)).clone())
+    }
+    
+    /// `offset`から読み込む
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let size = self.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = core::cmp::min(buffer.len() as u64, size - offset) as usize;
+        
+        let mut done = 0;
+        while done < len {
+            let position = offset + done as u64;
+            let start = (position % PAGE_SIZE as u64) as usize;
+            let chunk = core::cmp::min(PAGE_SIZE - start, len - done);
+            
+            let page = self.page(position / PAGE_SIZE as u64, true)?;
+            buffer[done..done + chunk].copy_from_slice(&page.frame.read().0[start..start + chunk]);
+            done += chunk;
+        }
+        Ok(len)
+    }
+    
+    /// `offset`に書き込み、ページをダーティにする
+    fn write(&self, data: &[u8], offset: u64) -> FsResult<usize> {
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done as u64;
+            let start = (position % PAGE_SIZE as u64) as usize;
+            let chunk = core::cmp::min(PAGE_SIZE - start, data.len() - done);
+            
+            let page = self.page(position / PAGE_SIZE as u64, chunk < PAGE_SIZE)?;
+            page.frame.write().0[start..start + chunk].copy_from_slice(&data[done..done + chunk]);
+            page.mark_dirty();
+            done += chunk;
+        }
+        
+        self.size.fetch_max(offset + data.len() as u64, Ordering::AcqRel);
+        Ok(data.len())
+    }
+    
+    /// サイズを変更（切り詰めた部分のページは捨て、末尾ページの残りを0にする）
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        let _guard = self.writeback_lock.lock();
+        self.backing.read().resize(new_size)?;
+        
+        let keep = new_size.div_ceil(PAGE_SIZE as u64);
+        let removed = self.pages.write().split_off(&keep);
+        drop(removed);
+        
+        let tail = (new_size % PAGE_SIZE as u64) as usize;
+        if tail != 0 {
+            if let Some(page) = self.pages.read().get(&(keep - 1)) {
+                page.frame.write().0[tail..].fill(0);
+            }
+        }
+        
+        self.size.store(new_size, Ordering::Release);
+        Ok(())
+    }
+    
+    /// ダーティページを書き出す
+    ///
+    /// `older_than`を指定した場合はその時刻より前にダーティになったページだけを書き出す
+    fn writeback(&self, older_than: Option<u64>) -> FsResult<usize> {
+        let _guard = self.writeback_lock.lock();
+        let dirty: Vec<(u64, Arc<CachedPage>)> = self.pages.read().iter()
+            .filter(|(_, page)| page.is_dirty())
+            .filter(|(_, page)| older_than.is_none_or(|time| page.dirtied_at.load(Ordering::Relaxed) < time))
+            .map(|(index, page)| (*index, page.clone()))
+            .collect();
+        
+        let size = self.size();
+        let backing = self.backing.read().clone();
+        let mut written = 0;
+        let mut result = Ok(());
+        for (index, page) in dirty {
+            let start = index * PAGE_SIZE as u64;
+            if start >= size {
+                page.clear_dirty();
+                continue;
+            }
+            let len = core::cmp::min(PAGE_SIZE as u64, size - start) as usize;
+            
+            // 書き出し中の変更を取りこぼさないよう、内容を写す前にダーティを解除して
+            // 書き込み可能なマッピングを外す（以降のmmap経由の書き込みはフォルトで再びダーティになる）
+            page.clear_dirty();
+            page.write_protect();
+            let data = page.frame.read().0[..len].to_vec();
+            match backing.write(&data, start) {
+                Ok(_) => written += 1,
+                Err(e) => {
+                    log::error!("ページキャッシュ: 書き出しに失敗 (マッピング{} ページ{}): {:?}", self.id, index, e);
+                    page.mark_dirty();
+                    self.writeback_error.store(true, Ordering::Release);
+                    result = Err(e);
+                },
+            }
+        }
+        
+        WRITTEN_BACK.fetch_add(written as u64, Ordering::Relaxed);
+        result.map(|_| written)
+    }
+    
+    /// 書き出して以前の書き出しエラーを報告する（fsync/fdatasyncの共通部分）
+    fn sync_pages(&self) -> FsResult<()> {
+        self.writeback(None)?;
+        if self.writeback_error.swap(false, Ordering::AcqRel) {
+            return Err(FsError::IoError);
+        }
+        Ok(())
+    }
+    
+    /// 追い出せるクリーンなページを最大`count`個解放し、解放した数を返す
+    fn evict_clean(&self, count: usize) -> usize {
+        let mut pages = self.pages.write();
+        let victims: Vec<u64> = pages.iter()
+            .filter(|(_, page)| !page.is_dirty() && !page.is_mapped() && Arc::strong_count(page) == 1)
+            .map(|(index, _)| *index)
+            .take(count)
+            .collect();
+        
+        for index in &victims {
+            pages.remove(index);
+        }
+        victims.len()
+    }
+    
+    /// ユーザー空間にマップされたページがあるか
+    fn has_mapped_pages(&self) -> bool {
+        self.pages.read().values().any(|page| page.is_mapped())
+    }
+}
+
+/// ページキャッシュの実装
+struct PageCache {
+    /// (マウント, アイノード) => マッピング
+    mappings: BTreeMap<(FsId, InodeNum), Arc<FileMapping>>,
+    /// マッピングID => (マウント, アイノード)
+    ids: BTreeMap<usize, (FsId, InodeNum)>,
+    /// 次のマッピングID
+    next_id: usize,
+    /// 最大ページ数
+    max_pages: usize,
+    /// ライトバックの設定
+    config: WritebackConfig,
+}
+
+/// グローバルページキャッシュ
+static PAGE_CACHE: RwLock<Option<PageCache>> = RwLock::new(None);
+
+/// ライトバックスレッドが動いているか
+static WRITEBACK_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 全マッピングの複製（ファイルシステムを呼び出す間はキャッシュをロックしない）
+fn all_mappings() -> Vec<Arc<FileMapping>> {
+    PAGE_CACHE.read().as_ref()
+        .map(|cache| cache.mappings.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// 上限と設定を取得
+fn limits() -> Option<(usize, WritebackConfig)> {
+    PAGE_CACHE.read().as_ref().map(|cache| (cache.max_pages, cache.config))
+}
+
+/// ページを1つ追加する前に、上限に達していればクリーンなページを追い出す
+fn reserve_pages() {
+    let max_pages = match limits() {
+        Some((max_pages, _)) => max_pages,
+        None => return,
+    };
+    let cached = CACHED_PAGES.load(Ordering::Relaxed);
+    if cached >= max_pages {
+        // 一度に1/16ずつ空けて追い出しの頻度を抑える
+        shrink(cached + 1 - max_pages + max_pages / 16);
+    }
+}
+
+/// ダーティページがdirty_ratioを超えていたら書き込み側で書き出す
+fn balance_dirty_pages(mapping: &FileMapping) -> FsResult<()> {
+    let (max_pages, config) = match limits() {
+        Some(limits) => limits,
+        None => return Ok(()),
+    };
+    if DIRTY_PAGES.load(Ordering::Relaxed) * 100 < max_pages * config.dirty_ratio as usize {
+        return Ok(());
+    }
+    
+    mapping.writeback(None)?;
+    if DIRTY_PAGES.load(Ordering::Relaxed) * 100 >= max_pages * config.dirty_ratio as usize {
+        writeback_all()?;
+    }
+    Ok(())
+}
+
+/// ページキャッシュを初期化
+pub fn init(max_pages: usize, config: WritebackConfig) -> FsResult<()> {
+    *PAGE_CACHE.write() = Some(PageCache {
+        mappings: BTreeMap::new(),
+        ids: BTreeMap::new(),
+        next_id: 1,
+        max_pages,
+        config,
+    });
+    
+    log::info!("ページキャッシュ初期化完了: 最大{}ページ, dirty_ratio {}%, dirty_background_ratio {}%",
+              max_pages, config.dirty_ratio, config.dirty_background_ratio);
+    Ok(())
+}
+
+/// ライトバックの設定を変更
+pub fn set_writeback_config(config: WritebackConfig) -> FsResult<()> {
+    if config.dirty_background_ratio > 100 || config.dirty_ratio > 100 || config.writeback_interval_ms == 0 {
+        return Err(FsError::InvalidData);
+    }
+    let mut cache = PAGE_CACHE.write();
+    cache.as_mut().ok_or(FsError::NotSupported)?.config = config;
+    Ok(())
+}
+
+/// ライトバックスレッドを起動
+pub fn start_writeback() -> FsResult<()> {
+    if WRITEBACK_RUNNING.swap(true, Ordering::AcqRel) {
+        return Err(FsError::ResourceBusy);
+    }
+    
+    crate::core::process::spawn_kernel_task("fs-writeback", 5, writeback_main_loop)
+        .map_err(|_| {
+            WRITEBACK_RUNNING.store(false, Ordering::Release);
+            FsError::Other("ライトバックスレッドの起動に失敗")
+        })?;
+    
+    log::info!("ページキャッシュ: ライトバックスレッド開始");
+    Ok(())
+}
+
+/// ライトバックスレッドの本体
+fn writeback_main_loop() {
+    while let Some((_, config)) = limits() {
+        crate::core::process::block_current(
+            crate::core::process::BlockReason::Sleep(config.writeback_interval_ms * 1_000_000));
+        
+        if let Err(e) = writeback_background() {
+            log::warn!("ページキャッシュ: バックグラウンド書き出しでエラー: {:?}", e);
+        }
+    }
+    WRITEBACK_RUNNING.store(false, Ordering::Release);
+}
+
+/// 期限切れのダーティページを書き出す（dirty_background_ratioを超えていればすべて）
+fn writeback_background() -> FsResult<usize> {
+    let (max_pages, config) = match limits() {
+        Some(limits) => limits,
+        None => return Ok(0),
+    };
+    
+    let over_background = DIRTY_PAGES.load(Ordering::Relaxed) * 100 >= max_pages * config.dirty_background_ratio as usize;
+    let older_than = if over_background {
+        None
+    } else {
+        Some(crate::time::current_time_ns().saturating_sub(config.dirty_expire_ms * 1_000_000))
+    };
+    
+    let mut written = 0;
+    let mut result = Ok(());
+    for mapping in all_mappings() {
+        match mapping.writeback(older_than) {
+            Ok(count) => written += count,
+            Err(e) => result = Err(e),
+        }
+    }
+    result.map(|_| written)
+}
+
+/// すべてのダーティページを書き出す
+pub fn writeback_all() -> FsResult<()> {
+    let mut result = Ok(());
+    for mapping in all_mappings() {
+        if let Err(e) = mapping.writeback(None) {
+            result = Err(e);
+        }
+    }
+    result
+}
+
+/// メモリ逼迫時にクリーンなページを最大`count`個解放し、解放した数を返す
+pub fn shrink(count: usize) -> usize {
+    let mut freed = 0;
+    for mapping in all_mappings() {
+        if freed >= count {
+            break;
+        }
+        freed += mapping.evict_clean(count - freed);
+    }
+    freed
+}
+
+/// ページキャッシュの統計を取得
+pub fn stats() -> Option<PageCacheStats> {
+    let cache = PAGE_CACHE.read();
+    let cache = cache.as_ref()?;
+    Some(PageCacheStats {
+        files: cache.mappings.len(),
+        pages: CACHED_PAGES.load(Ordering::Relaxed),
+        dirty_pages: DIRTY_PAGES.load(Ordering::Relaxed),
+        max_pages: cache.max_pages,
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        written_back: WRITTEN_BACK.load(Ordering::Relaxed),
+    })
+}
+
+/// IDからマッピングを引く
+fn find_mapping(mapping_id: usize) -> Option<Arc<FileMapping>> {
+    let cache = PAGE_CACHE.read();
+    let cache = cache.as_ref()?;
+    let key = cache.ids.get(&mapping_id)?;
+    cache.mappings.get(key).cloned()
+}
+
+/// mmap用に`mapping_id`のファイルの`offset`を含むページを取得
+///
+/// 呼び出し側は`CachedPage::map_into`でマップを記録し、記録が残っている間は追い出さない
+pub fn mapped_page(mapping_id: usize, offset: u64) -> Option<Arc<CachedPage>> {
+    let mapping = find_mapping(mapping_id)?;
+    match mapping.page(offset / PAGE_SIZE as u64, true) {
+        Ok(page) => Some(page),
+        Err(e) => {
+            log::error!("ページキャッシュ: マップするページの読み込みに失敗 (マッピング{}): {:?}", mapping_id, e);
+            None
+        },
+    }
+}
+
+/// munmapで`mapper`の`vaddr`から`mapping_id`のファイルの`offset`を含むページが外れたことを記録
+///
+/// 閉じられた後もマップで残していたファイルは、最後のマッピングが外れたら書き出して手放す
+pub fn release_mapped_page(mapping_id: usize, offset: u64, mapper: &Arc<dyn PageMapper>, vaddr: usize) {
+    let mapping = match find_mapping(mapping_id) {
+        Some(mapping) => mapping,
+        None => return,
+    };
+    let page = mapping.pages.read().get(&(offset / PAGE_SIZE as u64)).cloned();
+    if let Some(page) = page {
+        page.unmap_from(mapper, vaddr);
+    }
+    if mapping.handles.load(Ordering::Acquire) == 0 {
+        release_mapping(&mapping);
+    }
+}
+
+/// 開いているハンドルがなくなったマッピングを書き出し、マップされていなければ手放す
+fn release_mapping(mapping: &Arc<FileMapping>) {
+    if let Err(e) = mapping.writeback(None) {
+        log::error!("ページキャッシュ: 解放時の書き出しに失敗 (マッピング{}): {:?}", mapping.id, e);
+        return;
+    }
+    if mapping.has_mapped_pages() {
+        return;
+    }
+    
+    let mut cache = PAGE_CACHE.write();
+    if let Some(cache) = cache.as_mut() {
+        let key = match cache.ids.get(&mapping.id) {
+            Some(key) => *key,
+            None => return,
+        };
+        let current = cache.mappings.get(&key).is_some_and(|cached| Arc::ptr_eq(cached, mapping));
+        if current && mapping.handles.load(Ordering::Acquire) == 0 {
+            cache.mappings.remove(&key);
+            cache.ids.remove(&mapping.id);
+        }
+    }
+}
+
+/// キャッシュ中のファイルのサイズ（書き出し前の拡張を含む）
+pub(super) fn cached_size(fs: FsId, inode: InodeNum) -> Option<u64> {
+    let cache = PAGE_CACHE.read();
+    cache.as_ref()?.mappings.get(&(fs, inode)).map(|mapping| mapping.size())
+}
+
+/// VFSで開いた通常ファイルのハンドルをページキャッシュ経由にする（未初期化ならそのまま返す）
+pub(super) fn open(fs: FsId, handle: Arc<dyn FileHandle>, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+    let key = (fs, handle.metadata()?.inode);
+    let mapping = {
+        let mut cache = PAGE_CACHE.write();
+        let cache = match cache.as_mut() {
+            Some(cache) => cache,
+            None => return Ok(handle),
+        };
+        
+        match cache.mappings.get(&key) {
+            Some(mapping) => {
+                mapping.offer_backing(&handle);
+                mapping.clone()
+            },
+            None => {
+                let mapping = Arc::new(FileMapping::new(cache.next_id, handle.clone())?);
+                cache.ids.insert(cache.next_id, key);
+                cache.next_id += 1;
+                cache.mappings.insert(key, mapping.clone());
+                mapping
+            },
+        }
+    };
+    mapping.handles.fetch_add(1, Ordering::AcqRel);
+    
+    Ok(Arc::new(CachedFileHandle {
+        inner: handle,
+        mapping,
+        append: mode == OpenMode::Append,
+    }))
+}
+
+/// マウント`fs`のページをすべて書き出して破棄する（アンマウント）
+pub(super) fn release_filesystem(fs: FsId) -> FsResult<()> {
+    let mappings: Vec<((FsId, InodeNum), Arc<FileMapping>)> = PAGE_CACHE.read().as_ref()
+        .map(|cache| cache.mappings.iter()
+            .filter(|((id, _), _)| *id == fs)
+            .map(|(key, mapping)| (*key, mapping.clone()))
+            .collect())
+        .unwrap_or_default();
+    
+    for (_, mapping) in &mappings {
+        mapping.sync_pages()?;
+    }
+    
+    if let Some(cache) = PAGE_CACHE.write().as_mut() {
+        for (key, mapping) in mappings {
+            cache.mappings.remove(&key);
+            cache.ids.remove(&mapping.id);
+        }
+    }
+    Ok(())
+}
+
+/// ページキャッシュを経由するファイルハンドル
+struct CachedFileHandle {
+    /// ファイルシステムのハンドル
+    inner: Arc<dyn FileHandle>,
+    /// アイノードのページ群
+    mapping: Arc<FileMapping>,
+    /// 常に末尾へ書き込むか
+    append: bool,
+}
+
+impl CachedFileHandle {
+    /// flockロックの所有者としてのオープンファイル記述
+    fn description(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl Drop for CachedFileHandle {
+    fn drop(&mut self) {
+        lock::release_description(self.mapping.id, self.description());
+        
+        if self.mapping.handles.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        
+        // 最後のハンドルが閉じたら書き出し、マップされていなければページを手放す
+        release_mapping(&self.mapping);
+    }
+}
+
+impl FileHandle for CachedFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        if !self.inner.can_read() {
+            return Err(FsError::PermissionDenied);
+        }
+        self.mapping.read(buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        if !self.inner.can_write() {
+            return Err(FsError::PermissionDenied);
+        }
+        balance_dirty_pages(&self.mapping)?;
+        
+        let offset = if self.append { self.mapping.size() } else { offset };
+        self.mapping.write(buffer, offset)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        self.mapping.writeback(None)?;
+        self.inner.flush()
+    }
+    
+    fn fsync(&self) -> FsResult<()> {
+        self.mapping.sync_pages()?;
+        self.inner.fsync()
+    }
+    
+    fn fdatasync(&self) -> FsResult<()> {
+        self.mapping.sync_pages()?;
+        self.inner.fdatasync()
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.mapping.size())
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        if !self.inner.can_write() {
+            return Err(FsError::PermissionDenied);
+        }
+        // 縮める前に残る範囲の変更を書き出し、ファイルシステム側のサイズと揃える
+        self.mapping.writeback(None)?;
+        self.mapping.resize(new_size)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        let mut metadata = self.inner.metadata()?;
+        metadata.size = self.mapping.size();
+        Ok(metadata)
+    }
+    
+    fn lock(&self, exclusive: bool) -> FsResult<()> {
+        lock::set_whole(self.mapping.id, self.description(), Some(LockKind::from_exclusive(exclusive)), true)
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        lock::set_whole(self.mapping.id, self.description(), None, false)
+    }
+    
+    fn try_lock(&self, exclusive: bool) -> FsResult<()> {
+        lock::set_whole(self.mapping.id, self.description(), Some(LockKind::from_exclusive(exclusive)), false)
+    }
+    
+    fn can_read(&self) -> bool {
+        self.inner.can_read()
+    }
+    
+    fn can_write(&self) -> bool {
+        self.inner.can_write()
+    }
+    
+    fn mapping_id(&self) -> Option<usize> {
+        Some(self.mapping.id)
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.inner.get_xattr(name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.inner.set_xattr(name, value)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.inner.list_xattr()
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.inner.remove_xattr(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Filesystem, OpenMode};
+    use super::super::tmpfs::TmpfsFilesystem;
+    
+    #[test]
+    fn writes_stay_cached_until_writeback() {
+        let fs = TmpfsFilesystem::new();
+        fs.mount("tmpfs", "/", "").unwrap();
+        let backing = fs.open_file("/", "/f", OpenMode::Create).unwrap();
+        backing.write(b"0123456789", 0).unwrap();
+        
+        let mapping = FileMapping::new(1, backing.clone()).unwrap();
+        mapping.write(b"ab", 2).unwrap();
+        mapping.write(b"z", PAGE_SIZE as u64 + 1).unwrap();
+        assert_eq!(mapping.size(), PAGE_SIZE as u64 + 2);
+        
+        // 部分書き込みは元の内容を読み込んでから上書きする
+        let mut buffer = [0u8; 6];
+        assert_eq!(mapping.read(&mut buffer, 0).unwrap(), 6);
+        assert_eq!(&buffer, b"01ab45");
+        assert_eq!(backing.size().unwrap(), 10);
+        
+        // mmapと共有するページの内容はread()と同じ
+        let page = mapping.page(0, true).unwrap();
+        let mapped = unsafe { core::slice::from_raw_parts(page.as_ptr(), 4) };
+        assert_eq!(mapped, b"01ab");
+        assert_eq!(page.as_ptr() as usize % PAGE_SIZE, 0);
+        drop(page);
+        
+        assert_eq!(mapping.writeback(None).unwrap(), 2);
+        assert_eq!(backing.size().unwrap(), PAGE_SIZE as u64 + 2);
+        let mut buffer = [0u8; 4];
+        backing.read(&mut buffer, 0).unwrap();
+        assert_eq!(&buffer, b"01ab");
+        assert_eq!(mapping.writeback(None).unwrap(), 0);
+        
+        mapping.resize(3).unwrap();
+        assert_eq!(mapping.evict_clean(8), 1);
+        assert_eq!(mapping.read(&mut buffer, 0).unwrap(), 3);
+        assert_eq!(&buffer[..3], b"01a");
+    }
+    
+    /// 外されたマッピングを記録するだけのページテーブル
+    struct RecordingMapper(Mutex<Vec<usize>>);
+    
+    impl PageMapper for RecordingMapper {
+        fn unmap_page(&self, vaddr: usize, _page: *const u8) {
+            self.0.lock().push(vaddr);
+        }
+    }
+    
+    #[test]
+    fn mapped_writes_are_redirtied_after_writeback() {
+        let fs = TmpfsFilesystem::new();
+        fs.mount("tmpfs", "/", "").unwrap();
+        let backing = fs.open_file("/", "/f", OpenMode::Create).unwrap();
+        backing.write(b"0123", 0).unwrap();
+        
+        let mapping = FileMapping::new(1, backing.clone()).unwrap();
+        let page = mapping.page(0, true).unwrap();
+        let table = Arc::new(RecordingMapper(Mutex::new(Vec::new())));
+        let mapper: Arc<dyn PageMapper> = table.clone();
+        
+        // 読み込みフォルトでは読み取り専用でマップするのでダーティにならない
+        page.map_into(&mapper, 0x1000, false);
+        assert!(!page.is_dirty());
+        
+        // 書き出しで書き込み可能なマッピングを外すので、2回目の書き込みもフォルトを経てダーティになる
+        let mut buffer = [0u8; 4];
+        for data in [b"ab", b"cd"] {
+            page.map_into(&mapper, 0x1000, true);
+            page.frame.write().0[..2].copy_from_slice(data);
+            assert_eq!(mapping.writeback(None).unwrap(), 1);
+            backing.read(&mut buffer, 0).unwrap();
+            assert_eq!(&buffer[..2], data);
+        }
+        assert_eq!(*table.0.lock(), [0x1000, 0x1000]);
+        
+        // 最後のマッピングが外れるまでは追い出さない
+        page.map_into(&mapper, 0x2000, false);
+        drop(page);
+        assert_eq!(mapping.evict_clean(8), 0);
+        mapping.pages.read()[&0].unmap_from(&mapper, 0x2000);
+        assert_eq!(mapping.evict_clean(8), 1);
+    }
+}
@@ -0,0 +1,198 @@
+// GPTパーティションテーブル
+//
+// LBA 1のプライマリヘッダとエントリ配列をCRC32で検証し、壊れていれば
+// ディスク末尾のバックアップヘッダを使う
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, BlockDevice};
+use super::super::crc32::crc32;
+use super::{Guid, Partition, PartitionType};
+
+/// ヘッダのシグネチャ
+const SIGNATURE: &[u8; 8] = b"EFI PART";
+/// ヘッダの最小サイズ
+const MIN_HEADER_SIZE: usize = 92;
+/// エントリの最小サイズ
+const MIN_ENTRY_SIZE: usize = 128;
+/// エントリ配列の最大サイズ（壊れたヘッダで巨大な読み込みをしないため）
+const MAX_ENTRY_ARRAY_SIZE: usize = 1024 * 1024;
+/// レガシーBIOSブート可能属性
+const ATTRIBUTE_LEGACY_BOOTABLE: u64 = 1 << 2;
+
+/// リトルエンディアンのu32を読む
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// リトルエンディアンのu64を読む
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// GUIDを読む
+fn read_guid(data: &[u8], offset: usize) -> Guid {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&data[offset..offset + 16]);
+    Guid(bytes)
+}
+
+/// 検証済みのGPTヘッダ
+#[derive(Debug, Clone)]
+struct GptHeader {
+    /// もう一方のヘッダのLBA
+    alternate_lba: u64,
+    /// パーティションに使える最初のLBA
+    first_usable_lba: u64,
+    /// パーティションに使える最後のLBA
+    last_usable_lba: u64,
+    /// エントリ配列の開始LBA
+    entries_lba: u64,
+    /// エントリ数
+    entry_count: u32,
+    /// エントリのサイズ
+    entry_size: u32,
+    /// エントリ配列のCRC32
+    entries_crc32: u32,
+}
+
+impl GptHeader {
+    /// `lba`のヘッダを読んで検証
+    fn read(device: &dyn BlockDevice, lba: u64) -> FsResult<Self> {
+        let block = device.read_block(lba)?;
+        if block.len() < MIN_HEADER_SIZE || &block[0..8] != SIGNATURE {
+            return Err(FsError::BadMagic);
+        }
+        
+        let header_size = read_u32(&block, 12) as usize;
+        if header_size < MIN_HEADER_SIZE || header_size > block.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        let mut header = block[..header_size].to_vec();
+        header[16..20].fill(0);
+        if !crc32(!0, &header) != read_u32(&block, 16) {
+            return Err(FsError::ChecksumError);
+        }
+        if read_u64(&block, 24) != lba {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let header = Self {
+            alternate_lba: read_u64(&block, 32),
+            first_usable_lba: read_u64(&block, 40),
+            last_usable_lba: read_u64(&block, 48),
+            entries_lba: read_u64(&block, 72),
+            entry_count: read_u32(&block, 80),
+            entry_size: read_u32(&block, 84),
+            entries_crc32: read_u32(&block, 88),
+        };
+        let array_size = header.entry_count as usize * header.entry_size as usize;
+        if (header.entry_size as usize) < MIN_ENTRY_SIZE
+            || !header.entry_size.is_multiple_of(8)
+            || array_size > MAX_ENTRY_ARRAY_SIZE
+            || header.first_usable_lba > header.last_usable_lba
+        {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(header)
+    }
+    
+    /// エントリ配列を読んで検証し、パーティションに変換
+    fn read_entries(&self, device: &dyn BlockDevice) -> FsResult<Vec<Partition>> {
+        let array_size = self.entry_count as usize * self.entry_size as usize;
+        let blocks = (array_size as u64).div_ceil(device.block_size());
+        let data = device.read_blocks(self.entries_lba, blocks)?;
+        if data.len() < array_size || !crc32(!0, &data[..array_size]) != self.entries_crc32 {
+            return Err(FsError::ChecksumError);
+        }
+        
+        let mut partitions = Vec::new();
+        for (index, entry) in data[..array_size].chunks_exact(self.entry_size as usize).enumerate() {
+            let type_guid = read_guid(entry, 0);
+            if type_guid == Guid::ZERO {
+                continue;
+            }
+            let first_lba = read_u64(entry, 32);
+            let last_lba = read_u64(entry, 40);
+            if first_lba < self.first_usable_lba || last_lba > self.last_usable_lba || first_lba > last_lba {
+                log::warn!("GPTエントリ{}が使用可能な範囲外のため無視します", index);
+                continue;
+            }
+            
+            // 名前はUTF-16LEで最大36文字、NULで終わる
+            let units: Vec<u16> = entry[56..128].chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            partitions.push(Partition {
+                number: index as u32 + 1,
+                first_block: first_lba,
+                block_count: last_lba - first_lba + 1,
+                partition_type: PartitionType::Gpt(type_guid),
+                unique_guid: Some(read_guid(entry, 16)),
+                name: String::from_utf16_lossy(&units),
+                bootable: read_u64(entry, 48) & ATTRIBUTE_LEGACY_BOOTABLE != 0,
+            });
+        }
+        Ok(partitions)
+    }
+}
+
+/// GPTを読む（プライマリが壊れていればバックアップを使う）
+pub fn read(device: &dyn BlockDevice) -> FsResult<Vec<Partition>> {
+    let primary_error = match GptHeader::read(device, 1).and_then(|header| header.read_entries(device)) {
+        Ok(partitions) => return Ok(partitions),
+        Err(e) => e,
+    };
+    
+    // バックアップヘッダは通常ディスクの最終LBAにある
+    let last_lba = device.total_blocks().saturating_sub(1);
+    let backup = GptHeader::read(device, last_lba)?;
+    let partitions = backup.read_entries(device)?;
+    if backup.alternate_lba != 1 {
+        log::warn!("GPTバックアップヘッダのプライマリ位置が不正です: LBA {}", backup.alternate_lba);
+    }
+    log::warn!("GPTプライマリヘッダが壊れているためバックアップを使用します: {:?}", primary_error);
+    Ok(partitions)
+}
+
+/// テスト用に、プライマリとバックアップのGPTを書き込む（エントリは(タイプ, 固有GUID, 開始, 終了, 名前)）
+#[cfg(test)]
+pub fn write_test_table(image: &mut [u8], blocks: u64, entries: &[(Guid, Guid, u64, u64, &str)]) {
+    let mut array = alloc::vec![0u8; 128 * 128];
+    for (index, (type_guid, unique_guid, first, last, name)) in entries.iter().enumerate() {
+        let entry = &mut array[index * 128..(index + 1) * 128];
+        entry[0..16].copy_from_slice(&type_guid.0);
+        entry[16..32].copy_from_slice(&unique_guid.0);
+        entry[32..40].copy_from_slice(&first.to_le_bytes());
+        entry[40..48].copy_from_slice(&last.to_le_bytes());
+        for (i, unit) in name.encode_utf16().enumerate() {
+            entry[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+    let array_crc = !crc32(!0, &array);
+    
+    for (lba, alternate, entries_lba) in [(1, blocks - 1, 2), (blocks - 1, 1, blocks - 33)] {
+        let mut header = [0u8; 92];
+        header[0..8].copy_from_slice(SIGNATURE);
+        header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        header[12..16].copy_from_slice(&92u32.to_le_bytes());
+        header[24..32].copy_from_slice(&lba.to_le_bytes());
+        header[32..40].copy_from_slice(&alternate.to_le_bytes());
+        header[40..48].copy_from_slice(&34u64.to_le_bytes());
+        header[48..56].copy_from_slice(&(blocks - 34).to_le_bytes());
+        header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        header[80..84].copy_from_slice(&128u32.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+        header[88..92].copy_from_slice(&array_crc.to_le_bytes());
+        let crc = !crc32(!0, &header);
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+        
+        let offset = lba as usize * 512;
+        image[offset..offset + 92].copy_from_slice(&header);
+        let offset = entries_lba as usize * 512;
+        image[offset..offset + array.len()].copy_from_slice(&array);
+    }
+}
@@ -0,0 +1,146 @@
+// MBRパーティションテーブル
+//
+// LBA 0の基本パーティション4つと、拡張パーティション内のEBRチェーンで
+// つながった論理パーティションを読む
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsResult, BlockDevice};
+use super::{Partition, PartitionType};
+
+/// パーティションテーブルの位置
+const TABLE_OFFSET: usize = 446;
+/// エントリのサイズ
+const ENTRY_SIZE: usize = 16;
+/// GPTの保護MBRのシステムID
+const PROTECTIVE_TYPE: u8 = 0xEE;
+/// 論理パーティションの最大数（EBRチェーンの循環対策）
+const MAX_LOGICAL_PARTITIONS: u32 = 128;
+
+/// MBRのエントリ
+#[derive(Debug, Clone, Copy)]
+pub struct MbrEntry {
+    /// アクティブ（0x80）か
+    pub bootable: bool,
+    /// システムID
+    pub kind: u8,
+    /// 開始LBA（テーブルの位置からの相対値）
+    pub start: u32,
+    /// セクタ数
+    pub sectors: u32,
+}
+
+impl MbrEntry {
+    /// 拡張パーティションか
+    fn is_extended(&self) -> bool {
+        matches!(self.kind, 0x05 | 0x0F | 0x85)
+    }
+    
+    /// 使われているエントリか
+    fn is_used(&self) -> bool {
+        self.kind != 0 && self.sectors != 0
+    }
+}
+
+/// MBRまたはEBRのパーティションテーブル
+#[derive(Debug, Clone)]
+pub struct MbrTable {
+    /// 4つのエントリ
+    pub entries: [MbrEntry; 4],
+}
+
+impl MbrTable {
+    /// GPTの保護MBRか
+    pub fn is_protective(&self) -> bool {
+        self.entries.iter().any(|entry| entry.kind == PROTECTIVE_TYPE)
+    }
+}
+
+/// セクタをパーティションテーブルとして解釈（テーブルでなければNone）
+pub fn parse(sector: &[u8]) -> FsResult<Option<MbrTable>> {
+    if sector.len() < 512 || sector[510] != 0x55 || sector[511] != 0xAA {
+        return Ok(None);
+    }
+    
+    // パーティションされていないFAT/NTFS/exFATのブートセクタも0x55AAで終わる
+    if &sector[3..11] == b"NTFS    " || &sector[3..11] == b"EXFAT   " || &sector[0x36..0x39] == b"FAT" || &sector[0x52..0x57] == b"FAT32" {
+        return Ok(None);
+    }
+    
+    let mut entries = [MbrEntry { bootable: false, kind: 0, start: 0, sectors: 0 }; 4];
+    for (slot, entry) in entries.iter_mut().enumerate() {
+        let raw = &sector[TABLE_OFFSET + slot * ENTRY_SIZE..TABLE_OFFSET + (slot + 1) * ENTRY_SIZE];
+        // ブートフラグは0x00か0x80のみ
+        if raw[0] & 0x7F != 0 {
+            return Ok(None);
+        }
+        *entry = MbrEntry {
+            bootable: raw[0] == 0x80,
+            kind: raw[4],
+            start: u32::from_le_bytes([raw[8], raw[9], raw[10], raw[11]]),
+            sectors: u32::from_le_bytes([raw[12], raw[13], raw[14], raw[15]]),
+        };
+    }
+    Ok(Some(MbrTable { entries }))
+}
+
+/// 基本パーティションと論理パーティションを読む
+pub fn read(device: &dyn BlockDevice, table: &MbrTable) -> FsResult<Vec<Partition>> {
+    let mut partitions = Vec::new();
+    for (slot, entry) in table.entries.iter().enumerate() {
+        if !entry.is_used() {
+            continue;
+        }
+        if entry.is_extended() {
+            read_logical(device, entry.start as u64, &mut partitions)?;
+        } else {
+            partitions.push(partition(slot as u32 + 1, 0, entry));
+        }
+    }
+    partitions.sort_by_key(|partition| partition.number);
+    Ok(partitions)
+}
+
+/// 拡張パーティション内のEBRチェーンをたどる
+fn read_logical(device: &dyn BlockDevice, extended_start: u64, partitions: &mut Vec<Partition>) -> FsResult<()> {
+    let mut visited = BTreeSet::new();
+    let mut ebr = extended_start;
+    let mut number = 5;
+    
+    while number < 5 + MAX_LOGICAL_PARTITIONS && visited.insert(ebr) {
+        let table = match parse(&device.read_block(ebr)?)? {
+            Some(table) => table,
+            None => {
+                log::warn!("LBA {}のEBRが壊れているため論理パーティションの読み込みを中断します", ebr);
+                break;
+            },
+        };
+        
+        // 1つ目は論理パーティション（EBRからの相対）、2つ目は次のEBR（拡張パーティション先頭からの相対）
+        let logical = &table.entries[0];
+        if logical.is_used() {
+            partitions.push(partition(number, ebr, logical));
+            number += 1;
+        }
+        let next = &table.entries[1];
+        if !next.is_used() || !next.is_extended() {
+            break;
+        }
+        ebr = extended_start + next.start as u64;
+    }
+    Ok(())
+}
+
+/// エントリをパーティションに変換
+fn partition(number: u32, base: u64, entry: &MbrEntry) -> Partition {
+    Partition {
+        number,
+        first_block: base + entry.start as u64,
+        block_count: entry.sectors as u64,
+        partition_type: PartitionType::Mbr(entry.kind),
+        unique_guid: None,
+        name: String::new(),
+        bootable: entry.bootable,
+    }
+}
@@ -0,0 +1,270 @@
+// パーティション層
+//
+// ディスク先頭のMBR/GPTを読み、各パーティションをディスクの一部だけを見せる
+// ブロックデバイスとして切り出す。devfsはディスクの登録時にこれを使って
+// "sda1"や"nvme0n1p1"のノードを作る
+
+mod gpt;
+mod mbr;
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use super::{FsError, FsResult, BlockDevice};
+
+/// GPTのGUID（ディスク上の混合エンディアンのまま保持する）
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// すべて0のGUID（未使用エントリ）
+    pub const ZERO: Guid = Guid([0; 16]);
+    
+    /// 文字列表記の各フィールドから作成
+    pub const fn from_fields(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        let a = data1.to_le_bytes();
+        let b = data2.to_le_bytes();
+        let c = data3.to_le_bytes();
+        Guid([
+            a[0], a[1], a[2], a[3], b[0], b[1], c[0], c[1],
+            data4[0], data4[1], data4[2], data4[3], data4[4], data4[5], data4[6], data4[7],
+        ])
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let g = &self.0;
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+            u32::from_le_bytes([g[0], g[1], g[2], g[3]]),
+            u16::from_le_bytes([g[4], g[5]]),
+            u16::from_le_bytes([g[6], g[7]]),
+            g[8], g[9], g[10], g[11], g[12], g[13], g[14], g[15],
+        )
+    }
+}
+
+/// EFIシステムパーティション
+pub const GPT_EFI_SYSTEM: Guid = Guid::from_fields(0xC12A7328, 0xF81F, 0x11D2, [0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B]);
+/// Microsoft基本データ（NTFS/exFAT/FAT）
+pub const GPT_MICROSOFT_BASIC_DATA: Guid = Guid::from_fields(0xEBD0A0A2, 0xB9E5, 0x4433, [0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7]);
+/// Linuxファイルシステムデータ
+pub const GPT_LINUX_FILESYSTEM: Guid = Guid::from_fields(0x0FC63DAF, 0x8483, 0x4772, [0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D, 0xE4]);
+/// Linuxスワップ
+pub const GPT_LINUX_SWAP: Guid = Guid::from_fields(0x0657FD6D, 0xA4AB, 0x43C4, [0x84, 0xE5, 0x09, 0x33, 0xC8, 0x4B, 0x4F, 0x4F]);
+/// Apple APFSコンテナ
+pub const GPT_APPLE_APFS: Guid = Guid::from_fields(0x7C3457EF, 0x0000, 0x11AA, [0xAA, 0x11, 0x00, 0x30, 0x65, 0x43, 0xEC, 0xAC]);
+/// Apple HFS+
+pub const GPT_APPLE_HFS: Guid = Guid::from_fields(0x48465300, 0x0000, 0x11AA, [0xAA, 0x11, 0x00, 0x30, 0x65, 0x43, 0xEC, 0xAC]);
+
+/// パーティションの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    /// GPTのパーティションタイプGUID
+    Gpt(Guid),
+    /// MBRのシステムID
+    Mbr(u8),
+}
+
+impl PartitionType {
+    /// 中身として考えられるファイルシステム名（VFSに登録された名前、可能性の高い順）
+    pub fn filesystem_candidates(&self) -> &'static [&'static str] {
+        match *self {
+            PartitionType::Gpt(GPT_EFI_SYSTEM) => &["vfat"],
+            PartitionType::Gpt(GPT_MICROSOFT_BASIC_DATA) => &["ntfs", "exfat", "vfat"],
+            PartitionType::Gpt(GPT_LINUX_FILESYSTEM) => &["ext4", "xfs", "btrfs", "f2fs"],
+            PartitionType::Gpt(GPT_APPLE_APFS) => &["apfs"],
+            PartitionType::Gpt(GPT_APPLE_HFS) => &["hfsplus"],
+            PartitionType::Mbr(0x01 | 0x04 | 0x06 | 0x0B | 0x0C | 0x0E | 0xEF) => &["vfat"],
+            PartitionType::Mbr(0x07) => &["ntfs", "exfat"],
+            PartitionType::Mbr(0x83) => &["ext4", "xfs", "btrfs", "f2fs"],
+            PartitionType::Mbr(0x81) => &["minix"],
+            PartitionType::Mbr(0xAF) => &["hfsplus"],
+            _ => &[],
+        }
+    }
+}
+
+/// パーティションテーブルのエントリ
+#[derive(Debug, Clone)]
+pub struct Partition {
+    /// パーティション番号（GPTはエントリ番号+1、MBRは基本1〜4・論理5〜）
+    pub number: u32,
+    /// 開始LBA（デバイスのブロック単位）
+    pub first_block: u64,
+    /// ブロック数
+    pub block_count: u64,
+    /// 種類
+    pub partition_type: PartitionType,
+    /// GPTのパーティション固有GUID
+    pub unique_guid: Option<Guid>,
+    /// GPTのパーティション名
+    pub name: String,
+    /// ブート可能フラグ（MBRのアクティブ、GPTのレガシーBIOSブート属性）
+    pub bootable: bool,
+}
+
+/// ディスクのパーティションテーブルを読む（テーブルがなければ空）
+pub fn read_partitions(device: &dyn BlockDevice) -> FsResult<Vec<Partition>> {
+    let sector = device.read_block(0)?;
+    let table = match mbr::parse(&sector)? {
+        Some(table) => table,
+        None => return Ok(Vec::new()),
+    };
+    
+    let partitions = if table.is_protective() {
+        gpt::read(device)?
+    } else {
+        mbr::read(device, &table)?
+    };
+    
+    // ディスクからはみ出すエントリは使わない
+    let total_blocks = device.total_blocks();
+    Ok(partitions.into_iter()
+        .filter(|partition| {
+            let valid = partition.block_count > 0
+                && partition.first_block.checked_add(partition.block_count).is_some_and(|end| end <= total_blocks);
+            if !valid {
+                log::warn!("パーティション{}がディスクの範囲外のため無視します", partition.number);
+            }
+            valid
+        })
+        .collect())
+}
+
+/// ディスクの一部をブロックデバイスとして見せるパーティション
+pub struct PartitionDevice {
+    /// ディスク本体
+    disk: Arc<dyn BlockDevice>,
+    /// 開始ブロック
+    first_block: u64,
+    /// ブロック数
+    block_count: u64,
+}
+
+impl PartitionDevice {
+    /// ディスクの`first_block`から`block_count`ブロックを切り出す
+    pub fn new(disk: Arc<dyn BlockDevice>, first_block: u64, block_count: u64) -> FsResult<Self> {
+        if first_block.checked_add(block_count).is_none_or(|end| end > disk.total_blocks()) {
+            return Err(FsError::InvalidData);
+        }
+        Ok(Self { disk, first_block, block_count })
+    }
+    
+    /// パーティション内の範囲をディスク上のブロック番号に変換
+    fn translate(&self, block_index: u64, count: u64) -> FsResult<u64> {
+        match block_index.checked_add(count) {
+            Some(end) if end <= self.block_count => Ok(self.first_block + block_index),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+}
+
+impl BlockDevice for PartitionDevice {
+    fn device_id(&self) -> u64 {
+        self.disk.device_id() ^ (self.first_block << 20)
+    }
+    
+    fn block_size(&self) -> u64 {
+        self.disk.block_size()
+    }
+    
+    fn total_blocks(&self) -> u64 {
+        self.block_count
+    }
+    
+    fn read_block(&self, block_index: u64) -> FsResult<Vec<u8>> {
+        self.disk.read_block(self.translate(block_index, 1)?)
+    }
+    
+    fn read_blocks(&self, start_block: u64, count: u64) -> FsResult<Vec<u8>> {
+        self.disk.read_blocks(self.translate(start_block, count)?, count)
+    }
+    
+    fn write_block(&self, block_index: u64, data: &[u8]) -> FsResult<()> {
+        self.disk.write_block(self.translate(block_index, 1)?, data)
+    }
+    
+    fn write_blocks(&self, start_block: u64, data: &[u8]) -> FsResult<()> {
+        let count = (data.len() as u64).div_ceil(self.block_size());
+        self.disk.write_blocks(self.translate(start_block, count)?, data)
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        self.disk.sync()
+    }
+    
+    fn close(&self) -> FsResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use super::super::memory_disk::MemoryDisk;
+    
+    /// MBRのエントリを書き込む
+    fn put_mbr_entry(sector: &mut [u8], slot: usize, status: u8, kind: u8, start: u32, sectors: u32) {
+        let entry = &mut sector[446 + slot * 16..446 + (slot + 1) * 16];
+        entry[0] = status;
+        entry[4] = kind;
+        entry[8..12].copy_from_slice(&start.to_le_bytes());
+        entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+        sector[510] = 0x55;
+        sector[511] = 0xAA;
+    }
+    
+    #[test]
+    fn gpt_with_backup_fallback_and_extended_mbr() {
+        // 保護MBR + GPT（エントリ1つ）を作り、プライマリヘッダを壊す
+        let blocks = 256u64;
+        let disk = MemoryDisk::new(vec![0; blocks as usize * 512]);
+        let mut image = vec![0u8; blocks as usize * 512];
+        put_mbr_entry(&mut image[..512], 0, 0, 0xEE, 1, (blocks - 1) as u32);
+        let unique = Guid::from_fields(1, 2, 3, [4; 8]);
+        gpt::write_test_table(&mut image, blocks, &[(GPT_LINUX_FILESYSTEM, unique, 40, 99, "root")]);
+        image[512 + 24] ^= 0xFF;
+        disk.write_blocks(0, &image).unwrap();
+        
+        let partitions = read_partitions(&disk).unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!((partitions[0].number, partitions[0].first_block, partitions[0].block_count), (1, 40, 60));
+        assert_eq!(partitions[0].unique_guid, Some(unique));
+        assert_eq!(partitions[0].name, "root");
+        assert_eq!(partitions[0].partition_type.filesystem_candidates()[0], "ext4");
+        assert_eq!(format!("{}", GPT_EFI_SYSTEM), "C12A7328-F81F-11D2-BA4B-00A0C93EC93B");
+        
+        // 基本パーティション1つ + 拡張パーティション内の論理パーティション2つ
+        let mut image = vec![0u8; blocks as usize * 512];
+        put_mbr_entry(&mut image[..512], 0, 0x80, 0x0C, 2, 30);
+        put_mbr_entry(&mut image[..512], 1, 0, 0x05, 100, 100);
+        put_mbr_entry(&mut image[100 * 512..101 * 512], 0, 0, 0x83, 1, 20);
+        put_mbr_entry(&mut image[100 * 512..101 * 512], 1, 0, 0x05, 50, 50);
+        put_mbr_entry(&mut image[150 * 512..151 * 512], 0, 0, 0x07, 2, 10);
+        disk.write_blocks(0, &image).unwrap();
+        
+        let partitions = read_partitions(&disk).unwrap();
+        let layout: Vec<(u32, u64, u64, bool)> = partitions.iter()
+            .map(|p| (p.number, p.first_block, p.block_count, p.bootable))
+            .collect();
+        assert_eq!(layout, vec![(1, 2, 30, true), (5, 101, 20, false), (6, 152, 10, false)]);
+        assert_eq!(partitions[2].partition_type, PartitionType::Mbr(0x07));
+        
+        // パーティションデバイスは範囲外のブロックを拒否する
+        let disk: Arc<dyn BlockDevice> = Arc::new(disk);
+        let device = PartitionDevice::new(disk, 101, 20).unwrap();
+        assert!(device.read_block(19).is_ok());
+        assert!(device.read_blocks(19, 2).is_err());
+    }
+}
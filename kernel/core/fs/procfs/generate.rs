@@ -0,0 +1,87 @@
+// procfs ファイル内容の生成
+//
+// スナップショットをLinuxの/procと同じテキスト形式に変換する
+
+use alloc::string::String;
+use core::fmt::Write;
+use super::super::vfs::MountInfo;
+use super::source::{ProcessSnapshot, MemorySnapshot, LoadSnapshot};
+
+/// /proc/<pid>/status
+pub fn status(process: &ProcessSnapshot) -> String {
+    let vm_size: usize = process.vmas.iter().map(|vma| vma.end - vma.start).sum();
+    let mut text = String::new();
+    let _ = writeln!(text, "Name:\t{}", process.name);
+    let _ = writeln!(text, "State:\t{}", process.state);
+    let _ = writeln!(text, "Pid:\t{}", process.pid.0);
+    let _ = writeln!(text, "PPid:\t{}", process.parent.0);
+    let _ = writeln!(text, "Threads:\t{}", process.threads);
+    let _ = writeln!(text, "VmSize:\t{:>8} kB", vm_size / 1024);
+    text
+}
+
+/// /proc/<pid>/maps
+pub fn maps(process: &ProcessSnapshot) -> String {
+    let mut text = String::new();
+    for vma in &process.vmas {
+        let _ = writeln!(
+            text,
+            "{:08x}-{:08x} {}{}{}{} {:08x} {}",
+            vma.start,
+            vma.end,
+            if vma.read { 'r' } else { '-' },
+            if vma.write { 'w' } else { '-' },
+            if vma.execute { 'x' } else { '-' },
+            if vma.shared { 's' } else { 'p' },
+            vma.offset,
+            vma.name,
+        );
+    }
+    text
+}
+
+/// /proc/meminfo
+pub fn meminfo(memory: &MemorySnapshot) -> String {
+    let mut text = String::new();
+    let mut line = |name: &str, bytes: usize| {
+        let _ = writeln!(text, "{:<16}{:>10} kB", format!("{}:", name), bytes / 1024);
+    };
+    line("MemTotal", memory.total);
+    line("MemFree", memory.total.saturating_sub(memory.used));
+    line("MemAvailable", memory.available);
+    line("Buffers", memory.buffers);
+    line("Cached", memory.cached);
+    line("Dirty", memory.dirty);
+    line("SwapTotal", memory.swap_total);
+    line("SwapFree", memory.swap_total.saturating_sub(memory.swap_used));
+    let _ = writeln!(text, "{:<16}{:>10}", "BlockCache:", memory.block_cache_blocks);
+    let _ = writeln!(text, "{:<16}{:>10}", "Dentries:", memory.dentries);
+    text
+}
+
+/// /proc/loadavg
+pub fn loadavg(load: &LoadSnapshot) -> String {
+    let (one, five, fifteen) = load.load;
+    format!("{:.2} {:.2} {:.2} {}/{} {}\n", one, five, fifteen, load.running, load.threads, load.last_pid.0)
+}
+
+/// /proc/stat
+pub fn stat(load: &LoadSnapshot) -> String {
+    format!("ctxt {}\nprocesses {}\nprocs_running {}\n", load.context_switches, load.forks, load.processes)
+}
+
+/// /proc/uptime（アイドル時間は集計していないため0）
+pub fn uptime(uptime_ns: u64) -> String {
+    let centiseconds = uptime_ns / 10_000_000;
+    format!("{}.{:02} 0.00\n", centiseconds / 100, centiseconds % 100)
+}
+
+/// /proc/mounts
+pub fn mounts(mounts: &[MountInfo]) -> String {
+    let mut text = String::new();
+    for mount in mounts {
+        let options = if mount.options.is_empty() { "rw" } else { mount.options.as_str() };
+        let _ = writeln!(text, "{} {} {} {} 0 0", mount.device, mount.path, mount.fs_type, options);
+    }
+    text
+}
@@ -0,0 +1,519 @@
+// procfs 実装
+//
+// プロセス・メモリ・スケジューラの状態を読み取り専用のファイルとして公開する
+// 仮想ファイルシステム。ファイルの内容は読み込むたびに生成する
+
+mod generate;
+mod source;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::{Mutex, RwLock};
+use crate::core::process::ProcessId;
+use super::{FsError, FsResult, Metadata, FsStats, FileType, Permissions, OpenMode, InodeNum};
+use super::{Filesystem, FileHandle, DirHandle, DirEntry};
+
+/// ルートディレクトリのアイノード番号
+const ROOT_INODE: InodeNum = 1;
+
+/// プロセスごとのアイノード番号の幅（下位ビットでディレクトリ内の要素を区別する）
+const PID_INODE_SHIFT: u32 = 16;
+
+/// fdディレクトリ内のエントリのアイノード番号の開始値
+const FD_INODE_BASE: u64 = 0x100;
+
+/// ルート直下のファイル
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlobalFile {
+    /// メモリの使用状況
+    Meminfo,
+    /// 負荷平均
+    Loadavg,
+    /// スケジューラの統計
+    Stat,
+    /// 起動からの経過時間
+    Uptime,
+    /// マウントの一覧
+    Mounts,
+}
+
+impl GlobalFile {
+    /// すべてのファイル
+    const ALL: [GlobalFile; 5] = [GlobalFile::Meminfo, GlobalFile::Loadavg, GlobalFile::Stat, GlobalFile::Uptime, GlobalFile::Mounts];
+    
+    /// ファイル名
+    fn name(self) -> &'static str {
+        match self {
+            GlobalFile::Meminfo => "meminfo",
+            GlobalFile::Loadavg => "loadavg",
+            GlobalFile::Stat => "stat",
+            GlobalFile::Uptime => "uptime",
+            GlobalFile::Mounts => "mounts",
+        }
+    }
+}
+
+/// プロセスのディレクトリ内のファイル
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessFile {
+    /// 状態
+    Status,
+    /// 仮想メモリ領域
+    Maps,
+}
+
+impl ProcessFile {
+    /// すべてのファイル
+    const ALL: [ProcessFile; 2] = [ProcessFile::Status, ProcessFile::Maps];
+    
+    /// ファイル名
+    fn name(self) -> &'static str {
+        match self {
+            ProcessFile::Status => "status",
+            ProcessFile::Maps => "maps",
+        }
+    }
+}
+
+/// procfs内の要素
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcNode {
+    /// ルートディレクトリ
+    Root,
+    /// 現在のプロセスのディレクトリへのリンク（"self"）
+    SelfLink,
+    /// ルート直下のファイル
+    Global(GlobalFile),
+    /// プロセスのディレクトリ
+    Process(ProcessId),
+    /// プロセスのディレクトリ内のファイル
+    ProcessFile(ProcessId, ProcessFile),
+    /// 作業ディレクトリへのリンク（"cwd"）
+    Cwd(ProcessId),
+    /// 開いているファイルディスクリプタのディレクトリ（"fd"）
+    FdDir(ProcessId),
+    /// ファイルディスクリプタが開いているパスへのリンク
+    Fd(ProcessId, usize),
+}
+
+impl ProcNode {
+    /// パスを要素に変換（存在しないプロセスやディスクリプタはNotFound）
+    fn parse(path: &str) -> FsResult<Self> {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        let node = match components.as_slice() {
+            [] => ProcNode::Root,
+            ["self"] => ProcNode::SelfLink,
+            [name] if name.starts_with(|c: char| c.is_ascii_digit()) => ProcNode::Process(parse_pid(name)?),
+            [name] => GlobalFile::ALL.iter()
+                .find(|file| file.name() == *name)
+                .map(|file| ProcNode::Global(*file))
+                .ok_or(FsError::NotFound)?,
+            [pid, "cwd"] => ProcNode::Cwd(parse_pid(pid)?),
+            [pid, "fd"] => ProcNode::FdDir(parse_pid(pid)?),
+            [pid, "fd", fd] => {
+                let pid = parse_pid(pid)?;
+                let fd = fd.parse().map_err(|_| FsError::NotFound)?;
+                if !source::descriptors(pid).iter().any(|(open, _)| *open == fd) {
+                    return Err(FsError::NotFound);
+                }
+                ProcNode::Fd(pid, fd)
+            },
+            [pid, name] => {
+                let pid = parse_pid(pid)?;
+                ProcessFile::ALL.iter()
+                    .find(|file| file.name() == *name)
+                    .map(|file| ProcNode::ProcessFile(pid, *file))
+                    .ok_or(FsError::NotFound)?
+            },
+            _ => return Err(FsError::NotFound),
+        };
+        Ok(node)
+    }
+    
+    /// アイノード番号
+    fn inode(self) -> InodeNum {
+        let pid_base = |pid: ProcessId| (pid.0 as u64 + 1) << PID_INODE_SHIFT;
+        match self {
+            ProcNode::Root => ROOT_INODE,
+            ProcNode::SelfLink => ROOT_INODE + 1,
+            ProcNode::Global(file) => ROOT_INODE + 2 + file as u64,
+            ProcNode::Process(pid) => pid_base(pid),
+            ProcNode::ProcessFile(pid, file) => pid_base(pid) + 1 + file as u64,
+            ProcNode::Cwd(pid) => pid_base(pid) + 0x10,
+            ProcNode::FdDir(pid) => pid_base(pid) + 0x11,
+            ProcNode::Fd(pid, fd) => pid_base(pid) + FD_INODE_BASE + fd as u64,
+        }
+    }
+    
+    /// ファイルタイプ
+    fn file_type(self) -> FileType {
+        match self {
+            ProcNode::Root | ProcNode::Process(_) | ProcNode::FdDir(_) => FileType::Directory,
+            ProcNode::SelfLink | ProcNode::Cwd(_) | ProcNode::Fd(..) => FileType::SymbolicLink,
+            ProcNode::Global(_) | ProcNode::ProcessFile(..) => FileType::Regular,
+        }
+    }
+    
+    /// ディレクトリエントリ
+    fn entry(self, name: &str) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            inode: self.inode(),
+            file_type: self.file_type(),
+        }
+    }
+    
+    /// ディレクトリ内の要素
+    fn children(self) -> FsResult<Vec<DirEntry>> {
+        let entries = match self {
+            ProcNode::Root => {
+                let mut entries: Vec<DirEntry> = GlobalFile::ALL.iter()
+                    .map(|file| ProcNode::Global(*file).entry(file.name()))
+                    .collect();
+                if source::current_pid().is_some() {
+                    entries.push(ProcNode::SelfLink.entry("self"));
+                }
+                entries.extend(source::process_ids().into_iter()
+                    .map(|pid| ProcNode::Process(pid).entry(&pid.0.to_string())));
+                entries
+            },
+            ProcNode::Process(pid) => {
+                let mut entries: Vec<DirEntry> = ProcessFile::ALL.iter()
+                    .map(|file| ProcNode::ProcessFile(pid, *file).entry(file.name()))
+                    .collect();
+                entries.push(ProcNode::Cwd(pid).entry("cwd"));
+                entries.push(ProcNode::FdDir(pid).entry("fd"));
+                entries
+            },
+            ProcNode::FdDir(pid) => source::descriptors(pid).into_iter()
+                .map(|(fd, _)| ProcNode::Fd(pid, fd).entry(&fd.to_string()))
+                .collect(),
+            _ => return Err(FsError::NotDirectory),
+        };
+        Ok(entries)
+    }
+    
+    /// ファイルの内容を生成
+    fn contents(self) -> FsResult<String> {
+        let text = match self {
+            ProcNode::Global(GlobalFile::Meminfo) => generate::meminfo(&source::memory()),
+            ProcNode::Global(GlobalFile::Loadavg) => generate::loadavg(&source::load()),
+            ProcNode::Global(GlobalFile::Stat) => generate::stat(&source::load()),
+            ProcNode::Global(GlobalFile::Uptime) => generate::uptime(source::uptime_ns()),
+            ProcNode::Global(GlobalFile::Mounts) => generate::mounts(&source::mounts()),
+            ProcNode::ProcessFile(pid, file) => {
+                // 開いた後にプロセスが終了していればNotFound
+                let process = source::process(pid).ok_or(FsError::NotFound)?;
+                match file {
+                    ProcessFile::Status => generate::status(&process),
+                    ProcessFile::Maps => generate::maps(&process),
+                }
+            },
+            ProcNode::Root | ProcNode::Process(_) | ProcNode::FdDir(_) => return Err(FsError::IsDirectory),
+            _ => return Err(FsError::InvalidData),
+        };
+        Ok(text)
+    }
+    
+    /// シンボリックリンクのターゲット
+    fn link_target(self) -> FsResult<String> {
+        match self {
+            ProcNode::SelfLink => source::current_pid().map(|pid| pid.0.to_string()).ok_or(FsError::NotFound),
+            ProcNode::Cwd(pid) => Ok(source::working_directory(pid)),
+            ProcNode::Fd(pid, fd) => source::descriptors(pid).into_iter()
+                .find(|(open, _)| *open == fd)
+                .map(|(_, path)| path)
+                .ok_or(FsError::NotFound),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+    
+    /// メタデータ
+    fn metadata(self) -> Metadata {
+        let now = crate::time::current_time_ns() / 1_000_000_000;
+        let permissions = Permissions {
+            read: true,
+            write: false,
+            execute: self.file_type() != FileType::Regular,
+        };
+        Metadata {
+            inode: self.inode(),
+            file_type: self.file_type(),
+            // 内容は読むまで決まらないため、Linuxと同様にサイズは0とする
+            size: 0,
+            uid: 0,
+            gid: 0,
+            permissions,
+            created: now,
+            accessed: now,
+            modified: now,
+            links: if self.file_type() == FileType::Directory { 2 } else { 1 },
+            block_size: 1024,
+            blocks: 0,
+        }
+    }
+}
+
+/// パス要素をプロセスIDに変換（存在しなければNotFound）
+fn parse_pid(name: &str) -> FsResult<ProcessId> {
+    let pid = ProcessId(name.parse().map_err(|_| FsError::NotFound)?);
+    if !source::process_ids().contains(&pid) {
+        return Err(FsError::NotFound);
+    }
+    Ok(pid)
+}
+
+/// procfsファイルハンドル
+pub struct ProcFileHandle {
+    /// 対象のファイル
+    node: ProcNode,
+    /// 生成済みの内容（先頭から読むたびに作り直す）
+    contents: Mutex<Option<Vec<u8>>>,
+}
+
+impl FileHandle for ProcFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let mut contents = self.contents.lock();
+        if offset == 0 || contents.is_none() {
+            *contents = Some(self.node.contents()?.into_bytes());
+        }
+        
+        let data = contents.as_deref().unwrap_or_default();
+        if offset >= data.len() as u64 {
+            return Ok(0);
+        }
+        let start = offset as usize;
+        let len = core::cmp::min(buffer.len(), data.len() - start);
+        buffer[..len].copy_from_slice(&data[start..start + len]);
+        Ok(len)
+    }
+    
+    fn write(&self, _buffer: &[u8], _offset: u64) -> FsResult<usize> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(0)
+    }
+    
+    fn resize(&self, _new_size: u64) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.node.metadata())
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        false
+    }
+}
+
+/// procfsディレクトリハンドル
+pub struct ProcDirHandle {
+    /// 対象のディレクトリ
+    node: ProcNode,
+    /// ディレクトリのパス
+    path: String,
+}
+
+impl DirHandle for ProcDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.node.children()
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let node = ProcNode::parse(&format!("{}/{}", self.path.trim_end_matches('/'), name))?;
+        Ok(node.entry(name))
+    }
+    
+    fn create_file(&self, _name: &str, _permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_directory(&self, _name: &str, _permissions: Permissions) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn remove(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn rename(&self, _old_name: &str, _new_name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.node.metadata())
+    }
+}
+
+/// procfsファイルシステム
+pub struct ProcFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// マウントポイント
+    mount_points: RwLock<Vec<String>>,
+}
+
+impl ProcFilesystem {
+    /// 新しいprocfsインスタンスを作成
+    pub fn new() -> Self {
+        Self {
+            name: "proc".to_string(),
+            mount_points: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for ProcFilesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filesystem for ProcFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, _device: &str, mount_point: &str, _options: &str) -> FsResult<()> {
+        let mut mount_points = self.mount_points.write();
+        if mount_points.iter().any(|path| path == mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        mount_points.push(mount_point.to_string());
+        
+        log::info!("procfsをマウント: {}", mount_point);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let mut mount_points = self.mount_points.write();
+        let index = mount_points.iter().position(|path| path == mount_point).ok_or(FsError::NotFound)?;
+        mount_points.remove(index);
+        
+        log::info!("procfsをアンマウント: {}", mount_point);
+        Ok(())
+    }
+    
+    fn open_file(&self, _mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let node = ProcNode::parse(path)?;
+        match node.file_type() {
+            FileType::Directory => return Err(FsError::IsDirectory),
+            FileType::Regular => {},
+            _ => return Err(FsError::InvalidData),
+        }
+        if mode != OpenMode::ReadOnly {
+            return Err(FsError::ReadOnly);
+        }
+        
+        Ok(Arc::new(ProcFileHandle {
+            node,
+            contents: Mutex::new(None),
+        }))
+    }
+    
+    fn open_directory(&self, _mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let node = ProcNode::parse(path)?;
+        if node.file_type() != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        Ok(Arc::new(ProcDirHandle { node, path: path.to_string() }))
+    }
+    
+    fn metadata(&self, _mount_point: &str, path: &str) -> FsResult<Metadata> {
+        Ok(ProcNode::parse(path)?.metadata())
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        if !self.mount_points.read().iter().any(|path| path == mount_point) {
+            return Err(FsError::NotFound);
+        }
+        Ok(FsStats {
+            total_blocks: 0,
+            free_blocks: 0,
+            available_blocks: 0,
+            total_nodes: 0,
+            free_nodes: 0,
+            block_size: 1024,
+            max_filename_length: 255,
+        })
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn read_link(&self, _mount_point: &str, path: &str) -> FsResult<String> {
+        ProcNode::parse(path)?.link_target()
+    }
+    
+    fn is_virtual(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::source::{VmaSnapshot, ProcessSnapshot, LoadSnapshot};
+    
+    #[test]
+    fn renders_process_and_global_files() {
+        assert_eq!(ProcNode::parse("/uptime").unwrap(), ProcNode::Global(GlobalFile::Uptime));
+        assert!(matches!(ProcNode::parse("/nothing"), Err(FsError::NotFound)));
+        assert!(matches!(ProcNode::parse("/self/status/x"), Err(FsError::NotFound)));
+        assert_ne!(ProcNode::Fd(ProcessId(1), 0).inode(), ProcNode::FdDir(ProcessId(1)).inode());
+        assert_ne!(ProcNode::Process(ProcessId(0)).inode(), ROOT_INODE);
+        
+        let process = ProcessSnapshot {
+            pid: ProcessId(7),
+            parent: ProcessId(1),
+            name: "init".to_string(),
+            state: "Running".to_string(),
+            threads: 2,
+            vmas: vec![VmaSnapshot {
+                start: 0x400000,
+                end: 0x402000,
+                read: true,
+                write: false,
+                execute: true,
+                shared: false,
+                offset: 0,
+                name: String::new(),
+            }],
+        };
+        assert!(generate::status(&process).contains("PPid:\t1\nThreads:\t2\nVmSize:\t       8 kB\n"));
+        assert_eq!(generate::maps(&process), "00400000-00402000 r-xp 00000000 \n");
+        
+        let load = LoadSnapshot { load: (0.5, 0.25, 0.0), running: 1, threads: 3, last_pid: ProcessId(7), ..Default::default() };
+        assert_eq!(generate::loadavg(&load), "0.50 0.25 0.00 1/3 7\n");
+        assert_eq!(generate::uptime(12_345_678_901), "12.34 0.00\n");
+    }
+}
@@ -0,0 +1,198 @@
+// procfs データ収集
+//
+// プロセス・メモリ・スケジューラの状態を集めてスナップショットにする。
+// 表示形式への変換はgenerate.rsが行う
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::core::memory::mm::mmap::{MapType, VirtAddr};
+use crate::core::process::{self, ProcessId};
+use super::super::vfs::{self, MountInfo};
+use super::super::{page_cache, dcache, cache};
+
+/// 仮想メモリ領域の情報
+#[derive(Debug, Clone)]
+pub struct VmaSnapshot {
+    /// 開始アドレス
+    pub start: usize,
+    /// 終了アドレス
+    pub end: usize,
+    /// 読み取り可能
+    pub read: bool,
+    /// 書き込み可能
+    pub write: bool,
+    /// 実行可能
+    pub execute: bool,
+    /// 共有マッピングか
+    pub shared: bool,
+    /// ファイル内オフセット
+    pub offset: usize,
+    /// 種類を表す名前（ファイルマッピングは"file:<id>"）
+    pub name: String,
+}
+
+/// プロセスの情報
+#[derive(Debug, Clone)]
+pub struct ProcessSnapshot {
+    /// プロセスID
+    pub pid: ProcessId,
+    /// 親プロセスID（いなければ0）
+    pub parent: ProcessId,
+    /// 名前
+    pub name: String,
+    /// 状態
+    pub state: String,
+    /// スレッド数
+    pub threads: usize,
+    /// 仮想メモリ領域
+    pub vmas: Vec<VmaSnapshot>,
+}
+
+/// メモリの情報（バイト単位）
+#[derive(Debug, Clone, Default)]
+pub struct MemorySnapshot {
+    /// 物理メモリ総量
+    pub total: usize,
+    /// 利用可能量
+    pub available: usize,
+    /// 使用量
+    pub used: usize,
+    /// バッファ
+    pub buffers: usize,
+    /// ページキャッシュ
+    pub cached: usize,
+    /// ページキャッシュのダーティ分
+    pub dirty: usize,
+    /// スワップ総量
+    pub swap_total: usize,
+    /// スワップ使用量
+    pub swap_used: usize,
+    /// ブロックキャッシュ中のブロック数
+    pub block_cache_blocks: usize,
+    /// dcacheのエントリ数
+    pub dentries: usize,
+}
+
+/// スケジューラの情報
+#[derive(Debug, Clone, Default)]
+pub struct LoadSnapshot {
+    /// 1分・5分・15分の負荷平均
+    pub load: (f32, f32, f32),
+    /// 実行中のスレッド数
+    pub running: usize,
+    /// 全スレッド数
+    pub threads: usize,
+    /// 動作中のプロセス数
+    pub processes: usize,
+    /// 作成されたプロセス総数
+    pub forks: usize,
+    /// コンテキストスイッチ総数
+    pub context_switches: usize,
+    /// 最後に割り当てられたプロセスID
+    pub last_pid: ProcessId,
+}
+
+/// 現在のプロセスID
+pub fn current_pid() -> Option<ProcessId> {
+    process::current_process().map(|process| process.get_id())
+}
+
+/// 存在するプロセスのID（昇順）
+pub fn process_ids() -> Vec<ProcessId> {
+    let mut pids: Vec<ProcessId> = process::list_processes().iter().map(|process| process.get_id()).collect();
+    pids.sort_unstable();
+    pids
+}
+
+/// プロセスの情報を取得
+pub fn process(pid: ProcessId) -> Option<ProcessSnapshot> {
+    let process = process::get_process(pid)?;
+    
+    let vmas = process.get_address_space()
+        .map(|space| space.find_vmas_in_range(VirtAddr::new(0), VirtAddr::new(usize::MAX)))
+        .unwrap_or_default()
+        .iter()
+        .map(|vma| {
+            let (shared, offset, name) = match &vma.map_type {
+                MapType::File { file_id, offset, .. } => (true, *offset, format!("file:{}", file_id)),
+                MapType::Shared { shared_id, offset } => (true, *offset, format!("shm:{}", shared_id)),
+                MapType::Anonymous { .. } => (false, 0, String::new()),
+                MapType::Ram { .. } => (false, 0, "[ram]".to_string()),
+                MapType::Device { .. } => (true, 0, "[device]".to_string()),
+                MapType::TelePage { node_id, .. } => (true, 0, format!("[telepage:{}]", node_id)),
+            };
+            VmaSnapshot {
+                start: vma.start.as_usize(),
+                end: vma.end.as_usize(),
+                read: vma.permissions.read,
+                write: vma.permissions.write,
+                execute: vma.permissions.execute,
+                shared,
+                offset,
+                name,
+            }
+        })
+        .collect();
+    
+    Some(ProcessSnapshot {
+        pid,
+        parent: process.get_parent_id().unwrap_or_default(),
+        name: process.get_name().to_string(),
+        state: format!("{:?}", process.get_state()),
+        threads: process.thread_count(),
+        vmas,
+    })
+}
+
+/// プロセスの作業ディレクトリ
+pub fn working_directory(pid: ProcessId) -> String {
+    vfs::working_directory_of(pid).unwrap_or_else(|| "/".to_string())
+}
+
+/// プロセスの開いているファイルディスクリプタ
+pub fn descriptors(pid: ProcessId) -> Vec<(usize, String)> {
+    vfs::open_descriptors(pid)
+}
+
+/// メモリの情報を取得
+pub fn memory() -> MemorySnapshot {
+    let stats = crate::core::memory::get_stats();
+    let page_cache = page_cache::stats();
+    
+    MemorySnapshot {
+        total: stats.total_bytes,
+        available: stats.available_bytes,
+        used: stats.used_bytes,
+        buffers: stats.buffer_bytes,
+        cached: page_cache.map_or(stats.cached_bytes, |cache| cache.pages * page_cache::PAGE_SIZE),
+        dirty: page_cache.map_or(0, |cache| cache.dirty_pages * page_cache::PAGE_SIZE),
+        swap_total: stats.total_swap_bytes,
+        swap_used: stats.used_swap_bytes,
+        block_cache_blocks: cache::get_cache_stats().map_or(0, |cache| cache.total_blocks),
+        dentries: dcache::stats().map_or(0, |cache| cache.entries),
+    }
+}
+
+/// スケジューラの情報を取得
+pub fn load() -> LoadSnapshot {
+    let stats = process::get_stats();
+    LoadSnapshot {
+        load: crate::scheduler::calculate_load_average(),
+        running: stats.active_threads,
+        threads: stats.total_threads,
+        processes: stats.active_processes,
+        forks: stats.total_processes,
+        context_switches: stats.context_switches,
+        last_pid: process_ids().last().copied().unwrap_or_default(),
+    }
+}
+
+/// 起動からの経過時間（ナノ秒）
+pub fn uptime_ns() -> u64 {
+    crate::time::get_uptime_ns()
+}
+
+/// マウントの一覧
+pub fn mounts() -> Vec<MountInfo> {
+    vfs::mounts()
+}
@@ -0,0 +1,205 @@
+// tmpfs ファイル/ディレクトリハンドル
+//
+// ハンドルはアイノード番号を保持し、削除後も閉じるまでファイルの内容を保つ
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType, Metadata, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, InodeNum};
+use super::inode::{FileData, NodeKind};
+use super::{TmpfsVolume, MAX_SYMLINK_LENGTH};
+
+/// tmpfsファイルハンドル
+pub struct TmpfsFileHandle {
+    /// 所属ボリューム
+    volume: Arc<TmpfsVolume>,
+    /// アイノード番号
+    inode: InodeNum,
+    /// 書き込み可能か
+    writable: bool,
+    /// 常に末尾へ書き込むか
+    append: bool,
+}
+
+impl TmpfsFileHandle {
+    /// アイノードを開いてハンドルを作成
+    pub(super) fn open(volume: Arc<TmpfsVolume>, inode: InodeNum, mode: OpenMode) -> FsResult<Self> {
+        let writable = mode != OpenMode::ReadOnly;
+        volume.open_inode(inode, writable)?;
+        Ok(Self {
+            volume,
+            inode,
+            writable,
+            append: mode == OpenMode::Append,
+        })
+    }
+    
+    /// 作成したばかりのファイルのハンドルを作成（権限に関係なく書き込める）
+    pub(super) fn created(volume: Arc<TmpfsVolume>, inode: InodeNum) -> FsResult<Self> {
+        volume.open_inode(inode, false)?;
+        Ok(Self {
+            volume,
+            inode,
+            writable: true,
+            append: false,
+        })
+    }
+}
+
+impl Drop for TmpfsFileHandle {
+    fn drop(&mut self) {
+        self.volume.close_inode(self.inode);
+    }
+}
+
+impl FileHandle for TmpfsFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.volume.read(self.inode, buffer, offset)
+    }
+    
+    fn write(&self, buffer: &[u8], offset: u64) -> FsResult<usize> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.write(self.inode, buffer, offset, self.append)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.volume.metadata(self.inode)?.size)
+    }
+    
+    fn resize(&self, new_size: u64) -> FsResult<()> {
+        if !self.writable {
+            return Err(FsError::PermissionDenied);
+        }
+        self.volume.resize(self.inode, new_size)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.metadata(self.inode)
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        self.writable
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.volume.get_xattr(self.inode, name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.volume.set_xattr(self.inode, name, value)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.volume.list_xattr(self.inode)
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.volume.remove_xattr(self.inode, name)
+    }
+}
+
+/// tmpfsディレクトリハンドル
+pub struct TmpfsDirHandle {
+    /// 所属ボリューム
+    volume: Arc<TmpfsVolume>,
+    /// ディレクトリのアイノード番号
+    inode: InodeNum,
+}
+
+impl TmpfsDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<TmpfsVolume>, inode: InodeNum) -> Self {
+        Self { volume, inode }
+    }
+}
+
+impl DirHandle for TmpfsDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.volume.list_directory(self.inode)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        self.volume.lookup(self.inode, name)
+    }
+    
+    fn create_file(&self, name: &str, permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        let inode = self.volume.create(self.inode, name, NodeKind::File(FileData::default()), permissions)?;
+        Ok(Arc::new(TmpfsFileHandle::created(self.volume.clone(), inode)?))
+    }
+    
+    fn create_directory(&self, name: &str, permissions: Permissions) -> FsResult<()> {
+        let kind = NodeKind::Directory {
+            entries: Default::default(),
+            parent: self.inode,
+        };
+        self.volume.create(self.inode, name, kind, permissions)?;
+        Ok(())
+    }
+    
+    fn remove(&self, name: &str) -> FsResult<()> {
+        self.volume.remove(self.inode, name)
+    }
+    
+    fn rename(&self, old_name: &str, new_name: &str) -> FsResult<()> {
+        self.volume.rename(self.inode, old_name, new_name)
+    }
+    
+    fn create_symlink(&self, name: &str, target: &str) -> FsResult<()> {
+        if target.is_empty() || target.len() > MAX_SYMLINK_LENGTH {
+            return Err(FsError::InvalidData);
+        }
+        let permissions = Permissions { read: true, write: true, execute: true };
+        self.volume.create(self.inode, name, NodeKind::Symlink(target.into()), permissions)?;
+        Ok(())
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        self.volume.metadata(self.inode)
+    }
+    
+    fn create_device(&self, name: &str, file_type: FileType, major: u32, minor: u32) -> FsResult<()> {
+        if !matches!(file_type, FileType::BlockDevice | FileType::CharDevice) {
+            return Err(FsError::InvalidData);
+        }
+        self.volume.create(self.inode, name, NodeKind::Device { file_type, major, minor }, Permissions::default())?;
+        Ok(())
+    }
+    
+    fn device_number(&self, name: &str) -> FsResult<(u32, u32)> {
+        self.volume.device_number(self.inode, name)
+    }
+    
+    fn get_xattr(&self, name: &str) -> FsResult<Vec<u8>> {
+        self.volume.get_xattr(self.inode, name)
+    }
+    
+    fn set_xattr(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        self.volume.set_xattr(self.inode, name, value)
+    }
+    
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        self.volume.list_xattr(self.inode)
+    }
+    
+    fn remove_xattr(&self, name: &str) -> FsResult<()> {
+        self.volume.remove_xattr(self.inode, name)
+    }
+}
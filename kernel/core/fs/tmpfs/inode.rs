@@ -0,0 +1,231 @@
+// tmpfs アイノード
+//
+// メモリ上のアイノードと、書き込まれたページだけを保持する疎なファイルデータ
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FileType, Permissions, InodeNum};
+
+/// データを確保する単位
+pub const PAGE_SIZE: usize = 4096;
+
+/// 通常ファイルのデータ（穴の部分はページを持たず、読むと0になる）
+#[derive(Debug, Default)]
+pub struct FileData {
+    /// ファイルサイズ（バイト単位）
+    pub size: u64,
+    /// ページ番号 => ページ内容
+    pages: BTreeMap<u64, Box<[u8]>>,
+}
+
+impl FileData {
+    /// 確保済みのページ数
+    pub fn page_count(&self) -> u64 {
+        self.pages.len() as u64
+    }
+    
+    /// `offset`から読み込み、読んだバイト数を返す
+    pub fn read(&self, buffer: &mut [u8], offset: u64) -> usize {
+        if offset >= self.size {
+            return 0;
+        }
+        let len = core::cmp::min(buffer.len() as u64, self.size - offset) as usize;
+        
+        let mut done = 0;
+        while done < len {
+            let position = offset + done as u64;
+            let index = position / PAGE_SIZE as u64;
+            let start = (position % PAGE_SIZE as u64) as usize;
+            let chunk = core::cmp::min(PAGE_SIZE - start, len - done);
+            
+            let target = &mut buffer[done..done + chunk];
+            match self.pages.get(&index) {
+                Some(page) => target.copy_from_slice(&page[start..start + chunk]),
+                None => target.fill(0),
+            }
+            done += chunk;
+        }
+        len
+    }
+    
+    /// `offset`から`len`バイト書き込むために新たに確保が必要なページ数
+    pub fn pages_needed(&self, offset: u64, len: usize) -> u64 {
+        if len == 0 {
+            return 0;
+        }
+        let first = offset / PAGE_SIZE as u64;
+        let last = (offset + len as u64 - 1) / PAGE_SIZE as u64;
+        (first..=last).filter(|index| !self.pages.contains_key(index)).count() as u64
+    }
+    
+    /// `offset`に書き込む（容量の確認は呼び出し側で行う）
+    pub fn write(&mut self, data: &[u8], offset: u64) {
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done as u64;
+            let index = position / PAGE_SIZE as u64;
+            let start = (position % PAGE_SIZE as u64) as usize;
+            let chunk = core::cmp::min(PAGE_SIZE - start, data.len() - done);
+            
+            let page = self.pages.entry(index).or_insert_with(|| vec![0u8; PAGE_SIZE].into_boxed_slice());
+            page[start..start + chunk].copy_from_slice(&data[done..done + chunk]);
+            done += chunk;
+        }
+        
+        let end = offset + data.len() as u64;
+        if end > self.size {
+            self.size = end;
+        }
+    }
+    
+    /// サイズを変更し、解放したページ数を返す（拡張部分は穴になる）
+    pub fn truncate(&mut self, new_size: u64) -> u64 {
+        if new_size >= self.size {
+            self.size = new_size;
+            return 0;
+        }
+        
+        let keep = new_size.div_ceil(PAGE_SIZE as u64);
+        let removed = self.pages.split_off(&keep).len() as u64;
+        
+        // 後で再び伸ばしたときに古いデータが見えないよう、末尾ページの残りを0にする
+        let tail = (new_size % PAGE_SIZE as u64) as usize;
+        if tail != 0 {
+            if let Some(page) = self.pages.get_mut(&(keep - 1)) {
+                page[tail..].fill(0);
+            }
+        }
+        
+        self.size = new_size;
+        removed
+    }
+}
+
+/// アイノードの種類ごとの内容
+#[derive(Debug)]
+pub enum NodeKind {
+    /// 通常ファイル
+    File(FileData),
+    /// ディレクトリ
+    Directory {
+        /// 名前 => アイノード番号
+        entries: BTreeMap<String, InodeNum>,
+        /// 親ディレクトリ（ルートは自身）
+        parent: InodeNum,
+    },
+    /// シンボリックリンク
+    Symlink(String),
+    /// デバイスノード
+    Device {
+        /// BlockDeviceかCharDevice
+        file_type: FileType,
+        /// メジャー番号
+        major: u32,
+        /// マイナー番号
+        minor: u32,
+    },
+}
+
+/// メモリ上のアイノード
+#[derive(Debug)]
+pub struct Inode {
+    /// 内容
+    pub kind: NodeKind,
+    /// 権限
+    pub permissions: Permissions,
+    /// 作成時間（UNIX秒）
+    pub created: u64,
+    /// 最終アクセス時間
+    pub accessed: u64,
+    /// 最終変更時間
+    pub modified: u64,
+    /// ハードリンク数（ディレクトリは"."と子ディレクトリの".."を含む）
+    pub links: u32,
+    /// 開いているファイルハンドル数（リンク数0でも0になるまで解放しない）
+    pub open_handles: u32,
+    /// 拡張属性（名前 => 値）
+    pub xattrs: BTreeMap<String, Vec<u8>>,
+}
+
+impl Inode {
+    /// 新しいアイノードを作成
+    pub fn new(kind: NodeKind, permissions: Permissions, now: u64) -> Self {
+        let links = match kind {
+            NodeKind::Directory { .. } => 2,
+            _ => 1,
+        };
+        Self {
+            kind,
+            permissions,
+            created: now,
+            accessed: now,
+            modified: now,
+            links,
+            open_handles: 0,
+            xattrs: BTreeMap::new(),
+        }
+    }
+    
+    /// ファイルタイプ
+    pub fn file_type(&self) -> FileType {
+        match self.kind {
+            NodeKind::File(_) => FileType::Regular,
+            NodeKind::Directory { .. } => FileType::Directory,
+            NodeKind::Symlink(_) => FileType::SymbolicLink,
+            NodeKind::Device { file_type, .. } => file_type,
+        }
+    }
+    
+    /// ディレクトリかどうか
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory { .. })
+    }
+    
+    /// 見かけのサイズ（ディレクトリはエントリ数、リンクはターゲット長）
+    pub fn size(&self) -> u64 {
+        match &self.kind {
+            NodeKind::File(data) => data.size,
+            NodeKind::Directory { entries, .. } => entries.len() as u64,
+            NodeKind::Symlink(target) => target.len() as u64,
+            NodeKind::Device { .. } => 0,
+        }
+    }
+    
+    /// 確保済みのページ数
+    pub fn page_count(&self) -> u64 {
+        match &self.kind {
+            NodeKind::File(data) => data.page_count(),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn sparse_writes_and_truncation() {
+        let mut data = FileData::default();
+        assert_eq!(data.pages_needed(3 * PAGE_SIZE as u64 - 2, 4), 2);
+        data.write(b"abcd", 3 * PAGE_SIZE as u64 - 2);
+        assert_eq!(data.size, 3 * PAGE_SIZE as u64 + 2);
+        assert_eq!(data.page_count(), 2);
+        
+        // 穴は0として読める
+        let mut buffer = [0xFFu8; 8];
+        assert_eq!(data.read(&mut buffer, 100), 8);
+        assert_eq!(buffer, [0; 8]);
+        assert_eq!(data.read(&mut buffer, 3 * PAGE_SIZE as u64 - 4), 6);
+        assert_eq!(&buffer[..6], b"\0\0abcd");
+        
+        // 縮めてから伸ばしても切り捨てた内容は戻らない
+        assert_eq!(data.truncate(3 * PAGE_SIZE as u64 - 1), 1);
+        data.truncate(3 * PAGE_SIZE as u64 + 2);
+        assert_eq!(data.read(&mut buffer, 3 * PAGE_SIZE as u64 - 2), 4);
+        assert_eq!(&buffer[..4], b"a\0\0\0");
+        assert_eq!(data.pages_needed(0, 0), 0);
+    }
+}
@@ -0,0 +1,719 @@
+// tmpfs ファイルシステム実装
+//
+// ブロックデバイスを持たずメモリ上にだけ存在するファイルシステム
+// （疎なファイル、シンボリックリンク、ハードリンク、容量とアイノード数の上限）
+
+mod inode;
+mod file;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use spin::{Mutex, RwLock};
+use super::xattr;
+use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, Filesystem, InodeNum};
+use self::inode::{FileData, Inode, NodeKind, PAGE_SIZE};
+use self::file::{TmpfsFileHandle, TmpfsDirHandle};
+
+/// ルートディレクトリのアイノード番号
+const ROOT_INODE: InodeNum = 1;
+
+/// ファイル名の最大長（バイト単位）
+const MAX_NAME_LENGTH: usize = 255;
+
+/// シンボリックリンクのターゲットの最大長
+const MAX_SYMLINK_LENGTH: usize = PAGE_SIZE;
+
+/// 既定の容量上限
+const DEFAULT_SIZE_LIMIT: u64 = 256 * 1024 * 1024;
+
+/// 既定のアイノード数上限
+const DEFAULT_MAX_INODES: u64 = 65536;
+
+/// 現在時刻（UNIX秒）
+fn current_time() -> u64 {
+    crate::time::current_time_ns() / 1_000_000_000
+}
+
+/// エントリ名として使えるか確認
+fn validate_name(name: &str) -> FsResult<()> {
+    if name.is_empty() || name == "." || name == ".." || name.len() > MAX_NAME_LENGTH || name.contains(['/', '\0']) {
+        return Err(FsError::InvalidData);
+    }
+    Ok(())
+}
+
+/// サイズ指定を解析（k/m/g接尾辞に対応）
+fn parse_size(value: &str) -> FsResult<u64> {
+    let (digits, shift) = match value.as_bytes().last() {
+        Some(b'k' | b'K') => (&value[..value.len() - 1], 10),
+        Some(b'm' | b'M') => (&value[..value.len() - 1], 20),
+        Some(b'g' | b'G') => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    let number: u64 = digits.parse().map_err(|_| FsError::InvalidData)?;
+    number.checked_mul(1 << shift).ok_or(FsError::InvalidData)
+}
+
+/// tmpfsオプション
+#[derive(Debug, Clone, Copy)]
+pub struct TmpfsOptions {
+    /// 容量の上限（バイト単位、0は無制限）
+    pub size_limit: u64,
+    /// アイノード数の上限（0は無制限）
+    pub max_inodes: u64,
+}
+
+impl Default for TmpfsOptions {
+    fn default() -> Self {
+        Self {
+            size_limit: DEFAULT_SIZE_LIMIT,
+            max_inodes: DEFAULT_MAX_INODES,
+        }
+    }
+}
+
+impl TmpfsOptions {
+    /// マウントオプション（`size=`、`nr_inodes=`）で上書き
+    fn apply_mount_options(mut self, options: &str) -> FsResult<Self> {
+        for option in options.split(',').map(str::trim) {
+            if let Some(value) = option.strip_prefix("size=") {
+                self.size_limit = parse_size(value)?;
+            } else if let Some(value) = option.strip_prefix("nr_inodes=") {
+                self.max_inodes = parse_size(value)?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// ボリュームの可変状態（1つのロックで保護する）
+struct TmpfsState {
+    /// アイノード番号 => アイノード
+    inodes: BTreeMap<InodeNum, Inode>,
+    /// 次に割り当てるアイノード番号
+    next_inode: InodeNum,
+    /// 確保済みのデータページ数
+    used_pages: u64,
+}
+
+impl TmpfsState {
+    /// アイノードを取得（解放済みなら無効なハンドル）
+    fn inode(&self, number: InodeNum) -> FsResult<&Inode> {
+        self.inodes.get(&number).ok_or(FsError::StaleFileHandle)
+    }
+    
+    /// アイノードを可変で取得
+    fn inode_mut(&mut self, number: InodeNum) -> FsResult<&mut Inode> {
+        self.inodes.get_mut(&number).ok_or(FsError::StaleFileHandle)
+    }
+    
+    /// ディレクトリ内の名前を検索
+    fn child(&self, dir: InodeNum, name: &str) -> FsResult<InodeNum> {
+        match &self.inode(dir)?.kind {
+            NodeKind::Directory { entries, .. } => entries.get(name).copied().ok_or(FsError::NotFound),
+            _ => Err(FsError::NotDirectory),
+        }
+    }
+    
+    /// 親ディレクトリ
+    fn parent(&self, dir: InodeNum) -> FsResult<InodeNum> {
+        match &self.inode(dir)?.kind {
+            NodeKind::Directory { parent, .. } => Ok(*parent),
+            _ => Err(FsError::NotDirectory),
+        }
+    }
+    
+    /// パスを解決（`/`で始まればルートから、そうでなければ`start`から。リンクは辿らない）
+    fn resolve(&self, start: InodeNum, path: &str) -> FsResult<InodeNum> {
+        let mut current = if path.starts_with('/') { ROOT_INODE } else { start };
+        for component in path.split('/') {
+            current = match component {
+                "" | "." => continue,
+                ".." => self.parent(current)?,
+                name => self.child(current, name)?,
+            };
+        }
+        Ok(current)
+    }
+    
+    /// 親ディレクトリと最後の要素に分けて解決
+    fn resolve_parent<'a>(&self, start: InodeNum, path: &'a str) -> FsResult<(InodeNum, &'a str)> {
+        let path = path.trim_end_matches('/');
+        let (dir_path, leaf) = match path.rfind('/') {
+            Some(index) => (&path[..=index], &path[index + 1..]),
+            None => ("", path),
+        };
+        validate_name(leaf)?;
+        Ok((self.resolve(start, dir_path)?, leaf))
+    }
+    
+    /// ディレクトリにエントリを追加
+    fn attach(&mut self, dir: InodeNum, name: &str, child: InodeNum, now: u64) -> FsResult<()> {
+        let child_is_dir = self.inode(child)?.is_directory();
+        let dir_inode = self.inode_mut(dir)?;
+        match &mut dir_inode.kind {
+            NodeKind::Directory { entries, .. } => {
+                if entries.contains_key(name) {
+                    return Err(FsError::AlreadyExists);
+                }
+                entries.insert(name.to_string(), child);
+            },
+            _ => return Err(FsError::NotDirectory),
+        }
+        dir_inode.modified = now;
+        
+        // 子ディレクトリの".."が親を指す分だけ親のリンク数が増える
+        if child_is_dir {
+            dir_inode.links += 1;
+            if let NodeKind::Directory { parent, .. } = &mut self.inode_mut(child)?.kind {
+                *parent = dir;
+            }
+        }
+        Ok(())
+    }
+    
+    /// ディレクトリからエントリを外す（子のリンク数は変えない）
+    fn detach(&mut self, dir: InodeNum, name: &str, now: u64) -> FsResult<InodeNum> {
+        let dir_inode = self.inode_mut(dir)?;
+        let child = match &mut dir_inode.kind {
+            NodeKind::Directory { entries, .. } => entries.remove(name).ok_or(FsError::NotFound)?,
+            _ => return Err(FsError::NotDirectory),
+        };
+        dir_inode.modified = now;
+        
+        if self.inode(child)?.is_directory() {
+            self.inode_mut(dir)?.links -= 1;
+        }
+        Ok(child)
+    }
+    
+    /// エントリを削除してリンク数を減らす
+    fn unlink(&mut self, dir: InodeNum, name: &str, now: u64) -> FsResult<()> {
+        let child = self.child(dir, name)?;
+        if let NodeKind::Directory { entries, .. } = &self.inode(child)?.kind {
+            if !entries.is_empty() {
+                return Err(FsError::NotEmpty);
+            }
+        }
+        
+        self.detach(dir, name, now)?;
+        let inode = self.inode_mut(child)?;
+        inode.links = if inode.is_directory() { 0 } else { inode.links - 1 };
+        inode.modified = now;
+        self.release(child);
+        Ok(())
+    }
+    
+    /// リンクも開いているハンドルも無くなったアイノードを解放
+    fn release(&mut self, number: InodeNum) {
+        let unused = match self.inodes.get(&number) {
+            Some(inode) => inode.links == 0 && inode.open_handles == 0,
+            None => false,
+        };
+        if unused {
+            if let Some(inode) = self.inodes.remove(&number) {
+                self.used_pages -= inode.page_count();
+            }
+        }
+    }
+}
+
+/// マウントされたtmpfsボリューム
+struct TmpfsVolume {
+    /// データページ数の上限（0は無制限）
+    max_pages: u64,
+    /// アイノード数の上限（0は無制限）
+    max_inodes: u64,
+    /// 可変状態
+    state: Mutex<TmpfsState>,
+}
+
+impl TmpfsVolume {
+    /// 空のボリュームを作成
+    fn new(options: TmpfsOptions) -> Self {
+        let now = current_time();
+        let root = Inode::new(
+            NodeKind::Directory { entries: BTreeMap::new(), parent: ROOT_INODE },
+            Permissions { read: true, write: true, execute: true },
+            now,
+        );
+        let mut inodes = BTreeMap::new();
+        inodes.insert(ROOT_INODE, root);
+        
+        Self {
+            max_pages: options.size_limit.div_ceil(PAGE_SIZE as u64),
+            max_inodes: options.max_inodes,
+            state: Mutex::new(TmpfsState {
+                inodes,
+                next_inode: ROOT_INODE + 1,
+                used_pages: 0,
+            }),
+        }
+    }
+    
+    /// パスを解決
+    fn resolve(&self, path: &str) -> FsResult<InodeNum> {
+        self.state.lock().resolve(ROOT_INODE, path)
+    }
+    
+    /// ディレクトリ内の名前を検索
+    fn lookup(&self, dir: InodeNum, name: &str) -> FsResult<DirEntry> {
+        let state = self.state.lock();
+        let child = state.child(dir, name)?;
+        Ok(DirEntry {
+            name: name.to_string(),
+            inode: child,
+            file_type: state.inode(child)?.file_type(),
+        })
+    }
+    
+    /// ディレクトリの内容を列挙
+    fn list_directory(&self, dir: InodeNum) -> FsResult<Vec<DirEntry>> {
+        let state = self.state.lock();
+        let entries = match &state.inode(dir)?.kind {
+            NodeKind::Directory { entries, .. } => entries,
+            _ => return Err(FsError::NotDirectory),
+        };
+        
+        entries.iter()
+            .map(|(name, &child)| Ok(DirEntry {
+                name: name.clone(),
+                inode: child,
+                file_type: state.inode(child)?.file_type(),
+            }))
+            .collect()
+    }
+    
+    /// パスを親ディレクトリと最後の要素に分けて解決
+    fn resolve_parent<'a>(&self, path: &'a str) -> FsResult<(InodeNum, &'a str)> {
+        self.state.lock().resolve_parent(ROOT_INODE, path)
+    }
+    
+    /// ディレクトリにアイノードを作成
+    fn create(&self, dir: InodeNum, name: &str, kind: NodeKind, permissions: Permissions) -> FsResult<InodeNum> {
+        validate_name(name)?;
+        let mut state = self.state.lock();
+        if state.child(dir, name).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+        if self.max_inodes != 0 && state.inodes.len() as u64 >= self.max_inodes {
+            return Err(FsError::OutOfSpace);
+        }
+        
+        let now = current_time();
+        let number = state.next_inode;
+        state.next_inode += 1;
+        state.inodes.insert(number, Inode::new(kind, permissions, now));
+        if let Err(e) = state.attach(dir, name, number, now) {
+            state.inodes.remove(&number);
+            return Err(e);
+        }
+        Ok(number)
+    }
+    
+    /// エントリを削除（ディレクトリは空の場合のみ）
+    fn remove(&self, dir: InodeNum, name: &str) -> FsResult<()> {
+        self.state.lock().unlink(dir, name, current_time())
+    }
+    
+    /// エントリを移動（`new_path`は`dir`からの相対パスかルートからの絶対パス）
+    fn rename(&self, dir: InodeNum, old_name: &str, new_path: &str) -> FsResult<()> {
+        let mut state = self.state.lock();
+        let source = state.child(dir, old_name)?;
+        let (new_dir, new_name) = state.resolve_parent(dir, new_path)?;
+        let source_is_dir = state.inode(source)?.is_directory();
+        
+        // ディレクトリを自身の子孫へ移動することはできない
+        if source_is_dir {
+            let mut ancestor = new_dir;
+            loop {
+                if ancestor == source {
+                    return Err(FsError::InvalidData);
+                }
+                if ancestor == ROOT_INODE {
+                    break;
+                }
+                ancestor = state.parent(ancestor)?;
+            }
+        }
+        
+        let now = current_time();
+        match state.child(new_dir, new_name) {
+            // 同じアイノードを指す名前同士なら何もしない
+            Ok(target) if target == source => return Ok(()),
+            Ok(target) => {
+                match (source_is_dir, state.inode(target)?.is_directory()) {
+                    (true, false) => return Err(FsError::NotDirectory),
+                    (false, true) => return Err(FsError::IsDirectory),
+                    _ => {}
+                }
+                state.unlink(new_dir, new_name, now)?;
+            },
+            Err(FsError::NotFound) => {},
+            Err(e) => return Err(e),
+        }
+        
+        state.detach(dir, old_name, now)?;
+        state.attach(new_dir, new_name, source, now)
+    }
+    
+    /// 既存ファイルへのハードリンクを作成
+    fn link(&self, existing: &str, new_path: &str) -> FsResult<()> {
+        let mut state = self.state.lock();
+        let source = state.resolve(ROOT_INODE, existing)?;
+        if state.inode(source)?.is_directory() {
+            return Err(FsError::PermissionDenied);
+        }
+        
+        let (dir, name) = state.resolve_parent(ROOT_INODE, new_path)?;
+        state.attach(dir, name, source, current_time())?;
+        state.inode_mut(source)?.links += 1;
+        Ok(())
+    }
+    
+    /// シンボリックリンクのターゲットを読み取り
+    fn read_link(&self, path: &str) -> FsResult<String> {
+        let state = self.state.lock();
+        match &state.inode(state.resolve(ROOT_INODE, path)?)?.kind {
+            NodeKind::Symlink(target) => Ok(target.clone()),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+    
+    /// デバイスノードのデバイス番号
+    fn device_number(&self, dir: InodeNum, name: &str) -> FsResult<(u32, u32)> {
+        let state = self.state.lock();
+        match state.inode(state.child(dir, name)?)?.kind {
+            NodeKind::Device { major, minor, .. } => Ok((major, minor)),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+    
+    /// 拡張属性を取得
+    fn get_xattr(&self, number: InodeNum, name: &str) -> FsResult<Vec<u8>> {
+        self.state.lock().inode(number)?.xattrs.get(name).cloned().ok_or(FsError::NotFound)
+    }
+    
+    /// 拡張属性を設定
+    fn set_xattr(&self, number: InodeNum, name: &str, value: &[u8]) -> FsResult<()> {
+        xattr::validate(name, value)?;
+        let mut state = self.state.lock();
+        let inode = state.inode_mut(number)?;
+        inode.xattrs.insert(name.to_string(), value.to_vec());
+        inode.modified = current_time();
+        Ok(())
+    }
+    
+    /// 拡張属性の名前を列挙
+    fn list_xattr(&self, number: InodeNum) -> FsResult<Vec<String>> {
+        Ok(self.state.lock().inode(number)?.xattrs.keys().cloned().collect())
+    }
+    
+    /// 拡張属性を削除
+    fn remove_xattr(&self, number: InodeNum, name: &str) -> FsResult<()> {
+        let mut state = self.state.lock();
+        let inode = state.inode_mut(number)?;
+        inode.xattrs.remove(name).ok_or(FsError::NotFound)?;
+        inode.modified = current_time();
+        Ok(())
+    }
+    
+    /// ファイルハンドル用にアイノードを開く
+    fn open_inode(&self, number: InodeNum, writable: bool) -> FsResult<()> {
+        let mut state = self.state.lock();
+        let inode = state.inode_mut(number)?;
+        if inode.is_directory() {
+            return Err(FsError::IsDirectory);
+        }
+        if writable && !inode.permissions.write {
+            return Err(FsError::PermissionDenied);
+        }
+        inode.open_handles += 1;
+        Ok(())
+    }
+    
+    /// ファイルハンドルを閉じる（削除済みなら解放する）
+    fn close_inode(&self, number: InodeNum) {
+        let mut state = self.state.lock();
+        if let Ok(inode) = state.inode_mut(number) {
+            inode.open_handles -= 1;
+        }
+        state.release(number);
+    }
+    
+    /// データを読み込む（シンボリックリンクはターゲットを返す）
+    fn read(&self, number: InodeNum, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        let state = self.state.lock();
+        match &state.inode(number)?.kind {
+            NodeKind::File(data) => Ok(data.read(buffer, offset)),
+            NodeKind::Symlink(target) => {
+                let target = target.as_bytes();
+                let start = core::cmp::min(offset, target.len() as u64) as usize;
+                let len = core::cmp::min(buffer.len(), target.len() - start);
+                buffer[..len].copy_from_slice(&target[start..start + len]);
+                Ok(len)
+            },
+            NodeKind::Directory { .. } => Err(FsError::IsDirectory),
+            NodeKind::Device { .. } => Err(FsError::NotSupported),
+        }
+    }
+    
+    /// データを書き込む（容量を超える場合は何も書かずにOutOfSpace）
+    fn write(&self, number: InodeNum, data: &[u8], offset: u64, append: bool) -> FsResult<usize> {
+        let mut state = self.state.lock();
+        let used_pages = state.used_pages;
+        let inode = state.inode_mut(number)?;
+        let file = match &mut inode.kind {
+            NodeKind::File(file) => file,
+            NodeKind::Directory { .. } => return Err(FsError::IsDirectory),
+            NodeKind::Symlink(_) | NodeKind::Device { .. } => return Err(FsError::InvalidData),
+        };
+        
+        let offset = if append { file.size } else { offset };
+        if offset.checked_add(data.len() as u64).is_none_or(|end| end > i64::MAX as u64) {
+            return Err(FsError::OverflowError);
+        }
+        
+        let needed = file.pages_needed(offset, data.len());
+        if self.max_pages != 0 && used_pages + needed > self.max_pages {
+            return Err(FsError::OutOfSpace);
+        }
+        file.write(data, offset);
+        inode.modified = current_time();
+        
+        state.used_pages += needed;
+        Ok(data.len())
+    }
+    
+    /// ファイルサイズを変更（伸ばした部分は穴になる）
+    fn resize(&self, number: InodeNum, new_size: u64) -> FsResult<()> {
+        if new_size > i64::MAX as u64 {
+            return Err(FsError::OverflowError);
+        }
+        
+        let mut state = self.state.lock();
+        let inode = state.inode_mut(number)?;
+        let freed = match &mut inode.kind {
+            NodeKind::File(file) => file.truncate(new_size),
+            NodeKind::Directory { .. } => return Err(FsError::IsDirectory),
+            NodeKind::Symlink(_) | NodeKind::Device { .. } => return Err(FsError::InvalidData),
+        };
+        inode.modified = current_time();
+        
+        state.used_pages -= freed;
+        Ok(())
+    }
+    
+    /// アイノードのメタデータを取得
+    fn metadata(&self, number: InodeNum) -> FsResult<Metadata> {
+        let state = self.state.lock();
+        let inode = state.inode(number)?;
+        Ok(Metadata {
+            inode: number,
+            file_type: inode.file_type(),
+            size: inode.size(),
+            uid: 0,
+            gid: 0,
+            permissions: inode.permissions,
+            created: inode.created,
+            accessed: inode.accessed,
+            modified: inode.modified,
+            links: inode.links,
+            block_size: PAGE_SIZE as u32,
+            blocks: inode.page_count(),
+        })
+    }
+    
+    /// ファイルシステム統計を取得（上限なしの場合は総数0を返す）
+    fn stats(&self) -> FsStats {
+        let state = self.state.lock();
+        let free_blocks = self.max_pages.saturating_sub(state.used_pages);
+        FsStats {
+            total_blocks: self.max_pages,
+            free_blocks,
+            available_blocks: free_blocks,
+            total_nodes: self.max_inodes,
+            free_nodes: self.max_inodes.saturating_sub(state.inodes.len() as u64),
+            block_size: PAGE_SIZE as u32,
+            max_filename_length: MAX_NAME_LENGTH as u32,
+        }
+    }
+}
+
+/// tmpfsファイルシステム
+pub struct TmpfsFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// マウント時の既定オプション
+    options: TmpfsOptions,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<TmpfsVolume>>>,
+}
+
+impl TmpfsFilesystem {
+    /// 新しいtmpfsインスタンスを作成
+    pub fn new() -> Self {
+        Self::new_advanced(TmpfsOptions::default())
+    }
+    
+    /// 容量とアイノード数の既定上限を指定してインスタンスを作成（0は無制限）
+    pub fn new_with_options(size_limit: u64, max_inodes: u64) -> Self {
+        Self::new_advanced(TmpfsOptions { size_limit, max_inodes })
+    }
+    
+    /// 高度なオプションでtmpfsインスタンスを作成
+    pub fn new_advanced(options: TmpfsOptions) -> Self {
+        Self {
+            name: "tmpfs".to_string(),
+            options,
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// マウントポイントのボリュームを取得（マウントごとに別の内容を持つ）
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<TmpfsVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+    
+    /// `mount_point`のボリューム内で`existing`へのハードリンクを`new_path`に作成（ディレクトリは不可）
+    pub fn link(&self, mount_point: &str, existing: &str, new_path: &str) -> FsResult<()> {
+        self.find_volume(mount_point)?.link(existing, new_path)
+    }
+}
+
+impl Filesystem for TmpfsFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        
+        // デバイス名は表示用で、内容はマウントごとに空から始まる
+        let options = self.options.apply_mount_options(options)?;
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(TmpfsVolume::new(options)));
+        
+        log::info!("tmpfsをマウント: {} -> {} (上限 {}バイト)", device, mount_point, options.size_limit);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        
+        log::info!("tmpfsをアンマウント: {}", mount_point);
+        Ok(())
+    }
+    
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let number = match volume.resolve(path) {
+            Ok(_) if mode == OpenMode::CreateNew => return Err(FsError::AlreadyExists),
+            Ok(number) => number,
+            Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => {
+                let (dir, name) = volume.resolve_parent(path)?;
+                let number = volume.create(dir, name, NodeKind::File(FileData::default()), Permissions::default())?;
+                return Ok(Arc::new(TmpfsFileHandle::created(volume, number)?));
+            },
+            Err(e) => return Err(e),
+        };
+        
+        let handle = TmpfsFileHandle::open(volume, number, mode)?;
+        if mode == OpenMode::Truncate {
+            handle.resize(0)?;
+        }
+        Ok(Arc::new(handle))
+    }
+    
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let number = volume.resolve(path)?;
+        if volume.metadata(number)?.file_type != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        Ok(Arc::new(TmpfsDirHandle::new(volume, number)))
+    }
+    
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        volume.metadata(volume.resolve(path)?)
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        Ok(self.find_volume(mount_point)?.stats())
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        // 永続化先がないため何もしない
+        Ok(())
+    }
+    
+    fn read_link(&self, mount_point: &str, path: &str) -> FsResult<String> {
+        self.find_volume(mount_point)?.read_link(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn namespace_links_and_limits() {
+        let fs = TmpfsFilesystem::new();
+        fs.mount("tmpfs", "/tmp", "size=16k,nr_inodes=6").unwrap();
+        
+        let root = fs.open_directory("/tmp", "/").unwrap();
+        root.create_directory("a", Permissions::default()).unwrap();
+        let file = fs.open_file("/tmp", "/a/f", OpenMode::Create).unwrap();
+        assert_eq!(file.write(b"xyz", 3 * PAGE_SIZE as u64).unwrap(), 3);
+        assert_eq!(fs.metadata("/tmp", "/a/../a/f").unwrap().blocks, 1);
+        
+        // 穴を埋めると上限（4ページ）を超える
+        assert!(matches!(file.write(&[1u8; 4 * PAGE_SIZE + 1], 0), Err(FsError::OutOfSpace)));
+        assert_eq!(fs.stats("/tmp").unwrap().free_blocks, 3);
+        
+        fs.link("/tmp", "/a/f", "/g").unwrap();
+        root.create_symlink("s", "a/f").unwrap();
+        assert_eq!(fs.read_link("/tmp", "/s").unwrap(), "a/f");
+        assert_eq!(fs.metadata("/tmp", "/g").unwrap().links, 2);
+        assert!(matches!(root.remove("a"), Err(FsError::NotEmpty)));
+        assert!(matches!(root.rename("a", "a/b"), Err(FsError::InvalidData)));
+        
+        // 名前が消えても開いているハンドルからは読める
+        root.remove("g").unwrap();
+        fs.open_directory("/tmp", "/a").unwrap().remove("f").unwrap();
+        let mut buffer = [0u8; 3];
+        assert_eq!(file.read(&mut buffer, 3 * PAGE_SIZE as u64).unwrap(), 3);
+        assert_eq!(&buffer, b"xyz");
+        drop(file);
+        
+        let stats = fs.stats("/tmp").unwrap();
+        assert_eq!((stats.free_blocks, stats.free_nodes), (4, 3));
+        assert_eq!(parse_size("2M").unwrap(), 2 << 20);
+    }
+    
+    #[test]
+    fn each_mount_has_its_own_volume() {
+        let fs = TmpfsFilesystem::new();
+        fs.mount("tmpfs", "/a", "").unwrap();
+        fs.mount("tmpfs", "/b", "size=4k").unwrap();
+        assert!(matches!(fs.mount("tmpfs", "/a", ""), Err(FsError::AlreadyExists)));
+        
+        fs.open_file("/a", "/only-a", OpenMode::Create).unwrap().write(b"a", 0).unwrap();
+        assert!(fs.metadata("/a", "/only-a").is_ok());
+        assert!(matches!(fs.metadata("/b", "/only-a"), Err(FsError::NotFound)));
+        assert!(matches!(fs.open_directory("/c", "/"), Err(FsError::NotFound)));
+        assert_eq!(fs.stats("/b").unwrap().total_blocks, 1);
+        
+        fs.unmount("/a").unwrap();
+        assert!(fs.open_directory("/b", "/").is_ok());
+        assert!(matches!(fs.metadata("/a", "/only-a"), Err(FsError::NotFound)));
+    }
+}
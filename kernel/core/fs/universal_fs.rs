@@ -175,8 +175,19 @@ impl FileSystem for UniversalFS {
     }
     
     fn fsck(&self, repair: bool) -> FileSystemResult<bool> {
-        // すべてのアダプターでfsckを実行
-        Ok(true)
+        // マウント中のすべてのファイルシステムでfsckを実行（未対応のものは飛ばす）
+        let mut clean = true;
+        for mount in super::vfs::mounts() {
+            match super::vfs::fsck(&mount.path, repair) {
+                Ok(report) => clean &= report.unrepaired == 0,
+                Err(super::FsError::NotSupported) => {},
+                Err(e) => {
+                    log::warn!("fsck: {}のチェックに失敗しました: {:?}", mount.path, e);
+                    clean = false;
+                },
+            }
+        }
+        Ok(clean)
     }
     
     fn as_any(&self) -> &dyn Any {
@@ -8,21 +8,28 @@ use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use spin::RwLock;
 use crate::core::sync::Mutex;
+use crate::core::process::ProcessId;
 use super::{FsError, FsResult};
+use super::namei::{self, lookup_flags, MountEntry, ResolvedPath};
+use super::dcache::{self, CachedDirHandle};
+use super::page_cache;
+use super::lock::{self, LockKind, RangeLock};
+use super::notify::{self, event_mask};
+use super::xattr::{self, access, Credentials, PosixAcl, XattrNamespace, XattrTarget, POSIX_ACL_ACCESS};
 
 /// アイノード番号
 pub type InodeNum = u64;
 
 /// ファイルシステムID
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FsId(u64);
 
 impl FsId {
     /// 新しいFsIdを生成
-    fn new() -> Self {
+    pub(super) fn new() -> Self {
         static NEXT_FS_ID: AtomicU64 = AtomicU64::new(1);
         Self(NEXT_FS_ID.fetch_add(1, Ordering::SeqCst))
     }
@@ -56,6 +63,25 @@ pub trait BlockDevice: Send + Sync {
     
     /// デバイスを閉じる
     fn close(&self) -> FsResult<()>;
+    
+    /// ドライバ固有の制御要求（devfsが処理しない要求が渡される）
+    fn ioctl(&self, _request: u32, _arg: usize) -> FsResult<usize> {
+        Err(FsError::NotSupported)
+    }
+}
+
+/// キャラクタデバイスの抽象トレイト
+pub trait CharDevice: Send + Sync {
+    /// 読み込み（読めるデータがなければ0を返す）
+    fn read(&self, buffer: &mut [u8]) -> FsResult<usize>;
+    
+    /// 書き込み
+    fn write(&self, buffer: &[u8]) -> FsResult<usize>;
+    
+    /// ドライバ固有の制御要求
+    fn ioctl(&self, _request: u32, _arg: usize) -> FsResult<usize> {
+        Err(FsError::NotSupported)
+    }
 }
 
 /// ファイル/ディレクトリの権限
@@ -146,6 +172,62 @@ pub struct FsStats {
     pub max_filename_length: u32,
 }
 
+/// 整合性チェックで見つかった問題
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsckProblem {
+    /// ブロックビットマップが実際の使用状況と異なる（使用中なのに空きのブロック数と、参照されていないのに使用中のブロック数）
+    BlockBitmap { group: u32, marked_free: u32, unreferenced: u32 },
+    /// 空きブロック（クラスタ）数の記録が実際と異なる（`group`が`None`ならボリューム全体）
+    FreeCount { group: Option<u32>, recorded: u64, actual: u64 },
+    /// iノードのリンク数がディレクトリからの参照数と異なる
+    LinkCount { inode: u64, recorded: u32, actual: u32 },
+    /// 孤立iノードリストに残っていたiノード（`unlinked`ならリンク数0で解放、そうでなければ切り詰めの途中）
+    Orphan { inode: u64, unlinked: bool },
+    /// 複数の所有者が同じブロックを使っている（所有者0はファイルシステムのメタデータ）
+    DuplicateBlock { block: u64, owners: Vec<u64> },
+    /// 使用されていないiノードを指すディレクトリエントリ
+    DanglingEntry { dir: u64, name: String },
+    /// どのディレクトリからも参照されていない使用中のiノード（修復時はlost+foundへ再接続する）
+    Disconnected { inode: u64 },
+    /// 複数のファイルが同じクラスタを使っている（先に見つかった所有者が残る）
+    CrossLinked { cluster: u32, paths: Vec<String> },
+    /// 不正な値を指している、または循環しているクラスタチェーン（修復時は直前で切り詰める）
+    BrokenChain { path: String },
+    /// どのファイルからも参照されていないクラスタチェーン
+    LostClusters { first: u32, count: u32 },
+    /// FATのコピーが使用中のFATと一致しない（異なるエントリの数）
+    FatCopyMismatch { copy: u8, entries: u32 },
+}
+
+/// 整合性チェックの結果
+#[derive(Debug, Clone, Default)]
+pub struct FsckReport {
+    /// 見つかった問題
+    pub problems: Vec<FsckProblem>,
+    /// 修復していない問題の数（チェックのみの場合はすべて）
+    pub unrepaired: usize,
+    /// 調べたファイルとディレクトリの数
+    pub files: u64,
+    /// 使用中のブロック（クラスタ）数
+    pub used_blocks: u64,
+}
+
+impl FsckReport {
+    /// 問題が見つからなかったか
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+    
+    /// 問題を記録（`repaired`なら修復済み）
+    pub fn record(&mut self, problem: FsckProblem, repaired: bool) {
+        log::warn!("fsck: {:?}{}", problem, if repaired { "（修復済み）" } else { "" });
+        if !repaired {
+            self.unrepaired += 1;
+        }
+        self.problems.push(problem);
+    }
+}
+
 /// ファイルオープンモード
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpenMode {
@@ -196,17 +278,62 @@ pub trait FileHandle: Send + Sync {
     /// ファイルのメタデータを取得
     fn metadata(&self) -> FsResult<Metadata>;
     
-    /// ファイルをロック
+    /// ファイル全体をロック（flock相当。競合すれば解除されるまで待つ）
     fn lock(&self, exclusive: bool) -> FsResult<()>;
     
-    /// ファイルのロックを解除
+    /// ファイル全体のロックを解除
     fn unlock(&self) -> FsResult<()>;
     
+    /// ファイル全体を待たずにロック（競合すればResourceBusy、待たずに試せなければNotSupported）
+    fn try_lock(&self, _exclusive: bool) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    
     /// ファイルが読み込み可能か
     fn can_read(&self) -> bool;
     
     /// ファイルが書き込み可能か
     fn can_write(&self) -> bool;
+    
+    /// データとメタデータを永続ストレージに書き込む（fsync）
+    fn fsync(&self) -> FsResult<()> {
+        self.flush()
+    }
+    
+    /// データと、読み出しに必要なメタデータ（サイズなど）だけを書き込む（fdatasync）
+    fn fdatasync(&self) -> FsResult<()> {
+        self.fsync()
+    }
+    
+    /// ページキャッシュ上のマッピングID（`MapType::File`の`file_id`として使う）
+    fn mapping_id(&self) -> Option<usize> {
+        None
+    }
+    
+    /// デバイスへの制御要求（ioctl）。結果は戻り値で返す
+    fn ioctl(&self, _request: u32, _arg: usize) -> FsResult<usize> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// 拡張属性を取得（なければNotFound）
+    fn get_xattr(&self, _name: &str) -> FsResult<Vec<u8>> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// 拡張属性を設定（読み取り専用で開いたハンドルでも設定できる）
+    fn set_xattr(&self, _name: &str, _value: &[u8]) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// 拡張属性の名前を列挙
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// 拡張属性を削除（なければNotFound）
+    fn remove_xattr(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
 }
 
 /// ディレクトリハンドルの抽象トレイト
@@ -234,6 +361,36 @@ pub trait DirHandle: Send + Sync {
     
     /// ディレクトリのメタデータを取得
     fn metadata(&self) -> FsResult<Metadata>;
+    
+    /// デバイスノードを作成（mknod。`file_type`はBlockDeviceかCharDevice）
+    fn create_device(&self, _name: &str, _file_type: FileType, _major: u32, _minor: u32) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// デバイスノードのデバイス番号（メジャー, マイナー）
+    fn device_number(&self, _name: &str) -> FsResult<(u32, u32)> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// ディレクトリの拡張属性を取得
+    fn get_xattr(&self, _name: &str) -> FsResult<Vec<u8>> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// ディレクトリの拡張属性を設定
+    fn set_xattr(&self, _name: &str, _value: &[u8]) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// ディレクトリの拡張属性の名前を列挙
+    fn list_xattr(&self) -> FsResult<Vec<String>> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// ディレクトリの拡張属性を削除
+    fn remove_xattr(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
 }
 
 /// ファイルシステムドライバの抽象トレイト
@@ -250,14 +407,17 @@ pub trait Filesystem: Send + Sync {
     /// ファイルシステムをアンマウント
     fn unmount(&self, mount_point: &str) -> FsResult<()>;
     
-    /// ファイルを開く
-    fn open_file(&self, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>>;
+    /// マウントポイントのボリューム内のファイルを開く
+    ///
+    /// 同じドライバを複数の場所にマウントできるため、パスを解決したマウントを
+    /// `mount_point`で受け取り、`path`はそのボリュームのルートからのパスとする
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>>;
     
-    /// ディレクトリを開く
-    fn open_directory(&self, path: &str) -> FsResult<Arc<dyn DirHandle>>;
+    /// マウントポイントのボリューム内のディレクトリを開く
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>>;
     
-    /// ファイル/ディレクトリのメタデータを取得
-    fn metadata(&self, path: &str) -> FsResult<Metadata>;
+    /// マウントポイントのボリューム内のファイル/ディレクトリのメタデータを取得
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata>;
     
     /// ファイルシステムの統計情報を取得
     fn stats(&self, mount_point: &str) -> FsResult<FsStats>;
@@ -265,20 +425,32 @@ pub trait Filesystem: Send + Sync {
     /// ファイルシステムをシンク（すべての変更を永続化）
     fn sync(&self) -> FsResult<()>;
     
-    /// ファイルシステムをチェック（任意）
-    fn check(&self) -> FsResult<bool> {
-        Ok(true)
+    /// マウントポイントのボリュームの整合性をチェック（任意。ディスクには書き込まない）
+    fn check(&self, _mount_point: &str) -> FsResult<FsckReport> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// マウントポイントのボリュームの整合性をチェックし、見つかった問題を修復（任意）
+    fn repair(&self, _mount_point: &str) -> FsResult<FsckReport> {
+        Err(FsError::NotSupported)
     }
     
-    /// ファイルシステムを修復（任意）
-    fn repair(&self) -> FsResult<bool> {
-        Ok(false)
+    /// マウントポイントのボリューム内のシンボリックリンクのターゲットを読み取り（任意）
+    fn read_link(&self, _mount_point: &str, _path: &str) -> FsResult<String> {
+        Err(FsError::NotSupported)
+    }
+    
+    /// 内容を読むたびに生成する仮想ファイルシステムか（dcacheとページキャッシュを使わない）
+    fn is_virtual(&self) -> bool {
+        false
     }
 }
 
 /// マウントポイント情報
 #[derive(Debug)]
 struct MountPoint {
+    /// マウントごとのID（dcacheのキー）
+    id: FsId,
     /// マウントされたファイルシステム
     fs: Arc<dyn Filesystem>,
     /// デバイスパス
@@ -287,6 +459,8 @@ struct MountPoint {
     path: String,
     /// マウントオプション
     options: String,
+    /// ルートディレクトリのアイノード番号（dcacheのキー）
+    root: InodeNum,
 }
 
 /// ファイルシステムレジストリ
@@ -295,6 +469,8 @@ struct FilesystemRegistry {
     filesystems: BTreeMap<String, Arc<dyn Filesystem>>,
     /// マウントポイント
     mount_points: Vec<MountPoint>,
+    /// マウントせずに整合性をチェックしているデバイス（/devからの名前）
+    checking: BTreeSet<String>,
 }
 
 impl FilesystemRegistry {
@@ -303,9 +479,17 @@ impl FilesystemRegistry {
         Self {
             filesystems: BTreeMap::new(),
             mount_points: Vec::new(),
+            checking: BTreeSet::new(),
         }
     }
     
+    /// デバイスがマウント中か整合性チェック中か（"sda1"と"/dev/sda1"は同じデバイス）
+    fn device_in_use(&self, device: &str) -> bool {
+        let name = super::devfs::device_name(device);
+        self.checking.contains(name)
+            || self.mount_points.iter().any(|mp| super::devfs::device_name(&mp.device) == name)
+    }
+    
     /// ファイルシステムを登録
     fn register(&mut self, name: &str, fs: Arc<dyn Filesystem>) -> FsResult<()> {
         if self.filesystems.contains_key(name) {
@@ -316,42 +500,41 @@ impl FilesystemRegistry {
         Ok(())
     }
     
-    /// ファイルシステムをマウント
-    fn mount(&mut self, fs_type: &str, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
-        let fs = self.filesystems.get(fs_type)
-            .ok_or(FsError::NotFound)?
-            .clone();
-        
-        fs.mount(device, mount_point, options)?;
-        
+    /// マウント済みのファイルシステムを登録
+    fn add_mount(&mut self, fs: Arc<dyn Filesystem>, device: &str, mount_point: String, options: &str) {
+        let root = fs.metadata(&mount_point, "/").map_or(0, |metadata| metadata.inode);
         self.mount_points.push(MountPoint {
+            id: FsId::new(),
+            root,
             fs,
             device: device.to_string(),
-            path: mount_point.to_string(),
+            path: mount_point,
             options: options.to_string(),
         });
-        
-        Ok(())
     }
     
     /// ファイルシステムをアンマウント
     fn unmount(&mut self, mount_point: &str) -> FsResult<()> {
+        let mount_point = normalize_path(mount_point);
         let idx = self.mount_points.iter()
-            .position(|mp| mp.path == mount_point)
+            .rposition(|mp| mp.path == mount_point)
             .ok_or(FsError::NotFound)?;
         
         let mp = &self.mount_points[idx];
-        mp.fs.unmount(mount_point)?;
+        page_cache::release_filesystem(mp.id)?;
+        mp.fs.unmount(&mount_point)?;
+        dcache::invalidate_filesystem(mp.id);
+        notify::unmount(mp.id);
         
         self.mount_points.remove(idx);
         Ok(())
     }
     
-    /// パスに対応するマウントポイントを検索
-    fn find_mount_point(&self, path: &str) -> Option<&MountPoint> {
+    /// パス解決用にマウント情報を複製
+    fn mount_entries(&self) -> Vec<MountEntry> {
         self.mount_points.iter()
-            .filter(|mp| path.starts_with(&mp.path))
-            .max_by_key(|mp| mp.path.len())
+            .map(|mp| MountEntry { id: mp.id, path: mp.path.clone(), fs: mp.fs.clone(), root: mp.root })
+            .collect()
     }
 }
 
@@ -377,13 +560,53 @@ pub fn register_filesystem(name: &str, fs: impl Filesystem + 'static) -> FsResul
 }
 
 /// ファイルシステムをマウント
+///
+/// ドライバの`mount`はレジストリをロックせずに呼ぶため、overlayのように
+/// マウント時に他のパスを解決するドライバも使える
 pub fn mount(fs_type: &str, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
-    let mut registry = FS_REGISTRY.lock();
+    // "auto"はパーティションの種類から推定したファイルシステムを順に試す
+    if fs_type == "auto" {
+        let partition_type = super::devfs::partition_type(device).ok_or(FsError::NotSupported)?;
+        let mut last_error = FsError::NotSupported;
+        for candidate in partition_type.filesystem_candidates() {
+            if !FS_REGISTRY.lock().as_ref().is_some_and(|reg| reg.filesystems.contains_key(*candidate)) {
+                continue;
+            }
+            match mount(candidate, device, mount_point, options) {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = e,
+            }
+        }
+        return Err(last_error);
+    }
     
-    if let Some(reg) = registry.as_mut() {
-        reg.mount(fs_type, device, mount_point, options)
-    } else {
-        Err(FsError::NotSupported)
+    let fs = {
+        let registry = FS_REGISTRY.lock();
+        let reg = registry.as_ref().ok_or(FsError::NotSupported)?;
+        if reg.checking.contains(super::devfs::device_name(device)) {
+            return Err(FsError::ResourceBusy);
+        }
+        reg.filesystems.get(fs_type).cloned().ok_or(FsError::NotFound)?
+    };
+    let mount_point = normalize_path(mount_point);
+    
+    fs.mount(device, &mount_point, options)?;
+    
+    let mut registry = FS_REGISTRY.lock();
+    match registry.as_mut() {
+        // ドライバのマウント中にfsck_deviceが始まっていれば取り消す
+        Some(reg) if reg.checking.contains(super::devfs::device_name(device)) => {
+            let _ = fs.unmount(&mount_point);
+            Err(FsError::ResourceBusy)
+        },
+        Some(reg) => {
+            reg.add_mount(fs, device, mount_point, options);
+            Ok(())
+        },
+        None => {
+            let _ = fs.unmount(&mount_point);
+            Err(FsError::NotSupported)
+        },
     }
 }
 
@@ -415,86 +638,462 @@ fn normalize_path(path: &str) -> String {
     normalized
 }
 
+/// プロセスごとの作業ディレクトリ（未設定のプロセスとカーネルは"/"）
+static WORKING_DIRECTORIES: Mutex<BTreeMap<ProcessId, String>> = Mutex::new(BTreeMap::new());
+
+/// 現在のプロセスID
+fn current_process_id() -> Option<ProcessId> {
+    crate::core::process::current_process().map(|process| process.get_id())
+}
+
+/// 現在のプロセスの作業ディレクトリ
+pub fn current_directory() -> String {
+    current_process_id()
+        .and_then(|pid| WORKING_DIRECTORIES.lock().get(&pid).cloned())
+        .unwrap_or_else(|| "/".to_string())
+}
+
+/// 現在のプロセスの作業ディレクトリを変更
+pub fn change_directory(path: &str) -> FsResult<()> {
+    let resolved = resolve_path(path, lookup_flags::DIRECTORY)?;
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    WORKING_DIRECTORIES.lock().insert(pid, resolved.path);
+    Ok(())
+}
+
+/// 子プロセスに親の作業ディレクトリを引き継ぐ
+fn inherit_working_directory(parent: ProcessId, child: ProcessId) {
+    let mut directories = WORKING_DIRECTORIES.lock();
+    if let Some(cwd) = directories.get(&parent).cloned() {
+        directories.insert(child, cwd);
+    }
+}
+
+/// 終了したプロセスの作業ディレクトリを破棄
+fn release_working_directory(pid: ProcessId) {
+    WORKING_DIRECTORIES.lock().remove(&pid);
+}
+
+/// プロセスごとの資格情報（未設定のプロセスとカーネルはroot）
+static CREDENTIALS: Mutex<BTreeMap<ProcessId, Credentials>> = Mutex::new(BTreeMap::new());
+
+/// 現在のプロセスの資格情報
+pub(super) fn current_credentials() -> Credentials {
+    current_process_id()
+        .and_then(|pid| CREDENTIALS.lock().get(&pid).cloned())
+        .unwrap_or_else(Credentials::root)
+}
+
+/// 現在のプロセスの資格情報を設定（setuid/setgid相当）
+pub fn set_credentials(credentials: Credentials) -> FsResult<()> {
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    CREDENTIALS.lock().insert(pid, credentials);
+    Ok(())
+}
+
+/// プロセスの作業ディレクトリ（procfs用）
+pub fn working_directory_of(pid: ProcessId) -> Option<String> {
+    WORKING_DIRECTORIES.lock().get(&pid).cloned()
+}
+
+/// 開いているファイルディスクリプタ
+#[derive(Clone)]
+struct FileDescriptor {
+    /// ファイルハンドル
+    handle: Arc<dyn FileHandle>,
+    /// 開いたときのパス
+    path: String,
+}
+
+/// プロセスごとのファイルディスクリプタ表
+static FILE_DESCRIPTORS: Mutex<BTreeMap<ProcessId, BTreeMap<usize, FileDescriptor>>> = Mutex::new(BTreeMap::new());
+
+/// 現在のプロセスに、空いている最小の番号でファイルディスクリプタを割り当てる
+pub fn install_descriptor(handle: Arc<dyn FileHandle>, path: &str) -> FsResult<usize> {
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    let mut tables = FILE_DESCRIPTORS.lock();
+    let table = tables.entry(pid).or_default();
+    
+    let fd = (0..).find(|fd| !table.contains_key(fd)).unwrap_or_default();
+    table.insert(fd, FileDescriptor { handle, path: path.to_string() });
+    Ok(fd)
+}
+
+/// 現在のプロセスのファイルディスクリプタを取得
+fn current_descriptor(fd: usize) -> FsResult<FileDescriptor> {
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    FILE_DESCRIPTORS.lock().get(&pid)
+        .and_then(|table| table.get(&fd))
+        .cloned()
+        .ok_or(FsError::NotFound)
+}
+
+/// 現在のプロセスのファイルディスクリプタからハンドルを取得
+pub fn get_descriptor(fd: usize) -> FsResult<Arc<dyn FileHandle>> {
+    current_descriptor(fd).map(|descriptor| descriptor.handle)
+}
+
+/// ファイルを開いて現在のプロセスのファイルディスクリプタに割り当てる（openシステムコール）
+pub fn open(path: &str, mode: OpenMode, flags: u32) -> FsResult<usize> {
+    let handle = open_file_with_flags(path, mode, flags)?;
+    // /proc/<pid>/fdに出すのは解決済みの絶対パス
+    let path = resolve_path(path, flags).map_or_else(|_| normalize_path(path), |resolved| resolved.path);
+    install_descriptor(handle, &path)
+}
+
+/// ファイルディスクリプタを空いている最小の番号に複製する（dupシステムコール）
+///
+/// 複製はオープンファイル記述を共有するので、flockのロックも共有する
+pub fn dup(fd: usize) -> FsResult<usize> {
+    let descriptor = current_descriptor(fd)?;
+    install_descriptor(descriptor.handle, &descriptor.path)
+}
+
+/// 現在のプロセスのファイルディスクリプタを閉じる
+pub fn close_descriptor(fd: usize) -> FsResult<()> {
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    let descriptor = FILE_DESCRIPTORS.lock().get_mut(&pid)
+        .and_then(|table| table.remove(&fd))
+        .ok_or(FsError::NotFound)?;
+    
+    // POSIXの規則どおり、どのディスクリプタを閉じてもそのファイルのバイト範囲ロックはすべて外れる
+    if let Some(file) = descriptor.handle.mapping_id() {
+        lock::release_file(file, pid);
+    }
+    
+    // ハンドルの解放（ページキャッシュの書き出しなど）はロックの外で行う
+    drop(descriptor);
+    Ok(())
+}
+
+/// 子プロセスに親のファイルディスクリプタを複製する
+fn inherit_descriptors(parent: ProcessId, child: ProcessId) {
+    let mut tables = FILE_DESCRIPTORS.lock();
+    if let Some(table) = tables.get(&parent).cloned() {
+        tables.insert(child, table);
+    }
+}
+
+/// 終了したプロセスのファイルディスクリプタをすべて閉じる
+fn close_all_descriptors(pid: ProcessId) {
+    let table = FILE_DESCRIPTORS.lock().remove(&pid);
+    drop(table);
+}
+
+/// fork時に子プロセスへ親の資格情報、作業ディレクトリとファイルディスクリプタを引き継ぐ
+pub fn fork_process(parent: ProcessId, child: ProcessId) {
+    let credentials = CREDENTIALS.lock().get(&parent).cloned();
+    if let Some(credentials) = credentials {
+        CREDENTIALS.lock().insert(child, credentials);
+    }
+    inherit_working_directory(parent, child);
+    inherit_descriptors(parent, child);
+}
+
+/// 終了したプロセスのファイルディスクリプタを閉じてロックを外し、作業ディレクトリを破棄する
+///
+/// ロックは終了したプロセスが持っていたものすべてを外し、待っていた他のプロセスを起こす
+pub fn exit_process(pid: ProcessId) {
+    close_all_descriptors(pid);
+    lock::release_process(pid);
+    release_working_directory(pid);
+    CREDENTIALS.lock().remove(&pid);
+}
+
+/// 現在のプロセスのファイルディスクリプタに制御要求を送る
+pub fn ioctl(fd: usize, request: u32, arg: usize) -> FsResult<usize> {
+    get_descriptor(fd)?.ioctl(request, arg)
+}
+
+/// バイト範囲の終端（`len`が0ならファイル末尾以降すべて）
+fn lock_end(start: u64, len: u64) -> FsResult<u64> {
+    match len {
+        0 => Ok(u64::MAX),
+        len => start.checked_add(len).ok_or(FsError::InvalidData),
+    }
+}
+
+/// 現在のプロセスとして、ファイルディスクリプタの`[start, start + len)`をロックする（fcntlのF_SETLK/F_SETLKW）
+///
+/// `len`が0なら`start`以降すべて、`kind`が`None`なら解除。競合すると`wait`なら解除されるまで待ち、
+/// そうでなければResourceBusyを返す。待つとデッドロックになる場合はDeadlockを返す。
+pub fn lock_range(fd: usize, kind: Option<LockKind>, start: u64, len: u64, wait: bool) -> FsResult<()> {
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    let file = get_descriptor(fd)?.mapping_id().ok_or(FsError::NotSupported)?;
+    lock::set_range(file, pid, kind, start, lock_end(start, len)?, wait)
+}
+
+/// 現在のプロセスが範囲をロックするときに競合するロックを返す（fcntlのF_GETLK）
+pub fn test_lock(fd: usize, kind: LockKind, start: u64, len: u64) -> FsResult<Option<RangeLock>> {
+    let pid = current_process_id().ok_or(FsError::NotSupported)?;
+    let file = get_descriptor(fd)?.mapping_id().ok_or(FsError::NotSupported)?;
+    Ok(lock::test_range(file, pid, kind, start, lock_end(start, len)?))
+}
+
+/// ファイルディスクリプタのオープンファイル記述でファイル全体をロックする（flock。`kind`が`None`なら解除）
+pub fn flock(fd: usize, kind: Option<LockKind>, wait: bool) -> FsResult<()> {
+    let handle = get_descriptor(fd)?;
+    match kind {
+        Some(kind) if wait => handle.lock(kind == LockKind::Exclusive),
+        Some(kind) => handle.try_lock(kind == LockKind::Exclusive),
+        None => handle.unlock(),
+    }
+}
+
+/// プロセスの開いているファイルディスクリプタと、そのパスの一覧
+pub fn open_descriptors(pid: ProcessId) -> Vec<(usize, String)> {
+    FILE_DESCRIPTORS.lock().get(&pid)
+        .map(|table| table.iter().map(|(fd, descriptor)| (*fd, descriptor.path.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// マウントの情報
+#[derive(Debug, Clone)]
+pub struct MountInfo {
+    /// デバイスパス
+    pub device: String,
+    /// マウントポイント
+    pub path: String,
+    /// ファイルシステムの種類
+    pub fs_type: String,
+    /// マウントオプション
+    pub options: String,
+}
+
+/// マウントの一覧（マウントした順）
+pub fn mounts() -> Vec<MountInfo> {
+    let registry = FS_REGISTRY.lock();
+    registry.as_ref()
+        .map(|reg| reg.mount_points.iter()
+            .map(|mp| MountInfo {
+                device: mp.device.clone(),
+                path: mp.path.clone(),
+                fs_type: mp.fs.name().to_string(),
+                options: mp.options.clone(),
+            })
+            .collect())
+        .unwrap_or_default()
+}
+
+/// マウント情報を複製して取得（ファイルシステムを呼び出す間はレジストリをロックしない）
+fn mount_table() -> FsResult<Vec<MountEntry>> {
+    let registry = FS_REGISTRY.lock();
+    registry.as_ref().map(FilesystemRegistry::mount_entries).ok_or(FsError::NotSupported)
+}
+
+/// パスを解決（相対パスは現在のプロセスの作業ディレクトリから辿る）
+pub fn resolve_path(path: &str, flags: u32) -> FsResult<ResolvedPath> {
+    namei::walk(&mount_table()?, &current_directory(), path, flags)
+}
+
 /// ファイルを開く
 pub fn open_file(path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
-    let registry = FS_REGISTRY.lock();
+    open_file_with_flags(path, mode, 0)
+}
+
+/// 探索フラグを指定してファイルを開く
+///
+/// `lookup_flags::NOFOLLOW`で最後の要素がシンボリックリンクだった場合はSymlinkLoopを返す
+pub fn open_file_with_flags(path: &str, mode: OpenMode, flags: u32) -> FsResult<Arc<dyn FileHandle>> {
+    open_file_as(path, mode, flags, &current_credentials())
+}
+
+/// `credentials`の権限でファイルを開く
+fn open_file_as(path: &str, mode: OpenMode, flags: u32, credentials: &Credentials) -> FsResult<Arc<dyn FileHandle>> {
+    let mounts = mount_table()?;
+    let cwd = current_directory();
     
-    if let Some(reg) = registry.as_ref() {
-        let normalized_path = normalize_path(path);
-        let mp = reg.find_mount_point(&normalized_path)
-            .ok_or(FsError::NotFound)?;
-        
-        // マウントポイントのプレフィックスを削除してファイルシステム内の相対パスを取得
-        let rel_path = normalized_path.strip_prefix(&mp.path)
-            .unwrap_or(&normalized_path);
-        
-        // スラッシュで始まるようにする
-        let rel_path = if rel_path.starts_with('/') {
-            rel_path.to_string()
-        } else {
-            format!("/{}", rel_path)
-        };
-        
-        mp.fs.open_file(&rel_path, mode)
-    } else {
-        Err(FsError::NotSupported)
+    match namei::walk(&mounts, &cwd, path, flags) {
+        Ok(resolved) if resolved.file_type == FileType::SymbolicLink => Err(FsError::SymlinkLoop),
+        Ok(_) if mode == OpenMode::CreateNew => Err(FsError::AlreadyExists),
+        Ok(resolved) if resolved.file_type == FileType::Regular && !resolved.fs.is_virtual() => {
+            check_permission(&*resolved.fs, &resolved.mount_point, &resolved.fs_path, resolved.file_type, credentials, open_access(mode))?;
+            let handle = resolved.fs.open_file(&resolved.mount_point, &resolved.fs_path, mode)?;
+            let handle = page_cache::open(resolved.fs_id, handle, mode)?;
+            notify::watch_writes(handle, mode, &resolved.fs, &resolved.mount_point, resolved.fs_id, &resolved.fs_path)
+        },
+        Ok(resolved) => {
+            check_permission(&*resolved.fs, &resolved.mount_point, &resolved.fs_path, resolved.file_type, credentials, open_access(mode))?;
+            resolved.fs.open_file(&resolved.mount_point, &resolved.fs_path, mode)
+        },
+        Err(FsError::NotFound) if matches!(mode, OpenMode::Create | OpenMode::CreateNew) => {
+            // 最後の要素だけが存在しない場合は親ディレクトリのファイルシステムに作成させる
+            let (parent, name) = namei::walk_parent(&mounts, &cwd, path)?;
+            check_permission(&*parent.fs, &parent.mount_point, &parent.fs_path, FileType::Directory, credentials, access::WRITE | access::EXECUTE)?;
+            let fs_path = parent.child_fs_path(&name);
+            let result = parent.fs.open_file(&parent.mount_point, &fs_path, mode);
+            dcache::invalidate(parent.fs_id, parent.inode, &name);
+            if parent.fs.is_virtual() {
+                return result;
+            }
+            let handle = page_cache::open(parent.fs_id, result?, mode)?;
+            if notify::is_active() {
+                if let Ok(dir) = parent.fs.metadata(&parent.mount_point, &parent.fs_path) {
+                    notify::child_event(parent.fs_id, dir.inode, event_mask::CREATE, &name, false, 0);
+                }
+            }
+            notify::watch_writes(handle, mode, &parent.fs, &parent.mount_point, parent.fs_id, &fs_path)
+        },
+        Err(e) => Err(e),
     }
 }
 
 /// ディレクトリを開く
 pub fn open_directory(path: &str) -> FsResult<Arc<dyn DirHandle>> {
-    let registry = FS_REGISTRY.lock();
-    
-    if let Some(reg) = registry.as_ref() {
-        let normalized_path = normalize_path(path);
-        let mp = reg.find_mount_point(&normalized_path)
-            .ok_or(FsError::NotFound)?;
-        
-        // マウントポイントのプレフィックスを削除
-        let rel_path = normalized_path.strip_prefix(&mp.path)
-            .unwrap_or(&normalized_path);
-        
-        // スラッシュで始まるようにする
-        let rel_path = if rel_path.starts_with('/') {
-            rel_path.to_string()
-        } else {
-            format!("/{}", rel_path)
-        };
-        
-        mp.fs.open_directory(&rel_path)
-    } else {
-        Err(FsError::NotSupported)
+    let resolved = resolve_path(path, lookup_flags::DIRECTORY)?;
+    let handle = resolved.fs.open_directory(&resolved.mount_point, &resolved.fs_path)?;
+    Ok(Arc::new(CachedDirHandle::new(handle, resolved.fs.clone(), resolved.mount_point, resolved.fs_id, resolved.fs_path)))
+}
+
+/// 解決済みパスのメタデータ（ページキャッシュ上の未書き出しのサイズを反映する）
+fn resolved_metadata(resolved: &ResolvedPath) -> FsResult<Metadata> {
+    let mut metadata = resolved.fs.metadata(&resolved.mount_point, &resolved.fs_path)?;
+    if let Some(size) = page_cache::cached_size(resolved.fs_id, metadata.inode) {
+        metadata.size = size;
     }
+    Ok(metadata)
 }
 
-/// ファイル/ディレクトリのメタデータを取得
+/// ファイル/ディレクトリのメタデータを取得（シンボリックリンクは辿る）
 pub fn metadata(path: &str) -> FsResult<Metadata> {
-    let registry = FS_REGISTRY.lock();
-    
-    if let Some(reg) = registry.as_ref() {
-        let normalized_path = normalize_path(path);
-        let mp = reg.find_mount_point(&normalized_path)
-            .ok_or(FsError::NotFound)?;
-        
-        // マウントポイントのプレフィックスを削除
-        let rel_path = normalized_path.strip_prefix(&mp.path)
-            .unwrap_or(&normalized_path);
-        
-        // スラッシュで始まるようにする
-        let rel_path = if rel_path.starts_with('/') {
-            rel_path.to_string()
-        } else {
-            format!("/{}", rel_path)
-        };
-        
-        mp.fs.metadata(&rel_path)
+    resolved_metadata(&resolve_path(path, 0)?)
+}
+
+/// メタデータを取得（最後の要素がシンボリックリンクならリンク自身）
+pub fn symlink_metadata(path: &str) -> FsResult<Metadata> {
+    resolved_metadata(&resolve_path(path, lookup_flags::NOFOLLOW)?)
+}
+
+/// シンボリックリンクのターゲットを読み取り
+pub fn read_link(path: &str) -> FsResult<String> {
+    let resolved = resolve_path(path, lookup_flags::NOFOLLOW)?;
+    if resolved.file_type != FileType::SymbolicLink {
+        return Err(FsError::InvalidData);
+    }
+    resolved.fs.read_link(&resolved.mount_point, &resolved.fs_path)
+}
+
+/// 拡張属性を操作するため`path`を開く（シンボリックリンクは辿る）
+fn open_xattr_target(path: &str) -> FsResult<(ResolvedPath, XattrTarget)> {
+    let resolved = resolve_path(path, 0)?;
+    let target = XattrTarget::open(&*resolved.fs, &resolved.mount_point, &resolved.fs_path, resolved.file_type)?;
+    Ok((resolved, target))
+}
+
+/// 拡張属性を取得
+pub fn get_xattr(path: &str, name: &str) -> FsResult<Vec<u8>> {
+    XattrNamespace::of(name)?;
+    open_xattr_target(path)?.1.get(name)
+}
+
+/// 拡張属性を設定
+pub fn set_xattr(path: &str, name: &str, value: &[u8]) -> FsResult<()> {
+    xattr::validate(name, value)?;
+    let (resolved, target) = open_xattr_target(path)?;
+    target.set(name, value)?;
+    notify::attrib_changed(&resolved.fs, &resolved.mount_point, resolved.fs_id, &resolved.fs_path, resolved.file_type == FileType::Directory);
+    Ok(())
+}
+
+/// 拡張属性の名前を列挙
+pub fn list_xattr(path: &str) -> FsResult<Vec<String>> {
+    open_xattr_target(path)?.1.list()
+}
+
+/// 拡張属性を削除
+pub fn remove_xattr(path: &str, name: &str) -> FsResult<()> {
+    XattrNamespace::of(name)?;
+    let (resolved, target) = open_xattr_target(path)?;
+    target.remove(name)?;
+    notify::attrib_changed(&resolved.fs, &resolved.mount_point, resolved.fs_id, &resolved.fs_path, resolved.file_type == FileType::Directory);
+    Ok(())
+}
+
+/// アクセスACL（system.posix_acl_access）を取得（設定されていなければNone）
+pub fn get_acl(path: &str) -> FsResult<Option<PosixAcl>> {
+    match get_xattr(path, POSIX_ACL_ACCESS) {
+        Ok(value) => PosixAcl::from_xattr(&value).map(Some),
+        Err(FsError::NotFound) | Err(FsError::NotSupported) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// アクセスACLを設定
+pub fn set_acl(path: &str, acl: &PosixAcl) -> FsResult<()> {
+    set_xattr(path, POSIX_ACL_ACCESS, &acl.to_xattr())
+}
+
+/// `credentials`で`path`に`want`（`xattr::access`のビット）のアクセスができるか確認
+///
+/// アクセスACLがあればそれで、なければ権限から作る最小ACLで判定する。
+pub fn check_access(path: &str, credentials: &Credentials, want: u8) -> FsResult<()> {
+    let resolved = resolve_path(path, 0)?;
+    permission(&*resolved.fs, &resolved.mount_point, &resolved.fs_path, resolved.file_type, credentials, want)
+}
+
+/// ファイルシステム内の`path`に`credentials`で`want`のアクセスができるか判定
+fn permission(fs: &dyn Filesystem, mount_point: &str, path: &str, file_type: FileType, credentials: &Credentials, want: u8) -> FsResult<()> {
+    let metadata = fs.metadata(mount_point, path)?;
+    // 仮想ファイルシステムは拡張属性を持たず、デバイスを開くと副作用があるので権限だけで判定する
+    let acl = if fs.is_virtual() {
+        None
     } else {
-        Err(FsError::NotSupported)
+        match XattrTarget::open(fs, mount_point, path, file_type).and_then(|target| target.get(POSIX_ACL_ACCESS)) {
+            Ok(value) => Some(PosixAcl::from_xattr(&value)?),
+            Err(FsError::NotFound) | Err(FsError::NotSupported) => None,
+            Err(e) => return Err(e),
+        }
+    };
+    xattr::check_access(&metadata, acl.as_ref(), credentials, want)
+}
+
+/// `credentials`でファイルシステム内の`path`に`want`のアクセスをできるか確認（open、作成、削除、実行）
+pub(super) fn check_permission(fs: &dyn Filesystem, mount_point: &str, path: &str, file_type: FileType, credentials: &Credentials, want: u8) -> FsResult<()> {
+    // rootの読み書きはACLを読まずに許可する
+    if credentials.uid == 0 && want & access::EXECUTE == 0 {
+        return Ok(());
     }
+    permission(fs, mount_point, path, file_type, credentials, want)
+}
+
+/// オープンモードに必要なアクセス
+fn open_access(mode: OpenMode) -> u8 {
+    match mode {
+        OpenMode::ReadOnly => access::READ,
+        OpenMode::WriteOnly | OpenMode::Append | OpenMode::Truncate => access::WRITE,
+        OpenMode::ReadWrite | OpenMode::Create | OpenMode::CreateNew => access::READ | access::WRITE,
+    }
+}
+
+/// 実行するファイルの内容を読み込む（execの読み込み。実行権限がなければPermissionDenied）
+pub fn read_executable(path: &str) -> FsResult<Vec<u8>> {
+    let resolved = resolve_path(path, 0)?;
+    if resolved.file_type != FileType::Regular {
+        return Err(FsError::PermissionDenied);
+    }
+    check_permission(&*resolved.fs, &resolved.mount_point, &resolved.fs_path, resolved.file_type, &current_credentials(), access::EXECUTE)?;
+    
+    // 書き出し前の変更も見えるようページキャッシュを経由して読む
+    let handle = resolved.fs.open_file(&resolved.mount_point, &resolved.fs_path, OpenMode::ReadOnly)?;
+    let handle = if resolved.fs.is_virtual() { handle } else { page_cache::open(resolved.fs_id, handle, OpenMode::ReadOnly)? };
+    let mut data = alloc::vec![0; handle.size()? as usize];
+    let mut done = 0;
+    while done < data.len() {
+        let read = handle.read(&mut data[done..], done as u64)?;
+        if read == 0 {
+            break;
+        }
+        done += read;
+    }
+    data.truncate(done);
+    Ok(data)
 }
 
 /// すべてのファイルシステムを同期
 pub fn sync_all() -> FsResult<()> {
+    // 先にページキャッシュのダーティページをファイルシステムへ書き出す
+    let _ = page_cache::writeback_all();
+    
     let registry = FS_REGISTRY.lock();
     
     if let Some(reg) = registry.as_ref() {
@@ -508,278 +1107,106 @@ pub fn sync_all() -> FsResult<()> {
     }
 }
 
-/// ブロックデバイスを開く
-pub fn open_block_device(path: &str) -> FsResult<Arc<dyn BlockDevice>> {
-    // デバイスパスからデバイスドライバを決定
-    fn get_device_driver(device_path: &str) -> FsResult<Arc<dyn DeviceDriver>> {
-        // デバイスのパスからタイプを判断
-        if device_path.starts_with("/dev/sd") {
-            // SCSIディスク
-            return scsi::get_driver(device_path);
-        } else if device_path.starts_with("/dev/hd") {
-            // IDEディスク
-            return ide::get_driver(device_path);
-        } else if device_path.starts_with("/dev/nvme") {
-            // NVMeディスク
-            return nvme::get_driver(device_path);
-        } else if device_path.starts_with("/dev/mmcblk") {
-            // eMMC/SDカード
-            return mmc::get_driver(device_path);
-        } else if device_path.starts_with("/dev/loop") {
-            // ループデバイス
-            return loopdev::get_driver(device_path);
-        } else if device_path.starts_with("/dev/ram") {
-            // RAMディスク
-            return ramdisk::get_driver(device_path);
-        } else {
-            // その他のデバイス：ジェネリックブロックドライバを使用
-            return generic::get_driver(device_path);
-        }
-    }
+/// マウント中のファイルシステムの整合性をチェック（`repair`なら見つかった問題を修復する）
+///
+/// 修復はディスク上のメタデータを直接書き換えるため、先にページキャッシュを書き出して
+/// 破棄し、終わったらdcacheを捨てる
+pub fn fsck(mount_point: &str, repair: bool) -> FsResult<FsckReport> {
+    let mount_point = normalize_path(mount_point);
+    let (id, fs) = {
+        let registry = FS_REGISTRY.lock();
+        let mp = registry.as_ref()
+            .ok_or(FsError::NotSupported)?
+            .mount_points.iter()
+            .rfind(|mp| mp.path == mount_point)
+            .ok_or(FsError::NotFound)?;
+        (mp.id, mp.fs.clone())
+    };
     
-    // デバイスパスからデバイスファイルを開く
-    match crate::fs::devfs::open_device(path) {
-        Ok(handle) => {
-            let device_id = calculate_device_id(path);
-            let device_info = handle.get_device_info()?;
-            
-            Ok(Arc::new(Self {
-                path: path.to_string(),
-                device_id,
-                block_size: device_info.block_size,
-                total_blocks: device_info.total_blocks,
-            }))
-        },
-        Err(_) => {
-            // 通常のファイルとして開く
-            let file = crate::fs::open_file(path, OpenMode::ReadWrite)?;
-            let size = file.size()?;
-            let block_size = 512; // デフォルトブロックサイズ
-            let total_blocks = (size + block_size - 1) / block_size;
-            
-            Ok(Arc::new(Self {
-                path: path.to_string(),
-                device_id: calculate_device_id(path),
-                block_size,
-                total_blocks,
-            }))
-        }
-    }
-}
-
-/// ファイルベースのブロックデバイス実装
-struct FileBlockDevice {
-    path: String,
-    device_id: u64,
-    block_size: u64,
-    total_blocks: u64,
-}
-
-impl FileBlockDevice {
-    /// ファイルベースのブロックデバイスを開く
-    fn open(path: &str) -> FsResult<Self> {
-        // 実際のファイルを開く
-        let fs_type = identify_filesystem(path)?;
-        
-        // ファイルシステム固有のオープン処理
-        let mut file = match fs_type {
-            FilesystemType::Ext4 => {
-                crate::fs::ext4::open_file(path, OpenMode::ReadOnly)?
-            },
-            FilesystemType::ExFat => {
-                crate::fs::exfat::open_file(path, OpenMode::ReadOnly)?
-            },
-            FilesystemType::Ntfs => {
-                crate::fs::ntfs::open_file(path, OpenMode::ReadOnly)?
-            },
-            FilesystemType::Fat32 => {
-                crate::fs::fat32::open_file(path, OpenMode::ReadOnly)?
-            },
-            // その他のファイルシステムタイプに対応
-            _ => return Err(FsError::UnsupportedFilesystem),
-        };
-        
-        // ファイルのメタデータを取得
-        let metadata = file.metadata()?;
-        let size = metadata.size;
-        
-        Ok(Self {
-            path: path.to_string(),
-            size,
-            current_position: 0,
-            is_open: true,
-        })
+    if !repair {
+        fs.sync()?;
+        return fs.check(&mount_point);
     }
+    
+    page_cache::release_filesystem(id)?;
+    fs.sync()?;
+    let report = fs.repair(&mount_point);
+    dcache::invalidate_filesystem(id);
+    report
 }
 
-impl BlockDevice for FileBlockDevice {
-    fn device_id(&self) -> u64 {
-        self.device_id
-    }
+/// マウントしていないデバイスの整合性をチェック（`repair`なら見つかった問題を修復する）
+///
+/// ドライバにデバイスのパスを一時的なマウントポイントとしてマウントさせるが、
+/// マウント表には登録しない。マウント中のデバイスは修復が書き込みと競合するため
+/// ResourceBusyを返し、チェックが終わるまでそのデバイスのマウントも拒否する
+pub fn fsck_device(fs_type: &str, device: &str, repair: bool) -> FsResult<FsckReport> {
+    let name = super::devfs::device_name(device).to_string();
+    let fs = {
+        let mut registry = FS_REGISTRY.lock();
+        let reg = registry.as_mut().ok_or(FsError::NotSupported)?;
+        if reg.device_in_use(device) {
+            return Err(FsError::ResourceBusy);
+        }
+        let fs = reg.filesystems.get(fs_type).cloned().ok_or(FsError::NotFound)?;
+        reg.checking.insert(name.clone());
+        fs
+    };
     
-    fn block_size(&self) -> u64 {
-        self.block_size
-    }
+    let mount_point = format!("/dev/{}", name);
+    let report = fs.mount(device, &mount_point, if repair { "rw" } else { "ro" }).and_then(|()| {
+        let report = if repair { fs.repair(&mount_point) } else { fs.check(&mount_point) };
+        let unmounted = fs.unmount(&mount_point);
+        let report = report?;
+        unmounted?;
+        Ok(report)
+    });
     
-    fn total_blocks(&self) -> u64 {
-        self.total_blocks
+    if let Some(reg) = FS_REGISTRY.lock().as_mut() {
+        reg.checking.remove(&name);
     }
-    
-    fn read_block(&self, block_index: u64) -> FsResult<Vec<u8>> {
-        let offset = block_index * self.block_size;
-        let buffer_size = self.block_size as usize;
+    report
+}
 
-        match crate::fs::devfs::open_device(&self.path) {
-            Ok(device) => {
-                // デバイスから直接読み込み
-                let mut buffer = vec![0; buffer_size];
-                let bytes_read = device.read(&mut buffer, offset)?;
-                
-                if bytes_read < buffer_size {
-                    // パディング
-                    buffer.resize(buffer_size, 0);
-                }
-                
-                Ok(buffer)
-            },
-            Err(_) => {
-                // 通常のファイルとして読み込み
-                let file = crate::fs::open_file(&self.path, OpenMode::ReadOnly)?;
-                let mut buffer = vec![0; buffer_size];
-                let bytes_read = file.read(&mut buffer, offset)?;
-                
-                if bytes_read < buffer_size {
-                    // パディング
-                    buffer.resize(buffer_size, 0);
-                }
-                
-                Ok(buffer)
-            }
-        }
-    }
-    
-    fn read_blocks(&self, start_block: u64, count: u64) -> FsResult<Vec<u8>> {
-        // 現在は単純な実装として、各ブロックを個別に読み込む
-        let mut result = Vec::with_capacity((self.block_size as usize) * (count as usize));
-        
-        for i in 0..count {
-            let block_data = self.read_block(start_block + i)?;
-            result.extend_from_slice(&block_data);
-        }
-        
-        Ok(result)
-    }
-    
-    fn write_block(&self, block_index: u64, data: &[u8]) -> FsResult<()> {
-        let offset = block_index * self.block_size;
+/// ブロックデバイスを開く（"/dev/sda1"のようなdevfs上のパス）
+pub fn open_block_device(path: &str) -> FsResult<Arc<dyn BlockDevice>> {
+    super::devfs::open_block_device(path)
+}
 
-        match crate::fs::devfs::open_device(&self.path) {
-            Ok(device) => {
-                // デバイスに直接書き込み
-                let bytes_written = device.write(data, offset)?;
-                
-                if bytes_written < data.len() {
-                    return Err(FsError::IoError);
-                }
-                
-                Ok(())
-            },
-            Err(_) => {
-                // 通常のファイルとして書き込み
-                let file = crate::fs::open_file(&self.path, OpenMode::ReadWrite)?;
-                let bytes_written = file.write(data, offset)?;
-                
-                if bytes_written < data.len() {
-                    return Err(FsError::IoError);
-                }
-                
-                Ok(())
-            }
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tmpfs::TmpfsFilesystem;
+    use super::super::xattr::{AclEntry, AclTag};
     
-    fn write_blocks(&self, start_block: u64, data: &[u8]) -> FsResult<()> {
-        // 現在は単純な実装として、各ブロックを個別に書き込む
-        let block_size = self.block_size as usize;
-        let total_blocks = (data.len() + block_size - 1) / block_size;
+    #[test]
+    fn acl_denies_open_and_create() {
+        init().unwrap();
+        register_filesystem("tmpfs", TmpfsFilesystem::new()).unwrap();
+        mount("tmpfs", "none", "/", "").unwrap();
+        open_directory("/").unwrap().create_directory("shared", Permissions::default()).unwrap();
+        open_file("/shared/file", OpenMode::Create).unwrap();
         
-        for i in 0..total_blocks {
-            let block_index = start_block + (i as u64);
-            let start = i * block_size;
-            let end = core::cmp::min((i + 1) * block_size, data.len());
-            
-            // ブロックデータを準備
-            let mut block_data = Vec::with_capacity(block_size);
-            block_data.extend_from_slice(&data[start..end]);
-            
-            // ブロックサイズに満たない場合はゼロで埋める
-            if block_data.len() < block_size {
-                block_data.resize(block_size, 0);
-            }
-            
-            self.write_block(block_index, &block_data)?;
-        }
+        // 1000だけを拒否し、他のユーザーには読み取りだけを許す
+        let entry = |tag, perm| AclEntry { tag, perm };
+        let acl = PosixAcl::new(alloc::vec![
+            entry(AclTag::UserObj, access::READ | access::WRITE),
+            entry(AclTag::User(1000), 0),
+            entry(AclTag::GroupObj, access::READ),
+            entry(AclTag::Mask, access::READ),
+            entry(AclTag::Other, access::READ),
+        ]).unwrap();
+        set_acl("/shared/file", &acl).unwrap();
         
-        Ok(())
-    }
-    
-    fn sync(&self) -> FsResult<()> {
-        // デバイスに応じた同期処理を実行
-        if let Ok(device) = crate::fs::devfs::open_device(&self.path) {
-            // デバイスを同期
-            device.sync()?;
-            return Ok(());
-        }
-
-        // 通常のファイルの場合
-        match identify_filesystem(&self.path)? {
-            FilesystemType::Ext4 => crate::fs::ext4::sync_file(&self.path),
-            FilesystemType::ExFat => crate::fs::exfat::sync_file(&self.path),
-            FilesystemType::Ntfs => crate::fs::ntfs::sync_file(&self.path),
-            FilesystemType::Fat32 => crate::fs::fat32::sync_file(&self.path),
-            _ => {
-                // ジャーナルがある場合は同期
-                if crate::fs::journal::has_journal(&self.path) {
-                    crate::fs::journal::sync()?;
-                }
-                
-                // キャッシュバッファをフラッシュ
-                crate::fs::cache::flush_file(&self.path)?;
-                
-                Ok(())
-            }
-        }
-    }
-    
-    fn close(&self) -> FsResult<()> {
-        // ファイルシステムタイプを特定
-        let fs_type = identify_filesystem(&self.path)?;
+        let denied = Credentials::new(1000, 100);
+        assert!(matches!(open_file_as("/shared/file", OpenMode::ReadOnly, 0, &denied), Err(FsError::PermissionDenied)));
         
-        // 変更があればデータをフラッシュ
-        self.sync()?;
+        let other = Credentials::new(2000, 100);
+        open_file_as("/shared/file", OpenMode::ReadOnly, 0, &other).unwrap();
+        assert!(matches!(open_file_as("/shared/file", OpenMode::ReadWrite, 0, &other), Err(FsError::PermissionDenied)));
         
-        // ファイルシステム固有のクローズ処理
-        match fs_type {
-            FilesystemType::Ext4 => crate::fs::ext4::close_file(&self.path),
-            FilesystemType::ExFat => crate::fs::exfat::close_file(&self.path),
-            FilesystemType::Ntfs => crate::fs::ntfs::close_file(&self.path),
-            FilesystemType::Fat32 => crate::fs::fat32::close_file(&self.path),
-            _ => {
-                // 汎用的なクローズ処理
-                // リソース解放と関連するキャッシュのクリア
-                crate::fs::cache::invalidate_file(&self.path)?;
-                Ok(())
-            }
-        }
+        // rootのディレクトリには書き込み権限がないので作成できない
+        assert!(matches!(open_file_as("/shared/new", OpenMode::Create, 0, &other), Err(FsError::PermissionDenied)));
+        open_file_as("/shared/new", OpenMode::Create, 0, &Credentials::root()).unwrap();
     }
 }
-
-/// デバイスパスからデバイスIDを計算
-fn calculate_device_id(path: &str) -> u64 {
-    // シンプルなハッシュ関数
-    let mut id: u64 = 0;
-    for byte in path.bytes() {
-        id = id.wrapping_mul(31).wrapping_add(byte as u64);
-    }
-    id
-} 
\ No newline at end of file
@@ -0,0 +1,449 @@
+// 拡張属性の名前空間とPOSIX ACL
+//
+// VFSの拡張属性は"user."・"trusted."・"security."・"system."のいずれかの名前空間に属する。
+// system.posix_acl_access/defaultの値はLinuxのposix_acl_xattr形式（バージョン2、
+// 8バイトのエントリ列）で受け渡し、アクセス判定ではモードビットより優先する
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::{FsError, FsResult, FileType, Metadata, Permissions, FileHandle, DirHandle, Filesystem, OpenMode};
+
+/// 拡張属性名の最大長（名前空間の接頭辞を含む）
+pub const XATTR_NAME_MAX: usize = 255;
+
+/// 拡張属性の値の最大長
+pub const XATTR_SIZE_MAX: usize = 65536;
+
+/// アクセス判定に使うACLの属性名
+pub const POSIX_ACL_ACCESS: &str = "system.posix_acl_access";
+
+/// ディレクトリに作るエントリへ継承するACLの属性名
+pub const POSIX_ACL_DEFAULT: &str = "system.posix_acl_default";
+
+/// posix_acl_xattr形式のバージョン
+const ACL_XATTR_VERSION: u32 = 2;
+
+/// 名前付きエントリ以外のID
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+
+/// アクセスの種類（ACLの権限ビットと同じ値）
+pub mod access {
+    /// 読み取り
+    pub const READ: u8 = 4;
+    /// 書き込み
+    pub const WRITE: u8 = 2;
+    /// 実行（ディレクトリでは検索）
+    pub const EXECUTE: u8 = 1;
+}
+
+/// 拡張属性の名前空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XattrNamespace {
+    /// 一般ユーザーの属性（"user."）
+    User,
+    /// 特権プロセスだけが扱う属性（"trusted."）
+    Trusted,
+    /// セキュリティモジュールのラベル（"security."）
+    Security,
+    /// カーネルが解釈する属性（"system."、POSIX ACLのみ）
+    System,
+}
+
+impl XattrNamespace {
+    /// 属性名の名前空間を判定し、名前を検証する
+    pub fn of(name: &str) -> FsResult<Self> {
+        const PREFIXES: [(&str, XattrNamespace); 4] = [
+            ("user.", XattrNamespace::User),
+            ("trusted.", XattrNamespace::Trusted),
+            ("security.", XattrNamespace::Security),
+            ("system.", XattrNamespace::System),
+        ];
+        let (namespace, suffix) = PREFIXES.iter()
+            .find_map(|(prefix, namespace)| name.strip_prefix(prefix).map(|suffix| (*namespace, suffix)))
+            .ok_or(FsError::NotSupported)?;
+        
+        if suffix.is_empty() || name.len() > XATTR_NAME_MAX || name.contains('\0') {
+            return Err(FsError::InvalidData);
+        }
+        if namespace == XattrNamespace::System && name != POSIX_ACL_ACCESS && name != POSIX_ACL_DEFAULT {
+            return Err(FsError::NotSupported);
+        }
+        Ok(namespace)
+    }
+}
+
+/// 設定する属性の名前と値を検証する（ACLは形式も確認する）
+pub fn validate(name: &str, value: &[u8]) -> FsResult<XattrNamespace> {
+    let namespace = XattrNamespace::of(name)?;
+    if value.len() > XATTR_SIZE_MAX {
+        return Err(FsError::InvalidData);
+    }
+    if namespace == XattrNamespace::System {
+        PosixAcl::from_xattr(value)?;
+    }
+    Ok(namespace)
+}
+
+/// ACLエントリの対象（並び順はACL内の順序と同じ）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AclTag {
+    /// 所有者
+    UserObj,
+    /// 名前付きユーザー
+    User(u32),
+    /// 所有グループ
+    GroupObj,
+    /// 名前付きグループ
+    Group(u32),
+    /// 名前付きエントリと所有グループに適用する上限
+    Mask,
+    /// その他
+    Other,
+}
+
+impl AclTag {
+    /// タグの値
+    pub(super) fn code(&self) -> u16 {
+        match self {
+            AclTag::UserObj => 0x01,
+            AclTag::User(_) => 0x02,
+            AclTag::GroupObj => 0x04,
+            AclTag::Group(_) => 0x08,
+            AclTag::Mask => 0x10,
+            AclTag::Other => 0x20,
+        }
+    }
+    
+    /// 名前付きエントリのID
+    pub(super) fn id(&self) -> Option<u32> {
+        match self {
+            AclTag::User(id) | AclTag::Group(id) => Some(*id),
+            _ => None,
+        }
+    }
+    
+    /// タグの値とIDから作成
+    pub(super) fn from_raw(code: u16, id: u32) -> FsResult<Self> {
+        Ok(match code {
+            0x01 => AclTag::UserObj,
+            0x02 => AclTag::User(id),
+            0x04 => AclTag::GroupObj,
+            0x08 => AclTag::Group(id),
+            0x10 => AclTag::Mask,
+            0x20 => AclTag::Other,
+            _ => return Err(FsError::InvalidData),
+        })
+    }
+}
+
+/// ACLエントリ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AclEntry {
+    /// 対象
+    pub tag: AclTag,
+    /// 権限（access::READ | WRITE | EXECUTE）
+    pub perm: u8,
+}
+
+/// POSIX ACL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PosixAcl {
+    /// 対象の順に並んだエントリ
+    entries: Vec<AclEntry>,
+}
+
+impl PosixAcl {
+    /// エントリを並べ替えて検証する
+    ///
+    /// 所有者・所有グループ・その他のエントリがちょうど1つずつ必要で、名前付きエントリが
+    /// あればマスクも必要になる。
+    pub fn new(mut entries: Vec<AclEntry>) -> FsResult<Self> {
+        entries.sort_by_key(|entry| entry.tag);
+        if entries.windows(2).any(|pair| pair[0].tag == pair[1].tag) || entries.iter().any(|entry| entry.perm > 7) {
+            return Err(FsError::InvalidData);
+        }
+        
+        let has = |tag: AclTag| entries.iter().any(|entry| entry.tag == tag);
+        let named = entries.iter().any(|entry| entry.tag.id().is_some());
+        if !has(AclTag::UserObj) || !has(AclTag::GroupObj) || !has(AclTag::Other) || (named && !has(AclTag::Mask)) {
+            return Err(FsError::InvalidData);
+        }
+        Ok(Self { entries })
+    }
+    
+    /// VFSの権限と同じ意味の最小ACL（書き込みは所有者だけ、読み取りと実行は全員）
+    pub fn from_permissions(permissions: Permissions) -> Self {
+        let others = if permissions.read { access::READ } else { 0 } | if permissions.execute { access::EXECUTE } else { 0 };
+        let owner = others | if permissions.write { access::WRITE } else { 0 };
+        Self {
+            entries: vec![
+                AclEntry { tag: AclTag::UserObj, perm: owner },
+                AclEntry { tag: AclTag::GroupObj, perm: others },
+                AclEntry { tag: AclTag::Other, perm: others },
+            ],
+        }
+    }
+    
+    /// エントリ
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+    
+    /// マスク（なければNone）
+    pub fn mask(&self) -> Option<u8> {
+        self.entries.iter().find(|entry| entry.tag == AclTag::Mask).map(|entry| entry.perm)
+    }
+    
+    /// 拡張属性の値（posix_acl_xattr形式）から解析
+    pub fn from_xattr(data: &[u8]) -> FsResult<Self> {
+        if data.len() < 4 || !(data.len() - 4).is_multiple_of(8) {
+            return Err(FsError::InvalidData);
+        }
+        if u32::from_le_bytes([data[0], data[1], data[2], data[3]]) != ACL_XATTR_VERSION {
+            return Err(FsError::UnsupportedVersion);
+        }
+        
+        let entries = data[4..].chunks_exact(8)
+            .map(|raw| {
+                let code = u16::from_le_bytes([raw[0], raw[1]]);
+                let perm = u16::from_le_bytes([raw[2], raw[3]]);
+                let id = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]);
+                if perm > 7 {
+                    return Err(FsError::InvalidData);
+                }
+                Ok(AclEntry { tag: AclTag::from_raw(code, id)?, perm: perm as u8 })
+            })
+            .collect::<FsResult<Vec<_>>>()?;
+        Self::new(entries)
+    }
+    
+    /// 拡張属性の値（posix_acl_xattr形式）に変換
+    pub fn to_xattr(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(4 + self.entries.len() * 8);
+        data.extend_from_slice(&ACL_XATTR_VERSION.to_le_bytes());
+        for entry in &self.entries {
+            data.extend_from_slice(&entry.tag.code().to_le_bytes());
+            data.extend_from_slice(&(entry.perm as u16).to_le_bytes());
+            data.extend_from_slice(&entry.tag.id().unwrap_or(ACL_UNDEFINED_ID).to_le_bytes());
+        }
+        data
+    }
+    
+    /// 所有者`owner`・所有グループ`group`のファイルに`want`のアクセスを許すか（POSIX.1eの判定順）
+    pub fn permits(&self, owner: u32, group: u32, credentials: &Credentials, want: u8) -> bool {
+        let mask = self.mask().unwrap_or(7);
+        let granted = |perm: u8| perm & want == want;
+        
+        if credentials.uid == owner {
+            return self.entries.iter().any(|entry| entry.tag == AclTag::UserObj && granted(entry.perm));
+        }
+        if let Some(entry) = self.entries.iter().find(|entry| entry.tag == AclTag::User(credentials.uid)) {
+            return granted(entry.perm & mask);
+        }
+        
+        // 一致するグループのエントリのどれかが許せば許可し、一致したのにどれも許さなければ拒否する
+        let mut matched = false;
+        for entry in &self.entries {
+            let member = match entry.tag {
+                AclTag::GroupObj => credentials.in_group(group),
+                AclTag::Group(gid) => credentials.in_group(gid),
+                _ => false,
+            };
+            if member {
+                if granted(entry.perm & mask) {
+                    return true;
+                }
+                matched = true;
+            }
+        }
+        if matched {
+            return false;
+        }
+        
+        self.entries.iter().any(|entry| entry.tag == AclTag::Other && granted(entry.perm))
+    }
+}
+
+/// アクセス判定に使う資格情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// ユーザーID
+    pub uid: u32,
+    /// 主グループID
+    pub gid: u32,
+    /// 補助グループID
+    pub groups: Vec<u32>,
+}
+
+impl Credentials {
+    /// 補助グループなしの資格情報
+    pub fn new(uid: u32, gid: u32) -> Self {
+        Self { uid, gid, groups: Vec::new() }
+    }
+    
+    /// root（読み書きの判定を常に通す）
+    pub fn root() -> Self {
+        Self::new(0, 0)
+    }
+    
+    /// 補助グループを設定
+    pub fn with_groups(mut self, groups: Vec<u32>) -> Self {
+        self.groups = groups;
+        self
+    }
+    
+    /// グループ`gid`に属するか
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+}
+
+/// メタデータとACL（なければ権限から作る最小ACL）で`want`のアクセスを判定する
+pub fn check_access(metadata: &Metadata, acl: Option<&PosixAcl>, credentials: &Credentials, want: u8) -> FsResult<()> {
+    let minimal;
+    let acl = match acl {
+        Some(acl) => acl,
+        None => {
+            minimal = PosixAcl::from_permissions(metadata.permissions);
+            &minimal
+        },
+    };
+    
+    let allowed = if credentials.uid == 0 {
+        // rootは読み書きを常に許可し、実行はディレクトリか誰かに実行権限がある場合だけ許可する
+        want & access::EXECUTE == 0
+            || metadata.file_type == FileType::Directory
+            || acl.entries.iter().any(|entry| entry.perm & access::EXECUTE != 0)
+    } else {
+        acl.permits(metadata.uid, metadata.gid, credentials, want)
+    };
+    
+    if allowed {
+        Ok(())
+    } else {
+        Err(FsError::PermissionDenied)
+    }
+}
+
+/// パスの拡張属性を操作するハンドル（ディレクトリとそれ以外で開き方が異なる）
+pub(super) enum XattrTarget {
+    /// ディレクトリ
+    Directory(Arc<dyn DirHandle>),
+    /// ディレクトリ以外（読み取り専用で開く）
+    File(Arc<dyn FileHandle>),
+}
+
+impl XattrTarget {
+    /// `mount_point`にマウントされたファイルシステム内の`path`を開く
+    pub(super) fn open(fs: &dyn Filesystem, mount_point: &str, path: &str, file_type: FileType) -> FsResult<Self> {
+        match file_type {
+            FileType::Directory => Ok(XattrTarget::Directory(fs.open_directory(mount_point, path)?)),
+            FileType::SymbolicLink => Err(FsError::NotSupported),
+            _ => Ok(XattrTarget::File(fs.open_file(mount_point, path, OpenMode::ReadOnly)?)),
+        }
+    }
+    
+    /// 属性を取得
+    pub(super) fn get(&self, name: &str) -> FsResult<Vec<u8>> {
+        match self {
+            XattrTarget::Directory(handle) => handle.get_xattr(name),
+            XattrTarget::File(handle) => handle.get_xattr(name),
+        }
+    }
+    
+    /// 属性を設定
+    pub(super) fn set(&self, name: &str, value: &[u8]) -> FsResult<()> {
+        match self {
+            XattrTarget::Directory(handle) => handle.set_xattr(name, value),
+            XattrTarget::File(handle) => handle.set_xattr(name, value),
+        }
+    }
+    
+    /// 属性名を列挙
+    pub(super) fn list(&self) -> FsResult<Vec<String>> {
+        match self {
+            XattrTarget::Directory(handle) => handle.list_xattr(),
+            XattrTarget::File(handle) => handle.list_xattr(),
+        }
+    }
+    
+    /// 属性を削除
+    pub(super) fn remove(&self, name: &str) -> FsResult<()> {
+        match self {
+            XattrTarget::Directory(handle) => handle.remove_xattr(name),
+            XattrTarget::File(handle) => handle.remove_xattr(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn entry(tag: AclTag, perm: u8) -> AclEntry {
+        AclEntry { tag, perm }
+    }
+    
+    #[test]
+    fn namespaces_acl_format_and_access_checks() {
+        assert!(matches!(XattrNamespace::of("user.mime_type"), Ok(XattrNamespace::User)));
+        assert!(matches!(XattrNamespace::of("security.selinux"), Ok(XattrNamespace::Security)));
+        assert!(matches!(XattrNamespace::of("system.posix_acl_access"), Ok(XattrNamespace::System)));
+        assert!(matches!(XattrNamespace::of("system.other"), Err(FsError::NotSupported)));
+        assert!(matches!(XattrNamespace::of("os2.name"), Err(FsError::NotSupported)));
+        assert!(matches!(XattrNamespace::of("user."), Err(FsError::InvalidData)));
+        
+        // 名前付きエントリにはマスクが必要
+        let named = vec![
+            entry(AclTag::Other, 0),
+            entry(AclTag::User(1000), 6),
+            entry(AclTag::UserObj, 7),
+            entry(AclTag::GroupObj, 5),
+        ];
+        assert!(matches!(PosixAcl::new(named.clone()), Err(FsError::InvalidData)));
+        
+        let mut entries = named;
+        entries.push(entry(AclTag::Group(50), 7));
+        entries.push(entry(AclTag::Mask, 5));
+        let acl = PosixAcl::new(entries).unwrap();
+        assert_eq!(acl.entries()[1].tag, AclTag::User(1000));
+        let value = acl.to_xattr();
+        assert_eq!(value.len(), 4 + 6 * 8);
+        assert_eq!(PosixAcl::from_xattr(&value).unwrap(), acl);
+        assert!(validate(POSIX_ACL_ACCESS, &value).is_ok());
+        assert!(validate(POSIX_ACL_ACCESS, &value[..value.len() - 8]).is_err());
+        
+        // 名前付きユーザーとグループはマスクで制限され、一致したグループが許さなければ拒否
+        let user = Credentials::new(1000, 100);
+        assert!(acl.permits(0, 10, &user, access::READ));
+        assert!(!acl.permits(0, 10, &user, access::WRITE));
+        let member = Credentials::new(2000, 100).with_groups(vec![50]);
+        assert!(acl.permits(0, 10, &member, access::READ | access::EXECUTE));
+        assert!(!acl.permits(0, 10, &member, access::WRITE));
+        assert!(!acl.permits(0, 100, &Credentials::new(3000, 100), access::WRITE));
+        assert!(!acl.permits(0, 10, &Credentials::new(3000, 100), access::READ));
+        assert!(acl.permits(3000, 10, &Credentials::new(3000, 100), access::WRITE));
+        
+        // ACLがなければ権限から作る最小ACLで判定する
+        let metadata = Metadata {
+            inode: 2,
+            file_type: FileType::Regular,
+            size: 0,
+            uid: 1000,
+            gid: 100,
+            permissions: Permissions { read: true, write: true, execute: false },
+            created: 0,
+            accessed: 0,
+            modified: 0,
+            links: 1,
+            block_size: 4096,
+            blocks: 0,
+        };
+        assert!(check_access(&metadata, None, &user, access::WRITE).is_ok());
+        assert!(matches!(check_access(&metadata, None, &Credentials::new(1, 1), access::WRITE), Err(FsError::PermissionDenied)));
+        assert!(check_access(&metadata, None, &Credentials::root(), access::WRITE).is_ok());
+        assert!(check_access(&metadata, None, &Credentials::root(), access::EXECUTE).is_err());
+        assert!(check_access(&metadata, Some(&acl), &Credentials::new(1, 1), access::READ).is_err());
+    }
+}
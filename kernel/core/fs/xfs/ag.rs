@@ -0,0 +1,181 @@
+// XFS アロケーショングループ
+//
+// 各AGの先頭ブロックにあるAGF（空き領域）/AGI（iノード）ヘッダの解析と、
+// iノードB+tree（inobt）のレコード
+
+use super::super::{FsError, FsResult};
+use super::crc32c;
+use super::{be_u16, be_u32, be_u64};
+
+/// AGFのマジック "XAGF"
+const AGF_MAGIC: u32 = 0x5841_4746;
+/// AGIのマジック "XAGI"
+const AGI_MAGIC: u32 = 0x5841_4749;
+/// AGF/AGIのバージョン
+const AG_VERSION: u32 = 1;
+/// AGFのチェックサムのオフセット
+const AGF_CRC_OFFSET: usize = 216;
+/// AGIのチェックサムのオフセット
+const AGI_CRC_OFFSET: usize = 312;
+/// AGFのUUIDのオフセット
+const AGF_UUID_OFFSET: usize = 64;
+/// AGIのUUIDのオフセット
+const AGI_UUID_OFFSET: usize = 296;
+/// iノードチャンクのiノード数
+pub const INODES_PER_CHUNK: u32 = 64;
+/// holemaskの1ビットが表すiノード数
+const INODES_PER_HOLEMASK_BIT: u32 = INODES_PER_CHUNK / 16;
+
+/// AGのヘッダ（AGFとAGIから必要な値を取り出したもの）
+#[derive(Debug, Clone)]
+pub struct AgHeaders {
+    /// AGのブロック数
+    pub length: u32,
+    /// 空きブロック数（空きリストとB+treeの予備を含む）
+    pub free_blocks: u64,
+    /// 割り当て済みiノード数
+    pub inode_count: u32,
+    /// 空きiノード数
+    pub free_inodes: u32,
+    /// inobtのルートブロック
+    pub inobt_root: u32,
+    /// inobtの段数
+    pub inobt_levels: u32,
+}
+
+impl AgHeaders {
+    /// AGF（セクタ1）とAGI（セクタ2）を解析して検証
+    pub fn parse(agno: u32, agf: &[u8], agi: &[u8], uuid: &[u8; 16]) -> FsResult<Self> {
+        Self::verify_header(agno, agf, AGF_MAGIC, AGF_CRC_OFFSET, AGF_UUID_OFFSET, uuid, "AGF")?;
+        Self::verify_header(agno, agi, AGI_MAGIC, AGI_CRC_OFFSET, AGI_UUID_OFFSET, uuid, "AGI")?;
+        
+        let length = be_u32(agf, 12);
+        if be_u32(agi, 12) != length {
+            log::warn!("XFS: AG {}のAGFとAGIの長さが一致しません", agno);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        Ok(Self {
+            length,
+            free_blocks: be_u32(agf, 52) as u64 + be_u32(agf, 48) as u64 + be_u32(agf, 60) as u64,
+            inode_count: be_u32(agi, 16),
+            free_inodes: be_u32(agi, 28),
+            inobt_root: be_u32(agi, 20),
+            inobt_levels: be_u32(agi, 24),
+        })
+    }
+    
+    /// マジック・チェックサム・バージョン・AG番号・UUIDを確認
+    fn verify_header(agno: u32, data: &[u8], magic: u32, crc_offset: usize, uuid_offset: usize,
+                     uuid: &[u8; 16], name: &str) -> FsResult<()> {
+        if data.len() < crc_offset + 4 || be_u32(data, 0) != magic {
+            log::warn!("XFS: AG {}の{}のマジックが不正です", agno, name);
+            return Err(FsError::CorruptedFs);
+        }
+        if !crc32c::verify(data, crc_offset) {
+            log::warn!("XFS: AG {}の{}のチェックサムが一致しません", agno, name);
+            return Err(FsError::ChecksumError);
+        }
+        if be_u32(data, 4) != AG_VERSION || be_u32(data, 8) != agno || &data[uuid_offset..uuid_offset + 16] != uuid {
+            log::warn!("XFS: AG {}の{}の内容が不正です", agno, name);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(())
+    }
+}
+
+/// inobtのレコード（64個のiノードからなるチャンク）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InobtRecord {
+    /// 先頭のAG内iノード番号
+    pub start: u32,
+    /// 割り当てられていない部分（1ビットが4個のiノード、疎なチャンクのみ）
+    pub holemask: u16,
+    /// 空きiノードのビットマップ
+    pub free: u64,
+}
+
+impl InobtRecord {
+    /// レコードを解析（`sparse`なら疎なチャンクの形式）
+    pub fn parse(data: &[u8], sparse: bool) -> Self {
+        Self {
+            start: be_u32(data, 0),
+            holemask: if sparse { be_u16(data, 4) } else { 0 },
+            free: be_u64(data, 8),
+        }
+    }
+    
+    /// チャンクが`agino`を含むか
+    pub fn contains(&self, agino: u32) -> bool {
+        agino >= self.start && agino - self.start < INODES_PER_CHUNK
+    }
+    
+    /// `agino`が割り当て済みか（チャンクに含まれること）
+    pub fn is_allocated(&self, agino: u32) -> bool {
+        let index = agino - self.start;
+        let hole = self.holemask >> (index / INODES_PER_HOLEMASK_BIT) & 1 != 0;
+        !hole && self.free >> index & 1 == 0
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    
+    /// テスト用のAGFとAGIを作成（セクタサイズ512）
+    pub fn build(agno: u32, length: u32, uuid: &[u8; 16], free_blocks: u32, inodes: (u32, u32), inobt_root: u32) -> (Vec<u8>, Vec<u8>) {
+        let mut agf = vec![0u8; 512];
+        agf[0..4].copy_from_slice(&AGF_MAGIC.to_be_bytes());
+        agf[4..8].copy_from_slice(&AG_VERSION.to_be_bytes());
+        agf[8..12].copy_from_slice(&agno.to_be_bytes());
+        agf[12..16].copy_from_slice(&length.to_be_bytes());
+        agf[52..56].copy_from_slice(&free_blocks.to_be_bytes());
+        agf[AGF_UUID_OFFSET..AGF_UUID_OFFSET + 16].copy_from_slice(uuid);
+        seal(&mut agf, AGF_CRC_OFFSET);
+        
+        let mut agi = vec![0u8; 512];
+        agi[0..4].copy_from_slice(&AGI_MAGIC.to_be_bytes());
+        agi[4..8].copy_from_slice(&AG_VERSION.to_be_bytes());
+        agi[8..12].copy_from_slice(&agno.to_be_bytes());
+        agi[12..16].copy_from_slice(&length.to_be_bytes());
+        agi[16..20].copy_from_slice(&inodes.0.to_be_bytes());
+        agi[20..24].copy_from_slice(&inobt_root.to_be_bytes());
+        agi[24..28].copy_from_slice(&1u32.to_be_bytes());
+        agi[28..32].copy_from_slice(&inodes.1.to_be_bytes());
+        agi[AGI_UUID_OFFSET..AGI_UUID_OFFSET + 16].copy_from_slice(uuid);
+        seal(&mut agi, AGI_CRC_OFFSET);
+        (agf, agi)
+    }
+    
+    /// チェックサムを付け直す
+    fn seal(data: &mut [u8], crc_offset: usize) {
+        let crc = crc32c::checksum(data, crc_offset);
+        data[crc_offset..crc_offset + 4].copy_from_slice(&crc.to_le_bytes());
+    }
+    
+    #[test]
+    fn parses_headers_and_sparse_records() {
+        let uuid = [7u8; 16];
+        let (agf, agi) = build(1, 64, &uuid, 40, (64, 10), 1);
+        let ag = AgHeaders::parse(1, &agf, &agi, &uuid).unwrap();
+        assert_eq!((ag.free_blocks, ag.inode_count, ag.free_inodes, ag.inobt_levels), (40, 64, 10, 1));
+        // 別のAGや別のファイルシステムのヘッダは受け付けない
+        assert!(matches!(AgHeaders::parse(0, &agf, &agi, &uuid), Err(FsError::CorruptedFs)));
+        assert!(matches!(AgHeaders::parse(1, &agf, &agi, &[8u8; 16]), Err(FsError::CorruptedFs)));
+        let mut corrupted = agi.clone();
+        corrupted[100] ^= 1;
+        assert!(matches!(AgHeaders::parse(1, &agf, &corrupted, &uuid), Err(FsError::ChecksumError)));
+        
+        // 先頭16個が穴で、残りのうち64番目だけ空いている疎なチャンク
+        let mut raw = [0u8; 16];
+        raw[0..4].copy_from_slice(&128u32.to_be_bytes());
+        raw[4..6].copy_from_slice(&0x000Fu16.to_be_bytes());
+        raw[8..16].copy_from_slice(&(0xFFFFu64 | 1 << 63).to_be_bytes());
+        let record = InobtRecord::parse(&raw, true);
+        assert!(record.contains(128) && record.contains(191) && !record.contains(192));
+        assert!(!record.is_allocated(130));
+        assert!(record.is_allocated(144));
+        assert!(!record.is_allocated(191));
+    }
+}
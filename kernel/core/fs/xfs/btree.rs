@@ -0,0 +1,119 @@
+// XFS B+tree ブロック
+//
+// v5のショート形式（AG内のブロック番号で指す: inobt）とロング形式（ファイルシステム
+// ブロック番号で指す: bmbt）のブロックヘッダの検証と、レコード/キー/ポインタの取り出し
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult};
+use super::crc32c;
+use super::{be_u16, be_u32, be_u64};
+
+/// ショート形式のヘッダサイズ
+const SHORT_HEADER_SIZE: usize = 56;
+/// ロング形式のヘッダサイズ
+const LONG_HEADER_SIZE: usize = 72;
+/// ショート形式のチェックサムのオフセット
+const SHORT_CRC_OFFSET: usize = 52;
+/// ロング形式のチェックサムのオフセット
+const LONG_CRC_OFFSET: usize = 64;
+
+/// B+treeの種類ごとのレコード/キー/ポインタのサイズ
+#[derive(Debug, Clone, Copy)]
+pub struct BtreeLayout {
+    /// マジック
+    pub magic: u32,
+    /// リーフのレコードのサイズ
+    pub record_size: usize,
+    /// 内部ノードのキーのサイズ
+    pub key_size: usize,
+    /// 内部ノードのポインタのサイズ
+    pub pointer_size: usize,
+}
+
+/// iノードB+tree "IAB3"
+pub const INOBT: BtreeLayout = BtreeLayout { magic: 0x4941_4233, record_size: 16, key_size: 4, pointer_size: 4 };
+/// ブロックマップB+tree "BMA3"
+pub const BMBT: BtreeLayout = BtreeLayout { magic: 0x424D_4133, record_size: 16, key_size: 8, pointer_size: 8 };
+
+/// 検証済みのB+treeブロック
+#[derive(Debug)]
+pub struct BtreeBlock {
+    /// ブロックの内容
+    data: Vec<u8>,
+    /// 種類
+    layout: BtreeLayout,
+    /// ヘッダサイズ
+    header: usize,
+    /// 高さ（0がリーフ）
+    pub level: u16,
+    /// レコード（内部ノードならキー）の数
+    pub numrecs: usize,
+}
+
+impl BtreeBlock {
+    /// ショート形式のブロックを検証（`owner`はAG番号）
+    pub fn parse_short(data: Vec<u8>, layout: BtreeLayout, daddr: u64, uuid: &[u8; 16], owner: u32) -> FsResult<Self> {
+        Self::parse(data, layout, SHORT_HEADER_SIZE, SHORT_CRC_OFFSET, daddr, uuid, owner as u64)
+    }
+    
+    /// ロング形式のブロックを検証（`owner`はiノード番号）
+    pub fn parse_long(data: Vec<u8>, layout: BtreeLayout, daddr: u64, uuid: &[u8; 16], owner: u64) -> FsResult<Self> {
+        Self::parse(data, layout, LONG_HEADER_SIZE, LONG_CRC_OFFSET, daddr, uuid, owner)
+    }
+    
+    /// ヘッダサイズとチェックサムの位置を指定してブロックを検証
+    fn parse(data: Vec<u8>, layout: BtreeLayout, header: usize, crc_offset: usize,
+             daddr: u64, uuid: &[u8; 16], owner: u64) -> FsResult<Self> {
+        if data.len() < header || be_u32(&data, 0) != layout.magic {
+            log::warn!("XFS: B+treeブロック（セクタ{:#x}）のマジックが不正です", daddr);
+            return Err(FsError::CorruptedFs);
+        }
+        if !crc32c::verify(&data, crc_offset) {
+            log::warn!("XFS: B+treeブロック（セクタ{:#x}）のチェックサムが一致しません", daddr);
+            return Err(FsError::ChecksumError);
+        }
+        
+        // ショート形式はsiblingが4バイト、ロング形式は8バイト
+        let (blkno, block_uuid, block_owner) = if header == SHORT_HEADER_SIZE {
+            (be_u64(&data, 16), &data[32..48], be_u32(&data, 48) as u64)
+        } else {
+            (be_u64(&data, 24), &data[40..56], be_u64(&data, 56))
+        };
+        if blkno != daddr || block_uuid != uuid || block_owner != owner {
+            log::warn!("XFS: B+treeブロック（セクタ{:#x}）の位置/UUID/所有者が一致しません", daddr);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let level = be_u16(&data, 4);
+        let numrecs = be_u16(&data, 6) as usize;
+        let entry_size = if level == 0 { layout.record_size } else { layout.key_size + layout.pointer_size };
+        if numrecs > (data.len() - header) / entry_size {
+            log::warn!("XFS: B+treeブロック（セクタ{:#x}）のレコード数が不正です", daddr);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        Ok(Self { data, layout, header, level, numrecs })
+    }
+    
+    /// リーフの`index`番目のレコード
+    pub fn record(&self, index: usize) -> &[u8] {
+        let start = self.header + index * self.layout.record_size;
+        &self.data[start..start + self.layout.record_size]
+    }
+    
+    /// 内部ノードの`index`番目のキー
+    pub fn key(&self, index: usize) -> &[u8] {
+        let start = self.header + index * self.layout.key_size;
+        &self.data[start..start + self.layout.key_size]
+    }
+    
+    /// 内部ノードの`index`番目のポインタ（キーの配列はブロックに入る最大数の分だけ確保されている）
+    pub fn pointer(&self, index: usize) -> u64 {
+        let max = (self.data.len() - self.header) / (self.layout.key_size + self.layout.pointer_size);
+        let start = self.header + max * self.layout.key_size + index * self.layout.pointer_size;
+        match self.layout.pointer_size {
+            4 => be_u32(&self.data, start) as u64,
+            _ => be_u64(&self.data, start),
+        }
+    }
+}
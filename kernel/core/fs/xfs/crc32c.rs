@@ -0,0 +1,19 @@
+// XFS CRC32Cチェックサム
+//
+// v5形式のメタデータに付くCRC32C。チェックサムのフィールドを0とみなして
+// ブロック全体から計算し、反転した値をリトルエンディアンで格納する
+
+use super::super::crc32c::crc32c;
+
+/// `crc_offset`にあるチェックサムのフィールドを0とみなしたブロックのチェックサム
+pub fn checksum(data: &[u8], crc_offset: usize) -> u32 {
+    let crc = crc32c(!0, &data[..crc_offset]);
+    let crc = crc32c(crc, &[0; 4]);
+    !crc32c(crc, &data[crc_offset + 4..])
+}
+
+/// 格納されたチェックサムがブロックの内容と一致するか
+pub fn verify(data: &[u8], crc_offset: usize) -> bool {
+    data.len() >= crc_offset + 4
+        && checksum(data, crc_offset) == u32::from_le_bytes([data[crc_offset], data[crc_offset + 1], data[crc_offset + 2], data[crc_offset + 3]])
+}
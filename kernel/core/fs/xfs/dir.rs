@@ -0,0 +1,388 @@
+// XFS ディレクトリ
+//
+// v3ディレクトリの各形式（iノード内のショート形式、ブロック形式、リーフ形式、
+// ノード形式）のブロックの検証と解析、および名前のハッシュ
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType};
+use super::crc32c;
+use super::{be_u16, be_u32, be_u64};
+
+/// ブロック形式のデータブロックのマジック "XDB3"
+const BLOCK_MAGIC: u32 = 0x5844_4233;
+/// リーフ/ノード形式のデータブロックのマジック "XDD3"
+const DATA_MAGIC: u32 = 0x5844_4433;
+/// リーフ形式のリーフブロックのマジック
+const LEAF1_MAGIC: u16 = 0x3DF1;
+/// ノード形式のリーフブロックのマジック
+const LEAFN_MAGIC: u16 = 0x3DFF;
+/// 内部ノードのマジック
+const NODE_MAGIC: u16 = 0x3EBE;
+/// データブロックのヘッダサイズ
+const DATA_HEADER_SIZE: usize = 64;
+/// リーフ/ノードブロックのヘッダサイズ
+const DA_HEADER_SIZE: usize = 64;
+/// データブロックのチェックサムのオフセット
+const DATA_CRC_OFFSET: usize = 4;
+/// リーフ/ノードブロックのチェックサムのオフセット
+const DA_CRC_OFFSET: usize = 12;
+/// ブロック形式の末尾（リーフエントリ数と無効エントリ数）のサイズ
+const BLOCK_TAIL_SIZE: usize = 8;
+/// リーフ/ノードのエントリのサイズ
+const DA_ENTRY_SIZE: usize = 8;
+/// 未使用領域の印
+const FREE_TAG: u16 = 0xFFFF;
+/// データブロック内のエントリの整列
+const DATA_ALIGN: usize = 8;
+/// リーフブロックの置かれるディレクトリ内のバイト位置
+pub const LEAF_OFFSET: u64 = 32 << 30;
+
+/// ディレクトリエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// 名前
+    pub name: String,
+    /// iノード番号
+    pub inode: u64,
+    /// エントリに記録された種別（FTYPEが無効なら不明）
+    pub file_type: Option<FileType>,
+}
+
+/// ハッシュで引くための索引エントリ（ハッシュ, データ内のバイト位置/8）
+pub type LeafEntry = (u32, u32);
+
+/// リーフ/ノードブロック
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaBlock {
+    /// 内部ノード（各子のハッシュの最大値, 子のディレクトリ内ブロック番号）
+    Node(Vec<(u32, u32)>),
+    /// リーフ
+    Leaf {
+        /// 索引エントリ（ハッシュ順）
+        entries: Vec<LeafEntry>,
+        /// 次のリーフのブロック番号（0なら最後）
+        forw: u32,
+    },
+}
+
+/// 名前のハッシュ（xfs_da_hashname）
+pub fn hash_name(name: &[u8]) -> u32 {
+    let mut chunks = name.chunks_exact(4);
+    let mut hash = 0u32;
+    for c in &mut chunks {
+        hash = (c[0] as u32) << 21 ^ (c[1] as u32) << 14 ^ (c[2] as u32) << 7 ^ c[3] as u32 ^ hash.rotate_left(7 * 4);
+    }
+    match *chunks.remainder() {
+        [a, b, c] => (a as u32) << 14 ^ (b as u32) << 7 ^ c as u32 ^ hash.rotate_left(7 * 3),
+        [a, b] => (a as u32) << 7 ^ b as u32 ^ hash.rotate_left(7 * 2),
+        [a] => a as u32 ^ hash.rotate_left(7),
+        _ => hash,
+    }
+}
+
+/// エントリの種別バイトを変換
+fn file_type(ftype: u8) -> Option<FileType> {
+    match ftype {
+        1 => Some(FileType::Regular),
+        2 => Some(FileType::Directory),
+        3 => Some(FileType::CharDevice),
+        4 => Some(FileType::BlockDevice),
+        5 => Some(FileType::NamedPipe),
+        6 => Some(FileType::Socket),
+        7 => Some(FileType::SymbolicLink),
+        _ => None,
+    }
+}
+
+/// ショート形式のディレクトリ（iノード内）を解析して（親のiノード番号, エントリ）を返す
+pub fn parse_shortform(data: &[u8], has_ftype: bool) -> FsResult<(u64, Vec<Entry>)> {
+    let read_ino = |position: usize, wide: bool| -> FsResult<u64> {
+        match wide {
+            true => data.get(position..position + 8).map(|_| be_u64(data, position)),
+            false => data.get(position..position + 4).map(|_| be_u32(data, position) as u64),
+        }
+        .ok_or(FsError::CorruptedFs)
+    };
+    
+    let header = data.get(..2).ok_or(FsError::CorruptedFs)?;
+    let count = header[0] as usize;
+    // iノード番号が32ビットに収まらないエントリがあれば全て8バイトで格納される
+    let wide = header[1] != 0;
+    let ino_size = if wide { 8 } else { 4 };
+    let parent = read_ino(2, wide)?;
+    
+    let mut entries = Vec::with_capacity(count);
+    let mut position = 2 + ino_size;
+    for _ in 0..count {
+        let name_len = *data.get(position).ok_or(FsError::CorruptedFs)? as usize;
+        // 名前の長さ、データブロック内での位置（2バイト）、名前
+        let name = data.get(position + 3..position + 3 + name_len).ok_or(FsError::CorruptedFs)?;
+        position += 3 + name_len;
+        let ftype = match has_ftype {
+            true => {
+                position += 1;
+                file_type(*data.get(position - 1).ok_or(FsError::CorruptedFs)?)
+            },
+            false => None,
+        };
+        let inode = read_ino(position, wide)?;
+        position += ino_size;
+        if name_len == 0 {
+            return Err(FsError::CorruptedFs);
+        }
+        entries.push(Entry { name: String::from_utf8_lossy(name).into_owned(), inode, file_type: ftype });
+    }
+    Ok((parent, entries))
+}
+
+/// v3ヘッダのチェックサム・位置・UUID・所有者を確認
+fn verify_header(data: &[u8], crc_offset: usize, daddr: u64, uuid: &[u8; 16], owner: u64) -> FsResult<()> {
+    if !crc32c::verify(data, crc_offset) {
+        log::warn!("XFS: ディレクトリブロック（セクタ{:#x}）のチェックサムが一致しません", daddr);
+        return Err(FsError::ChecksumError);
+    }
+    // blkno・lsn・uuid・ownerの順に並ぶ
+    let fields = crc_offset + 4;
+    if be_u64(data, fields) != daddr || &data[fields + 16..fields + 32] != uuid || be_u64(data, fields + 32) != owner {
+        log::warn!("XFS: ディレクトリブロック（セクタ{:#x}）の位置/UUID/所有者が一致しません", daddr);
+        return Err(FsError::CorruptedFs);
+    }
+    Ok(())
+}
+
+/// 検証済みのデータブロック
+#[derive(Debug)]
+pub struct DataBlock {
+    /// ブロックの内容
+    data: Vec<u8>,
+    /// エントリ領域の終端（ブロック形式では末尾のリーフの手前）
+    end: usize,
+    /// ブロック形式の場合、末尾のリーフ
+    pub leaf: Option<Vec<LeafEntry>>,
+}
+
+impl DataBlock {
+    /// データブロックを検証
+    pub fn parse(data: Vec<u8>, daddr: u64, uuid: &[u8; 16], owner: u64) -> FsResult<Self> {
+        if data.len() < DATA_HEADER_SIZE + BLOCK_TAIL_SIZE {
+            return Err(FsError::CorruptedFs);
+        }
+        let block_form = match be_u32(&data, 0) {
+            BLOCK_MAGIC => true,
+            DATA_MAGIC => false,
+            _ => {
+                log::warn!("XFS: ディレクトリのデータブロック（セクタ{:#x}）のマジックが不正です", daddr);
+                return Err(FsError::CorruptedFs);
+            },
+        };
+        verify_header(&data, DATA_CRC_OFFSET, daddr, uuid, owner)?;
+        
+        if !block_form {
+            let end = data.len();
+            return Ok(Self { data, end, leaf: None });
+        }
+        
+        let tail = data.len() - BLOCK_TAIL_SIZE;
+        let count = be_u32(&data, tail) as usize;
+        let end = count.checked_mul(DA_ENTRY_SIZE).and_then(|size| tail.checked_sub(size))
+            .filter(|&end| end >= DATA_HEADER_SIZE)
+            .ok_or(FsError::CorruptedFs)?;
+        let leaf = data[end..tail].chunks_exact(DA_ENTRY_SIZE).map(|e| (be_u32(e, 0), be_u32(e, 4))).collect();
+        Ok(Self { data, end, leaf: Some(leaf) })
+    }
+    
+    /// `offset`バイト目のエントリを解析して（エントリ, 次のエントリの位置）を返す
+    pub fn entry_at(&self, offset: usize, has_ftype: bool) -> FsResult<(Entry, usize)> {
+        let data = &self.data[..self.end];
+        if offset < DATA_HEADER_SIZE || !offset.is_multiple_of(DATA_ALIGN) || offset + 12 > data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        let name_len = data[offset + 8] as usize;
+        let ftype_len = has_ftype as usize;
+        // iノード番号、名前の長さ、名前、種別、位置タグ
+        let size = (8 + 1 + name_len + ftype_len + 2).next_multiple_of(DATA_ALIGN);
+        if name_len == 0 || offset + size > data.len() || be_u16(data, offset + size - 2) as usize != offset {
+            log::warn!("XFS: ディレクトリのエントリ（位置{}）が不正です", offset);
+            return Err(FsError::CorruptedFs);
+        }
+        let name = &data[offset + 9..offset + 9 + name_len];
+        let entry = Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            inode: be_u64(data, offset),
+            file_type: if has_ftype { file_type(data[offset + 9 + name_len]) } else { None },
+        };
+        Ok((entry, offset + size))
+    }
+    
+    /// 全てのエントリを順に返す（未使用領域は飛ばす）
+    pub fn entries(&self, has_ftype: bool) -> FsResult<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let mut position = DATA_HEADER_SIZE;
+        while position < self.end {
+            if position + 4 > self.end {
+                return Err(FsError::CorruptedFs);
+            }
+            if be_u16(&self.data, position) == FREE_TAG {
+                let length = be_u16(&self.data, position + 2) as usize;
+                if length == 0 || !length.is_multiple_of(DATA_ALIGN) {
+                    return Err(FsError::CorruptedFs);
+                }
+                position += length;
+                continue;
+            }
+            let (entry, next) = self.entry_at(position, has_ftype)?;
+            entries.push(entry);
+            position = next;
+        }
+        Ok(entries)
+    }
+}
+
+/// リーフ/ノードブロックを検証して解析
+pub fn parse_da_block(data: &[u8], daddr: u64, uuid: &[u8; 16], owner: u64) -> FsResult<DaBlock> {
+    if data.len() < DA_HEADER_SIZE {
+        return Err(FsError::CorruptedFs);
+    }
+    let magic = be_u16(data, 8);
+    if ![LEAF1_MAGIC, LEAFN_MAGIC, NODE_MAGIC].contains(&magic) {
+        log::warn!("XFS: ディレクトリのリーフ/ノード（セクタ{:#x}）のマジックが不正です", daddr);
+        return Err(FsError::CorruptedFs);
+    }
+    verify_header(data, DA_CRC_OFFSET, daddr, uuid, owner)?;
+    
+    let count = be_u16(data, 56) as usize;
+    let entries = data[DA_HEADER_SIZE..].chunks_exact(DA_ENTRY_SIZE);
+    if count > entries.len() {
+        return Err(FsError::CorruptedFs);
+    }
+    let entries = entries.take(count).map(|e| (be_u32(e, 0), be_u32(e, 4))).collect();
+    Ok(match magic {
+        NODE_MAGIC => DaBlock::Node(entries),
+        _ => DaBlock::Leaf { entries, forw: be_u32(data, 0) },
+    })
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    
+    /// データブロック内のエントリを作成（`offset`はブロック内の位置）
+    pub fn data_entry(offset: usize, inode: u64, name: &str, ftype: u8) -> Vec<u8> {
+        let size = (8 + 1 + name.len() + 1 + 2).next_multiple_of(DATA_ALIGN);
+        let mut data = vec![0u8; size];
+        data[..8].copy_from_slice(&inode.to_be_bytes());
+        data[8] = name.len() as u8;
+        data[9..9 + name.len()].copy_from_slice(name.as_bytes());
+        data[9 + name.len()] = ftype;
+        data[size - 2..].copy_from_slice(&(offset as u16).to_be_bytes());
+        data
+    }
+    
+    /// v3ヘッダを書き込んでチェックサムを付ける
+    fn seal(data: &mut [u8], crc_offset: usize, daddr: u64, uuid: &[u8; 16], owner: u64) {
+        let fields = crc_offset + 4;
+        data[fields..fields + 8].copy_from_slice(&daddr.to_be_bytes());
+        data[fields + 16..fields + 32].copy_from_slice(uuid);
+        data[fields + 32..fields + 40].copy_from_slice(&owner.to_be_bytes());
+        let crc = crc32c::checksum(data, crc_offset);
+        data[crc_offset..crc_offset + 4].copy_from_slice(&crc.to_le_bytes());
+    }
+    
+    /// データブロックを作成（FTYPE有効、`entries`は（iノード番号, 名前, 種別））
+    /// `block_form`ならハッシュ順のリーフを末尾に置き、（ブロック, 各エントリの位置）を返す
+    pub fn data_block(size: usize, entries: &[(u64, &str, u8)], block_form: bool,
+                      daddr: u64, uuid: &[u8; 16], owner: u64) -> (Vec<u8>, Vec<usize>) {
+        let mut data = vec![0u8; size];
+        let magic = if block_form { BLOCK_MAGIC } else { DATA_MAGIC };
+        data[0..4].copy_from_slice(&magic.to_be_bytes());
+        
+        let mut offsets = Vec::new();
+        let mut position = DATA_HEADER_SIZE;
+        for &(inode, name, ftype) in entries {
+            let entry = data_entry(position, inode, name, ftype);
+            data[position..position + entry.len()].copy_from_slice(&entry);
+            offsets.push(position);
+            position += entry.len();
+        }
+        
+        let mut end = size;
+        if block_form {
+            let mut leaf: Vec<LeafEntry> = entries.iter().zip(&offsets)
+                .map(|(&(_, name, _), &offset)| (hash_name(name.as_bytes()), (offset / DATA_ALIGN) as u32))
+                .collect();
+            leaf.sort();
+            let tail = size - BLOCK_TAIL_SIZE;
+            data[tail..tail + 4].copy_from_slice(&(leaf.len() as u32).to_be_bytes());
+            end = tail - leaf.len() * DA_ENTRY_SIZE;
+            for (i, (hash, address)) in leaf.into_iter().enumerate() {
+                let at = end + i * DA_ENTRY_SIZE;
+                data[at..at + 4].copy_from_slice(&hash.to_be_bytes());
+                data[at + 4..at + 8].copy_from_slice(&address.to_be_bytes());
+            }
+        }
+        // 残りは未使用領域
+        data[position..position + 2].copy_from_slice(&FREE_TAG.to_be_bytes());
+        data[position + 2..position + 4].copy_from_slice(&((end - position) as u16).to_be_bytes());
+        
+        seal(&mut data, DATA_CRC_OFFSET, daddr, uuid, owner);
+        (data, offsets)
+    }
+    
+    /// リーフ/ノードブロックを作成
+    pub fn da_block(size: usize, magic: u16, entries: &[(u32, u32)], forw: u32,
+                    daddr: u64, uuid: &[u8; 16], owner: u64) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[0..4].copy_from_slice(&forw.to_be_bytes());
+        data[8..10].copy_from_slice(&magic.to_be_bytes());
+        data[56..58].copy_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (i, &(hash, value)) in entries.iter().enumerate() {
+            let at = DA_HEADER_SIZE + i * DA_ENTRY_SIZE;
+            data[at..at + 4].copy_from_slice(&hash.to_be_bytes());
+            data[at + 4..at + 8].copy_from_slice(&value.to_be_bytes());
+        }
+        seal(&mut data, DA_CRC_OFFSET, daddr, uuid, owner);
+        data
+    }
+    
+    /// リーフ形式のリーフのマジック
+    pub const LEAF1: u16 = LEAF1_MAGIC;
+    /// ノード形式のリーフのマジック
+    pub const LEAFN: u16 = LEAFN_MAGIC;
+    /// 内部ノードのマジック
+    pub const NODE: u16 = NODE_MAGIC;
+    
+    #[test]
+    fn parses_shortform_and_block_directories() {
+        // 1〜4バイトの端数とrol32の折り返し
+        assert_eq!(hash_name(b""), 0);
+        assert_eq!(hash_name(b"a"), 0x61);
+        assert_eq!(hash_name(b"abcd"), 0x61 << 21 ^ 0x62 << 14 ^ 0x63 << 7 ^ 0x64);
+        assert_eq!(hash_name(b"abcde"), 0x65 ^ hash_name(b"abcd").rotate_left(7));
+        
+        // 親128、"a"(ftype=1)→200、"bc"(ftype=2)→201
+        let short = [2, 0, 0, 0, 0, 128, 1, 0, 0x30, b'a', 1, 0, 0, 0, 200, 2, 0, 0x40, b'b', b'c', 2, 0, 0, 0, 201];
+        let (parent, entries) = parse_shortform(&short, true).unwrap();
+        assert_eq!(parent, 128);
+        assert_eq!(entries[1], Entry { name: "bc".into(), inode: 201, file_type: Some(FileType::Directory) });
+        assert!(matches!(parse_shortform(&short[..20], true), Err(FsError::CorruptedFs)));
+        
+        let uuid = [3u8; 16];
+        let (block, offsets) = data_block(4096, &[(128, ".", 2), (64, "..", 2), (300, "file", 1)], true, 80, &uuid, 128);
+        let parsed = DataBlock::parse(block.clone(), 80, &uuid, 128).unwrap();
+        let names: Vec<String> = parsed.entries(true).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec![".", "..", "file"]);
+        let leaf = parsed.leaf.as_ref().unwrap();
+        assert!(leaf.contains(&(hash_name(b"file"), (offsets[2] / 8) as u32)));
+        assert_eq!(parsed.entry_at(offsets[2], true).unwrap().0.inode, 300);
+        // 別のディレクトリや位置のブロックは受け付けない
+        assert!(matches!(DataBlock::parse(block.clone(), 80, &uuid, 129), Err(FsError::CorruptedFs)));
+        assert!(matches!(DataBlock::parse(block, 88, &uuid, 128), Err(FsError::CorruptedFs)));
+        
+        let node = da_block(4096, NODE, &[(0x1000, 8388609), (u32::MAX, 8388610)], 0, 96, &uuid, 128);
+        assert_eq!(parse_da_block(&node, 96, &uuid, 128).unwrap(), DaBlock::Node(vec![(0x1000, 8388609), (u32::MAX, 8388610)]));
+        let mut corrupted = node.clone();
+        corrupted[70] ^= 1;
+        assert!(matches!(parse_da_block(&corrupted, 96, &uuid, 128), Err(FsError::ChecksumError)));
+    }
+}
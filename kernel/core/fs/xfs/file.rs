@@ -0,0 +1,141 @@
+// XFS ファイル/ディレクトリハンドル
+//
+// 読み取り専用のハンドル実装（エクステントの読み込みとディレクトリの列挙）
+
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType, Metadata, Permissions, FileHandle, DirHandle, DirEntry};
+use super::inode::{DataFork, Extent, XfsInode};
+use super::XfsVolume;
+
+/// デバイス番号のうちマイナー番号のビット数
+const DEV_MINOR_BITS: u32 = 18;
+
+/// XFSファイルハンドル
+pub struct XfsFileHandle {
+    /// 所属ボリューム
+    volume: Arc<XfsVolume>,
+    /// 開いた時点のiノード（読み取り専用なので変わらない）
+    inode: Arc<XfsInode>,
+    /// データフォークのエクステント
+    extents: Vec<Extent>,
+}
+
+impl XfsFileHandle {
+    /// 新しいファイルハンドルを作成
+    pub(super) fn new(volume: Arc<XfsVolume>, inode: Arc<XfsInode>, extents: Vec<Extent>) -> Self {
+        Self { volume, inode, extents }
+    }
+}
+
+impl FileHandle for XfsFileHandle {
+    fn read(&self, buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        self.volume.read_file(&self.inode, &self.extents, buffer, offset)
+    }
+    
+    fn write(&self, _buffer: &[u8], _offset: u64) -> FsResult<usize> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn flush(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn size(&self) -> FsResult<u64> {
+        Ok(self.inode.size)
+    }
+    
+    fn resize(&self, _new_size: u64) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.volume.metadata(&self.inode))
+    }
+    
+    fn lock(&self, _exclusive: bool) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn unlock(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn can_read(&self) -> bool {
+        true
+    }
+    
+    fn can_write(&self) -> bool {
+        false
+    }
+}
+
+/// XFSディレクトリハンドル
+pub struct XfsDirHandle {
+    /// 所属ボリューム
+    volume: Arc<XfsVolume>,
+    /// ディレクトリのiノード
+    inode: Arc<XfsInode>,
+}
+
+impl XfsDirHandle {
+    /// 新しいディレクトリハンドルを作成
+    pub(super) fn new(volume: Arc<XfsVolume>, inode: Arc<XfsInode>) -> Self {
+        Self { volume, inode }
+    }
+}
+
+impl DirHandle for XfsDirHandle {
+    fn read_entries(&self) -> FsResult<Vec<DirEntry>> {
+        self.volume.list_directory(&self.inode)
+    }
+    
+    fn lookup(&self, name: &str) -> FsResult<DirEntry> {
+        let entry = self.volume.lookup_entry(&self.inode, name)?;
+        let file_type = match entry.file_type {
+            Some(file_type) => file_type,
+            None => self.volume.read_inode(entry.inode)?.file_type(),
+        };
+        Ok(DirEntry {
+            name: name.to_string(),
+            inode: entry.inode,
+            file_type,
+        })
+    }
+    
+    fn create_file(&self, _name: &str, _permissions: Permissions) -> FsResult<Arc<dyn FileHandle>> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_directory(&self, _name: &str, _permissions: Permissions) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn remove(&self, _name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn rename(&self, _old_name: &str, _new_name: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn create_symlink(&self, _name: &str, _target: &str) -> FsResult<()> {
+        Err(FsError::ReadOnly)
+    }
+    
+    fn metadata(&self) -> FsResult<Metadata> {
+        Ok(self.volume.metadata(&self.inode))
+    }
+    
+    fn device_number(&self, name: &str) -> FsResult<(u32, u32)> {
+        let inode = self.volume.read_inode(self.volume.lookup(&self.inode, name)?)?;
+        match (inode.file_type(), &inode.fork) {
+            // メジャー14ビット、マイナー18ビットの形式で保存されている
+            (FileType::BlockDevice | FileType::CharDevice, DataFork::Device(dev)) => {
+                Ok((dev >> DEV_MINOR_BITS, dev & ((1 << DEV_MINOR_BITS) - 1)))
+            },
+            _ => Err(FsError::InvalidData),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+// XFS iノード
+//
+// v3のディスク上iノード（dinode）の検証と、データフォーク（ローカル/エクステント/
+// B+treeのルート）およびエクステントレコードの解析
+
+use alloc::vec::Vec;
+use super::super::{FsError, FsResult, FileType};
+use super::crc32c;
+use super::{be_u16, be_u32, be_u64};
+
+/// マジック "IN"
+const MAGIC: u16 = 0x494E;
+/// CRC付きメタデータのiノードのバージョン
+const VERSION_3: u8 = 3;
+/// チェックサムのオフセット
+const CRC_OFFSET: usize = 100;
+/// データフォークの開始位置（v3のiノードコアのサイズ）
+const CORE_SIZE: usize = 176;
+/// エクステントレコードのサイズ
+pub const EXTENT_SIZE: usize = 16;
+/// フォーク内のB+treeルートのヘッダサイズ
+const BMDR_HEADER_SIZE: usize = 4;
+/// BIGTIMEのタイムスタンプの起点（1901年12月13日からの秒数で格納される）
+const BIGTIME_EPOCH_OFFSET: u64 = 1 << 31;
+
+/// データフォークの形式
+pub mod format {
+    pub const DEV: u8 = 0;
+    pub const LOCAL: u8 = 1;
+    pub const EXTENTS: u8 = 2;
+    pub const BTREE: u8 = 3;
+}
+
+/// di_flags
+pub mod flags {
+    /// リアルタイムデバイス上のデータ
+    pub const REALTIME: u16 = 1 << 0;
+}
+
+/// di_flags2
+mod flags2 {
+    /// 64ビットのタイムスタンプ
+    pub const BIGTIME: u64 = 1 << 3;
+    /// 64ビットのエクステント数
+    pub const NREXT64: u64 = 1 << 4;
+}
+
+/// エクステント（ファイル内の連続したブロックの範囲）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    /// ファイル内の開始ブロック
+    pub offset: u64,
+    /// ファイルシステムブロック番号
+    pub block: u64,
+    /// ブロック数
+    pub count: u64,
+    /// 事前割り当てのみで未書き込み（0として読む）
+    pub unwritten: bool,
+}
+
+impl Extent {
+    /// 128ビットのエクステントレコードを解析
+    pub fn parse(data: &[u8]) -> Self {
+        let l0 = be_u64(data, 0);
+        let l1 = be_u64(data, 8);
+        Self {
+            offset: (l0 & ((1 << 63) - 1)) >> 9,
+            block: ((l0 & 0x1FF) << 43) | (l1 >> 21),
+            count: l1 & ((1 << 21) - 1),
+            unwritten: l0 >> 63 != 0,
+        }
+    }
+    
+    /// 終端（含まない）のファイル内ブロック
+    pub fn end(&self) -> u64 {
+        self.offset + self.count
+    }
+}
+
+/// データフォーク
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataFork {
+    /// デバイス番号
+    Device(u32),
+    /// iノード内に置かれたデータ（短いディレクトリやシンボリックリンク）
+    Local(Vec<u8>),
+    /// エクステントの一覧
+    Extents(Vec<Extent>),
+    /// B+treeのルート（高さと、各子の開始ブロックとファイルシステムブロック番号）
+    Btree {
+        /// ルートの高さ
+        level: u16,
+        /// （キー, ポインタ）
+        children: Vec<(u64, u64)>,
+    },
+}
+
+/// iノード
+#[derive(Debug, Clone)]
+pub struct XfsInode {
+    /// iノード番号
+    pub number: u64,
+    /// 種別と権限
+    pub mode: u16,
+    /// 所有者
+    pub uid: u32,
+    /// グループ
+    pub gid: u32,
+    /// リンク数
+    pub nlink: u32,
+    /// ファイルサイズ
+    pub size: u64,
+    /// 割り当て済みのブロック数
+    pub nblocks: u64,
+    /// 最終アクセス時刻（秒）
+    pub atime: u64,
+    /// 最終更新時刻（秒）
+    pub mtime: u64,
+    /// 作成時刻（秒）
+    pub crtime: u64,
+    /// di_flags
+    pub flags: u16,
+    /// データフォーク
+    pub fork: DataFork,
+}
+
+impl XfsInode {
+    /// iノードを解析して検証（`number`と`uuid`はiノード内の記録と照合する）
+    pub fn parse(data: &[u8], number: u64, uuid: &[u8; 16]) -> FsResult<Self> {
+        if data.len() < CORE_SIZE || be_u16(data, 0) != MAGIC || data[4] != VERSION_3 {
+            log::warn!("XFS: iノード{}のマジック/バージョンが不正です", number);
+            return Err(FsError::CorruptedFs);
+        }
+        if !crc32c::verify(data, CRC_OFFSET) {
+            log::warn!("XFS: iノード{}のチェックサムが一致しません", number);
+            return Err(FsError::ChecksumError);
+        }
+        if be_u64(data, 152) != number || &data[160..176] != uuid {
+            log::warn!("XFS: iノード{}の番号/UUIDが一致しません", number);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let flags2 = be_u64(data, 120);
+        let bigtime = flags2 & flags2::BIGTIME != 0;
+        let nextents = if flags2 & flags2::NREXT64 != 0 { be_u64(data, 24) } else { be_u32(data, 76) as u64 };
+        let size = be_u64(data, 56);
+        
+        // 拡張属性フォークがあれば、データフォークはその手前まで
+        let fork_end = match data[82] {
+            0 => data.len(),
+            forkoff => CORE_SIZE + forkoff as usize * 8,
+        };
+        if fork_end > data.len() {
+            return Err(FsError::CorruptedFs);
+        }
+        let fork = Self::parse_fork(data[5], &data[CORE_SIZE..fork_end], nextents, size)
+            .inspect_err(|_| log::warn!("XFS: iノード{}のデータフォークが不正です", number))?;
+        
+        Ok(Self {
+            number,
+            mode: be_u16(data, 2),
+            uid: be_u32(data, 8),
+            gid: be_u32(data, 12),
+            nlink: be_u32(data, 16),
+            size,
+            nblocks: be_u64(data, 64),
+            atime: timestamp(data, 32, bigtime),
+            mtime: timestamp(data, 40, bigtime),
+            crtime: timestamp(data, 144, bigtime),
+            flags: be_u16(data, 90),
+            fork,
+        })
+    }
+    
+    /// データフォークを解析
+    fn parse_fork(fork_format: u8, fork: &[u8], nextents: u64, size: u64) -> FsResult<DataFork> {
+        match fork_format {
+            format::DEV if fork.len() >= 4 => Ok(DataFork::Device(be_u32(fork, 0))),
+            format::LOCAL if size <= fork.len() as u64 => Ok(DataFork::Local(fork[..size as usize].to_vec())),
+            format::EXTENTS if nextents <= (fork.len() / EXTENT_SIZE) as u64 => {
+                Ok(DataFork::Extents(fork.chunks_exact(EXTENT_SIZE).take(nextents as usize).map(Extent::parse).collect()))
+            },
+            format::BTREE if fork.len() >= BMDR_HEADER_SIZE => {
+                let level = be_u16(fork, 0);
+                let numrecs = be_u16(fork, 2) as usize;
+                // キーとポインタの配列はフォークに入る最大数の分だけ確保されている
+                let max = (fork.len() - BMDR_HEADER_SIZE) / 16;
+                if level == 0 || numrecs == 0 || numrecs > max {
+                    return Err(FsError::CorruptedFs);
+                }
+                let children = (0..numrecs)
+                    .map(|i| (be_u64(fork, BMDR_HEADER_SIZE + i * 8), be_u64(fork, BMDR_HEADER_SIZE + max * 8 + i * 8)))
+                    .collect();
+                Ok(DataFork::Btree { level, children })
+            },
+            _ => Err(FsError::CorruptedFs),
+        }
+    }
+    
+    /// ファイル種別
+    pub fn file_type(&self) -> FileType {
+        match self.mode & 0o170000 {
+            0o040000 => FileType::Directory,
+            0o120000 => FileType::SymbolicLink,
+            0o060000 => FileType::BlockDevice,
+            0o020000 => FileType::CharDevice,
+            0o010000 => FileType::NamedPipe,
+            0o140000 => FileType::Socket,
+            _ => FileType::Regular,
+        }
+    }
+}
+
+/// タイムスタンプを秒で読み込み（1970年より前は0とする）
+fn timestamp(data: &[u8], offset: usize, bigtime: bool) -> u64 {
+    if bigtime {
+        (be_u64(data, offset) / 1_000_000_000).saturating_sub(BIGTIME_EPOCH_OFFSET)
+    } else {
+        (be_u32(data, offset) as i32).max(0) as u64
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    
+    /// エクステントレコードを作成
+    pub fn extent(offset: u64, block: u64, count: u64, unwritten: bool) -> [u8; EXTENT_SIZE] {
+        let l0 = (unwritten as u64) << 63 | offset << 9 | block >> 43;
+        let l1 = (block & ((1 << 43) - 1)) << 21 | count;
+        let mut data = [0u8; EXTENT_SIZE];
+        data[..8].copy_from_slice(&l0.to_be_bytes());
+        data[8..].copy_from_slice(&l1.to_be_bytes());
+        data
+    }
+    
+    /// テスト用のiノード（512バイト）を作成（`fork`は形式とフォークの内容）
+    pub fn build(number: u64, uuid: &[u8; 16], mode: u16, size: u64, fork: (u8, &[u8]), nextents: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 512];
+        data[0..2].copy_from_slice(&MAGIC.to_be_bytes());
+        data[2..4].copy_from_slice(&mode.to_be_bytes());
+        data[4] = VERSION_3;
+        data[5] = fork.0;
+        data[8..12].copy_from_slice(&1000u32.to_be_bytes());
+        data[16..20].copy_from_slice(&1u32.to_be_bytes());
+        data[40..44].copy_from_slice(&1_700_000_000u32.to_be_bytes());
+        data[56..64].copy_from_slice(&size.to_be_bytes());
+        data[64..72].copy_from_slice(&size.div_ceil(4096).to_be_bytes());
+        data[76..80].copy_from_slice(&nextents.to_be_bytes());
+        data[152..160].copy_from_slice(&number.to_be_bytes());
+        data[160..176].copy_from_slice(uuid);
+        data[CORE_SIZE..CORE_SIZE + fork.1.len()].copy_from_slice(fork.1);
+        seal(&mut data);
+        data
+    }
+    
+    /// チェックサムを付け直す
+    pub fn seal(data: &mut [u8]) {
+        let crc = crc32c::checksum(data, CRC_OFFSET);
+        data[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+    }
+    
+    #[test]
+    fn parses_extents_and_bigtime() {
+        let record = extent(0x1234, (3 << 43) | 0x55, 0x1F_FFFF, true);
+        assert_eq!(Extent::parse(&record), Extent { offset: 0x1234, block: (3 << 43) | 0x55, count: 0x1F_FFFF, unwritten: true });
+        
+        let uuid = [1u8; 16];
+        let mut fork = Vec::new();
+        fork.extend_from_slice(&extent(0, 100, 2, false));
+        fork.extend_from_slice(&extent(5, 200, 1, false));
+        let mut data = build(131, &uuid, 0o100644, 9000, (format::EXTENTS, &fork), 2);
+        let inode = XfsInode::parse(&data, 131, &uuid).unwrap();
+        assert_eq!(inode.file_type(), FileType::Regular);
+        assert_eq!(inode.mtime, 1_700_000_000);
+        assert_eq!(inode.fork, DataFork::Extents(vec![Extent::parse(&fork[..16]), Extent::parse(&fork[16..])]));
+        assert!(matches!(XfsInode::parse(&data, 132, &uuid), Err(FsError::CorruptedFs)));
+        
+        // BIGTIMEではナノ秒単位で、起点が2^31秒ずれている
+        data[120..128].copy_from_slice(&flags2::BIGTIME.to_be_bytes());
+        data[40..48].copy_from_slice(&((BIGTIME_EPOCH_OFFSET + 5_000_000_000) * 1_000_000_000).to_be_bytes());
+        seal(&mut data);
+        assert_eq!(XfsInode::parse(&data, 131, &uuid).unwrap().mtime, 5_000_000_000);
+        
+        data[200] ^= 1;
+        assert!(matches!(XfsInode::parse(&data, 131, &uuid), Err(FsError::ChecksumError)));
+    }
+}
@@ -0,0 +1,1054 @@
+// XFS ファイルシステム実装
+//
+// XFS v5（CRC付きメタデータ）の読み取り専用実装（AGF/AGIヘッダ、inobtによるiノードの
+// 割り当て確認、ショート形式/ブロック/リーフ/ノード形式のディレクトリ、エクステント一覧と
+// B+treeのブロックマップ、全メタデータブロックのCRC32C・UUID・所有者・位置の検証）
+// ログは再生しないため、最後にチェックポイントされた状態が見える
+
+mod crc32c;
+mod superblock;
+mod ag;
+mod btree;
+mod inode;
+mod dir;
+mod file;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use spin::{Mutex, RwLock};
+use super::{FsError, FsResult, FileType, Metadata, FsStats, Permissions, FileHandle, DirHandle, DirEntry, OpenMode, Filesystem};
+use super::vfs::BlockDevice;
+use self::ag::{AgHeaders, InobtRecord};
+use self::btree::{BtreeBlock, BMBT, INOBT};
+use self::dir::{hash_name, parse_da_block, parse_shortform, DaBlock, DataBlock, Entry, LeafEntry, LEAF_OFFSET};
+use self::inode::{flags, DataFork, Extent, XfsInode};
+use self::superblock::{incompat, XfsSuperblock, SUPERBLOCK_MIN_SIZE};
+use self::file::{XfsFileHandle, XfsDirHandle};
+
+/// パス解決時のディレクトリ階層上限
+const MAX_PATH_DEPTH: usize = 256;
+
+/// キャッシュするiノード数の上限
+const INODE_CACHE_CAPACITY: usize = 1024;
+
+/// 名前の長さの上限
+const MAX_NAME_LENGTH: u32 = 255;
+
+/// B+treeの高さの上限
+const MAX_BTREE_LEVELS: u32 = 9;
+
+/// ディレクトリの検索で辿るリーフ/ノードブロック数の上限（ハッシュが衝突して続くリーフを含む）
+const MAX_DA_STEPS: usize = 64;
+
+/// シンボリックリンクの長さの上限
+const MAX_SYMLINK_LENGTH: u64 = 1024;
+
+/// リモートシンボリックリンクのブロックのマジック "XSLM"
+const SYMLINK_MAGIC: u32 = 0x5853_4C4D;
+
+/// リモートシンボリックリンクのブロックのヘッダサイズ
+const SYMLINK_HEADER_SIZE: usize = 56;
+
+/// リモートシンボリックリンクのブロックのチェックサムのオフセット
+const SYMLINK_CRC_OFFSET: usize = 12;
+
+/// ディスクアドレス（ヘッダに記録される位置）の単位
+const BBSIZE: u64 = 512;
+
+/// ビッグエンディアンのu16を読み込み
+fn be_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+/// ビッグエンディアンのu32を読み込み
+fn be_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// ビッグエンディアンのu64を読み込み
+fn be_u64(data: &[u8], offset: usize) -> u64 {
+    (be_u32(data, offset) as u64) << 32 | be_u32(data, offset + 4) as u64
+}
+
+/// デバイスからバイト単位で読み込み（ブロック境界をまたいでよい）
+fn read_bytes(device: &dyn BlockDevice, offset: u64, len: usize) -> FsResult<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let block_size = device.block_size();
+    let first_block = offset / block_size;
+    let last_block = (offset + len as u64 - 1) / block_size;
+    
+    if last_block >= device.total_blocks() {
+        return Err(FsError::IoError);
+    }
+    
+    let data = device.read_blocks(first_block, last_block - first_block + 1)?;
+    let start = (offset - first_block * block_size) as usize;
+    
+    if data.len() < start + len {
+        return Err(FsError::IoError);
+    }
+    
+    Ok(data[start..start + len].to_vec())
+}
+
+/// XFSオプション
+#[derive(Debug, Clone, Copy)]
+pub struct XfsOptions {
+    /// iノードを読む前にinobtで割り当て済みか確認する
+    pub verify_inode_allocation: bool,
+    /// 読み込んだiノードをキャッシュする
+    pub cache_inodes: bool,
+}
+
+impl Default for XfsOptions {
+    fn default() -> Self {
+        Self {
+            verify_inode_allocation: true,
+            cache_inodes: true,
+        }
+    }
+}
+
+/// マウントされたXFSボリューム
+struct XfsVolume {
+    /// デバイスパス
+    device_path: String,
+    /// ブロックデバイス
+    device: Arc<dyn BlockDevice>,
+    /// スーパーブロック
+    superblock: XfsSuperblock,
+    /// 各AGのヘッダ
+    ags: Vec<AgHeaders>,
+    /// iノードの割り当てを確認するか
+    verify_inode_allocation: bool,
+    /// iノードのキャッシュ（無効時はNone）
+    inode_cache: Option<Mutex<BTreeMap<u64, Arc<XfsInode>>>>,
+}
+
+impl XfsVolume {
+    /// デバイスからボリュームを構築
+    fn open(device_path: &str, device: Arc<dyn BlockDevice>, options: XfsOptions) -> FsResult<Self> {
+        let primary = read_bytes(&*device, 0, SUPERBLOCK_MIN_SIZE)?;
+        let sector_size = be_u16(&primary, 102) as usize;
+        let superblock = if sector_size > SUPERBLOCK_MIN_SIZE && sector_size <= 32768 {
+            XfsSuperblock::parse(&read_bytes(&*device, 0, sector_size)?)?
+        } else {
+            XfsSuperblock::parse(&primary)?
+        };
+        
+        let mut ags = Vec::with_capacity(superblock.ag_count as usize);
+        for agno in 0..superblock.ag_count {
+            let start = superblock.ag_block_offset(agno, 0);
+            let sector = superblock.sector_size as u64;
+            let agf = read_bytes(&*device, start + sector, sector as usize)?;
+            let agi = read_bytes(&*device, start + 2 * sector, sector as usize)?;
+            let headers = AgHeaders::parse(agno, &agf, &agi, &superblock.meta_uuid)?;
+            if headers.length > superblock.ag_blocks || headers.inobt_levels == 0 || headers.inobt_levels > MAX_BTREE_LEVELS {
+                log::warn!("XFS: AG {}のヘッダが不正です", agno);
+                return Err(FsError::CorruptedFs);
+            }
+            ags.push(headers);
+        }
+        
+        let volume = Self {
+            device_path: device_path.to_string(),
+            device,
+            superblock,
+            ags,
+            verify_inode_allocation: options.verify_inode_allocation,
+            inode_cache: options.cache_inodes.then(|| Mutex::new(BTreeMap::new())),
+        };
+        
+        if volume.read_inode(volume.superblock.root_ino)?.file_type() != FileType::Directory {
+            log::warn!("XFS: ルートディレクトリが不正です");
+            return Err(FsError::CorruptedFs);
+        }
+        
+        log::info!("XFS: ボリューム '{}' ({}ブロック x {}バイト, AG {}個)",
+                  volume.superblock.label, volume.superblock.data_blocks, volume.superblock.block_size,
+                  volume.superblock.ag_count);
+        
+        Ok(volume)
+    }
+    
+    /// ファイルシステムブロックから`len`バイトを読み込み、（内容, ディスクアドレス）を返す
+    fn read_block(&self, fsbno: u64, len: usize) -> FsResult<(Vec<u8>, u64)> {
+        let offset = self.superblock.fsb_offset(fsbno)?;
+        Ok((read_bytes(&*self.device, offset, len)?, offset / BBSIZE))
+    }
+    
+    /// iノードを読み込んで検証
+    fn read_inode(&self, ino: u64) -> FsResult<Arc<XfsInode>> {
+        if let Some(inode) = self.inode_cache.as_ref().and_then(|cache| cache.lock().get(&ino).cloned()) {
+            return Ok(inode);
+        }
+        
+        let Some((agno, agino, offset)) = self.superblock.inode_location(ino) else {
+            log::warn!("XFS: iノード番号{}が範囲外です", ino);
+            return Err(FsError::CorruptedFs);
+        };
+        if self.verify_inode_allocation && !self.inode_allocated(agno, agino)? {
+            log::warn!("XFS: iノード{}は割り当てられていません", ino);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let data = read_bytes(&*self.device, offset, self.superblock.inode_size as usize)?;
+        let inode = Arc::new(XfsInode::parse(&data, ino, &self.superblock.meta_uuid)?);
+        if inode.mode == 0 {
+            log::warn!("XFS: iノード{}は使用されていません", ino);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        if let Some(cache) = &self.inode_cache {
+            let mut cache = cache.lock();
+            if cache.len() >= INODE_CACHE_CAPACITY {
+                cache.pop_first();
+            }
+            cache.insert(ino, inode.clone());
+        }
+        Ok(inode)
+    }
+    
+    /// inobtを辿ってAG内のiノードが割り当て済みか確認
+    fn inode_allocated(&self, agno: u32, agino: u32) -> FsResult<bool> {
+        let ag = &self.ags[agno as usize];
+        let sparse = self.superblock.has(incompat::SPINODES);
+        let mut agbno = ag.inobt_root;
+        let mut level = ag.inobt_levels - 1;
+        
+        loop {
+            if agbno >= self.superblock.ag_blocks {
+                return Err(FsError::CorruptedFs);
+            }
+            let offset = self.superblock.ag_block_offset(agno, agbno);
+            let data = read_bytes(&*self.device, offset, self.superblock.block_size as usize)?;
+            let block = BtreeBlock::parse_short(data, INOBT, offset / BBSIZE, &self.superblock.meta_uuid, agno)?;
+            if block.level as u32 != level {
+                log::warn!("XFS: AG {}のinobtの高さが一致しません", agno);
+                return Err(FsError::CorruptedFs);
+            }
+            
+            if level == 0 {
+                return Ok((0..block.numrecs)
+                    .map(|i| InobtRecord::parse(block.record(i), sparse))
+                    .find(|record| record.contains(agino))
+                    .is_some_and(|record| record.is_allocated(agino)));
+            }
+            
+            // キーは各子の先頭のiノード番号
+            let children = (0..block.numrecs).take_while(|&i| be_u32(block.key(i), 0) <= agino).count();
+            let Some(index) = children.checked_sub(1) else {
+                return Ok(false);
+            };
+            agbno = block.pointer(index) as u32;
+            level -= 1;
+        }
+    }
+    
+    /// データフォークのエクステント一覧（ファイル内の位置順）
+    fn extents(&self, inode: &XfsInode) -> FsResult<Vec<Extent>> {
+        let extents = match &inode.fork {
+            DataFork::Extents(extents) => extents.clone(),
+            DataFork::Btree { level, children } => {
+                let mut extents = Vec::new();
+                for &(_, fsbno) in children {
+                    self.collect_bmbt(inode.number, fsbno, *level - 1, &mut extents)?;
+                }
+                extents
+            },
+            DataFork::Local(_) | DataFork::Device(_) => Vec::new(),
+        };
+        
+        // 重なりが無く、それぞれが1つのAGに収まっていること
+        let ag_log = self.superblock.ag_block_log;
+        let mut end = 0;
+        for extent in &extents {
+            let last = extent.block + extent.count.saturating_sub(1);
+            if extent.count == 0 || extent.offset < end || extent.block >> ag_log != last >> ag_log
+                || self.superblock.fsb_offset(last).is_err() {
+                log::warn!("XFS: iノード{}のエクステントが不正です", inode.number);
+                return Err(FsError::CorruptedFs);
+            }
+            end = extent.end();
+        }
+        Ok(extents)
+    }
+    
+    /// ブロックマップB+treeの部分木からエクステントを集める
+    fn collect_bmbt(&self, ino: u64, fsbno: u64, level: u16, extents: &mut Vec<Extent>) -> FsResult<()> {
+        let (data, daddr) = self.read_block(fsbno, self.superblock.block_size as usize)?;
+        let block = BtreeBlock::parse_long(data, BMBT, daddr, &self.superblock.meta_uuid, ino)?;
+        if block.level != level || level as u32 >= MAX_BTREE_LEVELS {
+            log::warn!("XFS: iノード{}のブロックマップの高さが一致しません", ino);
+            return Err(FsError::CorruptedFs);
+        }
+        
+        if level == 0 {
+            extents.extend((0..block.numrecs).map(|i| Extent::parse(block.record(i))));
+            return Ok(());
+        }
+        for i in 0..block.numrecs {
+            self.collect_bmbt(ino, block.pointer(i), level - 1, extents)?;
+        }
+        Ok(())
+    }
+    
+    /// ファイル内のブロックを含むエクステント
+    fn map_block(extents: &[Extent], file_block: u64) -> Option<&Extent> {
+        let index = extents.partition_point(|extent| extent.end() <= file_block);
+        extents.get(index).filter(|extent| extent.offset <= file_block)
+    }
+    
+    /// ファイルの内容を読み込み（穴と未書き込みの領域は0として読む）
+    fn read_file(&self, inode: &XfsInode, extents: &[Extent], buffer: &mut [u8], offset: u64) -> FsResult<usize> {
+        if offset >= inode.size {
+            return Ok(0);
+        }
+        let len = (buffer.len() as u64).min(inode.size - offset) as usize;
+        let end = offset + len as u64;
+        let buffer = &mut buffer[..len];
+        buffer.fill(0);
+        
+        let block_size = self.superblock.block_size as u64;
+        let first = extents.partition_point(|extent| extent.end() * block_size <= offset);
+        for extent in &extents[first..] {
+            let start = extent.offset * block_size;
+            if start >= end {
+                break;
+            }
+            let from = start.max(offset);
+            let to = (extent.end() * block_size).min(end);
+            if extent.unwritten {
+                continue;
+            }
+            let disk = self.superblock.fsb_offset(extent.block)? + (from - start);
+            let data = read_bytes(&*self.device, disk, (to - from) as usize)?;
+            buffer[(from - offset) as usize..(to - offset) as usize].copy_from_slice(&data);
+        }
+        Ok(len)
+    }
+    
+    /// ディレクトリブロック（ディレクトリ内のブロック番号`dablk`から始まる）を読み込み、
+    /// （内容, ディスクアドレス）を返す（割り当てられていなければNone）
+    fn read_dir_block(&self, dir: &XfsInode, extents: &[Extent], dablk: u64) -> FsResult<Option<(Vec<u8>, u64)>> {
+        let count = 1u64 << self.superblock.dir_block_log;
+        let block_size = self.superblock.block_size as usize;
+        let mut data = Vec::with_capacity(block_size << self.superblock.dir_block_log);
+        let mut daddr = 0;
+        for file_block in dablk..dablk + count {
+            let Some(extent) = Self::map_block(extents, file_block) else {
+                if file_block == dablk {
+                    return Ok(None);
+                }
+                log::warn!("XFS: ディレクトリ{}のブロック{}が途中までしか割り当てられていません", dir.number, dablk);
+                return Err(FsError::CorruptedFs);
+            };
+            let (block, address) = self.read_block(extent.block + (file_block - extent.offset), block_size)?;
+            if file_block == dablk {
+                daddr = address;
+            }
+            data.extend_from_slice(&block);
+        }
+        Ok(Some((data, daddr)))
+    }
+    
+    /// ディレクトリ内のバイト位置にあるデータブロックを読み込んで検証
+    fn data_block(&self, dir: &XfsInode, extents: &[Extent], position: u64) -> FsResult<DataBlock> {
+        let dablk = position / self.superblock.dir_block_size() as u64 * (1 << self.superblock.dir_block_log);
+        let (data, daddr) = self.read_dir_block(dir, extents, dablk)?.ok_or(FsError::CorruptedFs)?;
+        DataBlock::parse(data, daddr, &self.superblock.meta_uuid, dir.number)
+    }
+    
+    /// ディレクトリ内の名前を検索
+    fn lookup_entry(&self, dir: &XfsInode, name: &str) -> FsResult<Entry> {
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH as usize {
+            return Err(FsError::NotFound);
+        }
+        let has_ftype = self.superblock.has(incompat::FTYPE);
+        
+        if let DataFork::Local(data) = &dir.fork {
+            let (parent, entries) = parse_shortform(data, has_ftype)?;
+            let inode = match name {
+                "." => dir.number,
+                ".." => parent,
+                _ => return entries.into_iter().find(|entry| entry.name == name).ok_or(FsError::NotFound),
+            };
+            return Ok(Entry { name: name.to_string(), inode, file_type: Some(FileType::Directory) });
+        }
+        
+        let extents = self.extents(dir)?;
+        let hash = hash_name(name.as_bytes());
+        let leaf_block = LEAF_OFFSET / self.superblock.block_size as u64;
+        
+        // リーフが無ければブロック形式で、データブロックの末尾にリーフがある
+        if Self::map_block(&extents, leaf_block).is_none() {
+            let block = self.data_block(dir, &extents, 0)?;
+            let leaf = block.leaf.as_ref().ok_or(FsError::CorruptedFs)?;
+            return self.find_in_leaf(dir, &extents, leaf, hash, name)?.ok_or(FsError::NotFound);
+        }
+        
+        // 内部ノードを辿り、同じハッシュが続く限り次のリーフも調べる
+        let mut dablk = leaf_block;
+        for _ in 0..MAX_DA_STEPS {
+            let (data, daddr) = self.read_dir_block(dir, &extents, dablk)?.ok_or(FsError::CorruptedFs)?;
+            match parse_da_block(&data, daddr, &self.superblock.meta_uuid, dir.number)? {
+                DaBlock::Node(children) => {
+                    // 各子のハッシュは部分木の最大値
+                    let child = children.iter().find(|(max, _)| *max >= hash).or(children.last()).ok_or(FsError::CorruptedFs)?;
+                    dablk = child.1 as u64;
+                },
+                DaBlock::Leaf { entries, forw } => {
+                    if let Some(entry) = self.find_in_leaf(dir, &extents, &entries, hash, name)? {
+                        return Ok(entry);
+                    }
+                    if forw == 0 || entries.last().is_none_or(|&(last, _)| last != hash) {
+                        return Err(FsError::NotFound);
+                    }
+                    dablk = forw as u64;
+                },
+            }
+        }
+        log::warn!("XFS: ディレクトリ{}のリーフ/ノードの階層が深すぎます", dir.number);
+        Err(FsError::CorruptedFs)
+    }
+    
+    /// リーフのうちハッシュが一致するエントリの名前を確かめる
+    fn find_in_leaf(&self, dir: &XfsInode, extents: &[Extent], leaf: &[LeafEntry], hash: u32, name: &str) -> FsResult<Option<Entry>> {
+        let has_ftype = self.superblock.has(incompat::FTYPE);
+        let dir_block_size = self.superblock.dir_block_size() as u64;
+        let first = leaf.partition_point(|&(entry_hash, _)| entry_hash < hash);
+        
+        // 位置0は削除済み（stale）のエントリ
+        for &(_, address) in leaf[first..].iter().take_while(|&&(entry_hash, _)| entry_hash == hash).filter(|e| e.1 != 0) {
+            let position = address as u64 * 8;
+            let block = self.data_block(dir, extents, position)?;
+            let (entry, _) = block.entry_at((position % dir_block_size) as usize, has_ftype)?;
+            if entry.name == name {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// ディレクトリ内の名前を検索してiノード番号を返す
+    fn lookup(&self, dir: &XfsInode, name: &str) -> FsResult<u64> {
+        Ok(self.lookup_entry(dir, name)?.inode)
+    }
+    
+    /// ルートディレクトリからのパスを解決
+    fn resolve(&self, path: &str) -> FsResult<Arc<XfsInode>> {
+        let mut stack = vec![self.read_inode(self.superblock.root_ino)?];
+        
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if stack.len() > MAX_PATH_DEPTH {
+                return Err(FsError::InvalidData);
+            }
+            
+            match component {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                },
+                name => {
+                    let current = stack.last().ok_or(FsError::NotFound)?;
+                    if current.file_type() != FileType::Directory {
+                        return Err(FsError::NotDirectory);
+                    }
+                    let next = self.read_inode(self.lookup(current, name)?)?;
+                    stack.push(next);
+                },
+            }
+        }
+        
+        stack.pop().ok_or(FsError::NotFound)
+    }
+    
+    /// ディレクトリの内容を列挙（"."と".."は含めない）
+    fn list_directory(&self, dir: &XfsInode) -> FsResult<Vec<DirEntry>> {
+        let has_ftype = self.superblock.has(incompat::FTYPE);
+        let entries = match &dir.fork {
+            DataFork::Local(data) => parse_shortform(data, has_ftype)?.1,
+            _ => {
+                let extents = self.extents(dir)?;
+                let blocks_per_dir_block = 1u64 << self.superblock.dir_block_log;
+                let data_end = (LEAF_OFFSET.min(dir.size) / self.superblock.block_size as u64).div_ceil(blocks_per_dir_block)
+                    * blocks_per_dir_block;
+                
+                let mut entries = Vec::new();
+                let mut dablk = 0;
+                while dablk < data_end {
+                    // 割り当てられていないデータブロック（空になって解放されたもの）は飛ばす
+                    let Some(extent) = extents.get(extents.partition_point(|extent| extent.end() <= dablk)) else {
+                        break;
+                    };
+                    dablk = dablk.max(extent.offset / blocks_per_dir_block * blocks_per_dir_block);
+                    if dablk >= data_end {
+                        break;
+                    }
+                    let block = self.data_block(dir, &extents, dablk * self.superblock.block_size as u64)?;
+                    entries.extend(block.entries(has_ftype)?);
+                    dablk += blocks_per_dir_block;
+                }
+                entries
+            },
+        };
+        
+        entries
+            .into_iter()
+            .filter(|entry| entry.name != "." && entry.name != "..")
+            .map(|entry| {
+                let file_type = match entry.file_type {
+                    Some(file_type) => file_type,
+                    None => self.read_inode(entry.inode)?.file_type(),
+                };
+                Ok(DirEntry { name: entry.name, inode: entry.inode, file_type })
+            })
+            .collect()
+    }
+    
+    /// シンボリックリンクの内容を読み込み
+    fn read_link(&self, inode: &XfsInode) -> FsResult<String> {
+        if inode.file_type() != FileType::SymbolicLink {
+            return Err(FsError::InvalidData);
+        }
+        if inode.size == 0 || inode.size > MAX_SYMLINK_LENGTH {
+            return Err(FsError::CorruptedFs);
+        }
+        
+        let target = match &inode.fork {
+            DataFork::Local(data) => data.clone(),
+            _ => self.read_remote_symlink(inode)?,
+        };
+        String::from_utf8(target).map_err(|_| FsError::InvalidData)
+    }
+    
+    /// ブロックに置かれたシンボリックリンクを読み込み（各ブロックにヘッダが付く）
+    fn read_remote_symlink(&self, inode: &XfsInode) -> FsResult<Vec<u8>> {
+        let extents = self.extents(inode)?;
+        let block_size = self.superblock.block_size as usize;
+        let mut target = Vec::with_capacity(inode.size as usize);
+        let mut file_block = 0;
+        
+        while (target.len() as u64) < inode.size {
+            let extent = Self::map_block(&extents, file_block).ok_or(FsError::CorruptedFs)?;
+            let (data, daddr) = self.read_block(extent.block + (file_block - extent.offset), block_size)?;
+            if be_u32(&data, 0) != SYMLINK_MAGIC {
+                log::warn!("XFS: シンボリックリンク{}のブロックのマジックが不正です", inode.number);
+                return Err(FsError::CorruptedFs);
+            }
+            if !crc32c::verify(&data, SYMLINK_CRC_OFFSET) {
+                log::warn!("XFS: シンボリックリンク{}のブロックのチェックサムが一致しません", inode.number);
+                return Err(FsError::ChecksumError);
+            }
+            let bytes = be_u32(&data, 8) as usize;
+            if be_u32(&data, 4) as usize != target.len() || data[16..32] != self.superblock.meta_uuid
+                || be_u64(&data, 32) != inode.number || be_u64(&data, 40) != daddr
+                || bytes == 0 || SYMLINK_HEADER_SIZE + bytes > block_size {
+                log::warn!("XFS: シンボリックリンク{}のブロックのヘッダが不正です", inode.number);
+                return Err(FsError::CorruptedFs);
+            }
+            target.extend_from_slice(&data[SYMLINK_HEADER_SIZE..SYMLINK_HEADER_SIZE + bytes]);
+            file_block += 1;
+        }
+        
+        if target.len() as u64 != inode.size {
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(target)
+    }
+    
+    /// iノードのメタデータを構築
+    fn metadata(&self, inode: &XfsInode) -> Metadata {
+        Metadata {
+            inode: inode.number,
+            file_type: inode.file_type(),
+            size: inode.size,
+            uid: inode.uid,
+            gid: inode.gid,
+            permissions: Permissions {
+                read: inode.mode & 0o400 != 0,
+                write: false,
+                execute: inode.mode & 0o100 != 0,
+            },
+            created: inode.crtime,
+            accessed: inode.atime,
+            modified: inode.mtime,
+            links: inode.nlink,
+            block_size: self.superblock.block_size,
+            blocks: inode.nblocks,
+        }
+    }
+    
+    /// ファイルシステム統計を取得
+    fn stats(&self) -> FsStats {
+        let free = self.ags.iter().map(|ag| ag.free_blocks).sum();
+        FsStats {
+            total_blocks: self.superblock.data_blocks,
+            free_blocks: free,
+            available_blocks: free,
+            // iノードは動的に割り当てるため、割り当て済みのチャンクの分を数える
+            total_nodes: self.ags.iter().map(|ag| ag.inode_count as u64).sum(),
+            free_nodes: self.ags.iter().map(|ag| ag.free_inodes as u64).sum(),
+            block_size: self.superblock.block_size,
+            max_filename_length: MAX_NAME_LENGTH,
+        }
+    }
+}
+
+/// XFSファイルシステム
+pub struct XfsFilesystem {
+    /// ファイルシステム名
+    name: String,
+    /// オプション
+    options: XfsOptions,
+    /// マウントされたボリューム（マウントポイント => ボリューム）
+    volumes: RwLock<BTreeMap<String, Arc<XfsVolume>>>,
+}
+
+impl XfsFilesystem {
+    /// 新しいXFSファイルシステムインスタンスを作成
+    pub fn new() -> Self {
+        Self::new_advanced(XfsOptions::default())
+    }
+    
+    /// iノードをキャッシュする最適化されたXFSファイルシステムインスタンスを作成
+    pub fn new_optimized() -> Self {
+        Self::new_advanced(XfsOptions {
+            verify_inode_allocation: true,
+            cache_inodes: true,
+        })
+    }
+    
+    /// 高度なオプションでXFSファイルシステムインスタンスを作成
+    pub fn new_advanced(options: XfsOptions) -> Self {
+        Self {
+            name: "xfs".to_string(),
+            options,
+            volumes: RwLock::new(BTreeMap::new()),
+        }
+    }
+    
+    /// マウントポイントのボリュームを取得
+    fn find_volume(&self, mount_point: &str) -> FsResult<Arc<XfsVolume>> {
+        self.volumes.read().get(mount_point).cloned().ok_or(FsError::NotFound)
+    }
+}
+
+impl Default for XfsFilesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filesystem for XfsFilesystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn init(&self) -> FsResult<()> {
+        Ok(())
+    }
+    
+    fn mount(&self, device: &str, mount_point: &str, options: &str) -> FsResult<()> {
+        if self.volumes.read().contains_key(mount_point) {
+            return Err(FsError::AlreadyExists);
+        }
+        if options.split(',').any(|option| option.trim() == "rw") {
+            log::warn!("XFS: 書き込みには対応していないため読み取り専用でマウントします");
+        }
+        
+        let block_device = super::vfs::open_block_device(device)?;
+        let volume = XfsVolume::open(device, block_device, self.options)?;
+        
+        self.volumes.write().insert(mount_point.to_string(), Arc::new(volume));
+        
+        log::info!("XFSファイルシステムをマウント: {} -> {} (読み取り専用)", device, mount_point);
+        Ok(())
+    }
+    
+    fn unmount(&self, mount_point: &str) -> FsResult<()> {
+        let volume = self.volumes.write().remove(mount_point).ok_or(FsError::NotFound)?;
+        volume.device.close()?;
+        
+        log::info!("XFSファイルシステムをアンマウント: {} ({})", mount_point, volume.device_path);
+        Ok(())
+    }
+    
+    fn open_file(&self, mount_point: &str, path: &str, mode: OpenMode) -> FsResult<Arc<dyn FileHandle>> {
+        if mode != OpenMode::ReadOnly {
+            return Err(FsError::ReadOnly);
+        }
+        
+        let volume = self.find_volume(mount_point)?;
+        let inode = volume.resolve(path)?;
+        if inode.file_type() == FileType::Directory {
+            return Err(FsError::IsDirectory);
+        }
+        if inode.flags & flags::REALTIME != 0 {
+            log::warn!("XFS: リアルタイムデバイス上のファイルには対応していません");
+            return Err(FsError::NotSupported);
+        }
+        
+        let extents = volume.extents(&inode)?;
+        Ok(Arc::new(XfsFileHandle::new(volume, inode, extents)))
+    }
+    
+    fn open_directory(&self, mount_point: &str, path: &str) -> FsResult<Arc<dyn DirHandle>> {
+        let volume = self.find_volume(mount_point)?;
+        let inode = volume.resolve(path)?;
+        
+        if inode.file_type() != FileType::Directory {
+            return Err(FsError::NotDirectory);
+        }
+        
+        Ok(Arc::new(XfsDirHandle::new(volume, inode)))
+    }
+    
+    fn metadata(&self, mount_point: &str, path: &str) -> FsResult<Metadata> {
+        let volume = self.find_volume(mount_point)?;
+        Ok(volume.metadata(&*volume.resolve(path)?))
+    }
+    
+    fn stats(&self, mount_point: &str) -> FsResult<FsStats> {
+        Ok(self.find_volume(mount_point)?.stats())
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        // 読み取り専用のため書き戻すものは無い
+        Ok(())
+    }
+    
+    fn read_link(&self, mount_point: &str, path: &str) -> FsResult<String> {
+        let volume = self.find_volume(mount_point)?;
+        volume.read_link(&*volume.resolve(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::ag::tests as agh;
+    use super::dir::tests::{da_block, data_block, LEAF1, LEAFN, NODE};
+    use super::inode::format;
+    use super::inode::tests::{build as dinode, extent};
+    use super::superblock::tests::{self as sb, AG_BLOCKS, BLOCK_SIZE, ROOT_INO, UUID};
+    use super::super::memory_disk::MemoryDisk;
+    
+    /// iノードチャンクの先頭（AG 0のブロック8）
+    const CHUNK_START: u64 = 64;
+    /// 使用中のiノード（ルートから順に）
+    const USED_INODES: u64 = 10;
+    /// ディレクトリのリーフの開始ブロック
+    const LEAF_BLOCK: u64 = LEAF_OFFSET / BLOCK_SIZE as u64;
+    /// AG 1のブロック10（ファイルシステムブロック番号）
+    const AG1_BLOCK: u64 = 1 << 6 | 10;
+    const MODE_DIR: u16 = 0o040755;
+    const MODE_FILE: u16 = 0o100644;
+    
+    fn byte_offset(fsbno: u64) -> usize {
+        ((fsbno >> 6) * AG_BLOCKS as u64 + (fsbno & 63)) as usize * BLOCK_SIZE
+    }
+    
+    fn daddr(fsbno: u64) -> u64 {
+        byte_offset(fsbno) as u64 / BBSIZE
+    }
+    
+    fn put(image: &mut [u8], offset: usize, data: &[u8]) {
+        image[offset..offset + data.len()].copy_from_slice(data);
+    }
+    
+    /// AG 0のiノードの位置（ブロックあたり8個）
+    fn inode_offset(ino: u64) -> usize {
+        byte_offset(ino >> 3) + (ino & 7) as usize * 512
+    }
+    
+    fn put_inode(image: &mut [u8], ino: u64, mode: u16, size: u64, fork: (u8, &[u8]), nextents: u32) {
+        put(image, inode_offset(ino), &dinode(ino, &UUID, mode, size, fork, nextents));
+    }
+    
+    fn extents(list: &[(u64, u64, u64, bool)]) -> Vec<u8> {
+        list.iter().flat_map(|&(offset, block, count, unwritten)| extent(offset, block, count, unwritten)).collect()
+    }
+    
+    /// ショート形式のB+treeブロック（inobtのリーフ、レコード1つ）
+    fn inobt_leaf(agno: u32, fsbno: u64, record: Option<(u32, u64)>) -> Vec<u8> {
+        let mut data = vec![0u8; BLOCK_SIZE];
+        data[0..4].copy_from_slice(&INOBT.magic.to_be_bytes());
+        data[8..16].fill(0xFF);
+        data[16..24].copy_from_slice(&daddr(fsbno).to_be_bytes());
+        data[32..48].copy_from_slice(&UUID);
+        data[48..52].copy_from_slice(&agno.to_be_bytes());
+        if let Some((start, free)) = record {
+            data[6..8].copy_from_slice(&1u16.to_be_bytes());
+            data[56..60].copy_from_slice(&start.to_be_bytes());
+            data[60..64].copy_from_slice(&(free.count_ones()).to_be_bytes());
+            data[64..72].copy_from_slice(&free.to_be_bytes());
+        }
+        let crc = crc32c::checksum(&data, 52);
+        data[52..56].copy_from_slice(&crc.to_le_bytes());
+        data
+    }
+    
+    /// ロング形式のB+treeブロック（bmbtのリーフ）
+    fn bmbt_leaf(owner: u64, fsbno: u64, records: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; BLOCK_SIZE];
+        data[0..4].copy_from_slice(&BMBT.magic.to_be_bytes());
+        data[6..8].copy_from_slice(&((records.len() / 16) as u16).to_be_bytes());
+        data[8..24].fill(0xFF);
+        data[24..32].copy_from_slice(&daddr(fsbno).to_be_bytes());
+        data[40..56].copy_from_slice(&UUID);
+        data[56..64].copy_from_slice(&owner.to_be_bytes());
+        data[72..72 + records.len()].copy_from_slice(records);
+        let crc = crc32c::checksum(&data, 64);
+        data[64..68].copy_from_slice(&crc.to_le_bytes());
+        data
+    }
+    
+    /// リモートシンボリックリンクのブロック
+    fn symlink_block(owner: u64, fsbno: u64, target: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; BLOCK_SIZE];
+        data[0..4].copy_from_slice(&SYMLINK_MAGIC.to_be_bytes());
+        data[8..12].copy_from_slice(&(target.len() as u32).to_be_bytes());
+        data[16..32].copy_from_slice(&UUID);
+        data[32..40].copy_from_slice(&owner.to_be_bytes());
+        data[40..48].copy_from_slice(&daddr(fsbno).to_be_bytes());
+        data[SYMLINK_HEADER_SIZE..SYMLINK_HEADER_SIZE + target.len()].copy_from_slice(target);
+        let crc = crc32c::checksum(&data, SYMLINK_CRC_OFFSET);
+        data[SYMLINK_CRC_OFFSET..SYMLINK_CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        data
+    }
+    
+    /// ショート形式のディレクトリ（4バイトのiノード番号、FTYPE有効）
+    fn shortform(parent: u64, entries: &[(&str, u64, u8)]) -> Vec<u8> {
+        let mut data = vec![entries.len() as u8, 0];
+        data.extend_from_slice(&(parent as u32).to_be_bytes());
+        for &(name, inode, ftype) in entries {
+            data.push(name.len() as u8);
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(name.as_bytes());
+            data.push(ftype);
+            data.extend_from_slice(&(inode as u32).to_be_bytes());
+        }
+        data
+    }
+    
+    fn node_names() -> Vec<String> {
+        (0..40).map(|i| format!("n{:02}", i)).collect()
+    }
+    
+    fn big_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+    
+    fn long_target() -> String {
+        "deep/".repeat(60) + "target"
+    }
+    
+    fn build_image() -> Vec<u8> {
+        let mut image = vec![0u8; BLOCK_SIZE * AG_BLOCKS as usize * 2];
+        put(&mut image, 0, &sb::build(incompat::FTYPE));
+        
+        // AG 0はiノード64〜127のチャンクを持ち、先頭10個が使用中、AG 1にはiノードが無い
+        let used_mask = (1u64 << USED_INODES) - 1;
+        let (agf, agi) = agh::build(0, AG_BLOCKS, &UUID, 20, (64, 64 - USED_INODES as u32), 1);
+        put(&mut image, 512, &agf);
+        put(&mut image, 1024, &agi);
+        put(&mut image, byte_offset(1), &inobt_leaf(0, 1, Some((CHUNK_START as u32, !used_mask))));
+        let (agf, agi) = agh::build(1, AG_BLOCKS, &UUID, 30, (0, 0), 1);
+        put(&mut image, byte_offset(1 << 6) + 512, &agf);
+        put(&mut image, byte_offset(1 << 6) + 1024, &agi);
+        put(&mut image, byte_offset(1 << 6 | 1), &inobt_leaf(1, 1 << 6 | 1, None));
+        
+        // ルート（ショート形式）
+        let root = shortform(ROOT_INO, &[
+            ("hello.txt", 65, 1), ("blockdir", 66, 2), ("leafdir", 67, 2), ("nodedir", 68, 2),
+            ("big.bin", 69, 1), ("btree.bin", 70, 1), ("link", 71, 7), ("longlink", 72, 7),
+            ("dev", 73, 3), ("ghost", 80, 1),
+        ]);
+        put_inode(&mut image, 64, MODE_DIR, root.len() as u64, (format::LOCAL, &root), 0);
+        
+        put_inode(&mut image, 65, MODE_FILE, 10, (format::EXTENTS, &extents(&[(0, 20, 1, false)])), 1);
+        put(&mut image, byte_offset(20), b"hello xfs\n");
+        
+        // ブロック形式
+        let (block, _) = data_block(BLOCK_SIZE, &[(66, ".", 2), (64, "..", 2), (65, "a.txt", 1), (65, "b.txt", 1)],
+                                    true, daddr(21), &UUID, 66);
+        put(&mut image, byte_offset(21), &block);
+        put_inode(&mut image, 66, MODE_DIR, BLOCK_SIZE as u64, (format::EXTENTS, &extents(&[(0, 21, 1, false)])), 1);
+        
+        // リーフ形式
+        let mut leaf_entries = vec![(67, ".".to_string(), 2), (64, "..".to_string(), 2)];
+        leaf_entries.extend((0..10).map(|i| (65, format!("f{}", i), 1)));
+        let borrowed: Vec<(u64, &str, u8)> = leaf_entries.iter().map(|(ino, name, t)| (*ino, name.as_str(), *t)).collect();
+        let (block, offsets) = data_block(BLOCK_SIZE, &borrowed, false, daddr(22), &UUID, 67);
+        put(&mut image, byte_offset(22), &block);
+        let mut leaf: Vec<(u32, u32)> = borrowed.iter().zip(&offsets)
+            .map(|(&(_, name, _), &offset)| (hash_name(name.as_bytes()), offset as u32 / 8))
+            .collect();
+        leaf.sort();
+        put(&mut image, byte_offset(23), &da_block(BLOCK_SIZE, LEAF1, &leaf, 0, daddr(23), &UUID, 67));
+        put_inode(&mut image, 67, MODE_DIR, BLOCK_SIZE as u64,
+                  (format::EXTENTS, &extents(&[(0, 22, 1, false), (LEAF_BLOCK, 23, 1, false)])), 2);
+        
+        // ノード形式（データブロック2つ、リーフ2つ）
+        let names = node_names();
+        let mut leaf = Vec::new();
+        for (half, fsbno) in [(0usize, 24u64), (1, 25)] {
+            let mut entries: Vec<(u64, &str, u8)> = Vec::new();
+            if half == 0 {
+                entries.extend([(68, ".", 2), (64, "..", 2)]);
+            }
+            entries.extend(names[half * 20..half * 20 + 20].iter().map(|name| (65, name.as_str(), 1)));
+            let (block, offsets) = data_block(BLOCK_SIZE, &entries, false, daddr(fsbno), &UUID, 68);
+            put(&mut image, byte_offset(fsbno), &block);
+            leaf.extend(entries.iter().zip(&offsets)
+                .map(|(&(_, name, _), &offset)| (hash_name(name.as_bytes()), ((half * BLOCK_SIZE + offset) / 8) as u32)));
+        }
+        leaf.sort();
+        let (first, second) = leaf.split_at(leaf.len() / 2);
+        let node = [(first.last().unwrap().0, LEAF_BLOCK as u32 + 1), (second.last().unwrap().0, LEAF_BLOCK as u32 + 2)];
+        put(&mut image, byte_offset(26), &da_block(BLOCK_SIZE, NODE, &node, 0, daddr(26), &UUID, 68));
+        put(&mut image, byte_offset(27), &da_block(BLOCK_SIZE, LEAFN, first, LEAF_BLOCK as u32 + 2, daddr(27), &UUID, 68));
+        put(&mut image, byte_offset(28), &da_block(BLOCK_SIZE, LEAFN, second, 0, daddr(28), &UUID, 68));
+        put_inode(&mut image, 68, MODE_DIR, 2 * BLOCK_SIZE as u64,
+                  (format::EXTENTS, &extents(&[(0, 24, 2, false), (LEAF_BLOCK, 26, 3, false)])), 2);
+        
+        // 別のAGのエクステント、穴、未書き込みのエクステント
+        let content = big_data(4 * BLOCK_SIZE + 100);
+        put(&mut image, byte_offset(AG1_BLOCK), &content[..2 * BLOCK_SIZE]);
+        put(&mut image, byte_offset(30), &[0xEE; BLOCK_SIZE]);
+        put(&mut image, byte_offset(31), &content[4 * BLOCK_SIZE..]);
+        let big = extents(&[(0, AG1_BLOCK, 2, false), (3, 30, 1, true), (4, 31, 1, false)]);
+        put_inode(&mut image, 69, MODE_FILE, content.len() as u64, (format::EXTENTS, &big), 3);
+        
+        // 高さ1のブロックマップ（ルートはiノード内、リーフが2つ）
+        put(&mut image, byte_offset(33), &bmbt_leaf(70, 33, &extents(&[(0, 40, 1, false), (1, 41, 1, false)])));
+        put(&mut image, byte_offset(34), &bmbt_leaf(70, 34, &extents(&[(2, 42, 1, false)])));
+        for i in 0..3 {
+            put(&mut image, byte_offset(40 + i), &[b'A' + i as u8; BLOCK_SIZE]);
+        }
+        let max = (512 - 176 - 4) / 16;
+        let mut root = vec![0u8; 4 + max * 16];
+        root[0..2].copy_from_slice(&1u16.to_be_bytes());
+        root[2..4].copy_from_slice(&2u16.to_be_bytes());
+        for (i, (key, ptr)) in [(0u64, 33u64), (2, 34)].into_iter().enumerate() {
+            root[4 + i * 8..12 + i * 8].copy_from_slice(&key.to_be_bytes());
+            root[4 + max * 8 + i * 8..12 + max * 8 + i * 8].copy_from_slice(&ptr.to_be_bytes());
+        }
+        put_inode(&mut image, 70, MODE_FILE, 3 * BLOCK_SIZE as u64, (format::BTREE, &root), 3);
+        
+        // シンボリックリンク（iノード内とブロック）
+        put_inode(&mut image, 71, 0o120777, 9, (format::LOCAL, b"hello.txt"), 0);
+        let target = long_target();
+        put(&mut image, byte_offset(35), &symlink_block(72, 35, target.as_bytes()));
+        put_inode(&mut image, 72, 0o120777, target.len() as u64, (format::EXTENTS, &extents(&[(0, 35, 1, false)])), 1);
+        
+        put_inode(&mut image, 73, 0o020644, 0, (format::DEV, &(4u32 << 18 | 1).to_be_bytes()), 0);
+        image
+    }
+    
+    fn mounted(image: Vec<u8>) -> XfsFilesystem {
+        let disk: Arc<dyn BlockDevice> = Arc::new(MemoryDisk::read_only(image));
+        let volume = XfsVolume::open("/dev/test", disk, XfsOptions::default()).unwrap();
+        let fs = XfsFilesystem::new_optimized();
+        fs.volumes.write().insert("/mnt".to_string(), Arc::new(volume));
+        fs
+    }
+    
+    fn names(fs: &XfsFilesystem, path: &str) -> Vec<String> {
+        fs.open_directory("/mnt", path).unwrap().read_entries().unwrap().into_iter().map(|e| e.name).collect()
+    }
+    
+    #[test]
+    fn reads_all_directory_and_mapping_formats() {
+        let fs = mounted(build_image());
+        assert_eq!(names(&fs, "/"), vec!["hello.txt", "blockdir", "leafdir", "nodedir", "big.bin", "btree.bin",
+                                         "link", "longlink", "dev", "ghost"]);
+        assert_eq!(names(&fs, "blockdir"), vec!["a.txt", "b.txt"]);
+        assert_eq!(names(&fs, "leafdir").len(), 10);
+        assert_eq!(names(&fs, "nodedir"), node_names());
+        
+        let mut buffer = [0u8; 16];
+        let hello = fs.open_file("/mnt", "hello.txt", OpenMode::ReadOnly).unwrap();
+        assert_eq!(hello.read(&mut buffer, 0).unwrap(), 10);
+        assert_eq!(&buffer[..10], b"hello xfs\n");
+        assert!(matches!(fs.open_file("/mnt", "hello.txt", OpenMode::ReadWrite), Err(FsError::ReadOnly)));
+        
+        // ハッシュで引く（ブロック/リーフ/ノード形式）と"."/".."
+        assert_eq!(fs.metadata("/mnt", "blockdir/b.txt").unwrap().inode, 65);
+        assert_eq!(fs.metadata("/mnt", "leafdir/f7").unwrap().inode, 65);
+        for name in node_names() {
+            assert_eq!(fs.metadata("/mnt", &format!("nodedir/{}", name)).unwrap().inode, 65);
+        }
+        assert!(matches!(fs.metadata("/mnt", "nodedir/n40"), Err(FsError::NotFound)));
+        assert_eq!(fs.metadata("/mnt", "leafdir/../blockdir/./a.txt").unwrap().inode, 65);
+        let dir = fs.open_directory("/mnt", "nodedir").unwrap();
+        assert_eq!(dir.lookup("..").unwrap().inode, ROOT_INO);
+        assert_eq!(dir.lookup("n13").unwrap().file_type, FileType::Regular);
+        assert_eq!(fs.open_directory("/mnt", "/").unwrap().device_number("dev").unwrap(), (4, 1));
+        
+        // 別のAG、穴、未書き込みのエクステント
+        let content = big_data(4 * BLOCK_SIZE + 100);
+        let big = fs.open_file("/mnt", "big.bin", OpenMode::ReadOnly).unwrap();
+        let mut data = vec![0xFFu8; content.len() + 50];
+        assert_eq!(big.read(&mut data, 0).unwrap(), content.len());
+        assert_eq!(&data[..2 * BLOCK_SIZE], &content[..2 * BLOCK_SIZE]);
+        assert!(data[2 * BLOCK_SIZE..4 * BLOCK_SIZE].iter().all(|&b| b == 0));
+        assert_eq!(&data[4 * BLOCK_SIZE..content.len()], &content[4 * BLOCK_SIZE..]);
+        let mut window = [0u8; 200];
+        assert_eq!(big.read(&mut window, BLOCK_SIZE as u64 - 100).unwrap(), 200);
+        assert_eq!(&window[..], &content[BLOCK_SIZE - 100..BLOCK_SIZE + 100]);
+        
+        let btree = fs.open_file("/mnt", "btree.bin", OpenMode::ReadOnly).unwrap();
+        let mut data = vec![0u8; 3 * BLOCK_SIZE];
+        btree.read(&mut data, 0).unwrap();
+        assert!(data.chunks(BLOCK_SIZE).zip(b"ABC").all(|(block, &c)| block.iter().all(|&b| b == c)));
+        
+        assert_eq!(fs.read_link("/mnt", "link").unwrap(), "hello.txt");
+        assert_eq!(fs.read_link("/mnt", "longlink").unwrap(), long_target());
+        // inobtで空きとされているiノードは読まない
+        assert!(matches!(fs.metadata("/mnt", "ghost"), Err(FsError::CorruptedFs)));
+        
+        let stats = fs.stats("/mnt").unwrap();
+        assert_eq!((stats.total_blocks, stats.free_blocks, stats.total_nodes, stats.free_nodes), (128, 50, 64, 54));
+        
+        // マウントポイントごとにボリュームを選ぶ
+        let other = mounted(build_image()).volumes.write().remove("/mnt").unwrap();
+        fs.volumes.write().insert("/other".to_string(), other);
+        fs.volumes.write().remove("/mnt");
+        assert_eq!(fs.metadata("/other", "blockdir/b.txt").unwrap().inode, 65);
+        assert!(matches!(fs.metadata("/mnt", "blockdir/b.txt"), Err(FsError::NotFound)));
+        assert!(matches!(fs.stats("/mnt"), Err(FsError::NotFound)));
+    }
+    
+    #[test]
+    fn detects_corrupted_metadata() {
+        // ノード形式のリーフ、ブロックマップ、iノード、シンボリックリンクのブロックのそれぞれを壊す
+        for (offset, path) in [(byte_offset(28) + 100, "nodedir"), (byte_offset(33) + 200, "btree.bin"),
+                               (inode_offset(65) + 300, "hello.txt"), (byte_offset(35) + 60, "longlink")] {
+            let mut image = build_image();
+            image[offset] ^= 0x40;
+            let fs = mounted(image);
+            let result = match path {
+                "nodedir" => node_names().iter().map(|name| fs.metadata("/mnt", &format!("nodedir/{}", name)).map(|_| ())).find(Result::is_err).unwrap(),
+                "btree.bin" => fs.open_file("/mnt", path, OpenMode::ReadOnly).map(|_| ()),
+                "longlink" => fs.read_link("/mnt", path).map(|_| ()),
+                _ => fs.metadata("/mnt", path).map(|_| ()),
+            };
+            assert!(matches!(result, Err(FsError::ChecksumError)), "{}", path);
+        }
+        
+        // AGヘッダが壊れていればマウントしない
+        let mut image = build_image();
+        image[1024 + 40] ^= 1;
+        let disk: Arc<dyn BlockDevice> = Arc::new(MemoryDisk::read_only(image));
+        assert!(matches!(XfsVolume::open("/dev/test", disk, XfsOptions::default()), Err(FsError::ChecksumError)));
+    }
+}
@@ -0,0 +1,300 @@
+// XFS スーパーブロック
+//
+// AG 0の先頭セクタにあるスーパーブロックの検証と、ファイルシステムブロック番号や
+// iノード番号からディスク上の位置への変換
+
+use alloc::string::String;
+use super::super::{FsError, FsResult};
+use super::crc32c;
+use super::{be_u16, be_u32, be_u64};
+
+/// マジック "XFSB"
+const MAGIC: u32 = 0x5846_5342;
+/// スーパーブロックの最小サイズ（最小のセクタサイズ）
+pub const SUPERBLOCK_MIN_SIZE: usize = 512;
+/// チェックサムのオフセット
+const CRC_OFFSET: usize = 224;
+/// ラベルのオフセット
+const FNAME_OFFSET: usize = 108;
+/// ラベルの最大長
+const FNAME_SIZE: usize = 12;
+/// versionnumのうちバージョン番号のビット
+const VERSION_NUMBITS: u16 = 0x000F;
+/// 大文字小文字を区別しないディレクトリ（ASCII-CI）
+const VERSION_BORGBIT: u16 = 0x4000;
+/// 対応するバージョン（CRC付きメタデータ）
+const VERSION_5: u16 = 5;
+/// iノードが無いことを表す番号
+pub const NULL_INO: u64 = u64::MAX;
+
+/// incompatフラグ
+pub mod incompat {
+    /// ディレクトリエントリにファイル種別を持つ
+    pub const FTYPE: u32 = 1 << 0;
+    /// 疎なiノードチャンク
+    pub const SPINODES: u32 = 1 << 1;
+    /// uuidと別のメタデータUUID
+    pub const META_UUID: u32 = 1 << 2;
+    /// 64ビットのタイムスタンプ
+    pub const BIGTIME: u32 = 1 << 3;
+    /// xfs_repairが必要
+    pub const NEEDSREPAIR: u32 = 1 << 4;
+    /// 64ビットのエクステント数
+    pub const NREXT64: u32 = 1 << 5;
+    /// アトミックなファイル範囲の交換
+    pub const EXCHRANGE: u32 = 1 << 6;
+    /// 親ポインタ
+    pub const PARENT: u32 = 1 << 7;
+    /// メタデータディレクトリツリー
+    pub const METADIR: u32 = 1 << 8;
+    
+    /// 読み込みに対応しているフラグ
+    pub const SUPPORTED: u32 = FTYPE | SPINODES | META_UUID | BIGTIME | NREXT64 | EXCHRANGE | PARENT;
+}
+
+/// XFSスーパーブロック
+#[derive(Debug, Clone)]
+pub struct XfsSuperblock {
+    /// ブロックサイズ
+    pub block_size: u32,
+    /// データ領域のブロック数
+    pub data_blocks: u64,
+    /// メタデータに記録されるUUID
+    pub meta_uuid: [u8; 16],
+    /// ルートディレクトリのiノード番号
+    pub root_ino: u64,
+    /// AGあたりのブロック数
+    pub ag_blocks: u32,
+    /// AGの数
+    pub ag_count: u32,
+    /// セクタサイズ
+    pub sector_size: u16,
+    /// iノードサイズ
+    pub inode_size: u16,
+    /// ブロックあたりのiノード数
+    pub inodes_per_block: u16,
+    /// ボリュームラベル
+    pub label: String,
+    /// log2(ブロックサイズ)
+    pub block_log: u8,
+    /// log2(ブロックあたりのiノード数)
+    pub inopb_log: u8,
+    /// log2(AGあたりのブロック数)の切り上げ
+    pub ag_block_log: u8,
+    /// log2(ディレクトリブロックあたりのブロック数)
+    pub dir_block_log: u8,
+    /// incompatフラグ
+    pub incompat: u32,
+}
+
+impl XfsSuperblock {
+    /// スーパーブロックのセクタを解析して検証
+    pub fn parse(data: &[u8]) -> FsResult<Self> {
+        if data.len() < SUPERBLOCK_MIN_SIZE {
+            return Err(FsError::BadSuperblock);
+        }
+        if be_u32(data, 0) != MAGIC {
+            return Err(FsError::BadMagic);
+        }
+        
+        let version = be_u16(data, 100);
+        if version & VERSION_NUMBITS != VERSION_5 {
+            log::warn!("XFS: バージョン{}のファイルシステムには対応していません（v5のみ）", version & VERSION_NUMBITS);
+            return Err(FsError::UnsupportedVersion);
+        }
+        
+        let sector_size = be_u16(data, 102);
+        if !sector_size.is_power_of_two() || (sector_size as usize) < SUPERBLOCK_MIN_SIZE || sector_size as usize > data.len() {
+            return Err(FsError::BadSuperblock);
+        }
+        if !crc32c::verify(&data[..sector_size as usize], CRC_OFFSET) {
+            log::warn!("XFS: スーパーブロックのチェックサムが一致しません");
+            return Err(FsError::ChecksumError);
+        }
+        
+        let incompat = be_u32(data, 216);
+        if incompat & incompat::NEEDSREPAIR != 0 {
+            log::warn!("XFS: xfs_repairが必要なファイルシステムです");
+            return Err(FsError::FilesystemCorrupted);
+        }
+        if incompat & incompat::METADIR != 0 {
+            log::warn!("XFS: メタデータディレクトリツリーには対応していません");
+            return Err(FsError::UnsupportedFeature);
+        }
+        if incompat & !incompat::SUPPORTED != 0 {
+            log::warn!("XFS: 未対応のincompatフラグ {:#x}", incompat & !incompat::SUPPORTED);
+            return Err(FsError::UnsupportedFeature);
+        }
+        if version & VERSION_BORGBIT != 0 {
+            log::warn!("XFS: ASCII-CIのディレクトリには対応していません");
+            return Err(FsError::UnsupportedFeature);
+        }
+        
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(&data[32..48]);
+        let mut meta_uuid = uuid;
+        if incompat & incompat::META_UUID != 0 {
+            meta_uuid.copy_from_slice(&data[248..264]);
+        }
+        
+        let label = &data[FNAME_OFFSET..FNAME_OFFSET + FNAME_SIZE];
+        let label_len = label.iter().position(|&b| b == 0).unwrap_or(FNAME_SIZE);
+        
+        let superblock = Self {
+            block_size: be_u32(data, 4),
+            data_blocks: be_u64(data, 8),
+            meta_uuid,
+            root_ino: be_u64(data, 56),
+            ag_blocks: be_u32(data, 84),
+            ag_count: be_u32(data, 88),
+            sector_size,
+            inode_size: be_u16(data, 104),
+            inodes_per_block: be_u16(data, 106),
+            label: String::from_utf8_lossy(&label[..label_len]).into_owned(),
+            block_log: data[120],
+            inopb_log: data[123],
+            ag_block_log: data[124],
+            dir_block_log: data[192],
+            incompat,
+        };
+        superblock.validate(data[121], data[122])?;
+        Ok(superblock)
+    }
+    
+    /// ジオメトリの整合性を確認
+    fn validate(&self, sector_log: u8, inode_log: u8) -> FsResult<()> {
+        let valid = self.block_size.is_power_of_two()
+            && (512..=65536).contains(&self.block_size)
+            && 1u32.checked_shl(self.block_log as u32) == Some(self.block_size)
+            && 1u16.checked_shl(sector_log as u32) == Some(self.sector_size)
+            && self.sector_size as u32 <= self.block_size
+            // v5ではiノードは512バイト以上
+            && self.inode_size.is_power_of_two()
+            && (512..=2048).contains(&self.inode_size)
+            && 1u16.checked_shl(inode_log as u32) == Some(self.inode_size)
+            && self.inodes_per_block as u32 == self.block_size / self.inode_size as u32
+            && 1u16.checked_shl(self.inopb_log as u32) == Some(self.inodes_per_block)
+            && self.ag_blocks > 1
+            && self.ag_block_log < 32
+            && self.ag_blocks <= 1 << self.ag_block_log
+            && self.ag_blocks > 1 << (self.ag_block_log - 1)
+            && self.ag_count > 0
+            && self.data_blocks <= self.ag_count as u64 * self.ag_blocks as u64
+            && self.block_log as u32 + self.dir_block_log as u32 <= 16
+            && self.inode_location(self.root_ino).is_some();
+        if !valid {
+            log::warn!("XFS: スーパーブロックのジオメトリが不正です");
+            return Err(FsError::BadSuperblock);
+        }
+        Ok(())
+    }
+    
+    /// incompatフラグが立っているか
+    pub fn has(&self, flag: u32) -> bool {
+        self.incompat & flag != 0
+    }
+    
+    /// ディレクトリブロックのサイズ
+    pub fn dir_block_size(&self) -> usize {
+        (self.block_size as usize) << self.dir_block_log
+    }
+    
+    /// AG内のブロックのバイトオフセット
+    pub fn ag_block_offset(&self, agno: u32, agbno: u32) -> u64 {
+        (agno as u64 * self.ag_blocks as u64 + agbno as u64) << self.block_log
+    }
+    
+    /// ファイルシステムブロック番号（上位がAG番号、下位がAG内のブロック番号）のバイトオフセット
+    pub fn fsb_offset(&self, fsbno: u64) -> FsResult<u64> {
+        let agno = fsbno >> self.ag_block_log;
+        let agbno = fsbno & ((1 << self.ag_block_log) - 1);
+        if agno >= self.ag_count as u64 || agbno >= self.ag_blocks as u64 {
+            log::warn!("XFS: ブロック番号{:#x}が範囲外です", fsbno);
+            return Err(FsError::CorruptedFs);
+        }
+        Ok(self.ag_block_offset(agno as u32, agbno as u32))
+    }
+    
+    /// iノード番号を（AG番号, AG内のiノード番号, バイトオフセット）へ変換
+    pub fn inode_location(&self, ino: u64) -> Option<(u32, u32, u64)> {
+        let agino_bits = self.ag_block_log as u32 + self.inopb_log as u32;
+        let agno = ino.checked_shr(agino_bits).unwrap_or(0);
+        let agino = (ino & ((1u64 << agino_bits) - 1)) as u32;
+        let agbno = agino >> self.inopb_log;
+        if ino == NULL_INO || agno >= self.ag_count as u64 || agbno >= self.ag_blocks {
+            return None;
+        }
+        let index = (agino & (self.inodes_per_block as u32 - 1)) as u64;
+        Some((agno as u32, agino, self.ag_block_offset(agno as u32, agbno) + index * self.inode_size as u64))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    
+    /// テスト用のUUID
+    pub const UUID: [u8; 16] = [0x5A; 16];
+    /// ブロックサイズ
+    pub const BLOCK_SIZE: usize = 4096;
+    /// AGあたりのブロック数
+    pub const AG_BLOCKS: u32 = 64;
+    /// AGの数
+    pub const AG_COUNT: u32 = 2;
+    /// ルートディレクトリのiノード番号
+    pub const ROOT_INO: u64 = 64;
+    
+    /// テスト用のスーパーブロック（4KiBブロック、512バイトセクタとiノード、64ブロックのAGが2つ）を作成
+    pub fn build(incompat: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 512];
+        data[0..4].copy_from_slice(&MAGIC.to_be_bytes());
+        data[4..8].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+        data[8..16].copy_from_slice(&((AG_BLOCKS * AG_COUNT) as u64).to_be_bytes());
+        data[32..48].copy_from_slice(&UUID);
+        data[56..64].copy_from_slice(&ROOT_INO.to_be_bytes());
+        data[84..88].copy_from_slice(&AG_BLOCKS.to_be_bytes());
+        data[88..92].copy_from_slice(&AG_COUNT.to_be_bytes());
+        data[100..102].copy_from_slice(&(VERSION_5 | 0xB0).to_be_bytes());
+        data[102..104].copy_from_slice(&512u16.to_be_bytes());
+        data[104..106].copy_from_slice(&512u16.to_be_bytes());
+        data[106..108].copy_from_slice(&8u16.to_be_bytes());
+        data[FNAME_OFFSET..FNAME_OFFSET + 4].copy_from_slice(b"data");
+        data[120..125].copy_from_slice(&[12, 9, 9, 3, 6]);
+        data[216..220].copy_from_slice(&incompat.to_be_bytes());
+        seal(&mut data);
+        data
+    }
+    
+    /// チェックサムを付け直す
+    pub fn seal(data: &mut [u8]) {
+        let crc = crc32c::checksum(data, CRC_OFFSET);
+        data[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+    }
+    
+    #[test]
+    fn parses_geometry_and_rejects_unsupported() {
+        let sb = XfsSuperblock::parse(&build(incompat::FTYPE)).unwrap();
+        assert_eq!((sb.block_size, sb.ag_blocks, sb.label.as_str()), (4096, 64, "data"));
+        assert_eq!(sb.meta_uuid, UUID);
+        // AG 1の3ブロック目
+        assert_eq!(sb.fsb_offset((1 << 6) | 3).unwrap(), (64 + 3) * 4096);
+        assert!(matches!(sb.fsb_offset(2 << 6), Err(FsError::CorruptedFs)));
+        // AG 1、ブロック2の5番目のiノード
+        let ino = (1 << 9) | (2 << 3) | 5;
+        assert_eq!(sb.inode_location(ino), Some((1, (2 << 3) | 5, (64 + 2) * 4096 + 5 * 512)));
+        assert_eq!(sb.inode_location(2 << 9), None);
+        
+        let mut corrupted = build(incompat::FTYPE);
+        corrupted[0x100] ^= 1;
+        assert!(matches!(XfsSuperblock::parse(&corrupted), Err(FsError::ChecksumError)));
+        
+        let mut v4 = build(incompat::FTYPE);
+        v4[100..102].copy_from_slice(&0xB4A4u16.to_be_bytes());
+        seal(&mut v4);
+        assert!(matches!(XfsSuperblock::parse(&v4), Err(FsError::UnsupportedVersion)));
+        
+        assert!(matches!(XfsSuperblock::parse(&build(incompat::METADIR)), Err(FsError::UnsupportedFeature)));
+        assert!(matches!(XfsSuperblock::parse(&build(incompat::NEEDSREPAIR)), Err(FsError::FilesystemCorrupted)));
+    }
+}
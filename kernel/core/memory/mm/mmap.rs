@@ -22,6 +22,7 @@ use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use crate::core::sync::RwLock;
 use crate::core::memory::mm::slab::SlabCache;
 use crate::core::fs::vfs;
+use crate::core::fs::PageMapper;
 use crate::core::fs::ext4::Ext4FileSystem;
 use crate::core::fs::fat32::Fat32FileSystem;
 use crate::core::fs::ntfs::NtfsFileSystem;
@@ -1347,6 +1348,22 @@ impl VirtualMemoryArea {
     }
 }
 
+/// ページキャッシュがライトバックの前に書き込み可能なファイルページを外すための実装
+impl PageMapper for Mutex<PageTable> {
+    fn unmap_page(&self, vaddr: usize, page: *const u8) {
+        let mut page_table = self.lock();
+        let vaddr = VirtAddr::new(vaddr);
+        
+        // 別のページに張り替わっていれば触らない
+        let expected = page_table.translate(VirtAddr::new(page as usize));
+        if expected.is_some() && page_table.translate(vaddr) == expected {
+            if let Err(e) = page_table.unmap(vaddr) {
+                log::warn!("Failed to write-protect page at {:?}: {:?}", vaddr, e);
+            }
+        }
+    }
+}
+
 /// アドレス空間マネージャ
 #[derive(Debug)]
 pub struct AddressSpace {
@@ -1601,6 +1618,9 @@ impl AddressSpace {
             return Err(MmapError::RegionNotFound);
         }
         
+        // ページキャッシュのページにはこのページテーブルからのマッピングを記録している
+        let mapper: Arc<dyn PageMapper> = self.page_table.clone();
+        
         // 各VMAのページをアンマップ
         for vma in &vmas {
             // 対象範囲の開始と終了を計算
@@ -1619,6 +1639,13 @@ impl AddressSpace {
                     // エラーをログに記録するが処理は継続
                     log::warn!("Failed to unmap page at {:?}: {:?}", current_addr, e);
                 }
+                drop(page_table);
+                
+                // 最後のマッピングが外れたページはページキャッシュから追い出せるようになる
+                if let MapType::File { file_id, offset, .. } = &vma.map_type {
+                    let page_offset_in_file = (current_addr.as_usize() - vma.start.as_usize()) + offset;
+                    crate::core::fs::release_mapped_page(*file_id, page_offset_in_file as u64, &mapper, current_addr.as_usize());
+                }
                 
                 current_addr = VirtAddr::new(current_addr.as_usize() + PAGE_SIZE);
             }
@@ -1693,11 +1720,38 @@ impl AddressSpace {
                 )?;
             },
             MapType::File { file_id, offset, file_perms } => {
+                let page_offset_in_file = (page_addr.as_usize() - vma.start.as_usize()) + offset;
+                
+                // ページキャッシュに載っているファイルはread()/write()と同じページをそのままマップする
+                if let Some(cached) = crate::core::fs::mapped_page(*file_id, page_offset_in_file as u64) {
+                    // 読み込みでは読み取り専用でマップし、書き込みは書き込み保護フォルトで捕まえてダーティにする
+                    // （ライトバックは書き込み可能なマッピングを外すので、書き出し後の書き込みも再びフォルトする）
+                    let writable = vma.permissions.write && write_access;
+                    let permissions = MapPermissions { write: writable, ..vma.permissions };
+                    let mapper: Arc<dyn PageMapper> = self.page_table.clone();
+                    
+                    let mut page_table = self.page_table.lock();
+                    let phys_page_addr = page_table.translate(VirtAddr::new(cached.as_ptr() as usize))
+                        .ok_or(MmapError::FileError)?;
+                    cached.map_into(&mapper, page_addr.as_usize(), writable);
+                    if writable {
+                        // 読み取り専用でマップ済みなら張り替える
+                        let _ = page_table.unmap(page_addr);
+                    }
+                    page_table.map(
+                        page_addr,
+                        phys_page_addr,
+                        PageSize::Size4KiB,
+                        convert_to_arch_permissions(permissions),
+                    )?;
+                    vma.increment_access();
+                    return Ok(());
+                }
+                
                 // ファイルからページをロード
                 let page = Page::alloc(AllocFlags::NONE)?;
                 
                 // ファイルシステムからデータをロード（実際の実装はファイルシステムモジュールに依存）
-                let page_offset_in_file = (page_addr.as_usize() - vma.start.as_usize()) + offset;
                 let mut page_data = vec![0u8; PAGE_SIZE]; // ページサイズのバッファ
                 
                 // ファイルシステムマネージャーからファイルを読み込み
@@ -370,7 +370,13 @@ pub fn allocate_pages(count: usize, flags: AllocFlags) -> Result<usize, &'static
     }
     
     // デフォルトノードを0とする
-    buddy::allocate_pages(count, flags, 0)
+    buddy::allocate_pages(count, flags, 0).or_else(|error| {
+        // 空きがなければファイルシステムのキャッシュを回収して一度だけ再試行
+        if crate::core::fs::reclaim_memory(count) == 0 {
+            return Err(error);
+        }
+        buddy::allocate_pages(count, flags, 0)
+    })
 }
 
 /// ページ単位のメモリ解放
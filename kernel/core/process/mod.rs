@@ -248,6 +248,11 @@ impl ProcessManager {
     
     /// プロセスを登録
     fn register_process(&self, process: Arc<Process>) {
+        // forkした子は親の作業ディレクトリと開いているファイルを引き継ぐ
+        if let Some(parent) = process.get_parent_id() {
+            crate::core::fs::fork_process(parent, process.get_id());
+        }
+        
         let mut processes = self.processes.iter();
         processes.push(process.clone());
         
@@ -268,6 +273,9 @@ impl ProcessManager {
         let mut processes = self.processes.iter();
         
         if let Some(process) = processes.iter().find(|p| p.get_pid() == pid.0) {
+            // 開いていたファイルを閉じ、作業ディレクトリを破棄
+            crate::core::fs::exit_process(pid);
+            
             // 統計更新
             self.stats.active_processes.fetch_sub(1, Ordering::Relaxed);
         }
@@ -965,6 +973,16 @@ pub fn unblock_thread(thread: &Arc<Thread>) {
     thread::unblock_thread(thread);
 }
 
+/// 登録されているすべてのプロセスを取得
+pub fn list_processes() -> Vec<Arc<Process>> {
+    global_manager().processes.iter().cloned().collect()
+}
+
+/// プロセスIDからプロセスを取得
+pub fn get_process(pid: ProcessId) -> Option<Arc<Process>> {
+    global_manager().get_process(pid)
+}
+
 /// プロセスごとのリソース使用状況を取得
 pub fn get_process_usage(pid: ProcessId) -> Option<ResourceUsage> {
     if let Some(process) = global_manager().get_process(pid) {
@@ -13,6 +13,7 @@ use crate::core::security::SecurityError;
 use spin::RwLock as SpinRwLock;
 use core::sync::atomic::AtomicU64;
 use crate::core::security::access_control::{ConditionExpressionParser, SemanticAnalyzer, ExpressionOptimizer, ExpressionEvaluator};
+use crate::core::fs::{self as vfs, access, AclTag, PosixAcl};
 
 /// アクセス制御モデル
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -44,6 +45,36 @@ pub struct SecurityLabel {
     pub categories: BTreeSet<String>,
 }
 
+/// ファイルのセキュリティラベルを保存する拡張属性
+pub const SECURITY_LABEL_XATTR: &str = "security.aether";
+
+impl SecurityLabel {
+    /// 拡張属性の値に変換（"c=機密性;i=完全性;comp=区画,...;cat=カテゴリ,..."）
+    pub fn to_xattr(&self) -> Vec<u8> {
+        let join = |set: &BTreeSet<String>| set.iter().map(String::as_str).collect::<Vec<_>>().join(",");
+        alloc::format!(
+            "c={};i={};comp={};cat={}",
+            self.confidentiality, self.integrity, join(&self.compartments), join(&self.categories)
+        ).into_bytes()
+    }
+    
+    /// 拡張属性の値から復元（形式が不正なら`None`）
+    pub fn from_xattr(value: &[u8]) -> Option<Self> {
+        let attributes = parse_context_attributes(core::str::from_utf8(value).ok()?);
+        let split = |key: &str| -> BTreeSet<String> {
+            attributes.get(key)
+                .map(|list| list.split(',').filter(|item| !item.is_empty()).map(String::from).collect())
+                .unwrap_or_default()
+        };
+        Some(Self {
+            confidentiality: attributes.get("c")?.parse().ok()?,
+            integrity: attributes.get("i")?.parse().ok()?,
+            compartments: split("comp"),
+            categories: split("cat"),
+        })
+    }
+}
+
 /// アクセス権限
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Permission {
@@ -144,6 +175,39 @@ pub struct AccessControlList {
     pub inheritable: bool,
 }
 
+impl AccessControlList {
+    /// ファイルのPOSIX ACLから作成
+    ///
+    /// 名前付きユーザー・グループと所有グループの権限にはマスクを適用する。「その他」に
+    /// 当たるプリンシパルはないため含めない（VFSの`check_access`が評価する）。
+    pub fn from_posix_acl(resource_id: usize, acl: &PosixAcl, owner: u32, group: u32) -> Self {
+        let mask = acl.mask().unwrap_or(access::READ | access::WRITE | access::EXECUTE);
+        let entries = acl.entries().iter().filter_map(|entry| {
+            let (principal_id, principal_type, perm) = match entry.tag {
+                AclTag::UserObj => (owner, PrincipalType::User, entry.perm),
+                AclTag::User(uid) => (uid, PrincipalType::User, entry.perm & mask),
+                AclTag::GroupObj => (group, PrincipalType::Group, entry.perm & mask),
+                AclTag::Group(gid) => (gid, PrincipalType::Group, entry.perm & mask),
+                AclTag::Mask | AclTag::Other => return None,
+            };
+            let allowed_permissions = [(access::READ, Permission::Read), (access::WRITE, Permission::Write), (access::EXECUTE, Permission::Execute)]
+                .iter()
+                .filter(|(bit, _)| perm & bit != 0)
+                .map(|(_, permission)| *permission)
+                .collect();
+            Some(AccessControlEntry {
+                principal_id: principal_id as usize,
+                principal_type,
+                allowed_permissions,
+                denied_permissions: BTreeSet::new(),
+                condition: None,
+            })
+        }).collect();
+        
+        Self { resource_id, entries, inheritable: false }
+    }
+}
+
 /// アクセス制御エンジン
 pub struct AccessControlEngine {
     /// アクセス制御モデル
@@ -475,6 +539,42 @@ impl AccessControlEngine {
         Ok(())
     }
     
+    /// リソースのセキュリティラベルをファイルの拡張属性に保存
+    pub fn store_file_label(&self, resource_id: usize, path: &str) -> Result<(), &'static str> {
+        let label = self.resource_labels.read().unwrap().get(&resource_id).cloned()
+            .ok_or("リソースにセキュリティラベルがありません")?;
+        vfs::set_xattr(path, SECURITY_LABEL_XATTR, &label.to_xattr())
+            .map_err(|_| "セキュリティラベルを保存できません")
+    }
+    
+    /// ファイルの拡張属性からセキュリティラベルを読み込み、リソースに設定
+    ///
+    /// ラベルが保存されていなければ`false`を返す。
+    pub fn load_file_label(&self, resource_id: usize, path: &str) -> Result<bool, &'static str> {
+        let value = match vfs::get_xattr(path, SECURITY_LABEL_XATTR) {
+            Ok(value) => value,
+            Err(vfs::FsError::NotFound) => return Ok(false),
+            Err(_) => return Err("セキュリティラベルを読み込めません"),
+        };
+        let label = SecurityLabel::from_xattr(&value).ok_or("セキュリティラベルの形式が不正です")?;
+        self.resource_labels.write().unwrap().insert(resource_id, label);
+        Ok(true)
+    }
+    
+    /// ファイルのPOSIX ACLを読み込み、リソースのACLとして設定
+    ///
+    /// ACLが設定されていなければ`false`を返す。
+    pub fn load_file_acl(&self, resource_id: usize, path: &str) -> Result<bool, &'static str> {
+        let acl = match vfs::get_acl(path).map_err(|_| "ACLを読み込めません")? {
+            Some(acl) => acl,
+            None => return Ok(false),
+        };
+        let metadata = vfs::metadata(path).map_err(|_| "ファイルのメタデータを取得できません")?;
+        let list = AccessControlList::from_posix_acl(resource_id, &acl, metadata.uid, metadata.gid);
+        self.access_control_lists.write().unwrap().insert(resource_id, list);
+        Ok(true)
+    }
+    
     /// アクセス制御リスト（ACL）を設定
     pub fn set_acl(
         &self,
@@ -8,12 +8,15 @@
 //! PCIeインターフェースを介して接続されたSSDやNVMeデバイスにアクセスするための機能を実装しています。
 
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt;
 use core::mem::size_of;
 use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use spin::{Mutex, RwLock};
 
+use crate::core::fs::{FsError, FsResult, BlockDevice, CharDevice};
+use crate::core::fs::devfs::{self, DeviceClass, DeviceOps};
 use crate::drivers::pci::{PciDevice, PciClass};
 use crate::mm::{MemoryManager, PhysAddr, VirtAddr};
 use crate::sync::OnceCell;
@@ -81,6 +84,23 @@ const NVME_CMD_COMPARE: u8 = 0x05;         // Compare
 const NVME_CMD_WRITE_ZEROES: u8 = 0x08;    // Write Zeroes
 const NVME_CMD_DATASET_MANAGEMENT: u8 = 0x09; // Dataset Management
 
+/// Identifyコマンドで返す構造体の種類 (CNS)
+const NVME_CNS_NAMESPACE: u32 = 0x00;       // Identify Namespace
+const NVME_CNS_ACTIVE_NS_LIST: u32 = 0x02;  // Active Namespace ID list
+
+/// I/Oキュー（1組だけ作る）のIDとエントリ数
+const NVME_IO_QUEUE_ID: u16 = 1;
+const NVME_IO_QUEUE_SIZE: u32 = 64;
+
+/// メモリページサイズ（CC.MPS=0）
+const NVME_PAGE_SIZE: usize = 4096;
+/// 1コマンドで転送する最大バイト数（PRP1とPRP2で指せる2ページ）
+const NVME_MAX_TRANSFER: usize = 2 * NVME_PAGE_SIZE;
+/// コマンド完了を待つ最大時間（ミリ秒）
+const NVME_COMMAND_TIMEOUT_MS: u32 = 5000;
+/// 名前空間のブロックデバイスIDの上位ビット（"NVME"）
+const NVME_DEVICE_ID_BASE: u64 = 0x4E56_4D45 << 32;
+
 /// NVMeアイデンティファイコントローラーデータ構造 (簡略化)
 #[derive(Debug, Clone)]
 #[repr(C, packed)]
@@ -308,8 +328,16 @@ struct NvmeRegisters {
     admin_sq: Mutex<Option<NvmeQueue>>,
     /// Admin Completion Queue
     admin_cq: Mutex<Option<NvmeQueue>>,
+    /// I/O Submission Queue
+    io_sq: Mutex<Option<NvmeQueue>>,
+    /// I/O Completion Queue
+    io_cq: Mutex<Option<NvmeQueue>>,
 }
 
+// レジスタへのアクセスはMMIOで、キューはMutexで保護しているので複数CPUから使える
+unsafe impl Send for NvmeRegisters {}
+unsafe impl Sync for NvmeRegisters {}
+
 impl NvmeRegisters {
     /// 新しいNVMeレジスタセットを作成
     pub unsafe fn new(base_addr: *mut u8) -> Self {
@@ -317,6 +345,8 @@ impl NvmeRegisters {
             base: base_addr,
             admin_sq: Mutex::new(None),
             admin_cq: Mutex::new(None),
+            io_sq: Mutex::new(None),
+            io_cq: Mutex::new(None),
         }
     }
     
@@ -476,6 +506,9 @@ impl NvmeController {
         // コントローラーを有効化
         self.enable_controller(&registers)?;
         
+        // 以降のコマンドは保存したレジスタ経由で投入する
+        *self.registers.lock() = Some(registers);
+        
         // Identify Controllerコマンドを実装
         let identify_data = self.identify_controller()?;
 
@@ -491,8 +524,8 @@ impl NvmeController {
         // 10ミリ秒待機
         time::sleep_ms(10);
 
-        // レジスタを保存
-        *self.registers.lock() = Some(registers);
+        // 名前空間の読み書きに使うI/Oキューを作成
+        self.create_io_queues()?;
         
         self.initialized.store(true, Ordering::SeqCst);
         Ok(())
@@ -793,6 +826,271 @@ impl NvmeController {
     }
 }
 
+/// 名前空間の情報（Identify Namespaceから取得）
+#[derive(Debug, Clone, Copy)]
+pub struct NvmeNamespaceInfo {
+    /// 名前空間ID
+    pub nsid: u32,
+    /// 論理ブロックサイズ（バイト）
+    pub block_size: u64,
+    /// 論理ブロック数 (NSZE)
+    pub block_count: u64,
+}
+
+/// I/Oキューと名前空間の読み書き
+impl NvmeController {
+    /// コマンドをキューに投入し、完了をポーリングで待つ
+    ///
+    /// キューのロックを持ったまま待つので、1つのキューで同時に実行中のコマンドは1つだけ。
+    /// 成功すれば完了エントリのDWORD 0を返す。
+    fn submit_and_wait(
+        registers: &NvmeRegisters,
+        queue_id: u16,
+        sq: &NvmeQueue,
+        cq: &NvmeQueue,
+        command: NvmeCommand,
+    ) -> Result<u32, &'static str> {
+        if sq.is_full() {
+            return Err("NVMe Submission Queueがいっぱいです");
+        }
+        let cid = (command.cdw0 >> 16) as u16;
+        
+        // コマンドを書き込んでテールドアベルを鳴らす
+        let sq_tail = sq.tail.load(Ordering::Relaxed);
+        unsafe {
+            let cmd_ptr = (sq.virt_addr + sq_tail as usize * size_of::<NvmeCommand>()) as *mut NvmeCommand;
+            core::ptr::write_volatile(cmd_ptr, command);
+        }
+        let new_tail = (sq_tail + 1) % sq.size;
+        sq.tail.store(new_tail, Ordering::Release);
+        core::sync::atomic::fence(Ordering::SeqCst);
+        registers.ring_sq_doorbell(queue_id, new_tail);
+        
+        for _ in 0..NVME_COMMAND_TIMEOUT_MS {
+            let cq_head = cq.head.load(Ordering::Relaxed);
+            let phase = cq.phase.load(Ordering::Relaxed);
+            let completion: NvmeCompletion = unsafe {
+                let entry_ptr = (cq.virt_addr + cq_head as usize * size_of::<NvmeCompletion>()) as *const NvmeCompletion;
+                core::ptr::read_volatile(entry_ptr)
+            };
+            
+            // フェーズビットが一致しなければまだ書かれていない
+            if ((completion.status & 1) != 0) != phase {
+                time::sleep_ms(1);
+                continue;
+            }
+            
+            // エントリを消費（一周したらフェーズを反転）
+            let new_head = (cq_head + 1) % cq.size;
+            if new_head == 0 {
+                cq.phase.store(!phase, Ordering::Relaxed);
+            }
+            cq.head.store(new_head, Ordering::Release);
+            sq.head.store(completion.sqhd as u32, Ordering::Release);
+            registers.ring_cq_doorbell(queue_id, new_head);
+            
+            let status = completion.status >> 1;
+            let completed_cid = completion.cid;
+            if completed_cid != cid || status != 0 {
+                log::error!("NVMeコマンドが失敗: CID={}, ステータス=0x{:04x}", completed_cid, status);
+                return Err("NVMeコマンドが失敗しました");
+            }
+            return Ok(completion.cdw0);
+        }
+        
+        log::error!("NVMeコマンドがタイムアウトしました (CID={}, {}ms)", cid, NVME_COMMAND_TIMEOUT_MS);
+        Err("NVMeコマンドがタイムアウトしました")
+    }
+    
+    /// 管理コマンドを実行
+    fn admin_command(&self, command: NvmeCommand) -> Result<u32, &'static str> {
+        let registers = self.registers.lock();
+        let registers = registers.as_ref().ok_or("NVMeレジスタが初期化されていません")?;
+        let admin_sq = registers.admin_sq.lock();
+        let admin_cq = registers.admin_cq.lock();
+        match (admin_sq.as_ref(), admin_cq.as_ref()) {
+            (Some(sq), Some(cq)) => Self::submit_and_wait(registers, 0, sq, cq, command),
+            _ => Err("Admin Queueが初期化されていません"),
+        }
+    }
+    
+    /// I/Oコマンドを実行
+    fn io_command(&self, command: NvmeCommand) -> Result<u32, &'static str> {
+        let registers = self.registers.lock();
+        let registers = registers.as_ref().ok_or("NVMeレジスタが初期化されていません")?;
+        let io_sq = registers.io_sq.lock();
+        let io_cq = registers.io_cq.lock();
+        match (io_sq.as_ref(), io_cq.as_ref()) {
+            (Some(sq), Some(cq)) => Self::submit_and_wait(registers, NVME_IO_QUEUE_ID, sq, cq, command),
+            _ => Err("I/Oキューが作成されていません"),
+        }
+    }
+    
+    /// I/O Completion QueueとI/O Submission Queueを1組作成
+    fn create_io_queues(&self) -> Result<(), &'static str> {
+        let doorbell = |offset: usize| offset + NVME_IO_QUEUE_ID as usize * 8;
+        let io_cq = NvmeQueue::new(NVME_IO_QUEUE_ID, NVME_IO_QUEUE_SIZE, doorbell(NVME_REG_CQ0HDBL))?;
+        let io_sq = NvmeQueue::new(NVME_IO_QUEUE_ID, NVME_IO_QUEUE_SIZE, doorbell(NVME_REG_SQ0TDBL))?;
+        let queue_attributes = ((NVME_IO_QUEUE_SIZE - 1) << 16) | NVME_IO_QUEUE_ID as u32;
+        
+        // 割り込みは使わずポーリングする（PC=1, IEN=0）
+        let mut command = NvmeCommand::new();
+        command.set_opcode_and_cid(NVME_ADMIN_CMD_CREATE_CQ, self.get_next_cmd_id());
+        command.set_prp_entries(io_cq.phys_addr.as_u64(), 0);
+        command.cdw10 = queue_attributes;
+        command.cdw11 = 1;
+        self.admin_command(command)?;
+        
+        // SQは作成したCQに完了を返す（PC=1）
+        let mut command = NvmeCommand::new();
+        command.set_opcode_and_cid(NVME_ADMIN_CMD_CREATE_SQ, self.get_next_cmd_id());
+        command.set_prp_entries(io_sq.phys_addr.as_u64(), 0);
+        command.cdw10 = queue_attributes;
+        command.cdw11 = ((NVME_IO_QUEUE_ID as u32) << 16) | 1;
+        self.admin_command(command)?;
+        
+        let registers = self.registers.lock();
+        let registers = registers.as_ref().ok_or("NVMeレジスタが初期化されていません")?;
+        *registers.io_cq.lock() = Some(io_cq);
+        *registers.io_sq.lock() = Some(io_sq);
+        Ok(())
+    }
+    
+    /// Identifyコマンドを実行し、4KiBの結果を返す
+    fn identify(&self, cns: u32, nsid: u32) -> Result<Vec<u8>, &'static str> {
+        let (virt_addr, phys_addr) = MemoryManager::allocate_dma_buffer(NVME_PAGE_SIZE)
+            .map_err(|_| "Identify DMAバッファの割り当てに失敗しました")?;
+        unsafe {
+            core::ptr::write_bytes(virt_addr as *mut u8, 0, NVME_PAGE_SIZE);
+        }
+        
+        let mut command = NvmeCommand::new();
+        command.set_opcode_and_cid(NVME_ADMIN_CMD_IDENTIFY, self.get_next_cmd_id());
+        command.set_nsid(nsid);
+        command.set_prp_entries(phys_addr.as_u64(), 0);
+        command.cdw10 = cns;
+        let result = self.admin_command(command).map(|_| {
+            let mut data = vec![0u8; NVME_PAGE_SIZE];
+            unsafe {
+                core::ptr::copy_nonoverlapping(virt_addr as *const u8, data.as_mut_ptr(), NVME_PAGE_SIZE);
+            }
+            data
+        });
+        
+        if let Err(e) = MemoryManager::free_dma_buffer(virt_addr, phys_addr, NVME_PAGE_SIZE) {
+            log::warn!("DMAバッファの解放に失敗: {:?}", e);
+        }
+        result
+    }
+    
+    /// 有効な名前空間を列挙
+    pub fn namespaces(&self) -> Result<Vec<NvmeNamespaceInfo>, &'static str> {
+        let list = self.identify(NVME_CNS_ACTIVE_NS_LIST, 0)?;
+        let mut namespaces = Vec::new();
+        
+        // 名前空間IDの昇順リスト。0で終わる
+        for chunk in list.chunks_exact(4) {
+            let nsid = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            if nsid == 0 {
+                break;
+            }
+            
+            let data = self.identify(NVME_CNS_NAMESPACE, nsid)?;
+            let block_count = u64::from_le_bytes([
+                data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7],
+            ]);
+            // FLBASが指すLBAフォーマットのLBADS（ブロックサイズの2の対数）。
+            // 1回の転送で必ず1ブロック以上送れるよう、ページサイズ以下のブロックだけ扱う
+            let format = (data[26] & 0x0F) as usize;
+            let lba_data_shift = data[128 + format * 4 + 2];
+            if block_count == 0 || !(9..=12).contains(&lba_data_shift) {
+                log::warn!("NVMe名前空間{}を使用できません (NSZE={}, LBADS={})", nsid, block_count, lba_data_shift);
+                continue;
+            }
+            
+            namespaces.push(NvmeNamespaceInfo {
+                nsid,
+                block_size: 1 << lba_data_shift,
+                block_count,
+            });
+        }
+        
+        Ok(namespaces)
+    }
+    
+    /// 論理ブロックを読み書きする（`len`はNVME_MAX_TRANSFER以下）
+    fn transfer(&self, opcode: u8, namespace: &NvmeNamespaceInfo, lba: u64, len: usize, phys_addr: u64) -> Result<(), &'static str> {
+        // 2ページ目はバッファの続き（DMAバッファは物理的に連続している）
+        let prp2 = if len > NVME_PAGE_SIZE { phys_addr + NVME_PAGE_SIZE as u64 } else { 0 };
+        let blocks = (len as u64 / namespace.block_size) as u32;
+        
+        let mut command = NvmeCommand::new();
+        command.set_opcode_and_cid(opcode, self.get_next_cmd_id());
+        command.set_nsid(namespace.nsid);
+        command.set_prp_entries(phys_addr, prp2);
+        command.cdw10 = lba as u32;
+        command.cdw11 = (lba >> 32) as u32;
+        command.cdw12 = blocks - 1; // NLBは0始まり
+        self.io_command(command).map(|_| ())
+    }
+    
+    /// 名前空間の論理ブロックを読み込む
+    pub fn read_blocks(&self, namespace: &NvmeNamespaceInfo, lba: u64, count: u64) -> Result<Vec<u8>, &'static str> {
+        let total = (count * namespace.block_size) as usize;
+        let (virt_addr, phys_addr) = MemoryManager::allocate_dma_buffer(NVME_MAX_TRANSFER)
+            .map_err(|_| "NVMe I/O用のDMAバッファの割り当てに失敗しました")?;
+        
+        let mut data = vec![0u8; total];
+        let mut result = Ok(());
+        for (index, chunk) in data.chunks_mut(NVME_MAX_TRANSFER).enumerate() {
+            let start = lba + (index * NVME_MAX_TRANSFER) as u64 / namespace.block_size;
+            result = self.transfer(NVME_CMD_READ, namespace, start, chunk.len(), phys_addr.as_u64());
+            if result.is_err() {
+                break;
+            }
+            unsafe {
+                core::ptr::copy_nonoverlapping(virt_addr as *const u8, chunk.as_mut_ptr(), chunk.len());
+            }
+        }
+        
+        if let Err(e) = MemoryManager::free_dma_buffer(virt_addr, phys_addr, NVME_MAX_TRANSFER) {
+            log::warn!("DMAバッファの解放に失敗: {:?}", e);
+        }
+        result.map(|_| data)
+    }
+    
+    /// 名前空間の論理ブロックに書き込む（`data`はブロックサイズの倍数）
+    pub fn write_blocks(&self, namespace: &NvmeNamespaceInfo, lba: u64, data: &[u8]) -> Result<(), &'static str> {
+        let (virt_addr, phys_addr) = MemoryManager::allocate_dma_buffer(NVME_MAX_TRANSFER)
+            .map_err(|_| "NVMe I/O用のDMAバッファの割り当てに失敗しました")?;
+        
+        let mut result = Ok(());
+        for (index, chunk) in data.chunks(NVME_MAX_TRANSFER).enumerate() {
+            unsafe {
+                core::ptr::copy_nonoverlapping(chunk.as_ptr(), virt_addr as *mut u8, chunk.len());
+            }
+            let start = lba + (index * NVME_MAX_TRANSFER) as u64 / namespace.block_size;
+            result = self.transfer(NVME_CMD_WRITE, namespace, start, chunk.len(), phys_addr.as_u64());
+            if result.is_err() {
+                break;
+            }
+        }
+        
+        if let Err(e) = MemoryManager::free_dma_buffer(virt_addr, phys_addr, NVME_MAX_TRANSFER) {
+            log::warn!("DMAバッファの解放に失敗: {:?}", e);
+        }
+        result
+    }
+    
+    /// 名前空間の揮発性キャッシュを書き出す
+    pub fn flush(&self, namespace: &NvmeNamespaceInfo) -> Result<(), &'static str> {
+        let mut command = NvmeCommand::new();
+        command.set_opcode_and_cid(NVME_CMD_FLUSH, self.get_next_cmd_id());
+        command.set_nsid(namespace.nsid);
+        self.io_command(command).map(|_| ())
+    }
+}
+
 /// NvmeIdCtrl構造体にヘルパーメソッドを追加
 impl NvmeIdCtrl {
     /// モデル番号を文字列として取得
@@ -817,6 +1115,98 @@ impl NvmeIdCtrl {
     }
 }
 
+/// devfsに公開するNVMeコントローラー。データの読み書きは名前空間のブロックデバイスで行う
+struct NvmeControllerNode;
+
+impl CharDevice for NvmeControllerNode {
+    fn read(&self, _buffer: &mut [u8]) -> FsResult<usize> {
+        Err(FsError::NotSupported)
+    }
+    
+    fn write(&self, _buffer: &[u8]) -> FsResult<usize> {
+        Err(FsError::NotSupported)
+    }
+}
+
+/// devfsに公開するNVMe名前空間（/dev/nvme<n>n<nsid>）
+pub struct NvmeNamespace {
+    /// 名前空間を持つコントローラー
+    controller: Arc<NvmeController>,
+    /// 名前空間の情報
+    info: NvmeNamespaceInfo,
+    /// デバイスID
+    device_id: u64,
+}
+
+impl NvmeNamespace {
+    /// `index`番目のコントローラーの名前空間を作成
+    pub fn new(controller: Arc<NvmeController>, index: u32, info: NvmeNamespaceInfo) -> Self {
+        let device_id = NVME_DEVICE_ID_BASE | ((index as u64) << 24) | info.nsid as u64;
+        Self { controller, info, device_id }
+    }
+    
+    /// 範囲が名前空間に収まるか確認
+    fn check_range(&self, start_block: u64, count: u64) -> FsResult<()> {
+        match start_block.checked_add(count) {
+            Some(end) if end <= self.info.block_count => Ok(()),
+            _ => Err(FsError::InvalidData),
+        }
+    }
+}
+
+impl BlockDevice for NvmeNamespace {
+    fn device_id(&self) -> u64 {
+        self.device_id
+    }
+    
+    fn block_size(&self) -> u64 {
+        self.info.block_size
+    }
+    
+    fn total_blocks(&self) -> u64 {
+        self.info.block_count
+    }
+    
+    fn read_block(&self, block_index: u64) -> FsResult<Vec<u8>> {
+        self.read_blocks(block_index, 1)
+    }
+    
+    fn read_blocks(&self, start_block: u64, count: u64) -> FsResult<Vec<u8>> {
+        self.check_range(start_block, count)?;
+        self.controller.read_blocks(&self.info, start_block, count).map_err(|e| {
+            log::error!("NVMe名前空間{}の読み込みに失敗: {}", self.info.nsid, e);
+            FsError::IoError
+        })
+    }
+    
+    fn write_block(&self, block_index: u64, data: &[u8]) -> FsResult<()> {
+        self.write_blocks(block_index, data)
+    }
+    
+    fn write_blocks(&self, start_block: u64, data: &[u8]) -> FsResult<()> {
+        // ブロック単位でしか書けない（部分書き込みは呼び出し側で読み書きする）
+        if data.len() as u64 % self.info.block_size != 0 {
+            return Err(FsError::InvalidData);
+        }
+        self.check_range(start_block, data.len() as u64 / self.info.block_size)?;
+        self.controller.write_blocks(&self.info, start_block, data).map_err(|e| {
+            log::error!("NVMe名前空間{}への書き込みに失敗: {}", self.info.nsid, e);
+            FsError::IoError
+        })
+    }
+    
+    fn sync(&self) -> FsResult<()> {
+        self.controller.flush(&self.info).map_err(|e| {
+            log::error!("NVMe名前空間{}のフラッシュに失敗: {}", self.info.nsid, e);
+            FsError::IoError
+        })
+    }
+    
+    fn close(&self) -> FsResult<()> {
+        Ok(())
+    }
+}
+
 /// NVMeドライバー
 pub struct NvmeDriver {
     /// 検出されたコントローラー
@@ -850,9 +1240,34 @@ impl NvmeDriver {
         
         // 各コントローラーを初期化
         let controllers = self.controllers.read();
-        for controller in controllers.iter() {
+        for (index, controller) in controllers.iter().enumerate() {
             if let Err(e) = controller.initialize() {
                 log::error!("NVMeコントローラーの初期化に失敗: {}", e);
+                continue;
+            }
+            
+            // /dev/nvme<n>として公開
+            let class = DeviceClass::NvmeController { controller: index as u32 };
+            let node = DeviceOps::Char(Arc::new(NvmeControllerNode));
+            if let Err(e) = devfs::register(class, node) {
+                log::warn!("NVMeコントローラーをdevfsに登録できません: {:?}", e);
+            }
+            
+            // 名前空間を/dev/nvme<n>n<nsid>として公開（パーティションはdevfsが読み取る）
+            let namespaces = match controller.namespaces() {
+                Ok(namespaces) => namespaces,
+                Err(e) => {
+                    log::error!("NVMe名前空間の列挙に失敗: {}", e);
+                    continue;
+                },
+            };
+            for info in namespaces {
+                log::info!("NVMe名前空間{}: {}ブロック x {}バイト", info.nsid, info.block_count, info.block_size);
+                let class = DeviceClass::NvmeNamespace { controller: index as u32, namespace: info.nsid };
+                let device = Arc::new(NvmeNamespace::new(controller.clone(), index as u32, info));
+                if let Err(e) = devfs::register(class, DeviceOps::Block(device)) {
+                    log::warn!("NVMe名前空間{}をdevfsに登録できません: {:?}", info.nsid, e);
+                }
             }
         }
         
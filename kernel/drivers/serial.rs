@@ -3,11 +3,13 @@
 // 16550A UARTシリアルポートデバイスドライバ実装
 
 use core::fmt;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use spin::Mutex;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use super::{DriverInfo, DriverType, DriverInitState};
+use crate::core::fs::{FsError, FsResult, CharDevice};
+use crate::core::fs::devfs::{self, DeviceClass, DeviceOps};
 
 /// シリアルポートのベースアドレス (I/Oポート)
 const COM1_PORT: u16 = 0x3F8;
@@ -57,6 +59,17 @@ impl SerialPort {
             SerialPort::Custom(addr) => addr,
         }
     }
+    
+    /// devfsでのポート番号（ttyS<n>）。任意アドレスのポートは登録時に割り当てるのでNone
+    pub fn index(&self) -> Option<u32> {
+        match *self {
+            SerialPort::Com1 => Some(0),
+            SerialPort::Com2 => Some(1),
+            SerialPort::Com3 => Some(2),
+            SerialPort::Com4 => Some(3),
+            SerialPort::Custom(_) => None,
+        }
+    }
 }
 
 /// シリアルデバイスの設定
@@ -353,6 +366,60 @@ impl SerialDriver {
     }
 }
 
+/// ラインステータスレジスタを返すioctl要求（TIOCSERGETLSR）
+pub const TIOCSERGETLSR: u32 = 0x5459;
+
+/// 任意アドレスのポートに次に割り当てるポート番号（COM4の次から順に使う）
+static NEXT_CUSTOM_INDEX: AtomicU32 = AtomicU32::new(4);
+
+/// devfsに公開するシリアルポート（/dev/ttyS<n>）
+enum SerialNode {
+    /// COM1（グローバルインスタンスを共有する）
+    Com1(&'static SerialDriver),
+    /// その他のポート（ノードがドライバーを所有する）
+    Owned(SerialDriver),
+}
+
+impl SerialNode {
+    fn driver(&self) -> &SerialDriver {
+        match self {
+            SerialNode::Com1(driver) => driver,
+            SerialNode::Owned(driver) => driver,
+        }
+    }
+}
+
+impl CharDevice for SerialNode {
+    fn read(&self, buffer: &mut [u8]) -> FsResult<usize> {
+        // 受信済みのバイトだけを返し、待たない
+        let mut count = 0;
+        while count < buffer.len() {
+            match self.driver().try_read_byte() {
+                Some(byte) => {
+                    buffer[count] = byte;
+                    count += 1;
+                },
+                None => break,
+            }
+        }
+        Ok(count)
+    }
+    
+    fn write(&self, buffer: &[u8]) -> FsResult<usize> {
+        for &byte in buffer {
+            self.driver().write_byte(byte);
+        }
+        Ok(buffer.len())
+    }
+    
+    fn ioctl(&self, request: u32, _arg: usize) -> FsResult<usize> {
+        match request {
+            TIOCSERGETLSR => Ok(self.driver().get_line_status() as usize),
+            _ => Err(FsError::NotSupported),
+        }
+    }
+}
+
 impl fmt::Write for SerialDriver {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.write_string(s);
@@ -400,9 +467,17 @@ pub fn init(port_base: u16) -> Result<(), &'static str> {
     let driver = SerialDriver::new(port);
     driver.initialize(config)?;
     
-    // グローバルインスタンスとして設定（COM1の場合のみ）
-    if port_base == COM1_PORT {
-        SERIAL1.call_once(|| driver);
+    // グローバルインスタンスとして設定（COM1の場合のみ）。他のポートはdevfsのノードが所有する
+    let node = if port_base == COM1_PORT {
+        SerialNode::Com1(SERIAL1.call_once(|| driver))
+    } else {
+        SerialNode::Owned(driver)
+    };
+    
+    // /dev/ttyS<n>として公開。任意アドレスのポートには登録順に番号を割り当てる
+    let index = port.index().unwrap_or_else(|| NEXT_CUSTOM_INDEX.fetch_add(1, Ordering::Relaxed));
+    if let Err(e) = devfs::register(DeviceClass::Serial { port: index }, DeviceOps::Char(Arc::new(node))) {
+        log::warn!("シリアルポートをdevfsに登録できません: {:?}", e);
     }
     
     // ドライバー情報をドライバーマネージャーに登録
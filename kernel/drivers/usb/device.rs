@@ -22,6 +22,8 @@ use core::fmt;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use spin::{Mutex, RwLock};
 
+use crate::core::fs::{FsError, FsResult, CharDevice};
+use crate::core::fs::devfs::{self, DeviceClass, DeviceOps};
 use crate::drivers::usb::{
     UsbDeviceInfo, UsbError, UsbSpeed, UsbDeviceType, UsbDeviceDescriptor,
     UsbDirection, UsbRequestType, UsbRequestRecipient, UsbSetupPacket,
@@ -433,6 +435,7 @@ impl UsbDeviceManager {
     
     /// デバイスを追加
     pub fn add_device(&self, info: UsbDeviceInfo, hci: Arc<dyn UsbHci + Send + Sync>) -> Result<(), UsbError> {
+        let address = info.address;
         let mut device = UsbDevice::new(info, hci);
         
         // 適切なドライバーを検索
@@ -456,7 +459,14 @@ impl UsbDeviceManager {
         // デバイスを登録
         let device_arc = Arc::new(Mutex::new(device));
         let mut devices = self.devices.write();
-        devices.insert(info.address, device_arc);
+        devices.insert(address, device_arc.clone());
+        drop(devices);
+        
+        // /dev/bus/usb/<bus>/<address>として公開
+        let node = DeviceOps::Char(Arc::new(UsbDeviceNode { device: device_arc }));
+        if let Err(e) = devfs::register(usb_device_class(address), node) {
+            log::warn!("USBデバイス{}をdevfsに登録できません: {:?}", address, e);
+        }
         
         Ok(())
     }
@@ -466,6 +476,11 @@ impl UsbDeviceManager {
         let mut devices = self.devices.write();
         
         if let Some(device_arc) = devices.remove(&address) {
+            // デバイスノードを先に削除（開いているハンドルは以後エラーになる）
+            if let Some(name) = usb_device_class(address).fixed_name() {
+                let _ = devfs::unregister(&name);
+            }
+            
             let device = device_arc.lock();
             
             // ドライバーがあれば削除処理を呼び出す
@@ -493,6 +508,44 @@ impl UsbDeviceManager {
     }
 }
 
+/// devfs上のデバイスの種類（ルートハブは1つなのでバス番号は常に1）
+fn usb_device_class(address: u8) -> DeviceClass {
+    DeviceClass::Usb { bus: 1, address }
+}
+
+/// devfsに公開するUSBデバイス。読み込むとデバイスディスクリプタを返す
+struct UsbDeviceNode {
+    /// 対象のデバイス
+    device: Arc<Mutex<UsbDevice>>,
+}
+
+impl CharDevice for UsbDeviceNode {
+    fn read(&self, buffer: &mut [u8]) -> FsResult<usize> {
+        let device = self.device.lock();
+        let info = &device.info;
+        let mut descriptor = [0u8; 18];
+        descriptor[0] = 18;
+        descriptor[1] = 1; // DEVICE
+        descriptor[2..4].copy_from_slice(&info.usb_version.to_le_bytes());
+        descriptor[4] = info.device_class;
+        descriptor[5] = info.device_subclass;
+        descriptor[6] = info.device_protocol;
+        descriptor[7] = info.max_packet_size0;
+        descriptor[8..10].copy_from_slice(&info.vendor_id.to_le_bytes());
+        descriptor[10..12].copy_from_slice(&info.product_id.to_le_bytes());
+        descriptor[12..14].copy_from_slice(&info.device_version.to_le_bytes());
+        descriptor[17] = info.num_configurations;
+        
+        let len = core::cmp::min(buffer.len(), descriptor.len());
+        buffer[..len].copy_from_slice(&descriptor[..len]);
+        Ok(len)
+    }
+    
+    fn write(&self, _buffer: &[u8]) -> FsResult<usize> {
+        Err(FsError::NotSupported)
+    }
+}
+
 // シングルトンインスタンス
 static DEVICE_MANAGER: spin::Once<UsbDeviceManager> = spin::Once::new();
 
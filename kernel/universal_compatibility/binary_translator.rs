@@ -2264,16 +2264,14 @@ pub struct BinaryExecutionHandler;
 impl BinaryExecutionHandler {
     /// バイナリを実行
     pub fn execute_binary(binary_path: &str) -> Result<u32, &'static str> {
-        use crate::core::fs::FileSystem;
         use crate::core::process::ProcessManager;
         use super::CompatibilityManager;
         use super::version_manager;
         
-        let fs = FileSystem::instance();
-        
-        // ファイルを読み込み
-        let file_data = match fs.read_file(binary_path) {
+        // 実行権限を確認してファイルを読み込み
+        let file_data = match crate::core::fs::read_executable(binary_path) {
             Ok(data) => data,
+            Err(crate::core::fs::FsError::PermissionDenied) => return Err("バイナリの実行権限がありません"),
             Err(_) => return Err("バイナリファイルの読み込みに失敗しました"),
         };
         
@@ -2307,16 +2305,14 @@ impl BinaryExecutionHandler {
     
     /// バイナリの検出と実行
     pub fn detect_and_execute(file_path: &str) -> Result<u32, &'static str> {
-        use crate::core::fs::FileSystem;
         use super::CompatibilityManager;
         use super::BinaryTranslationStrategy;
         use super::version_manager;
         
-        let fs = FileSystem::instance();
-        
-        // ファイルを読み込み
-        let file_data = match fs.read_file(file_path) {
+        // 実行権限を確認してファイルを読み込み
+        let file_data = match crate::core::fs::read_executable(file_path) {
             Ok(data) => data,
+            Err(crate::core::fs::FsError::PermissionDenied) => return Err("バイナリの実行権限がありません"),
             Err(_) => return Err("バイナリファイルの読み込みに失敗しました"),
         };
         
@@ -2512,13 +2508,12 @@ impl BinaryExecutionHandler {
     
     /// JITコンパイラを使用したバイナリ実行
     fn execute_binary_jit(file_path: &str) -> Result<u32, &'static str> {
-        use crate::core::fs::FileSystem;
         use super::jit_compiler::JitCompiler;
         
-        // ファイルを読み込み
-        let fs = FileSystem::instance();
-        let binary_data = match fs.read_file(file_path) {
+        // 実行権限を確認してファイルを読み込み
+        let binary_data = match crate::core::fs::read_executable(file_path) {
             Ok(data) => data,
+            Err(crate::core::fs::FsError::PermissionDenied) => return Err("バイナリの実行権限がありません"),
             Err(_) => return Err("バイナリファイルの読み込みに失敗しました"),
         };
         